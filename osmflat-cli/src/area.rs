@@ -0,0 +1,66 @@
+use crate::args::AreaArgs;
+use crate::geo::{ring_area, way_coords, MultiPolygon};
+use crate::Error;
+
+use osmflat::{has_tag, FileResourceStorage, Osm};
+
+use std::collections::BTreeMap;
+
+/// Number of areas and their total area in m² per tag value.
+type Totals<'a> = BTreeMap<&'a [u8], (usize, f64)>;
+
+pub fn run(args: AreaArgs) -> Result<(), Error> {
+    let archive = Osm::open(FileResourceStorage::new(args.input))?;
+
+    let mut totals = Totals::new();
+    let mut add = |value, area| {
+        let total = totals.entry(value).or_default();
+        total.0 += 1;
+        total.1 += area;
+    };
+
+    // closed ways
+    for way in archive.ways() {
+        let value = match args.filter.matches(&archive, way.tags()) {
+            Some(value) => value,
+            None => continue,
+        };
+        match way_coords(&archive, way) {
+            Some(ring) if ring.len() >= 4 && ring.first() == ring.last() => {
+                add(value, ring_area(&ring))
+            }
+            _ => continue,
+        }
+    }
+
+    // multipolygons
+    for (idx, relation) in archive.relations().iter().enumerate() {
+        if !has_tag(&archive, relation.tags(), b"type", b"multipolygon") {
+            continue;
+        }
+        let value = match args.filter.matches(&archive, relation.tags()) {
+            Some(value) => value,
+            None => continue,
+        };
+        if let Some(polygon) = MultiPolygon::from_relation(&archive, idx) {
+            add(value, polygon.area());
+        }
+    }
+
+    let mut totals: Vec<_> = totals.into_iter().collect();
+    totals.sort_by(|a, b| b.1 .1.total_cmp(&a.1 .1));
+    println!("{:<30} {:>10} {:>16}", "value", "count", "area (km²)");
+    for (value, (count, area)) in &totals {
+        println!(
+            "{:<30} {:>10} {:>16.6}",
+            String::from_utf8_lossy(value),
+            count,
+            area / 1e6
+        );
+    }
+    let (count, area) = totals
+        .iter()
+        .fold((0, 0.0), |(c, a), (_, (count, area))| (c + count, a + area));
+    println!("{:<30} {:>10} {:>16.6}", "total", count, area / 1e6);
+    Ok(())
+}
@@ -0,0 +1,341 @@
+use crate::filter::Filter;
+use crate::geo::{BBox, LatLon};
+use crate::id::OsmId;
+use crate::mvt::LayerSpec;
+use crate::query::Query;
+
+use clap::{Parser, Subcommand};
+
+use std::path::PathBuf;
+
+/// Inspects and queries OpenStreetMap data in osm.flatdata format
+#[derive(Debug, Parser)]
+#[clap(about, version, author)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Shows a single entity by its OSM id
+    Show(ShowArgs),
+    /// Shows the nearest named feature and the admin areas at a location
+    Locate(LocateArgs),
+    /// Reports orphan nodes and dangling references
+    Orphans(OrphansArgs),
+    /// Sums the area of closed ways and multipolygons matching a tag filter
+    Area(AreaArgs),
+    /// Rasterizes the density of entities matching a tag filter
+    Density(DensityArgs),
+    /// Exports nodes, ways and relations matching a tag filter as GPX, KML,
+    /// GeoJSON or OSM XML
+    Export(ExportArgs),
+    /// Lists the entities matching a query, e.g. node[amenity=cafe][name]
+    Query(QueryArgs),
+    /// Reports counts, tag keys, resource sizes and layout diagnostics
+    Stats(StatsArgs),
+    /// Compares two archives by OSM id and reports the changed entities
+    Diff(DiffArgs),
+    /// Renders vector tiles (MVT) of a single tile or of a tile pyramid
+    Mvt(MvtArgs),
+    /// Exports nodes, ways and relations with their tags and geometries as
+    /// Parquet files
+    Parquet(ParquetArgs),
+    /// Searches the keys and values of tags for a regular expression and
+    /// lists the entities having matching tags
+    Grep(GrepArgs),
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ShowArgs {
+    /// OSM id prefixed by the entity type, e.g. n123, w123, or r123, or w-1
+    /// for a negative id
+    pub id: OsmId,
+
+    /// Input osmflat archive (must contain the ids subarchive)
+    pub input: PathBuf,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct LocateArgs {
+    /// Location as lat,lon
+    #[arg(allow_hyphen_values = true)]
+    pub coords: LatLon,
+
+    /// Input osmflat archive
+    pub input: PathBuf,
+
+    /// Output as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct OrphansArgs {
+    /// Input osmflat archive
+    pub input: PathBuf,
+
+    /// List the ids of the reported entities (or their indexes if the archive
+    /// does not contain ids)
+    #[arg(long)]
+    pub list: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct AreaArgs {
+    /// Tag filter: key=value, or key (resp. key=*) for totals per value
+    #[arg(long)]
+    pub filter: Filter,
+
+    /// Input osmflat archive
+    pub input: PathBuf,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct DensityArgs {
+    /// Tag filter: key=value, or key (resp. key=*) matching any value; all
+    /// entities are counted if not given
+    #[arg(long)]
+    pub filter: Option<Filter>,
+
+    /// Which entities to count: (n)odes and/or (w)ays
+    #[arg(long, default_value = "nw")]
+    pub types: String,
+
+    /// Size of a grid cell in degrees
+    #[arg(long, default_value = "0.01")]
+    pub resolution: f64,
+
+    /// Bounding box as min_lon,min_lat,max_lon,max_lat; defaults to the
+    /// extent of the matching entities
+    #[arg(long, value_parser = parse_bbox, allow_hyphen_values = true)]
+    pub bbox: Option<BBox>,
+
+    /// Input osmflat archive
+    pub input: PathBuf,
+
+    /// Output GeoTIFF, or PNG if the extension is png
+    #[arg(long, short = 'o')]
+    pub output: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum ExportFormat {
+    Gpx,
+    Kml,
+    /// GeoJSON FeatureCollection
+    Geojson,
+    /// Newline-delimited GeoJSON features
+    Geojsonl,
+    /// OSM XML including the nodes of ways and the members of relations
+    Osm,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ExportArgs {
+    /// Tag filter: key=value, or key (resp. key=*) matching any value
+    #[arg(long)]
+    pub filter: Filter,
+
+    /// Only export entities intersecting the bounding box given as
+    /// min_lon,min_lat,max_lon,max_lat
+    #[arg(long, value_parser = parse_bbox, allow_hyphen_values = true)]
+    pub bbox: Option<BBox>,
+
+    /// Output format; defaults to KML, GeoJSON, newline-delimited GeoJSON or
+    /// OSM XML if the output has the extension kml, geojson, geojsonl (resp.
+    /// ndjson), or osm, and GPX otherwise
+    #[arg(long, value_enum)]
+    pub format: Option<ExportFormat>,
+
+    /// Input osmflat archive
+    pub input: PathBuf,
+
+    /// Output file, or - for stdout; compressed with bzip2 if the extension
+    /// is bz2, e.g. export.osm.bz2
+    #[arg(long, short = 'o')]
+    pub output: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum QueryFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct QueryArgs {
+    /// Input osmflat archive
+    pub input: PathBuf,
+
+    /// Query: node, way, relation or nwr, followed by tag conditions [key],
+    /// [!key], [key=value] or [key!=value], and optionally a bounding box
+    /// (south,west,north,east)
+    pub query: Query,
+
+    /// Output format
+    #[arg(long, value_enum, default_value = "text")]
+    pub format: QueryFormat,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct StatsArgs {
+    /// Input osmflat archive
+    pub input: PathBuf,
+
+    /// Number of most frequent tag keys to report
+    #[arg(long, default_value = "20")]
+    pub top: usize,
+
+    /// Output as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct DiffArgs {
+    /// Old osmflat archive (must contain the ids subarchive)
+    pub old: PathBuf,
+
+    /// New osmflat archive (must contain the ids subarchive)
+    pub new: PathBuf,
+
+    /// List the ids of the changed entities together with their tag changes
+    #[arg(long)]
+    pub list: bool,
+
+    /// Write the changes as OsmChange file
+    #[arg(long)]
+    pub osc: Option<PathBuf>,
+}
+
+#[derive(Debug, clap::Args)]
+#[command(group(clap::ArgGroup::new("tiles").required(true).args(["tile", "zoom"])))]
+pub struct MvtArgs {
+    /// Input osmflat archive
+    pub input: PathBuf,
+
+    /// Single tile z/x/y written to the output file
+    #[arg(long, value_parser = parse_tile)]
+    pub tile: Option<(u8, u32, u32)>,
+
+    /// Zoom level, or range of zoom levels min-max, of the tile pyramid
+    /// written to the output directory as z/x/y.mvt
+    #[arg(long, value_parser = parse_zoom)]
+    pub zoom: Option<(u8, u8)>,
+
+    /// Only render the tiles of the pyramid intersecting the bounding box
+    /// given as min_lon,min_lat,max_lon,max_lat
+    #[arg(long, value_parser = parse_bbox, allow_hyphen_values = true, conflicts_with = "tile")]
+    pub bbox: Option<BBox>,
+
+    /// Layer as NAME:FILTER[:KEYS[:MIN_ZOOM]], e.g. roads:highway:name,ref:10,
+    /// containing the entities matching the tag filter from zoom level
+    /// MIN_ZOOM on, with the tags of the comma separated KEYS (default all) as
+    /// properties; can be given multiple times, defaults to roads, water,
+    /// buildings and pois layers
+    #[arg(long = "layer")]
+    pub layers: Vec<LayerSpec>,
+
+    /// Simplification tolerance in tile coordinates (of an extent of 4096)
+    #[arg(long, default_value = "1.0")]
+    pub tolerance: f64,
+
+    /// Output file of a single tile, or output directory of a tile pyramid
+    #[arg(long, short = 'o')]
+    pub output: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum ParquetCompression {
+    None,
+    Snappy,
+    Zstd,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ParquetArgs {
+    /// Tag filter: key=value, or key (resp. key=*) matching any value; all
+    /// entities are exported if not given
+    #[arg(long)]
+    pub filter: Option<Filter>,
+
+    /// Compression of the columns
+    #[arg(long, value_enum, default_value = "snappy")]
+    pub compression: ParquetCompression,
+
+    /// Input osmflat archive
+    pub input: PathBuf,
+
+    /// Output directory of nodes.parquet, ways.parquet and relations.parquet
+    #[arg(long, short = 'o')]
+    pub output: PathBuf,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct GrepArgs {
+    /// Regular expression searched in the keys and values of tags
+    pub pattern: String,
+
+    /// Input osmflat archive
+    pub input: PathBuf,
+
+    /// Only search the values of tags with this key
+    #[arg(long)]
+    pub key: Option<String>,
+
+    /// Search case insensitively
+    #[arg(long, short = 'i')]
+    pub ignore_case: bool,
+
+    /// Treat the pattern as literal string instead of regular expression
+    #[arg(long, short = 'F')]
+    pub fixed_strings: bool,
+
+    /// Which entities to search: (n)odes, (w)ays and/or (r)elations
+    #[arg(long, default_value = "nwr")]
+    pub types: String,
+
+    /// Only print the number of matching entities per type
+    #[arg(long, short = 'c')]
+    pub count: bool,
+}
+
+fn parse_tile(s: &str) -> Result<(u8, u32, u32), String> {
+    let invalid = || format!("invalid tile '{s}', expected z/x/y");
+    let zxy: Vec<u32> = s
+        .split('/')
+        .map(|v| v.parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| invalid())?;
+    match zxy[..] {
+        [z, x, y] if z < 32 && x < 1 << z && y < 1 << z => Ok((z as u8, x, y)),
+        _ => Err(invalid()),
+    }
+}
+
+fn parse_zoom(s: &str) -> Result<(u8, u8), String> {
+    let invalid = || format!("invalid zoom '{s}', expected z or min-max");
+    let (min, max) = s.split_once('-').unwrap_or((s, s));
+    let min: u8 = min.trim().parse().map_err(|_| invalid())?;
+    let max: u8 = max.trim().parse().map_err(|_| invalid())?;
+    if min > max {
+        return Err(invalid());
+    }
+    Ok((min, max))
+}
+
+fn parse_bbox(s: &str) -> Result<BBox, String> {
+    let values: Vec<f64> = s
+        .split(',')
+        .map(|v| v.trim().parse())
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("{e}"))?;
+    match values[..] {
+        [min_lon, min_lat, max_lon, max_lat] if min_lon < max_lon && min_lat < max_lat => {
+            Ok(((min_lon, min_lat), (max_lon, max_lat)))
+        }
+        _ => Err("expected min_lon,min_lat,max_lon,max_lat".into()),
+    }
+}
@@ -0,0 +1,192 @@
+use crate::args::DensityArgs;
+use crate::geo::{node_coord, way_coords, Coord};
+use crate::Error;
+
+use osmflat::{DensityGrid, FileResourceStorage, Osm};
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+/// Collects the locations of the matching entities: nodes by their
+/// coordinates, ways by the mean of their node coordinates.
+fn locations(archive: &Osm, args: &DensityArgs) -> Vec<Coord> {
+    let matches = |tags| match &args.filter {
+        Some(filter) => filter.matches(archive, tags).is_some(),
+        None => true,
+    };
+
+    let mut locations = Vec::new();
+    if args.types.contains('n') {
+        for (idx, node) in archive.nodes().iter().enumerate() {
+            if matches(node.tags()) {
+                locations.push(node_coord(archive, idx as u64));
+            }
+        }
+    }
+    if args.types.contains('w') {
+        for way in archive.ways() {
+            if !matches(way.tags()) {
+                continue;
+            }
+            if let Some(coords) = way_coords(archive, way).filter(|c| !c.is_empty()) {
+                let n = coords.len() as f64;
+                let (lon, lat) = coords
+                    .iter()
+                    .fold((0.0, 0.0), |(lon, lat), c| (lon + c.0, lat + c.1));
+                locations.push((lon / n, lat / n));
+            }
+        }
+    }
+    locations
+}
+
+/// Writes the grid as grayscale PNG with logarithmically scaled counts.
+fn write_png(out: impl Write, grid: &DensityGrid) -> Result<(), Error> {
+    let max = f64::from(grid.max_count()).ln_1p().max(f64::MIN_POSITIVE);
+    let data: Vec<u8> = grid
+        .counts()
+        .iter()
+        .map(|&count| (f64::from(count).ln_1p() / max * 255.0).round() as u8)
+        .collect();
+
+    let mut encoder = png::Encoder::new(out, grid.width() as u32, grid.height() as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    Ok(())
+}
+
+/// Writes the grid as uncompressed single band GeoTIFF with 32 bit unsigned
+/// counts in WGS84 coordinates.
+fn write_geotiff(mut out: impl Write, grid: &DensityGrid) -> io::Result<()> {
+    const SHORT: u16 = 3;
+    const LONG: u16 = 4;
+    const DOUBLE: u16 = 12;
+
+    let (width, height) = (grid.width() as u32, grid.height() as u32);
+    let image_size = width * height * 4;
+    let (lon, lat) = grid.origin();
+    let res = grid.resolution();
+
+    // data which does not fit into the IFD entries, stored after the image
+    let pixel_scale = [res, res, 0.0];
+    let tiepoint = [0.0, 0.0, 0.0, lon, lat, 0.0];
+    let geo_keys: [u16; 16] = [
+        1, 1, 0, 3, // version, revision, minor revision, number of keys
+        1024, 0, 1, 2, // GTModelTypeGeoKey: geographic
+        1025, 0, 1, 1, // GTRasterTypeGeoKey: pixel is area
+        2048, 0, 1, 4326, // GeographicTypeGeoKey: WGS84
+    ];
+    let pixel_scale_offset = 8 + image_size;
+    let tiepoint_offset = pixel_scale_offset + 3 * 8;
+    let geo_keys_offset = tiepoint_offset + 6 * 8;
+    let ifd_offset = geo_keys_offset + 16 * 2;
+
+    // header
+    out.write_all(b"II")?;
+    out.write_all(&42u16.to_le_bytes())?;
+    out.write_all(&ifd_offset.to_le_bytes())?;
+
+    // image data and out of line values
+    for count in grid.counts() {
+        out.write_all(&count.to_le_bytes())?;
+    }
+    for value in pixel_scale.iter().chain(&tiepoint) {
+        out.write_all(&value.to_le_bytes())?;
+    }
+    for value in geo_keys {
+        out.write_all(&value.to_le_bytes())?;
+    }
+
+    // image file directory: (tag, type, count, value or offset)
+    let entries: [(u16, u16, u32, u32); 13] = [
+        (256, LONG, 1, width),                  // ImageWidth
+        (257, LONG, 1, height),                 // ImageLength
+        (258, SHORT, 1, 32),                    // BitsPerSample
+        (259, SHORT, 1, 1),                     // Compression: none
+        (262, SHORT, 1, 1),                     // PhotometricInterpretation: black is zero
+        (273, LONG, 1, 8),                      // StripOffsets
+        (277, SHORT, 1, 1),                     // SamplesPerPixel
+        (278, LONG, 1, height),                 // RowsPerStrip
+        (279, LONG, 1, image_size),             // StripByteCounts
+        (339, SHORT, 1, 1),                     // SampleFormat: unsigned integer
+        (33550, DOUBLE, 3, pixel_scale_offset), // ModelPixelScaleTag
+        (33922, DOUBLE, 6, tiepoint_offset),    // ModelTiepointTag
+        (34735, SHORT, 16, geo_keys_offset),    // GeoKeyDirectoryTag
+    ];
+    out.write_all(&(entries.len() as u16).to_le_bytes())?;
+    for (tag, field_type, count, value) in entries {
+        out.write_all(&tag.to_le_bytes())?;
+        out.write_all(&field_type.to_le_bytes())?;
+        out.write_all(&count.to_le_bytes())?;
+        // short values are left-justified in the 4 bytes of the value field
+        if field_type == SHORT && count == 1 {
+            out.write_all(&(value as u16).to_le_bytes())?;
+            out.write_all(&[0, 0])?;
+        } else {
+            out.write_all(&value.to_le_bytes())?;
+        }
+    }
+    out.write_all(&0u32.to_le_bytes())?; // no next IFD
+    out.flush()
+}
+
+pub fn run(args: DensityArgs) -> Result<(), Error> {
+    let archive = Osm::open(FileResourceStorage::new(&args.input))?;
+
+    let locations = locations(&archive, &args);
+    let (min, max) = match args.bbox {
+        Some(bbox) => bbox,
+        None => locations.iter().fold(
+            ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN)),
+            |(min, max), c| {
+                (
+                    (min.0.min(c.0), min.1.min(c.1)),
+                    (max.0.max(c.0), max.1.max(c.1)),
+                )
+            },
+        ),
+    };
+    if locations.is_empty() || min.0 > max.0 || min.1 > max.1 {
+        return Err("no matching entities".into());
+    }
+
+    let mut grid = DensityGrid::new(min, max, args.resolution);
+    let binned = locations.into_iter().filter(|&c| grid.add(c)).count();
+    eprintln!(
+        "Binned {binned} entities into {}x{} cells (max {} per cell)",
+        grid.width(),
+        grid.height(),
+        grid.max_count()
+    );
+
+    let out = BufWriter::new(File::create(&args.output)?);
+    match args.output.extension().and_then(|ext| ext.to_str()) {
+        Some("png") => write_png(out, &grid)?,
+        _ => write_geotiff(out, &grid)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_geotiff_layout() {
+        let mut grid = DensityGrid::new((13.0, 52.0), (13.3, 52.2), 0.1);
+        assert!(grid.add((13.05, 52.15)));
+        assert!(!grid.add((14.0, 52.0)));
+        assert_eq!((grid.width(), grid.height()), (3, 2));
+
+        let mut data = Vec::new();
+        write_geotiff(&mut data, &grid).unwrap();
+        assert_eq!(&data[..4], b"II\x2a\x00");
+        // northwest cell contains the point
+        assert_eq!(&data[8..12], &1u32.to_le_bytes());
+        let ifd_offset = u32::from_le_bytes(data[4..8].try_into().unwrap()) as usize;
+        let num_entries = u16::from_le_bytes([data[ifd_offset], data[ifd_offset + 1]]);
+        assert_eq!(data.len(), ifd_offset + 2 + 12 * num_entries as usize + 4);
+    }
+}
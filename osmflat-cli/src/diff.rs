@@ -0,0 +1,326 @@
+use crate::args::DiffArgs;
+use crate::element::{Element, ElementData};
+use crate::id::IdLookup;
+use crate::Error;
+
+use osmflat::{EntityType, FileResourceStorage, Id, Osm};
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+/// Change of an element between the old and the new archive.
+#[derive(Debug, Clone, PartialEq)]
+enum Change {
+    Created(Element),
+    Modified { old: Element, new: Element },
+    Deleted(Element),
+}
+
+/// Change of a tag of a modified element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TagChange<'a> {
+    Added(&'a str, &'a str),
+    Removed(&'a str, &'a str),
+    Changed(&'a str, &'a str, &'a str),
+}
+
+/// Compares the tags of two versions of an element by key.
+fn tag_changes<'a>(old: &'a [(String, String)], new: &'a [(String, String)]) -> Vec<TagChange<'a>> {
+    let old: BTreeMap<&str, &str> = old.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+    let new: BTreeMap<&str, &str> = new.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+    let mut changes = Vec::new();
+    for (&key, &value) in &old {
+        match new.get(key) {
+            None => changes.push(TagChange::Removed(key, value)),
+            Some(&new_value) if new_value != value => {
+                changes.push(TagChange::Changed(key, value, new_value))
+            }
+            Some(_) => (),
+        }
+    }
+    for (&key, &value) in &new {
+        if !old.contains_key(key) {
+            changes.push(TagChange::Added(key, value));
+        }
+    }
+    changes
+}
+
+/// Returns the pairs of id and index of `ids` sorted by id.
+fn sorted_ids(ids: &[Id]) -> Vec<(i64, u64)> {
+    let mut sorted: Vec<(i64, u64)> = ids
+        .iter()
+        .enumerate()
+        .map(|(idx, id)| (id.signed_value(), idx as u64))
+        .collect();
+    sorted.sort_unstable();
+    sorted
+}
+
+/// Joins two lists of pairs of id and index sorted by id, and calls `f` with
+/// the indexes of each id in the old and in the new list.
+fn merge_ids(old: &[(i64, u64)], new: &[(i64, u64)], mut f: impl FnMut(Option<u64>, Option<u64>)) {
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        let order = match (old.get(i), new.get(j)) {
+            (Some(a), Some(b)) => a.0.cmp(&b.0),
+            (Some(_), None) => Ordering::Less,
+            _ => Ordering::Greater,
+        };
+        match order {
+            Ordering::Less => {
+                f(Some(old[i].1), None);
+                i += 1;
+            }
+            Ordering::Greater => {
+                f(None, Some(new[j].1));
+                j += 1;
+            }
+            Ordering::Equal => {
+                f(Some(old[i].1), Some(new[j].1));
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+}
+
+/// Archive with its ids.
+struct Side<'a> {
+    archive: &'a Osm,
+    ids: IdLookup<'a>,
+}
+
+/// Compares the entities of type `entity_type` of both archives by id.
+fn diff_entities(old: &Side, new: &Side, entity_type: EntityType, changes: &mut Vec<Change>) {
+    let ids = |side: &Side| {
+        let ids = side.archive.ids().expect("no ids");
+        match entity_type {
+            EntityType::Node => sorted_ids(ids.nodes()),
+            EntityType::Way => sorted_ids(ids.ways()),
+            EntityType::Relation => sorted_ids(ids.relations()),
+        }
+    };
+    let element = |side: &Side, idx| Element::new(side.archive, &side.ids, entity_type, idx);
+    merge_ids(&ids(old), &ids(new), |old_idx, new_idx| {
+        match (old_idx, new_idx) {
+            (Some(old_idx), Some(new_idx)) => {
+                let (old, new) = (element(old, old_idx), element(new, new_idx));
+                if old != new {
+                    changes.push(Change::Modified { old, new });
+                }
+            }
+            (Some(old_idx), None) => changes.push(Change::Deleted(element(old, old_idx))),
+            (None, Some(new_idx)) => changes.push(Change::Created(element(new, new_idx))),
+            (None, None) => (),
+        }
+    });
+}
+
+/// Writes the changes as OsmChange file.
+///
+/// Deleted elements are written in reverse order, so that relations are
+/// deleted before their members, and ways before their nodes.
+fn write_osc(mut out: impl Write, changes: &[Change]) -> io::Result<()> {
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(out, r#"<osmChange version="0.6" generator="osmflat">"#)?;
+    let mut write_section = |name: &str, elements: Vec<&Element>| {
+        if elements.is_empty() {
+            return Ok(());
+        }
+        writeln!(out, "  <{name}>")?;
+        for element in elements {
+            element.write_xml(&mut out, 4)?;
+        }
+        writeln!(out, "  </{name}>")
+    };
+    let created = changes.iter().filter_map(|change| match change {
+        Change::Created(element) => Some(element),
+        _ => None,
+    });
+    write_section("create", created.collect())?;
+    let modified = changes.iter().filter_map(|change| match change {
+        Change::Modified { new, .. } => Some(new),
+        _ => None,
+    });
+    write_section("modify", modified.collect())?;
+    let deleted = changes.iter().rev().filter_map(|change| match change {
+        Change::Deleted(element) => Some(element),
+        _ => None,
+    });
+    write_section("delete", deleted.collect())?;
+    writeln!(out, "</osmChange>")?;
+    out.flush()
+}
+
+fn print_change(change: &Change) {
+    match change {
+        Change::Created(element) => println!("+ {}", element.id),
+        Change::Deleted(element) => println!("- {}", element.id),
+        Change::Modified { old, new } => {
+            let mut line = format!("~ {}", new.id);
+            if old.data != new.data {
+                line.push_str(match new.data {
+                    ElementData::Node { .. } => " moved",
+                    ElementData::Way { .. } => " nodes",
+                    ElementData::Relation { .. } => " members",
+                });
+            }
+            for tag_change in tag_changes(&old.tags, &new.tags) {
+                match tag_change {
+                    TagChange::Added(k, v) => line.push_str(&format!(" +{k}={v}")),
+                    TagChange::Removed(k, v) => line.push_str(&format!(" -{k}={v}")),
+                    TagChange::Changed(k, old, new) => line.push_str(&format!(" {k}={old}->{new}")),
+                }
+            }
+            println!("{line}");
+        }
+    }
+}
+
+pub fn run(args: DiffArgs) -> Result<(), Error> {
+    let old = Osm::open(FileResourceStorage::new(args.old))?;
+    let new = Osm::open(FileResourceStorage::new(args.new))?;
+    let old = Side {
+        archive: &old,
+        ids: IdLookup::new(&old)?,
+    };
+    let new = Side {
+        archive: &new,
+        ids: IdLookup::new(&new)?,
+    };
+
+    let mut changes = Vec::new();
+    for (entity_type, name) in [
+        (EntityType::Node, "nodes"),
+        (EntityType::Way, "ways"),
+        (EntityType::Relation, "relations"),
+    ] {
+        let start = changes.len();
+        diff_entities(&old, &new, entity_type, &mut changes);
+        let count = |f: fn(&Change) -> bool| changes[start..].iter().filter(|c| f(c)).count();
+        println!(
+            "{name}: {} created, {} modified, {} deleted",
+            count(|c| matches!(c, Change::Created(_))),
+            count(|c| matches!(c, Change::Modified { .. })),
+            count(|c| matches!(c, Change::Deleted(_))),
+        );
+    }
+
+    let (mut added, mut removed, mut changed) = (0, 0, 0);
+    for change in &changes {
+        if let Change::Modified { old, new } = change {
+            for tag_change in tag_changes(&old.tags, &new.tags) {
+                match tag_change {
+                    TagChange::Added(..) => added += 1,
+                    TagChange::Removed(..) => removed += 1,
+                    TagChange::Changed(..) => changed += 1,
+                }
+            }
+        }
+    }
+    println!("tags of modified entities: {added} added, {removed} removed, {changed} changed");
+
+    if args.list {
+        changes.iter().for_each(print_change);
+    }
+    if let Some(osc) = args.osc {
+        write_osc(BufWriter::new(File::create(osc)?), &changes)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::id::OsmId;
+
+    #[test]
+    fn test_tag_changes() {
+        let tags = |tags: &[(&str, &str)]| -> Vec<(String, String)> {
+            tags.iter()
+                .map(|&(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        let old = tags(&[("name", "A"), ("note", "x"), ("highway", "primary")]);
+        let new = tags(&[("highway", "secondary"), ("name", "A"), ("ref", "B 1")]);
+        assert_eq!(
+            tag_changes(&old, &new),
+            [
+                TagChange::Changed("highway", "primary", "secondary"),
+                TagChange::Removed("note", "x"),
+                TagChange::Added("ref", "B 1"),
+            ]
+        );
+        assert!(tag_changes(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn test_merge_ids() {
+        let old = [(-1, 3), (1, 0), (2, 1), (5, 2)];
+        let new = [(1, 1), (3, 0), (5, 2), (6, 3)];
+        let mut pairs = Vec::new();
+        merge_ids(&old, &new, |a, b| pairs.push((a, b)));
+        assert_eq!(
+            pairs,
+            [
+                (Some(3), None),
+                (Some(0), Some(1)),
+                (Some(1), None),
+                (None, Some(0)),
+                (Some(2), Some(2)),
+                (None, Some(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_osc() {
+        let node = |id, lon| Element {
+            id: OsmId::Node(id),
+            tags: Vec::new(),
+            data: ElementData::Node { lon, lat: 0 },
+            meta: None,
+        };
+        let changes = [
+            Change::Created(node(1, 0)),
+            Change::Modified {
+                old: node(2, 0),
+                new: node(2, 10),
+            },
+            Change::Deleted(node(3, 0)),
+            Change::Deleted(node(4, 0)),
+        ];
+        let mut out = Vec::new();
+        write_osc(&mut out, &changes).unwrap();
+        let osc = String::from_utf8(out).unwrap();
+        let create = osc.find("<create>").unwrap();
+        let modify = osc.find("<modify>").unwrap();
+        let delete = osc.find("<delete>").unwrap();
+        assert!(create < modify && modify < delete);
+        assert!(osc[modify..delete].contains(r#"<node id="2" lat="0.0000000" lon="0.0000010"/>"#));
+        assert!(osc.find(r#"id="4""#) < osc.find(r#"id="3""#));
+    }
+
+    #[test]
+    fn test_write_osc_negative_ids() {
+        let archive = osmflat::TestArchive {
+            nodes: vec![(13.4, 52.5, vec![]), (13.5, 52.5, vec![])],
+            ways: vec![(vec![0, 1], vec![])],
+            relations: vec![],
+        };
+        let archive = crate::id::build_with_ids(&archive, [&[-1, 2], &[-1], &[]]);
+        let ids = IdLookup::new(&archive).unwrap();
+        let idx = ids.find("w-1".parse().unwrap()).unwrap();
+        let way = Element::new(&archive, &ids, EntityType::Way, idx as u64);
+        assert_eq!(way.id, OsmId::Way(-1));
+        let mut out = Vec::new();
+        write_osc(&mut out, &[Change::Created(way)]).unwrap();
+        let osc = String::from_utf8(out).unwrap();
+        assert!(osc.contains(r#"<way id="-1">"#));
+        assert!(osc.contains(r#"<nd ref="-1"/>"#));
+        assert!(osc.contains(r#"<nd ref="2"/>"#));
+    }
+}
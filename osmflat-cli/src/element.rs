@@ -0,0 +1,242 @@
+use crate::export::escape;
+use crate::id::{IdLookup, OsmId};
+use crate::stats::format_timestamp;
+
+use osmflat::{iter_tags, EntityType, Osm, RelationMembersRef};
+
+use std::io::{self, Write};
+
+/// Content of an entity besides its id and tags.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ElementData {
+    /// Coordinates `(lon, lat)` in units of 10^-7 degrees, the precision of
+    /// OSM coordinates
+    Node { lon: i64, lat: i64 },
+    /// Ids of the nodes of a way
+    Way { refs: Vec<OsmId> },
+    /// Members of a relation with their roles
+    Relation { members: Vec<(OsmId, String)> },
+}
+
+/// Version of an entity stored in the `history` subarchive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Meta {
+    pub version: u32,
+    /// Seconds since the epoch
+    pub timestamp: i64,
+}
+
+/// Entity with its references resolved to OSM ids.
+///
+/// Other than the entities of an archive, elements do not depend on the
+/// indexes of their archive, so that elements of different archives are
+/// compared directly. References which are unresolved in the archive are
+/// dropped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Element {
+    pub id: OsmId,
+    pub tags: Vec<(String, String)>,
+    pub data: ElementData,
+    /// Version, if the archive contains the `history` subarchive
+    pub meta: Option<Meta>,
+}
+
+impl Element {
+    /// Resolves the entity of type `entity_type` at `idx` in `archive`.
+    pub fn new(archive: &Osm, ids: &IdLookup, entity_type: EntityType, idx: u64) -> Self {
+        let tags = |range| {
+            iter_tags(archive, range)
+                .map(|(key, value)| {
+                    (
+                        String::from_utf8_lossy(key).into_owned(),
+                        String::from_utf8_lossy(value).into_owned(),
+                    )
+                })
+                .collect()
+        };
+        let meta = archive.history().map(|history| {
+            let versions = match entity_type {
+                EntityType::Node => history.nodes(),
+                EntityType::Way => history.ways(),
+                EntityType::Relation => history.relations(),
+            };
+            let version = &versions[idx as usize];
+            Meta {
+                version: version.version(),
+                timestamp: version.timestamp(),
+            }
+        });
+        match entity_type {
+            EntityType::Node => {
+                let (lon, lat) = archive.node_coord(idx as usize);
+                let units = |degrees: f64| (degrees * 1e7).round() as i64;
+                Element {
+                    id: ids.node(idx),
+                    tags: tags(archive.nodes()[idx as usize].tags()),
+                    data: ElementData::Node {
+                        lon: units(lon),
+                        lat: units(lat),
+                    },
+                    meta,
+                }
+            }
+            EntityType::Way => {
+                let way = &archive.ways()[idx as usize];
+                let nodes_index = archive.nodes_index();
+                let refs = way
+                    .refs()
+                    .filter_map(|i| nodes_index[i as usize].value())
+                    .map(|node_idx| ids.node(node_idx))
+                    .collect();
+                Element {
+                    id: ids.way(idx),
+                    tags: tags(way.tags()),
+                    data: ElementData::Way { refs },
+                    meta,
+                }
+            }
+            EntityType::Relation => {
+                let strings = archive.stringtable();
+                let members = archive
+                    .relation_members()
+                    .at(idx as usize)
+                    .filter_map(|member| {
+                        let (id, role_idx) = match member {
+                            RelationMembersRef::NodeMember(m) => {
+                                (m.node_idx().map(|i| ids.node(i)), m.role_idx())
+                            }
+                            RelationMembersRef::WayMember(m) => {
+                                (m.way_idx().map(|i| ids.way(i)), m.role_idx())
+                            }
+                            RelationMembersRef::RelationMember(m) => {
+                                (m.relation_idx().map(|i| ids.relation(i)), m.role_idx())
+                            }
+                        };
+                        let role = strings.substring_raw(role_idx as usize);
+                        Some((id?, String::from_utf8_lossy(role).into_owned()))
+                    })
+                    .collect();
+                Element {
+                    id: ids.relation(idx),
+                    tags: tags(archive.relations()[idx as usize].tags()),
+                    data: ElementData::Relation { members },
+                    meta,
+                }
+            }
+        }
+    }
+
+    /// Writes the element as OSM XML element indented by `indent` spaces.
+    pub fn write_xml(&self, out: &mut impl Write, indent: usize) -> io::Result<()> {
+        let (name, id) = match self.id {
+            OsmId::Node(id) => ("node", id),
+            OsmId::Way(id) => ("way", id),
+            OsmId::Relation(id) => ("relation", id),
+        };
+        write!(out, r#"{:indent$}<{name} id="{id}""#, "")?;
+        if let Some(meta) = self.meta {
+            write!(
+                out,
+                r#" version="{}" timestamp="{}""#,
+                meta.version,
+                format_timestamp(meta.timestamp)
+            )?;
+        }
+        if let ElementData::Node { lon, lat } = self.data {
+            write!(
+                out,
+                r#" lat="{:.7}" lon="{:.7}""#,
+                lat as f64 / 1e7,
+                lon as f64 / 1e7
+            )?;
+        }
+        let empty = match &self.data {
+            ElementData::Node { .. } => self.tags.is_empty(),
+            ElementData::Way { refs } => self.tags.is_empty() && refs.is_empty(),
+            ElementData::Relation { members } => self.tags.is_empty() && members.is_empty(),
+        };
+        if empty {
+            return writeln!(out, "/>");
+        }
+        writeln!(out, ">")?;
+
+        let child = indent + 2;
+        match &self.data {
+            ElementData::Node { .. } => (),
+            ElementData::Way { refs } => {
+                for id in refs {
+                    if let OsmId::Node(id) = id {
+                        writeln!(out, r#"{:child$}<nd ref="{id}"/>"#, "")?;
+                    }
+                }
+            }
+            ElementData::Relation { members } => {
+                for (id, role) in members {
+                    let (member_type, id) = match id {
+                        OsmId::Node(id) => ("node", id),
+                        OsmId::Way(id) => ("way", id),
+                        OsmId::Relation(id) => ("relation", id),
+                    };
+                    writeln!(
+                        out,
+                        r#"{:child$}<member type="{member_type}" ref="{id}" role="{}"/>"#,
+                        "",
+                        escape(role)
+                    )?;
+                }
+            }
+        }
+        for (key, value) in &self.tags {
+            writeln!(
+                out,
+                r#"{:child$}<tag k="{}" v="{}"/>"#,
+                "",
+                escape(key),
+                escape(value)
+            )?;
+        }
+        writeln!(out, "{:indent$}</{name}>", "")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_write_xml() {
+        let mut out = Vec::new();
+        let node = Element {
+            id: OsmId::Node(1),
+            tags: Vec::new(),
+            data: ElementData::Node {
+                lon: 134_000_000,
+                lat: -525_000_001,
+            },
+            meta: None,
+        };
+        node.write_xml(&mut out, 2).unwrap();
+        let relation = Element {
+            id: OsmId::Relation(3),
+            tags: vec![("name".into(), "A & B".into())],
+            data: ElementData::Relation {
+                members: vec![(OsmId::Way(2), "outer".into())],
+            },
+            meta: Some(Meta {
+                version: 2,
+                timestamp: 0,
+            }),
+        };
+        relation.write_xml(&mut out, 0).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                "  <node id=\"1\" lat=\"-52.5000001\" lon=\"13.4000000\"/>\n",
+                "<relation id=\"3\" version=\"2\" timestamp=\"1970-01-01T00:00:00Z\">\n",
+                "  <member type=\"way\" ref=\"2\" role=\"outer\"/>\n",
+                "  <tag k=\"name\" v=\"A &amp; B\"/>\n",
+                "</relation>\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,592 @@
+use crate::args::{ExportArgs, ExportFormat};
+use crate::element::Element;
+use crate::geo::{node_coord, way_coords, BBox, Coord};
+use crate::id::IdLookup;
+use crate::Error;
+
+use bzip2::write::BzEncoder;
+use osmflat::{
+    find_tag, to_geojson_feature, version_range, BBoxMatch, EntityType, FileResourceStorage, Osm,
+    RelationMembersRef,
+};
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+
+/// Feature exported to a GPS device.
+#[derive(Debug, Clone, PartialEq)]
+enum Feature {
+    /// Single location, exported from nodes
+    Waypoint { name: Option<String>, coord: Coord },
+    /// Line strings, exported from ways and flattened relations
+    Track {
+        name: Option<String>,
+        segments: Vec<Vec<Coord>>,
+    },
+}
+
+fn name(archive: &Osm, tags: Range<u64>) -> Option<String> {
+    find_tag(archive, tags, b"name").map(|name| String::from_utf8_lossy(name).into_owned())
+}
+
+/// Joins consecutive line strings sharing an endpoint, reversing them where
+/// necessary. Gaps in the sequence start a new line string.
+fn stitch_lines(lines: Vec<Vec<Coord>>) -> Vec<Vec<Coord>> {
+    let mut result: Vec<Vec<Coord>> = Vec::new();
+    // whether the last line string consists of a single input line, whose
+    // direction is not determined yet
+    let mut single = false;
+    for mut line in lines.into_iter().filter(|line| line.len() > 1) {
+        if let Some(last) = result.last_mut() {
+            let touches = |line: &[Coord], c: Coord| line[0] == c || line[line.len() - 1] == c;
+            if single && !touches(&line, last[last.len() - 1]) && touches(&line, last[0]) {
+                last.reverse();
+            }
+            let end = last[last.len() - 1];
+            if line[0] != end && line[line.len() - 1] == end {
+                line.reverse();
+            }
+            if line[0] == end {
+                last.extend(line.into_iter().skip(1));
+                single = false;
+                continue;
+            }
+        }
+        result.push(line);
+        single = true;
+    }
+    result
+}
+
+/// Indexes of the nodes, ways and relations matching the filter and the
+/// bounding box.
+#[derive(Debug, Default)]
+struct Selection {
+    nodes: Vec<u64>,
+    ways: Vec<u64>,
+    relations: Vec<u64>,
+}
+
+impl Selection {
+    /// Selects the entities matching the tag filter, and intersecting the
+    /// bounding box if given.
+    ///
+    /// The bounding box is looked up in the spatial index if the archive
+    /// contains it, cf. [`Osm::ways_in_bbox`].
+    fn new(archive: &Osm, args: &ExportArgs) -> Self {
+        let matches = |tags| args.filter.matches(archive, tags).is_some();
+        let select = |len: usize, in_bbox: Option<Vec<u64>>, tags: &dyn Fn(usize) -> Range<u64>| {
+            let mut indexes: Vec<u64> = match in_bbox {
+                Some(indexes) => indexes,
+                None => (0..len as u64).collect(),
+            };
+            indexes.sort_unstable();
+            indexes.retain(|&idx| matches(tags(idx as usize)));
+            indexes
+        };
+        let bbox = args
+            .bbox
+            .map(|((min_lon, min_lat), (max_lon, max_lat))| (min_lon, min_lat, max_lon, max_lat));
+
+        let nodes = archive.nodes();
+        let nodes_in_bbox = bbox.map(|b| archive.nodes_in_bbox(b.0, b.1, b.2, b.3).collect());
+        let ways = archive.ways();
+        let ways_in_bbox = bbox.map(|b| {
+            archive
+                .ways_in_bbox(b.0, b.1, b.2, b.3, BBoxMatch::Geometry)
+                .collect()
+        });
+        let relations = archive.relations();
+        let relations_in_bbox = bbox.map(|b| {
+            archive
+                .relations_in_bbox(b.0, b.1, b.2, b.3, BBoxMatch::Geometry)
+                .collect()
+        });
+        Self {
+            nodes: select(nodes.len(), nodes_in_bbox, &|idx| nodes[idx].tags()),
+            ways: select(ways.len(), ways_in_bbox, &|idx| ways[idx].tags()),
+            relations: select(relations.len(), relations_in_bbox, &|idx| {
+                relations[idx].tags()
+            }),
+        }
+    }
+}
+
+/// Converts the selected nodes to waypoints, and the selected ways and
+/// relations to tracks.
+///
+/// Relations are flattened to a track of their way members.
+fn features(archive: &Osm, selection: &Selection) -> Vec<Feature> {
+    let mut features = Vec::new();
+    let nodes = archive.nodes();
+    for &idx in &selection.nodes {
+        features.push(Feature::Waypoint {
+            name: name(archive, nodes[idx as usize].tags()),
+            coord: node_coord(archive, idx),
+        });
+    }
+
+    let ways = archive.ways();
+    for &idx in &selection.ways {
+        let way = &ways[idx as usize];
+        if let Some(coords) = way_coords(archive, way).filter(|c| c.len() > 1) {
+            features.push(Feature::Track {
+                name: name(archive, way.tags()),
+                segments: vec![coords],
+            });
+        }
+    }
+
+    let relations = archive.relations();
+    for &idx in &selection.relations {
+        let lines = archive
+            .relation_members()
+            .at(idx as usize)
+            .filter_map(|member| match member {
+                RelationMembersRef::WayMember(m) => m.way_idx(),
+                _ => None,
+            })
+            .filter_map(|way_idx| way_coords(archive, &ways[way_idx as usize]))
+            .collect();
+        let segments = stitch_lines(lines);
+        if !segments.is_empty() {
+            features.push(Feature::Track {
+                name: name(archive, relations[idx as usize].tags()),
+                segments,
+            });
+        }
+    }
+
+    features
+}
+
+/// Escapes the characters with special meaning in XML text and attributes.
+pub fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn write_gpx(mut out: impl Write, features: &[Feature]) -> io::Result<()> {
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<gpx version="1.1" creator="osmflat" xmlns="http://www.topografix.com/GPX/1/1">"#
+    )?;
+    // GPX requires all waypoints before the tracks
+    for feature in features {
+        if let Feature::Waypoint { name, coord } = feature {
+            writeln!(out, r#"  <wpt lat="{}" lon="{}">"#, coord.1, coord.0)?;
+            if let Some(name) = name {
+                writeln!(out, "    <name>{}</name>", escape(name))?;
+            }
+            writeln!(out, "  </wpt>")?;
+        }
+    }
+    for feature in features {
+        if let Feature::Track { name, segments } = feature {
+            writeln!(out, "  <trk>")?;
+            if let Some(name) = name {
+                writeln!(out, "    <name>{}</name>", escape(name))?;
+            }
+            for segment in segments {
+                writeln!(out, "    <trkseg>")?;
+                for (lon, lat) in segment {
+                    writeln!(out, r#"      <trkpt lat="{lat}" lon="{lon}"/>"#)?;
+                }
+                writeln!(out, "    </trkseg>")?;
+            }
+            writeln!(out, "  </trk>")?;
+        }
+    }
+    writeln!(out, "</gpx>")?;
+    out.flush()
+}
+
+fn write_kml_coords(mut out: impl Write, coords: &[Coord]) -> io::Result<()> {
+    write!(out, "<coordinates>")?;
+    for (i, (lon, lat)) in coords.iter().enumerate() {
+        let sep = if i == 0 { "" } else { " " };
+        write!(out, "{sep}{lon},{lat}")?;
+    }
+    write!(out, "</coordinates>")
+}
+
+fn write_kml(mut out: impl Write, features: &[Feature]) -> io::Result<()> {
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(out, r#"<kml xmlns="http://www.opengis.net/kml/2.2">"#)?;
+    writeln!(out, "  <Document>")?;
+    for feature in features {
+        writeln!(out, "    <Placemark>")?;
+        let name = match feature {
+            Feature::Waypoint { name, .. } | Feature::Track { name, .. } => name,
+        };
+        if let Some(name) = name {
+            writeln!(out, "      <name>{}</name>", escape(name))?;
+        }
+        match feature {
+            Feature::Waypoint { coord, .. } => {
+                write!(out, "      <Point>")?;
+                write_kml_coords(&mut out, &[*coord])?;
+                writeln!(out, "</Point>")?;
+            }
+            Feature::Track { segments, .. } => {
+                writeln!(out, "      <MultiGeometry>")?;
+                for segment in segments {
+                    write!(out, "        <LineString>")?;
+                    write_kml_coords(&mut out, segment)?;
+                    writeln!(out, "</LineString>")?;
+                }
+                writeln!(out, "      </MultiGeometry>")?;
+            }
+        }
+        writeln!(out, "    </Placemark>")?;
+    }
+    writeln!(out, "  </Document>")?;
+    writeln!(out, "</kml>")?;
+    out.flush()
+}
+
+/// Writes the selected entities as GeoJSON features, cf.
+/// [`osmflat::to_geojson_feature`], each on a line of its own: as
+/// `FeatureCollection`, or as newline-delimited GeoJSON if `delimited` is set.
+///
+/// Entities whose geometry cannot be built are skipped. Returns the number of
+/// written features.
+fn write_geojson(
+    mut out: impl Write,
+    archive: &Osm,
+    selection: &Selection,
+    delimited: bool,
+) -> io::Result<usize> {
+    if !delimited {
+        writeln!(out, r#"{{"type":"FeatureCollection","features":["#)?;
+    }
+    let entities = (selection.nodes.iter().map(|&idx| (EntityType::Node, idx)))
+        .chain(selection.ways.iter().map(|&idx| (EntityType::Way, idx)))
+        .chain(
+            selection
+                .relations
+                .iter()
+                .map(|&idx| (EntityType::Relation, idx)),
+        );
+    let mut num_features = 0;
+    for (entity_type, idx) in entities {
+        let feature = to_geojson_feature(archive, entity_type, idx as usize);
+        if feature.geometry.is_none() {
+            continue;
+        }
+        if num_features > 0 && !delimited {
+            writeln!(out, ",")?;
+        }
+        serde_json::to_writer(&mut out, &feature)?;
+        if delimited {
+            writeln!(out)?;
+        }
+        num_features += 1;
+    }
+    if !delimited {
+        if num_features > 0 {
+            writeln!(out)?;
+        }
+        writeln!(out, "]}}")?;
+    }
+    out.flush()?;
+    Ok(num_features)
+}
+
+/// Returns the indexes of the latest versions of the entities at `indexes`
+/// in ascending order, skipping deleted entities.
+///
+/// Archives without the `history` subarchive contain a single version of each
+/// entity, so that the indexes are returned unchanged.
+fn latest_versions(archive: &Osm, entity_type: EntityType, mut indexes: Vec<u64>) -> Vec<u64> {
+    if let Some(history) = archive.history() {
+        let versions = match entity_type {
+            EntityType::Node => history.nodes(),
+            EntityType::Way => history.ways(),
+            EntityType::Relation => history.relations(),
+        };
+        indexes = indexes
+            .into_iter()
+            .filter_map(|idx| {
+                let last = match version_range(archive, entity_type, idx as usize) {
+                    Some(range) => range.end - 1,
+                    None => idx as usize,
+                };
+                versions[last].visible().then_some(last as u64)
+            })
+            .collect();
+    }
+    indexes.sort_unstable();
+    indexes.dedup();
+    indexes
+}
+
+/// Writes the selected entities as OSM XML, cf. [`Element::write_xml`].
+///
+/// The selection is completed by the node and way members of the selected
+/// relations and by the nodes of the selected ways, so that the file can be
+/// opened in editors like JOSM. Relation members of relations are referenced,
+/// but not written. If `bbox` is given, it is written as `bounds` element.
+///
+/// Returns the numbers of written nodes, ways and relations.
+fn write_osm(
+    mut out: impl Write,
+    archive: &Osm,
+    selection: &Selection,
+    bbox: Option<BBox>,
+) -> io::Result<[usize; 3]> {
+    let mut nodes = selection.nodes.clone();
+    let mut ways = selection.ways.clone();
+    for &idx in &selection.relations {
+        for member in archive.relation_members().at(idx as usize) {
+            match member {
+                RelationMembersRef::NodeMember(m) => nodes.extend(m.node_idx()),
+                RelationMembersRef::WayMember(m) => ways.extend(m.way_idx()),
+                RelationMembersRef::RelationMember(_) => (),
+            }
+        }
+    }
+    let ways = latest_versions(archive, EntityType::Way, ways);
+    let nodes_index = archive.nodes_index();
+    for &idx in &ways {
+        let refs = archive.ways()[idx as usize].refs();
+        nodes.extend(refs.filter_map(|i| nodes_index[i as usize].value()));
+    }
+    let nodes = latest_versions(archive, EntityType::Node, nodes);
+    let relations = latest_versions(archive, EntityType::Relation, selection.relations.clone());
+
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(out, r#"<osm version="0.6" generator="osmflat">"#)?;
+    if let Some(((min_lon, min_lat), (max_lon, max_lat))) = bbox {
+        writeln!(
+            out,
+            r#"  <bounds minlat="{min_lat}" minlon="{min_lon}" maxlat="{max_lat}" maxlon="{max_lon}"/>"#
+        )?;
+    }
+    let ids = IdLookup::or_indexes(archive);
+    for (entity_type, indexes) in [
+        (EntityType::Node, &nodes),
+        (EntityType::Way, &ways),
+        (EntityType::Relation, &relations),
+    ] {
+        for &idx in indexes {
+            Element::new(archive, &ids, entity_type, idx).write_xml(&mut out, 2)?;
+        }
+    }
+    writeln!(out, "</osm>")?;
+    out.flush()?;
+    Ok([nodes.len(), ways.len(), relations.len()])
+}
+
+fn write(
+    out: impl Write,
+    archive: &Osm,
+    selection: &Selection,
+    format: ExportFormat,
+    bbox: Option<BBox>,
+) -> Result<(), Error> {
+    match format {
+        ExportFormat::Geojson | ExportFormat::Geojsonl => {
+            let delimited = matches!(format, ExportFormat::Geojsonl);
+            let num_features = write_geojson(out, archive, selection, delimited)?;
+            eprintln!("Exported {num_features} features");
+        }
+        ExportFormat::Osm => {
+            let [nodes, ways, relations] = write_osm(out, archive, selection, bbox)?;
+            eprintln!("Exported {nodes} nodes, {ways} ways and {relations} relations");
+        }
+        ExportFormat::Gpx | ExportFormat::Kml => {
+            let features = features(archive, selection);
+            let waypoints = features
+                .iter()
+                .filter(|f| matches!(f, Feature::Waypoint { .. }))
+                .count();
+            eprintln!(
+                "Exporting {waypoints} waypoints and {} tracks",
+                features.len() - waypoints
+            );
+            if let ExportFormat::Gpx = format {
+                write_gpx(out, &features)?;
+            } else {
+                write_kml(out, &features)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn run(args: ExportArgs) -> Result<(), Error> {
+    let archive = Osm::open(FileResourceStorage::new(&args.input))?;
+    let selection = Selection::new(&archive, &args);
+
+    let compressed = args.output.extension().is_some_and(|ext| ext == "bz2");
+    let output = if compressed {
+        args.output.with_extension("")
+    } else {
+        args.output.clone()
+    };
+    let format =
+        args.format
+            .unwrap_or_else(|| match output.extension().and_then(|ext| ext.to_str()) {
+                Some("kml") => ExportFormat::Kml,
+                Some("geojson") => ExportFormat::Geojson,
+                Some("geojsonl" | "ndjson") => ExportFormat::Geojsonl,
+                Some("osm") => ExportFormat::Osm,
+                _ => ExportFormat::Gpx,
+            });
+    let out: Box<dyn Write> = if args.output.as_os_str() == "-" {
+        Box::new(io::stdout().lock())
+    } else {
+        Box::new(File::create(&args.output)?)
+    };
+    let out = BufWriter::new(out);
+
+    if compressed {
+        let mut encoder = BzEncoder::new(out, bzip2::Compression::default());
+        write(&mut encoder, &archive, &selection, format, args.bbox)?;
+        encoder.finish()?.flush()?;
+    } else {
+        write(out, &archive, &selection, format, args.bbox)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_stitch_lines() {
+        let a = (0.0, 0.0);
+        let b = (1.0, 0.0);
+        let c = (2.0, 0.0);
+        let d = (3.0, 0.0);
+        let e = (5.0, 0.0);
+        let f = (6.0, 0.0);
+        // first line reversed, second line reversed, then a gap
+        let lines = vec![vec![b, a], vec![b, c], vec![d, c], vec![e, f]];
+        assert_eq!(stitch_lines(lines), vec![vec![a, b, c, d], vec![e, f]]);
+    }
+
+    #[test]
+    fn test_gpx_escapes_names() {
+        let features = vec![
+            Feature::Waypoint {
+                name: Some("Tom & Jerry's <Hut>".into()),
+                coord: (13.4, 52.5),
+            },
+            Feature::Track {
+                name: None,
+                segments: vec![vec![(13.0, 52.0), (13.1, 52.1)]],
+            },
+        ];
+        let mut data = Vec::new();
+        write_gpx(&mut data, &features).unwrap();
+        let gpx = String::from_utf8(data).unwrap();
+        assert!(gpx.contains("<name>Tom &amp; Jerry&apos;s &lt;Hut&gt;</name>"));
+        assert!(gpx.contains(r#"<wpt lat="52.5" lon="13.4">"#));
+        assert!(gpx.contains(r#"<trkpt lat="52.1" lon="13.1"/>"#));
+    }
+
+    #[test]
+    fn test_geojson() {
+        let archive = osmflat::TestArchive {
+            nodes: vec![
+                (0.0, 0.0, vec![("amenity", "cafe")]),
+                (1.0, 0.0, vec![]),
+                (1.0, 1.0, vec![("amenity", "pub")]),
+            ],
+            ways: vec![(vec![0, 1, 2, 0], vec![("amenity", "parking")])],
+            relations: vec![],
+        }
+        .build();
+        let args = |bbox| ExportArgs {
+            filter: "amenity".parse().unwrap(),
+            bbox,
+            format: None,
+            input: "archive".into(),
+            output: "-".into(),
+        };
+
+        let selection = Selection::new(&archive, &args(Some(((0.5, 0.5), (2.0, 2.0)))));
+        assert_eq!(
+            (selection.nodes, selection.ways, selection.relations),
+            (vec![2], vec![0], vec![])
+        );
+
+        let selection = Selection::new(&archive, &args(None));
+        let mut out = Vec::new();
+        assert_eq!(
+            write_geojson(&mut out, &archive, &selection, false).unwrap(),
+            3
+        );
+        let collection: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(collection["features"][2]["geometry"]["type"], "Polygon");
+        assert_eq!(collection["features"][1]["properties"]["amenity"], "pub");
+
+        let mut out = Vec::new();
+        write_geojson(&mut out, &archive, &selection, true).unwrap();
+        let lines: Vec<serde_json::Value> = out
+            .split(|&c| c == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0]["geometry"]["coordinates"],
+            serde_json::json!([0.0, 0.0])
+        );
+    }
+
+    #[test]
+    fn test_write_osm() {
+        let archive = osmflat::TestArchive {
+            nodes: vec![
+                (0.0, 0.0, vec![]),
+                (1.0, 0.0, vec![]),
+                (2.0, 0.0, vec![("amenity", "bench")]),
+                (3.0, 0.0, vec![]),
+            ],
+            ways: vec![
+                (vec![0, 1], vec![("highway", "path")]),
+                (vec![1, 3], vec![("highway", "path")]),
+            ],
+            relations: vec![(
+                vec![osmflat::Member::Way(0, ""), osmflat::Member::Node(2, "")],
+                vec![("route", "hiking")],
+            )],
+        }
+        .build();
+        let args = ExportArgs {
+            filter: "route".parse().unwrap(),
+            bbox: None,
+            format: None,
+            input: "archive".into(),
+            output: "-".into(),
+        };
+        let selection = Selection::new(&archive, &args);
+        let mut out = Vec::new();
+        let counts = write_osm(&mut out, &archive, &selection, None).unwrap();
+        assert_eq!(counts, [3, 1, 1]);
+        let osm = String::from_utf8(out).unwrap();
+        // entities are numbered by their index starting at 1 without ids
+        assert!(osm.contains(r#"<node id="3" lat="0.0000000" lon="2.0000000">"#));
+        assert!(osm.contains(r#"<way id="1">"#));
+        assert!(!osm.contains(r#"<way id="2">"#));
+        assert!(osm.contains(r#"<member type="way" ref="1" role=""/>"#));
+        assert!(osm.find("<node") < osm.find("<way") && osm.find("<way") < osm.find("<relation"));
+        assert!(osm.ends_with("</osm>\n"));
+    }
+}
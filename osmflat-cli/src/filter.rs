@@ -0,0 +1,64 @@
+use osmflat::{find_tag, Osm};
+
+use std::ops::Range;
+use std::str::FromStr;
+
+/// Tag filter given on the command line.
+///
+/// Supported forms are `key=value`, and `key` or `key=*` matching any value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filter {
+    key: String,
+    value: Option<String>,
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, value) = match s.split_once('=') {
+            Some((key, "*")) => (key, None),
+            Some((key, value)) => (key, Some(value.to_string())),
+            None => (s, None),
+        };
+        if key.is_empty() {
+            return Err(format!("invalid filter '{s}', expected key=value or key"));
+        }
+        Ok(Self {
+            key: key.to_string(),
+            value,
+        })
+    }
+}
+
+impl Filter {
+    /// Returns the value of the filtered key if the tags match the filter.
+    pub fn matches<'a>(&self, archive: &'a Osm, tags: Range<u64>) -> Option<&'a [u8]> {
+        let value = find_tag(archive, tags, self.key.as_bytes())?;
+        match &self.value {
+            Some(expected) if expected.as_bytes() != value => None,
+            _ => Some(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let filter = |key: &str, value: Option<&str>| Filter {
+            key: key.into(),
+            value: value.map(Into::into),
+        };
+        assert_eq!(
+            "landuse=forest".parse(),
+            Ok(filter("landuse", Some("forest")))
+        );
+        assert_eq!("landuse=*".parse(), Ok(filter("landuse", None)));
+        assert_eq!("landuse".parse(), Ok(filter("landuse", None)));
+        assert_eq!("a=b=c".parse(), Ok(filter("a", Some("b=c"))));
+        assert!("=forest".parse::<Filter>().is_err());
+    }
+}
@@ -0,0 +1,233 @@
+use osmflat::{Osm, RelationMembersRef, Way};
+
+use std::str::FromStr;
+
+/// Earth's radius for WGS84 in meters
+pub const EARTH_RADIUS_IN_METERS: f64 = 6_372_797.560_856;
+
+/// Coordinates represented by (longitude, latitude).
+pub type Coord = (f64, f64);
+
+/// Bounding box represented by its (min, max) coordinates.
+pub type BBox = (Coord, Coord);
+
+/// Coordinates given on the command line as `lat,lon`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LatLon(pub Coord);
+
+impl FromStr for LatLon {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid coordinates '{s}', expected lat,lon");
+        let (lat, lon) = s.split_once(',').ok_or_else(invalid)?;
+        let lat: f64 = lat.trim().parse().map_err(|_| invalid())?;
+        let lon: f64 = lon.trim().parse().map_err(|_| invalid())?;
+        if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+            return Err(invalid());
+        }
+        Ok(Self((lon, lat)))
+    }
+}
+
+pub fn node_coord(archive: &Osm, node_idx: u64) -> Coord {
+    archive.node_coord(node_idx as usize)
+}
+
+/// Returns the coordinates of a way, or `None` if a node is not resolved.
+pub fn way_coords(archive: &Osm, way: &Way) -> Option<Vec<Coord>> {
+    let nodes_index = archive.nodes_index();
+    way.refs()
+        .map(|idx| Some(node_coord(archive, nodes_index[idx as usize].value()?)))
+        .collect()
+}
+
+pub fn haversine_distance(c1: Coord, c2: Coord) -> f64 {
+    let mut lonh = ((c1.0 - c2.0).to_radians() * 0.5).sin();
+    lonh *= lonh;
+    let mut lath = ((c1.1 - c2.1).to_radians() * 0.5).sin();
+    lath *= lath;
+    let tmp = c1.1.to_radians().cos() * c2.1.to_radians().cos();
+    2.0 * EARTH_RADIUS_IN_METERS * (lath + tmp * lonh).sqrt().asin()
+}
+
+/// Distance in meters from `p` to the segment `(a, b)`.
+///
+/// Uses an equirectangular projection around `p`, which is precise enough for
+/// small distances.
+pub fn distance_to_segment(p: Coord, (a, b): (Coord, Coord)) -> f64 {
+    let cos_lat = p.1.to_radians().cos();
+    let to_xy = |c: Coord| ((c.0 - p.0) * cos_lat, c.1 - p.1);
+    let (ax, ay) = to_xy(a);
+    let (bx, by) = to_xy(b);
+    let (dx, dy) = (bx - ax, by - ay);
+    let len2 = dx * dx + dy * dy;
+    let t = if len2 == 0.0 {
+        0.0
+    } else {
+        (-(ax * dx + ay * dy) / len2).clamp(0.0, 1.0)
+    };
+    let (x, y) = (ax + t * dx, ay + t * dy);
+    (x * x + y * y).sqrt().to_radians() * EARTH_RADIUS_IN_METERS
+}
+
+/// Distance in meters from `p` to the line string `line`.
+pub fn distance_to_line(p: Coord, line: &[Coord]) -> f64 {
+    match line {
+        [] => f64::INFINITY,
+        [c] => haversine_distance(p, *c),
+        _ => line
+            .windows(2)
+            .map(|w| distance_to_segment(p, (w[0], w[1])))
+            .fold(f64::INFINITY, f64::min),
+    }
+}
+
+/// Geodesic area of a closed ring in m² on a spherical earth.
+///
+/// Cf. "Some Algorithms for Polygons on a Sphere" by Chamberlain and Duquette.
+pub fn ring_area(ring: &[Coord]) -> f64 {
+    let sum: f64 = ring
+        .windows(2)
+        .map(|w| {
+            let (c1, c2) = (w[0], w[1]);
+            (c2.0 - c1.0).to_radians() * (2.0 + c1.1.to_radians().sin() + c2.1.to_radians().sin())
+        })
+        .sum();
+    (sum * EARTH_RADIUS_IN_METERS * EARTH_RADIUS_IN_METERS / 2.0).abs()
+}
+
+fn is_closed(ring: &[Coord]) -> bool {
+    ring.len() >= 4 && ring.first() == ring.last()
+}
+
+/// Stitches line strings into closed rings by joining them at their
+/// endpoints. Line strings which cannot be closed are dropped.
+pub fn assemble_rings(mut segments: Vec<Vec<Coord>>) -> Vec<Vec<Coord>> {
+    let mut rings = Vec::new();
+    while let Some(mut ring) = segments.pop() {
+        while !ring.is_empty() && !is_closed(&ring) {
+            let last = ring[ring.len() - 1];
+            let next = segments
+                .iter()
+                .position(|s| s.first() == Some(&last) || s.last() == Some(&last));
+            match next {
+                Some(pos) => {
+                    let mut segment = segments.swap_remove(pos);
+                    if segment.first() != Some(&last) {
+                        segment.reverse();
+                    }
+                    ring.extend(segment.into_iter().skip(1));
+                }
+                None => break,
+            }
+        }
+        if is_closed(&ring) {
+            rings.push(ring);
+        }
+    }
+    rings
+}
+
+/// Point in polygon test with the ray casting algorithm.
+pub fn ring_contains(ring: &[Coord], (x, y): Coord) -> bool {
+    let mut inside = false;
+    for w in ring.windows(2) {
+        let ((x0, y0), (x1, y1)) = (w[0], w[1]);
+        if (y0 > y) != (y1 > y) && x < (x1 - x0) * (y - y0) / (y1 - y0) + x0 {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Area given by outer and inner rings.
+pub struct MultiPolygon {
+    pub outer: Vec<Vec<Coord>>,
+    pub inner: Vec<Vec<Coord>>,
+}
+
+impl MultiPolygon {
+    /// Assembles the rings of a multipolygon-like relation from its way
+    /// members. Returns `None` if no closed outer ring could be assembled.
+    pub fn from_relation(archive: &Osm, relation_idx: usize) -> Option<Self> {
+        let strings = archive.stringtable();
+        let ways = archive.ways();
+
+        let mut outer = Vec::new();
+        let mut inner = Vec::new();
+        for member in archive.relation_members().at(relation_idx) {
+            if let RelationMembersRef::WayMember(m) = member {
+                let coords = match m.way_idx() {
+                    Some(idx) => way_coords(archive, &ways[idx as usize]),
+                    None => None,
+                };
+                match (coords, strings.substring(m.role_idx() as usize)) {
+                    (Some(coords), Ok("inner")) => inner.push(coords),
+                    (Some(coords), _) => outer.push(coords),
+                    (None, _) => (),
+                }
+            }
+        }
+
+        let outer = assemble_rings(outer);
+        if outer.is_empty() {
+            return None;
+        }
+        Some(Self {
+            outer,
+            inner: assemble_rings(inner),
+        })
+    }
+
+    pub fn contains(&self, c: Coord) -> bool {
+        self.outer.iter().any(|r| ring_contains(r, c))
+            && !self.inner.iter().any(|r| ring_contains(r, c))
+    }
+
+    /// Geodesic area in m².
+    pub fn area(&self) -> f64 {
+        let outer: f64 = self.outer.iter().map(|r| ring_area(r)).sum();
+        let inner: f64 = self.inner.iter().map(|r| ring_area(r)).sum();
+        (outer - inner).max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_lat_lon() {
+        assert_eq!("52.5, 13.4".parse(), Ok(LatLon((13.4, 52.5))));
+        assert!("52.5".parse::<LatLon>().is_err());
+        assert!("91,0".parse::<LatLon>().is_err());
+        assert!("0,x".parse::<LatLon>().is_err());
+    }
+
+    #[test]
+    fn test_multipolygon_with_hole() {
+        let square =
+            |min: f64, max: f64| vec![(min, min), (max, min), (max, max), (min, max), (min, min)];
+        // outer ring split into two line strings
+        let outer = square(0.0, 4.0);
+        let outer = assemble_rings(vec![outer[..3].to_vec(), outer[2..].to_vec()]);
+        assert_eq!(outer.len(), 1);
+        let polygon = MultiPolygon {
+            outer,
+            inner: vec![square(1.0, 2.0)],
+        };
+        assert!(polygon.contains((3.0, 3.0)));
+        assert!(!polygon.contains((1.5, 1.5)));
+        assert!(!polygon.contains((5.0, 5.0)));
+    }
+
+    #[test]
+    fn test_ring_area() {
+        // 0.001° x 0.001° at the equator is ~111 m x 111 m
+        let d: f64 = 0.001;
+        let ring = [(0.0, 0.0), (d, 0.0), (d, d), (0.0, d), (0.0, 0.0)];
+        let side = d.to_radians() * EARTH_RADIUS_IN_METERS;
+        assert!((ring_area(&ring) - side * side).abs() < 1.0);
+    }
+}
@@ -0,0 +1,195 @@
+use crate::args::GrepArgs;
+use crate::id::IdLookup;
+use crate::query::location;
+use crate::Error;
+
+use osmflat::rayon::prelude::*;
+use osmflat::{EntityType, FileResourceStorage, Osm};
+use regex::bytes::{Regex, RegexBuilder};
+
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+
+/// Returns for each tag of the archive whether it matches the pattern.
+///
+/// Tags are unique in an archive, so each key and value is tested once per
+/// tag it occurs in rather than once per entity. If `key` is given, only the
+/// values of tags with this key are tested, otherwise keys and values.
+fn matching_tags(archive: &Osm, regex: &Regex, key: Option<&str>) -> Vec<bool> {
+    archive
+        .tags()
+        .par_iter()
+        .map(|tag| {
+            let tag_key = archive.substring_fast(tag.key_idx() as usize);
+            let value = archive.substring_fast(tag.value_idx() as usize);
+            match key {
+                Some(key) => key.as_bytes() == tag_key && regex.is_match(value),
+                None => regex.is_match(tag_key) || regex.is_match(value),
+            }
+        })
+        .collect()
+}
+
+/// Returns the indexes of the entities having a matching tag, together with
+/// the indexes of their matching tags, in ascending order.
+fn matching_entities<T: Sync>(
+    archive: &Osm,
+    entities: &[T],
+    tags: impl Fn(&T) -> Range<u64> + Sync,
+    matching: &[bool],
+) -> Vec<(usize, Vec<u64>)> {
+    let tags_index = archive.tags_index();
+    entities
+        .par_iter()
+        .enumerate()
+        .filter_map(|(idx, entity)| {
+            let matches: Vec<u64> = tags(entity)
+                .map(|i| tags_index[i as usize].value())
+                .filter(|&tag_idx| matching[tag_idx as usize])
+                .collect();
+            (!matches.is_empty()).then_some((idx, matches))
+        })
+        .collect()
+}
+
+fn write_match(
+    out: &mut impl Write,
+    archive: &Osm,
+    ids: Option<&IdLookup>,
+    entity_type: EntityType,
+    idx: usize,
+    tags: &[u64],
+) -> io::Result<()> {
+    let id = ids.map(|ids| match entity_type {
+        EntityType::Node => ids.node(idx as u64),
+        EntityType::Way => ids.way(idx as u64),
+        EntityType::Relation => ids.relation(idx as u64),
+    });
+    match id {
+        Some(id) => write!(out, "{id}")?,
+        None => {
+            let prefix = match entity_type {
+                EntityType::Node => 'n',
+                EntityType::Way => 'w',
+                EntityType::Relation => 'r',
+            };
+            write!(out, "{prefix}#{idx}")?
+        }
+    }
+    match location(archive, entity_type, idx) {
+        Some((lon, lat)) => write!(out, " {lat:.7},{lon:.7}")?,
+        None => write!(out, " -")?,
+    }
+    let all_tags = archive.tags();
+    for &tag_idx in tags {
+        let tag = &all_tags[tag_idx as usize];
+        let key = archive.substring_fast(tag.key_idx() as usize);
+        let value = archive.substring_fast(tag.value_idx() as usize);
+        write!(
+            out,
+            " {}={}",
+            String::from_utf8_lossy(key),
+            String::from_utf8_lossy(value)
+        )?;
+    }
+    writeln!(out)
+}
+
+pub fn run(args: GrepArgs) -> Result<(), Error> {
+    let archive = Osm::open(FileResourceStorage::new(&args.input))?;
+    let ids = IdLookup::new(&archive).ok();
+
+    let pattern = if args.fixed_strings {
+        regex::escape(&args.pattern)
+    } else {
+        args.pattern.clone()
+    };
+    let regex = RegexBuilder::new(&pattern)
+        .case_insensitive(args.ignore_case)
+        .build()?;
+    let matching = matching_tags(&archive, &regex, args.key.as_deref());
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    for (entity_type, name, c) in [
+        (EntityType::Node, "nodes", 'n'),
+        (EntityType::Way, "ways", 'w'),
+        (EntityType::Relation, "relations", 'r'),
+    ] {
+        if !args.types.contains(c) {
+            continue;
+        }
+        let entities = match entity_type {
+            EntityType::Node => {
+                matching_entities(&archive, archive.nodes(), |n| n.tags(), &matching)
+            }
+            EntityType::Way => matching_entities(&archive, archive.ways(), |w| w.tags(), &matching),
+            EntityType::Relation => {
+                matching_entities(&archive, archive.relations(), |r| r.tags(), &matching)
+            }
+        };
+        if args.count {
+            writeln!(out, "{name}: {}", entities.len())?;
+            continue;
+        }
+        for (idx, tags) in entities {
+            write_match(&mut out, &archive, ids.as_ref(), entity_type, idx, &tags)?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_matching_entities() {
+        let archive = osmflat::TestArchive {
+            nodes: vec![
+                (
+                    13.4,
+                    52.5,
+                    vec![("name", "Café Einstein"), ("amenity", "cafe")],
+                ),
+                (13.5, 52.5, vec![("amenity", "bar")]),
+                (13.6, 52.5, vec![("note", "fixme: cafe or bar?")]),
+            ],
+            ways: vec![(vec![0, 1], vec![("name:de", "Caféstraße")])],
+            relations: vec![],
+        }
+        .build();
+        let regex = |pattern: &str| {
+            RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .unwrap()
+        };
+        let nodes = |matching: &[bool]| -> Vec<(usize, usize)> {
+            matching_entities(&archive, archive.nodes(), |n| n.tags(), matching)
+                .into_iter()
+                .map(|(idx, tags)| (idx, tags.len()))
+                .collect()
+        };
+
+        let matching = matching_tags(&archive, &regex("caf"), None);
+        assert_eq!(nodes(&matching), [(0, 2), (2, 1)]);
+        let ways = matching_entities(&archive, archive.ways(), |w| w.tags(), &matching);
+        assert_eq!(ways.len(), 1);
+
+        // keys are matched as well
+        let matching = matching_tags(&archive, &regex("^name"), None);
+        assert_eq!(nodes(&matching), [(0, 1)]);
+
+        let matching = matching_tags(&archive, &regex("caf"), Some("amenity"));
+        assert_eq!(nodes(&matching), [(0, 1)]);
+
+        let mut out = Vec::new();
+        let (idx, tags) = &matching_entities(&archive, archive.nodes(), |n| n.tags(), &matching)[0];
+        write_match(&mut out, &archive, None, EntityType::Node, *idx, tags).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "n#0 52.5000000,13.4000000 amenity=cafe\n"
+        );
+    }
+}
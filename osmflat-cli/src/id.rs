@@ -0,0 +1,252 @@
+use osmflat::{EntityType, Ids, Osm};
+use serde::{Serialize, Serializer};
+
+use std::fmt;
+use std::str::FromStr;
+
+/// OSM id of a node, way or relation.
+///
+/// Ids are signed: entities which were not uploaded yet, e.g. in files exported
+/// from JOSM, have negative ids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OsmId {
+    Node(i64),
+    Way(i64),
+    Relation(i64),
+}
+
+impl FromStr for OsmId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid id '{s}', expected e.g. n123, w123, or r123");
+        let mut chars = s.chars();
+        let kind = chars.next().ok_or_else(invalid)?;
+        let id = chars.as_str().parse().map_err(|_| invalid())?;
+        match kind {
+            'n' | 'N' => Ok(Self::Node(id)),
+            'w' | 'W' => Ok(Self::Way(id)),
+            'r' | 'R' => Ok(Self::Relation(id)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for OsmId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Node(id) => write!(f, "n{id}"),
+            Self::Way(id) => write!(f, "w{id}"),
+            Self::Relation(id) => write!(f, "r{id}"),
+        }
+    }
+}
+
+impl Serialize for OsmId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Lookup between OSM ids and indexes into the archive.
+///
+/// Requires the optional ids subarchive, unless created with
+/// [`IdLookup::or_indexes`].
+pub struct IdLookup<'a> {
+    archive: &'a Osm,
+    ids: Option<&'a Ids>,
+}
+
+impl<'a> IdLookup<'a> {
+    pub fn new(archive: &'a Osm) -> Result<Self, String> {
+        let ids = archive
+            .ids()
+            .ok_or("archive does not contain ids, compile it with osmflatc --ids")?;
+        Ok(Self {
+            archive,
+            ids: Some(ids),
+        })
+    }
+
+    /// Returns a lookup which numbers the entities by their index starting at
+    /// 1 if the archive does not contain ids, like `osmflatc export-pbf`.
+    pub fn or_indexes(archive: &'a Osm) -> Self {
+        Self {
+            archive,
+            ids: archive.ids(),
+        }
+    }
+
+    /// Returns the index of the entity with the given id.
+    ///
+    /// Uses [`Osm::find_by_id`], i.e. the `id_index` subarchive if present,
+    /// and a binary search over the ids otherwise. The binary search requires
+    /// the ids to be in ascending order, which they are not in archives
+    /// compiled with `--sort hilbert` or containing negative ids, so if it
+    /// finds nothing, the ids are scanned linearly.
+    pub fn find(&self, id: OsmId) -> Option<usize> {
+        let (entity_type, value) = match id {
+            OsmId::Node(value) => (EntityType::Node, value),
+            OsmId::Way(value) => (EntityType::Way, value),
+            OsmId::Relation(value) => (EntityType::Relation, value),
+        };
+        if let Some(ids) = self.ids {
+            if self.archive.id_index().is_some() {
+                return self.archive.find_by_id(entity_type, value);
+            }
+            let ids = match entity_type {
+                EntityType::Node => ids.nodes(),
+                EntityType::Way => ids.ways(),
+                EntityType::Relation => ids.relations(),
+            };
+            return self
+                .archive
+                .find_by_id(entity_type, value)
+                .or_else(|| ids.iter().position(|id| id.signed_value() == value));
+        }
+        let len = match entity_type {
+            EntityType::Node => self.archive.nodes().len(),
+            EntityType::Way => self.archive.ways().len(),
+            EntityType::Relation => self.archive.relations().len(),
+        };
+        let idx = value.checked_sub(1)?;
+        usize::try_from(idx).ok().filter(|&idx| idx < len)
+    }
+
+    pub fn node(&self, idx: u64) -> OsmId {
+        OsmId::Node(self.ids.map_or(idx as i64 + 1, |ids| {
+            ids.nodes()[idx as usize].signed_value()
+        }))
+    }
+
+    pub fn way(&self, idx: u64) -> OsmId {
+        OsmId::Way(self.ids.map_or(idx as i64 + 1, |ids| {
+            ids.ways()[idx as usize].signed_value()
+        }))
+    }
+
+    pub fn relation(&self, idx: u64) -> OsmId {
+        OsmId::Relation(self.ids.map_or(idx as i64 + 1, |ids| {
+            ids.relations()[idx as usize].signed_value()
+        }))
+    }
+}
+
+/// Builds the archive described by `archive` in memory, with the given node,
+/// way and relation ids.
+#[cfg(test)]
+pub fn build_with_ids(archive: &osmflat::TestArchive, ids: [&[i64]; 3]) -> Osm {
+    use flatdata::ResourceStorage;
+    use osmflat::{Id, IdsBuilder, OsmWriter};
+
+    let storage = flatdata::MemoryResourceStorage::new("/osmflat-cli-test");
+    let mut writer = OsmWriter::new(storage.clone()).unwrap();
+    for (lon, lat, tags) in &archive.nodes {
+        writer.add_node(*lon, *lat, tags.iter().copied());
+    }
+    for (refs, tags) in &archive.ways {
+        writer.add_way(refs, tags.iter().copied());
+    }
+    for (members, tags) in &archive.relations {
+        writer.add_relation(members, tags.iter().copied());
+    }
+    writer.finish().unwrap();
+
+    let to_ids = |ids: &[i64]| -> Vec<Id> {
+        ids.iter()
+            .map(|&value| {
+                let mut id = Id::new();
+                id.set_signed_value(value);
+                id
+            })
+            .collect()
+    };
+    let builder = IdsBuilder::new(storage.subdir("ids")).unwrap();
+    builder.set_nodes(&to_ids(ids[0])).unwrap();
+    builder.set_ways(&to_ids(ids[1])).unwrap();
+    builder.set_relations(&to_ids(ids[2])).unwrap();
+    Osm::open(storage).unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        for s in ["n1", "w123", "r-1", "n-9223372036854775808"] {
+            assert_eq!(s.parse::<OsmId>().unwrap().to_string(), s);
+        }
+        assert_eq!("N42".parse(), Ok(OsmId::Node(42)));
+        assert!("".parse::<OsmId>().is_err());
+        assert!("x1".parse::<OsmId>().is_err());
+        assert!("n".parse::<OsmId>().is_err());
+        assert_eq!("w-1".parse(), Ok(OsmId::Way(-1)));
+        assert!("w-".parse::<OsmId>().is_err());
+        assert!("r18446744073709551615".parse::<OsmId>().is_err());
+    }
+
+    #[test]
+    fn test_or_indexes() {
+        let archive = osmflat::TestArchive {
+            nodes: vec![(0.0, 0.0, vec![]), (1.0, 1.0, vec![])],
+            ways: vec![],
+            relations: vec![],
+        }
+        .build();
+        assert!(IdLookup::new(&archive).is_err());
+        let ids = IdLookup::or_indexes(&archive);
+        assert_eq!(ids.node(1), OsmId::Node(2));
+        assert_eq!(ids.find(OsmId::Node(2)), Some(1));
+        assert_eq!(ids.find(OsmId::Node(0)), None);
+        assert_eq!(ids.find(OsmId::Node(-1)), None);
+        assert_eq!(ids.find(OsmId::Node(i64::MIN)), None);
+        assert_eq!(ids.find(OsmId::Node(3)), None);
+        assert_eq!(ids.find(OsmId::Way(1)), None);
+    }
+
+    #[test]
+    fn test_negative_ids() {
+        let archive = osmflat::TestArchive {
+            nodes: vec![(0.0, 0.0, vec![]), (1.0, 1.0, vec![])],
+            ways: vec![(vec![0, 1], vec![])],
+            relations: vec![],
+        };
+        let archive = build_with_ids(&archive, [&[-2, 5], &[-1], &[]]);
+        let ids = IdLookup::new(&archive).unwrap();
+        assert_eq!(ids.node(0), OsmId::Node(-2));
+        assert_eq!(ids.way(0).to_string(), "w-1");
+        assert_eq!(ids.find("w-1".parse().unwrap()), Some(0));
+        assert_eq!(ids.find(OsmId::Node(5)), Some(1));
+        assert_eq!(ids.find(OsmId::Way(1)), None);
+    }
+
+    #[test]
+    fn test_find_in_hilbert_sorted_archive() {
+        // nodes with ascending ids, reordered along the Hilbert curve like
+        // `osmflatc --sort hilbert` does
+        let coords = [(10.0, 10.0), (-10.0, -10.0), (10.0, -10.0), (-10.0, 10.0)];
+        let mut nodes: Vec<(i64, (f64, f64))> = (1..).zip(coords).collect();
+        nodes.sort_by_key(|&(_, (lon, lat))| {
+            osmflat::hilbert_index((lon * 1e7) as i32, (lat * 1e7) as i32)
+        });
+        let node_ids: Vec<i64> = nodes.iter().map(|&(id, _)| id).collect();
+        assert!(node_ids.windows(2).any(|w| w[0] > w[1]));
+
+        let archive = osmflat::TestArchive {
+            nodes: nodes
+                .iter()
+                .map(|&(_, (lon, lat))| (lon, lat, vec![]))
+                .collect(),
+            ways: vec![],
+            relations: vec![],
+        };
+        let archive = build_with_ids(&archive, [&node_ids, &[], &[]]);
+        assert!(archive.id_index().is_none());
+        let ids = IdLookup::new(&archive).unwrap();
+        for (idx, &id) in node_ids.iter().enumerate() {
+            assert_eq!(ids.find(OsmId::Node(id)), Some(idx));
+        }
+        assert_eq!(ids.find(OsmId::Node(5)), None);
+    }
+}
@@ -0,0 +1,110 @@
+use crate::args::LocateArgs;
+use crate::geo::{distance_to_line, haversine_distance, node_coord, way_coords, MultiPolygon};
+use crate::id::{IdLookup, OsmId};
+use crate::Error;
+
+use osmflat::{find_tag, has_tag, FileResourceStorage, Osm};
+use serde::Serialize;
+
+use std::str;
+
+#[derive(Debug, Serialize)]
+struct Feature {
+    name: String,
+    id: Option<OsmId>,
+    /// Distance in meters
+    distance: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct AdminArea {
+    admin_level: u8,
+    name: String,
+    id: Option<OsmId>,
+}
+
+#[derive(Debug, Serialize)]
+struct Location {
+    nearest: Option<Feature>,
+    admin_areas: Vec<AdminArea>,
+}
+
+fn name(archive: &Osm, tags: std::ops::Range<u64>) -> Option<String> {
+    let name = find_tag(archive, tags, b"name")?;
+    Some(String::from_utf8_lossy(name).into_owned())
+}
+
+/// Finds the named node or way closest to `coord`.
+fn nearest_feature(archive: &Osm, ids: Option<&IdLookup>, coord: (f64, f64)) -> Option<Feature> {
+    let nodes = archive
+        .nodes()
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, node)| {
+            let name = name(archive, node.tags())?;
+            let distance = haversine_distance(coord, node_coord(archive, idx as u64));
+            Some((name, ids.map(|ids| ids.node(idx as u64)), distance))
+        });
+    let ways = archive.ways().iter().enumerate().filter_map(|(idx, way)| {
+        let name = name(archive, way.tags())?;
+        let distance = distance_to_line(coord, &way_coords(archive, way)?);
+        Some((name, ids.map(|ids| ids.way(idx as u64)), distance))
+    });
+    nodes
+        .chain(ways)
+        .min_by(|a, b| a.2.total_cmp(&b.2))
+        .map(|(name, id, distance)| Feature { name, id, distance })
+}
+
+/// Finds all administrative boundaries containing `coord`, ordered by their
+/// admin level.
+fn admin_areas(archive: &Osm, ids: Option<&IdLookup>, coord: (f64, f64)) -> Vec<AdminArea> {
+    let mut areas: Vec<AdminArea> = archive
+        .relations()
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| has_tag(archive, r.tags(), b"boundary", b"administrative"))
+        .filter_map(|(idx, r)| {
+            let admin_level = find_tag(archive, r.tags(), b"admin_level")?;
+            let admin_level = str::from_utf8(admin_level).ok()?.parse().ok()?;
+            if !MultiPolygon::from_relation(archive, idx)?.contains(coord) {
+                return None;
+            }
+            Some(AdminArea {
+                admin_level,
+                name: name(archive, r.tags()).unwrap_or_default(),
+                id: ids.map(|ids| ids.relation(idx as u64)),
+            })
+        })
+        .collect();
+    areas.sort_by_key(|area| area.admin_level);
+    areas
+}
+
+pub fn run(args: LocateArgs) -> Result<(), Error> {
+    let archive = Osm::open(FileResourceStorage::new(args.input))?;
+    let ids = IdLookup::new(&archive).ok();
+    let coord = args.coords.0;
+
+    let location = Location {
+        nearest: nearest_feature(&archive, ids.as_ref(), coord),
+        admin_areas: admin_areas(&archive, ids.as_ref(), coord),
+    };
+
+    if args.json {
+        serde_json::to_writer_pretty(std::io::stdout().lock(), &location)?;
+        println!();
+        return Ok(());
+    }
+
+    let id = |id: Option<OsmId>| id.map_or_else(String::new, |id| format!(" ({id})"));
+    match location.nearest {
+        Some(f) => println!("nearest: {}{} {:.1} m", f.name, id(f.id), f.distance),
+        None => println!("nearest: -"),
+    }
+    println!("admin areas:");
+    for area in location.admin_areas {
+        println!("  {:>2} {}{}", area.admin_level, area.name, id(area.id));
+    }
+    Ok(())
+}
@@ -0,0 +1,48 @@
+mod area;
+mod args;
+mod density;
+mod diff;
+mod element;
+mod export;
+mod filter;
+mod geo;
+mod grep;
+mod id;
+mod locate;
+mod mvt;
+mod orphans;
+mod parquet_export;
+mod query;
+mod show;
+mod stats;
+
+use crate::args::{Args, Command};
+
+use clap::Parser;
+
+type Error = Box<dyn std::error::Error>;
+
+fn run(args: Args) -> Result<(), Error> {
+    match args.command {
+        Command::Show(args) => show::run(args),
+        Command::Locate(args) => locate::run(args),
+        Command::Orphans(args) => orphans::run(args),
+        Command::Area(args) => area::run(args),
+        Command::Density(args) => density::run(args),
+        Command::Export(args) => export::run(args),
+        Command::Query(args) => query::run(args),
+        Command::Stats(args) => stats::run(args),
+        Command::Diff(args) => diff::run(args),
+        Command::Mvt(args) => mvt::run(args),
+        Command::Parquet(args) => parquet_export::run(args),
+        Command::Grep(args) => grep::run(args),
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(args) {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,977 @@
+//! Rendering of Mapbox vector tiles (MVT).
+//!
+//! Cf. <https://github.com/mapbox/vector-tile-spec/tree/master/2.1>.
+
+use crate::args::MvtArgs;
+use crate::filter::Filter;
+use crate::geo::{ring_contains, way_coords, BBox, Coord, MultiPolygon};
+use crate::Error;
+
+use osmflat::{
+    find_tag, iter_tags, tile_at, tile_bounds, BBoxMatch, FileResourceStorage, Osm,
+    MAX_TILE_LATITUDE, MAX_TILE_ZOOM,
+};
+
+use std::collections::{BTreeMap, HashMap};
+use std::f64::consts::PI;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::str::FromStr;
+
+/// Extent of a tile in tile coordinates.
+const EXTENT: u32 = 4096;
+
+/// Buffer around a tile in tile coordinates, in which geometries are kept to
+/// avoid rendering artifacts at the tile borders.
+const BUFFER: u32 = 64;
+
+/// Keys of closed ways which are lines unless tagged `area=yes`.
+const LINEAR_KEYS: [&[u8]; 4] = [b"highway", b"railway", b"waterway", b"barrier"];
+
+/// Layer of the rendered tiles given on the command line.
+///
+/// The format is `NAME:FILTER[:KEYS[:MIN_ZOOM]]`, e.g.
+/// `roads:highway:name,highway,ref:10`: the entities matching the tag filter
+/// are added to the layer `NAME` from zoom level `MIN_ZOOM` on (default 0),
+/// with the tags of the comma separated `KEYS` as properties (default all
+/// tags).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayerSpec {
+    name: String,
+    filter: Filter,
+    keys: Option<Vec<String>>,
+    min_zoom: u8,
+}
+
+impl FromStr for LayerSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid layer '{s}', expected NAME:FILTER[:KEYS[:MIN_ZOOM]]");
+        let mut parts = s.split(':');
+        let name = parts.next().filter(|name| !name.is_empty());
+        let filter = parts.next().ok_or_else(invalid)?.parse()?;
+        let keys = parts
+            .next()
+            .filter(|keys| !keys.is_empty())
+            .map(|keys| keys.split(',').map(String::from).collect());
+        let min_zoom = match parts.next() {
+            Some(zoom) => zoom.parse().map_err(|_| invalid())?,
+            None => 0,
+        };
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            name: name.ok_or_else(invalid)?.to_string(),
+            filter,
+            keys,
+            min_zoom,
+        })
+    }
+}
+
+impl LayerSpec {
+    /// Layers rendered if none are given on the command line.
+    pub fn defaults() -> Vec<Self> {
+        [
+            "roads:highway:highway,name,ref",
+            "water:waterway:waterway,name",
+            "buildings:building:building,name",
+            "pois:amenity:amenity,name",
+        ]
+        .iter()
+        .map(|s| s.parse().expect("invalid default layer"))
+        .collect()
+    }
+
+    fn properties(&self, archive: &Osm, tags: Range<u64>) -> Vec<(String, String)> {
+        iter_tags(archive, tags)
+            .filter(|(key, _)| match &self.keys {
+                Some(keys) => keys.iter().any(|k| k.as_bytes() == *key),
+                None => true,
+            })
+            .map(|(key, value)| {
+                (
+                    String::from_utf8_lossy(key).into_owned(),
+                    String::from_utf8_lossy(value).into_owned(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Geometry of a feature in `(lon, lat)` coordinates.
+#[derive(Debug, Clone, PartialEq)]
+enum Geometry {
+    Point(Coord),
+    Line(Vec<Coord>),
+    /// Polygons given by their exterior ring followed by their interior rings
+    Polygons(Vec<Vec<Vec<Coord>>>),
+}
+
+impl Geometry {
+    fn bbox(&self) -> BBox {
+        let coords: Box<dyn Iterator<Item = &Coord>> = match self {
+            Geometry::Point(coord) => Box::new(std::iter::once(coord)),
+            Geometry::Line(line) => Box::new(line.iter()),
+            Geometry::Polygons(polygons) => Box::new(polygons.iter().flatten().flatten()),
+        };
+        coords.fold(
+            ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN)),
+            |(min, max), &(lon, lat)| {
+                (
+                    (min.0.min(lon), min.1.min(lat)),
+                    (max.0.max(lon), max.1.max(lat)),
+                )
+            },
+        )
+    }
+}
+
+/// Entity added to a layer.
+#[derive(Debug, Clone, PartialEq)]
+struct Feature {
+    layer: usize,
+    min_zoom: u8,
+    geometry: Geometry,
+    properties: Vec<(String, String)>,
+}
+
+/// Returns whether a closed way is an area.
+fn is_area(archive: &Osm, tags: Range<u64>) -> bool {
+    match find_tag(archive, tags.clone(), b"area") {
+        Some(b"yes") => true,
+        Some(b"no") => false,
+        _ => !LINEAR_KEYS
+            .iter()
+            .any(|key| find_tag(archive, tags.clone(), key).is_some()),
+    }
+}
+
+/// Collects the entities matching the layers as features.
+///
+/// Tagged nodes become points, closed ways areas (cf. [`is_area`]) and other
+/// ways lines. Relations are only added if they are multipolygons. An entity
+/// is added to every layer it matches. If `bbox` is given, only entities
+/// intersecting it are collected.
+fn collect_features(archive: &Osm, layers: &[LayerSpec], bbox: Option<BBox>) -> Vec<Feature> {
+    let candidates = |len: usize, in_bbox: Option<Vec<u64>>| -> Vec<u64> {
+        let mut indexes = in_bbox.unwrap_or_else(|| (0..len as u64).collect());
+        indexes.sort_unstable();
+        indexes
+    };
+    let bbox =
+        bbox.map(|((min_lon, min_lat), (max_lon, max_lat))| (min_lon, min_lat, max_lon, max_lat));
+
+    let mut features = Vec::new();
+    let mut add = |tags: Range<u64>, geometry: &dyn Fn() -> Option<Geometry>| {
+        let mut geometry_cache = None;
+        for (layer, spec) in layers.iter().enumerate() {
+            if spec.filter.matches(archive, tags.clone()).is_none() {
+                continue;
+            }
+            if geometry_cache.is_none() {
+                geometry_cache = Some(geometry());
+            }
+            if let Some(Some(geometry)) = &geometry_cache {
+                features.push(Feature {
+                    layer,
+                    min_zoom: spec.min_zoom,
+                    geometry: geometry.clone(),
+                    properties: spec.properties(archive, tags.clone()),
+                });
+            }
+        }
+    };
+
+    let nodes = archive.nodes();
+    let nodes_in_bbox = bbox.map(|b| archive.nodes_in_bbox(b.0, b.1, b.2, b.3).collect());
+    for idx in candidates(nodes.len(), nodes_in_bbox) {
+        let tags = nodes[idx as usize].tags();
+        if !tags.is_empty() {
+            add(tags, &|| {
+                Some(Geometry::Point(archive.node_coord(idx as usize)))
+            });
+        }
+    }
+
+    let ways = archive.ways();
+    let ways_in_bbox = bbox.map(|b| {
+        archive
+            .ways_in_bbox(b.0, b.1, b.2, b.3, BBoxMatch::Geometry)
+            .collect()
+    });
+    for idx in candidates(ways.len(), ways_in_bbox) {
+        let way = &ways[idx as usize];
+        add(way.tags(), &|| {
+            let coords = way_coords(archive, way).filter(|c| c.len() > 1)?;
+            let closed = coords.len() >= 4 && coords.first() == coords.last();
+            Some(if closed && is_area(archive, way.tags()) {
+                Geometry::Polygons(vec![vec![coords]])
+            } else {
+                Geometry::Line(coords)
+            })
+        });
+    }
+
+    let relations = archive.relations();
+    let relations_in_bbox = bbox.map(|b| {
+        archive
+            .relations_in_bbox(b.0, b.1, b.2, b.3, BBoxMatch::Geometry)
+            .collect()
+    });
+    for idx in candidates(relations.len(), relations_in_bbox) {
+        let tags = relations[idx as usize].tags();
+        if find_tag(archive, tags.clone(), b"type") != Some(b"multipolygon") {
+            continue;
+        }
+        add(tags, &|| {
+            let multipolygon = MultiPolygon::from_relation(archive, idx as usize)?;
+            let mut polygons: Vec<Vec<Vec<Coord>>> = multipolygon
+                .outer
+                .into_iter()
+                .map(|ring| vec![ring])
+                .collect();
+            for inner in multipolygon.inner {
+                // an interior ring belongs to the first exterior ring containing it
+                if let Some(polygon) = polygons
+                    .iter_mut()
+                    .find(|polygon| ring_contains(&polygon[0], inner[0]))
+                {
+                    polygon.push(inner);
+                }
+            }
+            Some(Geometry::Polygons(polygons))
+        });
+    }
+
+    features
+}
+
+/// Projects a coordinate to Web Mercator at zoom level `z`, in units of tiles.
+fn project(z: u8, (lon, lat): Coord) -> (f64, f64) {
+    let n = f64::from(1u32 << z);
+    let lat = lat
+        .clamp(-MAX_TILE_LATITUDE, MAX_TILE_LATITUDE)
+        .to_radians();
+    let x = (lon + 180.0) / 360.0 * n;
+    let y = (1.0 - lat.tan().asinh() / PI) / 2.0 * n;
+    (x, y)
+}
+
+/// Point in tile coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Point {
+    x: f64,
+    y: f64,
+}
+
+/// Transforms coordinates into the coordinates of the tile `z/x/y`.
+#[derive(Debug, Clone, Copy)]
+struct TileTransform {
+    z: u8,
+    x: u32,
+    y: u32,
+}
+
+impl TileTransform {
+    fn project(&self, coord: Coord) -> Point {
+        let (x, y) = project(self.z, coord);
+        Point {
+            x: (x - f64::from(self.x)) * f64::from(EXTENT),
+            y: (y - f64::from(self.y)) * f64::from(EXTENT),
+        }
+    }
+}
+
+/// Axis aligned rectangle used for clipping.
+#[derive(Debug, Clone, Copy)]
+struct Rect {
+    min: f64,
+    max: f64,
+}
+
+impl Rect {
+    fn contains(&self, p: Point) -> bool {
+        self.min <= p.x && p.x <= self.max && self.min <= p.y && p.y <= self.max
+    }
+}
+
+/// Clips a line segment with the Liang-Barsky algorithm.
+fn clip_segment(rect: Rect, a: Point, b: Point) -> Option<(Point, Point)> {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let mut t0: f64 = 0.0;
+    let mut t1: f64 = 1.0;
+    for (p, q) in [
+        (-dx, a.x - rect.min),
+        (dx, rect.max - a.x),
+        (-dy, a.y - rect.min),
+        (dy, rect.max - a.y),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else {
+            let r = q / p;
+            if p < 0.0 {
+                t0 = t0.max(r);
+            } else {
+                t1 = t1.min(r);
+            }
+        }
+    }
+    if t0 > t1 {
+        return None;
+    }
+    let at = |t: f64| Point {
+        x: a.x + t * dx,
+        y: a.y + t * dy,
+    };
+    Some((at(t0), at(t1)))
+}
+
+/// Clips a line string and returns the parts inside of the rectangle.
+fn clip_line(rect: Rect, line: &[Point]) -> Vec<Vec<Point>> {
+    let mut parts: Vec<Vec<Point>> = Vec::new();
+    let mut current: Vec<Point> = Vec::new();
+    for w in line.windows(2) {
+        match clip_segment(rect, w[0], w[1]) {
+            Some((a, b)) => {
+                if current.last() != Some(&a) {
+                    if current.len() > 1 {
+                        parts.push(std::mem::take(&mut current));
+                    }
+                    current.clear();
+                    current.push(a);
+                }
+                current.push(b);
+            }
+            None => {
+                if current.len() > 1 {
+                    parts.push(std::mem::take(&mut current));
+                }
+                current.clear();
+            }
+        }
+    }
+    if current.len() > 1 {
+        parts.push(current);
+    }
+    parts
+}
+
+/// Clips a closed ring with the Sutherland-Hodgman algorithm.
+fn clip_ring(rect: Rect, ring: &[Point]) -> Vec<Point> {
+    type Inside = fn(&Rect, Point) -> bool;
+    type Intersect = fn(&Rect, Point, Point) -> Point;
+    let edges: [(Inside, Intersect); 4] = [
+        (|r, p| p.x >= r.min, |r, a, b| lerp_x(a, b, r.min)),
+        (|r, p| p.x <= r.max, |r, a, b| lerp_x(a, b, r.max)),
+        (|r, p| p.y >= r.min, |r, a, b| lerp_y(a, b, r.min)),
+        (|r, p| p.y <= r.max, |r, a, b| lerp_y(a, b, r.max)),
+    ];
+
+    let mut output: Vec<Point> = ring.to_vec();
+    for (inside, intersect) in edges {
+        let input = std::mem::take(&mut output);
+        let mut prev = match input.last() {
+            Some(p) => *p,
+            None => break,
+        };
+        for p in input {
+            if inside(&rect, p) {
+                if !inside(&rect, prev) {
+                    output.push(intersect(&rect, prev, p));
+                }
+                output.push(p);
+            } else if inside(&rect, prev) {
+                output.push(intersect(&rect, prev, p));
+            }
+            prev = p;
+        }
+    }
+    if let (Some(first), Some(last)) = (output.first(), output.last()) {
+        if first != last {
+            output.push(*first);
+        }
+    }
+    output
+}
+
+fn lerp_x(a: Point, b: Point, x: f64) -> Point {
+    let t = (x - a.x) / (b.x - a.x);
+    Point {
+        x,
+        y: a.y + t * (b.y - a.y),
+    }
+}
+
+fn lerp_y(a: Point, b: Point, y: f64) -> Point {
+    let t = (y - a.y) / (b.y - a.y);
+    Point {
+        x: a.x + t * (b.x - a.x),
+        y,
+    }
+}
+
+/// Simplifies a line string with the Douglas-Peucker algorithm.
+fn simplify(points: &[Point], epsilon: f64) -> Vec<Point> {
+    fn distance(p: Point, a: Point, b: Point) -> f64 {
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let len = (dx * dx + dy * dy).sqrt();
+        if len == 0.0 {
+            ((p.x - a.x).powi(2) + (p.y - a.y).powi(2)).sqrt()
+        } else {
+            (dy * p.x - dx * p.y + b.x * a.y - b.y * a.x).abs() / len
+        }
+    }
+
+    fn recurse(points: &[Point], epsilon: f64, result: &mut Vec<Point>) {
+        let (first, last) = (points[0], points[points.len() - 1]);
+        let farthest = points[1..points.len() - 1]
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (i + 1, distance(*p, first, last)))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        match farthest {
+            Some((idx, dist)) if dist > epsilon => {
+                recurse(&points[..=idx], epsilon, result);
+                recurse(&points[idx..], epsilon, result);
+            }
+            _ => result.push(last),
+        }
+    }
+
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+    let mut result = vec![points[0]];
+    recurse(points, epsilon, &mut result);
+    result
+}
+
+/// Converts points to integer tile coordinates and removes repeated points.
+fn quantize(points: &[Point]) -> Vec<(i32, i32)> {
+    let mut result: Vec<(i32, i32)> = points
+        .iter()
+        .map(|p| (p.x.round() as i32, p.y.round() as i32))
+        .collect();
+    result.dedup();
+    result
+}
+
+/// Twice the signed area of a ring in integer tile coordinates, positive when
+/// clockwise on screen, i.e. with y pointing down.
+fn signed_area(ring: &[(i32, i32)]) -> i64 {
+    ring.windows(2)
+        .map(|w| i64::from(w[0].0) * i64::from(w[1].1) - i64::from(w[1].0) * i64::from(w[0].1))
+        .sum()
+}
+
+/// Minimal protobuf writer sufficient for encoding vector tiles.
+#[derive(Default)]
+struct ProtoWriter {
+    buf: Vec<u8>,
+}
+
+impl ProtoWriter {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buf.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.buf.push(value as u8);
+    }
+
+    fn key(&mut self, field: u32, wire_type: u32) {
+        self.varint(u64::from(field << 3 | wire_type));
+    }
+
+    fn uint(&mut self, field: u32, value: u64) {
+        self.key(field, 0);
+        self.varint(value);
+    }
+
+    fn bytes(&mut self, field: u32, value: &[u8]) {
+        self.key(field, 2);
+        self.varint(value.len() as u64);
+        self.buf.extend_from_slice(value);
+    }
+
+    fn packed(&mut self, field: u32, values: &[u32]) {
+        let mut inner = ProtoWriter::default();
+        for v in values {
+            inner.varint(u64::from(*v));
+        }
+        self.bytes(field, &inner.buf);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GeomType {
+    Point = 1,
+    LineString = 2,
+    Polygon = 3,
+}
+
+/// Encodes geometry commands of a feature.
+#[derive(Default)]
+struct GeometryEncoder {
+    commands: Vec<u32>,
+    cursor: (i32, i32),
+}
+
+impl GeometryEncoder {
+    fn command(id: u32, count: usize) -> u32 {
+        (id & 0x7) | ((count as u32) << 3)
+    }
+
+    fn zigzag(x: i32) -> u32 {
+        ((x << 1) ^ (x >> 31)) as u32
+    }
+
+    fn push_point(&mut self, p: (i32, i32)) {
+        self.commands.push(Self::zigzag(p.0 - self.cursor.0));
+        self.commands.push(Self::zigzag(p.1 - self.cursor.1));
+        self.cursor = p;
+    }
+
+    fn point(&mut self, p: (i32, i32)) {
+        self.commands.push(Self::command(1, 1));
+        self.push_point(p);
+    }
+
+    /// Adds a path; closed paths are terminated with a `ClosePath` command.
+    fn path(&mut self, points: &[(i32, i32)], closed: bool) {
+        let points = if closed {
+            &points[..points.len() - 1]
+        } else {
+            points
+        };
+        self.point(points[0]);
+        self.commands.push(Self::command(2, points.len() - 1));
+        for p in &points[1..] {
+            self.push_point(*p);
+        }
+        if closed {
+            self.commands.push(Self::command(7, 1));
+        }
+    }
+}
+
+/// Clips, simplifies and quantizes a ring. Returns `None` if the ring
+/// degenerates.
+fn tile_ring(
+    ring: &[Point],
+    rect: Rect,
+    tolerance: f64,
+    exterior: bool,
+) -> Option<Vec<(i32, i32)>> {
+    let mut ring = quantize(&simplify(&clip_ring(rect, ring), tolerance));
+    if ring.len() < 4 {
+        return None;
+    }
+    // exterior rings must be clockwise in tile coordinates, interior rings
+    // counterclockwise
+    match signed_area(&ring) {
+        0 => return None,
+        area if (area > 0) != exterior => ring.reverse(),
+        _ => (),
+    }
+    Some(ring)
+}
+
+/// Encodes the geometry of a feature in a tile. Returns `None` if nothing of
+/// the geometry is left in the tile.
+fn encode_geometry(
+    geometry: &Geometry,
+    t: &TileTransform,
+    tolerance: f64,
+) -> Option<(GeomType, Vec<u32>)> {
+    let rect = Rect {
+        min: -f64::from(BUFFER),
+        max: f64::from(EXTENT + BUFFER),
+    };
+    let project =
+        |coords: &[Coord]| -> Vec<Point> { coords.iter().map(|&c| t.project(c)).collect() };
+
+    let mut encoder = GeometryEncoder::default();
+    let geom_type = match geometry {
+        Geometry::Point(coord) => {
+            let p = t.project(*coord);
+            if !rect.contains(p) {
+                return None;
+            }
+            encoder.point((p.x.round() as i32, p.y.round() as i32));
+            GeomType::Point
+        }
+        Geometry::Line(line) => {
+            for part in clip_line(rect, &project(line)) {
+                let part = quantize(&simplify(&part, tolerance));
+                if part.len() >= 2 {
+                    encoder.path(&part, false);
+                }
+            }
+            GeomType::LineString
+        }
+        Geometry::Polygons(polygons) => {
+            for rings in polygons {
+                let exterior = match tile_ring(&project(&rings[0]), rect, tolerance, true) {
+                    Some(exterior) => exterior,
+                    None => continue,
+                };
+                encoder.path(&exterior, true);
+                for ring in &rings[1..] {
+                    if let Some(interior) = tile_ring(&project(ring), rect, tolerance, false) {
+                        encoder.path(&interior, true);
+                    }
+                }
+            }
+            GeomType::Polygon
+        }
+    };
+    if encoder.commands.is_empty() {
+        return None;
+    }
+    Some((geom_type, encoder.commands))
+}
+
+/// Encodes a layer of a tile with deduplicated keys and values.
+fn encode_layer(tile: &mut ProtoWriter, name: &str, features: &[(&Feature, GeomType, Vec<u32>)]) {
+    let mut keys: HashMap<&str, u32> = HashMap::new();
+    let mut values: HashMap<&str, u32> = HashMap::new();
+    let mut layer = ProtoWriter::default();
+    layer.uint(15, 2); // version
+    layer.bytes(1, name.as_bytes());
+    for (feature, geom_type, geometry) in features {
+        let mut tags = Vec::new();
+        for (k, v) in &feature.properties {
+            let next_key = keys.len() as u32;
+            tags.push(*keys.entry(k).or_insert(next_key));
+            let next_value = values.len() as u32;
+            tags.push(*values.entry(v).or_insert(next_value));
+        }
+        let mut f = ProtoWriter::default();
+        f.packed(2, &tags);
+        f.uint(3, *geom_type as u64);
+        f.packed(4, geometry);
+        layer.bytes(2, &f.buf);
+    }
+    let mut keys: Vec<_> = keys.into_iter().collect();
+    keys.sort_by_key(|(_, idx)| *idx);
+    for (key, _) in keys {
+        layer.bytes(3, key.as_bytes());
+    }
+    let mut values: Vec<_> = values.into_iter().collect();
+    values.sort_by_key(|(_, idx)| *idx);
+    for (value, _) in values {
+        let mut v = ProtoWriter::default();
+        v.bytes(1, value.as_bytes());
+        layer.bytes(4, &v.buf);
+    }
+    layer.uint(5, u64::from(EXTENT));
+    tile.bytes(3, &layer.buf);
+}
+
+/// Renders a tile from the features. Layers without features in the tile are
+/// omitted. Returns `None` if the tile is empty.
+fn render_tile(
+    layers: &[LayerSpec],
+    features: &[&Feature],
+    t: &TileTransform,
+    tolerance: f64,
+) -> Option<Vec<u8>> {
+    let mut tile = ProtoWriter::default();
+    for (layer, spec) in layers.iter().enumerate() {
+        let encoded: Vec<_> = features
+            .iter()
+            .filter(|feature| feature.layer == layer)
+            .filter_map(|&feature| {
+                let (geom_type, geometry) = encode_geometry(&feature.geometry, t, tolerance)?;
+                Some((feature, geom_type, geometry))
+            })
+            .collect();
+        if !encoded.is_empty() {
+            encode_layer(&mut tile, &spec.name, &encoded);
+        }
+    }
+    (!tile.buf.is_empty()).then_some(tile.buf)
+}
+
+/// Tiles to render: all tiles of the zoom levels, or the tiles intersecting a
+/// bounding box, or a single tile.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TileRange {
+    min_zoom: u8,
+    max_zoom: u8,
+    bbox: Option<BBox>,
+    tile: Option<(u32, u32)>,
+}
+
+impl TileRange {
+    fn tile(z: u8, x: u32, y: u32) -> Self {
+        // extend the bounds by the buffer for collecting the features
+        let ((min_lon, min_lat), (max_lon, max_lat)) = tile_bounds(z, x, y);
+        let buffer = f64::from(BUFFER) / f64::from(EXTENT);
+        let (dx, dy) = ((max_lon - min_lon) * buffer, (max_lat - min_lat) * buffer);
+        Self {
+            min_zoom: z,
+            max_zoom: z,
+            bbox: Some(((min_lon - dx, min_lat - dy), (max_lon + dx, max_lat + dy))),
+            tile: Some((x, y)),
+        }
+    }
+
+    /// Returns the range of tiles `(min_x, min_y, max_x, max_y)` at zoom level
+    /// `z`.
+    fn tiles(&self, z: u8) -> (u32, u32, u32, u32) {
+        match (self.tile, self.bbox) {
+            (Some((x, y)), _) => (x, y, x, y),
+            (None, Some(((min_lon, min_lat), (max_lon, max_lat)))) => {
+                let (min_x, min_y) = tile_at(z, min_lon, max_lat);
+                let (max_x, max_y) = tile_at(z, max_lon, min_lat);
+                (min_x, min_y, max_x, max_y)
+            }
+            (None, None) => (0, 0, (1 << z) - 1, (1 << z) - 1),
+        }
+    }
+}
+
+/// Renders the tiles of `range` containing features, and calls `f` with the
+/// coordinates `(z, x, y)` and the data of each tile.
+///
+/// Features are assigned to the tiles intersecting their bounding box
+/// including the tile buffer. Returns the number of rendered tiles.
+fn render_tiles(
+    features: &[Feature],
+    layers: &[LayerSpec],
+    range: &TileRange,
+    tolerance: f64,
+    mut f: impl FnMut((u8, u32, u32), Vec<u8>) -> io::Result<()>,
+) -> io::Result<usize> {
+    let bboxes: Vec<BBox> = features.iter().map(|f| f.geometry.bbox()).collect();
+    let mut num_tiles = 0;
+    for z in range.min_zoom..=range.max_zoom {
+        let (min_x, min_y, max_x, max_y) = range.tiles(z);
+        let buffer = f64::from(BUFFER) / f64::from(EXTENT);
+        let last = (1u32 << z) - 1;
+        let tile = |v: f64| (v.floor().max(0.0) as u32).min(last);
+
+        let mut tiles: BTreeMap<(u32, u32), Vec<&Feature>> = BTreeMap::new();
+        for (feature, (min, max)) in features.iter().zip(&bboxes) {
+            if feature.min_zoom > z {
+                continue;
+            }
+            // y grows to the south
+            let (x0, y0) = project(z, (min.0, max.1));
+            let (x1, y1) = project(z, (max.0, min.1));
+            let xs = tile(x0 - buffer).max(min_x)..=tile(x1 + buffer).min(max_x);
+            let ys = tile(y0 - buffer).max(min_y)..=tile(y1 + buffer).min(max_y);
+            for x in xs {
+                for y in ys.clone() {
+                    tiles.entry((x, y)).or_default().push(feature);
+                }
+            }
+        }
+
+        for ((x, y), features) in tiles {
+            let t = TileTransform { z, x, y };
+            if let Some(data) = render_tile(layers, &features, &t, tolerance) {
+                f((z, x, y), data)?;
+                num_tiles += 1;
+            }
+        }
+    }
+    Ok(num_tiles)
+}
+
+pub fn run(args: MvtArgs) -> Result<(), Error> {
+    let archive = Osm::open(FileResourceStorage::new(&args.input))?;
+    let layers = if args.layers.is_empty() {
+        LayerSpec::defaults()
+    } else {
+        args.layers
+    };
+
+    let range = match (args.tile, args.zoom) {
+        (Some((z, x, y)), _) => TileRange::tile(z, x, y),
+        (None, Some((min_zoom, max_zoom))) => TileRange {
+            min_zoom,
+            max_zoom,
+            bbox: args.bbox,
+            tile: None,
+        },
+        (None, None) => return Err("either --tile or --zoom is required".into()),
+    };
+    if range.max_zoom > MAX_TILE_ZOOM {
+        return Err(format!("zoom level is larger than {MAX_TILE_ZOOM}").into());
+    }
+
+    let features = collect_features(&archive, &layers, range.bbox);
+    let num_tiles = if args.tile.is_some() {
+        let mut data = Vec::new();
+        let num_tiles = render_tiles(&features, &layers, &range, args.tolerance, |_, tile| {
+            data = tile;
+            Ok(())
+        })?;
+        // an empty tile is still a valid tile
+        fs::write(&args.output, data)?;
+        num_tiles
+    } else {
+        render_tiles(
+            &features,
+            &layers,
+            &range,
+            args.tolerance,
+            |(z, x, y), data| {
+                let dir = args.output.join(z.to_string()).join(x.to_string());
+                fs::create_dir_all(&dir)?;
+                fs::write(dir.join(format!("{y}.mvt")), data)
+            },
+        )?
+    };
+    eprintln!(
+        "Rendered {} features into {num_tiles} non-empty tiles",
+        features.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_layer() {
+        let layer: LayerSpec = "roads:highway=primary:name,ref:8".parse().unwrap();
+        assert_eq!(
+            layer,
+            LayerSpec {
+                name: "roads".into(),
+                filter: "highway=primary".parse().unwrap(),
+                keys: Some(vec!["name".into(), "ref".into()]),
+                min_zoom: 8,
+            }
+        );
+        let layer: LayerSpec = "pois:amenity".parse().unwrap();
+        assert_eq!(layer.keys, None);
+        assert_eq!(layer.min_zoom, 0);
+        assert_eq!("pois:amenity::12".parse::<LayerSpec>().unwrap().keys, None);
+        assert!("pois".parse::<LayerSpec>().is_err());
+        assert!(":amenity".parse::<LayerSpec>().is_err());
+        assert!("pois:amenity:name:x".parse::<LayerSpec>().is_err());
+        assert!("pois:amenity:name:1:2".parse::<LayerSpec>().is_err());
+        assert_eq!(LayerSpec::defaults().len(), 4);
+    }
+
+    #[test]
+    fn test_encode_geometry() {
+        // examples of the vector tile specification
+        let mut encoder = GeometryEncoder::default();
+        encoder.point((25, 17));
+        assert_eq!(encoder.commands, [9, 50, 34]);
+
+        let mut encoder = GeometryEncoder::default();
+        encoder.path(&[(2, 2), (2, 10), (10, 10)], false);
+        assert_eq!(encoder.commands, [9, 4, 4, 18, 0, 16, 16, 0]);
+
+        let mut encoder = GeometryEncoder::default();
+        encoder.path(&[(3, 6), (8, 12), (20, 34), (3, 6)], true);
+        assert_eq!(encoder.commands, [9, 6, 12, 18, 10, 12, 24, 44, 15]);
+    }
+
+    #[test]
+    fn test_clip() {
+        let rect = Rect {
+            min: 0.0,
+            max: 10.0,
+        };
+        let p = |x, y| Point { x, y };
+        let parts = clip_line(rect, &[p(-5.0, 5.0), p(5.0, 5.0), p(5.0, 15.0)]);
+        assert_eq!(parts, [vec![p(0.0, 5.0), p(5.0, 5.0), p(5.0, 10.0)]]);
+
+        let square = [
+            p(5.0, 5.0),
+            p(15.0, 5.0),
+            p(15.0, 15.0),
+            p(5.0, 15.0),
+            p(5.0, 5.0),
+        ];
+        let ring = quantize(&clip_ring(rect, &square));
+        assert_eq!(ring.len(), 5);
+        assert!(ring
+            .iter()
+            .all(|&(x, y)| (5..=10).contains(&x) && (5..=10).contains(&y)));
+        assert_eq!(signed_area(&ring), 2 * 25);
+    }
+
+    #[test]
+    fn test_render_tiles() {
+        let archive = osmflat::TestArchive {
+            nodes: vec![
+                (13.40, 52.50, vec![("amenity", "cafe"), ("name", "Café")]),
+                (13.41, 52.50, vec![]),
+                (13.41, 52.51, vec![]),
+                (13.40, 52.51, vec![]),
+            ],
+            ways: vec![
+                (vec![0, 1, 2, 3, 0], vec![("building", "yes")]),
+                (vec![1, 2, 3, 1], vec![("highway", "service")]),
+            ],
+            relations: vec![],
+        }
+        .build();
+        let layers: Vec<LayerSpec> = [
+            "pois:amenity:name:14",
+            "buildings:building",
+            "roads:highway",
+        ]
+        .iter()
+        .map(|s| s.parse().unwrap())
+        .collect();
+        let features = collect_features(&archive, &layers, None);
+        assert_eq!(features.len(), 3);
+        assert_eq!(features[0].properties, [("name".into(), "Café".into())]);
+        assert!(matches!(features[1].geometry, Geometry::Polygons(_)));
+        assert!(matches!(features[2].geometry, Geometry::Line(_)));
+
+        let range = TileRange {
+            min_zoom: 12,
+            max_zoom: 14,
+            bbox: None,
+            tile: None,
+        };
+        let mut tiles = BTreeMap::new();
+        let num_tiles = render_tiles(&features, &layers, &range, 1.0, |tile, data| {
+            tiles.insert(tile, data);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(num_tiles, tiles.len());
+        let zooms: Vec<u8> = tiles.keys().map(|&(z, _, _)| z).collect();
+        assert_eq!(zooms.iter().filter(|&&z| z == 12).count(), 1);
+        let contains = |data: &[u8], s: &str| data.windows(s.len()).any(|w| w == s.as_bytes());
+        for (&(z, _, _), data) in &tiles {
+            assert!(contains(data, "buildings"));
+            assert!(contains(data, "roads"));
+            assert!(z == 14 || !contains(data, "pois"));
+        }
+        let (x, y) = tile_at(14, 13.40, 52.50);
+        assert!(contains(&tiles[&(14, x, y)], "pois"));
+
+        let range = TileRange::tile(14, x, y);
+        let features = collect_features(&archive, &layers, range.bbox);
+        assert_eq!(features.len(), 3);
+        let mut num_tiles = 0;
+        render_tiles(&features, &layers, &range, 1.0, |tile, _| {
+            assert_eq!(tile, (14, x, y));
+            num_tiles += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(num_tiles, 1);
+    }
+}
@@ -0,0 +1,119 @@
+use crate::args::OrphansArgs;
+use crate::id::{IdLookup, OsmId};
+use crate::Error;
+
+use osmflat::{FileResourceStorage, Osm, RelationMembersRef};
+
+/// Data health report of an archive.
+#[derive(Debug, Default)]
+struct Report {
+    /// Nodes referenced by no way and no relation
+    orphan_nodes: Vec<u64>,
+    /// Number of orphan nodes without tags
+    untagged_orphan_nodes: usize,
+    /// Ways with at least one unresolved node reference
+    ways_with_unresolved_nodes: Vec<u64>,
+    /// Relations with at least one unresolved member
+    relations_with_unresolved_members: Vec<u64>,
+}
+
+impl Report {
+    fn new(archive: &Osm) -> Self {
+        let nodes = archive.nodes();
+        let nodes_index = archive.nodes_index();
+        let mut report = Report::default();
+
+        let mut referenced = vec![false; nodes.len()];
+        for (way_idx, way) in archive.ways().iter().enumerate() {
+            let mut unresolved = false;
+            for idx in way.refs() {
+                match nodes_index[idx as usize].value() {
+                    Some(node_idx) => referenced[node_idx as usize] = true,
+                    None => unresolved = true,
+                }
+            }
+            if unresolved {
+                report.ways_with_unresolved_nodes.push(way_idx as u64);
+            }
+        }
+
+        for relation_idx in 0..archive.relations().len() {
+            let mut unresolved = false;
+            for member in archive.relation_members().at(relation_idx) {
+                let idx = match member {
+                    RelationMembersRef::NodeMember(m) => {
+                        if let Some(node_idx) = m.node_idx() {
+                            referenced[node_idx as usize] = true;
+                        }
+                        m.node_idx()
+                    }
+                    RelationMembersRef::WayMember(m) => m.way_idx(),
+                    RelationMembersRef::RelationMember(m) => m.relation_idx(),
+                };
+                unresolved |= idx.is_none();
+            }
+            if unresolved {
+                report
+                    .relations_with_unresolved_members
+                    .push(relation_idx as u64);
+            }
+        }
+
+        for (idx, node) in nodes.iter().enumerate() {
+            if !referenced[idx] {
+                report.orphan_nodes.push(idx as u64);
+                if node.tags().is_empty() {
+                    report.untagged_orphan_nodes += 1;
+                }
+            }
+        }
+        report
+    }
+}
+
+/// Prints entities by their ids, or by their indexes if there are no ids.
+fn print_list<'a>(
+    indexes: &[u64],
+    ids: Option<&IdLookup<'a>>,
+    to_id: fn(&IdLookup<'a>, u64) -> OsmId,
+) {
+    for &idx in indexes {
+        match ids {
+            Some(ids) => println!("  {}", to_id(ids, idx)),
+            None => println!("  #{idx}"),
+        }
+    }
+}
+
+pub fn run(args: OrphansArgs) -> Result<(), Error> {
+    let archive = Osm::open(FileResourceStorage::new(args.input))?;
+    let ids = IdLookup::new(&archive).ok();
+    let report = Report::new(&archive);
+
+    let list = |indexes: &[u64], to_id| {
+        if args.list {
+            print_list(indexes, ids.as_ref(), to_id);
+        }
+    };
+
+    println!(
+        "orphan nodes: {} (untagged: {})",
+        report.orphan_nodes.len(),
+        report.untagged_orphan_nodes
+    );
+    list(&report.orphan_nodes, IdLookup::node);
+    println!(
+        "ways with unresolved nodes: {}",
+        report.ways_with_unresolved_nodes.len()
+    );
+    list(&report.ways_with_unresolved_nodes, IdLookup::way);
+    println!(
+        "relations with unresolved members: {}",
+        report.relations_with_unresolved_members.len()
+    );
+    list(
+        &report.relations_with_unresolved_members,
+        IdLookup::relation,
+    );
+    Ok(())
+}
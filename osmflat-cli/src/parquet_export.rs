@@ -0,0 +1,515 @@
+//! Export of nodes, ways and relations as Parquet files.
+//!
+//! The geometries are written as WKB together with GeoParquet metadata, cf.
+//! <https://geoparquet.org/releases/v1.0.0/>, so that they are recognized by
+//! e.g. DuckDB and GDAL.
+
+use crate::args::{ParquetArgs, ParquetCompression};
+use crate::Error;
+
+use osmflat::geometry::{multipolygon, multipolygon_to_wkb, node_to_wkb, way_to_wkb};
+use osmflat::{find_tag, iter_tags, EntityType, FileResourceStorage, Osm, RelationMembersRef};
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::data_type::{ByteArray, ByteArrayType, DataType, Int64Type};
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::{SerializedFileWriter, SerializedRowGroupWriter};
+use parquet::format::KeyValue;
+use parquet::schema::parser::parse_message_type;
+
+use std::fs::{self, File};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Number of rows of a row group.
+const ROW_GROUP_SIZE: usize = 65_536;
+
+const NODES_SCHEMA: &str = "
+message nodes {
+    required int64 id;
+    required group tags (MAP) {
+        repeated group key_value {
+            required binary key (STRING);
+            required binary value (STRING);
+        }
+    }
+    required binary geometry;
+}";
+
+const WAYS_SCHEMA: &str = "
+message ways {
+    required int64 id;
+    required group tags (MAP) {
+        repeated group key_value {
+            required binary key (STRING);
+            required binary value (STRING);
+        }
+    }
+    required group nodes (LIST) {
+        repeated group list {
+            required int64 element;
+        }
+    }
+    optional binary geometry;
+}";
+
+const RELATIONS_SCHEMA: &str = "
+message relations {
+    required int64 id;
+    required group tags (MAP) {
+        repeated group key_value {
+            required binary key (STRING);
+            required binary value (STRING);
+        }
+    }
+    required group members (LIST) {
+        repeated group list {
+            required group element {
+                required binary type (STRING);
+                required int64 ref;
+                required binary role (STRING);
+            }
+        }
+    }
+    optional binary geometry;
+}";
+
+/// Values of a leaf column of a row group with their definition and
+/// repetition levels.
+///
+/// The levels of all columns of the schemas above are either 0 or 1: optional
+/// values are defined at level 1, and the entries of maps and lists are
+/// defined at level 1 and repeated at level 1 except for the first entry.
+#[derive(Debug)]
+struct Column<T> {
+    values: Vec<T>,
+    def_levels: Vec<i16>,
+    rep_levels: Vec<i16>,
+}
+
+impl<T> Default for Column<T> {
+    fn default() -> Self {
+        Self {
+            values: Vec::new(),
+            def_levels: Vec::new(),
+            rep_levels: Vec::new(),
+        }
+    }
+}
+
+impl<T> Column<T> {
+    fn push(&mut self, value: Option<T>, rep_level: i16) {
+        self.def_levels.push(i16::from(value.is_some()));
+        self.rep_levels.push(rep_level);
+        self.values.extend(value);
+    }
+
+    fn push_repeated(&mut self, values: impl IntoIterator<Item = T>) {
+        let len = self.def_levels.len();
+        for (i, value) in values.into_iter().enumerate() {
+            self.push(Some(value), i16::from(i > 0));
+        }
+        if self.def_levels.len() == len {
+            self.push(None, 0);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.values.clear();
+        self.def_levels.clear();
+        self.rep_levels.clear();
+    }
+}
+
+#[derive(Debug)]
+enum ColumnValues {
+    Int64(Column<i64>),
+    Bytes(Column<ByteArray>),
+}
+
+impl ColumnValues {
+    fn int64(&mut self) -> &mut Column<i64> {
+        match self {
+            ColumnValues::Int64(column) => column,
+            ColumnValues::Bytes(_) => panic!("expected int64 column"),
+        }
+    }
+
+    fn bytes(&mut self) -> &mut Column<ByteArray> {
+        match self {
+            ColumnValues::Bytes(column) => column,
+            ColumnValues::Int64(_) => panic!("expected binary column"),
+        }
+    }
+}
+
+fn write_column<T: DataType>(
+    row_group: &mut SerializedRowGroupWriter<File>,
+    column: &mut Column<T::T>,
+) -> Result<(), ParquetError> {
+    let mut writer = row_group.next_column()?.expect("missing column");
+    let typed = writer.typed::<T>();
+    let descr = typed.get_descriptor().clone();
+    let def_levels = (descr.max_def_level() > 0).then_some(&column.def_levels[..]);
+    let rep_levels = (descr.max_rep_level() > 0).then_some(&column.rep_levels[..]);
+    typed.write_batch(&column.values, def_levels, rep_levels)?;
+    writer.close()?;
+    column.clear();
+    Ok(())
+}
+
+/// Parquet file written in row groups of [`ROW_GROUP_SIZE`] rows.
+struct Table {
+    writer: SerializedFileWriter<File>,
+    columns: Vec<ColumnValues>,
+    num_rows: usize,
+    /// Number of rows in the columns not written yet
+    buffered_rows: usize,
+}
+
+impl Table {
+    fn create(path: &Path, schema: &str, compression: Compression) -> Result<Self, ParquetError> {
+        let schema = Arc::new(parse_message_type(schema)?);
+        let columns = schema
+            .get_fields()
+            .iter()
+            .flat_map(|field| leaves(field))
+            .collect();
+        let geo = r#"{"version":"1.0.0","primary_column":"geometry","columns":{"geometry":{"encoding":"WKB","geometry_types":[]}}}"#;
+        let properties = WriterProperties::builder()
+            .set_compression(compression)
+            .set_created_by("osmflat".into())
+            .set_key_value_metadata(Some(vec![KeyValue::new("geo".into(), geo.to_string())]))
+            .build();
+        let writer = SerializedFileWriter::new(File::create(path)?, schema, Arc::new(properties))?;
+        Ok(Self {
+            writer,
+            columns,
+            num_rows: 0,
+            buffered_rows: 0,
+        })
+    }
+
+    /// Appends a row whose values are pushed by `f` to the columns in the
+    /// order of the schema.
+    fn push_row(&mut self, f: impl FnOnce(&mut Row)) -> Result<(), ParquetError> {
+        f(&mut Row {
+            columns: self.columns.iter_mut(),
+        });
+        self.num_rows += 1;
+        self.buffered_rows += 1;
+        if self.buffered_rows == ROW_GROUP_SIZE {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), ParquetError> {
+        let mut row_group = self.writer.next_row_group()?;
+        for column in &mut self.columns {
+            match column {
+                ColumnValues::Int64(column) => write_column::<Int64Type>(&mut row_group, column)?,
+                ColumnValues::Bytes(column) => {
+                    write_column::<ByteArrayType>(&mut row_group, column)?
+                }
+            }
+        }
+        row_group.close()?;
+        self.buffered_rows = 0;
+        Ok(())
+    }
+
+    /// Writes the remaining rows and the footer, and returns the number of
+    /// rows.
+    fn close(mut self) -> Result<usize, ParquetError> {
+        if self.buffered_rows > 0 {
+            self.flush()?;
+        }
+        self.writer.close()?;
+        Ok(self.num_rows)
+    }
+}
+
+/// Returns empty columns of the leaves of a schema field.
+fn leaves(field: &parquet::schema::types::Type) -> Vec<ColumnValues> {
+    use parquet::basic::Type as PhysicalType;
+    if field.is_group() {
+        return field.get_fields().iter().flat_map(|f| leaves(f)).collect();
+    }
+    match field.get_physical_type() {
+        PhysicalType::INT64 => vec![ColumnValues::Int64(Column::default())],
+        _ => vec![ColumnValues::Bytes(Column::default())],
+    }
+}
+
+/// Values of a row pushed to the columns of a table in the order of its
+/// schema.
+struct Row<'a> {
+    columns: std::slice::IterMut<'a, ColumnValues>,
+}
+
+impl Row<'_> {
+    fn next(&mut self) -> &mut ColumnValues {
+        self.columns.next().expect("more values than columns")
+    }
+
+    fn id(&mut self, id: i64) {
+        self.next().int64().push(Some(id), 0);
+    }
+
+    fn tags(&mut self, archive: &Osm, tags: Range<u64>) {
+        let bytes = |s: &[u8]| ByteArray::from(s.to_vec());
+        let tags: Vec<_> = iter_tags(archive, tags).collect();
+        self.next()
+            .bytes()
+            .push_repeated(tags.iter().map(|(k, _)| bytes(k)));
+        self.next()
+            .bytes()
+            .push_repeated(tags.iter().map(|(_, v)| bytes(v)));
+    }
+
+    fn list(&mut self, ids: Vec<i64>) {
+        self.next().int64().push_repeated(ids);
+    }
+
+    fn members(&mut self, members: Vec<(&str, i64, &[u8])>) {
+        self.next()
+            .bytes()
+            .push_repeated(members.iter().map(|m| ByteArray::from(m.0)));
+        self.next()
+            .int64()
+            .push_repeated(members.iter().map(|m| m.1));
+        self.next()
+            .bytes()
+            .push_repeated(members.iter().map(|m| ByteArray::from(m.2.to_vec())));
+    }
+
+    fn geometry(&mut self, wkb: Option<Vec<u8>>) {
+        self.next().bytes().push(wkb.map(ByteArray::from), 0);
+    }
+}
+
+/// OSM ids of the entities of an archive, or their indexes if the archive
+/// does not contain ids.
+struct Ids<'a>(Option<&'a osmflat::Ids>);
+
+impl Ids<'_> {
+    fn get(&self, entity_type: EntityType, idx: u64) -> i64 {
+        match &self.0 {
+            Some(ids) => match entity_type {
+                EntityType::Node => ids.nodes()[idx as usize].signed_value(),
+                EntityType::Way => ids.ways()[idx as usize].signed_value(),
+                EntityType::Relation => ids.relations()[idx as usize].signed_value(),
+            },
+            None => idx as i64,
+        }
+    }
+}
+
+/// Writes the entities of `archive` whose tags match `matches` into the files
+/// `nodes.parquet`, `ways.parquet` and `relations.parquet` in `dir`.
+///
+/// Nodes have a point geometry, ways a line string, unless one of their nodes
+/// is unresolved, and multipolygon and boundary relations a multipolygon.
+/// Returns the numbers of written nodes, ways and relations.
+fn write_parquet(
+    archive: &Osm,
+    dir: &Path,
+    compression: Compression,
+    matches: impl Fn(Range<u64>) -> bool,
+) -> Result<[usize; 3], Error> {
+    let ids = Ids(archive.ids());
+
+    let mut table = Table::create(&dir.join("nodes.parquet"), NODES_SCHEMA, compression)?;
+    for (idx, node) in archive.nodes().iter().enumerate() {
+        if !matches(node.tags()) {
+            continue;
+        }
+        table.push_row(|row| {
+            row.id(ids.get(EntityType::Node, idx as u64));
+            row.tags(archive, node.tags());
+            row.geometry(Some(node_to_wkb(archive, idx, None)));
+        })?;
+    }
+    let num_nodes = table.close()?;
+
+    let nodes_index = archive.nodes_index();
+    let mut table = Table::create(&dir.join("ways.parquet"), WAYS_SCHEMA, compression)?;
+    for (idx, way) in archive.ways().iter().enumerate() {
+        if !matches(way.tags()) {
+            continue;
+        }
+        table.push_row(|row| {
+            row.id(ids.get(EntityType::Way, idx as u64));
+            row.tags(archive, way.tags());
+            row.list(
+                way.refs()
+                    .filter_map(|i| nodes_index[i as usize].value())
+                    .map(|node_idx| ids.get(EntityType::Node, node_idx))
+                    .collect(),
+            );
+            row.geometry(way_to_wkb(archive, way, None));
+        })?;
+    }
+    let num_ways = table.close()?;
+
+    let strings = archive.stringtable();
+    let mut table = Table::create(
+        &dir.join("relations.parquet"),
+        RELATIONS_SCHEMA,
+        compression,
+    )?;
+    for (idx, relation) in archive.relations().iter().enumerate() {
+        if !matches(relation.tags()) {
+            continue;
+        }
+        let members = archive
+            .relation_members()
+            .at(idx)
+            .filter_map(|member| {
+                let (member_type, id, role_idx) = match member {
+                    RelationMembersRef::NodeMember(m) => (
+                        "node",
+                        m.node_idx().map(|i| ids.get(EntityType::Node, i)),
+                        m.role_idx(),
+                    ),
+                    RelationMembersRef::WayMember(m) => (
+                        "way",
+                        m.way_idx().map(|i| ids.get(EntityType::Way, i)),
+                        m.role_idx(),
+                    ),
+                    RelationMembersRef::RelationMember(m) => (
+                        "relation",
+                        m.relation_idx().map(|i| ids.get(EntityType::Relation, i)),
+                        m.role_idx(),
+                    ),
+                };
+                Some((member_type, id?, strings.substring_raw(role_idx as usize)))
+            })
+            .collect();
+        let area_type = find_tag(archive, relation.tags(), b"type");
+        let geometry = match area_type {
+            Some(b"multipolygon" | b"boundary") => multipolygon(archive, idx)
+                .ok()
+                .map(|polygons| multipolygon_to_wkb(&polygons, None)),
+            _ => None,
+        };
+        table.push_row(|row| {
+            row.id(ids.get(EntityType::Relation, idx as u64));
+            row.tags(archive, relation.tags());
+            row.members(members);
+            row.geometry(geometry);
+        })?;
+    }
+    let num_relations = table.close()?;
+
+    Ok([num_nodes, num_ways, num_relations])
+}
+
+pub fn run(args: ParquetArgs) -> Result<(), Error> {
+    let archive = Osm::open(FileResourceStorage::new(&args.input))?;
+    let compression = match args.compression {
+        ParquetCompression::None => Compression::UNCOMPRESSED,
+        ParquetCompression::Snappy => Compression::SNAPPY,
+        ParquetCompression::Zstd => Compression::ZSTD(ZstdLevel::default()),
+    };
+    fs::create_dir_all(&args.output)?;
+    let [nodes, ways, relations] = write_parquet(&archive, &args.output, compression, |tags| {
+        match &args.filter {
+            Some(filter) => filter.matches(&archive, tags).is_some(),
+            None => true,
+        }
+    })?;
+    eprintln!("Exported {nodes} nodes, {ways} ways and {relations} relations");
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use osmflat::Member;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::Field;
+
+    #[test]
+    fn test_push_repeated() {
+        let mut column = Column::default();
+        column.push_repeated([1, 2]);
+        column.push_repeated([]);
+        column.push_repeated([3]);
+        assert_eq!(column.values, [1, 2, 3]);
+        assert_eq!(column.def_levels, [1, 1, 0, 1]);
+        assert_eq!(column.rep_levels, [0, 1, 0, 0]);
+    }
+
+    #[test]
+    fn test_write_parquet() {
+        let archive = osmflat::TestArchive {
+            nodes: vec![
+                (0.0, 0.0, vec![("amenity", "cafe"), ("name", "A")]),
+                (1.0, 0.0, vec![]),
+                (1.0, 1.0, vec![]),
+            ],
+            ways: vec![(vec![0, 1, 2, 0], vec![("building", "yes")])],
+            relations: vec![(
+                vec![Member::Way(0, "outer")],
+                vec![("type", "multipolygon")],
+            )],
+        }
+        .build();
+        let dir = tempfile::tempdir().unwrap();
+        let counts = write_parquet(&archive, dir.path(), Compression::SNAPPY, |_| true).unwrap();
+        assert_eq!(counts, [3, 1, 1]);
+
+        let rows = |name: &str| {
+            let file = File::open(dir.path().join(name)).unwrap();
+            let reader = SerializedFileReader::new(file).unwrap();
+            let key_value_metadata = reader.metadata().file_metadata().key_value_metadata();
+            assert_eq!(key_value_metadata.unwrap()[0].key, "geo");
+            reader
+                .get_row_iter(None)
+                .unwrap()
+                .map(|row| row.unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let nodes = rows("nodes.parquet");
+        assert_eq!(nodes.len(), 3);
+        let node = nodes[0].get_column_iter().collect::<Vec<_>>();
+        assert_eq!(node[0], (&"id".to_string(), &Field::Long(0)));
+        assert_eq!(
+            node[1].1.to_string(),
+            r#"{"amenity" -> "cafe", "name" -> "A"}"#
+        );
+        match node[2].1 {
+            Field::Bytes(ref wkb) => assert_eq!(wkb.len(), 21),
+            ref field => panic!("unexpected geometry {field}"),
+        }
+        assert_eq!(
+            nodes[1].get_column_iter().nth(1).unwrap().1.to_string(),
+            "{}"
+        );
+
+        let ways = rows("ways.parquet");
+        assert_eq!(
+            ways[0].get_column_iter().nth(2).unwrap().1.to_string(),
+            "[0, 1, 2, 0]"
+        );
+
+        let relations = rows("relations.parquet");
+        let relation = relations[0].get_column_iter().collect::<Vec<_>>();
+        assert_eq!(
+            relation[2].1.to_string(),
+            r#"[{type: "way", ref: 0, role: "outer"}]"#
+        );
+        assert!(matches!(relation[3].1, Field::Bytes(_)));
+
+        let counts = write_parquet(&archive, dir.path(), Compression::UNCOMPRESSED, |tags| {
+            find_tag(&archive, tags, b"amenity").is_some()
+        })
+        .unwrap();
+        assert_eq!(counts, [1, 0, 0]);
+    }
+}
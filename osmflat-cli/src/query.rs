@@ -0,0 +1,445 @@
+use crate::args::{QueryArgs, QueryFormat};
+use crate::geo::BBox;
+use crate::id::{IdLookup, OsmId};
+use crate::Error;
+
+use osmflat::{
+    find_tag, has_tag, iter_tags, query, BBoxMatch, EntityType, FileResourceStorage, Osm, OsmEntity,
+};
+use serde::Serialize;
+
+use std::collections::BTreeMap;
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+use std::str::FromStr;
+
+/// Condition on the tags of an entity, e.g. `[amenity=cafe]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Condition {
+    /// `[key]`: has a tag with the key
+    Has(String),
+    /// `[!key]`: has no tag with the key
+    HasNot(String),
+    /// `[key=value]`: has the tag
+    Equals(String, String),
+    /// `[key!=value]`: has no tag with the key, or with another value
+    NotEquals(String, String),
+}
+
+impl Condition {
+    fn matches(&self, archive: &Osm, tags: Range<u64>) -> bool {
+        match self {
+            Self::Has(key) => find_tag(archive, tags, key.as_bytes()).is_some(),
+            Self::HasNot(key) => find_tag(archive, tags, key.as_bytes()).is_none(),
+            Self::Equals(key, value) => has_tag(archive, tags, key.as_bytes(), value.as_bytes()),
+            Self::NotEquals(key, value) => {
+                !has_tag(archive, tags, key.as_bytes(), value.as_bytes())
+            }
+        }
+    }
+}
+
+/// Query given on the command line, e.g. `node[amenity=cafe][name]`.
+///
+/// A query consists of the entity type (`node`, `way`, `relation`, or `nwr`
+/// for all types), any number of tag conditions (`[key]`, `[!key]`,
+/// `[key=value]`, `[key!=value]`), and an optional bounding box
+/// `(south,west,north,east)` in degrees. Keys and values containing special
+/// characters are quoted with `"`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    types: Vec<EntityType>,
+    conditions: Vec<Condition>,
+    bbox: Option<BBox>,
+}
+
+/// Parser of the text of a query.
+struct Parser<'a> {
+    s: &'a str,
+    rest: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, expected: &str) -> String {
+        let pos = self.s.len() - self.rest.len();
+        format!("invalid query '{}': expected {expected} at {pos}", self.s)
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.rest = self.rest.trim_start();
+    }
+
+    fn eat(&mut self, prefix: &str) -> bool {
+        self.skip_whitespace();
+        match self.rest.strip_prefix(prefix) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn expect(&mut self, prefix: &str) -> Result<(), String> {
+        if self.eat(prefix) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("'{prefix}'")))
+        }
+    }
+
+    /// Parses a key or value: a quoted string, or the characters up to the
+    /// next special character.
+    fn string(&mut self) -> Result<String, String> {
+        self.skip_whitespace();
+        if let Some(quoted) = self.rest.strip_prefix('"') {
+            let (s, rest) = quoted
+                .split_once('"')
+                .ok_or_else(|| self.error("closing '\"'"))?;
+            self.rest = rest;
+            return Ok(s.to_string());
+        }
+        let end = self
+            .rest
+            .find(['[', ']', '(', ')', '=', '!', '"', ','])
+            .unwrap_or(self.rest.len());
+        let s = self.rest[..end].trim();
+        if s.is_empty() {
+            return Err(self.error("key or value"));
+        }
+        self.rest = &self.rest[end..];
+        Ok(s.to_string())
+    }
+
+    fn number(&mut self) -> Result<f64, String> {
+        let s = self.string()?;
+        s.parse().map_err(|_| self.error("number"))
+    }
+
+    fn condition(&mut self) -> Result<Condition, String> {
+        if self.eat("!") {
+            let key = self.string()?;
+            self.expect("]")?;
+            return Ok(Condition::HasNot(key));
+        }
+        let key = self.string()?;
+        let condition = if self.eat("!=") {
+            Condition::NotEquals(key, self.string()?)
+        } else if self.eat("=") {
+            Condition::Equals(key, self.string()?)
+        } else {
+            Condition::Has(key)
+        };
+        self.expect("]")?;
+        Ok(condition)
+    }
+
+    fn bbox(&mut self) -> Result<BBox, String> {
+        let south = self.number()?;
+        self.expect(",")?;
+        let west = self.number()?;
+        self.expect(",")?;
+        let north = self.number()?;
+        self.expect(",")?;
+        let east = self.number()?;
+        self.expect(")")?;
+        if south > north || west > east {
+            return Err(self.error("south <= north and west <= east in bounding box"));
+        }
+        Ok(((west, south), (east, north)))
+    }
+}
+
+impl FromStr for Query {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { s, rest: s };
+        parser.skip_whitespace();
+        let end = parser
+            .rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(parser.rest.len());
+        let types = match &parser.rest[..end] {
+            "node" => vec![EntityType::Node],
+            "way" => vec![EntityType::Way],
+            "relation" => vec![EntityType::Relation],
+            "nwr" => vec![EntityType::Node, EntityType::Way, EntityType::Relation],
+            _ => return Err(parser.error("node, way, relation, or nwr")),
+        };
+        parser.rest = &parser.rest[end..];
+
+        let mut query = Query {
+            types,
+            conditions: Vec::new(),
+            bbox: None,
+        };
+        loop {
+            if parser.eat("[") {
+                query.conditions.push(parser.condition()?);
+            } else if query.bbox.is_none() && parser.eat("(") {
+                query.bbox = Some(parser.bbox()?);
+            } else {
+                break;
+            }
+        }
+        parser.skip_whitespace();
+        if !parser.rest.is_empty() {
+            return Err(parser.error("'[' or end of query"));
+        }
+        Ok(query)
+    }
+}
+
+/// Representative location of an entity, used for matching the bounding box
+/// and for the output.
+pub fn location(archive: &Osm, entity_type: EntityType, idx: usize) -> Option<(f64, f64)> {
+    match entity_type {
+        EntityType::Node => Some(archive.node_coord(idx)),
+        EntityType::Way => archive.way_centroid(idx),
+        EntityType::Relation => archive.relation_centroid(idx),
+    }
+}
+
+impl Query {
+    /// Returns the indexes of the entities of type `entity_type` matching the
+    /// query in ascending order.
+    ///
+    /// The candidates are taken from the inverted index for the first tag
+    /// condition, or from the spatial index for the bounding box, if the
+    /// archive contains them, cf. [`osmflat::query`] and
+    /// [`Osm::nodes_in_bbox`]; otherwise, all entities are scanned.
+    fn matches(&self, archive: &Osm, entity_type: EntityType) -> Vec<u64> {
+        let tags = |idx: u64| archive.entity(entity_type, idx as usize).tags();
+        let positive = self
+            .conditions
+            .iter()
+            .find_map(|condition| match condition {
+                Condition::Has(key) => Some((key, None)),
+                Condition::Equals(key, value) => Some((key, Some(value))),
+                _ => None,
+            });
+        let mut candidates: Vec<u64> = match (positive, self.bbox) {
+            (Some((key, value)), _) => query::entities_of_type_with_tag(
+                archive,
+                entity_type,
+                key.as_bytes(),
+                value.map(|value| value.as_bytes()),
+            )
+            .collect(),
+            (None, Some(((min_lon, min_lat), (max_lon, max_lat)))) => {
+                let bbox = (min_lon, min_lat, max_lon, max_lat);
+                let mut indexes: Vec<u64> = match entity_type {
+                    EntityType::Node => archive
+                        .nodes_in_bbox(bbox.0, bbox.1, bbox.2, bbox.3)
+                        .collect(),
+                    EntityType::Way => archive
+                        .ways_in_bbox(bbox.0, bbox.1, bbox.2, bbox.3, BBoxMatch::BoundingBox)
+                        .collect(),
+                    EntityType::Relation => archive
+                        .relations_in_bbox(bbox.0, bbox.1, bbox.2, bbox.3, BBoxMatch::BoundingBox)
+                        .collect(),
+                };
+                indexes.sort_unstable();
+                indexes
+            }
+            (None, None) => {
+                let len = match entity_type {
+                    EntityType::Node => archive.nodes().len(),
+                    EntityType::Way => archive.ways().len(),
+                    EntityType::Relation => archive.relations().len(),
+                };
+                (0..len as u64).collect()
+            }
+        };
+        candidates.retain(|&idx| {
+            self.conditions
+                .iter()
+                .all(|condition| condition.matches(archive, tags(idx)))
+                && self
+                    .bbox
+                    .is_none_or(|((min_lon, min_lat), (max_lon, max_lat))| {
+                        location(archive, entity_type, idx as usize).is_some_and(|(lon, lat)| {
+                            (min_lon..=max_lon).contains(&lon) && (min_lat..=max_lat).contains(&lat)
+                        })
+                    })
+        });
+        candidates
+    }
+}
+
+/// Entity matching a query, as written to the output.
+#[derive(Debug, Serialize)]
+struct Match {
+    #[serde(rename = "type")]
+    entity_type: &'static str,
+    /// OSM id, if the archive contains ids
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<OsmId>,
+    /// Index in the vector of the entity type
+    index: u64,
+    /// Location of a node, or representative point of a way or relation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lat: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lon: Option<f64>,
+    tags: BTreeMap<String, String>,
+}
+
+fn to_match(archive: &Osm, ids: Option<&IdLookup>, entity_type: EntityType, idx: u64) -> Match {
+    let (name, id) = match entity_type {
+        EntityType::Node => ("node", ids.map(|ids| ids.node(idx))),
+        EntityType::Way => ("way", ids.map(|ids| ids.way(idx))),
+        EntityType::Relation => ("relation", ids.map(|ids| ids.relation(idx))),
+    };
+    let location = location(archive, entity_type, idx as usize);
+    let tags = archive.entity(entity_type, idx as usize).tags();
+    Match {
+        entity_type: name,
+        id,
+        index: idx,
+        lat: location.map(|(_, lat)| lat),
+        lon: location.map(|(lon, _)| lon),
+        tags: iter_tags(archive, tags)
+            .map(|(key, value)| {
+                (
+                    String::from_utf8_lossy(key).into_owned(),
+                    String::from_utf8_lossy(value).into_owned(),
+                )
+            })
+            .collect(),
+    }
+}
+
+fn write_text(out: &mut impl Write, m: &Match) -> io::Result<()> {
+    match m.id {
+        Some(id) => write!(out, "{id}")?,
+        None => write!(out, "{}#{}", &m.entity_type[..1], m.index)?,
+    }
+    match (m.lat, m.lon) {
+        (Some(lat), Some(lon)) => write!(out, " {lat:.7},{lon:.7}")?,
+        _ => write!(out, " -")?,
+    }
+    for (key, value) in &m.tags {
+        write!(out, " {key}={value}")?;
+    }
+    writeln!(out)
+}
+
+pub fn run(args: QueryArgs) -> Result<(), Error> {
+    let archive = Osm::open(FileResourceStorage::new(args.input))?;
+    let ids = IdLookup::new(&archive).ok();
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    let mut num_matches = 0;
+    if let QueryFormat::Json = args.format {
+        write!(out, "[")?;
+    }
+    for &entity_type in &args.query.types {
+        for idx in args.query.matches(&archive, entity_type) {
+            let m = to_match(&archive, ids.as_ref(), entity_type, idx);
+            match args.format {
+                QueryFormat::Text => write_text(&mut out, &m)?,
+                QueryFormat::Json => {
+                    if num_matches > 0 {
+                        write!(out, ",")?;
+                    }
+                    write!(out, "\n  ")?;
+                    serde_json::to_writer(&mut out, &m)?;
+                }
+            }
+            num_matches += 1;
+        }
+    }
+    if let QueryFormat::Json = args.format {
+        writeln!(out, "\n]")?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use osmflat::TestArchive;
+
+    #[test]
+    fn test_parse() {
+        let query: Query = "node[amenity=cafe][name]".parse().unwrap();
+        assert_eq!(query.types, [EntityType::Node]);
+        assert_eq!(
+            query.conditions,
+            [
+                Condition::Equals("amenity".into(), "cafe".into()),
+                Condition::Has("name".into())
+            ]
+        );
+        assert_eq!(query.bbox, None);
+
+        let query: Query = r#" nwr [ !building ] ["name:en" != "A [b]"] (52.5, 13.3, 52.6, 13.4) "#
+            .parse()
+            .unwrap();
+        assert_eq!(query.types.len(), 3);
+        assert_eq!(
+            query.conditions,
+            [
+                Condition::HasNot("building".into()),
+                Condition::NotEquals("name:en".into(), "A [b]".into())
+            ]
+        );
+        assert_eq!(query.bbox, Some(((13.3, 52.5), (13.4, 52.6))));
+
+        for invalid in [
+            "",
+            "nodes",
+            "node[",
+            "node[amenity",
+            "node[=cafe]",
+            "node[name=\"a]",
+            "node(1,2,3)",
+            "node(52.6,13.3,52.5,13.4)",
+            "node[a] b",
+        ] {
+            assert!(invalid.parse::<Query>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_matches() {
+        let archive = TestArchive {
+            nodes: vec![
+                (13.0, 52.0, vec![("amenity", "cafe"), ("name", "A")]),
+                (13.5, 52.5, vec![("amenity", "cafe")]),
+                (14.0, 53.0, vec![("amenity", "cafe"), ("name", "C")]),
+                (14.0, 53.0, vec![("amenity", "pub"), ("name", "D")]),
+            ],
+            ways: vec![(vec![0, 1], vec![("highway", "primary")])],
+            relations: vec![],
+        }
+        .build();
+
+        let matches = |query: &str, entity_type| {
+            query
+                .parse::<Query>()
+                .unwrap()
+                .matches(&archive, entity_type)
+        };
+        assert_eq!(
+            matches("node[amenity=cafe][name]", EntityType::Node),
+            [0, 2]
+        );
+        assert_eq!(matches("node[amenity][!name]", EntityType::Node), [1]);
+        assert_eq!(matches("node[amenity!=cafe]", EntityType::Node), [3]);
+        assert_eq!(
+            matches("node[name](51,12,52.6,13.6)", EntityType::Node),
+            [0]
+        );
+        assert_eq!(
+            matches("node(52.4,13.4,53,14)", EntityType::Node),
+            [1, 2, 3]
+        );
+        assert_eq!(matches("way(52.1,13.1,52.4,13.4)", EntityType::Way), [0]);
+        assert!(matches("way(53.1,14.1,54,15)", EntityType::Way).is_empty());
+        assert_eq!(matches("way", EntityType::Way), [0]);
+    }
+}
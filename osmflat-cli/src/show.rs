@@ -0,0 +1,185 @@
+use crate::args::ShowArgs;
+use crate::id::{IdLookup, OsmId};
+use crate::Error;
+
+use osmflat::{iter_tags, FileResourceStorage, Osm, RelationMembersRef};
+
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+
+pub fn run(args: ShowArgs) -> Result<(), Error> {
+    let archive = Osm::open(FileResourceStorage::new(args.input))?;
+    let mut out = BufWriter::new(io::stdout().lock());
+    show(&mut out, &archive, args.id)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Writes the entity with the given id, and the relations referencing it.
+fn show(out: &mut impl Write, archive: &Osm, id: OsmId) -> Result<(), Error> {
+    let ids = IdLookup::new(archive)?;
+    let idx = ids.find(id).ok_or_else(|| format!("{id} not found"))?;
+
+    match id {
+        OsmId::Node(_) => show_node(out, archive, &ids, idx)?,
+        OsmId::Way(_) => show_way(out, archive, &ids, idx)?,
+        OsmId::Relation(_) => show_relation(out, archive, &ids, idx)?,
+    }
+    show_parent_relations(out, archive, &ids, id, idx as u64)?;
+    Ok(())
+}
+
+fn coords(archive: &Osm, node_idx: u64) -> (f64, f64) {
+    let (lon, lat) = archive.node_coord(node_idx as usize);
+    (lat, lon)
+}
+
+fn show_tags(out: &mut impl Write, archive: &Osm, tags: Range<u64>) -> io::Result<()> {
+    writeln!(out, "  tags:")?;
+    for (key, value) in iter_tags(archive, tags) {
+        writeln!(
+            out,
+            "    {} = {}",
+            String::from_utf8_lossy(key),
+            String::from_utf8_lossy(value)
+        )?;
+    }
+    Ok(())
+}
+
+fn show_node(out: &mut impl Write, archive: &Osm, ids: &IdLookup, idx: usize) -> io::Result<()> {
+    let (lat, lon) = coords(archive, idx as u64);
+    writeln!(out, "{} (idx {idx})", ids.node(idx as u64))?;
+    writeln!(out, "  location: {lat:.7},{lon:.7}")?;
+    show_tags(out, archive, archive.nodes()[idx].tags())?;
+
+    let nodes_index = archive.nodes_index();
+    writeln!(out, "  ways:")?;
+    for (way_idx, way) in archive.ways().iter().enumerate() {
+        if way
+            .refs()
+            .any(|i| nodes_index[i as usize].value() == Some(idx as u64))
+        {
+            writeln!(out, "    {}", ids.way(way_idx as u64))?;
+        }
+    }
+    Ok(())
+}
+
+fn show_way(out: &mut impl Write, archive: &Osm, ids: &IdLookup, idx: usize) -> io::Result<()> {
+    let way = &archive.ways()[idx];
+    writeln!(out, "{} (idx {idx})", ids.way(idx as u64))?;
+    show_tags(out, archive, way.tags())?;
+
+    let nodes_index = archive.nodes_index();
+    writeln!(out, "  nodes:")?;
+    for node_idx in way.refs().map(|i| nodes_index[i as usize].value()) {
+        match node_idx {
+            Some(node_idx) => {
+                let (lat, lon) = coords(archive, node_idx);
+                writeln!(out, "    {} {lat:.7},{lon:.7}", ids.node(node_idx))?;
+            }
+            None => writeln!(out, "    <unresolved>")?,
+        }
+    }
+    Ok(())
+}
+
+fn show_relation(
+    out: &mut impl Write,
+    archive: &Osm,
+    ids: &IdLookup,
+    idx: usize,
+) -> io::Result<()> {
+    let relation = &archive.relations()[idx];
+    writeln!(out, "{} (idx {idx})", ids.relation(idx as u64))?;
+    show_tags(out, archive, relation.tags())?;
+
+    let strings = archive.stringtable();
+    writeln!(out, "  members:")?;
+    for member in archive.relation_members().at(idx) {
+        let (id, role_idx) = match member {
+            RelationMembersRef::NodeMember(m) => (m.node_idx().map(|i| ids.node(i)), m.role_idx()),
+            RelationMembersRef::WayMember(m) => (m.way_idx().map(|i| ids.way(i)), m.role_idx()),
+            RelationMembersRef::RelationMember(m) => {
+                (m.relation_idx().map(|i| ids.relation(i)), m.role_idx())
+            }
+        };
+        let role = strings.substring_raw(role_idx as usize);
+        let id = id.map_or_else(|| "<unresolved>".to_string(), |id| id.to_string());
+        writeln!(out, "    {id} role={}", String::from_utf8_lossy(role))?;
+    }
+    Ok(())
+}
+
+/// Shows all relations which have the entity as a member.
+fn show_parent_relations(
+    out: &mut impl Write,
+    archive: &Osm,
+    ids: &IdLookup,
+    id: OsmId,
+    idx: u64,
+) -> io::Result<()> {
+    let strings = archive.stringtable();
+    writeln!(out, "  relations:")?;
+    for relation_idx in 0..archive.relations().len() {
+        for member in archive.relation_members().at(relation_idx) {
+            let role_idx = match (id, member) {
+                (OsmId::Node(_), RelationMembersRef::NodeMember(m))
+                    if m.node_idx() == Some(idx) =>
+                {
+                    m.role_idx()
+                }
+                (OsmId::Way(_), RelationMembersRef::WayMember(m)) if m.way_idx() == Some(idx) => {
+                    m.role_idx()
+                }
+                (OsmId::Relation(_), RelationMembersRef::RelationMember(m))
+                    if m.relation_idx() == Some(idx) =>
+                {
+                    m.role_idx()
+                }
+                _ => continue,
+            };
+            let role = strings.substring_raw(role_idx as usize);
+            writeln!(
+                out,
+                "    {} role={}",
+                ids.relation(relation_idx as u64),
+                String::from_utf8_lossy(role)
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::id::build_with_ids;
+    use osmflat::Member;
+
+    #[test]
+    fn test_show_negative_id() {
+        let archive = osmflat::TestArchive {
+            nodes: vec![(13.4, 52.5, vec![]), (13.5, 52.5, vec![])],
+            ways: vec![(vec![0, 1], vec![("highway", "path")])],
+            relations: vec![(vec![Member::Way(0, "")], vec![])],
+        };
+        let archive = build_with_ids(&archive, [&[-1, 2], &[-1], &[-3]]);
+        let mut out = Vec::new();
+        show(&mut out, &archive, "w-1".parse().unwrap()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                "w-1 (idx 0)\n",
+                "  tags:\n",
+                "    highway = path\n",
+                "  nodes:\n",
+                "    n-1 52.5000000,13.4000000\n",
+                "    n2 52.5000000,13.5000000\n",
+                "  relations:\n",
+                "    r-3 role=\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,426 @@
+use crate::args::StatsArgs;
+use crate::Error;
+
+use osmflat::{hilbert_index, member_role_idx, FileResourceStorage, Id, Osm};
+use serde::Serialize;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Bounding box in degrees.
+#[derive(Debug, Serialize)]
+struct Extent {
+    min_lon: f64,
+    min_lat: f64,
+    max_lon: f64,
+    max_lat: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct Replication {
+    /// Seconds since the Unix epoch
+    timestamp: i64,
+    sequence_number: i64,
+    base_url: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct KeyCount {
+    key: String,
+    count: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct StringtableUsage {
+    /// Size of the stringtable in bytes
+    size: u64,
+    /// Bytes of the distinct strings referenced by tags, relation members and
+    /// the header, including their terminators
+    referenced: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct ResourceSize {
+    /// Path of the file relative to the archive
+    path: String,
+    size: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct Sortedness {
+    /// Fraction of consecutive nodes in ascending order on the Hilbert curve,
+    /// 1 for archives compiled with `osmflatc --sort hilbert`
+    hilbert_locality: f64,
+    /// Whether the entities are sorted by their ids, if the archive contains
+    /// ids
+    node_ids_sorted: Option<bool>,
+    way_ids_sorted: Option<bool>,
+    relation_ids_sorted: Option<bool>,
+}
+
+/// Statistics of an archive.
+#[derive(Debug, Serialize)]
+struct Report {
+    num_nodes: u64,
+    num_ways: u64,
+    num_relations: u64,
+    num_relation_members: u64,
+    /// Number of distinct tags
+    num_tags: u64,
+    /// Number of references to tags by nodes, ways and relations
+    num_tag_refs: u64,
+    /// Number of distinct tag keys
+    num_keys: u64,
+    /// Most frequent tag keys by their number of references
+    top_keys: Vec<KeyCount>,
+    /// Bounding box stored in the header
+    bbox: Option<Extent>,
+    /// Bounding box of the nodes
+    extent: Option<Extent>,
+    replication: Option<Replication>,
+    stringtable: StringtableUsage,
+    sortedness: Sortedness,
+    resources: Vec<ResourceSize>,
+}
+
+/// Counts the references to the tag keys, and returns the `top` most frequent
+/// keys together with the number of distinct keys.
+fn key_histogram(archive: &Osm, top: usize) -> (Vec<KeyCount>, u64) {
+    let tags = archive.tags();
+    let strings = archive.stringtable();
+    let mut counts: HashMap<u64, u64> = HashMap::new();
+    for tag_idx in archive.tags_index() {
+        *counts
+            .entry(tags[tag_idx.value() as usize].key_idx())
+            .or_default() += 1;
+    }
+    let num_keys = counts.len() as u64;
+    let mut counts: Vec<(u64, u64)> = counts.into_iter().collect();
+    counts.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let top_keys = counts
+        .into_iter()
+        .take(top)
+        .map(|(key_idx, count)| KeyCount {
+            key: String::from_utf8_lossy(strings.substring_raw(key_idx as usize)).into_owned(),
+            count,
+        })
+        .collect();
+    (top_keys, num_keys)
+}
+
+fn stringtable_usage(archive: &Osm) -> StringtableUsage {
+    let strings = archive.stringtable();
+    let header = archive.header();
+    let mut offsets: Vec<u64> = [
+        Some(header.writingprogram_idx()),
+        header.source_idx(),
+        header.replication_base_url_idx(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    offsets.extend(
+        archive
+            .tags()
+            .iter()
+            .flat_map(|tag| [tag.key_idx(), tag.value_idx()]),
+    );
+    let relation_members = archive.relation_members();
+    for idx in 0..archive.relations().len() {
+        offsets.extend(
+            relation_members
+                .at(idx)
+                .map(|member| member_role_idx(&member)),
+        );
+    }
+    offsets.sort_unstable();
+    offsets.dedup();
+    let referenced = offsets
+        .into_iter()
+        .map(|idx| strings.substring_raw(idx as usize).len() as u64 + 1)
+        .sum();
+    StringtableUsage {
+        size: strings.as_bytes().len() as u64,
+        referenced,
+    }
+}
+
+/// Fraction of consecutive pairs of `values` which are in ascending order, 1
+/// if there are less than two values.
+fn ascending_fraction(values: impl Iterator<Item = u64>) -> f64 {
+    let (mut num_pairs, mut num_ascending) = (0u64, 0u64);
+    let mut last = None;
+    for value in values {
+        if let Some(last) = last {
+            num_pairs += 1;
+            num_ascending += u64::from(last <= value);
+        }
+        last = Some(value);
+    }
+    if num_pairs == 0 {
+        1.0
+    } else {
+        num_ascending as f64 / num_pairs as f64
+    }
+}
+
+fn sortedness(archive: &Osm) -> Sortedness {
+    let nodes = archive.nodes();
+    let sorted = |ids: &[Id]| {
+        ids.windows(2)
+            .all(|w| w[0].signed_value() <= w[1].signed_value())
+    };
+    let ids = archive.ids();
+    Sortedness {
+        hilbert_locality: ascending_fraction(
+            nodes
+                .iter()
+                .map(|node| hilbert_index(node.lon(), node.lat())),
+        ),
+        node_ids_sorted: ids.map(|ids| sorted(ids.nodes())),
+        way_ids_sorted: ids.map(|ids| sorted(ids.ways())),
+        relation_ids_sorted: ids.map(|ids| sorted(ids.relations())),
+    }
+}
+
+fn extent(archive: &Osm) -> Option<Extent> {
+    let coords = archive.coord_reader();
+    archive.nodes().iter().fold(None, |extent, node| {
+        let (lon, lat) = (coords.lon(node), coords.lat(node));
+        Some(match extent {
+            None => Extent {
+                min_lon: lon,
+                min_lat: lat,
+                max_lon: lon,
+                max_lat: lat,
+            },
+            Some(e) => Extent {
+                min_lon: e.min_lon.min(lon),
+                min_lat: e.min_lat.min(lat),
+                max_lon: e.max_lon.max(lon),
+                max_lat: e.max_lat.max(lat),
+            },
+        })
+    })
+}
+
+/// Returns the sizes of all files of the archive at `path`, sorted by path.
+fn resource_sizes(path: &Path) -> io::Result<Vec<ResourceSize>> {
+    fn visit(dir: &Path, prefix: &str, result: &mut Vec<ResourceSize>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = format!("{prefix}{}", entry.file_name().to_string_lossy());
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                visit(&entry.path(), &format!("{path}/"), result)?;
+            } else {
+                result.push(ResourceSize {
+                    path,
+                    size: metadata.len(),
+                });
+            }
+        }
+        Ok(())
+    }
+    let mut result = Vec::new();
+    visit(path, "", &mut result)?;
+    result.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(result)
+}
+
+impl Report {
+    fn new(archive: &Osm, top: usize) -> Self {
+        let relation_members = archive.relation_members();
+        let (top_keys, num_keys) = key_histogram(archive, top);
+        Report {
+            num_nodes: archive.nodes().len() as u64,
+            num_ways: archive.ways().len() as u64,
+            num_relations: archive.relations().len() as u64,
+            num_relation_members: (0..archive.relations().len())
+                .map(|idx| relation_members.at(idx).count() as u64)
+                .sum(),
+            num_tags: archive.tags().len() as u64,
+            num_tag_refs: archive.tags_index().len() as u64,
+            num_keys,
+            top_keys,
+            bbox: archive.header().bounding_box().map(|bbox| {
+                let ((min_lon, min_lat), (max_lon, max_lat)) = bbox.to_degrees(archive);
+                Extent {
+                    min_lon,
+                    min_lat,
+                    max_lon,
+                    max_lat,
+                }
+            }),
+            extent: extent(archive),
+            replication: archive.replication().map(|replication| Replication {
+                timestamp: replication.timestamp.timestamp(),
+                sequence_number: replication.sequence_number,
+                base_url: replication.base_url,
+            }),
+            stringtable: stringtable_usage(archive),
+            sortedness: sortedness(archive),
+            resources: Vec::new(),
+        }
+    }
+}
+
+/// Formats seconds since the Unix epoch as UTC date and time in ISO 8601.
+pub fn format_timestamp(seconds: i64) -> String {
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = seconds.div_euclid(86_400) + 719_468;
+    let time = seconds.rem_euclid(86_400);
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+fn print_report(report: &Report) {
+    println!("nodes: {}", report.num_nodes);
+    println!("ways: {}", report.num_ways);
+    println!(
+        "relations: {} ({} members)",
+        report.num_relations, report.num_relation_members
+    );
+    println!(
+        "tags: {} distinct, {} references, {} keys",
+        report.num_tags, report.num_tag_refs, report.num_keys
+    );
+    for key in &report.top_keys {
+        println!("  {:>12} {}", key.count, key.key);
+    }
+
+    let extent = |e: &Option<Extent>| match e {
+        Some(e) => format!(
+            "{:.7},{:.7},{:.7},{:.7}",
+            e.min_lon, e.min_lat, e.max_lon, e.max_lat
+        ),
+        None => "-".into(),
+    };
+    println!("bbox: {}", extent(&report.bbox));
+    println!("extent of nodes: {}", extent(&report.extent));
+    match &report.replication {
+        Some(r) => println!(
+            "replication: {} (sequence number {}{})",
+            format_timestamp(r.timestamp),
+            r.sequence_number,
+            r.base_url
+                .as_ref()
+                .map_or_else(String::new, |url| format!(", {url}"))
+        ),
+        None => println!("replication: -"),
+    }
+
+    let stringtable = &report.stringtable;
+    println!(
+        "stringtable: {} bytes, {} referenced ({:.1}%)",
+        stringtable.size,
+        stringtable.referenced,
+        100.0 * stringtable.referenced as f64 / stringtable.size.max(1) as f64
+    );
+
+    let sorted = |sorted: Option<bool>| match sorted {
+        Some(true) => "yes",
+        Some(false) => "no",
+        None => "-",
+    };
+    let sortedness = &report.sortedness;
+    println!("hilbert locality: {:.3}", sortedness.hilbert_locality);
+    println!(
+        "sorted by id: nodes {}, ways {}, relations {}",
+        sorted(sortedness.node_ids_sorted),
+        sorted(sortedness.way_ids_sorted),
+        sorted(sortedness.relation_ids_sorted)
+    );
+
+    let total: u64 = report.resources.iter().map(|r| r.size).sum();
+    println!("resources: {total} bytes");
+    for resource in &report.resources {
+        println!("  {:>12} {}", resource.size, resource.path);
+    }
+}
+
+pub fn run(args: StatsArgs) -> Result<(), Error> {
+    let archive = Osm::open(FileResourceStorage::new(args.input.clone()))?;
+    let mut report = Report::new(&archive, args.top);
+    report.resources = resource_sizes(&args.input)?;
+
+    if args.json {
+        serde_json::to_writer_pretty(io::stdout().lock(), &report)?;
+        println!();
+    } else {
+        print_report(&report);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use osmflat::TestArchive;
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14T22:13:20Z");
+        assert_eq!(format_timestamp(-1), "1969-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn test_report() {
+        let archive = TestArchive {
+            nodes: vec![
+                (1.0, 1.0, vec![("amenity", "cafe"), ("name", "A")]),
+                (-1.0, 2.0, vec![("amenity", "pub")]),
+                (1.0, -1.0, vec![]),
+            ],
+            ways: vec![(vec![0, 1], vec![("amenity", "cafe")])],
+            relations: vec![],
+        }
+        .build();
+
+        let report = Report::new(&archive, 1);
+        assert_eq!((report.num_nodes, report.num_ways), (3, 1));
+        assert_eq!((report.num_tags, report.num_tag_refs), (3, 4));
+        assert_eq!(report.num_keys, 2);
+        assert_eq!(report.top_keys.len(), 1);
+        assert_eq!(
+            (report.top_keys[0].key.as_str(), report.top_keys[0].count),
+            ("amenity", 3)
+        );
+        let extent = report.extent.unwrap();
+        assert_eq!(
+            (
+                extent.min_lon,
+                extent.min_lat,
+                extent.max_lon,
+                extent.max_lat
+            ),
+            (-1.0, -1.0, 1.0, 2.0)
+        );
+        assert!(report.stringtable.referenced <= report.stringtable.size);
+        assert_eq!(report.sortedness.node_ids_sorted, None);
+    }
+
+    #[test]
+    fn test_ascending_fraction() {
+        assert_eq!(ascending_fraction([1, 2, 2, 3].into_iter()), 1.0);
+        assert_eq!(ascending_fraction([3, 1, 2].into_iter()), 0.5);
+        assert_eq!(ascending_fraction(std::iter::empty()), 1.0);
+    }
+}
@@ -0,0 +1,105 @@
+//! Exports administrative boundaries of a given admin level as GeoJSON.
+//!
+//! Boundaries are relations tagged with `boundary=administrative`. Their way
+//! members are assembled into polygons with holes by
+//! `osmflat::geometry::multipolygon`, and the result is written as a
+//! `FeatureCollection` of `MultiPolygon`s.
+//!
+//! Demonstrates
+//!
+//!  * filtering of relations by tags
+//!  * assembly of multipolygons with holes from relation members
+//!  * serialization of geometries as GeoJSON
+//!
+//! LICENSE
+//!
+//! The code in this example file is released into the Public Domain.
+
+use clap::Parser;
+use osmflat::{find_tag, geometry, has_tag, iter_tags, FileResourceStorage, Osm};
+use serde_json::{json, Map, Value};
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::str;
+
+/// Coordinates represented by (longitude, latitude) as in GeoJSON.
+type Coord = (f64, f64);
+
+/// Exports administrative boundaries as GeoJSON
+#[derive(Debug, Parser)]
+#[clap(name = "admin-boundaries")]
+struct Args {
+    /// osmflat archive
+    osmflat_archive: PathBuf,
+
+    /// admin level of the boundaries to export
+    #[clap(long, default_value = "8")]
+    admin_level: String,
+
+    /// GeoJSON filename to output
+    #[clap(long, short = 'o')]
+    output: PathBuf,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let archive = Osm::open(FileResourceStorage::new(args.osmflat_archive))?;
+    let relation_ids = archive.ids().map(|ids| ids.relations());
+
+    let mut features = Vec::new();
+    for (idx, relation) in archive.relations().iter().enumerate() {
+        if !has_tag(&archive, relation.tags(), b"boundary", b"administrative")
+            || find_tag(&archive, relation.tags(), b"admin_level")
+                != Some(args.admin_level.as_bytes())
+        {
+            continue;
+        }
+
+        let polygons = match geometry::multipolygon(&archive, idx) {
+            Ok(polygons) => polygons,
+            Err(e) => {
+                let name = find_tag(&archive, relation.tags(), b"name").unwrap_or(b"");
+                eprintln!(
+                    "Skipping boundary {} '{}': {e}",
+                    idx,
+                    String::from_utf8_lossy(name)
+                );
+                continue;
+            }
+        };
+
+        let coordinates: Vec<Vec<Vec<Coord>>> = polygons
+            .into_iter()
+            .map(|p| std::iter::once(p.outer).chain(p.inner).collect())
+            .collect();
+
+        let mut properties: Map<String, Value> = iter_tags(&archive, relation.tags())
+            .filter_map(|(k, v)| Some((str::from_utf8(k).ok()?, str::from_utf8(v).ok()?)))
+            .map(|(k, v)| (k.to_string(), Value::from(v)))
+            .collect();
+        if let Some(ids) = relation_ids {
+            properties.insert("@id".into(), ids[idx].signed_value().into());
+        }
+
+        features.push(json!({
+            "type": "Feature",
+            "geometry": {
+                "type": "MultiPolygon",
+                "coordinates": coordinates,
+            },
+            "properties": properties,
+        }));
+    }
+
+    eprintln!("Exported {} boundaries", features.len());
+    let collection = json!({
+        "type": "FeatureCollection",
+        "features": features,
+    });
+    serde_json::to_writer(BufWriter::new(File::create(args.output)?), &collection)?;
+
+    Ok(())
+}
@@ -0,0 +1,148 @@
+//! Computes statistics about building footprints: a histogram of footprint
+//! areas and totals per building type.
+//!
+//! Buildings are closed ways and multipolygon relations tagged with
+//! `building=*`. The area of a footprint is computed on the sphere; holes
+//! (inner rings) of multipolygons are subtracted from the outer rings.
+//!
+//! Demonstrates
+//!
+//!  * detection of closed ways
+//!  * assembly of multipolygon relations with `osmflat::geometry`
+//!  * area calculation on the Earth
+//!
+//! LICENSE
+//!
+//! The code in this example file is released into the Public Domain.
+
+use osmflat::{find_tag, geometry, FileResourceStorage, Osm};
+
+use std::collections::HashMap;
+use std::str;
+
+/// Earth's radius for WGS84 in meters
+const EARTH_RADIUS_IN_METERS: f64 = 6_372_797.560_856;
+
+/// Upper bounds (exclusive) of the histogram buckets in square meters.
+const BUCKETS: [f64; 8] = [
+    10.0,
+    50.0,
+    100.0,
+    250.0,
+    500.0,
+    1_000.0,
+    5_000.0,
+    f64::INFINITY,
+];
+
+/// Coordinates represented by (longitude, latitude).
+type Coord = (f64, f64);
+
+/// Returns the area of a ring on the sphere in square meters.
+///
+/// The ring is expected to be closed, i.e. the first and the last coordinates
+/// are the same. Cf. "Some Algorithms for Polygons on a Sphere" by Chamberlain
+/// and Duquette.
+fn ring_area(ring: &[Coord]) -> f64 {
+    let sum: f64 = ring
+        .windows(2)
+        .map(|w| {
+            let ((lon1, lat1), (lon2, lat2)) = (w[0], w[1]);
+            (lon2 - lon1).to_radians() * (2.0 + lat1.to_radians().sin() + lat2.to_radians().sin())
+        })
+        .sum();
+    (sum * EARTH_RADIUS_IN_METERS * EARTH_RADIUS_IN_METERS / 2.0).abs()
+}
+
+fn is_closed(coords: &[Coord]) -> bool {
+    coords.len() >= 4 && coords.first() == coords.last()
+}
+
+/// Computes the area of a multipolygon relation as area of its outer rings
+/// minus the area of its inner rings.
+fn multipolygon_area(archive: &Osm, relation_idx: usize) -> Option<f64> {
+    let polygons = geometry::multipolygon(archive, relation_idx).ok()?;
+    let area = polygons
+        .iter()
+        .map(|p| ring_area(&p.outer) - p.inner.iter().map(|r| ring_area(r)).sum::<f64>())
+        .sum::<f64>();
+    Some(area.max(0.0))
+}
+
+#[derive(Debug, Default)]
+struct Stats {
+    histogram: [usize; BUCKETS.len()],
+    per_type: HashMap<String, (usize, f64)>,
+}
+
+impl Stats {
+    fn add(&mut self, building_type: &[u8], area: f64) {
+        let bucket = BUCKETS.iter().position(|&b| area < b).unwrap();
+        self.histogram[bucket] += 1;
+
+        let building_type = str::from_utf8(building_type).unwrap_or("<invalid>");
+        let entry = self.per_type.entry(building_type.into()).or_default();
+        entry.0 += 1;
+        entry.1 += area;
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let archive_dir = std::env::args()
+        .nth(1)
+        .ok_or("USAGE: building-stats <osmflat-archive>")?;
+    let archive = Osm::open(FileResourceStorage::new(archive_dir))?;
+
+    let mut stats = Stats::default();
+
+    for way in archive.ways() {
+        let building_type = match find_tag(&archive, way.tags(), b"building") {
+            Some(t) => t,
+            None => continue,
+        };
+        let coords: Vec<Coord> = match geometry::way_coords(&archive, way) {
+            Some(coords) => coords.collect(),
+            None => continue,
+        };
+        if is_closed(&coords) {
+            stats.add(building_type, ring_area(&coords));
+        }
+    }
+
+    for (idx, relation) in archive.relations().iter().enumerate() {
+        let building_type = match find_tag(&archive, relation.tags(), b"building") {
+            Some(t) => t,
+            None => continue,
+        };
+        if find_tag(&archive, relation.tags(), b"type") != Some(b"multipolygon") {
+            continue;
+        }
+        if let Some(area) = multipolygon_area(&archive, idx) {
+            stats.add(building_type, area);
+        }
+    }
+
+    let total_count: usize = stats.histogram.iter().sum();
+    println!("Footprint areas (m²):");
+    let max_count = stats.histogram.iter().copied().max().unwrap_or(0).max(1);
+    let mut lower = 0.0;
+    for (upper, count) in BUCKETS.iter().zip(stats.histogram.iter()) {
+        let bar = "#".repeat(count * 50 / max_count);
+        println!("  {:>6} - {:<6} {:>10} {}", lower, upper, count, bar);
+        lower = *upper;
+    }
+
+    let mut per_type: Vec<_> = stats.per_type.into_iter().collect();
+    per_type.sort_by(|a, b| b.1 .1.total_cmp(&a.1 .1));
+
+    println!("Building types:");
+    println!("  {:<24} {:>10} {:>16}", "type", "count", "area (m²)");
+    for (building_type, (count, area)) in &per_type {
+        println!("  {building_type:<24} {count:>10} {area:>16.0}");
+    }
+
+    let total_area: f64 = per_type.iter().map(|(_, (_, area))| area).sum();
+    println!("Total: {total_count} buildings, {total_area:.0} m²");
+
+    Ok(())
+}
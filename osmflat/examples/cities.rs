@@ -1,11 +1,11 @@
-//! Scans all OSM nodes and extracts list of cities with name and
-//! population in JSON format.
+//! Queries the OSM nodes tagged as city and extracts list of cities with name
+//! and population in JSON format.
 //!
 //! LICENSE
 //!
 //! The code in this example file is released into the Public Domain.
 
-use osmflat::{find_tag, has_tag, Osm};
+use osmflat::{get_tags, query, Osm};
 use serde::Serialize;
 use std::str;
 
@@ -21,20 +21,17 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .ok_or("USAGE: cities <osmflat-archive>")?;
     let archive = Osm::open(osmflat::FileResourceStorage::new(archive_dir))?;
 
-    // Iterate through all nodes
-    let cities: Vec<City> = archive
-        .nodes()
-        .iter()
-        // filter nodes that does not have a place=city tag
-        .filter(|node| has_tag(&archive, node.tags(), b"place", b"city"))
+    // Iterate through all nodes with a place=city tag
+    let nodes = archive.nodes();
+    let cities: Vec<City> = query::entities_with_tag(&archive, b"place", Some(b"city"))
+        .nodes
+        .map(|idx| &nodes[idx as usize])
         .filter_map(|node| {
             // try to collect population and country
+            let [name, population] = get_tags(&archive, node.tags(), &[b"name", b"population"]);
             Some(City {
-                name: str::from_utf8(find_tag(&archive, node.tags(), b"name")?).ok()?,
-                population: str::from_utf8(find_tag(&archive, node.tags(), b"population")?)
-                    .ok()?
-                    .parse()
-                    .ok()?,
+                name: str::from_utf8(name?).ok()?,
+                population: str::from_utf8(population?).ok()?.parse().ok()?,
             })
         })
         .collect();
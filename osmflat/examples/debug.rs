@@ -10,8 +10,9 @@
 //! The code in this example file is released into the Public Domain.
 
 use clap::Parser;
-use osmflat::{iter_tags, FileResourceStorage, Osm, RelationMembersRef};
+use osmflat::{iter_tags_lossy, FileResourceStorage, Osm, RelationMembersRef};
 
+use std::borrow::Cow;
 use std::path::PathBuf;
 use std::str::{self, Utf8Error};
 
@@ -22,33 +23,33 @@ struct Header<'ar> {
     #[allow(unused)]
     writingprogram: &'ar str,
     #[allow(unused)]
-    source: &'ar str,
+    source: Option<String>,
     #[allow(unused)]
     replication_timestamp: i64,
     #[allow(unused)]
     replication_sequence_number: i64,
     #[allow(unused)]
-    replication_base_url: &'ar str,
+    replication_base_url: Option<String>,
 }
 
 #[derive(Debug)]
 struct Node<'ar> {
     #[allow(unused)]
-    id: Option<u64>,
+    id: Option<i64>,
     #[allow(unused)]
     lat: f64,
     #[allow(unused)]
     lon: f64,
     #[allow(unused)]
-    tags: Vec<(&'ar str, &'ar str)>,
+    tags: Vec<(Cow<'ar, str>, Cow<'ar, str>)>,
 }
 
 #[derive(Debug)]
 struct Way<'ar> {
     #[allow(unused)]
-    id: Option<u64>,
+    id: Option<i64>,
     #[allow(unused)]
-    tags: Vec<(&'ar str, &'ar str)>,
+    tags: Vec<(Cow<'ar, str>, Cow<'ar, str>)>,
     #[allow(unused)]
     nodes: Vec<Option<u64>>,
 }
@@ -56,9 +57,9 @@ struct Way<'ar> {
 #[derive(Debug)]
 struct Relation<'ar> {
     #[allow(unused)]
-    id: Option<u64>,
+    id: Option<i64>,
     #[allow(unused)]
-    tags: Vec<(&'ar str, &'ar str)>,
+    tags: Vec<(Cow<'ar, str>, Cow<'ar, str>)>,
     #[allow(unused)]
     members: Vec<Member<'ar>>,
 }
@@ -143,31 +144,24 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             scale_coord(header.bbox_bottom()),
         ),
         writingprogram: strings.substring(header.writingprogram_idx() as usize)?,
-        source: strings.substring(header.source_idx() as usize)?,
+        source: archive.header_string(header.source_idx()),
         replication_timestamp: header.replication_timestamp(),
         replication_sequence_number: header.replication_sequence_number(),
-        replication_base_url: strings.substring(header.replication_base_url_idx() as usize)?,
+        replication_base_url: archive.header_string(header.replication_base_url_idx()),
     };
     println!("{header:#?}");
 
-    let collect_utf8_tags = |tags| -> Vec<(&str, &str)> {
-        iter_tags(&archive, tags)
-            .filter_map(|(k, v)| match (str::from_utf8(k), str::from_utf8(v)) {
-                (Ok(k), Ok(v)) => Some((k, v)),
-                _ => None,
-            })
-            .collect()
-    };
+    let collect_tags = |tags| -> Vec<_> { iter_tags_lossy(&archive, tags).collect() };
 
     // print nodes
     let mut node_ids = archive.ids().map(|x| x.nodes()).into_iter().flatten();
     if args.types.contains('n') {
         for node in archive.nodes().iter().take(args.num.unwrap_or(usize::MAX)) {
             let node = Node {
-                id: node_ids.next().map(|x| x.value()),
+                id: node_ids.next().map(|x| x.signed_value()),
                 lat: scale_coord(node.lat()),
                 lon: scale_coord(node.lon()),
-                tags: collect_utf8_tags(node.tags()),
+                tags: collect_tags(node.tags()),
             };
 
             println!("{node:#?}");
@@ -180,8 +174,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     if args.types.contains('w') {
         for way in archive.ways().iter().take(args.num.unwrap_or(usize::MAX)) {
             let way = Way {
-                id: way_ids.next().map(|x| x.value()),
-                tags: collect_utf8_tags(way.tags()),
+                id: way_ids.next().map(|x| x.signed_value()),
+                tags: collect_tags(way.tags()),
                 nodes: way
                     .refs()
                     .map(|idx| nodes_index[idx as usize].value())
@@ -202,8 +196,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         {
             let members: Result<Vec<_>, _> = Member::new_slice(&archive, relation_idx).collect();
             let relation = Relation {
-                id: relation_ids.next().map(|x| x.value()),
-                tags: collect_utf8_tags(relation.tags()),
+                id: relation_ids.next().map(|x| x.signed_value()),
+                tags: collect_tags(relation.tags()),
                 members: members?,
             };
 
@@ -0,0 +1,333 @@
+//! Computes an isochrone polygon: the area reachable by car from a start
+//! coordinate within a given travel time. The result is written as GeoJSON.
+//!
+//! First, the routing graph is extracted from all drivable highways. Travel
+//! times of edges are derived from the `maxspeed` tag or from a default speed
+//! per highway class; oneway streets are respected. Then, Dijkstra's algorithm
+//! computes the travel times from the node closest to the start coordinate.
+//!
+//! Finally, a concave hull of the reached nodes is computed by covering all
+//! reached nodes and edges with cells of a regular grid and tracing the outline
+//! of the union of these cells.
+//!
+//! Demonstrates
+//!
+//!  * extraction of a routing graph via `nodes_index`
+//!  * tag-based edge weights
+//!  * shortest path computation
+//!
+//! LICENSE
+//!
+//! The code in this example file is released into the Public Domain.
+
+use clap::Parser;
+use osmflat::{find_tag, FileResourceStorage, Osm};
+use serde_json::json;
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::str;
+
+/// Earth's radius for WGS84 in meters
+const EARTH_RADIUS_IN_METERS: f64 = 6_372_797.560_856;
+
+/// Coordinates represented by (longitude, latitude).
+type Coord = (f64, f64);
+
+fn haversine_distance(c1: Coord, c2: Coord) -> f64 {
+    let mut lonh = ((c1.0 - c2.0).to_radians() * 0.5).sin();
+    lonh *= lonh;
+    let mut lath = ((c1.1 - c2.1).to_radians() * 0.5).sin();
+    lath *= lath;
+    let tmp = c1.1.to_radians().cos() * c2.1.to_radians().cos();
+    2.0 * EARTH_RADIUS_IN_METERS * (lath + tmp * lonh).sqrt().asin()
+}
+
+/// Default speed in km/h for drivable highway classes.
+fn default_speed(highway: &[u8]) -> Option<f64> {
+    let speed = match highway {
+        b"motorway" | b"motorway_link" => 120.0,
+        b"trunk" | b"trunk_link" => 100.0,
+        b"primary" | b"primary_link" => 80.0,
+        b"secondary" | b"secondary_link" => 60.0,
+        b"tertiary" | b"tertiary_link" => 50.0,
+        b"unclassified" => 40.0,
+        b"residential" => 30.0,
+        b"service" => 20.0,
+        b"living_street" => 10.0,
+        _ => return None,
+    };
+    Some(speed)
+}
+
+/// Parses a `maxspeed` value in km/h (or mph with suffix).
+fn parse_maxspeed(value: &[u8]) -> Option<f64> {
+    let value = str::from_utf8(value).ok()?.trim();
+    match value.strip_suffix("mph") {
+        Some(mph) => Some(mph.trim().parse::<f64>().ok()? * 1.609_344),
+        None => value.parse().ok(),
+    }
+}
+
+/// Routing graph with travel times in seconds as edge weights.
+#[derive(Default)]
+struct Graph {
+    /// Adjacency list: node idx -> [(node idx, seconds)]
+    edges: HashMap<u64, Vec<(u64, f64)>>,
+    coords: HashMap<u64, Coord>,
+}
+
+impl Graph {
+    fn new(archive: &Osm) -> Self {
+        let nodes = archive.nodes();
+        let nodes_index = archive.nodes_index();
+        let scale = f64::from(archive.header().coord_scale());
+
+        let mut graph = Graph::default();
+        for way in archive.ways() {
+            let tags = way.tags();
+            let speed = match find_tag(archive, tags.clone(), b"highway").and_then(default_speed) {
+                Some(speed) => find_tag(archive, tags.clone(), b"maxspeed")
+                    .and_then(parse_maxspeed)
+                    .unwrap_or(speed),
+                None => continue,
+            };
+            let oneway = find_tag(archive, tags.clone(), b"oneway");
+            let (forward, backward) = match oneway {
+                Some(b"yes") | Some(b"1") | Some(b"true") => (true, false),
+                Some(b"-1") => (false, true),
+                _ => (true, true),
+            };
+
+            let path: Vec<u64> = way
+                .refs()
+                .filter_map(|idx| nodes_index[idx as usize].value())
+                .collect();
+            for &idx in &path {
+                graph.coords.entry(idx).or_insert_with(|| {
+                    let node = &nodes[idx as usize];
+                    (f64::from(node.lon()) / scale, f64::from(node.lat()) / scale)
+                });
+            }
+            for w in path.windows(2) {
+                let distance = haversine_distance(graph.coords[&w[0]], graph.coords[&w[1]]);
+                let seconds = distance / (speed / 3.6);
+                if forward {
+                    graph.edges.entry(w[0]).or_default().push((w[1], seconds));
+                }
+                if backward {
+                    graph.edges.entry(w[1]).or_default().push((w[0], seconds));
+                }
+            }
+        }
+        graph
+    }
+
+    fn closest_node(&self, c: Coord) -> Option<u64> {
+        self.coords
+            .iter()
+            .min_by(|a, b| haversine_distance(*a.1, c).total_cmp(&haversine_distance(*b.1, c)))
+            .map(|(idx, _)| *idx)
+    }
+
+    /// Computes travel times to all nodes reachable within `max_seconds`.
+    fn dijkstra(&self, start: u64, max_seconds: f64) -> HashMap<u64, f64> {
+        let mut times: HashMap<u64, f64> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+        // f64 is not Ord, but non-negative floats compare like their bits
+        queue.push(Reverse((0f64.to_bits(), start)));
+        while let Some(Reverse((time, idx))) = queue.pop() {
+            let time = f64::from_bits(time);
+            if times.contains_key(&idx) {
+                continue;
+            }
+            times.insert(idx, time);
+            for &(next, seconds) in self.edges.get(&idx).into_iter().flatten() {
+                let next_time = time + seconds;
+                if next_time <= max_seconds && !times.contains_key(&next) {
+                    queue.push(Reverse((next_time.to_bits(), next)));
+                }
+            }
+        }
+        times
+    }
+}
+
+/// Grid of cells covering the reached part of the network.
+struct CellGrid {
+    size_lon: f64,
+    size_lat: f64,
+    cells: HashSet<(i64, i64)>,
+}
+
+impl CellGrid {
+    fn new(cell_size: f64, lat: f64) -> Self {
+        let size_lat = (cell_size / EARTH_RADIUS_IN_METERS).to_degrees();
+        Self {
+            size_lon: size_lat / lat.to_radians().cos(),
+            size_lat,
+            cells: HashSet::new(),
+        }
+    }
+
+    fn cell(&self, c: Coord) -> (i64, i64) {
+        (
+            (c.0 / self.size_lon).floor() as i64,
+            (c.1 / self.size_lat).floor() as i64,
+        )
+    }
+
+    /// Covers the segment from `a` to `b` by sampling it at half the cell size.
+    fn cover(&mut self, a: Coord, b: Coord) {
+        let steps = ((b.0 - a.0).abs() / self.size_lon)
+            .max((b.1 - a.1).abs() / self.size_lat)
+            .mul_add(2.0, 1.0) as usize;
+        for i in 0..=steps {
+            let t = i as f64 / steps as f64;
+            let cell = self.cell((a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1)));
+            self.cells.insert(cell);
+        }
+    }
+
+    /// Traces the outline of the union of all cells.
+    ///
+    /// Outer rings are counterclockwise and holes are clockwise, as required
+    /// by GeoJSON.
+    fn outline(&self) -> Vec<Vec<Coord>> {
+        // directed boundary edges with the covered cell on the left
+        let mut edges: HashMap<(i64, i64), Vec<(i64, i64)>> = HashMap::new();
+        for &(x, y) in &self.cells {
+            let mut add = |from, to| edges.entry(from).or_default().push(to);
+            if !self.cells.contains(&(x, y - 1)) {
+                add((x, y), (x + 1, y));
+            }
+            if !self.cells.contains(&(x + 1, y)) {
+                add((x + 1, y), (x + 1, y + 1));
+            }
+            if !self.cells.contains(&(x, y + 1)) {
+                add((x + 1, y + 1), (x, y + 1));
+            }
+            if !self.cells.contains(&(x - 1, y)) {
+                add((x, y + 1), (x, y));
+            }
+        }
+
+        let mut rings = Vec::new();
+        while let Some(&start) = edges.keys().next() {
+            let mut ring = vec![start];
+            let mut current = start;
+            loop {
+                let targets = edges.get_mut(&current).expect("open outline");
+                let next = targets.pop().expect("open outline");
+                if targets.is_empty() {
+                    edges.remove(&current);
+                }
+                ring.push(next);
+                current = next;
+                if current == start {
+                    break;
+                }
+            }
+            rings.push(
+                ring.into_iter()
+                    .map(|(x, y)| (x as f64 * self.size_lon, y as f64 * self.size_lat))
+                    .collect(),
+            );
+        }
+        rings
+    }
+}
+
+fn signed_area(ring: &[Coord]) -> f64 {
+    ring.windows(2)
+        .map(|w| w[0].0 * w[1].1 - w[1].0 * w[0].1)
+        .sum::<f64>()
+        / 2.0
+}
+
+fn ring_contains(ring: &[Coord], (x, y): Coord) -> bool {
+    let mut inside = false;
+    for w in ring.windows(2) {
+        let ((x0, y0), (x1, y1)) = (w[0], w[1]);
+        if (y0 > y) != (y1 > y) && x < (x1 - x0) * (y - y0) / (y1 - y0) + x0 {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Computes an isochrone and writes it as GeoJSON
+#[derive(Debug, Parser)]
+#[clap(name = "isochrone")]
+struct Args {
+    /// osmflat archive
+    osmflat_archive: PathBuf,
+
+    /// start coordinate as `lat,lon`
+    #[clap(long)]
+    start: String,
+
+    /// travel time in minutes
+    #[clap(long, default_value = "10")]
+    minutes: f64,
+
+    /// size of the grid cells used for hulling in meters
+    #[clap(long, default_value = "100")]
+    cell_size: f64,
+
+    /// GeoJSON filename to output
+    #[clap(long, short = 'o')]
+    output: PathBuf,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let (lat, lon) = args.start.split_once(',').ok_or("expected lat,lon")?;
+    let start: Coord = (lon.trim().parse()?, lat.trim().parse()?);
+
+    let archive = Osm::open(FileResourceStorage::new(args.osmflat_archive))?;
+
+    let graph = Graph::new(&archive);
+    let start_node = graph.closest_node(start).ok_or("no drivable roads")?;
+    let times = graph.dijkstra(start_node, args.minutes * 60.0);
+    eprintln!("Reached {} nodes", times.len());
+
+    let mut grid = CellGrid::new(args.cell_size, start.1);
+    for &idx in times.keys() {
+        let from = graph.coords[&idx];
+        grid.cover(from, from);
+        for (next, _) in graph.edges.get(&idx).into_iter().flatten() {
+            if times.contains_key(next) {
+                grid.cover(from, graph.coords[next]);
+            }
+        }
+    }
+
+    let (outer, holes): (Vec<_>, Vec<_>) = grid
+        .outline()
+        .into_iter()
+        .partition(|ring| signed_area(ring) > 0.0);
+    let mut polygons: Vec<Vec<Vec<Coord>>> = outer.into_iter().map(|ring| vec![ring]).collect();
+    for hole in holes {
+        if let Some(polygon) = polygons.iter_mut().find(|p| ring_contains(&p[0], hole[0])) {
+            polygon.push(hole);
+        }
+    }
+
+    let feature = json!({
+        "type": "Feature",
+        "geometry": {
+            "type": "MultiPolygon",
+            "coordinates": polygons,
+        },
+        "properties": {
+            "minutes": args.minutes,
+            "start": [start.0, start.1],
+        },
+    });
+    serde_json::to_writer(BufWriter::new(File::create(args.output)?), &feature)?;
+    Ok(())
+}
@@ -0,0 +1,388 @@
+//! Matches a GPS trace to the road network.
+//!
+//! The trace is read either from a GPX file (track points) or from a CSV file
+//! with `lat,lon` per line. For each point of the trace, candidate roads are
+//! looked up in a grid index built over all road segments. The most likely
+//! sequence of roads is then determined with the Viterbi algorithm based on a
+//! simple score:
+//!
+//!  * emission: squared distance of the point to the road, normalized by the
+//!    expected GPS noise,
+//!  * transition: staying on the same road is free, switching to a road sharing
+//!    a node is cheap, switching to an unconnected road is expensive.
+//!
+//! The result is written as CSV to stdout.
+//!
+//! Demonstrates
+//!
+//!  * nearest-neighbor queries on way geometries
+//!  * accessing of nodes belonging to a way
+//!
+//! LICENSE
+//!
+//! The code in this example file is released into the Public Domain.
+
+use clap::Parser;
+use osmflat::{find_tag, FileResourceStorage, Osm};
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+/// Earth's radius for WGS84 in meters
+const EARTH_RADIUS_IN_METERS: f64 = 6_372_797.560_856;
+/// Size of a grid cell in degrees.
+const CELL_SIZE: f64 = 0.005;
+/// Cost of switching to a road which shares a node with the current one.
+const CONNECTED_SWITCH_COST: f64 = 1.0;
+/// Cost of switching to a road which is not connected to the current one.
+const UNCONNECTED_SWITCH_COST: f64 = 25.0;
+
+/// Geographic coordinates represented by (latitude, longitude).
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct GeoCoord {
+    lat: f64,
+    lon: f64,
+}
+
+/// A straight segment of a road.
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    way_idx: usize,
+    from: GeoCoord,
+    to: GeoCoord,
+}
+
+impl Segment {
+    /// Returns the closest point on the segment to `p` and the distance to it
+    /// in meters.
+    ///
+    /// Uses an equirectangular projection around `p`, which is precise enough
+    /// for the small distances we are interested in.
+    fn project(&self, p: GeoCoord) -> (GeoCoord, f64) {
+        let cos_lat = p.lat.to_radians().cos();
+        let to_xy = |c: GeoCoord| ((c.lon - p.lon) * cos_lat, c.lat - p.lat);
+        let (ax, ay) = to_xy(self.from);
+        let (bx, by) = to_xy(self.to);
+        let (dx, dy) = (bx - ax, by - ay);
+        let len2 = dx * dx + dy * dy;
+        let t = if len2 == 0.0 {
+            0.0
+        } else {
+            (-(ax * dx + ay * dy) / len2).clamp(0.0, 1.0)
+        };
+        let (x, y) = (ax + t * dx, ay + t * dy);
+        let dist = (x * x + y * y).sqrt().to_radians() * EARTH_RADIUS_IN_METERS;
+        let closest = GeoCoord {
+            lat: self.from.lat + t * (self.to.lat - self.from.lat),
+            lon: self.from.lon + t * (self.to.lon - self.from.lon),
+        };
+        (closest, dist)
+    }
+}
+
+/// Grid index over road segments.
+#[derive(Default)]
+struct SegmentGrid {
+    cells: HashMap<(i32, i32), Vec<Segment>>,
+    /// For each road the set of node indexes it consists of.
+    way_nodes: HashMap<usize, HashSet<u64>>,
+}
+
+fn cell(c: GeoCoord) -> (i32, i32) {
+    (
+        (c.lon / CELL_SIZE).floor() as i32,
+        (c.lat / CELL_SIZE).floor() as i32,
+    )
+}
+
+/// Returns the number of cells in longitude and latitude direction around the
+/// cell of `p` which may contain points within `radius` meters of `p`.
+fn cell_range(p: GeoCoord, radius: f64) -> (i32, i32) {
+    let lat_degrees = (radius / EARTH_RADIUS_IN_METERS).to_degrees();
+    // meridians converge towards the poles; capped to keep the range finite
+    let lon_degrees = lat_degrees / p.lat.to_radians().cos().max(0.01);
+    (
+        (lon_degrees / CELL_SIZE).ceil() as i32,
+        (lat_degrees / CELL_SIZE).ceil() as i32,
+    )
+}
+
+impl SegmentGrid {
+    fn new(archive: &Osm) -> Self {
+        let nodes = archive.nodes();
+        let nodes_index = archive.nodes_index();
+        let scale = f64::from(archive.header().coord_scale());
+
+        let mut grid = Self::default();
+        for (way_idx, way) in archive.ways().iter().enumerate() {
+            if find_tag(archive, way.tags(), b"highway").is_none() {
+                continue;
+            }
+            let refs: Option<Vec<u64>> = way
+                .refs()
+                .map(|idx| nodes_index[idx as usize].value())
+                .collect();
+            let refs = match refs {
+                Some(refs) => refs,
+                None => continue,
+            };
+            let coords: Vec<GeoCoord> = refs
+                .iter()
+                .map(|&idx| {
+                    let node = &nodes[idx as usize];
+                    GeoCoord {
+                        lat: f64::from(node.lat()) / scale,
+                        lon: f64::from(node.lon()) / scale,
+                    }
+                })
+                .collect();
+            for w in coords.windows(2) {
+                grid.insert(Segment {
+                    way_idx,
+                    from: w[0],
+                    to: w[1],
+                });
+            }
+            grid.way_nodes.insert(way_idx, refs.into_iter().collect());
+        }
+        grid
+    }
+
+    /// Registers the segment in all cells covered by its bounding box.
+    fn insert(&mut self, segment: Segment) {
+        let (x0, y0) = cell(segment.from);
+        let (x1, y1) = cell(segment.to);
+        for x in x0.min(x1)..=x0.max(x1) {
+            for y in y0.min(y1)..=y0.max(y1) {
+                self.cells.entry((x, y)).or_default().push(segment);
+            }
+        }
+    }
+
+    /// Returns for each road within `radius` meters the closest point and the
+    /// distance to it.
+    fn candidates(&self, p: GeoCoord, radius: f64) -> Vec<Candidate> {
+        let mut best: HashMap<usize, Candidate> = HashMap::new();
+        let (x, y) = cell(p);
+        let (rx, ry) = cell_range(p, radius);
+        for dx in -rx..=rx {
+            for dy in -ry..=ry {
+                for segment in self.cells.get(&(x + dx, y + dy)).into_iter().flatten() {
+                    let (snapped, dist) = segment.project(p);
+                    if dist > radius {
+                        continue;
+                    }
+                    let candidate = Candidate {
+                        way_idx: segment.way_idx,
+                        snapped,
+                        dist,
+                    };
+                    best.entry(segment.way_idx)
+                        .and_modify(|c| {
+                            if dist < c.dist {
+                                *c = candidate;
+                            }
+                        })
+                        .or_insert(candidate);
+                }
+            }
+        }
+        best.into_values().collect()
+    }
+
+    fn connected(&self, a: usize, b: usize) -> bool {
+        match (self.way_nodes.get(&a), self.way_nodes.get(&b)) {
+            (Some(a), Some(b)) => !a.is_disjoint(b),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    way_idx: usize,
+    snapped: GeoCoord,
+    dist: f64,
+}
+
+/// Parses a trace either from GPX track points or from CSV lines `lat,lon`.
+fn parse_trace(data: &str) -> Result<Vec<GeoCoord>, Box<dyn std::error::Error>> {
+    fn attribute<'a>(element: &'a str, name: &str) -> Option<&'a str> {
+        let start = element.find(&format!("{name}=\""))? + name.len() + 2;
+        let len = element[start..].find('"')?;
+        Some(&element[start..start + len])
+    }
+
+    if data.contains("<gpx") {
+        data.split("<trkpt")
+            .skip(1)
+            .map(|element| {
+                let lat = attribute(element, "lat").ok_or("trkpt without lat")?;
+                let lon = attribute(element, "lon").ok_or("trkpt without lon")?;
+                Ok(GeoCoord {
+                    lat: lat.parse()?,
+                    lon: lon.parse()?,
+                })
+            })
+            .collect()
+    } else {
+        data.lines()
+            .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let (lat, lon) = line.split_once(',').ok_or("expected lat,lon")?;
+                Ok(GeoCoord {
+                    lat: lat.trim().parse()?,
+                    lon: lon.trim().parse()?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Finds the most likely sequence of candidates with the Viterbi algorithm.
+///
+/// Points without any candidate are skipped and reported as unmatched.
+fn viterbi(
+    grid: &SegmentGrid,
+    candidates: &[Vec<Candidate>],
+    sigma: f64,
+) -> Vec<Option<Candidate>> {
+    let emission = |c: &Candidate| (c.dist / sigma).powi(2) / 2.0;
+
+    // costs and back pointers for each layer of candidates
+    let mut costs: Vec<Vec<f64>> = Vec::with_capacity(candidates.len());
+    let mut back: Vec<Vec<Option<usize>>> = Vec::with_capacity(candidates.len());
+    let mut prev_layer: Option<usize> = None;
+    for (layer, cs) in candidates.iter().enumerate() {
+        let mut layer_costs = Vec::with_capacity(cs.len());
+        let mut layer_back = Vec::with_capacity(cs.len());
+        for c in cs {
+            let (cost, from) = match prev_layer {
+                None => (0.0, None),
+                Some(p) => candidates[p]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, prev)| {
+                        let transition = if prev.way_idx == c.way_idx {
+                            0.0
+                        } else if grid.connected(prev.way_idx, c.way_idx) {
+                            CONNECTED_SWITCH_COST
+                        } else {
+                            UNCONNECTED_SWITCH_COST
+                        };
+                        (costs[p][i] + transition, Some(i))
+                    })
+                    .min_by(|a, b| a.0.total_cmp(&b.0))
+                    .unwrap_or((0.0, None)),
+            };
+            layer_costs.push(cost + emission(c));
+            layer_back.push(from);
+        }
+        costs.push(layer_costs);
+        back.push(layer_back);
+        if !cs.is_empty() {
+            prev_layer = Some(layer);
+        }
+    }
+
+    // backtrack from the cheapest candidate of the last non-empty layer
+    let mut result = vec![None; candidates.len()];
+    let mut current = prev_layer.and_then(|layer| {
+        let (idx, _) = costs[layer]
+            .iter()
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(b.1))?;
+        Some((layer, idx))
+    });
+    while let Some((layer, idx)) = current {
+        result[layer] = Some(candidates[layer][idx]);
+        current = back[layer][idx].map(|prev_idx| {
+            let prev_layer = (0..layer)
+                .rev()
+                .find(|&l| !candidates[l].is_empty())
+                .expect("back pointer without previous layer");
+            (prev_layer, prev_idx)
+        });
+    }
+    result
+}
+
+/// Matches a GPS trace (GPX or CSV) to the road network
+#[derive(Debug, Parser)]
+#[clap(name = "map-matching")]
+struct Args {
+    /// osmflat archive
+    osmflat_archive: PathBuf,
+
+    /// GPX or CSV (lat,lon) file with the trace
+    trace: PathBuf,
+
+    /// radius in meters to search for candidate roads
+    #[clap(long, default_value = "50")]
+    radius: f64,
+
+    /// expected GPS noise (standard deviation) in meters
+    #[clap(long, default_value = "10")]
+    sigma: f64,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let archive = Osm::open(FileResourceStorage::new(args.osmflat_archive))?;
+    let trace = parse_trace(&std::fs::read_to_string(&args.trace)?)?;
+
+    let grid = SegmentGrid::new(&archive);
+    let candidates: Vec<_> = trace
+        .iter()
+        .map(|p| grid.candidates(*p, args.radius))
+        .collect();
+    let matched = viterbi(&grid, &candidates, args.sigma);
+
+    let way_ids = archive.ids().map(|ids| ids.ways());
+    println!("lat,lon,way,matched_lat,matched_lon,distance");
+    for (p, m) in trace.iter().zip(matched) {
+        match m {
+            Some(c) => {
+                let way = match way_ids {
+                    Some(ids) => ids[c.way_idx].signed_value().to_string(),
+                    None => format!("#{}", c.way_idx),
+                };
+                println!(
+                    "{:.7},{:.7},{},{:.7},{:.7},{:.1}",
+                    p.lat, p.lon, way, c.snapped.lat, c.snapped.lon, c.dist
+                );
+            }
+            None => println!("{:.7},{:.7},,,,", p.lat, p.lon),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_candidates_beyond_neighboring_cells() {
+        let coord = |lat, lon| GeoCoord { lat, lon };
+        let mut grid = SegmentGrid::default();
+        // about 1.1 km north of the point, i.e. more than 2 cells away
+        grid.insert(Segment {
+            way_idx: 7,
+            from: coord(52.01, 13.0),
+            to: coord(52.01, 13.001),
+        });
+        let p = coord(52.0, 13.0005);
+        assert_eq!(cell_range(p, 10.0), (1, 1));
+        assert!(cell_range(p, 1200.0).1 > 1);
+
+        assert!(grid.candidates(p, 1000.0).is_empty());
+        let candidates = grid.candidates(p, 1200.0);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].way_idx, 7);
+        assert_eq!(candidates[0].snapped, coord(52.01, 13.0005));
+        assert!((candidates[0].dist - 1112.0).abs() < 1.0);
+    }
+}
@@ -0,0 +1,253 @@
+//! Runs a few quality assurance checks on the input archive and outputs the
+//! found issues as GeoJSON.
+//!
+//! Implemented checks:
+//!
+//!  * `short-way`: ways consisting of less than 2 nodes,
+//!  * `self-intersecting-building`: building rings intersecting themselves,
+//!  * `unconnected-highway`: highways ending within a small distance (1 m by
+//!    default) of another highway without sharing a node with it.
+//!
+//! Demonstrates
+//!
+//!  * iteration through ways and their nodes
+//!  * geometric predicates on way geometries
+//!  * spatial grid index for proximity queries
+//!
+//! LICENSE
+//!
+//! The code in this example file is released into the Public Domain.
+
+use clap::Parser;
+use osmflat::{find_tag, query, FileResourceStorage, Osm};
+use serde_json::{json, Value};
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+/// Earth's radius for WGS84 in meters
+const EARTH_RADIUS_IN_METERS: f64 = 6_372_797.560_856;
+/// Size of a grid cell in degrees.
+const CELL_SIZE: f64 = 0.001;
+
+/// Coordinates represented by (longitude, latitude).
+type Coord = (f64, f64);
+
+/// A found issue.
+struct Issue {
+    check: &'static str,
+    way_idx: usize,
+    location: Coord,
+}
+
+/// Resolves node indexes and coordinates of a way; `None` if a node is not
+/// resolved.
+fn way_nodes(archive: &Osm, way_idx: usize) -> Option<Vec<(u64, Coord)>> {
+    let nodes = archive.nodes();
+    let nodes_index = archive.nodes_index();
+    let coords = archive.coord_reader();
+    archive.ways()[way_idx]
+        .refs()
+        .map(|idx| {
+            let node_idx = nodes_index[idx as usize].value()?;
+            Some((node_idx, coords.coord(&nodes[node_idx as usize])))
+        })
+        .collect()
+}
+
+/// Checks whether the segments `a` and `b` properly intersect.
+fn segments_intersect((p1, p2): (Coord, Coord), (q1, q2): (Coord, Coord)) -> bool {
+    fn orientation(a: Coord, b: Coord, c: Coord) -> f64 {
+        (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+    }
+    let d1 = orientation(q1, q2, p1);
+    let d2 = orientation(q1, q2, p2);
+    let d3 = orientation(p1, p2, q1);
+    let d4 = orientation(p1, p2, q2);
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
+
+/// Returns the location of the first self-intersection of a closed ring.
+fn self_intersection(ring: &[Coord]) -> Option<Coord> {
+    let segments: Vec<_> = ring.windows(2).map(|w| (w[0], w[1])).collect();
+    let n = segments.len();
+    for i in 0..n {
+        // skip adjacent segments, including the pair closing the ring
+        for j in i + 2..n {
+            if i == 0 && j == n - 1 {
+                continue;
+            }
+            if segments_intersect(segments[i], segments[j]) {
+                return Some(segments[i].0);
+            }
+        }
+    }
+    None
+}
+
+/// Distance in meters from `p` to the segment `(a, b)`.
+fn distance_to_segment(p: Coord, (a, b): (Coord, Coord)) -> f64 {
+    let cos_lat = p.1.to_radians().cos();
+    let to_xy = |c: Coord| ((c.0 - p.0) * cos_lat, c.1 - p.1);
+    let (ax, ay) = to_xy(a);
+    let (bx, by) = to_xy(b);
+    let (dx, dy) = (bx - ax, by - ay);
+    let len2 = dx * dx + dy * dy;
+    let t = if len2 == 0.0 {
+        0.0
+    } else {
+        (-(ax * dx + ay * dy) / len2).clamp(0.0, 1.0)
+    };
+    let (x, y) = (ax + t * dx, ay + t * dy);
+    (x * x + y * y).sqrt().to_radians() * EARTH_RADIUS_IN_METERS
+}
+
+fn cell(c: Coord) -> (i32, i32) {
+    (
+        (c.0 / CELL_SIZE).floor() as i32,
+        (c.1 / CELL_SIZE).floor() as i32,
+    )
+}
+
+fn check_unconnected_highways(archive: &Osm, tolerance: f64, issues: &mut Vec<Issue>) {
+    // collect highways with their geometry
+    let highways: Vec<(usize, Vec<(u64, Coord)>)> =
+        query::entities_with_tag(archive, b"highway", None)
+            .ways
+            .filter_map(|idx| Some((idx as usize, way_nodes(archive, idx as usize)?)))
+            .filter(|(_, nodes)| nodes.len() >= 2)
+            .collect();
+
+    // grid of highway segments: cell -> (highway pos, segment pos)
+    let mut grid: HashMap<(i32, i32), Vec<(usize, usize)>> = HashMap::new();
+    for (pos, (_, nodes)) in highways.iter().enumerate() {
+        for (seg, w) in nodes.windows(2).enumerate() {
+            let (x0, y0) = cell(w[0].1);
+            let (x1, y1) = cell(w[1].1);
+            for x in x0.min(x1)..=x0.max(x1) {
+                for y in y0.min(y1)..=y0.max(y1) {
+                    grid.entry((x, y)).or_default().push((pos, seg));
+                }
+            }
+        }
+    }
+
+    for (pos, (way_idx, nodes)) in highways.iter().enumerate() {
+        let first = nodes.first().unwrap();
+        let last = nodes.last().unwrap();
+        for &(node_idx, coord) in [first, last] {
+            let (x, y) = cell(coord);
+            let close_to_other = (-1..=1)
+                .flat_map(|dx| (-1..=1).map(move |dy| (x + dx, y + dy)))
+                .flat_map(|c| grid.get(&c).into_iter().flatten())
+                .filter(|&&(other, _)| other != pos)
+                .any(|&(other, seg)| {
+                    let other_nodes = &highways[other].1;
+                    let connected = other_nodes.iter().any(|(idx, _)| *idx == node_idx);
+                    let segment = (other_nodes[seg].1, other_nodes[seg + 1].1);
+                    !connected && distance_to_segment(coord, segment) <= tolerance
+                });
+            if close_to_other {
+                issues.push(Issue {
+                    check: "unconnected-highway",
+                    way_idx: *way_idx,
+                    location: coord,
+                });
+            }
+        }
+    }
+}
+
+/// Runs QA checks and outputs issues as GeoJSON
+#[derive(Debug, Parser)]
+#[clap(name = "qa-checks")]
+struct Args {
+    /// osmflat archive
+    osmflat_archive: PathBuf,
+
+    /// GeoJSON filename to output
+    #[clap(long, short = 'o')]
+    output: PathBuf,
+
+    /// maximal distance in meters of a highway end to another highway to be
+    /// reported as unconnected
+    #[clap(long, default_value = "1.0")]
+    tolerance: f64,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let archive = Osm::open(FileResourceStorage::new(args.osmflat_archive))?;
+
+    let mut issues = Vec::new();
+    for (way_idx, way) in archive.ways().iter().enumerate() {
+        let nodes = way_nodes(&archive, way_idx);
+        if way.refs().end - way.refs().start < 2 {
+            let location = nodes
+                .as_ref()
+                .and_then(|nodes| nodes.first())
+                .map_or((0.0, 0.0), |(_, c)| *c);
+            issues.push(Issue {
+                check: "short-way",
+                way_idx,
+                location,
+            });
+            continue;
+        }
+
+        if find_tag(&archive, way.tags(), b"building").is_some() {
+            let ring: Vec<Coord> = match nodes {
+                Some(nodes) => nodes.into_iter().map(|(_, c)| c).collect(),
+                None => continue,
+            };
+            if ring.first() == ring.last() {
+                if let Some(location) = self_intersection(&ring) {
+                    issues.push(Issue {
+                        check: "self-intersecting-building",
+                        way_idx,
+                        location,
+                    });
+                }
+            }
+        }
+    }
+    check_unconnected_highways(&archive, args.tolerance, &mut issues);
+
+    let way_ids = archive.ids().map(|ids| ids.ways());
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    let features: Vec<Value> = issues
+        .iter()
+        .map(|issue| {
+            *counts.entry(issue.check).or_default() += 1;
+            let way = way_ids.map(|ids| ids[issue.way_idx].signed_value());
+            json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "Point",
+                    "coordinates": [issue.location.0, issue.location.1],
+                },
+                "properties": {
+                    "check": issue.check,
+                    "way_idx": issue.way_idx,
+                    "way_id": way,
+                },
+            })
+        })
+        .collect();
+
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort();
+    for (check, count) in counts {
+        eprintln!("{check}: {count}");
+    }
+
+    let collection = json!({
+        "type": "FeatureCollection",
+        "features": features,
+    });
+    serde_json::to_writer(BufWriter::new(File::create(args.output)?), &collection)?;
+    Ok(())
+}
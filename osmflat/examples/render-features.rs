@@ -1,4 +1,4 @@
-//! Renders selected features from the input archive as svg.
+//! Renders selected features from the input archive as svg or png.
 //!
 //! For supported features check `Category` enum and `classify` function.
 //!
@@ -6,6 +6,15 @@
 //! then produce polylines styled based on the category, cf. `render_svg`
 //! function. The coordinates are in lon, lat.
 //!
+//! Multipolygons (parks and lakes) are assembled from their `outer` and
+//! `inner` member ways into closed rings, cf. `assemble_rings` function, and
+//! rendered as paths with the `evenodd` fill rule, such that inner rings are
+//! cut out as holes (e.g. islands in lakes).
+//!
+//! If the output filename has the extension `png`, the features are rasterized
+//! instead, cf. `render_png` function: roads and rivers are drawn as lines
+//! (rivers with their width), parks and lakes are filled polygons.
+//!
 //! Inside of svg we just use the coordinates as is (except for swapped x/y
 //! axes), plus we apply a transformation to adjust the coordinates to the
 //! viewport. Obviously, it is slower the render such svg on the screen.
@@ -24,7 +33,8 @@ use svg::{node::element, Document};
 
 use std::f64;
 use std::fmt::Write;
-use std::io;
+use std::fs::File;
+use std::io::{self, BufWriter};
 use std::ops::Range;
 use std::path::PathBuf;
 use std::str;
@@ -94,6 +104,58 @@ impl Polyline {
             }))
         }
     }
+
+    /// Returns the coordinates of each part of the polyline separately.
+    fn into_parts(self, archive: &Osm) -> Option<Vec<Vec<GeoCoord>>> {
+        let nodes_index = archive.nodes_index();
+        let nodes = archive.nodes();
+        let scale = archive.header().coord_scale();
+        self.inner
+            .into_iter()
+            .map(|range| {
+                range
+                    .map(|idx| {
+                        let node = &nodes[nodes_index[idx as usize].value()? as usize];
+                        Some(GeoCoord::from_node(node, scale))
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Returns the closed rings formed by the parts of the polyline.
+    fn into_rings(self, archive: &Osm) -> Option<Vec<Vec<GeoCoord>>> {
+        self.into_parts(archive).map(assemble_rings)
+    }
+}
+
+/// Stitches line strings into rings by joining them at their endpoints.
+///
+/// Line strings which cannot be closed are returned as they are.
+fn assemble_rings(mut parts: Vec<Vec<GeoCoord>>) -> Vec<Vec<GeoCoord>> {
+    let mut rings = Vec::new();
+    while let Some(mut ring) = parts.pop() {
+        while let (Some(&first), Some(&last)) = (ring.first(), ring.last()) {
+            if ring.len() > 1 && first == last {
+                break;
+            }
+            let pos = parts
+                .iter()
+                .position(|p| p.first() == Some(&last) || p.last() == Some(&last));
+            match pos {
+                Some(pos) => {
+                    let mut part = parts.swap_remove(pos);
+                    if part.first() != Some(&last) {
+                        part.reverse();
+                    }
+                    ring.extend(part.into_iter().skip(1));
+                }
+                None => break,
+            }
+        }
+        rings.push(ring);
+    }
+    rings
 }
 
 /// Categories of features we support in this renderer.
@@ -135,10 +197,15 @@ fn multipolygon_into_polyline(archive: &Osm, idx: usize) -> Option<Polyline> {
     let strings = archive.stringtable();
     let ways = archive.ways();
 
+    // outer and inner rings are not distinguished: holes are produced by
+    // the evenodd fill rule
     let inner: Option<SmallVec<[Range<u64>; 4]>> = members
         .filter_map(|m| match m {
             RelationMembersRef::WayMember(way_member)
-                if strings.substring(way_member.role_idx() as usize) == Ok("outer") =>
+                if matches!(
+                    strings.substring(way_member.role_idx() as usize),
+                    Ok("outer") | Ok("inner")
+                ) =>
             {
                 Some(way_member.way_idx().map(|idx| ways[idx as usize].refs()))
             }
@@ -251,7 +318,8 @@ where
     let mut park_group = element::Group::new()
         .set("stroke", "#3D9970")
         .set("fill", "#3D9970")
-        .set("fill-opacity", 0.3);
+        .set("fill-opacity", 0.3)
+        .set("fill-rule", "evenodd");
     let mut river_group = element::Group::new()
         .set("stroke", "#0074D9")
         .set("fill", "none")
@@ -259,7 +327,8 @@ where
     let mut lake_group = element::Group::new()
         .set("stroke", "#0074D9")
         .set("fill", "#0074D9")
-        .set("fill-opacity", 0.3);
+        .set("fill-opacity", 0.3)
+        .set("fill-rule", "evenodd");
 
     let mut min_coord = GeoCoord {
         lat: f64::MAX,
@@ -271,35 +340,58 @@ where
     };
 
     let mut points = String::new(); // reuse string buffer inside the for-loop
+    let mut extend = |coord: GeoCoord| {
+        min_coord = min_coord.min(coord);
+        max_coord = max_coord.max(coord);
+    };
     for (poly, cat) in classified_polylines {
         points.clear();
-        let poly_iter = match poly.into_iter(archive) {
-            Some(x) => x,
-            None => continue,
-        };
-        for coord in poly_iter {
-            // collect extent
-            min_coord = min_coord.min(coord);
-            max_coord = max_coord.max(coord);
-            // accumulate polyline points
-            write!(&mut points, "{:.5},{:.5} ", coord.lon, coord.lat)
-                .expect("failed to write coordinates");
+        match cat {
+            Category::Road | Category::River(_) => {
+                let poly_iter = match poly.into_iter(archive) {
+                    Some(x) => x,
+                    None => continue,
+                };
+                for coord in poly_iter {
+                    // collect extent
+                    extend(coord);
+                    // accumulate polyline points
+                    write!(&mut points, "{:.5},{:.5} ", coord.lon, coord.lat)
+                        .expect("failed to write coordinates");
+                }
+            }
+            Category::Park | Category::Water => {
+                let rings = match poly.into_rings(archive) {
+                    Some(x) => x,
+                    None => continue,
+                };
+                // accumulate path data with one closed subpath per ring
+                for ring in rings {
+                    for (i, coord) in ring.into_iter().enumerate() {
+                        extend(coord);
+                        let cmd = if i == 0 { 'M' } else { 'L' };
+                        write!(&mut points, "{}{:.5},{:.5} ", cmd, coord.lon, coord.lat)
+                            .expect("failed to write coordinates");
+                    }
+                    points.push_str("Z ");
+                }
+            }
         }
 
-        let polyline = element::Polyline::new().set("points", &points[..]);
-
         match cat {
             Category::Road => {
-                road_group = road_group.add(polyline);
+                road_group = road_group.add(element::Polyline::new().set("points", &points[..]));
             }
             Category::River(width) => {
-                river_group = river_group.add(polyline).set("stroke-width", width);
+                river_group = river_group
+                    .add(element::Polyline::new().set("points", &points[..]))
+                    .set("stroke-width", width);
             }
             Category::Park => {
-                park_group = park_group.add(polyline);
+                park_group = park_group.add(element::Path::new().set("d", &points[..]));
             }
             Category::Water => {
-                lake_group = lake_group.add(polyline);
+                lake_group = lake_group.add(element::Path::new().set("d", &points[..]));
             }
         }
     }
@@ -331,7 +423,7 @@ where
             opacity: 0.3;
         }
 
-        polyline {
+        polyline, path {
             vector-effect: non-scaling-stroke;
         }
     "#,
@@ -346,14 +438,194 @@ where
     svg::save(output, &document)
 }
 
-/// render map features as a SVG
+/// RGB image with alpha blending.
+struct Canvas {
+    w: u32,
+    h: u32,
+    data: Vec<u8>,
+}
+
+type Color = [u8; 3];
+
+impl Canvas {
+    fn new(w: u32, h: u32) -> Self {
+        Self {
+            w,
+            h,
+            data: vec![255; (w * h * 3) as usize],
+        }
+    }
+
+    fn blend(&mut self, x: i32, y: i32, color: Color, alpha: f64) {
+        if x < 0 || y < 0 || x >= self.w as i32 || y >= self.h as i32 {
+            return;
+        }
+        let pos = ((y as u32 * self.w + x as u32) * 3) as usize;
+        for (c, value) in self.data[pos..pos + 3].iter_mut().zip(color) {
+            *c = (f64::from(*c) * (1.0 - alpha) + f64::from(value) * alpha) as u8;
+        }
+    }
+
+    /// Draws a line with the given width by stamping squares along the
+    /// Bresenham line.
+    fn line(&mut self, (x0, y0): (i32, i32), (x1, y1): (i32, i32), width: u32, color: Color) {
+        let r = (width as i32 - 1) / 2;
+        let (dx, sx) = ((x1 - x0).abs(), if x0 < x1 { 1 } else { -1 });
+        let (dy, sy) = (-(y1 - y0).abs(), if y0 < y1 { 1 } else { -1 });
+        let (mut x, mut y, mut err) = (x0, y0, dx + dy);
+        loop {
+            for ox in -r..=r {
+                for oy in -r..=r {
+                    self.blend(x + ox, y + oy, color, 1.0);
+                }
+            }
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// Fills a polygon given by its rings with the even-odd rule.
+    ///
+    /// Rings do not need to be closed explicitly and may consist of several
+    /// parts: only the edges between consecutive points of each part are
+    /// considered.
+    fn fill(&mut self, parts: &[Vec<(f64, f64)>], color: Color, alpha: f64) {
+        let edges: Vec<_> = parts
+            .iter()
+            .flat_map(|part| part.windows(2).map(|w| (w[0], w[1])))
+            .collect();
+        let (min_y, max_y) = edges
+            .iter()
+            .flat_map(|&(a, b)| [a.1, b.1])
+            .fold((f64::MAX, f64::MIN), |(min, max), y| {
+                (min.min(y), max.max(y))
+            });
+        let min_y = min_y.max(0.0) as i32;
+        let max_y = max_y.min(f64::from(self.h)) as i32;
+
+        let mut xs = Vec::new();
+        for y in min_y..max_y {
+            let yc = f64::from(y) + 0.5; // sample at the pixel center
+            xs.clear();
+            for &((x0, y0), (x1, y1)) in &edges {
+                if (y0 <= yc) != (y1 <= yc) {
+                    xs.push(x0 + (yc - y0) / (y1 - y0) * (x1 - x0));
+                }
+            }
+            xs.sort_by(|a, b| a.total_cmp(b));
+            for span in xs.chunks_exact(2) {
+                for x in span[0].round() as i32..span[1].round() as i32 {
+                    self.blend(x, y, color, alpha);
+                }
+            }
+        }
+    }
+}
+
+/// Renders png from classified polylines.
+///
+/// In contrast to `render_svg`, the polylines have to be provided twice: first
+/// for computing the extent of the image, then for drawing.
+fn render_png<P, F>(
+    archive: &Osm,
+    classified_polylines: F,
+    output: PathBuf,
+    width: u32,
+    height: u32,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: Fn() -> P,
+    P: Iterator<Item = (Polyline, Category)>,
+{
+    const ROAD_COLOR: Color = [0x00, 0x1F, 0x3F];
+    const PARK_COLOR: Color = [0x3D, 0x99, 0x70];
+    const WATER_COLOR: Color = [0x00, 0x74, 0xD9];
+
+    let mut min_coord = GeoCoord {
+        lat: f64::MAX,
+        lon: f64::MAX,
+    };
+    let mut max_coord = GeoCoord {
+        lat: f64::MIN,
+        lon: f64::MIN,
+    };
+    for (poly, _) in classified_polylines() {
+        for coord in poly.into_iter(archive).into_iter().flatten() {
+            min_coord = min_coord.min(coord);
+            max_coord = max_coord.max(coord);
+        }
+    }
+
+    let (w, h) = (f64::from(width - 1), f64::from(height - 1));
+    let transform = |coord: &GeoCoord| {
+        (
+            (coord.lon - min_coord.lon) * w / (max_coord.lon - min_coord.lon),
+            (max_coord.lat - coord.lat) * h / (max_coord.lat - min_coord.lat),
+        )
+    };
+
+    // polygons are drawn first, such that lines are drawn on top of them
+    let mut canvas = Canvas::new(width, height);
+    let mut lines = Vec::new();
+    for (poly, cat) in classified_polylines() {
+        let parts = match cat {
+            Category::Park | Category::Water => poly.into_rings(archive),
+            Category::Road | Category::River(_) => poly.into_parts(archive),
+        };
+        let parts = match parts {
+            Some(x) => x,
+            None => continue,
+        };
+        let parts: Vec<Vec<_>> = parts
+            .iter()
+            .map(|part| part.iter().map(transform).collect())
+            .collect();
+        match cat {
+            Category::Park => canvas.fill(&parts, PARK_COLOR, 0.3),
+            Category::Water => canvas.fill(&parts, WATER_COLOR, 0.3),
+            Category::Road => lines.push((parts, 1, ROAD_COLOR)),
+            Category::River(width) => lines.push((parts, width.clamp(1, 5), WATER_COLOR)),
+        }
+    }
+    for (parts, width, color) in lines {
+        for part in parts {
+            let part: Vec<_> = part
+                .into_iter()
+                .map(|(x, y)| (x as i32, y as i32))
+                .collect();
+            for w in part.windows(2) {
+                canvas.line(w[0], w[1], width, color);
+            }
+        }
+    }
+
+    let buf = BufWriter::new(File::create(output)?);
+    let mut encoder = png::Encoder::new(buf, canvas.w, canvas.h);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&canvas.data)?;
+    Ok(())
+}
+
+/// render map features as a SVG or PNG
 #[derive(Debug, Parser)]
 #[clap(name = "render-features")]
 struct Args {
     /// osmflat archive
     osmflat_archive: PathBuf,
 
-    /// SVG filename to output
+    /// SVG or PNG filename to output (format is derived from the extension)
     #[clap(long, short = 'o')]
     output: PathBuf,
 
@@ -372,18 +644,29 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let storage = FileResourceStorage::new(args.osmflat_archive);
     let archive = Osm::open(storage)?;
 
-    let features = classify(&archive);
-    let archive_inner = archive.clone();
-    let classified_polylines = features.filter_map(move |f| {
-        let cat = f.cat;
-        f.into_polyline(&archive_inner).map(|p| (p, cat))
-    });
-    render_svg(
-        &archive,
-        classified_polylines,
-        args.output,
-        args.width,
-        args.height,
-    )?;
+    let classified_polylines = || {
+        let archive_inner = archive.clone();
+        classify(&archive).filter_map(move |f| {
+            let cat = f.cat;
+            f.into_polyline(&archive_inner).map(|p| (p, cat))
+        })
+    };
+    if args.output.extension().is_some_and(|ext| ext == "png") {
+        render_png(
+            &archive,
+            classified_polylines,
+            args.output,
+            args.width,
+            args.height,
+        )?;
+    } else {
+        render_svg(
+            &archive,
+            classified_polylines(),
+            args.output,
+            args.width,
+            args.height,
+        )?;
+    }
     Ok(())
 }
@@ -1,18 +1,31 @@
 //! Renders all roads by using a simple Bresenham line algorithm.
 //!
+//! The image is split into tiles which are rasterized in parallel, each into
+//! its own buffer; the buffers are merged into the final image at the end.
+//! Roads are stroked with a width depending on their highway class. With
+//! supersampling, the tiles are rasterized at a multiple of the final
+//! resolution and then downsampled, which results in anti-aliased lines.
+//!
+//! Demonstrates
+//!
+//!  * parallel processing of ways with rayon
+//!  * accessing of nodes belonging to a way
+//!
 //! LICENSE
 //!
 //! The code in this example file is released into the Public Domain.
 
-use osmflat::{find_tag_by, FileResourceStorage, Node, Osm, Way};
+use osmflat::{find_tag, geometry, FileResourceStorage, Osm, Way};
 
 use clap::Parser;
 use itertools::Itertools;
+use rayon::prelude::*;
 
 use std::f64::consts::PI;
 use std::fs::File;
 use std::io::BufWriter;
 use std::path::PathBuf;
+use std::time::Instant;
 
 /// Geographic coordinates represented by (latitude, longitude).
 #[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
@@ -21,37 +34,6 @@ struct GeoCoord {
     lon: f64,
 }
 
-/// Convert osmflat Node into GeoCoord.
-impl GeoCoord {
-    fn from_node(node: &Node, coord_scale: i32) -> Self {
-        Self {
-            lat: node.lat() as f64 / coord_scale as f64,
-            lon: node.lon() as f64 / coord_scale as f64,
-        }
-    }
-}
-
-#[derive(Debug)]
-struct Image {
-    w: u32,
-    h: u32,
-    data: Vec<u8>,
-}
-
-impl Image {
-    fn new(w: u32, h: u32) -> Self {
-        Self {
-            w,
-            h,
-            data: vec![255; (w * h) as usize],
-        }
-    }
-
-    fn set_black(&mut self, x: u32, y: u32) {
-        self.data[(y * self.w + x) as usize] = 0;
-    }
-}
-
 fn compute_bounds(mut iter: impl Iterator<Item = GeoCoord>) -> (GeoCoord, GeoCoord) {
     let first_coord = iter.next().unwrap_or_default();
     iter.fold((first_coord, first_coord), |(min, max), coord| {
@@ -81,49 +63,28 @@ fn map_transform(
 }
 
 fn way_coords<'a>(archive: &'a Osm, way: &Way) -> Option<impl Iterator<Item = GeoCoord> + 'a> {
-    let nodes = archive.nodes();
-    let nodes_index = archive.nodes_index();
-    let path = way.refs().map(move |i| &nodes_index[i as usize]);
-    let scale = archive.header().coord_scale();
-    if path.clone().any(|node| node.value().is_none()) {
-        None
-    } else {
-        Some(
-            path.map(move |node| {
-                GeoCoord::from_node(&nodes[node.value().unwrap() as usize], scale)
-            }),
-        )
-    }
+    let coords = geometry::way_coords(archive, way)?;
+    Some(coords.map(|(lon, lat)| GeoCoord { lat, lon }))
 }
 
-fn way_filter(way: &Way, archive: &Osm) -> bool {
-    const UNWANTED_HIGHWAY_TYPES: [&[u8]; 9] = [
-        b"pedestrian\0",
-        b"steps\0",
-        b"footway\0",
-        b"construction\0",
-        b"bic\0",
-        b"cycleway\0",
-        b"layby\0",
-        b"bridleway\0",
-        b"path\0",
-    ];
-
-    // Filter all ways that do not have desirable highway tag.
-    find_tag_by(archive, way.tags(), |key_block, val_block| {
-        key_block.starts_with(b"highway\0")
-            && !UNWANTED_HIGHWAY_TYPES
-                .iter()
-                .any(|t| val_block.starts_with(t))
-    })
-    .is_some()
+/// Returns the stroke width in pixels of a road, or `None` if the way is not a
+/// road we want to render.
+fn road_width(archive: &Osm, way: &Way) -> Option<u32> {
+    let width = match find_tag(archive, way.tags(), b"highway")? {
+        b"motorway" | b"trunk" => 3,
+        b"motorway_link" | b"trunk_link" | b"primary" | b"secondary" => 2,
+        b"pedestrian" | b"steps" | b"footway" | b"construction" | b"bic" | b"cycleway"
+        | b"layby" | b"bridleway" | b"path" => return None,
+        _ => 1,
+    };
+    Some(width)
 }
 
-fn roads(archive: &Osm) -> impl Iterator<Item = &Way> {
-    archive
-        .ways()
-        .iter()
-        .filter(move |&way| way_filter(way, archive))
+fn roads(archive: &Osm) -> impl ParallelIterator<Item = (&Way, u32)> {
+    let ways = archive.ways();
+    (0..ways.len())
+        .into_par_iter()
+        .filter_map(move |idx| Some((&ways[idx], road_width(archive, &ways[idx])?)))
 }
 
 /// Bresenham's line algorithm
@@ -154,34 +115,183 @@ fn bresenham(mut x0: i32, mut y0: i32, x1: i32, y1: i32) -> impl Iterator<Item =
     })
 }
 
-fn render(archive: &Osm, width: u32) -> Image {
+/// Clips the line from `p0` to `p1` to the rectangle `(min, max)` with the
+/// Liang-Barsky algorithm.
+fn clip(
+    (p0, p1): ((i32, i32), (i32, i32)),
+    (min, max): ((i32, i32), (i32, i32)),
+) -> Option<((i32, i32), (i32, i32))> {
+    let (dx, dy) = (f64::from(p1.0 - p0.0), f64::from(p1.1 - p0.1));
+    let (mut t0, mut t1) = (0.0f64, 1.0f64);
+    for (p, q) in [
+        (-dx, p0.0 - min.0),
+        (dx, max.0 - p0.0),
+        (-dy, p0.1 - min.1),
+        (dy, max.1 - p0.1),
+    ] {
+        let q = f64::from(q);
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else if p < 0.0 {
+            t0 = t0.max(q / p);
+        } else {
+            t1 = t1.min(q / p);
+        }
+    }
+    let at = |t: f64| {
+        (
+            p0.0 + (t * dx).round() as i32,
+            p0.1 + (t * dy).round() as i32,
+        )
+    };
+    (t0 <= t1).then(|| (at(t0), at(t1)))
+}
+
+/// A line segment in supersampled raster coordinates.
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    from: (i32, i32),
+    to: (i32, i32),
+    /// Radius of the stroke in supersampled pixels
+    radius: i32,
+}
+
+/// A tile of the image rasterized into its own buffer.
+///
+/// Origin and size are in supersampled pixels.
+struct Tile {
+    x0: i32,
+    y0: i32,
+    w: u32,
+    h: u32,
+    /// Indexes of the segments touching this tile
+    segments: Vec<usize>,
+}
+
+impl Tile {
+    /// Rasterizes the segments of this tile and returns the downsampled
+    /// grayscale buffer.
+    fn render(&self, segments: &[Segment], supersampling: u32) -> Vec<u8> {
+        let mut covered = vec![false; (self.w * self.h) as usize];
+        for segment in self.segments.iter().map(|&idx| &segments[idx]) {
+            let r = segment.radius;
+            let bounds = (
+                (self.x0 - r, self.y0 - r),
+                (self.x0 + self.w as i32 + r, self.y0 + self.h as i32 + r),
+            );
+            let ((x0, y0), (x1, y1)) = match clip((segment.from, segment.to), bounds) {
+                Some(line) => line,
+                None => continue,
+            };
+            for (x, y) in bresenham(x0, y0, x1, y1).chain(std::iter::once((x1, y1))) {
+                // stamp a disc with the radius of the stroke
+                for dy in -r..=r {
+                    for dx in -r..=r {
+                        let (tx, ty) = (x + dx - self.x0, y + dy - self.y0);
+                        if dx * dx + dy * dy <= r * r
+                            && (0..self.w as i32).contains(&tx)
+                            && (0..self.h as i32).contains(&ty)
+                        {
+                            covered[(ty as u32 * self.w + tx as u32) as usize] = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        // downsample by averaging blocks of supersampling x supersampling pixels
+        let s = supersampling;
+        let (w, h) = (self.w / s, self.h / s);
+        let mut data = vec![255; (w * h) as usize];
+        for y in 0..h {
+            for x in 0..w {
+                let count = (0..s * s)
+                    .filter(|i| covered[((y * s + i / s) * self.w + x * s + i % s) as usize])
+                    .count() as u32;
+                data[(y * w + x) as usize] = (255 - 255 * count / (s * s)) as u8;
+            }
+        }
+        data
+    }
+}
+
+/// Renders all roads as grayscale image data; returns the data and the height
+/// of the image.
+fn render(archive: &Osm, width: u32, tile_size: u32, supersampling: u32) -> (Vec<u8>, u32) {
     // compute extent
-    let coords = roads(archive)
-        .filter_map(|way| way_coords(archive, way))
-        .flatten();
-    let (min, max) = compute_bounds(coords);
+    let bounds: Vec<_> = roads(archive)
+        .filter_map(|(way, _)| way_coords(archive, way).map(compute_bounds))
+        .collect();
+    let (min, max) = compute_bounds(bounds.into_iter().flat_map(|(min, max)| [min, max]));
 
     // compute ratio and height
     let ratio = (max.lat - min.lat) / (max.lon - min.lon) / (max.lat / 180. * PI).cos();
     let height = (f64::from(width) * ratio) as u32;
 
-    // create world -> raster transformation
-    let t = map_transform((width - 1, height - 1), (min, max));
+    // create world -> raster transformation in supersampled resolution
+    let (ss_width, ss_height) = (width * supersampling, height * supersampling);
+    let t = map_transform((ss_width - 1, ss_height - 1), (min, max));
+
+    // collect line segments
+    let segments: Vec<Segment> = roads(archive)
+        .filter_map(|(way, width)| {
+            let radius = (width * supersampling / 2) as i32;
+            let segments = way_coords(archive, way)?
+                .map(t)
+                .tuple_windows()
+                .map(|(from, to)| Segment { from, to, radius });
+            Some(segments.collect::<Vec<_>>())
+        })
+        .flatten_iter()
+        .collect();
 
-    // draw
-    let mut image = Image::new(width, height);
+    // assign segments to tiles
+    let ss_tile_size = tile_size * supersampling;
+    let (tiles_x, tiles_y) = (width.div_ceil(tile_size), height.div_ceil(tile_size));
+    let mut tiles: Vec<Tile> = (0..tiles_y)
+        .cartesian_product(0..tiles_x)
+        .map(|(ty, tx)| Tile {
+            x0: (tx * ss_tile_size) as i32,
+            y0: (ty * ss_tile_size) as i32,
+            w: ss_tile_size.min(ss_width - tx * ss_tile_size),
+            h: ss_tile_size.min(ss_height - ty * ss_tile_size),
+            segments: Vec::new(),
+        })
+        .collect();
+    for (idx, segment) in segments.iter().enumerate() {
+        let tile_range = |a: i32, b: i32, num_tiles: u32| {
+            let first = (a.min(b) - segment.radius).max(0) as u32 / ss_tile_size;
+            let last = (a.max(b) + segment.radius).max(0) as u32 / ss_tile_size;
+            first..=last.min(num_tiles - 1)
+        };
+        for ty in tile_range(segment.from.1, segment.to.1, tiles_y) {
+            for tx in tile_range(segment.from.0, segment.to.0, tiles_x) {
+                tiles[(ty * tiles_x + tx) as usize].segments.push(idx);
+            }
+        }
+    }
 
-    let line_segments = roads(archive)
-        .filter_map(|way| Some(way_coords(archive, way)?.map(t).tuple_windows()))
-        .flatten();
+    // rasterize tiles in parallel
+    let buffers: Vec<Vec<u8>> = tiles
+        .par_iter()
+        .map(|tile| tile.render(&segments, supersampling))
+        .collect();
 
-    for ((x0, y0), (x1, y1)) in line_segments {
-        for (x, y) in bresenham(x0, y0, x1, y1) {
-            image.set_black(x as u32, y as u32);
+    // merge tile buffers into the image
+    let mut data = vec![255; (width * height) as usize];
+    for (tile, buffer) in tiles.iter().zip(buffers) {
+        let x0 = tile.x0 as u32 / supersampling;
+        let y0 = tile.y0 as u32 / supersampling;
+        let w = (tile.w / supersampling) as usize;
+        for (row, line) in buffer.chunks_exact(w).enumerate() {
+            let pos = (y0 as usize + row) * width as usize + x0 as usize;
+            data[pos..pos + w].copy_from_slice(line);
         }
     }
 
-    image
+    (data, height)
 }
 
 /// Renders roads as a PNG
@@ -195,6 +305,12 @@ struct Args {
     /// width of the image (height is derived from ratio)
     #[clap(long, short = 'w', default_value = "4320")]
     width: u32,
+    /// supersampling factor used for anti-aliasing
+    #[clap(long, short = 's', default_value = "1", value_parser = ["1", "2", "4"])]
+    supersampling: String,
+    /// size of the tiles rendered in parallel in pixels
+    #[clap(long, default_value = "256")]
+    tile_size: u32,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -202,14 +318,17 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let archive = Osm::open(FileResourceStorage::new(args.input))?;
 
-    let image = render(&archive, args.width);
+    let supersampling = args.supersampling.parse()?;
+    let start = Instant::now();
+    let (data, height) = render(&archive, args.width, args.tile_size, supersampling);
+    eprintln!("Rendered in {:.2?}", start.elapsed());
 
     let buf = BufWriter::new(File::create(&args.output)?);
-    let mut encoder = png::Encoder::new(buf, image.w, image.h);
+    let mut encoder = png::Encoder::new(buf, args.width, height);
     encoder.set_color(png::ColorType::Grayscale);
     encoder.set_depth(png::BitDepth::Eight);
     let mut writer = encoder.write_header()?;
-    writer.write_image_data(&image.data[..])?;
+    writer.write_image_data(&data[..])?;
 
     Ok(())
 }
@@ -0,0 +1,321 @@
+//! Computes shortest routes by car between pairs of coordinates.
+//!
+//! First, the routing graph is extracted from all drivable highways. Every
+//! edge stores its length, its travel time derived from the `maxspeed` tag or
+//! from a default speed per highway class, and the way it belongs to; oneway
+//! streets are respected. Then, for each query, Dijkstra's algorithm computes
+//! the fastest (or shortest) path between the nodes closest to the given
+//! coordinates.
+//!
+//! A single query is given by `--from` and `--to`. Without them, queries are
+//! read from stdin as lines `from_lat,from_lon to_lat,to_lon`, so that the
+//! graph is built only once for many queries. For each route, its length, its
+//! travel time, and the OSM ids of the ways and nodes along it are printed.
+//!
+//! Demonstrates
+//!
+//!  * extraction of a routing graph via `nodes_index`
+//!  * tag-based edge weights
+//!  * shortest path computation
+//!  * reporting of OSM ids of entities
+//!
+//! LICENSE
+//!
+//! The code in this example file is released into the Public Domain.
+
+use clap::{Parser, ValueEnum};
+use osmflat::{find_tag, FileResourceStorage, Osm};
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::io::{self, BufRead};
+use std::path::PathBuf;
+use std::str;
+
+/// Earth's radius for WGS84 in meters
+const EARTH_RADIUS_IN_METERS: f64 = 6_372_797.560_856;
+
+/// Coordinates represented by (longitude, latitude).
+type Coord = (f64, f64);
+
+fn haversine_distance(c1: Coord, c2: Coord) -> f64 {
+    let mut lonh = ((c1.0 - c2.0).to_radians() * 0.5).sin();
+    lonh *= lonh;
+    let mut lath = ((c1.1 - c2.1).to_radians() * 0.5).sin();
+    lath *= lath;
+    let tmp = c1.1.to_radians().cos() * c2.1.to_radians().cos();
+    2.0 * EARTH_RADIUS_IN_METERS * (lath + tmp * lonh).sqrt().asin()
+}
+
+/// Default speed in km/h for drivable highway classes.
+fn default_speed(highway: &[u8]) -> Option<f64> {
+    let speed = match highway {
+        b"motorway" | b"motorway_link" => 120.0,
+        b"trunk" | b"trunk_link" => 100.0,
+        b"primary" | b"primary_link" => 80.0,
+        b"secondary" | b"secondary_link" => 60.0,
+        b"tertiary" | b"tertiary_link" => 50.0,
+        b"unclassified" => 40.0,
+        b"residential" => 30.0,
+        b"service" => 20.0,
+        b"living_street" => 10.0,
+        _ => return None,
+    };
+    Some(speed)
+}
+
+/// Parses a `maxspeed` value in km/h (or mph with suffix).
+fn parse_maxspeed(value: &[u8]) -> Option<f64> {
+    let value = str::from_utf8(value).ok()?.trim();
+    match value.strip_suffix("mph") {
+        Some(mph) => Some(mph.trim().parse::<f64>().ok()? * 1.609_344),
+        None => value.parse().ok(),
+    }
+}
+
+/// Quantity minimized by the route.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Metric {
+    /// Travel time
+    Time,
+    /// Length
+    Distance,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    to: u64,
+    way_idx: usize,
+    meters: f64,
+    seconds: f64,
+}
+
+impl Edge {
+    fn weight(&self, metric: Metric) -> f64 {
+        match metric {
+            Metric::Time => self.seconds,
+            Metric::Distance => self.meters,
+        }
+    }
+}
+
+/// Routing graph of the drivable road network.
+#[derive(Default)]
+struct Graph {
+    /// Adjacency list: node idx -> outgoing edges
+    edges: HashMap<u64, Vec<Edge>>,
+    coords: HashMap<u64, Coord>,
+}
+
+impl Graph {
+    fn new(archive: &Osm) -> Self {
+        let nodes = archive.nodes();
+        let nodes_index = archive.nodes_index();
+        let scale = f64::from(archive.header().coord_scale());
+
+        let mut graph = Graph::default();
+        for (way_idx, way) in archive.ways().iter().enumerate() {
+            let tags = way.tags();
+            let speed = match find_tag(archive, tags.clone(), b"highway").and_then(default_speed) {
+                Some(speed) => find_tag(archive, tags.clone(), b"maxspeed")
+                    .and_then(parse_maxspeed)
+                    .unwrap_or(speed),
+                None => continue,
+            };
+            let oneway = find_tag(archive, tags.clone(), b"oneway");
+            let (forward, backward) = match oneway {
+                Some(b"yes") | Some(b"1") | Some(b"true") => (true, false),
+                Some(b"-1") => (false, true),
+                _ => (true, true),
+            };
+
+            // nodes missing in the archive (e.g. cut off by a bounding box)
+            // are skipped, connecting their neighbors directly
+            let path: Vec<u64> = way
+                .refs()
+                .filter_map(|idx| nodes_index[idx as usize].value())
+                .collect();
+            for &idx in &path {
+                graph.coords.entry(idx).or_insert_with(|| {
+                    let node = &nodes[idx as usize];
+                    (f64::from(node.lon()) / scale, f64::from(node.lat()) / scale)
+                });
+            }
+            for w in path.windows(2) {
+                let meters = haversine_distance(graph.coords[&w[0]], graph.coords[&w[1]]);
+                let seconds = meters / (speed / 3.6);
+                let edge = |to| Edge {
+                    to,
+                    way_idx,
+                    meters,
+                    seconds,
+                };
+                if forward {
+                    graph.edges.entry(w[0]).or_default().push(edge(w[1]));
+                }
+                if backward {
+                    graph.edges.entry(w[1]).or_default().push(edge(w[0]));
+                }
+            }
+        }
+        graph
+    }
+
+    fn closest_node(&self, c: Coord) -> Option<u64> {
+        self.coords
+            .iter()
+            .min_by(|a, b| haversine_distance(*a.1, c).total_cmp(&haversine_distance(*b.1, c)))
+            .map(|(idx, _)| *idx)
+    }
+
+    /// Computes the path from `start` to `target` minimizing `metric`.
+    ///
+    /// Returns the edges along the path, or `None` if `target` is not
+    /// reachable from `start`.
+    fn dijkstra(&self, start: u64, target: u64, metric: Metric) -> Option<Vec<Edge>> {
+        // node idx -> incoming edge on the best path, as (node idx, edge number)
+        let mut settled: HashMap<u64, Option<(u64, usize)>> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+        // f64 is not Ord, but non-negative floats compare like their bits
+        queue.push(Reverse((0f64.to_bits(), start, None)));
+        while let Some(Reverse((weight, idx, incoming))) = queue.pop() {
+            if settled.contains_key(&idx) {
+                continue;
+            }
+            settled.insert(idx, incoming);
+            if idx == target {
+                break;
+            }
+            let weight = f64::from_bits(weight);
+            for (i, edge) in self.edges.get(&idx).into_iter().flatten().enumerate() {
+                if !settled.contains_key(&edge.to) {
+                    let next_weight = weight + edge.weight(metric);
+                    queue.push(Reverse((next_weight.to_bits(), edge.to, Some((idx, i)))));
+                }
+            }
+        }
+
+        settled.get(&target)?;
+        let mut path = Vec::new();
+        let mut idx = target;
+        while let Some(&Some((from, i))) = settled.get(&idx) {
+            path.push(self.edges[&from][i]);
+            idx = from;
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+/// Computes shortest routes by car and prints the ids of their ways and nodes
+#[derive(Debug, Parser)]
+#[clap(name = "routing")]
+struct Args {
+    /// osmflat archive
+    osmflat_archive: PathBuf,
+
+    /// start coordinate as `lat,lon`; without `--from` and `--to`, queries
+    /// are read from stdin
+    #[clap(long, requires = "to")]
+    from: Option<String>,
+
+    /// target coordinate as `lat,lon`
+    #[clap(long, requires = "from")]
+    to: Option<String>,
+
+    /// quantity to minimize
+    #[clap(long, value_enum, default_value = "time")]
+    metric: Metric,
+}
+
+fn parse_coord(s: &str) -> Result<Coord, Box<dyn std::error::Error>> {
+    let (lat, lon) = s.split_once(',').ok_or("expected lat,lon")?;
+    Ok((lon.trim().parse()?, lat.trim().parse()?))
+}
+
+/// Computes and prints the route between `from` and `to`.
+fn route(archive: &Osm, graph: &Graph, from: Coord, to: Coord, metric: Metric) {
+    let (start, target) = match (graph.closest_node(from), graph.closest_node(to)) {
+        (Some(start), Some(target)) => (start, target),
+        _ => return println!("no drivable roads"),
+    };
+    let path = match graph.dijkstra(start, target, metric) {
+        Some(path) => path,
+        None => return println!("no route found"),
+    };
+
+    let ids = archive.ids();
+    let node_id = |idx: u64| match ids {
+        Some(ids) => ids.nodes()[idx as usize].signed_value().to_string(),
+        None => format!("#{idx}"),
+    };
+    let way_id = |idx: usize| match ids {
+        Some(ids) => ids.ways()[idx].signed_value().to_string(),
+        None => format!("#{idx}"),
+    };
+
+    let meters: f64 = path.iter().map(|e| e.meters).sum();
+    let seconds: f64 = path.iter().map(|e| e.seconds).sum();
+    println!("distance: {:.0} m, time: {:.1} min", meters, seconds / 60.0);
+
+    let mut ways: Vec<usize> = path.iter().map(|e| e.way_idx).collect();
+    ways.dedup();
+    let ways: Vec<_> = ways
+        .into_iter()
+        .map(|idx| {
+            let way = &archive.ways()[idx];
+            match find_tag(archive, way.tags(), b"name") {
+                Some(name) => format!("{} ({})", way_id(idx), String::from_utf8_lossy(name)),
+                None => way_id(idx),
+            }
+        })
+        .collect();
+    println!("ways: {}", ways.join(", "));
+
+    let nodes: Vec<_> = std::iter::once(start)
+        .chain(path.iter().map(|e| e.to))
+        .map(node_id)
+        .collect();
+    println!("nodes: {}", nodes.join(" "));
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let archive = Osm::open(FileResourceStorage::new(args.osmflat_archive))?;
+    let graph = Graph::new(&archive);
+    eprintln!(
+        "Graph with {} nodes and {} edges",
+        graph.coords.len(),
+        graph.edges.values().map(Vec::len).sum::<usize>()
+    );
+
+    if let (Some(from), Some(to)) = (&args.from, &args.to) {
+        route(
+            &archive,
+            &graph,
+            parse_coord(from)?,
+            parse_coord(to)?,
+            args.metric,
+        );
+        return Ok(());
+    }
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (from, to) = line
+            .trim()
+            .split_once(char::is_whitespace)
+            .ok_or("expected `from_lat,from_lon to_lat,to_lon`")?;
+        route(
+            &archive,
+            &graph,
+            parse_coord(from)?,
+            parse_coord(to.trim())?,
+            args.metric,
+        );
+    }
+    Ok(())
+}
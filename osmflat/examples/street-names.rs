@@ -0,0 +1,175 @@
+//! Computes the most common street names per district and outputs them as
+//! JSON.
+//!
+//! Districts are administrative boundaries of a given admin level. A street
+//! (a way tagged with `highway=*` and `name=*`) belongs to the district which
+//! contains its midpoint.
+//!
+//! Demonstrates
+//!
+//!  * assembly of polygons from relation members with `osmflat::geometry`
+//!  * point in polygon tests
+//!  * combining spatial and tag queries
+//!
+//! LICENSE
+//!
+//! The code in this example file is released into the Public Domain.
+
+use clap::Parser;
+use osmflat::{find_tag, geometry, has_tag, FileResourceStorage, Osm};
+use serde::Serialize;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str;
+
+/// Coordinates represented by (longitude, latitude).
+type Coord = (f64, f64);
+
+fn node_coord(archive: &Osm, node_idx: u64) -> Coord {
+    archive.node_coord(node_idx as usize)
+}
+
+/// Point in polygon test with the ray casting algorithm.
+fn ring_contains(ring: &[Coord], (x, y): Coord) -> bool {
+    let mut inside = false;
+    for w in ring.windows(2) {
+        let ((x0, y0), (x1, y1)) = (w[0], w[1]);
+        if (y0 > y) != (y1 > y) && x < (x1 - x0) * (y - y0) / (y1 - y0) + x0 {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Area of a district given by its outer and inner rings.
+struct District {
+    name: String,
+    min: Coord,
+    max: Coord,
+    outer: Vec<Vec<Coord>>,
+    inner: Vec<Vec<Coord>>,
+}
+
+impl District {
+    fn new(archive: &Osm, relation_idx: usize, name: String) -> Option<Self> {
+        let polygons = geometry::multipolygon(archive, relation_idx).ok()?;
+        let (outer, inner): (Vec<_>, Vec<_>) =
+            polygons.into_iter().map(|p| (p.outer, p.inner)).unzip();
+        let inner = inner.into_iter().flatten().collect();
+        let (min, max) = outer.iter().flatten().fold(
+            ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN)),
+            |(min, max), c| {
+                (
+                    (min.0.min(c.0), min.1.min(c.1)),
+                    (max.0.max(c.0), max.1.max(c.1)),
+                )
+            },
+        );
+        Some(Self {
+            name,
+            min,
+            max,
+            outer,
+            inner,
+        })
+    }
+
+    fn contains(&self, c: Coord) -> bool {
+        self.min.0 <= c.0
+            && c.0 <= self.max.0
+            && self.min.1 <= c.1
+            && c.1 <= self.max.1
+            && self.outer.iter().any(|r| ring_contains(r, c))
+            && !self.inner.iter().any(|r| ring_contains(r, c))
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Street<'a> {
+    name: &'a str,
+    count: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct DistrictStreets<'a> {
+    district: &'a str,
+    streets: Vec<Street<'a>>,
+}
+
+/// Outputs the most common street names per district as JSON
+#[derive(Debug, Parser)]
+#[clap(name = "street-names")]
+struct Args {
+    /// osmflat archive
+    osmflat_archive: PathBuf,
+
+    /// admin level of the districts
+    #[clap(long, default_value = "8")]
+    admin_level: String,
+
+    /// number of street names to output per district
+    #[clap(long, default_value = "10")]
+    top: usize,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let archive = Osm::open(FileResourceStorage::new(args.osmflat_archive))?;
+
+    let districts: Vec<District> = archive
+        .relations()
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| {
+            has_tag(&archive, r.tags(), b"boundary", b"administrative")
+                && find_tag(&archive, r.tags(), b"admin_level") == Some(args.admin_level.as_bytes())
+        })
+        .filter_map(|(idx, r)| {
+            let name = find_tag(&archive, r.tags(), b"name").unwrap_or(b"<unnamed>");
+            District::new(&archive, idx, String::from_utf8_lossy(name).into())
+        })
+        .collect();
+
+    let nodes_index = archive.nodes_index();
+    let mut counts: Vec<HashMap<&str, usize>> = vec![HashMap::new(); districts.len()];
+    for way in archive.ways() {
+        if find_tag(&archive, way.tags(), b"highway").is_none() {
+            continue;
+        }
+        let name = match find_tag(&archive, way.tags(), b"name").map(str::from_utf8) {
+            Some(Ok(name)) => name,
+            _ => continue,
+        };
+        let refs = way.refs();
+        let mid = refs.start + (refs.end - refs.start) / 2;
+        let midpoint = match nodes_index.get(mid as usize).and_then(|idx| idx.value()) {
+            Some(node_idx) => node_coord(&archive, node_idx),
+            None => continue,
+        };
+        if let Some(pos) = districts.iter().position(|d| d.contains(midpoint)) {
+            *counts[pos].entry(name).or_default() += 1;
+        }
+    }
+
+    let result: Vec<DistrictStreets> = districts
+        .iter()
+        .zip(counts)
+        .map(|(district, counts)| {
+            let mut streets: Vec<Street> = counts
+                .into_iter()
+                .map(|(name, count)| Street { name, count })
+                .collect();
+            streets.sort_by(|a, b| b.count.cmp(&a.count).then(a.name.cmp(b.name)));
+            streets.truncate(args.top);
+            DistrictStreets {
+                district: &district.name,
+                streets,
+            }
+        })
+        .collect();
+
+    serde_json::to_writer_pretty(std::io::stdout().lock(), &result)?;
+    Ok(())
+}
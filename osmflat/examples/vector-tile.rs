@@ -0,0 +1,573 @@
+//! Renders a single Mapbox Vector Tile (MVT) for the given `z/x/y` tile.
+//!
+//! The tile contains the layers `roads`, `water`, `buildings` and `pois`. Ways
+//! and nodes are selected by checking whether they intersect the (buffered)
+//! tile bounds; geometries are then projected to tile coordinates, clipped to
+//! the buffered tile, and simplified with the Douglas-Peucker algorithm.
+//!
+//! The protobuf encoding of the tile is done by hand, cf. the
+//! [vector tile specification].
+//!
+//! Demonstrates
+//!
+//!  * spatial selection of nodes and ways
+//!  * clipping of lines and polygons
+//!  * simplification of geometries
+//!
+//! LICENSE
+//!
+//! The code in this example file is released into the Public Domain.
+//!
+//! [vector tile specification]: https://github.com/mapbox/vector-tile-spec/tree/master/2.1
+
+use clap::Parser;
+use osmflat::{find_tag, iter_tags, FileResourceStorage, Osm, Way};
+
+use std::collections::HashMap;
+use std::f64::consts::PI;
+use std::path::PathBuf;
+
+/// Extent of a tile in tile coordinates.
+const EXTENT: i32 = 4096;
+/// Buffer around the tile in tile coordinates.
+const BUFFER: i32 = 64;
+/// Tags which are copied as properties to features.
+const PROPERTIES: [&[u8]; 6] = [
+    b"name",
+    b"highway",
+    b"waterway",
+    b"building",
+    b"amenity",
+    b"shop",
+];
+
+/// Point in tile coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Point {
+    x: f64,
+    y: f64,
+}
+
+/// Transforms geographic coordinates to coordinates of a single tile.
+struct TileTransform {
+    z: u32,
+    x: u32,
+    y: u32,
+}
+
+impl TileTransform {
+    fn project(&self, lat: f64, lon: f64) -> Point {
+        let n = f64::from(1u32 << self.z);
+        let lat = lat.to_radians();
+        let x = (lon + 180.0) / 360.0 * n;
+        let y = (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / PI) / 2.0 * n;
+        Point {
+            x: (x - f64::from(self.x)) * f64::from(EXTENT),
+            y: (y - f64::from(self.y)) * f64::from(EXTENT),
+        }
+    }
+}
+
+/// Axis aligned rectangle used for clipping.
+#[derive(Debug, Clone, Copy)]
+struct Rect {
+    min: f64,
+    max: f64,
+}
+
+impl Rect {
+    fn contains(&self, p: Point) -> bool {
+        self.min <= p.x && p.x <= self.max && self.min <= p.y && p.y <= self.max
+    }
+
+    fn intersects(&self, points: &[Point]) -> bool {
+        let (mut min_x, mut min_y) = (f64::MAX, f64::MAX);
+        let (mut max_x, mut max_y) = (f64::MIN, f64::MIN);
+        for p in points {
+            min_x = min_x.min(p.x);
+            min_y = min_y.min(p.y);
+            max_x = max_x.max(p.x);
+            max_y = max_y.max(p.y);
+        }
+        min_x <= self.max && self.min <= max_x && min_y <= self.max && self.min <= max_y
+    }
+}
+
+/// Clips a line segment with the Liang-Barsky algorithm.
+fn clip_segment(rect: Rect, a: Point, b: Point) -> Option<(Point, Point)> {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let mut t0: f64 = 0.0;
+    let mut t1: f64 = 1.0;
+    for (p, q) in [
+        (-dx, a.x - rect.min),
+        (dx, rect.max - a.x),
+        (-dy, a.y - rect.min),
+        (dy, rect.max - a.y),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else {
+            let r = q / p;
+            if p < 0.0 {
+                t0 = t0.max(r);
+            } else {
+                t1 = t1.min(r);
+            }
+        }
+    }
+    if t0 > t1 {
+        return None;
+    }
+    let at = |t: f64| Point {
+        x: a.x + t * dx,
+        y: a.y + t * dy,
+    };
+    Some((at(t0), at(t1)))
+}
+
+/// Clips a line string and returns the parts inside of the rectangle.
+fn clip_line(rect: Rect, line: &[Point]) -> Vec<Vec<Point>> {
+    let mut parts: Vec<Vec<Point>> = Vec::new();
+    let mut current: Vec<Point> = Vec::new();
+    for w in line.windows(2) {
+        match clip_segment(rect, w[0], w[1]) {
+            Some((a, b)) => {
+                if current.last() != Some(&a) {
+                    if current.len() > 1 {
+                        parts.push(std::mem::take(&mut current));
+                    }
+                    current.clear();
+                    current.push(a);
+                }
+                current.push(b);
+            }
+            None => {
+                if current.len() > 1 {
+                    parts.push(std::mem::take(&mut current));
+                }
+                current.clear();
+            }
+        }
+    }
+    if current.len() > 1 {
+        parts.push(current);
+    }
+    parts
+}
+
+/// Clips a closed ring with the Sutherland-Hodgman algorithm.
+fn clip_ring(rect: Rect, ring: &[Point]) -> Vec<Point> {
+    type Inside = fn(&Rect, Point) -> bool;
+    type Intersect = fn(&Rect, Point, Point) -> Point;
+    let edges: [(Inside, Intersect); 4] = [
+        (|r, p| p.x >= r.min, |r, a, b| lerp_x(a, b, r.min)),
+        (|r, p| p.x <= r.max, |r, a, b| lerp_x(a, b, r.max)),
+        (|r, p| p.y >= r.min, |r, a, b| lerp_y(a, b, r.min)),
+        (|r, p| p.y <= r.max, |r, a, b| lerp_y(a, b, r.max)),
+    ];
+
+    let mut output: Vec<Point> = ring.to_vec();
+    for (inside, intersect) in edges {
+        let input = std::mem::take(&mut output);
+        let mut prev = match input.last() {
+            Some(p) => *p,
+            None => break,
+        };
+        for p in input {
+            if inside(&rect, p) {
+                if !inside(&rect, prev) {
+                    output.push(intersect(&rect, prev, p));
+                }
+                output.push(p);
+            } else if inside(&rect, prev) {
+                output.push(intersect(&rect, prev, p));
+            }
+            prev = p;
+        }
+    }
+    if let (Some(first), Some(last)) = (output.first(), output.last()) {
+        if first != last {
+            output.push(*first);
+        }
+    }
+    output
+}
+
+fn lerp_x(a: Point, b: Point, x: f64) -> Point {
+    let t = (x - a.x) / (b.x - a.x);
+    Point {
+        x,
+        y: a.y + t * (b.y - a.y),
+    }
+}
+
+fn lerp_y(a: Point, b: Point, y: f64) -> Point {
+    let t = (y - a.y) / (b.y - a.y);
+    Point {
+        x: a.x + t * (b.x - a.x),
+        y,
+    }
+}
+
+/// Simplifies a line string with the Douglas-Peucker algorithm.
+fn simplify(points: &[Point], epsilon: f64) -> Vec<Point> {
+    fn distance(p: Point, a: Point, b: Point) -> f64 {
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let len = (dx * dx + dy * dy).sqrt();
+        if len == 0.0 {
+            ((p.x - a.x).powi(2) + (p.y - a.y).powi(2)).sqrt()
+        } else {
+            (dy * p.x - dx * p.y + b.x * a.y - b.y * a.x).abs() / len
+        }
+    }
+
+    fn recurse(points: &[Point], epsilon: f64, result: &mut Vec<Point>) {
+        let (first, last) = (points[0], points[points.len() - 1]);
+        let farthest = points[1..points.len() - 1]
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (i + 1, distance(*p, first, last)))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        match farthest {
+            Some((idx, dist)) if dist > epsilon => {
+                recurse(&points[..=idx], epsilon, result);
+                recurse(&points[idx..], epsilon, result);
+            }
+            _ => result.push(last),
+        }
+    }
+
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+    let mut result = vec![points[0]];
+    recurse(points, epsilon, &mut result);
+    result
+}
+
+/// Signed area of a ring in tile coordinates (positive when clockwise on
+/// screen, i.e. with y pointing down).
+fn signed_area(ring: &[Point]) -> f64 {
+    ring.windows(2)
+        .map(|w| w[0].x * w[1].y - w[1].x * w[0].y)
+        .sum::<f64>()
+        / 2.0
+}
+
+/// Minimal protobuf writer sufficient for encoding vector tiles.
+#[derive(Default)]
+struct ProtoWriter {
+    buf: Vec<u8>,
+}
+
+impl ProtoWriter {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buf.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.buf.push(value as u8);
+    }
+
+    fn key(&mut self, field: u32, wire_type: u32) {
+        self.varint(u64::from(field << 3 | wire_type));
+    }
+
+    fn uint(&mut self, field: u32, value: u64) {
+        self.key(field, 0);
+        self.varint(value);
+    }
+
+    fn bytes(&mut self, field: u32, value: &[u8]) {
+        self.key(field, 2);
+        self.varint(value.len() as u64);
+        self.buf.extend_from_slice(value);
+    }
+
+    fn packed(&mut self, field: u32, values: &[u32]) {
+        let mut inner = ProtoWriter::default();
+        for v in values {
+            inner.varint(u64::from(*v));
+        }
+        self.bytes(field, &inner.buf);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GeomType {
+    Point = 1,
+    LineString = 2,
+    Polygon = 3,
+}
+
+/// Encodes geometry commands of a feature.
+#[derive(Default)]
+struct GeometryEncoder {
+    commands: Vec<u32>,
+    cursor: (i32, i32),
+}
+
+impl GeometryEncoder {
+    fn command(id: u32, count: usize) -> u32 {
+        (id & 0x7) | ((count as u32) << 3)
+    }
+
+    fn zigzag(x: i32) -> u32 {
+        ((x << 1) ^ (x >> 31)) as u32
+    }
+
+    fn push_point(&mut self, p: (i32, i32)) {
+        self.commands.push(Self::zigzag(p.0 - self.cursor.0));
+        self.commands.push(Self::zigzag(p.1 - self.cursor.1));
+        self.cursor = p;
+    }
+
+    /// Adds a path; closed paths are terminated with a `ClosePath` command.
+    fn path(&mut self, points: &[(i32, i32)], closed: bool) {
+        let points = if closed {
+            &points[..points.len() - 1]
+        } else {
+            points
+        };
+        self.commands.push(Self::command(1, 1));
+        self.push_point(points[0]);
+        self.commands.push(Self::command(2, points.len() - 1));
+        for p in &points[1..] {
+            self.push_point(*p);
+        }
+        if closed {
+            self.commands.push(Self::command(7, 1));
+        }
+    }
+}
+
+/// Converts points to integer tile coordinates and removes repeated points.
+fn quantize(points: &[Point]) -> Vec<(i32, i32)> {
+    let mut result: Vec<(i32, i32)> = points
+        .iter()
+        .map(|p| (p.x.round() as i32, p.y.round() as i32))
+        .collect();
+    result.dedup();
+    result
+}
+
+struct Feature {
+    geom_type: GeomType,
+    geometry: Vec<u32>,
+    tags: Vec<(String, String)>,
+}
+
+/// A layer of a vector tile with deduplicated keys and values.
+struct Layer {
+    name: &'static str,
+    features: Vec<Feature>,
+}
+
+impl Layer {
+    fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            features: Vec::new(),
+        }
+    }
+
+    fn encode(&self, tile: &mut ProtoWriter) {
+        let mut keys: HashMap<&str, u32> = HashMap::new();
+        let mut values: HashMap<&str, u32> = HashMap::new();
+        let mut layer = ProtoWriter::default();
+        layer.uint(15, 2); // version
+        layer.bytes(1, self.name.as_bytes());
+        for feature in &self.features {
+            let mut tags = Vec::new();
+            for (k, v) in &feature.tags {
+                let next_key = keys.len() as u32;
+                tags.push(*keys.entry(k).or_insert(next_key));
+                let next_value = values.len() as u32;
+                tags.push(*values.entry(v).or_insert(next_value));
+            }
+            let mut f = ProtoWriter::default();
+            f.packed(2, &tags);
+            f.uint(3, feature.geom_type as u64);
+            f.packed(4, &feature.geometry);
+            layer.bytes(2, &f.buf);
+        }
+        let mut keys: Vec<_> = keys.into_iter().collect();
+        keys.sort_by_key(|(_, idx)| *idx);
+        for (key, _) in keys {
+            layer.bytes(3, key.as_bytes());
+        }
+        let mut values: Vec<_> = values.into_iter().collect();
+        values.sort_by_key(|(_, idx)| *idx);
+        for (value, _) in values {
+            let mut v = ProtoWriter::default();
+            v.bytes(1, value.as_bytes());
+            layer.bytes(4, &v.buf);
+        }
+        layer.uint(5, EXTENT as u64);
+        tile.bytes(3, &layer.buf);
+    }
+}
+
+fn properties(archive: &Osm, tags: std::ops::Range<u64>) -> Vec<(String, String)> {
+    iter_tags(archive, tags)
+        .filter(|(k, _)| PROPERTIES.contains(k))
+        .filter_map(|(k, v)| {
+            Some((
+                std::str::from_utf8(k).ok()?.into(),
+                std::str::from_utf8(v).ok()?.into(),
+            ))
+        })
+        .collect()
+}
+
+fn way_points(archive: &Osm, way: &Way, t: &TileTransform) -> Option<Vec<Point>> {
+    let nodes = archive.nodes();
+    let nodes_index = archive.nodes_index();
+    let scale = f64::from(archive.header().coord_scale());
+    way.refs()
+        .map(|idx| {
+            let node = &nodes[nodes_index[idx as usize].value()? as usize];
+            Some(t.project(f64::from(node.lat()) / scale, f64::from(node.lon()) / scale))
+        })
+        .collect()
+}
+
+fn render_tile(archive: &Osm, t: &TileTransform, tolerance: f64) -> Vec<u8> {
+    let rect = Rect {
+        min: -f64::from(BUFFER),
+        max: f64::from(EXTENT + BUFFER),
+    };
+
+    let mut roads = Layer::new("roads");
+    let mut water = Layer::new("water");
+    let mut buildings = Layer::new("buildings");
+    let mut pois = Layer::new("pois");
+
+    for way in archive.ways() {
+        let layer = if find_tag(archive, way.tags(), b"highway").is_some() {
+            &mut roads
+        } else if find_tag(archive, way.tags(), b"waterway").is_some() {
+            &mut water
+        } else if find_tag(archive, way.tags(), b"building").is_some() {
+            &mut buildings
+        } else {
+            continue;
+        };
+        let points = match way_points(archive, way, t) {
+            Some(points) if points.len() >= 2 && rect.intersects(&points) => points,
+            _ => continue,
+        };
+
+        let mut encoder = GeometryEncoder::default();
+        let geom_type = if layer.name == "buildings" {
+            if points.len() < 4 || points.first() != points.last() {
+                continue;
+            }
+            let mut ring = quantize(&simplify(&clip_ring(rect, &points), tolerance));
+            if ring.len() < 4 {
+                continue;
+            }
+            let ring_f: Vec<Point> = ring
+                .iter()
+                .map(|&(x, y)| Point {
+                    x: f64::from(x),
+                    y: f64::from(y),
+                })
+                .collect();
+            // exterior rings must be clockwise in tile coordinates
+            if signed_area(&ring_f) < 0.0 {
+                ring.reverse();
+            }
+            encoder.path(&ring, true);
+            GeomType::Polygon
+        } else {
+            for part in clip_line(rect, &points) {
+                let part = quantize(&simplify(&part, tolerance));
+                if part.len() >= 2 {
+                    encoder.path(&part, false);
+                }
+            }
+            GeomType::LineString
+        };
+        if encoder.commands.is_empty() {
+            continue;
+        }
+
+        layer.features.push(Feature {
+            geom_type,
+            geometry: encoder.commands,
+            tags: properties(archive, way.tags()),
+        });
+    }
+
+    let scale = f64::from(archive.header().coord_scale());
+    for node in archive.nodes() {
+        if node.tags().is_empty() {
+            continue;
+        }
+        let p = t.project(f64::from(node.lat()) / scale, f64::from(node.lon()) / scale);
+        if !rect.contains(p) {
+            continue;
+        }
+        let tags = properties(archive, node.tags());
+        if !tags.iter().any(|(k, _)| k == "amenity" || k == "shop") {
+            continue;
+        }
+        let mut encoder = GeometryEncoder::default();
+        encoder.commands.push(GeometryEncoder::command(1, 1));
+        encoder.push_point((p.x.round() as i32, p.y.round() as i32));
+        pois.features.push(Feature {
+            geom_type: GeomType::Point,
+            geometry: encoder.commands,
+            tags,
+        });
+    }
+
+    let mut tile = ProtoWriter::default();
+    for layer in [&roads, &water, &buildings, &pois] {
+        if !layer.features.is_empty() {
+            layer.encode(&mut tile);
+        }
+    }
+    tile.buf
+}
+
+/// Renders a single vector tile (MVT) from an osmflat archive
+#[derive(Debug, Parser)]
+#[clap(name = "vector-tile")]
+struct Args {
+    /// osmflat archive
+    osmflat_archive: PathBuf,
+
+    /// tile in the format z/x/y
+    tile: String,
+
+    /// MVT filename to output
+    #[clap(long, short = 'o')]
+    output: PathBuf,
+
+    /// simplification tolerance in tile coordinates
+    #[clap(long, default_value = "1.0")]
+    tolerance: f64,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let zxy: Vec<u32> = args
+        .tile
+        .split('/')
+        .map(|x| x.parse())
+        .collect::<Result<_, _>>()?;
+    let (z, x, y) = match zxy[..] {
+        [z, x, y] if z < 32 && x < 1 << z && y < 1 << z => (z, x, y),
+        _ => return Err(format!("invalid tile: {}", args.tile).into()),
+    };
+
+    let archive = Osm::open(FileResourceStorage::new(args.osmflat_archive))?;
+    let tile = render_tile(&archive, &TileTransform { z, x, y }, args.tolerance);
+    std::fs::write(&args.output, tile)?;
+    Ok(())
+}
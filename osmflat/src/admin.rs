@@ -0,0 +1,244 @@
+//! Administrative boundaries containing the nodes, stored in the
+//! `admin_hierarchy` subarchive.
+//!
+//! Reverse geocoding a node, i.e. finding its country, state and city, needs
+//! point-in-polygon tests against all administrative boundaries. The
+//! `admin_hierarchy` subarchive, compiled with `osmflatc --admin-hierarchy`,
+//! stores the result of these tests for each node, so the lookup is an array
+//! access, cf. [`AdminHierarchy::boundaries_of_node`].
+
+use crate::geometry::{multipolygon_rings, ring_contains, Vertex};
+use crate::{
+    find_tag, has_tag, AdminChain, AdminHierarchy, AdminHierarchyBuilder, EntityIndex, Osm,
+};
+
+use std::collections::HashMap;
+use std::io;
+use std::str;
+
+impl AdminHierarchy {
+    /// Returns the indexes of the administrative boundary relations containing
+    /// the node at `idx`, ordered by ascending admin level.
+    ///
+    /// Panics if the index is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use osmflat::{find_tag, FileResourceStorage, Osm};
+    ///
+    /// let archive = Osm::open(FileResourceStorage::new("path/to/archive.osm.flatdata")).unwrap();
+    /// let admin = archive.admin_hierarchy().expect("compiled without --admin-hierarchy");
+    /// for relation_idx in admin.boundaries_of_node(0) {
+    ///     let tags = archive.relations()[relation_idx as usize].tags();
+    ///     println!("{:?}", find_tag(&archive, tags, b"name"));
+    /// }
+    /// ```
+    pub fn boundaries_of_node(&self, idx: usize) -> impl ExactSizeIterator<Item = u64> + '_ {
+        let chain = self.node_chains()[idx].value();
+        let range = self.chains()[chain as usize].boundaries();
+        self.boundaries()[range.start as usize..range.end as usize]
+            .iter()
+            .map(|relation_idx| relation_idx.value())
+    }
+}
+
+/// Returns the admin level of the relation at `relation_idx` if it is an
+/// administrative boundary, i.e. tagged `boundary=administrative` with a
+/// numeric `admin_level`.
+pub fn admin_level(archive: &Osm, relation_idx: usize) -> Option<u8> {
+    let tags = archive.relations()[relation_idx].tags();
+    if !has_tag(archive, tags.clone(), b"boundary", b"administrative") {
+        return None;
+    }
+    str::from_utf8(find_tag(archive, tags, b"admin_level")?)
+        .ok()?
+        .parse()
+        .ok()
+}
+
+/// Administrative boundary with its assembled polygons.
+struct Boundary {
+    relation_idx: u64,
+    admin_level: u8,
+    min: (f64, f64),
+    max: (f64, f64),
+    polygons: Vec<Vec<Vec<Vertex>>>,
+}
+
+impl Boundary {
+    fn contains(&self, (lon, lat): (f64, f64)) -> bool {
+        if lon < self.min.0 || self.max.0 < lon || lat < self.min.1 || self.max.1 < lat {
+            return false;
+        }
+        self.polygons.iter().any(|rings| {
+            ring_contains(&rings[0], (lon, lat))
+                && !rings[1..]
+                    .iter()
+                    .any(|hole| ring_contains(hole, (lon, lat)))
+        })
+    }
+}
+
+/// Cell of a coordinate in the grid of 1° cells used to find the candidate
+/// boundaries of a node.
+fn grid_cell((lon, lat): (f64, f64)) -> (i32, i32) {
+    (lon.floor() as i32, lat.floor() as i32)
+}
+
+/// Builds the `admin_hierarchy` subarchive of `archive`.
+///
+/// The boundaries are assembled like multipolygons, cf.
+/// [`geometry::multipolygon`]; invalid boundaries are skipped. A node on the
+/// edge of a boundary might be considered outside of it.
+///
+/// Returns the number of boundaries.
+///
+/// [`geometry::multipolygon`]: crate::geometry::multipolygon
+pub fn build_admin_hierarchy(archive: &Osm, builder: &AdminHierarchyBuilder) -> io::Result<usize> {
+    let mut boundaries = Vec::new();
+    for relation_idx in 0..archive.relations().len() {
+        let Some(admin_level) = admin_level(archive, relation_idx) else {
+            continue;
+        };
+        let Ok(polygons) = multipolygon_rings(archive, relation_idx) else {
+            continue;
+        };
+        let (mut min, mut max) = ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN));
+        for (_, (lon, lat)) in polygons.iter().flat_map(|rings| &rings[0]) {
+            min = (min.0.min(*lon), min.1.min(*lat));
+            max = (max.0.max(*lon), max.1.max(*lat));
+        }
+        boundaries.push(Boundary {
+            relation_idx: relation_idx as u64,
+            admin_level,
+            min,
+            max,
+            polygons,
+        });
+    }
+    boundaries.sort_by_key(|b| (b.admin_level, b.relation_idx));
+
+    let mut grid: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+    for (idx, boundary) in boundaries.iter().enumerate() {
+        let (min_x, min_y) = grid_cell(boundary.min);
+        let (max_x, max_y) = grid_cell(boundary.max);
+        for x in min_x..=max_x {
+            for y in min_y..=max_y {
+                grid.entry((x, y)).or_default().push(idx);
+            }
+        }
+    }
+
+    // the empty chain has index 0
+    let mut chains: Vec<Vec<u64>> = vec![Vec::new()];
+    let mut chain_indexes: HashMap<Vec<u64>, u32> = HashMap::from([(Vec::new(), 0)]);
+    let mut node_chains = builder.start_node_chains()?;
+    let mut chain = Vec::new();
+    for node_idx in 0..archive.nodes().len() {
+        let coord = archive.node_coord(node_idx);
+        chain.clear();
+        if let Some(candidates) = grid.get(&grid_cell(coord)) {
+            chain.extend(
+                candidates
+                    .iter()
+                    .map(|&idx| &boundaries[idx])
+                    .filter(|boundary| boundary.contains(coord))
+                    .map(|boundary| boundary.relation_idx),
+            );
+        }
+        let chain_idx = match chain_indexes.get(&chain) {
+            Some(&chain_idx) => chain_idx,
+            None => {
+                let chain_idx = u32::try_from(chains.len())
+                    .map_err(|_| io::Error::other("too many chains of boundaries"))?;
+                chain_indexes.insert(chain.clone(), chain_idx);
+                chains.push(chain.clone());
+                chain_idx
+            }
+        };
+        node_chains.grow()?.set_value(chain_idx);
+    }
+    node_chains.close().map_err(io::Error::other)?;
+
+    let mut chain_ranges = flatdata::Vector::<AdminChain>::new();
+    let mut chain_boundaries = Vec::new();
+    for chain in &chains {
+        chain_ranges
+            .grow()
+            .set_first_boundary_idx(chain_boundaries.len() as u64);
+        chain_boundaries.extend(chain.iter().map(|&relation_idx| {
+            let mut entry = EntityIndex::new();
+            entry.set_value(relation_idx);
+            entry
+        }));
+    }
+    // sentinel
+    chain_ranges
+        .grow()
+        .set_first_boundary_idx(chain_boundaries.len() as u64);
+    builder.set_chains(chain_ranges.as_view())?;
+    builder.set_boundaries(&chain_boundaries)?;
+    Ok(boundaries.len())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Member, OsmWriter};
+    use flatdata::ResourceStorage;
+
+    #[test]
+    fn test_admin_hierarchy() {
+        let storage = flatdata::MemoryResourceStorage::new("/test");
+        let mut writer = OsmWriter::new(storage.clone()).unwrap();
+        let tags = None::<(&str, &str)>;
+        let square = |writer: &mut OsmWriter, min: f64, size: f64| -> u64 {
+            let max = min + size;
+            let nodes: Vec<u64> = [(min, min), (max, min), (max, max), (min, max)]
+                .into_iter()
+                .map(|(lon, lat)| writer.add_node(lon, lat, tags))
+                .collect();
+            writer.add_way(&[nodes[0], nodes[1], nodes[2], nodes[3], nodes[0]], tags)
+        };
+        let country = square(&mut writer, 0.0, 4.0);
+        let city = square(&mut writer, 1.0, 1.0);
+        let inside_city = writer.add_node(1.5, 1.5, tags);
+        let inside_country = writer.add_node(3.0, 1.5, tags);
+        let outside = writer.add_node(5.0, 5.0, tags);
+        let admin = |level| {
+            [
+                ("type", "boundary"),
+                ("boundary", "administrative"),
+                ("admin_level", level),
+            ]
+        };
+        // the city is listed before the country
+        writer.add_relation(&[Member::Way(city, "outer")], admin("8"));
+        writer.add_relation(&[Member::Way(country, "outer")], admin("2"));
+        writer.add_relation(
+            &[Member::Way(country, "outer")],
+            [("boundary", "postal_code")],
+        );
+        writer.add_relation(&[Member::Way(city, "outer")], admin("unknown"));
+        writer.finish().unwrap();
+
+        let archive = Osm::open(storage.clone()).unwrap();
+        assert!(archive.admin_hierarchy().is_none());
+        assert_eq!(admin_level(&archive, 0), Some(8));
+        assert_eq!(admin_level(&archive, 2), None);
+        assert_eq!(admin_level(&archive, 3), None);
+        let builder = AdminHierarchyBuilder::new(storage.subdir("admin_hierarchy")).unwrap();
+        assert_eq!(build_admin_hierarchy(&archive, &builder).unwrap(), 2);
+
+        let archive = Osm::open(storage).unwrap();
+        let admin = archive.admin_hierarchy().unwrap();
+        let boundaries = |idx: u64| admin.boundaries_of_node(idx as usize).collect::<Vec<_>>();
+        assert_eq!(boundaries(inside_city), [1, 0]);
+        assert_eq!(boundaries(inside_country), [1]);
+        assert!(boundaries(outside).is_empty());
+        // the empty chain, and one chain per distinct list of boundaries
+        assert_eq!(admin.chains().len(), 3);
+        assert_eq!(admin.node_chains()[outside as usize].value(), 0);
+    }
+}
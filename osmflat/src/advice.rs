@@ -0,0 +1,285 @@
+//! Hints to the OS about how the memory mapped resources are accessed.
+//!
+//! Archives are read through memory mapped files, so the OS decides when pages
+//! are read from disk. On cold caches, in particular on spinning disks and
+//! network filesystems, reading ahead the right amount makes a big difference:
+//! a full scan over the nodes profits from aggressive read-ahead, while random
+//! lookups in the tags should not read neighboring pages. The hints are passed
+//! to `madvise`; on other platforms than Unix, they are ignored.
+//!
+//! [`PrefetchIter`] scans a vector while keeping the pages ahead of it
+//! prefetched, so a full scan on cold caches does not stall on a page fault
+//! every few entities.
+
+use crate::Osm;
+
+use flatdata::SliceExt;
+
+use std::io;
+use std::ops::Range;
+
+/// Expected access pattern of a range of memory, cf. `madvise(2)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessPattern {
+    /// Pages are read in sequential order, so read ahead aggressively
+    Sequential,
+    /// Pages are read in random order, so do not read ahead
+    Random,
+    /// Pages will be read soon, so read them in the background now
+    WillNeed,
+}
+
+/// Resource of an archive to hint the access pattern of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resource {
+    /// `nodes`
+    Nodes,
+    /// `ways`
+    Ways,
+    /// `relations`
+    Relations,
+    /// `nodes_index`
+    NodesIndex,
+    /// `tags`
+    Tags,
+    /// `tags_index`
+    TagsIndex,
+    /// `stringtable`
+    Stringtable,
+}
+
+/// Hints the OS that `data` is accessed with the pattern.
+///
+/// `data` is extended to whole pages. Does nothing on other platforms than
+/// Unix.
+pub fn advise(data: &[u8], pattern: AccessPattern) -> io::Result<()> {
+    if data.is_empty() {
+        return Ok(());
+    }
+    madvise(data, pattern)
+}
+
+#[cfg(unix)]
+fn madvise(data: &[u8], pattern: AccessPattern) -> io::Result<()> {
+    let advice = match pattern {
+        AccessPattern::Sequential => libc::MADV_SEQUENTIAL,
+        AccessPattern::Random => libc::MADV_RANDOM,
+        AccessPattern::WillNeed => libc::MADV_WILLNEED,
+    };
+    // madvise requires the address to be aligned to pages
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+    let offset = data.as_ptr() as usize % page_size;
+    let res = unsafe {
+        libc::madvise(
+            data.as_ptr().sub(offset) as *mut libc::c_void,
+            data.len() + offset,
+            advice,
+        )
+    };
+    if res != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn madvise(_data: &[u8], _pattern: AccessPattern) -> io::Result<()> {
+    Ok(())
+}
+
+impl Osm {
+    /// Returns the raw bytes of the resource.
+    pub fn resource_bytes(&self, resource: Resource) -> &[u8] {
+        match resource {
+            Resource::Nodes => self.nodes().as_bytes(),
+            Resource::Ways => self.ways().as_bytes(),
+            Resource::Relations => self.relations().as_bytes(),
+            Resource::NodesIndex => self.nodes_index().as_bytes(),
+            Resource::Tags => self.tags().as_bytes(),
+            Resource::TagsIndex => self.tags_index().as_bytes(),
+            Resource::Stringtable => self.stringtable().as_bytes(),
+        }
+    }
+
+    /// Hints the OS that the resource is accessed with the pattern, e.g.
+    /// [`AccessPattern::Random`] before looking up tags of single entities.
+    pub fn advise(&self, resource: Resource, pattern: AccessPattern) -> io::Result<()> {
+        advise(self.resource_bytes(resource), pattern)
+    }
+
+    /// Asks the OS to read the byte range of the resource in the background.
+    ///
+    /// E.g. the nodes of a tile of a Hilbert sorted archive are contiguous, so
+    /// their bytes can be prefetched before iterating over them; use
+    /// [`advise`] with [`AccessPattern::WillNeed`] to prefetch a slice of
+    /// entities directly. Fails if the range is out of the bounds of the
+    /// resource.
+    pub fn prefetch(&self, resource: Resource, range: Range<usize>) -> io::Result<()> {
+        let data = self.resource_bytes(resource);
+        let len = data.len();
+        let data = data.get(range.clone()).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("range {range:?} out of bounds of {resource:?} of size {len}"),
+            )
+        })?;
+        advise(data, AccessPattern::WillNeed)
+    }
+}
+
+/// Default number of bytes a [`PrefetchIter`] prefetches at once.
+pub const DEFAULT_PREFETCH_CHUNK_SIZE: usize = 4 << 20;
+
+/// Iterator over a slice of a memory mapped resource, which prefetches the
+/// elements ahead of it in chunks.
+///
+/// Before the iterator reaches the last prefetched chunk, the next chunk is
+/// passed to `madvise` with [`AccessPattern::WillNeed`], so the OS reads it in
+/// the background while the current chunk is processed. With
+/// [`PrefetchIter::touch_pages`], the pages of the next chunk are additionally
+/// read once by the iterator, which also works where `madvise` is ignored, at
+/// the cost of blocking on the reads.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use osmflat::{FileResourceStorage, Osm, PrefetchIter};
+///
+/// let archive = Osm::open(FileResourceStorage::new("path/to/archive.osm.flatdata")).unwrap();
+/// let max_lat = PrefetchIter::new(archive.nodes())
+///     .chunk_size(16 << 20)
+///     .map(|node| node.lat())
+///     .max();
+/// ```
+#[derive(Debug, Clone)]
+pub struct PrefetchIter<'a, T> {
+    data: &'a [T],
+    pos: usize,
+    prefetched: usize,
+    chunk_len: usize,
+    touch_pages: bool,
+}
+
+impl<'a, T> PrefetchIter<'a, T> {
+    /// Creates an iterator over `data` prefetching
+    /// [`DEFAULT_PREFETCH_CHUNK_SIZE`] bytes at once.
+    pub fn new(data: &'a [T]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            prefetched: 0,
+            chunk_len: 1,
+            touch_pages: false,
+        }
+        .chunk_size(DEFAULT_PREFETCH_CHUNK_SIZE)
+    }
+
+    /// Sets the number of bytes to prefetch at once; it is rounded up to whole
+    /// elements.
+    pub fn chunk_size(mut self, bytes: usize) -> Self {
+        self.chunk_len = bytes.div_ceil(std::mem::size_of::<T>().max(1)).max(1);
+        self
+    }
+
+    /// Sets whether the pages of the next chunk are read by the iterator in
+    /// addition to the hint to the OS.
+    pub fn touch_pages(mut self, touch_pages: bool) -> Self {
+        self.touch_pages = touch_pages;
+        self
+    }
+
+    fn prefetch_next_chunk(&mut self) {
+        let end = (self.prefetched + self.chunk_len).min(self.data.len());
+        let chunk = &self.data[self.prefetched..end];
+        let len = std::mem::size_of_val(chunk);
+        // Safety: the bytes of the elements are initialized and live as long
+        // as the slice
+        let bytes = unsafe { std::slice::from_raw_parts(chunk.as_ptr() as *const u8, len) };
+        // the hint is best effort, so errors are ignored
+        let _ = advise(bytes, AccessPattern::WillNeed);
+        if self.touch_pages {
+            for offset in (0..bytes.len()).step_by(4096) {
+                // Safety: the offset is in bounds of the bytes
+                unsafe { std::ptr::read_volatile(bytes.as_ptr().add(offset)) };
+            }
+        }
+        self.prefetched = end;
+    }
+}
+
+impl<'a, T> Iterator for PrefetchIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        while self.prefetched < self.data.len() && self.pos + self.chunk_len >= self.prefetched {
+            self.prefetch_next_chunk();
+        }
+        let item = self.data.get(self.pos)?;
+        self.pos += 1;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.data.len() - self.pos;
+        (len, Some(len))
+    }
+}
+
+impl<T> ExactSizeIterator for PrefetchIter<'_, T> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Node, OsmWriter};
+
+    #[test]
+    fn test_advise() {
+        let data = vec![0u8; 3 * 4096 + 17];
+        for pattern in [
+            AccessPattern::Sequential,
+            AccessPattern::Random,
+            AccessPattern::WillNeed,
+        ] {
+            advise(&data[5..], pattern).unwrap();
+        }
+        advise(&[], AccessPattern::Random).unwrap();
+    }
+
+    #[test]
+    fn test_prefetch() {
+        let storage = flatdata::MemoryResourceStorage::new("/test");
+        let mut writer = OsmWriter::new(storage.clone()).unwrap();
+        writer.add_node(1.0, 2.0, [("a", "b")]);
+        writer.add_node(3.0, 4.0, None::<(&str, &str)>);
+        writer.finish().unwrap();
+        let archive = Osm::open(storage).unwrap();
+
+        let nodes = archive.resource_bytes(Resource::Nodes);
+        // the sentinel is included
+        assert_eq!(nodes.len(), 3 * std::mem::size_of::<Node>());
+        archive
+            .advise(Resource::Tags, AccessPattern::Random)
+            .unwrap();
+        archive.prefetch(Resource::Nodes, 0..nodes.len()).unwrap();
+        let err = archive
+            .prefetch(Resource::Stringtable, 0..1 << 20)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_prefetch_iter() {
+        let data: Vec<u64> = (0..10_000).collect();
+        for touch_pages in [false, true] {
+            let iter = PrefetchIter::new(&data)
+                .chunk_size(1000)
+                .touch_pages(touch_pages);
+            assert_eq!(iter.len(), data.len());
+            assert!(iter.copied().eq(0..10_000));
+        }
+        let mut iter = PrefetchIter::new(&data[..3]).chunk_size(0);
+        assert_eq!(iter.next(), Some(&0));
+        assert_eq!(iter.len(), 2);
+        assert_eq!(PrefetchIter::new(&data[..0]).next(), None);
+    }
+}
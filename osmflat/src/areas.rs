@@ -0,0 +1,264 @@
+//! Areas assembled from closed ways and multipolygon relations, stored in the
+//! `areas` subarchive.
+//!
+//! Assembling the rings of a multipolygon relation joins its ways at common
+//! endpoints, cf. [`geometry::multipolygon`]. The `areas` subarchive, compiled
+//! with `osmflatc --areas`, stores the rings of all areas as lists of nodes, so
+//! consumers rendering land use or computing coverage read the polygons
+//! directly, cf. [`Osm::area_polygons`].
+//!
+//! [`geometry::multipolygon`]: crate::geometry::multipolygon
+
+use crate::geometry::{multipolygon_rings, signed_area, way_vertices, Polygon, Vertex};
+use crate::{find_tag, Area, Areas, AreasBuilder, EntityIndex, EntityType, Osm, Way};
+
+use std::io;
+
+impl Areas {
+    /// Returns the polygons of the area at `idx`, each as its outer ring
+    /// followed by its holes, and each ring as the indexes of its nodes.
+    ///
+    /// Panics if the index is out of bounds.
+    pub fn polygons_of(
+        &self,
+        idx: usize,
+    ) -> impl ExactSizeIterator<Item = impl ExactSizeIterator<Item = &[EntityIndex]> + '_> + '_
+    {
+        let polygons = self.areas()[idx].polygons();
+        self.polygons()[polygons.start as usize..polygons.end as usize]
+            .iter()
+            .map(move |polygon| {
+                let rings = polygon.rings();
+                self.rings()[rings.start as usize..rings.end as usize]
+                    .iter()
+                    .map(move |ring| {
+                        let nodes = ring.nodes();
+                        &self.nodes()[nodes.start as usize..nodes.end as usize]
+                    })
+            })
+    }
+
+    /// Returns the index of the area assembled from the way or relation with
+    /// the given index.
+    ///
+    /// Returns `None` if there is no such area, e.g. for open ways, invalid
+    /// multipolygons, or nodes.
+    pub fn find(&self, entity_type: EntityType, idx: u64) -> Option<usize> {
+        let areas = self.areas();
+        let (pos, entity_idx): (usize, fn(&Area) -> Option<u64>) = match entity_type {
+            EntityType::Node => return None,
+            EntityType::Way => (
+                areas.partition_point(|area| area.way_idx().is_some_and(|w| w < idx)),
+                Area::way_idx,
+            ),
+            EntityType::Relation => (
+                areas.partition_point(|area| {
+                    area.way_idx().is_some() || area.relation_idx().is_some_and(|r| r < idx)
+                }),
+                Area::relation_idx,
+            ),
+        };
+        (entity_idx(areas.get(pos)?) == Some(idx)).then_some(pos)
+    }
+}
+
+/// Returns the ring of `way` if it is closed and not tagged `area=no`, oriented
+/// counterclockwise.
+pub(crate) fn way_ring(archive: &Osm, way: &Way) -> Option<Vec<Vertex>> {
+    if find_tag(archive, way.tags(), b"area") == Some(b"no") {
+        return None;
+    }
+    let mut ring = way_vertices(archive, way)?;
+    if ring.len() < 4 || ring.first()?.0 != ring.last()?.0 {
+        return None;
+    }
+    if signed_area(&ring) < 0.0 {
+        ring.reverse();
+    }
+    Some(ring)
+}
+
+/// Builds the `areas` subarchive of `archive`.
+///
+/// Areas are assembled from all closed ways with resolved nodes, except ways
+/// tagged `area=no`, and from all relations tagged `type=multipolygon` or
+/// `type=boundary` for which [`geometry::multipolygon`] succeeds. Invalid
+/// multipolygons are skipped. Closed ways which are also members of a
+/// multipolygon get an area of their own.
+///
+/// Returns the number of areas.
+///
+/// [`geometry::multipolygon`]: crate::geometry::multipolygon
+pub fn build_areas(archive: &Osm, builder: &AreasBuilder) -> io::Result<usize> {
+    let mut areas = builder.start_areas()?;
+    let mut polygons = builder.start_polygons()?;
+    let mut rings = builder.start_rings()?;
+    let mut nodes = builder.start_nodes()?;
+    let (mut num_areas, mut num_polygons, mut num_rings, mut num_nodes) = (0, 0, 0, 0);
+
+    let mut add_area =
+        |polygon_rings: Vec<Vec<Vec<Vertex>>>, way_idx, relation_idx| -> io::Result<()> {
+            let area = areas.grow()?;
+            area.set_first_polygon_idx(num_polygons);
+            area.set_way_idx(way_idx);
+            area.set_relation_idx(relation_idx);
+            num_areas += 1;
+            for polygon in polygon_rings {
+                polygons.grow()?.set_first_ring_idx(num_rings);
+                num_polygons += 1;
+                for ring in polygon {
+                    rings.grow()?.set_first_node_idx(num_nodes);
+                    num_rings += 1;
+                    for (node_idx, _) in ring {
+                        nodes.grow()?.set_value(node_idx);
+                        num_nodes += 1;
+                    }
+                }
+            }
+            Ok(())
+        };
+
+    for (way_idx, way) in archive.ways().iter().enumerate() {
+        if let Some(ring) = way_ring(archive, way) {
+            add_area(vec![vec![ring]], Some(way_idx as u64), None)?;
+        }
+    }
+    for (relation_idx, relation) in archive.relations().iter().enumerate() {
+        let area_type = find_tag(archive, relation.tags(), b"type");
+        if !matches!(area_type, Some(b"multipolygon" | b"boundary")) {
+            continue;
+        }
+        if let Ok(polygon_rings) = multipolygon_rings(archive, relation_idx) {
+            add_area(polygon_rings, None, Some(relation_idx as u64))?;
+        }
+    }
+
+    // sentinels
+    let area = areas.grow()?;
+    area.set_first_polygon_idx(num_polygons);
+    area.set_way_idx(None);
+    area.set_relation_idx(None);
+    polygons.grow()?.set_first_ring_idx(num_rings);
+    rings.grow()?.set_first_node_idx(num_nodes);
+
+    areas.close().map_err(io::Error::other)?;
+    polygons.close().map_err(io::Error::other)?;
+    rings.close().map_err(io::Error::other)?;
+    nodes.close().map_err(io::Error::other)?;
+    Ok(num_areas)
+}
+
+impl Osm {
+    /// Returns the polygons of the area at index `idx` of the `areas`
+    /// subarchive, with the coordinates of their rings in degrees.
+    ///
+    /// Returns `None` if the archive does not contain the `areas` subarchive.
+    /// Panics if the index is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use osmflat::{find_tag, FileResourceStorage, Osm};
+    ///
+    /// let archive = Osm::open(FileResourceStorage::new("path/to/archive.osm.flatdata")).unwrap();
+    /// let areas = archive.areas().expect("compiled without --areas");
+    /// for (idx, area) in areas.areas().iter().enumerate() {
+    ///     let tags = match (area.way_idx(), area.relation_idx()) {
+    ///         (Some(way_idx), _) => archive.ways()[way_idx as usize].tags(),
+    ///         (_, Some(relation_idx)) => archive.relations()[relation_idx as usize].tags(),
+    ///         _ => unreachable!(),
+    ///     };
+    ///     if find_tag(&archive, tags, b"landuse").is_some() {
+    ///         println!("{:?}", archive.area_polygons(idx).unwrap());
+    ///     }
+    /// }
+    /// ```
+    pub fn area_polygons(&self, idx: usize) -> Option<Vec<Polygon>> {
+        let nodes = self.nodes();
+        let coords = self.coord_reader();
+        let ring_coords = |ring: &[EntityIndex]| {
+            ring.iter()
+                .map(|node_idx| coords.coord(&nodes[node_idx.value() as usize]))
+                .collect()
+        };
+        let polygons = self
+            .areas()?
+            .polygons_of(idx)
+            .map(|mut rings| Polygon {
+                outer: rings.next().map(ring_coords).unwrap_or_default(),
+                inner: rings.map(ring_coords).collect(),
+            })
+            .collect();
+        Some(polygons)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{geometry, Member, OsmWriter};
+    use flatdata::ResourceStorage;
+
+    #[test]
+    fn test_areas() {
+        let storage = flatdata::MemoryResourceStorage::new("/test");
+        let mut writer = OsmWriter::new(storage.clone()).unwrap();
+        let tags = None::<(&str, &str)>;
+        // clockwise square with the lower left corner at (min, min)
+        let square = |writer: &mut OsmWriter, min: f64, size: f64| -> Vec<u64> {
+            let max = min + size;
+            [(min, min), (min, max), (max, max), (max, min)]
+                .into_iter()
+                .map(|(lon, lat)| writer.add_node(lon, lat, tags))
+                .collect()
+        };
+        let a = square(&mut writer, 0.0, 4.0);
+        let b = square(&mut writer, 1.0, 1.0);
+        // closed clockwise way
+        writer.add_way(&[a[0], a[1], a[2], a[3], a[0]], [("landuse", "forest")]);
+        // open way, and closed way which is not an area
+        writer.add_way(&[a[0], a[1]], tags);
+        writer.add_way(&[b[0], b[1], b[2], b[0]], [("area", "no")]);
+        // hole split into two ways
+        let inner_1 = writer.add_way(&[b[0], b[1], b[2]], tags);
+        let inner_2 = writer.add_way(&[b[2], b[3], b[0]], tags);
+        writer.add_relation(
+            &[
+                Member::Way(0, "outer"),
+                Member::Way(inner_1, "inner"),
+                Member::Way(inner_2, "inner"),
+            ],
+            [("type", "multipolygon")],
+        );
+        writer.add_relation(&[Member::Way(1, "outer")], [("type", "multipolygon")]);
+        writer.add_relation(&[Member::Way(0, "outer")], [("type", "route")]);
+        writer.finish().unwrap();
+
+        let archive = Osm::open(storage.clone()).unwrap();
+        assert!(archive.area_polygons(0).is_none());
+        let builder = AreasBuilder::new(storage.subdir("areas")).unwrap();
+        assert_eq!(build_areas(&archive, &builder).unwrap(), 2);
+
+        let archive = Osm::open(storage).unwrap();
+        let areas = archive.areas().unwrap();
+        assert_eq!(areas.find(EntityType::Way, 0), Some(0));
+        assert_eq!(areas.find(EntityType::Way, 1), None);
+        assert_eq!(areas.find(EntityType::Way, 2), None);
+        assert_eq!(areas.find(EntityType::Relation, 0), Some(1));
+        assert_eq!(areas.find(EntityType::Relation, 1), None);
+        assert_eq!(areas.find(EntityType::Node, 0), None);
+
+        let rings: Vec<Vec<u64>> = areas
+            .polygons_of(0)
+            .flatten()
+            .map(|ring| ring.iter().map(|idx| idx.value()).collect())
+            .collect();
+        assert_eq!(rings, [vec![a[0], a[3], a[2], a[1], a[0]]]);
+
+        let polygons = archive.area_polygons(1).unwrap();
+        assert_eq!(polygons, geometry::multipolygon(&archive, 0).unwrap());
+        assert_eq!(polygons.len(), 1);
+        assert_eq!(polygons[0].inner.len(), 1);
+        assert_eq!(polygons[0].outer[..2], [(0.0, 0.0), (4.0, 0.0)]);
+    }
+}
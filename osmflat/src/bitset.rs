@@ -0,0 +1,98 @@
+//! Bitsets over the entities of an archive, stored as vectors of
+//! [`BitsetWord`].
+//!
+//! The `tagged_node_bits` and `tagged_way_bits` resources, compiled with
+//! `osmflatc --tagged-bitsets`, mark the entities having tags. At 1 bit per
+//! entity they are much smaller than the tag ranges of the entities, and
+//! [`set_bits`] skips 64 untagged entities at once.
+
+use crate::BitsetWord;
+
+/// Iterator over the indexes of the set bits of a bitset in ascending order,
+/// cf. [`set_bits`].
+#[derive(Debug, Clone)]
+pub struct SetBits<'a> {
+    words: &'a [BitsetWord],
+    /// Index of the word after `word`
+    next_word_idx: usize,
+    /// Remaining set bits of the current word
+    word: u64,
+}
+
+impl Iterator for SetBits<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.word == 0 {
+            self.word = self.words.get(self.next_word_idx)?.value();
+            self.next_word_idx += 1;
+        }
+        let bit = self.word.trailing_zeros() as usize;
+        self.word &= self.word - 1;
+        Some((self.next_word_idx - 1) * 64 + bit)
+    }
+}
+
+/// Returns the indexes of the set bits of the bitset `words` in ascending
+/// order.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use osmflat::{set_bits, FileResourceStorage, Osm};
+///
+/// let archive = Osm::open(FileResourceStorage::new("path/to/archive.osm.flatdata")).unwrap();
+/// let tagged_nodes = archive.tagged_node_bits().expect("compiled without --tagged-bitsets");
+/// for idx in set_bits(tagged_nodes) {
+///     println!("{:?}", archive.nodes()[idx]);
+/// }
+/// ```
+pub fn set_bits(words: &[BitsetWord]) -> SetBits<'_> {
+    SetBits {
+        words,
+        next_word_idx: 0,
+        word: 0,
+    }
+}
+
+/// Whether the bit at `idx` of the bitset `words` is set.
+///
+/// Returns `false` if the index is beyond the bitset.
+pub fn is_bit_set(words: &[BitsetWord], idx: usize) -> bool {
+    words
+        .get(idx / 64)
+        .is_some_and(|word| word.value() & (1 << (idx % 64)) != 0)
+}
+
+/// Packs `bits` into the words of a bitset.
+pub fn build_bitset(bits: impl IntoIterator<Item = bool>) -> Vec<BitsetWord> {
+    let mut words = Vec::new();
+    for (idx, bit) in bits.into_iter().enumerate() {
+        if idx % 64 == 0 {
+            words.push(BitsetWord::new());
+        }
+        if bit {
+            let word = words.last_mut().expect("no word");
+            word.set_value(word.value() | (1 << (idx % 64)));
+        }
+    }
+    words
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_set_bits() {
+        let indexes = [0, 5, 63, 64, 130, 199];
+        let words = build_bitset((0..200).map(|idx| indexes.contains(&idx)));
+        assert_eq!(words.len(), 4);
+        assert_eq!(set_bits(&words).collect::<Vec<_>>(), indexes);
+        assert!(is_bit_set(&words, 130));
+        assert!(!is_bit_set(&words, 131));
+        assert!(!is_bit_set(&words, 1000));
+        assert_eq!(set_bits(&build_bitset([false; 100])).count(), 0);
+        assert_eq!(set_bits(&[]).count(), 0);
+    }
+}
@@ -0,0 +1,283 @@
+//! Representative points of ways and relations, stored in the optional
+//! `way_centroids` and `relation_centroids` vectors.
+//!
+//! Label placement, clustering or sorting entities by distance need a single
+//! point per entity, which otherwise requires resolving its full geometry.
+//! The vectors, compiled with `osmflatc --centroids`, store the point of each
+//! way and relation, cf. [`Osm::way_centroid`] and
+//! [`Osm::relation_centroid`].
+
+use crate::areas::way_ring;
+use crate::geometry::{multipolygon_rings, ring_contains, signed_area, way_vertices, Vertex};
+use crate::{find_tag, Centroid, Osm, OsmBuilder, RelationMembersRef, Way};
+
+use std::io;
+
+/// Returns a point inside of the polygon consisting of the outer ring
+/// `rings[0]` and the holes `rings[1..]`.
+///
+/// This is the centroid of the polygon if it is inside of it. Otherwise, e.g.
+/// for concave polygons, it is the middle of the widest section of the
+/// horizontal line through the centroid inside of the polygon.
+fn polygon_point(rings: &[Vec<Vertex>]) -> Option<(f64, f64)> {
+    let (mut area, mut x, mut y) = (0.0, 0.0, 0.0);
+    for w in rings.iter().flat_map(|ring| ring.windows(2)) {
+        let ((x0, y0), (x1, y1)) = (w[0].1, w[1].1);
+        let cross = x0 * y1 - x1 * y0;
+        area += cross;
+        x += (x0 + x1) * cross;
+        y += (y0 + y1) * cross;
+    }
+    let first = rings.first()?.first()?.1;
+    if area == 0.0 {
+        return Some(first);
+    }
+    let centroid = (x / (3.0 * area), y / (3.0 * area));
+    let inside = |coord| {
+        ring_contains(&rings[0], coord) && !rings[1..].iter().any(|hole| ring_contains(hole, coord))
+    };
+    if inside(centroid) {
+        return Some(centroid);
+    }
+
+    let y = centroid.1;
+    let mut xs: Vec<f64> = rings
+        .iter()
+        .flat_map(|ring| ring.windows(2))
+        .filter_map(|w| {
+            let ((x0, y0), (x1, y1)) = (w[0].1, w[1].1);
+            ((y0 > y) != (y1 > y)).then(|| (x1 - x0) * (y - y0) / (y1 - y0) + x0)
+        })
+        .collect();
+    xs.sort_by(f64::total_cmp);
+    let widest = xs
+        .chunks_exact(2)
+        .max_by(|a, b| (a[1] - a[0]).total_cmp(&(b[1] - b[0])));
+    Some(widest.map_or(first, |section| ((section[0] + section[1]) / 2.0, y)))
+}
+
+/// Returns the point in the middle of the length of a line.
+fn line_point(line: &[Vertex]) -> Option<(f64, f64)> {
+    let distance = |(x0, y0): (f64, f64), (x1, y1): (f64, f64)| (x1 - x0).hypot(y1 - y0);
+    let length: f64 = line.windows(2).map(|w| distance(w[0].1, w[1].1)).sum();
+    let mut remaining = length / 2.0;
+    for w in line.windows(2) {
+        let ((x0, y0), (x1, y1)) = (w[0].1, w[1].1);
+        let segment = distance(w[0].1, w[1].1);
+        if segment > 0.0 && remaining <= segment {
+            let t = remaining / segment;
+            return Some((x0 + t * (x1 - x0), y0 + t * (y1 - y0)));
+        }
+        remaining -= segment;
+    }
+    line.first().map(|v| v.1)
+}
+
+/// Computes the representative point of `way` as `(lon, lat)` in degrees.
+///
+/// The point of a closed way, except ways tagged `area=no`, is inside of its
+/// polygon; the point of an open way is the middle of its length.
+///
+/// Returns `None` if one of the nodes is not resolved in the archive, or if
+/// the way has no nodes.
+pub fn compute_way_centroid(archive: &Osm, way: &Way) -> Option<(f64, f64)> {
+    match way_ring(archive, way) {
+        Some(ring) => polygon_point(&[ring]),
+        None => line_point(&way_vertices(archive, way)?),
+    }
+}
+
+/// Computes the representative point of the relation at `relation_idx` as
+/// `(lon, lat)` in degrees.
+///
+/// The point of a relation tagged `type=multipolygon` or `type=boundary` is
+/// inside of its largest polygon, cf. [`geometry::multipolygon`]. The point
+/// of other relations, and of invalid multipolygons, is the average of the
+/// nodes and of the points of the ways among its members; relation members
+/// are ignored.
+///
+/// Returns `None` if no node or way member is resolved in the archive.
+///
+/// [`geometry::multipolygon`]: crate::geometry::multipolygon
+pub fn compute_relation_centroid(archive: &Osm, relation_idx: usize) -> Option<(f64, f64)> {
+    let relation = &archive.relations()[relation_idx];
+    let area_type = find_tag(archive, relation.tags(), b"type");
+    if matches!(area_type, Some(b"multipolygon" | b"boundary")) {
+        if let Ok(polygons) = multipolygon_rings(archive, relation_idx) {
+            let largest = polygons
+                .iter()
+                .max_by(|a, b| signed_area(&a[0]).total_cmp(&signed_area(&b[0])))?;
+            return polygon_point(largest);
+        }
+    }
+
+    let (mut num_points, mut lon, mut lat) = (0, 0.0, 0.0);
+    for member in archive.relation_members().at(relation_idx) {
+        let point = match member {
+            RelationMembersRef::NodeMember(m) => m
+                .node_idx()
+                .map(|node_idx| archive.node_coord(node_idx as usize)),
+            RelationMembersRef::WayMember(m) => m.way_idx().and_then(|way_idx| {
+                compute_way_centroid(archive, &archive.ways()[way_idx as usize])
+            }),
+            RelationMembersRef::RelationMember(_) => None,
+        };
+        if let Some((x, y)) = point {
+            num_points += 1;
+            lon += x;
+            lat += y;
+        }
+    }
+    (num_points > 0).then(|| (lon / num_points as f64, lat / num_points as f64))
+}
+
+/// Writes the `way_centroids` and `relation_centroids` vectors of `archive`.
+pub fn build_centroids(archive: &Osm, builder: &OsmBuilder) -> io::Result<()> {
+    let scale = f64::from(archive.header().coord_scale());
+    let set = |centroid: &mut Centroid, point: Option<(f64, f64)>| match point {
+        Some((lon, lat)) => {
+            centroid.set_lat(Some((lat * scale).round() as i32));
+            centroid.set_lon((lon * scale).round() as i32);
+        }
+        None => {
+            centroid.set_lat(None);
+            centroid.set_lon(0);
+        }
+    };
+
+    let mut way_centroids = builder.start_way_centroids()?;
+    for way in archive.ways() {
+        set(way_centroids.grow()?, compute_way_centroid(archive, way));
+    }
+    way_centroids.close().map_err(io::Error::other)?;
+
+    let mut relation_centroids = builder.start_relation_centroids()?;
+    for relation_idx in 0..archive.relations().len() {
+        let point = compute_relation_centroid(archive, relation_idx);
+        set(relation_centroids.grow()?, point);
+    }
+    relation_centroids.close().map_err(io::Error::other)?;
+    Ok(())
+}
+
+impl Osm {
+    /// Returns the representative point of the way with the given index as
+    /// `(lon, lat)` in degrees, cf. [`compute_way_centroid`].
+    ///
+    /// The point is read from the `way_centroids` vector if present, and
+    /// computed otherwise. Panics if the index is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use osmflat::{find_tag, FileResourceStorage, Osm};
+    ///
+    /// let archive = Osm::open(FileResourceStorage::new("path/to/archive.osm.flatdata")).unwrap();
+    /// for (idx, way) in archive.ways().iter().enumerate() {
+    ///     if let (Some(name), Some(point)) = (
+    ///         find_tag(&archive, way.tags(), b"name"),
+    ///         archive.way_centroid(idx),
+    ///     ) {
+    ///         println!("{} at {point:?}", String::from_utf8_lossy(name));
+    ///     }
+    /// }
+    /// ```
+    pub fn way_centroid(&self, idx: usize) -> Option<(f64, f64)> {
+        match self.way_centroids() {
+            Some(centroids) => self.coord_reader().centroid_of(&centroids[idx]),
+            None => compute_way_centroid(self, &self.ways()[idx]),
+        }
+    }
+
+    /// Returns the representative point of the relation with the given index
+    /// as `(lon, lat)` in degrees, cf. [`compute_relation_centroid`].
+    ///
+    /// The point is read from the `relation_centroids` vector if present, and
+    /// computed otherwise. Panics if the index is out of bounds.
+    pub fn relation_centroid(&self, idx: usize) -> Option<(f64, f64)> {
+        match self.relation_centroids() {
+            Some(centroids) => self.coord_reader().centroid_of(&centroids[idx]),
+            None => compute_relation_centroid(self, idx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Member, OsmWriter};
+
+    fn assert_near((lon, lat): (f64, f64), expected: (f64, f64)) {
+        assert!(
+            (lon - expected.0).abs() < 1e-9 && (lat - expected.1).abs() < 1e-9,
+            "{:?} != {:?}",
+            (lon, lat),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_centroids() {
+        let storage = flatdata::MemoryResourceStorage::new("/test");
+        let mut writer = OsmWriter::new(storage.clone()).unwrap();
+        let tags = None::<(&str, &str)>;
+        let ring = |writer: &mut OsmWriter, coords: &[(f64, f64)]| -> u64 {
+            let mut nodes: Vec<u64> = coords
+                .iter()
+                .map(|&(lon, lat)| writer.add_node(lon, lat, tags))
+                .collect();
+            nodes.push(nodes[0]);
+            writer.add_way(&nodes, tags)
+        };
+        let square = ring(
+            &mut writer,
+            &[(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)],
+        );
+        // U shape, whose centroid is in the gap between its arms
+        let u_shape = ring(
+            &mut writer,
+            &[
+                (0.0, 0.0),
+                (3.0, 0.0),
+                (3.0, 3.0),
+                (2.0, 3.0),
+                (2.0, 1.0),
+                (1.0, 1.0),
+                (1.0, 3.0),
+                (0.0, 3.0),
+            ],
+        );
+        let hole = ring(
+            &mut writer,
+            &[(0.5, 0.5), (1.5, 0.5), (1.5, 1.5), (0.5, 1.5)],
+        );
+        let start = writer.add_node(10.0, 10.0, tags);
+        let end = writer.add_node(14.0, 10.0, tags);
+        let line = writer.add_way(&[start, end], tags);
+        writer.add_relation(
+            &[Member::Way(square, "outer"), Member::Way(hole, "inner")],
+            [("type", "multipolygon")],
+        );
+        writer.add_relation(
+            &[Member::Node(start, ""), Member::Way(line, "")],
+            [("type", "route")],
+        );
+        writer.add_relation(&[Member::Relation(0, "")], [("type", "route")]);
+        writer.finish().unwrap();
+
+        let archive = Osm::open(storage).unwrap();
+        assert!(archive.way_centroids().is_none());
+        assert_near(archive.way_centroid(square as usize).unwrap(), (1.0, 1.0));
+        let centroid_y = (9.0 * 1.5 - 2.0 * 2.0) / 7.0;
+        assert_near(
+            archive.way_centroid(u_shape as usize).unwrap(),
+            (2.5, centroid_y),
+        );
+        assert_near(archive.way_centroid(line as usize).unwrap(), (12.0, 10.0));
+
+        // the centroid of the square with a hole is in the hole
+        assert_near(archive.relation_centroid(0).unwrap(), (1.75, 1.0));
+        assert_near(archive.relation_centroid(1).unwrap(), (11.0, 10.0));
+        assert_eq!(archive.relation_centroid(2), None);
+    }
+}
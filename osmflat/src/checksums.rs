@@ -0,0 +1,241 @@
+//! Checksums of the files of an archive stored in its `checksums` resource.
+//!
+//! The manifest is written by `osmflatc` at the end of the conversion. It
+//! detects archives which were truncated or corrupted when copied around,
+//! before opening them fails with an obscure error or a panic.
+
+use crate::{schema, FileResourceStorage, Osm};
+
+use flatdata::ResourceStorage;
+use xxhash_rust::xxh64::Xxh64;
+
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Name of the resource storing the checksums of the files of an archive.
+pub(crate) const CHECKSUMS: &str = "checksums";
+
+/// Computes the 64-bit xxHash (XXH64) of `data`.
+pub fn xxh64(data: &[u8], seed: u64) -> u64 {
+    xxhash_rust::xxh64::xxh64(data, seed)
+}
+
+/// Size and checksum of a file of an archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChecksum {
+    /// Path of the file relative to the archive, separated by `/`
+    pub path: String,
+    /// Size of the file in bytes
+    pub size: u64,
+    /// [`xxh64`] of the content of the file with seed 0
+    pub xxh64: u64,
+}
+
+/// Computes the checksum of the file `name` in the directory `dir`.
+///
+/// The file is hashed while reading it in chunks, so that neither the whole
+/// file has to fit into memory nor it has to be mapped.
+fn file_checksum(dir: &Path, name: &str, path: String) -> io::Result<FileChecksum> {
+    let mut file = File::open(dir.join(name))?;
+    let mut hasher = Xxh64::new(0);
+    let mut buf = vec![0; 1 << 16];
+    let mut size = 0;
+    loop {
+        match file.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => {
+                hasher.update(&buf[..n]);
+                size += n as u64;
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(FileChecksum {
+        path,
+        size,
+        xxh64: hasher.digest(),
+    })
+}
+
+/// Computes the checksums of all files of the archive at `path`, sorted by
+/// path.
+///
+/// The `checksums` resource of the archive and its schema are skipped.
+pub fn compute_checksums(path: impl AsRef<Path>) -> io::Result<Vec<FileChecksum>> {
+    fn visit(dir: &Path, prefix: &str, result: &mut Vec<FileChecksum>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let path = format!("{prefix}{name}");
+            if entry.file_type()?.is_dir() {
+                visit(&entry.path(), &format!("{path}/"), result)?;
+            } else if path != CHECKSUMS && path != format!("{CHECKSUMS}.schema") {
+                result.push(file_checksum(dir, &name, path)?);
+            }
+        }
+        Ok(())
+    }
+    let mut result = Vec::new();
+    visit(path.as_ref(), "", &mut result)?;
+    result.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(result)
+}
+
+/// Formats checksums as content of the `checksums` resource.
+pub fn format_checksums(checksums: &[FileChecksum]) -> String {
+    checksums
+        .iter()
+        .map(|c| format!("{} {} {:016x}\n", c.path, c.size, c.xxh64))
+        .collect()
+}
+
+/// Parses the content of the `checksums` resource.
+pub fn parse_checksums(data: &str) -> io::Result<Vec<FileChecksum>> {
+    data.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut fields = line.rsplitn(3, ' ');
+            let xxh64 = fields.next().and_then(|x| u64::from_str_radix(x, 16).ok());
+            let size = fields.next().and_then(|x| x.parse().ok());
+            match (fields.next(), size, xxh64) {
+                (Some(path), Some(size), Some(xxh64)) => Ok(FileChecksum {
+                    path: path.to_string(),
+                    size,
+                    xxh64,
+                }),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid line in {CHECKSUMS}: {line}"),
+                )),
+            }
+        })
+        .collect()
+}
+
+impl Osm {
+    /// Verifies the sizes and checksums of the files of the archive at `path`
+    /// against its `checksums` resource.
+    ///
+    /// Fails on the first file which is missing or differs, and if the
+    /// archive has no `checksums` resource. Files not listed in the resource
+    /// are not checked.
+    pub fn verify_checksums(path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let storage = FileResourceStorage::new(path);
+        let data = storage
+            .read(CHECKSUMS, schema::osm::resources::CHECKSUMS)
+            .map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("failed to read {CHECKSUMS} of {}: {e}", path.display()),
+                )
+            })?;
+        let expected = parse_checksums(&String::from_utf8_lossy(data))?;
+        for expected in expected {
+            let file = path.join(&expected.path);
+            let (dir, name) = match (file.parent(), file.file_name()) {
+                (Some(dir), Some(name)) => (dir, name.to_string_lossy()),
+                _ => return Err(io::Error::other(format!("invalid path {}", expected.path))),
+            };
+            let actual = file_checksum(dir, &name, expected.path.clone()).map_err(|e| {
+                io::Error::new(e.kind(), format!("failed to read {}: {e}", expected.path))
+            })?;
+            if actual.size != expected.size {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "{} has size {}, expected {}",
+                        expected.path, actual.size, expected.size
+                    ),
+                ));
+            }
+            if actual.xxh64 != expected.xxh64 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "{} has checksum {:016x}, expected {:016x}",
+                        expected.path, actual.xxh64, expected.xxh64
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_xxh64() {
+        assert_eq!(xxh64(b"", 0), 0xEF46_DB37_51D8_E999);
+        assert_eq!(xxh64(b"a", 0), 0xD24E_C4F1_A98C_6E5B);
+        assert_eq!(xxh64(b"abc", 0), 0x44BC_2CF5_AD77_0999);
+        assert_eq!(
+            xxh64(b"Nobody inspects the spammish repetition", 0),
+            0xFBCE_A83C_8A37_8BF1
+        );
+        assert_eq!(xxh64(b"xxhash", 20141025), 0xB559_B98D_844E_0635);
+    }
+
+    #[test]
+    fn test_file_checksum() {
+        let dir =
+            std::env::temp_dir().join(format!("osmflat-file-checksum-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        // larger than one chunk read at a time
+        let data: Vec<u8> = (0..200_001u32).map(|i| (i % 251) as u8).collect();
+        fs::write(dir.join("nodes"), &data).unwrap();
+        fs::write(dir.join("empty"), b"").unwrap();
+
+        let checksum = file_checksum(&dir, "nodes", "nodes".into()).unwrap();
+        assert_eq!(checksum.size, data.len() as u64);
+        assert_eq!(checksum.xxh64, xxh64(&data, 0));
+        let checksum = file_checksum(&dir, "empty", "empty".into()).unwrap();
+        assert_eq!((checksum.size, checksum.xxh64), (0, xxh64(b"", 0)));
+        assert!(file_checksum(&dir, "missing", "missing".into()).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_checksums() {
+        let dir = std::env::temp_dir().join(format!("osmflat-checksums-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("nodes"), b"some nodes").unwrap();
+        fs::write(dir.join("sub/ways"), b"").unwrap();
+
+        let checksums = compute_checksums(&dir).unwrap();
+        assert_eq!(
+            checksums.iter().map(|c| &c.path[..]).collect::<Vec<_>>(),
+            ["nodes", "sub/ways"]
+        );
+        assert_eq!(checksums[0].size, 10);
+        assert_eq!(checksums[0].xxh64, xxh64(b"some nodes", 0));
+        let data = format_checksums(&checksums);
+        assert_eq!(parse_checksums(&data).unwrap(), checksums);
+        assert!(parse_checksums("nodes 10").is_err());
+
+        assert!(Osm::verify_checksums(&dir).is_err());
+        FileResourceStorage::new(&dir)
+            .write(
+                CHECKSUMS,
+                schema::osm::resources::CHECKSUMS,
+                data.as_bytes(),
+            )
+            .unwrap();
+        Osm::verify_checksums(&dir).unwrap();
+
+        fs::write(dir.join("nodes"), b"some node").unwrap();
+        let err = Osm::verify_checksums(&dir).unwrap_err();
+        assert_eq!(err.to_string(), "nodes has size 9, expected 10");
+        fs::write(dir.join("nodes"), b"some notes").unwrap();
+        assert!(Osm::verify_checksums(&dir).is_err());
+        fs::remove_file(dir.join("sub/ways")).unwrap();
+        fs::write(dir.join("nodes"), b"some nodes").unwrap();
+        assert!(Osm::verify_checksums(&dir).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,315 @@
+//! Reading archives whose resources are compressed by `osmflatc --compress`.
+//!
+//! A compressed resource `<name>` is stored as zstd stream in the file
+//! `<name>.zst`. Each directory of the archive containing compressed resources
+//! has an index file [`ZSTD_INDEX`], listing them with their uncompressed sizes
+//! as one `<name> <size>` line per resource. Schemas and the resources not
+//! listed in the index are stored uncompressed.
+//!
+//! Compressed resources cannot be memory mapped. They are either decompressed
+//! into memory by [`zstd_resource_storage`], or into a cache directory, from
+//! which they are memory mapped, by [`zstd_cached_resource_storage`].
+//!
+//! Reading compressed archives requires the feature `zstd`.
+
+#[cfg(feature = "zstd")]
+use flatdata::{FileResourceStorage, MemoryResourceStorage, ResourceStorage, StorageHandle};
+
+use std::collections::BTreeMap;
+use std::io;
+#[cfg(feature = "zstd")]
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+/// Name of the index file of the compressed resources in a directory of an
+/// archive.
+pub const ZSTD_INDEX: &str = "zstd.index";
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Parses the content of a [`ZSTD_INDEX`] file into the uncompressed sizes of
+/// the resources by their names.
+pub fn parse_zstd_index(data: &str) -> io::Result<BTreeMap<String, usize>> {
+    data.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            line.rsplit_once(' ')
+                .and_then(|(name, size)| Some((name.to_string(), size.parse().ok()?)))
+                .ok_or_else(|| invalid_data(format!("invalid line in {ZSTD_INDEX}: {line}")))
+        })
+        .collect()
+}
+
+#[cfg(feature = "zstd")]
+fn decompress(path: &Path, size: usize) -> io::Result<Vec<u8>> {
+    let mut data = Vec::with_capacity(size);
+    zstd::Decoder::new(File::open(path)?)?.read_to_end(&mut data)?;
+    if data.len() != size {
+        return Err(invalid_data(format!(
+            "{} has size {} when decompressed, expected {size}",
+            path.display(),
+            data.len()
+        )));
+    }
+    Ok(data)
+}
+
+/// Decompresses the file at `path` into the file `target`, which is only
+/// created once it is complete.
+#[cfg(feature = "zstd")]
+fn decompress_into(path: &Path, size: usize, target: &Path) -> io::Result<()> {
+    let mut partial = target.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+    let mut out = BufWriter::new(File::create(&partial)?);
+    let len = io::copy(&mut zstd::Decoder::new(File::open(path)?)?, &mut out)?;
+    out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    if len != size as u64 {
+        fs::remove_file(&partial)?;
+        return Err(invalid_data(format!(
+            "{} has size {len} when decompressed, expected {size}",
+            path.display(),
+        )));
+    }
+    fs::rename(partial, target)
+}
+
+/// Whether `target` is a copy of `source` of size `size` from a previous call
+/// of [`zstd_cached_resource_storage`], i.e. has this size and is not older
+/// than `source`.
+#[cfg(feature = "zstd")]
+fn is_cached(source: &Path, size: u64, target: &Path) -> io::Result<bool> {
+    let target = match fs::metadata(target) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    Ok(target.len() == size && target.modified()? >= fs::metadata(source)?.modified()?)
+}
+
+/// Reads the index of the compressed resources in `dir`, which is empty if the
+/// directory has no index file.
+#[cfg(feature = "zstd")]
+fn read_index(dir: &Path) -> io::Result<BTreeMap<String, usize>> {
+    match fs::read_to_string(dir.join(ZSTD_INDEX)) {
+        Ok(data) => parse_zstd_index(&data),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Whether the file `name` is the index or a compressed resource listed in
+/// `index`.
+#[cfg(feature = "zstd")]
+fn is_compressed(name: &str, index: &BTreeMap<String, usize>) -> bool {
+    name == ZSTD_INDEX
+        || name
+            .strip_suffix(".zst")
+            .is_some_and(|name| index.contains_key(name))
+}
+
+/// Reads the resources in `dir` into `storage`, recursing into subdirectories.
+#[cfg(feature = "zstd")]
+fn read_dir(dir: &Path, storage: &dyn ResourceStorage) -> io::Result<()> {
+    let index = read_index(dir)?;
+    for (name, &size) in &index {
+        let data = decompress(&dir.join(format!("{name}.zst")), size)?;
+        storage.create_output_stream(name)?.write_all(&data)?;
+    }
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if entry.file_type()?.is_dir() {
+            read_dir(&entry.path(), &*storage.subdir(&name))?;
+        } else if !is_compressed(&name, &index) {
+            storage
+                .create_output_stream(&name)?
+                .write_all(&fs::read(entry.path())?)?;
+        }
+    }
+    Ok(())
+}
+
+/// Decompresses and copies the resources in `dir` into the directory `cache`,
+/// recursing into subdirectories.
+#[cfg(feature = "zstd")]
+fn cache_dir(dir: &Path, cache: &Path) -> io::Result<()> {
+    fs::create_dir_all(cache)?;
+    let index = read_index(dir)?;
+    for (name, &size) in &index {
+        let source = dir.join(format!("{name}.zst"));
+        let target = cache.join(name);
+        if !is_cached(&source, size as u64, &target)? {
+            decompress_into(&source, size, &target)?;
+        }
+    }
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let target = cache.join(&name);
+        if entry.file_type()?.is_dir() {
+            cache_dir(&entry.path(), &target)?;
+        } else if !is_compressed(&name, &index)
+            && !is_cached(&entry.path(), entry.metadata()?.len(), &target)?
+        {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Opens the archive at `path` whose resources may be compressed with zstd.
+///
+/// All resources are decompressed into memory when the storage is created, so
+/// it needs as much memory as the uncompressed archive; therefore, the storage
+/// is meant for archives which fit into memory, for larger ones use
+/// [`zstd_cached_resource_storage`]. Uncompressed archives can be read as well,
+/// but are better memory mapped with [`flatdata::FileResourceStorage`].
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use osmflat::{zstd_resource_storage, Osm};
+///
+/// let storage = zstd_resource_storage("path/to/archive.osm.flatdata").unwrap();
+/// let archive = Osm::open(storage).unwrap();
+/// println!("{} nodes", archive.nodes().len());
+/// ```
+#[cfg(feature = "zstd")]
+pub fn zstd_resource_storage(path: impl AsRef<Path>) -> io::Result<StorageHandle> {
+    let path = path.as_ref();
+    let storage = MemoryResourceStorage::new(path);
+    read_dir(path, &*storage)?;
+    Ok(storage)
+}
+
+/// Opens the archive at `path` whose resources may be compressed with zstd,
+/// decompressing them into the directory `cache`.
+///
+/// Unlike [`zstd_resource_storage`], the resources are memory mapped from the
+/// cache, so reading them does not need more memory than an uncompressed
+/// archive, but the cache needs as much disk space. Resources decompressed
+/// into the cache by a previous call are reused if they have the expected size
+/// and are not older than their compressed files. Uncompressed resources are
+/// copied into the cache.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use osmflat::{zstd_cached_resource_storage, Osm};
+///
+/// let storage = zstd_cached_resource_storage(
+///     "path/to/archive.osm.flatdata",
+///     "path/to/cache",
+/// )
+/// .unwrap();
+/// let archive = Osm::open(storage).unwrap();
+/// println!("{} nodes", archive.nodes().len());
+/// ```
+#[cfg(feature = "zstd")]
+pub fn zstd_cached_resource_storage(
+    path: impl AsRef<Path>,
+    cache: impl AsRef<Path>,
+) -> io::Result<StorageHandle> {
+    cache_dir(path.as_ref(), cache.as_ref())?;
+    Ok(FileResourceStorage::new(cache.as_ref().to_path_buf()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_zstd_index() {
+        let index = parse_zstd_index("nodes 1200\ntags_index 40\n\n").unwrap();
+        assert_eq!(index.len(), 2);
+        assert_eq!(index["nodes"], 1200);
+        assert_eq!(index["tags_index"], 40);
+        assert!(parse_zstd_index("nodes").is_err());
+        assert!(parse_zstd_index("nodes -1").is_err());
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_resource_storage() {
+        let dir = std::env::temp_dir().join(format!("osmflat-zstd-{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        let data: Vec<u8> = (0..1000u32).map(|i| (i % 7) as u8).collect();
+        let compressed = zstd::encode_all(&data[..], 3).unwrap();
+        fs::write(dir.join("data.zst"), &compressed).unwrap();
+        fs::write(dir.join(ZSTD_INDEX), "data 1000\n").unwrap();
+        fs::write(dir.join("plain"), b"plain").unwrap();
+        fs::write(dir.join("sub/data.zst"), &compressed).unwrap();
+        fs::write(dir.join("sub").join(ZSTD_INDEX), "data 1000\n").unwrap();
+
+        let storage = zstd_resource_storage(&dir).unwrap();
+        assert_eq!(storage.read_resource("data").unwrap(), &data[..]);
+        assert_eq!(storage.read_resource("plain").unwrap(), b"plain");
+        assert_eq!(
+            storage.subdir("sub").read_resource("data").unwrap(),
+            &data[..]
+        );
+        assert!(!storage.exists("data.zst"));
+        assert!(!storage.exists(ZSTD_INDEX));
+
+        fs::write(dir.join(ZSTD_INDEX), "data 999\n").unwrap();
+        assert!(zstd_resource_storage(&dir).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_cached_resource_storage() {
+        let dir = std::env::temp_dir().join(format!("osmflat-zstd-cached-{}", std::process::id()));
+        let (archive, cache) = (dir.join("archive"), dir.join("cache"));
+        fs::create_dir_all(archive.join("sub")).unwrap();
+        let data: Vec<u8> = (0..1000u32).map(|i| (i % 7) as u8).collect();
+        let compressed = zstd::encode_all(&data[..], 3).unwrap();
+        fs::write(archive.join("data.zst"), &compressed).unwrap();
+        fs::write(archive.join(ZSTD_INDEX), "data 1000\n").unwrap();
+        fs::write(archive.join("plain"), b"plain").unwrap();
+        fs::write(archive.join("sub/data.zst"), &compressed).unwrap();
+        fs::write(archive.join("sub").join(ZSTD_INDEX), "data 1000\n").unwrap();
+
+        let storage = zstd_cached_resource_storage(&archive, &cache).unwrap();
+        assert_eq!(storage.read_resource("data").unwrap(), &data[..]);
+        assert_eq!(storage.read_resource("plain").unwrap(), b"plain");
+        assert_eq!(
+            storage.subdir("sub").read_resource("data").unwrap(),
+            &data[..]
+        );
+        assert!(!cache.join("data.zst").exists());
+        assert!(!cache.join(ZSTD_INDEX).exists());
+        drop(storage);
+
+        // a resource in the cache is reused, unless its compressed file is newer
+        fs::write(cache.join("data"), [0; 1000]).unwrap();
+        let storage = zstd_cached_resource_storage(&archive, &cache).unwrap();
+        assert_eq!(storage.read_resource("data").unwrap(), &[0; 1000][..]);
+        drop(storage);
+        let later = fs::metadata(cache.join("data"))
+            .unwrap()
+            .modified()
+            .unwrap()
+            + std::time::Duration::from_secs(1);
+        File::options()
+            .write(true)
+            .open(archive.join("data.zst"))
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        let storage = zstd_cached_resource_storage(&archive, &cache).unwrap();
+        assert_eq!(storage.read_resource("data").unwrap(), &data[..]);
+        drop(storage);
+
+        fs::write(archive.join(ZSTD_INDEX), "data 999\n").unwrap();
+        assert!(zstd_cached_resource_storage(&archive, dir.join("other")).is_err());
+        assert!(!dir.join("other/data.partial").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
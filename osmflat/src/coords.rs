@@ -0,0 +1,189 @@
+//! Coordinates of nodes in degrees.
+//!
+//! Nodes store their coordinates as integers scaled by `Header::coord_scale`,
+//! which differs between archives. [`CoordReader`] binds the scale of an
+//! archive once, instead of dividing by the scale at every use.
+//!
+//! Archives compiled with `osmflatc --node-coords` additionally store a copy
+//! of the coordinates in the `node_coords` vector, which holds 8 instead of 13
+//! bytes per node. [`Osm::node_coord`] and the bounding box computations read
+//! it if present; [`validate`](crate::validate) checks that both copies agree.
+
+use crate::{Centroid, Header, Node, NodeCoord, Osm};
+
+/// Converts the coordinates of the nodes of an archive to degrees.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use osmflat::{FileResourceStorage, Osm};
+///
+/// let archive = Osm::open(FileResourceStorage::new("path/to/archive.osm.flatdata")).unwrap();
+/// let coords = archive.coord_reader();
+/// for node in archive.nodes() {
+///     let (lon, lat) = coords.coord(node);
+///     println!("{lon} {lat}");
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoordReader {
+    scale: f64,
+}
+
+impl CoordReader {
+    /// Creates a reader with the coordinate scale of `header`.
+    pub fn new(header: &Header) -> Self {
+        Self {
+            scale: f64::from(header.coord_scale()),
+        }
+    }
+
+    /// Returns the longitude of `node` in degrees.
+    pub fn lon(&self, node: &Node) -> f64 {
+        f64::from(node.lon()) / self.scale
+    }
+
+    /// Returns the latitude of `node` in degrees.
+    pub fn lat(&self, node: &Node) -> f64 {
+        f64::from(node.lat()) / self.scale
+    }
+
+    /// Returns the coordinates of `node` as `(lon, lat)` in degrees.
+    pub fn coord(&self, node: &Node) -> (f64, f64) {
+        (self.lon(node), self.lat(node))
+    }
+
+    /// Returns the coordinates of an element of the `node_coords` vector as
+    /// `(lon, lat)` in degrees.
+    pub fn coord_of(&self, coord: &NodeCoord) -> (f64, f64) {
+        (
+            f64::from(coord.lon()) / self.scale,
+            f64::from(coord.lat()) / self.scale,
+        )
+    }
+
+    /// Returns the coordinates of an element of the `way_centroids` or
+    /// `relation_centroids` vectors as `(lon, lat)` in degrees.
+    ///
+    /// Returns `None` if the entity has no centroid.
+    pub fn centroid_of(&self, centroid: &Centroid) -> Option<(f64, f64)> {
+        let lat = centroid.lat()?;
+        Some((
+            f64::from(centroid.lon()) / self.scale,
+            f64::from(lat) / self.scale,
+        ))
+    }
+}
+
+impl Osm {
+    /// Returns a reader of the coordinates of the nodes in degrees.
+    pub fn coord_reader(&self) -> CoordReader {
+        CoordReader::new(self.header())
+    }
+
+    /// Returns the coordinates of the node with the given index as
+    /// `(lon, lat)` in degrees.
+    ///
+    /// The coordinates are read from the `node_coords` vector if present.
+    ///
+    /// Panics if the index is out of bounds.
+    pub fn node_coord(&self, idx: usize) -> (f64, f64) {
+        let coords = self.coord_reader();
+        match self.node_coords() {
+            Some(node_coords) => coords.coord_of(&node_coords[idx]),
+            None => coords.coord(&self.nodes()[idx]),
+        }
+    }
+
+    /// Returns the coordinates of the node with the given index as
+    /// `(lon, lat)` scaled with `Header::coord_scale`.
+    ///
+    /// The coordinates are read from the `node_coords` vector if present.
+    ///
+    /// Panics if the index is out of bounds.
+    pub fn node_coord_scaled(&self, idx: usize) -> (i32, i32) {
+        match self.node_coords() {
+            Some(node_coords) => (node_coords[idx].lon(), node_coords[idx].lat()),
+            None => (self.nodes()[idx].lon(), self.nodes()[idx].lat()),
+        }
+    }
+
+    /// Returns the elevation of the node with the given index in meters.
+    ///
+    /// Returns `None` if the elevation is unknown, or if the archive does not
+    /// contain the `elevation` vector, cf. `osmflatc --elevation`. Panics if
+    /// the index is out of bounds.
+    pub fn node_elevation(&self, idx: usize) -> Option<i16> {
+        self.elevation()?[idx].value()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::OsmWriter;
+
+    #[test]
+    fn test_node_coord() {
+        let storage = flatdata::MemoryResourceStorage::new("/test");
+        let mut writer = OsmWriter::new(storage.clone()).unwrap();
+        writer.add_node(13.3777, 52.5163, None::<(&str, &str)>);
+        writer.add_node(-180.0, -90.0, None::<(&str, &str)>);
+        writer.finish().unwrap();
+        let archive = Osm::open(storage).unwrap();
+
+        assert_eq!(archive.node_coord(0), (13.3777, 52.5163));
+        assert_eq!(archive.node_coord(1), (-180.0, -90.0));
+
+        let mut header = Header::new();
+        header.set_coord_scale(1000);
+        let coords = CoordReader::new(&header);
+        let node = &archive.nodes()[0];
+        assert_eq!((coords.lon(node), coords.lat(node)), (133_777.0, 525_163.0));
+    }
+
+    #[test]
+    fn test_node_coords() {
+        let storage = flatdata::MemoryResourceStorage::new("/test");
+        let builder = crate::OsmBuilder::new(storage.clone()).unwrap();
+        let mut header = Header::new();
+        header.set_coord_scale(10);
+        builder.set_header(&header).unwrap();
+        let mut nodes = builder.start_nodes().unwrap();
+        let mut node_coords = builder.start_node_coords().unwrap();
+        for (lon, lat) in [(15, -25), (-180, 90)] {
+            let node = nodes.grow().unwrap();
+            node.set_lon(lon);
+            node.set_lat(lat);
+            // differs from the node, to check which one is read
+            let coord = node_coords.grow().unwrap();
+            coord.set_lon(lon * 2);
+            coord.set_lat(lat * 2);
+        }
+        nodes.grow().unwrap();
+        nodes.close().unwrap();
+        node_coords.close().unwrap();
+        builder.start_ways().unwrap().close().unwrap();
+        builder.start_relations().unwrap().close().unwrap();
+        builder.start_relation_members().unwrap().close().unwrap();
+        builder.start_tags().unwrap().close().unwrap();
+        builder.start_tags_index().unwrap().close().unwrap();
+        builder.start_nodes_index().unwrap().close().unwrap();
+        builder.set_stringtable(b"").unwrap();
+        let mut elevation = builder.start_elevation().unwrap();
+        elevation.grow().unwrap().set_value(Some(34));
+        elevation.grow().unwrap().set_value(None);
+        elevation.close().unwrap();
+        let archive = Osm::open(storage).unwrap();
+
+        assert_eq!(archive.node_coords().unwrap().len(), 2);
+        assert_eq!(archive.node_coord(0), (3.0, -5.0));
+        assert_eq!(archive.node_coord_scaled(1), (-360, 180));
+        let coords = archive.coord_reader();
+        assert_eq!(coords.coord(&archive.nodes()[0]), (1.5, -2.5));
+        let nodes: Vec<_> = archive.nodes_in_bbox(2.0, -6.0, 4.0, -4.0).collect();
+        assert_eq!(nodes, [0]);
+        assert_eq!(archive.node_elevation(0), Some(34));
+        assert_eq!(archive.node_elevation(1), None);
+    }
+}
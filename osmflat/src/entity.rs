@@ -0,0 +1,228 @@
+//! Common access to nodes, ways, and relations.
+//!
+//! [`EntityType`] names the type of an entity. [`OsmEntity`] exposes what all
+//! entities have in common, so generic code, e.g. exporters or tag filters, is
+//! written once for all entity types, either generic over the trait or on the
+//! [`Entity`] enum.
+
+use crate::{Node, Osm, Relation, Way};
+
+use std::ops::Range;
+
+/// Type of an OSM entity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EntityType {
+    /// Entity in the `nodes` vector
+    Node,
+    /// Entity in the `ways` vector
+    Way,
+    /// Entity in the `relations` vector
+    Relation,
+}
+
+/// Properties common to nodes, ways, and relations.
+pub trait OsmEntity {
+    /// Type of the entity.
+    fn entity_type(&self) -> EntityType;
+
+    /// Range of the tags of the entity in the `tags_index` vector.
+    fn tags(&self) -> Range<u64>;
+
+    /// Index of the entity in its vector of `archive`, or `None` if the
+    /// entity is not an element of the archive.
+    fn index(&self, archive: &Osm) -> Option<usize>;
+
+    /// Signed OSM id of the entity in `archive`.
+    ///
+    /// Returns `None` if the entity is not an element of the archive, or if
+    /// the archive does not contain the `ids` subarchive.
+    fn id(&self, archive: &Osm) -> Option<i64> {
+        let idx = self.index(archive)?;
+        let ids = archive.ids()?;
+        let ids = match self.entity_type() {
+            EntityType::Node => ids.nodes(),
+            EntityType::Way => ids.ways(),
+            EntityType::Relation => ids.relations(),
+        };
+        Some(ids.get(idx)?.signed_value())
+    }
+}
+
+/// Index of `item` in `slice`, if it is one of its elements.
+fn index_of<T>(slice: &[T], item: &T) -> Option<usize> {
+    let size = std::mem::size_of::<T>();
+    let offset = (item as *const T as usize).checked_sub(slice.as_ptr() as usize)?;
+    (offset % size == 0 && offset / size < slice.len()).then_some(offset / size)
+}
+
+impl OsmEntity for Node {
+    fn entity_type(&self) -> EntityType {
+        EntityType::Node
+    }
+
+    fn tags(&self) -> Range<u64> {
+        Node::tags(self)
+    }
+
+    fn index(&self, archive: &Osm) -> Option<usize> {
+        index_of(archive.nodes(), self)
+    }
+}
+
+impl OsmEntity for Way {
+    fn entity_type(&self) -> EntityType {
+        EntityType::Way
+    }
+
+    fn tags(&self) -> Range<u64> {
+        Way::tags(self)
+    }
+
+    fn index(&self, archive: &Osm) -> Option<usize> {
+        index_of(archive.ways(), self)
+    }
+}
+
+impl OsmEntity for Relation {
+    fn entity_type(&self) -> EntityType {
+        EntityType::Relation
+    }
+
+    fn tags(&self) -> Range<u64> {
+        Relation::tags(self)
+    }
+
+    fn index(&self, archive: &Osm) -> Option<usize> {
+        index_of(archive.relations(), self)
+    }
+}
+
+/// Node, way, or relation of an archive.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use osmflat::{iter_tags_lossy, FileResourceStorage, Osm, OsmEntity};
+///
+/// let archive = Osm::open(FileResourceStorage::new("path/to/archive.osm.flatdata")).unwrap();
+/// for entity in archive.entities() {
+///     let tags: Vec<_> = iter_tags_lossy(&archive, entity.tags()).collect();
+///     println!("{:?} {:?} {tags:?}", entity.entity_type(), entity.id(&archive));
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Entity<'a> {
+    /// Node
+    Node(&'a Node),
+    /// Way
+    Way(&'a Way),
+    /// Relation
+    Relation(&'a Relation),
+}
+
+impl Entity<'_> {
+    fn as_dyn(&self) -> &dyn OsmEntity {
+        match *self {
+            Entity::Node(node) => node,
+            Entity::Way(way) => way,
+            Entity::Relation(relation) => relation,
+        }
+    }
+}
+
+impl OsmEntity for Entity<'_> {
+    fn entity_type(&self) -> EntityType {
+        self.as_dyn().entity_type()
+    }
+
+    fn tags(&self) -> Range<u64> {
+        self.as_dyn().tags()
+    }
+
+    fn index(&self, archive: &Osm) -> Option<usize> {
+        self.as_dyn().index(archive)
+    }
+
+    fn id(&self, archive: &Osm) -> Option<i64> {
+        self.as_dyn().id(archive)
+    }
+}
+
+impl Osm {
+    /// Returns the entity of type `entity_type` at index `idx`.
+    ///
+    /// Panics if the index is out of bounds.
+    pub fn entity(&self, entity_type: EntityType, idx: usize) -> Entity<'_> {
+        match entity_type {
+            EntityType::Node => Entity::Node(&self.nodes()[idx]),
+            EntityType::Way => Entity::Way(&self.ways()[idx]),
+            EntityType::Relation => Entity::Relation(&self.relations()[idx]),
+        }
+    }
+
+    /// Returns an iterator over all nodes, ways, and relations, in this order.
+    pub fn entities(&self) -> impl Iterator<Item = Entity<'_>> + Clone {
+        let nodes = self.nodes().iter().map(Entity::Node);
+        let ways = self.ways().iter().map(Entity::Way);
+        let relations = self.relations().iter().map(Entity::Relation);
+        nodes.chain(ways).chain(relations)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{find_tag, Id, IdsBuilder, Member, OsmWriter};
+    use flatdata::ResourceStorage;
+
+    #[test]
+    fn test_entities() {
+        let storage = flatdata::MemoryResourceStorage::new("/test");
+        let mut writer = OsmWriter::new(storage.clone()).unwrap();
+        let a = writer.add_node(0.0, 0.0, [("name", "a")]);
+        let b = writer.add_node(1.0, 0.0, [("name", "b")]);
+        let way = writer.add_way(&[a, b], [("name", "w")]);
+        writer.add_relation(&[Member::Way(way, "")], [("name", "r")]);
+        writer.finish().unwrap();
+        let ids = |ids: &[i64]| -> Vec<Id> {
+            ids.iter()
+                .map(|&x| {
+                    let mut id = Id::new();
+                    id.set_signed_value(x);
+                    id
+                })
+                .collect()
+        };
+        let builder = IdsBuilder::new(storage.subdir("ids")).unwrap();
+        builder.set_nodes(&ids(&[10, -11])).unwrap();
+        builder.set_ways(&ids(&[20])).unwrap();
+        builder.set_relations(&ids(&[30])).unwrap();
+        let archive = Osm::open(storage).unwrap();
+
+        let entities: Vec<_> = archive
+            .entities()
+            .map(|e| (e.entity_type(), e.index(&archive), e.id(&archive)))
+            .collect();
+        assert_eq!(
+            entities,
+            [
+                (EntityType::Node, Some(0), Some(10)),
+                (EntityType::Node, Some(1), Some(-11)),
+                (EntityType::Way, Some(0), Some(20)),
+                (EntityType::Relation, Some(0), Some(30)),
+            ]
+        );
+        let entity = archive.entity(EntityType::Way, 0);
+        assert_eq!(find_tag(&archive, entity.tags(), b"name"), Some(&b"w"[..]));
+        assert_eq!(entity, Entity::Way(&archive.ways()[0]));
+
+        // entities of other archives are not found
+        let storage = flatdata::MemoryResourceStorage::new("/other");
+        let mut writer = OsmWriter::new(storage.clone()).unwrap();
+        writer.add_node(0.0, 0.0, None::<(&str, &str)>);
+        writer.finish().unwrap();
+        let other = Osm::open(storage).unwrap();
+        assert_eq!(other.nodes()[0].index(&archive), None);
+        assert_eq!(other.nodes()[0].id(&archive), None);
+    }
+}
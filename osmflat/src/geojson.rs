@@ -0,0 +1,92 @@
+//! Conversion of entities to GeoJSON features, enabled by the feature
+//! `geojson`.
+
+use crate::areas::way_ring;
+use crate::geometry::{multipolygon, way_coords};
+use crate::{find_tag, iter_tags_lossy, EntityType, Osm, RelationMembersRef};
+
+use ::geojson::{feature::Id, Feature, Geometry, JsonObject, JsonValue};
+
+fn way_geometry(archive: &Osm, idx: usize) -> Option<Geometry> {
+    let way = &archive.ways()[idx];
+    if let Some(ring) = way_ring(archive, way) {
+        let coords = ring.into_iter().map(|(_, coord)| coord);
+        return Some(Geometry::new_polygon([coords]));
+    }
+    let coords = way_coords(archive, way)?;
+    Some(Geometry::new_line_string(coords))
+}
+
+fn relation_geometry(archive: &Osm, idx: usize) -> Option<Geometry> {
+    let relation = &archive.relations()[idx];
+    if matches!(
+        find_tag(archive, relation.tags(), b"type"),
+        Some(b"multipolygon" | b"boundary")
+    ) {
+        let polygons = multipolygon(archive, idx).ok()?;
+        return Some(Geometry::new_multi_polygon(
+            polygons
+                .into_iter()
+                .map(|p| std::iter::once(p.outer).chain(p.inner)),
+        ));
+    }
+    let geometries = archive
+        .relation_members()
+        .at(idx)
+        .filter_map(|member| match member {
+            RelationMembersRef::NodeMember(m) => Some(Geometry::new_point(
+                archive.node_coord(m.node_idx()? as usize),
+            )),
+            RelationMembersRef::WayMember(m) => way_geometry(archive, m.way_idx()? as usize),
+            RelationMembersRef::RelationMember(_) => None,
+        });
+    Some(Geometry::new_geometry_collection(geometries))
+}
+
+/// Returns the entity of type `entity_type` at `idx` as GeoJSON feature.
+///
+/// Nodes are converted to `Point`s, closed ways to `Polygon`s with a
+/// counterclockwise ring unless they are tagged `area=no`, other ways to
+/// `LineString`s, relations of type `multipolygon` or `boundary` to
+/// `MultiPolygon`s, cf. [`geometry::multipolygon`], and other relations to
+/// `GeometryCollection`s of their node and way members. The geometry is `null` if it cannot be built,
+/// e.g. because of unresolved nodes.
+///
+/// The tags are the properties of the feature. If the archive contains the
+/// `ids` subarchive, the id of the feature is the OSM id prefixed with the
+/// type, e.g. `w123`, as in `osmium export`.
+///
+/// [`geometry::multipolygon`]: crate::geometry::multipolygon
+pub fn to_geojson_feature(archive: &Osm, entity_type: EntityType, idx: usize) -> Feature {
+    let (geometry, tags, prefix) = match entity_type {
+        EntityType::Node => (
+            Some(Geometry::new_point(archive.node_coord(idx))),
+            archive.nodes()[idx].tags(),
+            'n',
+        ),
+        EntityType::Way => (way_geometry(archive, idx), archive.ways()[idx].tags(), 'w'),
+        EntityType::Relation => (
+            relation_geometry(archive, idx),
+            archive.relations()[idx].tags(),
+            'r',
+        ),
+    };
+    let properties: JsonObject = iter_tags_lossy(archive, tags)
+        .map(|(key, value)| (key.into_owned(), JsonValue::from(value.into_owned())))
+        .collect();
+    let id = archive.ids().map(|ids| {
+        let ids = match entity_type {
+            EntityType::Node => ids.nodes(),
+            EntityType::Way => ids.ways(),
+            EntityType::Relation => ids.relations(),
+        };
+        Id::String(format!("{prefix}{}", ids[idx].signed_value()))
+    });
+    Feature {
+        bbox: None,
+        geometry,
+        id,
+        properties: Some(properties),
+        foreign_members: None,
+    }
+}
@@ -0,0 +1,416 @@
+//! Geometries of entities, and exact intersection tests of geometries with
+//! bounding boxes.
+//!
+//! Coordinates are `(lon, lat)`; the intersection tests use the coordinates
+//! of an archive, cf. `Header::coord_scale`.
+
+use crate::{BBox, Osm, RelationMembersRef, Way};
+
+use std::fmt;
+
+#[cfg(feature = "geo")]
+mod geo;
+mod wkb;
+
+#[cfg(feature = "geo")]
+pub use self::geo::*;
+pub use self::wkb::*;
+
+/// Returns the coordinates of the nodes of `way` in degrees.
+///
+/// Returns `None` if one of the nodes is not resolved in the archive, e.g.
+/// when it was cut off by an extract.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use osmflat::{geometry, FileResourceStorage, Osm};
+///
+/// let archive = Osm::open(FileResourceStorage::new("path/to/archive.osm.flatdata")).unwrap();
+/// for way in archive.ways() {
+///     if let Some(coords) = geometry::way_coords(&archive, way) {
+///         let coords: Vec<(f64, f64)> = coords.collect();
+///         println!("{coords:?}");
+///     }
+/// }
+/// ```
+pub fn way_coords<'a>(
+    archive: &'a Osm,
+    way: &Way,
+) -> Option<impl Iterator<Item = (f64, f64)> + Clone + 'a> {
+    let nodes = archive.nodes();
+    let nodes_index = archive.nodes_index();
+    let coords = archive.coord_reader();
+    let path = way.refs().map(move |idx| nodes_index[idx as usize].value());
+    if path.clone().any(|node_idx| node_idx.is_none()) {
+        return None;
+    }
+    Some(
+        path.flatten()
+            .map(move |node_idx| coords.coord(&nodes[node_idx as usize])),
+    )
+}
+
+fn contains(bbox: &BBox, (lon, lat): (i32, i32)) -> bool {
+    bbox.left <= lon && lon <= bbox.right && bbox.bottom <= lat && lat <= bbox.top
+}
+
+/// Sign of the cross product of `b - a` and `c - a`.
+fn orientation(a: (i32, i32), b: (i32, i32), c: (i32, i32)) -> i64 {
+    let (ax, ay) = (i64::from(a.0), i64::from(a.1));
+    let cross = (i64::from(b.0) - ax) * (i64::from(c.1) - ay)
+        - (i64::from(b.1) - ay) * (i64::from(c.0) - ax);
+    cross.signum()
+}
+
+/// Whether the collinear point `p` lies on the segment `a`-`b`.
+fn on_segment(a: (i32, i32), b: (i32, i32), p: (i32, i32)) -> bool {
+    a.0.min(b.0) <= p.0 && p.0 <= a.0.max(b.0) && a.1.min(b.1) <= p.1 && p.1 <= a.1.max(b.1)
+}
+
+fn segments_intersect(a: (i32, i32), b: (i32, i32), c: (i32, i32), d: (i32, i32)) -> bool {
+    let (o1, o2) = (orientation(a, b, c), orientation(a, b, d));
+    let (o3, o4) = (orientation(c, d, a), orientation(c, d, b));
+    (o1 != o2 && o3 != o4)
+        || (o1 == 0 && on_segment(a, b, c))
+        || (o2 == 0 && on_segment(a, b, d))
+        || (o3 == 0 && on_segment(c, d, a))
+        || (o4 == 0 && on_segment(c, d, b))
+}
+
+/// Polygon with an outer ring and holes, cf. [`multipolygon`].
+///
+/// Rings are closed, i.e. their first and last coordinates are equal. Outer
+/// rings are counterclockwise and holes clockwise, as required by GeoJSON.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polygon {
+    /// Coordinates of the outer ring in degrees
+    pub outer: Vec<(f64, f64)>,
+    /// Coordinates of the holes in degrees
+    pub inner: Vec<Vec<(f64, f64)>>,
+}
+
+/// Error assembling the polygons of a relation, cf. [`multipolygon`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum MultipolygonError {
+    /// The way member at the given position is not resolved in the archive.
+    UnresolvedWay(usize),
+    /// A node of the way at the given index is not resolved in the archive.
+    UnresolvedNode(u64),
+    /// The way at the given index has no nodes.
+    EmptyWay(u64),
+    /// The ways cannot be joined into a closed ring, which ends at the given
+    /// coordinates.
+    UnclosedRing((f64, f64)),
+    /// The inner ring starting at the given coordinates is outside of all
+    /// outer rings.
+    InnerRingOutside((f64, f64)),
+    /// The relation has no outer ring.
+    NoOuterRing,
+}
+
+impl fmt::Display for MultipolygonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnresolvedWay(member) => write!(f, "unresolved way member {member}"),
+            Self::UnresolvedNode(way_idx) => write!(f, "unresolved node in way {way_idx}"),
+            Self::EmptyWay(way_idx) => write!(f, "empty way {way_idx}"),
+            Self::UnclosedRing(coord) => write!(f, "ring is not closed at {coord:?}"),
+            Self::InnerRingOutside(coord) => {
+                write!(f, "inner ring at {coord:?} is outside of all outer rings")
+            }
+            Self::NoOuterRing => write!(f, "no outer ring"),
+        }
+    }
+}
+
+impl std::error::Error for MultipolygonError {}
+
+/// Node of a ring: its index in the `nodes` vector and its coordinates in
+/// degrees.
+pub(crate) type Vertex = (u64, (f64, f64));
+
+/// Returns the nodes of `way`, or `None` if one of them is not resolved in the
+/// archive.
+pub(crate) fn way_vertices(archive: &Osm, way: &Way) -> Option<Vec<Vertex>> {
+    let nodes = archive.nodes();
+    let nodes_index = archive.nodes_index();
+    let coords = archive.coord_reader();
+    way.refs()
+        .map(|idx| {
+            let node_idx = nodes_index[idx as usize].value()?;
+            Some((node_idx, coords.coord(&nodes[node_idx as usize])))
+        })
+        .collect()
+}
+
+fn is_closed(ring: &[Vertex]) -> bool {
+    ring.len() >= 4 && ring.first().map(|v| v.1) == ring.last().map(|v| v.1)
+}
+
+/// Joins line strings into closed rings at their common endpoints.
+fn assemble_rings(mut lines: Vec<Vec<Vertex>>) -> Result<Vec<Vec<Vertex>>, MultipolygonError> {
+    let mut rings = Vec::new();
+    while let Some(mut ring) = lines.pop() {
+        while !is_closed(&ring) {
+            let last = ring.last().expect("empty ways are rejected").1;
+            let pos = lines
+                .iter()
+                .position(|l| {
+                    l.first().map(|v| v.1) == Some(last) || l.last().map(|v| v.1) == Some(last)
+                })
+                .ok_or(MultipolygonError::UnclosedRing(last))?;
+            let mut line = lines.swap_remove(pos);
+            if line.first().map(|v| v.1) != Some(last) {
+                line.reverse();
+            }
+            ring.extend(line.into_iter().skip(1));
+        }
+        rings.push(ring);
+    }
+    Ok(rings)
+}
+
+/// Signed area of a closed ring in degrees², positive if counterclockwise.
+pub(crate) fn signed_area(ring: &[Vertex]) -> f64 {
+    ring.windows(2)
+        .map(|w| {
+            let ((x0, y0), (x1, y1)) = (w[0].1, w[1].1);
+            x0 * y1 - x1 * y0
+        })
+        .sum::<f64>()
+        / 2.0
+}
+
+/// Whether `(x, y)` is inside the closed ring, following the even-odd rule.
+pub(crate) fn ring_contains(ring: &[Vertex], (x, y): (f64, f64)) -> bool {
+    let mut inside = false;
+    for w in ring.windows(2) {
+        let ((x0, y0), (x1, y1)) = (w[0].1, w[1].1);
+        if (y0 > y) != (y1 > y) && x < (x1 - x0) * (y - y0) / (y1 - y0) + x0 {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Assembles the polygons of the multipolygon or boundary relation at
+/// `relation_idx`.
+///
+/// The way members with role `inner` are joined into the holes, all other way
+/// members into the outer rings, where ways are joined at common endpoints
+/// independently of their order and direction. Each hole is assigned to the
+/// smallest outer ring containing it. Node and relation members are ignored.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use osmflat::{geometry, FileResourceStorage, Osm};
+///
+/// let archive = Osm::open(FileResourceStorage::new("path/to/archive.osm.flatdata")).unwrap();
+/// match geometry::multipolygon(&archive, 0) {
+///     Ok(polygons) => println!("{} polygons", polygons.len()),
+///     Err(e) => eprintln!("invalid multipolygon: {e}"),
+/// }
+/// ```
+pub fn multipolygon(archive: &Osm, relation_idx: usize) -> Result<Vec<Polygon>, MultipolygonError> {
+    let coords = |ring: Vec<Vertex>| ring.into_iter().map(|v| v.1).collect();
+    let polygons = multipolygon_rings(archive, relation_idx)?
+        .into_iter()
+        .map(|rings| {
+            let mut rings = rings.into_iter().map(coords);
+            Polygon {
+                outer: rings.next().expect("polygon without outer ring"),
+                inner: rings.collect(),
+            }
+        })
+        .collect();
+    Ok(polygons)
+}
+
+/// Assembles the rings of the polygons of a relation, cf. [`multipolygon`].
+///
+/// Each polygon consists of its outer ring followed by its holes.
+pub(crate) fn multipolygon_rings(
+    archive: &Osm,
+    relation_idx: usize,
+) -> Result<Vec<Vec<Vec<Vertex>>>, MultipolygonError> {
+    let strings = archive.stringtable();
+    let ways = archive.ways();
+
+    let mut outer = Vec::new();
+    let mut inner = Vec::new();
+    for (pos, member) in archive.relation_members().at(relation_idx).enumerate() {
+        let m = match member {
+            RelationMembersRef::WayMember(m) => m,
+            _ => continue,
+        };
+        let way_idx = m.way_idx().ok_or(MultipolygonError::UnresolvedWay(pos))?;
+        let vertices = way_vertices(archive, &ways[way_idx as usize])
+            .ok_or(MultipolygonError::UnresolvedNode(way_idx))?;
+        if vertices.is_empty() {
+            return Err(MultipolygonError::EmptyWay(way_idx));
+        }
+        match strings.substring_raw(m.role_idx() as usize) {
+            b"inner" => inner.push(vertices),
+            _ => outer.push(vertices),
+        }
+    }
+
+    let mut polygons: Vec<Vec<Vec<Vertex>>> = assemble_rings(outer)?
+        .into_iter()
+        .map(|mut ring| {
+            if signed_area(&ring) < 0.0 {
+                ring.reverse();
+            }
+            vec![ring]
+        })
+        .collect();
+    if polygons.is_empty() {
+        return Err(MultipolygonError::NoOuterRing);
+    }
+    for mut ring in assemble_rings(inner)? {
+        if signed_area(&ring) > 0.0 {
+            ring.reverse();
+        }
+        let coord = ring[0].1;
+        let polygon = polygons
+            .iter_mut()
+            .filter(|p| ring_contains(&p[0], coord))
+            .min_by(|a, b| signed_area(&a[0]).total_cmp(&signed_area(&b[0])))
+            .ok_or(MultipolygonError::InnerRingOutside(coord))?;
+        polygon.push(ring);
+    }
+    Ok(polygons)
+}
+
+/// Whether the segment `a`-`b` has a point in common with `bbox`.
+pub(crate) fn segment_intersects(a: (i32, i32), b: (i32, i32), bbox: &BBox) -> bool {
+    if contains(bbox, a) || contains(bbox, b) {
+        return true;
+    }
+    let segment_bbox = BBox {
+        left: a.0.min(b.0),
+        bottom: a.1.min(b.1),
+        right: a.0.max(b.0),
+        top: a.1.max(b.1),
+    };
+    if !segment_bbox.intersects(bbox) {
+        return false;
+    }
+    let corners = [
+        (bbox.left, bbox.bottom),
+        (bbox.right, bbox.bottom),
+        (bbox.right, bbox.top),
+        (bbox.left, bbox.top),
+    ];
+    (0..4).any(|i| segments_intersect(a, b, corners[i], corners[(i + 1) % 4]))
+}
+
+/// Whether the polyline through `coords` has a point in common with `bbox`.
+pub(crate) fn polyline_intersects(coords: &[(i32, i32)], bbox: &BBox) -> bool {
+    match coords {
+        [] => false,
+        [coord] => contains(bbox, *coord),
+        _ => coords
+            .windows(2)
+            .any(|w| segment_intersects(w[0], w[1], bbox)),
+    }
+}
+
+/// Whether `point` is inside the area bounded by the rings formed by
+/// `segments`, following the even-odd rule.
+///
+/// The segments need not be ordered, so rings split into several ways, e.g.
+/// of multipolygon relations, are supported.
+pub(crate) fn area_contains(
+    segments: impl IntoIterator<Item = ((i32, i32), (i32, i32))>,
+    point: (i32, i32),
+) -> bool {
+    let (px, py) = (i64::from(point.0), i64::from(point.1));
+    let mut inside = false;
+    for (a, b) in segments {
+        let (ax, ay, bx, by) = (
+            i64::from(a.0),
+            i64::from(a.1),
+            i64::from(b.0),
+            i64::from(b.1),
+        );
+        if (ay > py) != (by > py) {
+            // x coordinate of the crossing of the segment with the horizontal
+            // line through the point, compared without division
+            let lhs = (px - ax) * (by - ay);
+            let rhs = (bx - ax) * (py - ay);
+            if (by > ay && lhs < rhs) || (by < ay && lhs > rhs) {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const BBOX: BBox = BBox {
+        left: 0,
+        bottom: 0,
+        right: 10,
+        top: 10,
+    };
+
+    #[test]
+    fn test_segment_intersects() {
+        assert!(segment_intersects((5, 5), (20, 20), &BBOX));
+        assert!(segment_intersects((-5, 5), (15, 5), &BBOX));
+        assert!(segment_intersects((-5, 15), (15, -5), &BBOX));
+        assert!(segment_intersects((-5, 10), (15, 10), &BBOX));
+        assert!(!segment_intersects((-5, 11), (15, 11), &BBOX));
+        assert!(!segment_intersects((-5, 4), (4, -5), &BBOX));
+        assert!(segment_intersects((-5, 5), (5, -5), &BBOX));
+    }
+
+    #[test]
+    fn test_area_contains() {
+        let square = [(-5, -5), (15, -5), (15, 15), (-5, 15), (-5, -5)];
+        let segments = || square.windows(2).map(|w| (w[0], w[1]));
+        assert!(area_contains(segments(), (0, 0)));
+        assert!(!area_contains(segments(), (20, 0)));
+        assert!(!polyline_intersects(&square, &BBOX));
+
+        // ring split into two unordered parts with a hole
+        let hole = [(2, 2), (8, 2), (8, 8), (2, 8), (2, 2)];
+        let segments = square[2..]
+            .windows(2)
+            .chain(square[..3].windows(2))
+            .chain(hole.windows(2))
+            .map(|w| (w[0], w[1]));
+        assert!(!area_contains(segments.clone(), (5, 5)));
+        assert!(area_contains(segments, (0, 0)));
+    }
+
+    #[test]
+    fn test_assemble_rings() {
+        let line = |coords: &[(f64, f64)]| coords.iter().map(|&c| (0, c)).collect::<Vec<_>>();
+        let lines = vec![
+            line(&[(0.0, 0.0), (1.0, 0.0)]),
+            line(&[(0.0, 1.0), (1.0, 1.0)]),
+            line(&[(1.0, 0.0), (1.0, 1.0)]),
+            line(&[(0.0, 1.0), (0.0, 0.0)]),
+        ];
+        let rings = assemble_rings(lines).unwrap();
+        assert_eq!(rings.len(), 1);
+        assert!(is_closed(&rings[0]));
+        assert_eq!(signed_area(&rings[0]).abs(), 1.0);
+
+        let lines = vec![
+            line(&[(0.0, 0.0), (1.0, 0.0)]),
+            line(&[(1.0, 0.0), (1.0, 1.0)]),
+        ];
+        assert_eq!(
+            assemble_rings(lines),
+            Err(MultipolygonError::UnclosedRing((1.0, 1.0)))
+        );
+    }
+}
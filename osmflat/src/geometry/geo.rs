@@ -0,0 +1,73 @@
+//! Conversions of entities to [`geo_types`] geometries, enabled by the feature
+//! `geo`.
+//!
+//! Coordinates are `x = lon` and `y = lat` in degrees, so the algorithms of
+//! the `geo` crate work directly on the entities of an archive.
+
+use super::{multipolygon, way_coords, MultipolygonError, Polygon};
+use crate::{Node, Osm, Way};
+
+use geo_types::{Coord, LineString, MultiPolygon, Point};
+
+fn to_line_string(coords: Vec<(f64, f64)>) -> LineString<f64> {
+    coords.into_iter().map(Coord::from).collect()
+}
+
+impl From<Polygon> for geo_types::Polygon<f64> {
+    fn from(polygon: Polygon) -> Self {
+        geo_types::Polygon::new(
+            to_line_string(polygon.outer),
+            polygon.inner.into_iter().map(to_line_string).collect(),
+        )
+    }
+}
+
+/// Returns the location of `node` as point.
+pub fn node_point(archive: &Osm, node: &Node) -> Point<f64> {
+    archive.coord_reader().coord(node).into()
+}
+
+/// Returns the line string through the nodes of `way`.
+///
+/// Returns `None` if one of the nodes is not resolved in the archive.
+pub fn way_line_string(archive: &Osm, way: &Way) -> Option<LineString<f64>> {
+    Some(way_coords(archive, way)?.map(Coord::from).collect())
+}
+
+/// Returns the polygon bounded by the closed `way`.
+///
+/// Returns `None` if the way is not closed, or if one of the nodes is not
+/// resolved in the archive.
+pub fn way_polygon(archive: &Osm, way: &Way) -> Option<geo_types::Polygon<f64>> {
+    let line_string = way_line_string(archive, way)?;
+    (line_string.0.len() >= 4 && line_string.is_closed())
+        .then(|| geo_types::Polygon::new(line_string, Vec::new()))
+}
+
+/// Returns the multipolygon assembled from the relation at `relation_idx`, cf.
+/// [`multipolygon`].
+pub fn relation_multi_polygon(
+    archive: &Osm,
+    relation_idx: usize,
+) -> Result<MultiPolygon<f64>, MultipolygonError> {
+    let polygons = multipolygon(archive, relation_idx)?;
+    Ok(polygons.into_iter().map(geo_types::Polygon::from).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_polygon_from() {
+        let polygon = Polygon {
+            outer: vec![(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 0.0)],
+            inner: vec![vec![(1.0, 0.5), (2.0, 1.5), (3.0, 0.5), (1.0, 0.5)]],
+        };
+        let polygon = geo_types::Polygon::from(polygon);
+        assert_eq!(polygon.exterior().0.len(), 4);
+        assert_eq!(polygon.exterior().0[1], Coord { x: 4.0, y: 0.0 });
+        assert_eq!(polygon.interiors().len(), 1);
+        assert_eq!(polygon.interiors()[0].0[1], Coord { x: 2.0, y: 1.5 });
+    }
+}
@@ -0,0 +1,224 @@
+//! Serialization of geometries as WKB, EWKB and WKT.
+//!
+//! WKB is written in little endian byte order. EWKB additionally contains the
+//! SRID of the coordinates, which are WGS 84 (SRID 4326) in osmflat archives,
+//! and can be inserted into PostGIS directly.
+
+use super::{way_coords, Polygon};
+use crate::{Osm, Way};
+
+use std::fmt::Write;
+
+/// SRID of the WGS 84 coordinates of osmflat archives, cf. [`way_to_wkb`].
+pub const WGS84_SRID: u32 = 4326;
+
+const WKB_POINT: u32 = 1;
+const WKB_LINE_STRING: u32 = 2;
+const WKB_POLYGON: u32 = 3;
+const WKB_MULTI_POLYGON: u32 = 6;
+const EWKB_SRID_FLAG: u32 = 0x2000_0000;
+
+fn write_header(buf: &mut Vec<u8>, geometry_type: u32, srid: Option<u32>) {
+    buf.push(1);
+    match srid {
+        Some(srid) => {
+            buf.extend((geometry_type | EWKB_SRID_FLAG).to_le_bytes());
+            buf.extend(srid.to_le_bytes());
+        }
+        None => buf.extend(geometry_type.to_le_bytes()),
+    }
+}
+
+fn write_points(buf: &mut Vec<u8>, coords: &[(f64, f64)]) {
+    buf.extend((coords.len() as u32).to_le_bytes());
+    for (x, y) in coords {
+        buf.extend(x.to_le_bytes());
+        buf.extend(y.to_le_bytes());
+    }
+}
+
+fn point_wkb((x, y): (f64, f64), srid: Option<u32>) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(25);
+    write_header(&mut buf, WKB_POINT, srid);
+    buf.extend(x.to_le_bytes());
+    buf.extend(y.to_le_bytes());
+    buf
+}
+
+fn line_string_wkb(coords: &[(f64, f64)], srid: Option<u32>) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(13 + 16 * coords.len());
+    write_header(&mut buf, WKB_LINE_STRING, srid);
+    write_points(&mut buf, coords);
+    buf
+}
+
+/// Writes the multipolygon; only the outermost geometry has an SRID.
+fn multipolygon_wkb(polygons: &[Polygon], srid: Option<u32>) -> Vec<u8> {
+    let mut buf = Vec::new();
+    write_header(&mut buf, WKB_MULTI_POLYGON, srid);
+    buf.extend((polygons.len() as u32).to_le_bytes());
+    for polygon in polygons {
+        write_header(&mut buf, WKB_POLYGON, None);
+        buf.extend((1 + polygon.inner.len() as u32).to_le_bytes());
+        for ring in std::iter::once(&polygon.outer).chain(&polygon.inner) {
+            write_points(&mut buf, ring);
+        }
+    }
+    buf
+}
+
+fn write_wkt_points(wkt: &mut String, coords: &[(f64, f64)]) {
+    wkt.push('(');
+    for (i, (x, y)) in coords.iter().enumerate() {
+        if i > 0 {
+            wkt.push(',');
+        }
+        write!(wkt, "{x} {y}").unwrap();
+    }
+    wkt.push(')');
+}
+
+fn line_string_wkt(coords: &[(f64, f64)]) -> String {
+    if coords.is_empty() {
+        return "LINESTRING EMPTY".into();
+    }
+    let mut wkt = String::from("LINESTRING");
+    write_wkt_points(&mut wkt, coords);
+    wkt
+}
+
+/// Returns the point of the node at `node_idx` as WKB, or as EWKB if `srid` is
+/// given, e.g. [`WGS84_SRID`].
+pub fn node_to_wkb(archive: &Osm, node_idx: usize, srid: Option<u32>) -> Vec<u8> {
+    point_wkb(archive.node_coord(node_idx), srid)
+}
+
+/// Returns the line string through the nodes of `way` as WKB, or as EWKB if
+/// `srid` is given, e.g. [`WGS84_SRID`].
+///
+/// Returns `None` if one of the nodes is not resolved in the archive.
+pub fn way_to_wkb(archive: &Osm, way: &Way, srid: Option<u32>) -> Option<Vec<u8>> {
+    let coords: Vec<_> = way_coords(archive, way)?.collect();
+    Some(line_string_wkb(&coords, srid))
+}
+
+/// Returns the line string through the nodes of `way` as WKT.
+///
+/// Returns `None` if one of the nodes is not resolved in the archive.
+pub fn way_to_wkt(archive: &Osm, way: &Way) -> Option<String> {
+    let coords: Vec<_> = way_coords(archive, way)?.collect();
+    Some(line_string_wkt(&coords))
+}
+
+/// Returns polygons assembled from a relation, cf. [`multipolygon`], as WKB
+/// multipolygon, or as EWKB if `srid` is given, e.g. [`WGS84_SRID`].
+///
+/// [`multipolygon`]: super::multipolygon
+pub fn multipolygon_to_wkb(polygons: &[Polygon], srid: Option<u32>) -> Vec<u8> {
+    multipolygon_wkb(polygons, srid)
+}
+
+/// Returns polygons assembled from a relation, cf. [`multipolygon`], as WKT
+/// multipolygon.
+///
+/// [`multipolygon`]: super::multipolygon
+pub fn multipolygon_to_wkt(polygons: &[Polygon]) -> String {
+    if polygons.is_empty() {
+        return "MULTIPOLYGON EMPTY".into();
+    }
+    let mut wkt = String::from("MULTIPOLYGON(");
+    for (i, polygon) in polygons.iter().enumerate() {
+        if i > 0 {
+            wkt.push(',');
+        }
+        wkt.push('(');
+        for (j, ring) in std::iter::once(&polygon.outer)
+            .chain(&polygon.inner)
+            .enumerate()
+        {
+            if j > 0 {
+                wkt.push(',');
+            }
+            write_wkt_points(&mut wkt, ring);
+        }
+        wkt.push(')');
+    }
+    wkt.push(')');
+    wkt
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{b:02X}")).collect()
+    }
+
+    #[test]
+    fn test_point() {
+        assert_eq!(
+            hex(&point_wkb((1.0, 2.0), None)),
+            "0101000000000000000000F03F0000000000000040"
+        );
+        assert_eq!(
+            hex(&point_wkb((1.0, 2.0), Some(WGS84_SRID))),
+            "0101000020E6100000000000000000F03F0000000000000040"
+        );
+    }
+
+    #[test]
+    fn test_line_string() {
+        let coords = [(1.0, 2.0), (3.0, 4.5)];
+        assert_eq!(
+            hex(&line_string_wkb(&coords, None)),
+            "010200000002000000\
+             000000000000F03F0000000000000040\
+             00000000000008400000000000001240"
+        );
+        assert_eq!(
+            hex(&line_string_wkb(&coords, Some(WGS84_SRID))),
+            "0102000020E610000002000000\
+             000000000000F03F0000000000000040\
+             00000000000008400000000000001240"
+        );
+        assert_eq!(line_string_wkt(&coords), "LINESTRING(1 2,3 4.5)");
+        assert_eq!(line_string_wkt(&[]), "LINESTRING EMPTY");
+    }
+
+    #[test]
+    fn test_multipolygon() {
+        let square = |x: f64, size: f64| {
+            vec![
+                (x, x),
+                (x + size, x),
+                (x + size, x + size),
+                (x, x + size),
+                (x, x),
+            ]
+        };
+        let polygons = [
+            Polygon {
+                outer: square(0.0, 4.0),
+                inner: vec![square(1.0, 1.0)],
+            },
+            Polygon {
+                outer: square(10.0, 0.5),
+                inner: Vec::new(),
+            },
+        ];
+        assert_eq!(
+            multipolygon_to_wkt(&polygons),
+            "MULTIPOLYGON(((0 0,4 0,4 4,0 4,0 0),(1 1,2 1,2 2,1 2,1 1)),\
+             ((10 10,10.5 10,10.5 10.5,10 10.5,10 10)))"
+        );
+        assert_eq!(multipolygon_to_wkt(&[]), "MULTIPOLYGON EMPTY");
+
+        let wkb = multipolygon_to_wkb(&polygons, Some(WGS84_SRID));
+        assert_eq!(hex(&wkb[..13]), "0106000020E610000002000000");
+        // polygon header and ring count of the first polygon
+        assert_eq!(hex(&wkb[13..22]), "010300000002000000");
+        let num_points = 5 + 5 + 5;
+        assert_eq!(wkb.len(), 13 + 2 * 9 + 3 * 4 + 16 * num_points);
+    }
+}
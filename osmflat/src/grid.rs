@@ -0,0 +1,104 @@
+//! Binning of coordinates into a regular grid, e.g. for producing density
+//! rasters.
+//!
+//! Coordinates are given as (longitude, latitude) in degrees. The grid is laid
+//! out as a raster image: row 0 is the northernmost row.
+
+use crate::Osm;
+
+/// Regular grid counting the number of points per cell.
+#[derive(Debug, Clone)]
+pub struct DensityGrid {
+    min: (f64, f64),
+    max: (f64, f64),
+    resolution: f64,
+    width: usize,
+    height: usize,
+    counts: Vec<u32>,
+}
+
+impl DensityGrid {
+    /// Creates an empty grid covering the bounding box `min`..`max` with
+    /// square cells of `resolution` degrees.
+    ///
+    /// Panics if the resolution is not positive.
+    pub fn new(min: (f64, f64), max: (f64, f64), resolution: f64) -> Self {
+        assert!(resolution > 0.0, "resolution must be positive");
+        // tolerate rounding errors, e.g. 0.3 / 0.1 > 3
+        let cells = |extent: f64| (((extent / resolution) - 1e-9).ceil() as usize).max(1);
+        let width = cells(max.0 - min.0);
+        let height = cells(max.1 - min.1);
+        Self {
+            min,
+            max,
+            resolution,
+            width,
+            height,
+            counts: vec![0; width * height],
+        }
+    }
+
+    /// Returns the column and row of the cell containing the coordinate, or
+    /// `None` if the coordinate is outside of the grid.
+    pub fn cell(&self, (lon, lat): (f64, f64)) -> Option<(usize, usize)> {
+        if !(self.min.0..=self.max.0).contains(&lon) || !(self.min.1..=self.max.1).contains(&lat) {
+            return None;
+        }
+        let x = ((lon - self.min.0) / self.resolution) as usize;
+        let y = ((self.max.1 - lat) / self.resolution) as usize;
+        Some((x.min(self.width - 1), y.min(self.height - 1)))
+    }
+
+    /// Counts a point in the cell containing the coordinate.
+    ///
+    /// Returns `false` if the coordinate is outside of the grid.
+    pub fn add(&mut self, coord: (f64, f64)) -> bool {
+        match self.cell(coord) {
+            Some((x, y)) => {
+                self.counts[y * self.width + x] += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Counts the location of the node with the given index.
+    pub fn add_node(&mut self, archive: &Osm, node_idx: usize) -> bool {
+        self.add(archive.node_coord(node_idx))
+    }
+
+    /// Number of columns.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of rows.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Size of a cell in degrees.
+    pub fn resolution(&self) -> f64 {
+        self.resolution
+    }
+
+    /// Northwest corner of the grid as (longitude, latitude).
+    pub fn origin(&self) -> (f64, f64) {
+        (self.min.0, self.max.1)
+    }
+
+    /// Number of points in the cell at column `x` and row `y`.
+    pub fn get(&self, x: usize, y: usize) -> u32 {
+        self.counts[y * self.width + x]
+    }
+
+    /// Counts of all cells in row-major order.
+    pub fn counts(&self) -> &[u32] {
+        &self.counts
+    }
+
+    /// Maximum count of all cells.
+    pub fn max_count(&self) -> u32 {
+        self.counts.iter().copied().max().unwrap_or(0)
+    }
+}
@@ -0,0 +1,148 @@
+//! Typed access to the bounding box and the replication state in the header.
+//!
+//! The header stores the bounding box scaled by `coord_scale`, and the source
+//! and the replication base URL as optional indexes into the `stringtable`.
+//! Unset numbers are 0.
+
+use crate::{BBox, Header, Osm};
+
+use chrono::{DateTime, Utc};
+
+/// Replication state of an archive, cf. [`state.txt`].
+///
+/// [`state.txt`]: https://wiki.openstreetmap.org/wiki/Planet.osm/diffs#Minute.2C_Hour.2C_and_Day_Files_Organisation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplicationInfo {
+    /// Time of the replication state
+    pub timestamp: DateTime<Utc>,
+    /// Sequence number of the replication state
+    pub sequence_number: i64,
+    /// Base URL of the replication server, if any
+    pub base_url: Option<String>,
+}
+
+impl Header {
+    /// Returns the bounding box in the coordinates of the archive, or `None`
+    /// if the header has no bounding box; cf. [`BBox::to_degrees`].
+    pub fn bounding_box(&self) -> Option<BBox> {
+        let bbox = BBox {
+            left: self.bbox_left(),
+            bottom: self.bbox_bottom(),
+            right: self.bbox_right(),
+            top: self.bbox_top(),
+        };
+        let unset = [bbox.left, bbox.bottom, bbox.right, bbox.top]
+            .iter()
+            .all(|&x| x == 0);
+        (!unset).then_some(bbox)
+    }
+}
+
+impl Osm {
+    /// Returns the string referenced by an optional header field, e.g.
+    /// `header.source_idx()`, or `None` if the field is unset.
+    pub fn header_string(&self, idx: Option<u64>) -> Option<String> {
+        let string = self.stringtable().as_bytes().get(idx? as usize..)?;
+        let len = string.iter().position(|&b| b == 0)?;
+        Some(String::from_utf8_lossy(&string[..len]).into_owned())
+    }
+
+    /// Returns the replication state of the archive, or `None` if it has none,
+    /// e.g. when it was converted from a PBF file without replication headers,
+    /// or if its timestamp is out of the range of `DateTime`.
+    pub fn replication(&self) -> Option<ReplicationInfo> {
+        let header = self.header();
+        let base_url = self.header_string(header.replication_base_url_idx());
+        let seconds = header.replication_timestamp();
+        if seconds == 0 && header.replication_sequence_number() == 0 && base_url.is_none() {
+            return None;
+        }
+        Some(ReplicationInfo {
+            timestamp: DateTime::from_timestamp(seconds, 0)?,
+            sequence_number: header.replication_sequence_number(),
+            base_url,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bounding_box() {
+        let mut header = Header::new();
+        header.set_coord_scale(1000);
+        assert_eq!(header.bounding_box(), None);
+
+        header.set_bbox_left(-1500);
+        header.set_bbox_right(2000);
+        header.set_bbox_bottom(-250);
+        header.set_bbox_top(500);
+        let bbox = header.bounding_box().unwrap();
+        assert_eq!(
+            bbox,
+            BBox {
+                left: -1500,
+                bottom: -250,
+                right: 2000,
+                top: 500
+            }
+        );
+        assert_eq!(
+            bbox.to_degrees(&archive(&header)),
+            ((-1.5, -0.25), (2.0, 0.5))
+        );
+    }
+
+    fn archive(header: &Header) -> Osm {
+        let storage = flatdata::MemoryResourceStorage::new("/test");
+        let builder = crate::OsmBuilder::new(storage.clone()).unwrap();
+        builder.set_header(header).unwrap();
+        builder.start_nodes().unwrap().close().unwrap();
+        builder.start_ways().unwrap().close().unwrap();
+        builder.start_relations().unwrap().close().unwrap();
+        builder.start_relation_members().unwrap().close().unwrap();
+        builder.start_tags().unwrap().close().unwrap();
+        builder.start_tags_index().unwrap().close().unwrap();
+        builder.start_nodes_index().unwrap().close().unwrap();
+        builder
+            .set_stringtable(b"osmflatc\0https://example.com/minute\0")
+            .unwrap();
+        Osm::open(storage).unwrap()
+    }
+
+    #[test]
+    fn test_replication() {
+        let url = "https://example.com/minute";
+        let mut header = Header::new();
+        header.set_source_idx(None);
+        header.set_replication_base_url_idx(None);
+        assert_eq!(archive(&header).replication(), None);
+        assert_eq!(archive(&header).header_string(header.source_idx()), None);
+
+        header.set_replication_base_url_idx(Some(9));
+        let replication = archive(&header).replication().unwrap();
+        assert_eq!(replication.timestamp, DateTime::UNIX_EPOCH);
+        assert_eq!(replication.sequence_number, 0);
+        assert_eq!(replication.base_url.as_deref(), Some(url));
+
+        header.set_replication_timestamp(-86_400);
+        header.set_replication_sequence_number(42);
+        header.set_replication_base_url_idx(None);
+        let replication = archive(&header).replication().unwrap();
+        assert_eq!(
+            replication.timestamp.to_rfc3339(),
+            "1969-12-31T00:00:00+00:00"
+        );
+        assert_eq!(replication.sequence_number, 42);
+        assert_eq!(replication.base_url, None);
+
+        // out of bounds indexes are not read
+        header.set_replication_base_url_idx(Some(1000));
+        assert_eq!(archive(&header).replication().unwrap().base_url, None);
+
+        header.set_replication_timestamp(i64::MAX);
+        assert_eq!(archive(&header).replication(), None);
+    }
+}
@@ -0,0 +1,285 @@
+//! Hilbert space-filling curve over archive coordinates.
+//!
+//! In archives compiled with `osmflatc --sort hilbert`, the order of the nodes
+//! is an index of their coordinates: the nodes inside a bounding box are found
+//! by decomposing it into ranges of positions on the curve, cf.
+//! [`hilbert_ranges`], and searching the nodes of each range, cf.
+//! [`hilbert_nodes_in_bbox`].
+
+use crate::{BBox, Node, Osm};
+
+use std::ops::{Range, RangeInclusive};
+
+/// Maximal number of curve ranges a bounding box is decomposed into by
+/// [`hilbert_nodes_in_bbox`].
+pub const HILBERT_QUERY_MAX_RANGES: usize = 64;
+
+/// Returns the position of a coordinate on the Hilbert curve covering the
+/// full range of `i32` coordinates.
+///
+/// Coordinates which are close to each other tend to have close positions on
+/// the curve. Nodes in archives compiled with `osmflatc --sort hilbert` are
+/// sorted by this position.
+pub fn hilbert_index(lon: i32, lat: i32) -> u64 {
+    // shift the coordinates into the unsigned range preserving the order
+    let mut x = (lon as u32) ^ (1 << 31);
+    let mut y = (lat as u32) ^ (1 << 31);
+    let mut d = 0u64;
+    let mut s = 1u32 << 31;
+    while s > 0 {
+        let rx = (x & s != 0) as u32;
+        let ry = (y & s != 0) as u32;
+        d += u64::from(s) * u64::from(s) * u64::from((3 * rx) ^ ry);
+        // rotate the quadrant
+        if ry == 0 {
+            if rx == 1 {
+                x = !x;
+                y = !y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s >>= 1;
+    }
+    d
+}
+
+/// Shifts a coordinate into the unsigned range preserving the order.
+fn to_unsigned(x: i32) -> u32 {
+    (x as u32) ^ (1 << 31)
+}
+
+fn to_signed(x: u32) -> i32 {
+    (x ^ (1 << 31)) as i32
+}
+
+/// Square cell of the curve, aligned to its size, with the lower left corner
+/// `(x, y)` in unsigned coordinates.
+#[derive(Debug, Clone, Copy)]
+struct Cell {
+    x: u32,
+    y: u32,
+    level: u32,
+}
+
+impl Cell {
+    fn size(&self) -> u64 {
+        1 << self.level
+    }
+
+    /// Positions of the cell on the curve, which are consecutive since the
+    /// cell is aligned.
+    fn range(&self) -> RangeInclusive<u64> {
+        let len = self.size() * self.size();
+        let start = hilbert_index(to_signed(self.x), to_signed(self.y)) & !(len - 1);
+        start..=start + (len - 1)
+    }
+
+    fn children(&self) -> [Cell; 4] {
+        let (level, half) = (self.level - 1, 1 << (self.level - 1));
+        [(0, 0), (half, 0), (0, half), (half, half)].map(|(dx, dy)| Cell {
+            x: self.x + dx,
+            y: self.y + dy,
+            level,
+        })
+    }
+}
+
+/// Relation of a cell to a bounding box in unsigned coordinates.
+#[derive(Debug, PartialEq, Eq)]
+enum Overlap {
+    Disjoint,
+    Partial,
+    Inside,
+}
+
+fn overlap(cell: &Cell, (xs, ys): &(Range<u64>, Range<u64>)) -> Overlap {
+    let cell_xs = u64::from(cell.x)..u64::from(cell.x) + cell.size();
+    let cell_ys = u64::from(cell.y)..u64::from(cell.y) + cell.size();
+    if cell_xs.end <= xs.start
+        || xs.end <= cell_xs.start
+        || cell_ys.end <= ys.start
+        || ys.end <= cell_ys.start
+    {
+        Overlap::Disjoint
+    } else if xs.start <= cell_xs.start
+        && cell_xs.end <= xs.end
+        && ys.start <= cell_ys.start
+        && cell_ys.end <= ys.end
+    {
+        Overlap::Inside
+    } else {
+        Overlap::Partial
+    }
+}
+
+/// Decomposes `bbox` into sorted and disjoint ranges of positions on the
+/// Hilbert curve, such that the positions of all coordinates inside `bbox` are
+/// contained in the ranges, cf. [`hilbert_index`].
+///
+/// The decomposition is refined until there would be more than `max_ranges`
+/// ranges, but at least into the 4 quadrants of the curve. The ranges of a
+/// coarse decomposition also contain positions outside of `bbox`.
+pub fn hilbert_ranges(bbox: &BBox, max_ranges: usize) -> Vec<RangeInclusive<u64>> {
+    let bounds = (
+        u64::from(to_unsigned(bbox.left))..u64::from(to_unsigned(bbox.right)) + 1,
+        u64::from(to_unsigned(bbox.bottom))..u64::from(to_unsigned(bbox.top)) + 1,
+    );
+    let mut ranges = Vec::new();
+    let mut partial = Vec::new();
+    let classify = |cells: &[Cell], ranges: &mut Vec<_>, partial: &mut Vec<Cell>| {
+        for cell in cells {
+            match overlap(cell, &bounds) {
+                Overlap::Disjoint => (),
+                Overlap::Inside => ranges.push(cell.range()),
+                Overlap::Partial => partial.push(*cell),
+            }
+        }
+    };
+    let quadrants = Cell {
+        x: 0,
+        y: 0,
+        level: 32,
+    };
+    classify(&quadrants.children(), &mut ranges, &mut partial);
+    while partial.first().is_some_and(|cell| cell.level > 0)
+        && ranges.len() + 4 * partial.len() <= max_ranges
+    {
+        let mut next = Vec::new();
+        for cell in std::mem::take(&mut partial) {
+            classify(&cell.children(), &mut ranges, &mut next);
+        }
+        partial = next;
+    }
+    ranges.extend(partial.iter().map(Cell::range));
+
+    ranges.sort_unstable_by_key(|range| *range.start());
+    let mut merged: Vec<RangeInclusive<u64>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if last.end().checked_add(1) == Some(*range.start()) => {
+                *last = *last.start()..=*range.end();
+            }
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Iterator over the ascending indexes of the nodes inside a bounding box, cf.
+/// [`hilbert_nodes_in_bbox`].
+#[derive(Debug, Clone)]
+pub struct HilbertQuery<'a> {
+    nodes: &'a [Node],
+    bbox: BBox,
+    ranges: std::vec::IntoIter<RangeInclusive<u64>>,
+    current: Range<usize>,
+}
+
+impl Iterator for HilbertQuery<'_> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        loop {
+            if let Some(idx) = self.current.next() {
+                let node = &self.nodes[idx];
+                if self
+                    .bbox
+                    .intersects(&BBox::from_coord(node.lon(), node.lat()))
+                {
+                    return Some(idx as u64);
+                }
+                continue;
+            }
+            let range = self.ranges.next()?;
+            let position = |node: &Node| hilbert_index(node.lon(), node.lat());
+            let start = self.nodes.partition_point(|n| position(n) < *range.start());
+            let end = start + self.nodes[start..].partition_point(|n| position(n) <= *range.end());
+            self.current = start..end;
+        }
+    }
+}
+
+/// Returns the indexes of the nodes inside `bbox` of an archive whose nodes
+/// are sorted along the Hilbert curve, i.e. compiled with
+/// `osmflatc --sort hilbert`.
+///
+/// Each range of the decomposition of `bbox` is found by binary search, so
+/// the query does not need a spatial index. For archives with nodes in a
+/// different order, the result is incomplete; use [`Osm::nodes_in_bbox`]
+/// instead.
+pub fn hilbert_nodes_in_bbox(archive: &Osm, bbox: BBox) -> HilbertQuery<'_> {
+    HilbertQuery {
+        nodes: archive.nodes(),
+        bbox,
+        ranges: hilbert_ranges(&bbox, HILBERT_QUERY_MAX_RANGES).into_iter(),
+        current: 0..0,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hilbert_index_is_continuous() {
+        // Consecutive positions on the curve are neighboring cells. Check this
+        // for the lower left corner of the curve.
+        let origin = i32::MIN;
+        let mut cells: Vec<_> = (0..16)
+            .flat_map(|x| (0..16).map(move |y| (x, y)))
+            .map(|(x, y)| (hilbert_index(origin + x, origin + y), (x, y)))
+            .collect();
+        cells.sort();
+        assert_eq!(cells[0], (0, (0, 0)));
+        for (i, w) in cells.windows(2).enumerate() {
+            let ((d0, (x0, y0)), (d1, (x1, y1))) = (w[0], w[1]);
+            assert_eq!((d0, d1), (i as u64, i as u64 + 1));
+            assert_eq!((x0 - x1).abs() + (y0 - y1).abs(), 1);
+        }
+    }
+
+    #[test]
+    fn test_hilbert_ranges() {
+        let origin = i32::MIN;
+        let bbox = BBox {
+            left: origin + 3,
+            bottom: origin + 2,
+            right: origin + 9,
+            top: origin + 12,
+        };
+        let contains = |ranges: &[RangeInclusive<u64>], x, y| {
+            let d = hilbert_index(x, y);
+            ranges.iter().any(|range| range.contains(&d))
+        };
+
+        // fine decomposition covers exactly the bounding box
+        let ranges = hilbert_ranges(&bbox, usize::MAX);
+        let len: u64 = ranges.iter().map(|r| r.end() - r.start() + 1).sum();
+        assert_eq!(len, 7 * 11);
+        for x in origin..origin + 16 {
+            for y in origin..origin + 16 {
+                let inside = bbox.intersects(&BBox::from_coord(x, y));
+                assert_eq!(contains(&ranges, x, y), inside, "({x}, {y})");
+            }
+        }
+        assert!(ranges.windows(2).all(|w| w[0].end() + 1 < *w[1].start()));
+
+        // coarse decomposition still contains the bounding box
+        let ranges = hilbert_ranges(&bbox, 8);
+        assert!(ranges.len() <= 8);
+        for x in bbox.left..=bbox.right {
+            for y in bbox.bottom..=bbox.top {
+                assert!(contains(&ranges, x, y));
+            }
+        }
+
+        // the whole plane is a single range
+        let everything = BBox {
+            left: i32::MIN,
+            bottom: i32::MIN,
+            right: i32::MAX,
+            top: i32::MAX,
+        };
+        assert_eq!(hilbert_ranges(&everything, 64), [0..=u64::MAX]);
+    }
+}
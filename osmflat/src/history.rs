@@ -0,0 +1,81 @@
+//! Access to all versions of entities in full-history archives.
+//!
+//! An archive compiled from a full-history file (`osmflatc --history --ids`)
+//! stores all versions of an entity consecutively in the `nodes`, `ways`, and
+//! `relations` vectors, ordered by version. The version of each entry is
+//! stored in the `history` subarchive, and its OSM id in the `ids` subarchive.
+//!
+//! Note that references between entities, e.g. from a way to its nodes, point
+//! to the first version of the referenced entity.
+
+use crate::{EntityType, Id, Osm, Version};
+
+use std::ops::Range;
+
+fn ids(archive: &Osm, entity_type: EntityType) -> Option<&[Id]> {
+    let ids = archive.ids()?;
+    Some(match entity_type {
+        EntityType::Node => ids.nodes(),
+        EntityType::Way => ids.ways(),
+        EntityType::Relation => ids.relations(),
+    })
+}
+
+fn history(archive: &Osm, entity_type: EntityType) -> Option<&[Version]> {
+    let history = archive.history()?;
+    Some(match entity_type {
+        EntityType::Node => history.nodes(),
+        EntityType::Way => history.ways(),
+        EntityType::Relation => history.relations(),
+    })
+}
+
+/// Returns the range of indexes of all versions of the entity at index `idx`.
+///
+/// Returns `None` if the archive does not contain the `ids` subarchive.
+pub fn version_range(archive: &Osm, entity_type: EntityType, idx: usize) -> Option<Range<usize>> {
+    let ids = ids(archive, entity_type)?;
+    let id = ids[idx].value();
+    let start = ids[..idx]
+        .iter()
+        .rposition(|x| x.value() != id)
+        .map_or(0, |pos| pos + 1);
+    let end = ids[idx..]
+        .iter()
+        .position(|x| x.value() != id)
+        .map_or(ids.len(), |pos| idx + pos);
+    Some(start..end)
+}
+
+/// Returns an iterator over the indexes and versions of all versions of the
+/// entity at index `idx`, ordered by version.
+///
+/// Returns `None` if the archive does not contain the `ids` and `history`
+/// subarchives.
+pub fn versions(
+    archive: &Osm,
+    entity_type: EntityType,
+    idx: usize,
+) -> Option<impl Iterator<Item = (usize, &Version)> + Clone> {
+    let range = version_range(archive, entity_type, idx)?;
+    let history = history(archive, entity_type)?;
+    Some(range.clone().zip(&history[range]))
+}
+
+/// Returns the index of the version of the entity at index `idx`, which was
+/// current at `timestamp` (in seconds since the epoch).
+///
+/// Returns `None` if the entity did not exist yet or was deleted at that time,
+/// or if the archive does not contain the `ids` and `history` subarchives.
+pub fn version_at(
+    archive: &Osm,
+    entity_type: EntityType,
+    idx: usize,
+    timestamp: i64,
+) -> Option<usize> {
+    versions(archive, entity_type, idx)?
+        .take_while(|(_, version)| version.timestamp() <= timestamp)
+        .last()
+        .filter(|(_, version)| version.visible())
+        .map(|(idx, _)| idx)
+}
@@ -0,0 +1,214 @@
+//! Signed OSM ids stored in the `ids` subarchive.
+//!
+//! Files exported from editors like JOSM contain entities with negative ids,
+//! which were not uploaded yet. The 40 bits of [`Id`] store the ids in two's
+//! complement, so positive ids up to 2^39 - 1 and negative ids down to -2^39
+//! are representable.
+//!
+//! The optional `id_index` subarchive, compiled with `osmflatc --id-index`,
+//! maps ids back to the indexes of the entities, cf. [`Osm::node_index_by_id`].
+//! Archives compiled from input sorted by id, as extracts usually are, do not
+//! need it: their ids are ascending per entity type, so [`Osm::find_by_id`]
+//! binary searches the `ids` subarchive directly.
+
+use crate::{EntityIndex, EntityType, Id, Osm};
+
+const ID_BITS: u32 = 40;
+
+impl Id {
+    /// Returns the signed OSM id.
+    ///
+    /// Unlike [`Id::value`], it returns negative ids as such instead of large
+    /// positive values.
+    #[inline]
+    pub fn signed_value(&self) -> i64 {
+        ((self.value() << (64 - ID_BITS)) as i64) >> (64 - ID_BITS)
+    }
+
+    /// Sets the signed OSM id.
+    #[inline]
+    pub fn set_signed_value(&mut self, id: i64) {
+        self.set_value(id as u64 & ((1 << ID_BITS) - 1));
+    }
+}
+
+/// Builds the vector of the [`IdIndex`] subarchive from the ids of the entities
+/// of a type.
+///
+/// The indexes of entities with equal ids, i.e. versions in a full-history
+/// archive, keep their order.
+///
+/// [`IdIndex`]: crate::IdIndex
+pub fn build_id_index(ids: &[Id]) -> Vec<EntityIndex> {
+    let mut order: Vec<usize> = (0..ids.len()).collect();
+    order.sort_by_key(|&idx| ids[idx].signed_value());
+    order
+        .into_iter()
+        .map(|idx| {
+            let mut entity = EntityIndex::new();
+            entity.set_value(idx as u64);
+            entity
+        })
+        .collect()
+}
+
+impl Osm {
+    /// Returns the index of the entity of type `entity_type` with OSM id `id`.
+    ///
+    /// In a full-history archive, the index of the first version is returned.
+    /// Returns `None` if there is no such entity, or if the archive does not
+    /// contain the `ids` and `id_index` subarchives.
+    pub fn index_by_id(&self, entity_type: EntityType, id: i64) -> Option<usize> {
+        let (ids, id_index) = (self.ids()?, self.id_index()?);
+        let (ids, index) = match entity_type {
+            EntityType::Node => (ids.nodes(), id_index.nodes()),
+            EntityType::Way => (ids.ways(), id_index.ways()),
+            EntityType::Relation => (ids.relations(), id_index.relations()),
+        };
+        let pos = index.partition_point(|idx| ids[idx.value() as usize].signed_value() < id);
+        let idx = index.get(pos)?.value() as usize;
+        (ids[idx].signed_value() == id).then_some(idx)
+    }
+
+    /// Returns the index of the node with OSM id `id`, cf.
+    /// [`Osm::index_by_id`].
+    pub fn node_index_by_id(&self, id: i64) -> Option<usize> {
+        self.index_by_id(EntityType::Node, id)
+    }
+
+    /// Returns the index of the way with OSM id `id`, cf.
+    /// [`Osm::index_by_id`].
+    pub fn way_index_by_id(&self, id: i64) -> Option<usize> {
+        self.index_by_id(EntityType::Way, id)
+    }
+
+    /// Returns the index of the relation with OSM id `id`, cf.
+    /// [`Osm::index_by_id`].
+    pub fn relation_index_by_id(&self, id: i64) -> Option<usize> {
+        self.index_by_id(EntityType::Relation, id)
+    }
+
+    /// Returns the index of the entity of type `entity_type` with OSM id `id`
+    /// by binary search over the `ids` subarchive.
+    ///
+    /// The ids of the entity type must be in ascending order, as in archives
+    /// compiled from input sorted by id without reordering the nodes, e.g. by
+    /// `--sort hilbert`. Otherwise, the result is unspecified: an existing
+    /// entity might not be found, but the index returned, if any, is always
+    /// the index of an entity with id `id`. If the archive contains the
+    /// `id_index` subarchive, it is used instead, which works for any order,
+    /// cf. [`Osm::index_by_id`].
+    ///
+    /// In a full-history archive, the index of the first version is returned.
+    /// Returns `None` if there is no such entity, or if the archive does not
+    /// contain the `ids` subarchive.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use osmflat::{FileResourceStorage, Osm};
+    ///
+    /// let archive = Osm::open(FileResourceStorage::new("path/to/archive.osm.flatdata")).unwrap();
+    /// if let Some(idx) = archive.find_way_by_id(4_305_944) {
+    ///     println!("{:?}", archive.ways()[idx]);
+    /// }
+    /// ```
+    pub fn find_by_id(&self, entity_type: EntityType, id: i64) -> Option<usize> {
+        if self.id_index().is_some() {
+            return self.index_by_id(entity_type, id);
+        }
+        let ids = self.ids()?;
+        let ids = match entity_type {
+            EntityType::Node => ids.nodes(),
+            EntityType::Way => ids.ways(),
+            EntityType::Relation => ids.relations(),
+        };
+        let idx = ids.partition_point(|x| x.signed_value() < id);
+        (ids.get(idx)?.signed_value() == id).then_some(idx)
+    }
+
+    /// Returns the index of the node with OSM id `id`, cf.
+    /// [`Osm::find_by_id`].
+    pub fn find_node_by_id(&self, id: i64) -> Option<usize> {
+        self.find_by_id(EntityType::Node, id)
+    }
+
+    /// Returns the index of the way with OSM id `id`, cf.
+    /// [`Osm::find_by_id`].
+    pub fn find_way_by_id(&self, id: i64) -> Option<usize> {
+        self.find_by_id(EntityType::Way, id)
+    }
+
+    /// Returns the index of the relation with OSM id `id`, cf.
+    /// [`Osm::find_by_id`].
+    pub fn find_relation_by_id(&self, id: i64) -> Option<usize> {
+        self.find_by_id(EntityType::Relation, id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{IdsBuilder, OsmWriter};
+    use flatdata::ResourceStorage;
+
+    fn to_ids(ids: &[i64]) -> Vec<Id> {
+        ids.iter()
+            .map(|&x| {
+                let mut id = Id::new();
+                id.set_signed_value(x);
+                id
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_signed_value() {
+        let mut id = Id::new();
+        for x in [0, 1, 42, -1, -1234, (1 << 39) - 1, -(1 << 39)] {
+            id.set_signed_value(x);
+            assert_eq!(id.signed_value(), x);
+        }
+        id.set_signed_value(-1);
+        assert_eq!(id.value(), (1 << 40) - 1);
+        id.set_signed_value(1234);
+        assert_eq!(id.value(), 1234);
+    }
+
+    #[test]
+    fn test_build_id_index() {
+        let ids = to_ids(&[7, -2, 3, 3, -5]);
+        let index: Vec<u64> = build_id_index(&ids).iter().map(|e| e.value()).collect();
+        assert_eq!(index, [4, 1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn test_find_by_id() {
+        let storage = flatdata::MemoryResourceStorage::new("/test");
+        let mut writer = OsmWriter::new(storage.clone()).unwrap();
+        let tags = None::<(&str, &str)>;
+        for i in 0..5 {
+            writer.add_node(f64::from(i), 0.0, tags);
+        }
+        writer.add_way(&[0, 1], tags);
+        writer.add_way(&[1, 2], tags);
+        writer.finish().unwrap();
+
+        let archive = Osm::open(storage.clone()).unwrap();
+        assert_eq!(archive.find_node_by_id(1), None);
+        let builder = IdsBuilder::new(storage.subdir("ids")).unwrap();
+        builder.set_nodes(&to_ids(&[-3, 1, 2, 2, 10])).unwrap();
+        builder.set_ways(&to_ids(&[5, 7])).unwrap();
+        builder.set_relations(&[]).unwrap();
+
+        let archive = Osm::open(storage).unwrap();
+        let found: Vec<_> = [-3, 0, 1, 2, 10, 11]
+            .into_iter()
+            .map(|id| archive.find_node_by_id(id))
+            .collect();
+        assert_eq!(found, [Some(0), None, Some(1), Some(2), Some(4), None]);
+        assert_eq!(archive.find_way_by_id(7), Some(1));
+        assert_eq!(archive.find_way_by_id(6), None);
+        assert_eq!(archive.find_relation_by_id(1), None);
+    }
+}
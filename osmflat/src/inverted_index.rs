@@ -0,0 +1,60 @@
+//! Lookup of entities by tag backed by the `inverted_index` subarchive.
+//!
+//! The subarchive is compiled with `osmflatc --inverted-index`, cf.
+//! [`InvertedIndex`].
+
+use crate::{EntityIndex, EntityType, InvertedIndex, Osm, TagPostings};
+
+use std::cmp::Ordering;
+
+impl InvertedIndex {
+    fn postings(&self, entity_type: EntityType) -> (&[TagPostings], &[EntityIndex]) {
+        match entity_type {
+            EntityType::Node => (self.node_tags(), self.nodes()),
+            EntityType::Way => (self.way_tags(), self.ways()),
+            EntityType::Relation => (self.relation_tags(), self.relations()),
+        }
+    }
+
+    /// Returns the indexes of the entities of type `entity_type` having a tag
+    /// with `key`, and with `value` if given, in ascending order.
+    ///
+    /// `archive` is the parent archive of the index.
+    pub fn entities_with_tag(
+        &self,
+        archive: &Osm,
+        entity_type: EntityType,
+        key: &[u8],
+        value: Option<&[u8]>,
+    ) -> impl ExactSizeIterator<Item = u64> + '_ {
+        self.postings_of_tag(archive, entity_type, key, value)
+            .map(|entity| entity.value())
+    }
+
+    pub(crate) fn postings_of_tag(
+        &self,
+        archive: &Osm,
+        entity_type: EntityType,
+        key: &[u8],
+        value: Option<&[u8]>,
+    ) -> std::slice::Iter<'_, EntityIndex> {
+        let (postings, entities) = self.postings(entity_type);
+        let strings = archive.stringtable();
+        // the last postings are the sentinel
+        let found = postings[..postings.len().saturating_sub(1)].binary_search_by(|p| {
+            strings
+                .substring_raw(p.key_idx() as usize)
+                .cmp(key)
+                .then_with(|| match (p.value_idx(), value) {
+                    (None, None) => Ordering::Equal,
+                    (None, Some(_)) => Ordering::Less,
+                    (Some(_), None) => Ordering::Greater,
+                    (Some(idx), Some(value)) => strings.substring_raw(idx as usize).cmp(value),
+                })
+        });
+        let range = found.map_or(0..0, |i| {
+            postings[i].first_idx() as usize..postings[i + 1].first_idx() as usize
+        });
+        entities[range].iter()
+    }
+}
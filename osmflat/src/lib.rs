@@ -35,15 +35,95 @@
 //! [`osmflatc`]: https://github.com/boxdot/osmflat-rs/tree/master/osmflatc
 //! [examples]: https://github.com/boxdot/osmflat-rs/tree/master/osmflat/examples
 
-// generated osm module
-include!("osmflat_generated.rs");
+// generated osm module, which elides the lifetimes of the returned
+// `ExternalVector`s; `unknown_lints` keeps toolchains without this lint quiet
+#[allow(unknown_lints, mismatched_lifetime_syntaxes)]
+mod generated {
+    include!("osmflat_generated.rs");
+}
+pub use crate::generated::osm;
 
+mod admin;
+mod advice;
+mod areas;
+mod bitset;
+mod centroids;
+mod checksums;
+mod compressed;
+mod coords;
+mod entity;
+#[cfg(feature = "geojson")]
+mod geojson;
+pub mod geometry;
+mod grid;
+mod header;
+mod hilbert;
+mod history;
+mod id;
+mod inverted_index;
+mod numbers;
+mod open;
+#[cfg(feature = "rayon")]
+mod parallel;
+pub mod query;
+mod restriction;
+mod reverse_index;
+mod roles;
+mod spatial;
+mod strings;
 mod tags;
+#[cfg(feature = "tar")]
+mod tar_archive;
+#[cfg(feature = "testing")]
+mod testing;
+mod tile_index;
+mod tiles;
+mod validate;
+mod view;
+mod writer;
 
+pub use crate::admin::*;
+pub use crate::advice::*;
+pub use crate::areas::*;
+pub use crate::bitset::*;
+pub use crate::centroids::*;
+pub use crate::checksums::*;
+pub use crate::compressed::*;
+pub use crate::coords::*;
+pub use crate::entity::*;
+#[cfg(feature = "geojson")]
+pub use crate::geojson::*;
+pub use crate::grid::*;
+pub use crate::header::*;
+pub use crate::hilbert::*;
+pub use crate::history::*;
+pub use crate::id::*;
+pub use crate::numbers::*;
+pub use crate::open::*;
 pub use crate::osm::*;
+#[cfg(feature = "rayon")]
+pub use crate::parallel::*;
+pub use crate::restriction::*;
+pub use crate::reverse_index::*;
+pub use crate::roles::*;
+pub use crate::spatial::*;
+pub use crate::strings::*;
 pub use crate::tags::*;
+#[cfg(feature = "tar")]
+pub use crate::tar_archive::*;
+#[cfg(feature = "testing")]
+pub use crate::testing::*;
+pub use crate::tile_index::*;
+pub use crate::tiles::*;
+pub use crate::validate::*;
+pub use crate::view::*;
+pub use crate::writer::*;
 
 // re-export what is needed from flatdata to use osmflat
 pub use flatdata::FileResourceStorage;
+// the timestamps of `ReplicationInfo` are `chrono` types
+pub use chrono;
 #[cfg(feature = "tar")]
 pub use flatdata::TarArchiveResourceStorage;
+#[cfg(feature = "rayon")]
+pub use rayon;
@@ -0,0 +1,163 @@
+//! Numeric values of tags like `population`, `maxspeed` or `width`.
+//!
+//! The `tag_numbers` resource, compiled with `osmflatc --numeric-tags`,
+//! stores the values of the tags with the given keys parsed as numbers, so
+//! that analytics over these tags do not parse the same strings on every run.
+
+use crate::Osm;
+
+use std::ops::Range;
+
+/// Scale of the values stored in the `tag_numbers` resource, cf.
+/// [`TagNumber`].
+///
+/// [`TagNumber`]: crate::TagNumber
+pub const TAG_NUMBER_SCALE: i64 = 1000;
+
+/// Parses the number at the beginning of a tag value, scaled by
+/// [`TAG_NUMBER_SCALE`].
+///
+/// The number consists of an optional sign, digits and optional decimals
+/// separated by `.`; leading whitespace and anything after the number, e.g. a
+/// unit as in `maxspeed=50 mph`, are ignored. Decimals beyond the scale are
+/// truncated.
+///
+/// Returns `None` if the value does not start with a number, or if the number
+/// is out of range.
+///
+/// # Examples
+///
+/// ```rust
+/// use osmflat::parse_tag_number;
+///
+/// assert_eq!(parse_tag_number(b"3500"), Some(3_500_000));
+/// assert_eq!(parse_tag_number(b"2.5 m"), Some(2_500));
+/// assert_eq!(parse_tag_number(b"none"), None);
+/// ```
+pub fn parse_tag_number(value: &[u8]) -> Option<i64> {
+    let value = value.trim_ascii_start();
+    let (negative, value) = match value.split_first() {
+        Some((b'-', rest)) => (true, rest),
+        Some((b'+', rest)) => (false, rest),
+        _ => (false, value),
+    };
+    let num_digits = value.iter().take_while(|c| c.is_ascii_digit()).count();
+    let (integer, rest) = value.split_at(num_digits);
+    let decimals = match rest.split_first() {
+        Some((b'.', rest)) => {
+            let num_decimals = rest.iter().take_while(|c| c.is_ascii_digit()).count();
+            &rest[..num_decimals]
+        }
+        _ => &[],
+    };
+    if integer.is_empty() && decimals.is_empty() {
+        return None;
+    }
+
+    let mut number: i64 = 0;
+    for digit in integer {
+        number = number
+            .checked_mul(10)?
+            .checked_add(i64::from(digit - b'0'))?;
+    }
+    number = number.checked_mul(TAG_NUMBER_SCALE)?;
+    let mut scale = TAG_NUMBER_SCALE;
+    for digit in decimals {
+        scale /= 10;
+        if scale == 0 {
+            break;
+        }
+        number += i64::from(digit - b'0') * scale;
+    }
+    Some(if negative { -number } else { number })
+}
+
+/// Finds a tag by its key in the given `range` and returns its value as a
+/// number, cf. [`parse_tag_number`].
+///
+/// The values stored in the `tag_numbers` resource are used if present;
+/// values of other keys are parsed on the fly.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use osmflat::{tag_number, FileResourceStorage, Osm};
+///
+/// let archive = Osm::open(FileResourceStorage::new("path/to/archive.osm.flatdata")).unwrap();
+/// let population: f64 = archive
+///     .nodes()
+///     .iter()
+///     .filter_map(|node| tag_number(&archive, node.tags(), b"population"))
+///     .sum();
+/// ```
+pub fn tag_number(archive: &Osm, range: Range<u64>, key: &[u8]) -> Option<f64> {
+    let tags = archive.tags();
+    let tags_index = archive.tags_index();
+    let strings = archive.stringtable();
+
+    let tag_idx = range
+        .map(|idx| tags_index[idx as usize].value() as usize)
+        .find(|&tag_idx| strings.substring_raw(tags[tag_idx].key_idx() as usize) == key)?;
+    let number = archive
+        .tag_numbers()
+        .and_then(|numbers| numbers[tag_idx].value())
+        .or_else(|| parse_tag_number(strings.substring_raw(tags[tag_idx].value_idx() as usize)))?;
+    Some(number as f64 / TAG_NUMBER_SCALE as f64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{OsmWriter, TagNumber};
+    use flatdata::{ResourceStorage, SliceExt};
+
+    #[test]
+    fn test_parse_tag_number() {
+        assert_eq!(parse_tag_number(b"50"), Some(50_000));
+        assert_eq!(parse_tag_number(b" 30 mph"), Some(30_000));
+        assert_eq!(parse_tag_number(b"-1.25"), Some(-1_250));
+        assert_eq!(parse_tag_number(b"+.5"), Some(500));
+        assert_eq!(parse_tag_number(b"3."), Some(3_000));
+        assert_eq!(parse_tag_number(b"0.12345"), Some(123));
+        assert_eq!(parse_tag_number(b"50;70"), Some(50_000));
+        assert_eq!(parse_tag_number(b"signals"), None);
+        assert_eq!(parse_tag_number(b"."), None);
+        assert_eq!(parse_tag_number(b"-"), None);
+        assert_eq!(parse_tag_number(b""), None);
+        assert_eq!(parse_tag_number(b"99999999999999999999"), None);
+    }
+
+    #[test]
+    fn test_tag_number() {
+        let storage = flatdata::MemoryResourceStorage::new("/test");
+        let mut writer = OsmWriter::new(storage.clone()).unwrap();
+        writer.add_node(0.0, 0.0, [("population", "3500"), ("name", "12")]);
+        writer.add_node(0.0, 0.0, [("population", "unknown")]);
+        writer.finish().unwrap();
+
+        let archive = Osm::open(storage.clone()).unwrap();
+        let number = |archive: &Osm, idx: usize, key: &[u8]| {
+            tag_number(archive, archive.nodes()[idx].tags(), key)
+        };
+        assert_eq!(number(&archive, 0, b"population"), Some(3500.0));
+        assert_eq!(number(&archive, 0, b"name"), Some(12.0));
+        assert_eq!(number(&archive, 1, b"population"), None);
+        assert_eq!(number(&archive, 1, b"name"), None);
+
+        // stored numbers take precedence over the values
+        let mut number_7 = TagNumber::new();
+        number_7.set_value(Some(7));
+        let numbers = vec![number_7; archive.tags().len()];
+        storage
+            .write(
+                "tag_numbers",
+                crate::schema::osm::resources::TAG_NUMBERS,
+                numbers.as_slice().as_bytes(),
+            )
+            .unwrap();
+        let archive = Osm::open(storage).unwrap();
+        assert!(archive.tag_numbers().is_some());
+        assert_eq!(number(&archive, 0, b"name"), Some(0.007));
+        assert_eq!(number(&archive, 1, b"population"), Some(0.007));
+    }
+}
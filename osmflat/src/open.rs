@@ -0,0 +1,222 @@
+//! Opening archives from a path with checks beyond [`Osm::open`].
+//!
+//! [`Osm::open`] only fails when a resource is missing or has an unexpected
+//! size, often without naming the file, e.g. for an archive which was copied
+//! only partially. [`Osm::open_with_options`] can verify the checksums first,
+//! reject unknown files and missing optional resources, and names the archive
+//! and, if possible, the offending file in its errors.
+
+use crate::checksums::CHECKSUMS;
+use crate::Osm;
+
+use flatdata::{FileResourceStorage, MemoryResourceStorage, ResourceStorage, StorageHandle};
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Resources and subarchives of the [`Osm`] archive.
+const OSM_RESOURCES: &[&str] = &[
+    "Osm.archive",
+    "header",
+    "nodes",
+    "ways",
+    "relations",
+    "relation_members",
+    "relation_members_index",
+    "tags",
+    "tags_index",
+    "nodes_index",
+    "stringtable",
+    "ids",
+    "history",
+    "spatial_index",
+    "inverted_index",
+    "provenance",
+    "way_bboxes",
+    "relation_bboxes",
+    "tagged_nodes",
+    "changesets",
+    CHECKSUMS,
+    "id_index",
+    "node_ways",
+    "node_relations",
+    "way_relations",
+    "relation_relations",
+    "areas",
+    "stringtable_offsets",
+    "node_coords",
+    "elevation",
+    "tile_index",
+    "admin_hierarchy",
+    "tagged_node_bits",
+    "tagged_way_bits",
+    "tag_numbers",
+    "roles",
+    "way_centroids",
+    "relation_centroids",
+];
+
+/// Options for opening an archive with [`Osm::open_with_options`].
+///
+/// The default options open the archive like [`Osm::open`] with a
+/// [`FileResourceStorage`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpenOptions {
+    /// Fail if the directory of the archive contains files or directories
+    /// which are not resources of the schema, e.g. leftovers of another
+    /// archive
+    pub deny_unknown_resources: bool,
+    /// Fail if an optional resource or subarchive is missing, cf.
+    /// [`Osm::missing_optional_resources`]
+    pub deny_missing_optional: bool,
+    /// Verify the files against the `checksums` resource before opening, cf.
+    /// [`Osm::verify_checksums`]
+    pub verify_checksums: bool,
+    /// Read the resources into memory instead of memory mapping them, e.g. on
+    /// filesystems without support for memory mapping
+    pub buffered: bool,
+}
+
+fn invalid_data(path: &Path, msg: impl std::fmt::Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("failed to open {}: {msg}", path.display()),
+    )
+}
+
+/// Reads the files in `dir` into `storage`, recursing into subdirectories.
+fn read_dir(dir: &Path, storage: &dyn ResourceStorage) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if entry.file_type()?.is_dir() {
+            read_dir(&entry.path(), &*storage.subdir(&name))?;
+        } else {
+            storage
+                .create_output_stream(&name)?
+                .write_all(&fs::read(entry.path())?)?;
+        }
+    }
+    Ok(())
+}
+
+fn unknown_resources(path: &Path) -> io::Result<Vec<String>> {
+    let mut unknown = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        let resource = name.strip_suffix(".schema").unwrap_or(&name);
+        if !OSM_RESOURCES.contains(&resource) {
+            unknown.push(name);
+        }
+    }
+    unknown.sort();
+    Ok(unknown)
+}
+
+impl Osm {
+    /// Opens the archive at `path` with the checks and the storage given by
+    /// `options`.
+    ///
+    /// If opening fails and the archive has a `checksums` resource, the
+    /// checksums are verified to name the file which is truncated or
+    /// corrupted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use osmflat::{OpenOptions, Osm};
+    ///
+    /// let options = OpenOptions {
+    ///     verify_checksums: true,
+    ///     deny_unknown_resources: true,
+    ///     ..Default::default()
+    /// };
+    /// let archive = Osm::open_with_options("path/to/archive.osm.flatdata", &options).unwrap();
+    /// ```
+    pub fn open_with_options(path: impl AsRef<Path>, options: &OpenOptions) -> io::Result<Self> {
+        let path = path.as_ref();
+        if !path.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} is not a directory", path.display()),
+            ));
+        }
+        if options.deny_unknown_resources {
+            let unknown = unknown_resources(path)?;
+            if !unknown.is_empty() {
+                return Err(invalid_data(
+                    path,
+                    format!("unknown resources {}", unknown.join(", ")),
+                ));
+            }
+        }
+        if options.verify_checksums {
+            Self::verify_checksums(path).map_err(|e| invalid_data(path, e))?;
+        }
+
+        let storage: StorageHandle = if options.buffered {
+            let storage = MemoryResourceStorage::new(path);
+            read_dir(path, &*storage)?;
+            storage
+        } else {
+            FileResourceStorage::new(path)
+        };
+        let archive = Self::open(storage).map_err(|e| {
+            let has_checksums = path.join(CHECKSUMS).is_file();
+            match has_checksums.then(|| Self::verify_checksums(path)) {
+                Some(Err(checksum_error)) => invalid_data(path, checksum_error),
+                _ => invalid_data(path, e),
+            }
+        })?;
+
+        if options.deny_missing_optional {
+            let missing = archive.missing_optional_resources();
+            if !missing.is_empty() {
+                return Err(invalid_data(
+                    path,
+                    format!("missing optional resources {}", missing.join(", ")),
+                ));
+            }
+        }
+        Ok(archive)
+    }
+
+    /// Returns the names of the optional resources and subarchives which are
+    /// missing in the archive.
+    pub fn missing_optional_resources(&self) -> Vec<&'static str> {
+        [
+            ("ids", self.ids().is_some()),
+            ("history", self.history().is_some()),
+            ("spatial_index", self.spatial_index().is_some()),
+            ("inverted_index", self.inverted_index().is_some()),
+            ("provenance", self.provenance().is_some()),
+            ("way_bboxes", self.way_bboxes().is_some()),
+            ("relation_bboxes", self.relation_bboxes().is_some()),
+            ("tagged_nodes", self.tagged_nodes().is_some()),
+            ("changesets", self.changesets().is_some()),
+            (CHECKSUMS, self.checksums().is_some()),
+            ("id_index", self.id_index().is_some()),
+            ("node_ways", self.node_ways().is_some()),
+            ("node_relations", self.node_relations().is_some()),
+            ("way_relations", self.way_relations().is_some()),
+            ("relation_relations", self.relation_relations().is_some()),
+            ("areas", self.areas().is_some()),
+            ("stringtable_offsets", self.stringtable_offsets().is_some()),
+            ("node_coords", self.node_coords().is_some()),
+            ("elevation", self.elevation().is_some()),
+            ("tile_index", self.tile_index().is_some()),
+            ("admin_hierarchy", self.admin_hierarchy().is_some()),
+            ("tagged_node_bits", self.tagged_node_bits().is_some()),
+            ("tagged_way_bits", self.tagged_way_bits().is_some()),
+            ("tag_numbers", self.tag_numbers().is_some()),
+            ("roles", self.roles().is_some()),
+            ("way_centroids", self.way_centroids().is_some()),
+            ("relation_centroids", self.relation_centroids().is_some()),
+        ]
+        .into_iter()
+        .filter(|(_, present)| !present)
+        .map(|(name, _)| name)
+        .collect()
+    }
+}
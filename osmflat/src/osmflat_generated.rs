@@ -7,6 +7,15 @@ use flatdata::{flatdata_read_bytes, flatdata_write_bytes};
 
     /// Special value which represents an invalid index.
 pub const INVALID_IDX: u64 = 1_099_511_627_775;
+
+    /// Special value which represents an unknown elevation, as voids in SRTM tiles.
+pub const NO_ELEVATION: i16 = -32_768;
+
+    /// Special value which represents a tag value which is not a number.
+pub const NO_NUMBER: i64 = -9_223_372_036_854_775_808;
+
+    /// Special value which represents a missing centroid.
+pub const NO_CENTROID: i32 = -2_147_483_648;
 /// Metadata attached to the archive.
 #[repr(transparent)]
 #[derive(Clone)]
@@ -128,11 +137,12 @@ impl Header {
         unsafe { std::mem::transmute::<u64, u64>(value) }
     }
 
-    /// The origin (source) of the data.
+    /// The origin (source) of the data (reference to `stringtable`), if known.
     #[inline]
-    pub fn source_idx(&self) -> u64 {
+    pub fn source_idx(&self) -> Option<u64> {
         let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 200, 40);
-        unsafe { std::mem::transmute::<u64, u64>(value) }
+        let x = unsafe { std::mem::transmute::<u64, u64>(value) };
+        Some(x).filter(|&x| x != super::osm::INVALID_IDX)
     }
 
     /// Replication timestamp, expressed in seconds since the epoch.
@@ -154,11 +164,12 @@ impl Header {
         unsafe { std::mem::transmute::<i64, i64>(value) }
     }
 
-    /// Replication base URL (reference to `stringtable`).
+    /// Replication base URL (reference to `stringtable`), if the data has replication headers.
     #[inline]
-    pub fn replication_base_url_idx(&self) -> u64 {
+    pub fn replication_base_url_idx(&self) -> Option<u64> {
         let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 368, 40);
-        unsafe { std::mem::transmute::<u64, u64>(value) }
+        let x = unsafe { std::mem::transmute::<u64, u64>(value) };
+        Some(x).filter(|&x| x != super::osm::INVALID_IDX)
     }
 
 }
@@ -230,11 +241,11 @@ impl Header {
         flatdata_write_bytes!(u64; value, self.data, 160, 40)
     }
 
-    /// The origin (source) of the data.
+    /// The origin (source) of the data (reference to `stringtable`), if known.
     #[inline]
     #[allow(missing_docs)]
-    pub fn set_source_idx(&mut self, value: u64) {
-        flatdata_write_bytes!(u64; value, self.data, 200, 40)
+    pub fn set_source_idx(&mut self, value: Option<u64>) {
+let value = value.unwrap_or(super::osm::INVALID_IDX);        flatdata_write_bytes!(u64; value, self.data, 200, 40)
     }
 
     /// Replication timestamp, expressed in seconds since the epoch.
@@ -256,11 +267,11 @@ impl Header {
         flatdata_write_bytes!(i64; value, self.data, 304, 64)
     }
 
-    /// Replication base URL (reference to `stringtable`).
+    /// Replication base URL (reference to `stringtable`), if the data has replication headers.
     #[inline]
     #[allow(missing_docs)]
-    pub fn set_replication_base_url_idx(&mut self, value: u64) {
-        flatdata_write_bytes!(u64; value, self.data, 368, 40)
+    pub fn set_replication_base_url_idx(&mut self, value: Option<u64>) {
+let value = value.unwrap_or(super::osm::INVALID_IDX);        flatdata_write_bytes!(u64; value, self.data, 368, 40)
     }
 
 
@@ -1356,6 +1367,9 @@ impl Relation {
         self.set_tag_first_idx(other.tag_first_idx());
     }
 }
+/// OSM id of an entity.
+///
+/// Negative ids are stored in two's complement, cf. `Id::signed_value`.
 #[repr(transparent)]
 #[derive(Clone)]
 pub struct Id {
@@ -1470,6 +1484,154 @@ impl Id {
     }
 }
 
+/// Version information of an entity in a full-history archive.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct Version {
+    data: [u8; 13],
+}
+
+impl Version {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 13]}
+    }
+}
+
+impl flatdata::Struct for Version {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 13]}
+    }
+
+    const SIZE_IN_BYTES: usize = 13;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
+}
+
+impl Version {
+    pub fn new( ) -> Self {
+        Self{data : [0; 13]}
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 13]) -> &Self {
+        // Safety: This is safe since Version is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 13]) -> &mut Self {
+        // Safety: This is safe since Version is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 13 {
+            assert_eq!(data.len(), 13);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *const [u8; 13];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 13 {
+            assert_eq!(data.len(), 13);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 13];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 13] {
+        &self.data
+    }
+}
+
+impl Default for Version {
+    fn default( ) -> Self {
+        Self::new( )
+    }
+}
+
+unsafe impl flatdata::NoOverlap for Version {}
+
+impl Version {
+    /// Version number of the entity, starting at 1
+    #[inline]
+    pub fn version(&self) -> u32 {
+        let value = flatdata_read_bytes!(u32, self.data.as_ptr(), 0, 32);
+        unsafe { std::mem::transmute::<u32, u32>(value) }
+    }
+
+    /// Time of the change, expressed in seconds since the epoch
+    #[inline]
+    pub fn timestamp(&self) -> i64 {
+        let value = flatdata_read_bytes!(i64, self.data.as_ptr(), 32, 64);
+        unsafe { std::mem::transmute::<i64, i64>(value) }
+    }
+
+    /// Whether the entity exists in this version, i.e. is not deleted
+    #[inline]
+    pub fn visible(&self) -> bool {
+        let value = flatdata_read_bytes!(bool, self.data.as_ptr(), 96, 1);
+        unsafe { std::mem::transmute::<bool, bool>(value) }
+    }
+
+}
+
+impl std::fmt::Debug for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Version")
+            .field("version", &self.version())
+            .field("timestamp", &self.timestamp())
+            .field("visible", &self.visible())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for Version {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.version() == other.version() &&        self.timestamp() == other.timestamp() &&        self.visible() == other.visible()     }
+}
+
+impl Version {
+    /// Version number of the entity, starting at 1
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_version(&mut self, value: u32) {
+        flatdata_write_bytes!(u32; value, self.data, 0, 32)
+    }
+
+    /// Time of the change, expressed in seconds since the epoch
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_timestamp(&mut self, value: i64) {
+        flatdata_write_bytes!(i64; value, self.data, 32, 64)
+    }
+
+    /// Whether the entity exists in this version, i.e. is not deleted
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_visible(&mut self, value: bool) {
+        flatdata_write_bytes!(bool; value, self.data, 96, 1)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &Version) {
+        self.set_version(other.version());
+        self.set_timestamp(other.timestamp());
+        self.set_visible(other.visible());
+    }
+}
 
 
 /// An optional sub-archive storing the original OSM ids of nodes, ways, and relations
@@ -1649,281 +1811,421 @@ impl IdsBuilder {
 
 
 
-/// Enum for read-only heterogeneous access to elements in a
-/// bucket of the [`relation_members`] resource.
+/// An optional sub-archive storing the version information of full-history archives.
 ///
-/// [`relation_members`]: struct.Archive{.osm.Osm}.html#method.relation_members
-#[derive(Clone, PartialEq)]
-pub enum RelationMembersRef<'a> {
-    #[allow(missing_docs)]
-    NodeMember(&'a super::osm::NodeMember),    #[allow(missing_docs)]
-    WayMember(&'a super::osm::WayMember),    #[allow(missing_docs)]
-    RelationMember(&'a super::osm::RelationMember),}
+/// In a full-history archive, all versions of an entity are stored consecutively and ordered
+/// by version in the `nodes`, `ways`, and `relations` vectors of the parent archive.
+#[derive(Clone)]
+pub struct History {
+    _storage: flatdata::StorageHandle,
+    nodes : &'static [super::osm::Version],
+    ways : &'static [super::osm::Version],
+    relations : &'static [super::osm::Version],
+}
 
-impl<'a> ::std::fmt::Debug for RelationMembersRef<'a> {
+impl History {
+    fn signature_name(archive_name: &str) -> String {
+        format!("{}.archive", archive_name)
+    }
+
+    /// Versions of all nodes in the parent archive
+/// nodes[i] has its version stored in history.nodes[i]
+    #[inline]
+    pub fn nodes(&self) -> &[super::osm::Version] {
+        self.nodes
+    }
+
+    /// Versions of all ways in the parent archive
+/// ways[i] has its version stored in history.ways[i]
+    #[inline]
+    pub fn ways(&self) -> &[super::osm::Version] {
+        self.ways
+    }
+
+    /// Versions of all relations in the parent archive
+/// relations[i] has its version stored in history.relations[i]
+    #[inline]
+    pub fn relations(&self) -> &[super::osm::Version] {
+        self.relations
+    }
+
+}
+
+impl ::std::fmt::Debug for History {
     fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
-        match *self {
-            RelationMembersRef::NodeMember(ref inner) => write!(f, "{:?}", inner),
-            RelationMembersRef::WayMember(ref inner) => write!(f, "{:?}", inner),
-            RelationMembersRef::RelationMember(ref inner) => write!(f, "{:?}", inner),
-        }
+        f.debug_struct("History")
+            .field("nodes", &self.nodes())
+            .field("ways", &self.ways())
+            .field("relations", &self.relations())
+            .finish()
     }
 }
 
-impl<'a> flatdata::VariadicRef for RelationMembersRef<'a> {
-    #[inline]
-    fn size_in_bytes(&self) -> usize {
-        match *self {
-            RelationMembersRef::NodeMember(_) => <super::osm::NodeMember as flatdata::Struct>::SIZE_IN_BYTES,
-            RelationMembersRef::WayMember(_) => <super::osm::WayMember as flatdata::Struct>::SIZE_IN_BYTES,
-            RelationMembersRef::RelationMember(_) => <super::osm::RelationMember as flatdata::Struct>::SIZE_IN_BYTES,
-        }
+impl History {
+    pub fn open(storage: flatdata::StorageHandle)
+        -> ::std::result::Result<Self, flatdata::ResourceStorageError>
+    {
+        #[allow(unused_imports)]
+        use flatdata::SliceExt;
+        #[allow(unused_variables)]
+        use flatdata::ResourceStorageError as Error;
+        // extend lifetime since Rust cannot know that we reference a cache here
+        #[allow(unused_variables)]
+        let extend = |x : Result<&[u8], Error>| -> Result<&'static [u8], Error> {x.map(|x| unsafe{std::mem::transmute(x)})};
+
+        storage.read(&Self::signature_name("History"), schema::history::HISTORY)?;
+
+        let nodes = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("nodes", schema::history::resources::NODES));
+            check("nodes", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Version]>::from_bytes(x)))?
+        };
+        let ways = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("ways", schema::history::resources::WAYS));
+            check("ways", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Version]>::from_bytes(x)))?
+        };
+        let relations = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("relations", schema::history::resources::RELATIONS));
+            check("relations", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Version]>::from_bytes(x)))?
+        };
+
+        Ok(Self {
+            _storage: storage,
+            nodes,
+            ways,
+            relations,
+        })
     }
 }
 
-/// Builder of buckets in the [`relation_members`] resource.
-///
-/// Refers to a single bucket in the [`relation_members`] multivector and
-/// provides methods for adding heterogeneous data to the bucket.
+/// Builder for creating [`History`] archives.
 ///
-/// [`relation_members`]: struct.Archive{.osm.Osm}.html#method.relation_members
-pub struct RelationMembersBuilder<'a> {
-    data: &'a mut Vec<u8>
+///[`History`]: struct.History.html
+#[derive(Clone, Debug)]
+pub struct HistoryBuilder {
+    storage: flatdata::StorageHandle
 }
 
-impl<'a> RelationMembersBuilder<'a> {
-    /// Adds data of the type [`NodeMember`] to the bucket.
-    ///
-    /// [`NodeMember`]: struct.NodeMember.html
+impl HistoryBuilder {
     #[inline]
-    pub fn add_node_member<'b>(&'b mut self) -> &'b mut super::osm::NodeMember {
-        let old_len = self.data.len();
-        let increment = 1 + <super::osm::NodeMember as flatdata::Struct>::SIZE_IN_BYTES;
-        self.data.resize(old_len + increment, 0);
-        self.data[old_len] = 0;
-        let slice = &mut self.data[1 + old_len..];
-        super::osm::NodeMember::from_bytes_slice_mut(slice).expect("Logic error: Cannot create super::osm::NodeMember from slice")
+    /// Stores [`nodes`] in the archive.
+    ///
+    /// [`nodes`]: struct.History.html#method.nodes
+    pub fn set_nodes(&self, vector: &[super::osm::Version]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("nodes", schema::history::resources::NODES, vector.as_bytes())
     }
-    /// Adds data of the type [`WayMember`] to the bucket.
+
+    /// Opens [`nodes`] in the archive for buffered writing.
     ///
-    /// [`WayMember`]: struct.WayMember.html
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`nodes`]: struct.History.html#method.nodes
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
     #[inline]
-    pub fn add_way_member<'b>(&'b mut self) -> &'b mut super::osm::WayMember {
-        let old_len = self.data.len();
-        let increment = 1 + <super::osm::WayMember as flatdata::Struct>::SIZE_IN_BYTES;
-        self.data.resize(old_len + increment, 0);
-        self.data[old_len] = 1;
-        let slice = &mut self.data[1 + old_len..];
-        super::osm::WayMember::from_bytes_slice_mut(slice).expect("Logic error: Cannot create super::osm::WayMember from slice")
+    pub fn start_nodes(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Version>> {
+        flatdata::create_external_vector(&*self.storage, "nodes", schema::history::resources::NODES)
     }
-    /// Adds data of the type [`RelationMember`] to the bucket.
+
+    #[inline]
+    /// Stores [`ways`] in the archive.
     ///
-    /// [`RelationMember`]: struct.RelationMember.html
+    /// [`ways`]: struct.History.html#method.ways
+    pub fn set_ways(&self, vector: &[super::osm::Version]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("ways", schema::history::resources::WAYS, vector.as_bytes())
+    }
+
+    /// Opens [`ways`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`ways`]: struct.History.html#method.ways
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
     #[inline]
-    pub fn add_relation_member<'b>(&'b mut self) -> &'b mut super::osm::RelationMember {
-        let old_len = self.data.len();
-        let increment = 1 + <super::osm::RelationMember as flatdata::Struct>::SIZE_IN_BYTES;
-        self.data.resize(old_len + increment, 0);
-        self.data[old_len] = 2;
-        let slice = &mut self.data[1 + old_len..];
-        super::osm::RelationMember::from_bytes_slice_mut(slice).expect("Logic error: Cannot create super::osm::RelationMember from slice")
+    pub fn start_ways(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Version>> {
+        flatdata::create_external_vector(&*self.storage, "ways", schema::history::resources::WAYS)
     }
-}
 
-/// Variadic struct attached to the [`relation_members`] archive resource.
-///
-/// It unifies the following data types:
-//
-/// * [`NodeMember`]
-/// * [`WayMember`]
-/// * [`RelationMember`]
-///
-/// ## Access pattern
-///
-/// This structure is used as a template parameter in [`relation_members`] multivector/
-/// multiarray view. It does not contain any data, instead it references
-///
-/// * [`RelationMembersRef`] for the read-only heterogeneous access, and
-/// * [`RelationMembersBuilder`] for the mutable builder pattern access.
+    #[inline]
+    /// Stores [`relations`] in the archive.
+    ///
+    /// [`relations`]: struct.History.html#method.relations
+    pub fn set_relations(&self, vector: &[super::osm::Version]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("relations", schema::history::resources::RELATIONS, vector.as_bytes())
+    }
+
+    /// Opens [`relations`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`relations`]: struct.History.html#method.relations
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_relations(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Version>> {
+        flatdata::create_external_vector(&*self.storage, "relations", schema::history::resources::RELATIONS)
+    }
+
+}
+
+impl HistoryBuilder {
+    pub fn new(
+        storage: flatdata::StorageHandle,
+    ) -> Result<Self, flatdata::ResourceStorageError> {
+        flatdata::create_archive("History", schema::history::HISTORY, &storage)?;
+        Ok(Self { storage })
+    }
+}
+
+
+
+/// Bounding box in the spatial index.
 ///
-/// [`relation_members`]: struct.Archive{.osm.Osm}.html#method.relation_members
-/// [`RelationMembersRef`]: enum.RelationMembersRef.html
-/// [`RelationMembersBuilder`]: struct.RelationMembersBuilder.html
-/// [`NodeMember`]: struct.NodeMember.html
-/// [`WayMember`]: struct.WayMember.html
-/// [`RelationMember`]: struct.RelationMember.html
+/// Coordinates are stored in the same scale as node coordinates, cf. `Header.coord_scale`.
+#[repr(transparent)]
 #[derive(Clone)]
-pub struct RelationMembers {}
+pub struct SpatialBox {
+    data: [u8; 21],
+}
 
-impl flatdata::VariadicIndex for RelationMembers {
-    type Index = super::_builtin::multivector::IndexType40;
+impl SpatialBox {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 21]}
+    }
 }
 
-impl<'a> flatdata::VariadicStruct<'a> for RelationMembers {
-    type Item = RelationMembersRef<'a>;
+impl flatdata::Struct for SpatialBox {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 21]}
+    }
 
-    #[inline]
-    fn create(index: flatdata::TypeIndex, data: &'a [u8]) -> Self::Item
-    {
-        match index {
-                0 => RelationMembersRef::NodeMember(super::osm::NodeMember::from_bytes_slice(&data).expect("Corrupted data")),
-                1 => RelationMembersRef::WayMember(super::osm::WayMember::from_bytes_slice(&data).expect("Corrupted data")),
-                2 => RelationMembersRef::RelationMember(super::osm::RelationMember::from_bytes_slice(&data).expect("Corrupted data")),
-            _ => panic!("invalid type index {} for variadic type RelationMembersRef", index),
+    const SIZE_IN_BYTES: usize = 21;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
+}
+
+impl SpatialBox {
+    pub fn new( ) -> Self {
+        Self{data : [0; 21]}
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 21]) -> &Self {
+        // Safety: This is safe since SpatialBox is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 21]) -> &mut Self {
+        // Safety: This is safe since SpatialBox is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 21 {
+            assert_eq!(data.len(), 21);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
         }
+        let ptr = data.as_ptr() as *const [u8; 21];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
     }
 
-    type ItemMut = RelationMembersBuilder<'a>;
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 21 {
+            assert_eq!(data.len(), 21);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 21];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
+    }
 
-    #[inline]
-    fn create_mut(data: &'a mut Vec<u8>) -> Self::ItemMut
-    {
-        Self::ItemMut { data }
+    pub fn as_bytes(&self) -> &[u8; 21] {
+        &self.data
     }
 }
 
-/// OSM data archive
-///
-/// Relations and relation members are indexed with the same index, i.e.
-/// a relation at index `i` in the vector `relations` has the members
-/// at index `i` in the multivector `relation_members`.
-///
-/// All 1:n relationships are modeled in-place by using an additional index. This is a
-/// common pattern in flatdata. For example, a node might have multiple tags attached
-/// to it. To model this, a node in `nodes` references the first tag attached to it
-/// by storing an index in the `tags_index` vector. The next node in `nodes` again
-/// references its first tag, which is the last tag (exclusive) of the previous node.
-///
-/// ```text
-/// nodes:      [ ..., n_1, n_2, ... ]
-///                     |    |
-///                     |    +-------+
-///                     v            v
-/// tags_index: [ ..., t_11, t_12, ..., t_1n, t_21, ... t_2m, ... ]
-/// ```
-#[derive(Clone)]
-pub struct Osm {
-    _storage: flatdata::StorageHandle,
-    header : &'static super::osm::Header,
-    nodes : &'static [super::osm::Node],
-    ways : &'static [super::osm::Way],
-    relations : &'static [super::osm::Relation],
-    relation_members : flatdata::MultiArrayView<'static, RelationMembers>,
-    tags : &'static [super::osm::Tag],
-    tags_index : &'static [super::osm::TagIndex],
-    nodes_index : &'static [super::osm::NodeIndex],
-    stringtable : flatdata::RawData<'static>,
-    ids : Option<super::osm::Ids
->,
+impl Default for SpatialBox {
+    fn default( ) -> Self {
+        Self::new( )
+    }
 }
 
-impl Osm {
-    fn signature_name(archive_name: &str) -> String {
-        format!("{}.archive", archive_name)
+unsafe impl flatdata::NoOverlap for SpatialBox {}
+
+impl SpatialBox {
+    /// Minimal longitude
+    #[inline]
+    pub fn left(&self) -> i32 {
+        let value = flatdata_read_bytes!(i32, self.data.as_ptr(), 0, 32);
+        unsafe { std::mem::transmute::<i32, i32>(value) }
     }
 
-    /// Header which contains the metadata attached to the archive.
+    /// Minimal latitude
     #[inline]
-    pub fn header(&self) -> &super::osm::Header {
-        self.header
+    pub fn bottom(&self) -> i32 {
+        let value = flatdata_read_bytes!(i32, self.data.as_ptr(), 32, 32);
+        unsafe { std::mem::transmute::<i32, i32>(value) }
     }
 
-    /// List of nodes.
-///
-/// A node references a range of tags in the `tags_index` vector.
+    /// Maximal longitude
     #[inline]
-    pub fn nodes(&self) -> &[super::osm::Node] {
-        self.nodes
+    pub fn right(&self) -> i32 {
+        let value = flatdata_read_bytes!(i32, self.data.as_ptr(), 64, 32);
+        unsafe { std::mem::transmute::<i32, i32>(value) }
     }
 
-    /// List of ways.
-///
-/// A way references
-///
-/// * a range of tags in the `tags_index` vector, and
-/// * a range of nodes in the `nodes_index` vector.
+    /// Maximal latitude
     #[inline]
-    pub fn ways(&self) -> &[super::osm::Way] {
-        self.ways
+    pub fn top(&self) -> i32 {
+        let value = flatdata_read_bytes!(i32, self.data.as_ptr(), 96, 32);
+        unsafe { std::mem::transmute::<i32, i32>(value) }
     }
 
-    /// List of relations.
-///
-/// A relation references a range of tags in `tags_index` vectors.
-/// Members are attached to a relation implicitly: members that belong to a
-/// relation at index `i` are at index `i` in the `relation_members` multivector.
+    /// Index of the entity in a leaf box, index of the first child box otherwise
     #[inline]
-    pub fn relations(&self) -> &[super::osm::Relation] {
-        self.relations
+    pub fn index(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 128, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
     }
 
-    /// Members attached to relations.
-///
-/// An index in this multivector corresponds to an index in the `relations` vector.
-///
-/// A member has a variadic type: `NodeMember`, `WayMember` or `RelationMember`.
-/// Each type references its role in the `stringtable` raw data. Additionally,
-///
-/// * a node member references a node in the `nodes` vector,
-/// * a way member references a way in the `ways` vector,
-/// * a relation member references a relation in the `relations` vector.
+}
+
+impl std::fmt::Debug for SpatialBox {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("SpatialBox")
+            .field("left", &self.left())
+            .field("bottom", &self.bottom())
+            .field("right", &self.right())
+            .field("top", &self.top())
+            .field("index", &self.index())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for SpatialBox {
     #[inline]
-    pub fn relation_members(&self) -> &flatdata::MultiArrayView<RelationMembers> {
-        &self.relation_members
+    fn eq(&self, other: &Self) -> bool {
+        self.left() == other.left() &&        self.bottom() == other.bottom() &&        self.right() == other.right() &&        self.top() == other.top() &&        self.index() == other.index()     }
+}
+
+impl SpatialBox {
+    /// Minimal longitude
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_left(&mut self, value: i32) {
+        flatdata_write_bytes!(i32; value, self.data, 0, 32)
     }
 
-    /// List of tags.
-///
-/// A tag references its key and value in the `stringtable` raw data.
+    /// Minimal latitude
     #[inline]
-    pub fn tags(&self) -> &[super::osm::Tag] {
-        self.tags
+    #[allow(missing_docs)]
+    pub fn set_bottom(&mut self, value: i32) {
+        flatdata_write_bytes!(i32; value, self.data, 32, 32)
     }
 
-    /// Auxiliary index of tags to model 1:n relationships between nodes, ways, relations
-/// and tags.
+    /// Maximal longitude
     #[inline]
-    pub fn tags_index(&self) -> &[super::osm::TagIndex] {
-        self.tags_index
+    #[allow(missing_docs)]
+    pub fn set_right(&mut self, value: i32) {
+        flatdata_write_bytes!(i32; value, self.data, 64, 32)
     }
 
-    /// Auxiliary index of nodes to model 1:n relationship between ways and nodes.
+    /// Maximal latitude
     #[inline]
-    pub fn nodes_index(&self) -> &[super::osm::NodeIndex] {
-        self.nodes_index
+    #[allow(missing_docs)]
+    pub fn set_top(&mut self, value: i32) {
+        flatdata_write_bytes!(i32; value, self.data, 96, 32)
     }
 
-    /// List of strings separated by `\0`.
+    /// Index of the entity in a leaf box, index of the first child box otherwise
     #[inline]
-    pub fn stringtable(&self) -> flatdata::RawData {
-        self.stringtable
+    #[allow(missing_docs)]
+    pub fn set_index(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 128, 40)
     }
 
+
+    /// Copies the data from `other` into this struct.
     #[inline]
-    pub fn ids(&self) -> Option<&super::osm::Ids> {
-        self.ids.as_ref()
+    pub fn fill_from(&mut self, other: &SpatialBox) {
+        self.set_left(other.left());
+        self.set_bottom(other.bottom());
+        self.set_right(other.right());
+        self.set_top(other.top());
+        self.set_index(other.index());
     }
+}
+
 
+/// An optional sub-archive storing a spatial index of nodes, ways, and relations.
+///
+/// Each vector is a packed R-tree. It starts with the bounding boxes of the entities
+/// (the leaves) sorted by the position of their centers on the Hilbert curve, followed
+/// by the levels of the tree bottom up. A box of a level covers up to 16 consecutive
+/// boxes of the level below. The last box is the root. Entities without coordinates,
+/// e.g. ways whose nodes are all missing, are not contained.
+#[derive(Clone)]
+pub struct SpatialIndex {
+    _storage: flatdata::StorageHandle,
+    nodes : &'static [super::osm::SpatialBox],
+    ways : &'static [super::osm::SpatialBox],
+    relations : &'static [super::osm::SpatialBox],
 }
 
-impl ::std::fmt::Debug for Osm {
+impl SpatialIndex {
+    fn signature_name(archive_name: &str) -> String {
+        format!("{}.archive", archive_name)
+    }
+
+    /// R-tree of the nodes in the parent archive
+    #[inline]
+    pub fn nodes(&self) -> &[super::osm::SpatialBox] {
+        self.nodes
+    }
+
+    /// R-tree of the ways in the parent archive
+    #[inline]
+    pub fn ways(&self) -> &[super::osm::SpatialBox] {
+        self.ways
+    }
+
+    /// R-tree of the relations in the parent archive
+    #[inline]
+    pub fn relations(&self) -> &[super::osm::SpatialBox] {
+        self.relations
+    }
+
+}
+
+impl ::std::fmt::Debug for SpatialIndex {
     fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
-        f.debug_struct("Osm")
-            .field("header", &self.header())
+        f.debug_struct("SpatialIndex")
             .field("nodes", &self.nodes())
             .field("ways", &self.ways())
             .field("relations", &self.relations())
-            .field("relation_members", &self.relation_members())
-            .field("tags", &self.tags())
-            .field("tags_index", &self.tags_index())
-            .field("nodes_index", &self.nodes_index())
-            .field("stringtable", &self.stringtable())
-            .field("ids", &self.ids())
             .finish()
     }
 }
 
-impl Osm {
+impl SpatialIndex {
     pub fn open(storage: flatdata::StorageHandle)
         -> ::std::result::Result<Self, flatdata::ResourceStorageError>
     {
@@ -1935,126 +2237,52 @@ impl Osm {
         #[allow(unused_variables)]
         let extend = |x : Result<&[u8], Error>| -> Result<&'static [u8], Error> {x.map(|x| unsafe{std::mem::transmute(x)})};
 
-        storage.read(&Self::signature_name("Osm"), schema::osm::OSM)?;
+        storage.read(&Self::signature_name("SpatialIndex"), schema::spatial_index::SPATIAL_INDEX)?;
 
-        let header = {
+        let nodes = {
             use flatdata::check_resource as check;
             let max_size = None;
-            let resource = extend(storage.read("header", schema::osm::resources::HEADER));
-            check("header", |_| 0, max_size, resource.and_then(|x| super::osm::Header::from_bytes_slice(x)))?
-        };
-        let nodes = {
-            use flatdata::check_resource as check;
-            let max_size = Some(1099511627776);
-            let resource = extend(storage.read("nodes", schema::osm::resources::NODES));
-            check("nodes", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Node]>::from_bytes(x)))?
+            let resource = extend(storage.read("nodes", schema::spatial_index::resources::NODES));
+            check("nodes", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::SpatialBox]>::from_bytes(x)))?
         };
         let ways = {
-            use flatdata::check_resource as check;
-            let max_size = Some(1099511627776);
-            let resource = extend(storage.read("ways", schema::osm::resources::WAYS));
-            check("ways", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Way]>::from_bytes(x)))?
-        };
-        let relations = {
-            use flatdata::check_resource as check;
-            let max_size = Some(1099511627776);
-            let resource = extend(storage.read("relations", schema::osm::resources::RELATIONS));
-            check("relations", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Relation]>::from_bytes(x)))?
-        };
-        let relation_members = {
             use flatdata::check_resource as check;
             let max_size = None;
-            let index_schema = &format!("index({})", schema::osm::resources::RELATION_MEMBERS);
-            let index = extend(storage.read("relation_members_index", &index_schema));
-            let data = extend(storage.read("relation_members", schema::osm::resources::RELATION_MEMBERS));
-            let result = match (index, data) {
-                (Ok(index), Ok(data)) => {
-                    Ok(flatdata::MultiArrayView::new(
-                        <&[super::_builtin::multivector::IndexType40]>::from_bytes(index)?,
-                        data
-                    ))
-                }
-                // is resource completely missing?
-                (Err(Error::Missing), Err(Error::Missing))  => Err(Error::Missing),
-                // is resource partially missing / broken -> extract best error to propagate
-                (Ok(_), Err(Error::Missing)) | (Err(Error::Missing), Ok(_)) => Err(Error::MissingData),
-                (Err(Error::Missing), Err(x)) | (Err(x), Err(Error::Missing)) => {return Err(x);}
-                (_, Err(x)) | (Err(x), _) => {return Err(x);}
-            };
-            check("relation_members", |r| r.len(), max_size, result)?
-        };
-        let tags = {
-            use flatdata::check_resource as check;
-            let max_size = Some(1099511627776);
-            let resource = extend(storage.read("tags", schema::osm::resources::TAGS));
-            check("tags", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Tag]>::from_bytes(x)))?
-        };
-        let tags_index = {
-            use flatdata::check_resource as check;
-            let max_size = Some(1099511627776);
-            let resource = extend(storage.read("tags_index", schema::osm::resources::TAGS_INDEX));
-            check("tags_index", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::TagIndex]>::from_bytes(x)))?
+            let resource = extend(storage.read("ways", schema::spatial_index::resources::WAYS));
+            check("ways", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::SpatialBox]>::from_bytes(x)))?
         };
-        let nodes_index = {
-            use flatdata::check_resource as check;
-            let max_size = Some(1099511627776);
-            let resource = extend(storage.read("nodes_index", schema::osm::resources::NODES_INDEX));
-            check("nodes_index", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::NodeIndex]>::from_bytes(x)))?
-        };
-        let stringtable = {
+        let relations = {
             use flatdata::check_resource as check;
-            let max_size = Some(1099511627776);
-            let resource = extend(storage.read("stringtable", schema::osm::resources::STRINGTABLE));
-            check("stringtable", |r| r.len(), max_size, resource.map(|x| flatdata::RawData::new(x)))?
-        };
-        let ids = {
-            use flatdata::check_optional_resource as check;
             let max_size = None;
-            check("ids", |_| 0, max_size, super::osm::Ids::open(storage.subdir("ids")))?
+            let resource = extend(storage.read("relations", schema::spatial_index::resources::RELATIONS));
+            check("relations", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::SpatialBox]>::from_bytes(x)))?
         };
 
         Ok(Self {
             _storage: storage,
-            header,
             nodes,
             ways,
             relations,
-            relation_members,
-            tags,
-            tags_index,
-            nodes_index,
-            stringtable,
-            ids,
         })
     }
 }
 
-/// Builder for creating [`Osm`] archives.
+/// Builder for creating [`SpatialIndex`] archives.
 ///
-///[`Osm`]: struct.Osm.html
+///[`SpatialIndex`]: struct.SpatialIndex.html
 #[derive(Clone, Debug)]
-pub struct OsmBuilder {
+pub struct SpatialIndexBuilder {
     storage: flatdata::StorageHandle
 }
 
-impl OsmBuilder {
-    #[inline]
-    /// Stores [`header`] in the archive.
-    ///
-    /// [`header`]: struct.Osm.html#method.header
-    /// Stores [`header`] in the archive.
-    pub fn set_header(&self, resource: &super::osm::Header) -> ::std::io::Result<()> {
-        let data = resource.as_bytes();
-        self.storage.write("header", schema::osm::resources::HEADER, data)
-    }
-
+impl SpatialIndexBuilder {
     #[inline]
     /// Stores [`nodes`] in the archive.
     ///
-    /// [`nodes`]: struct.Osm.html#method.nodes
-    pub fn set_nodes(&self, vector: &[super::osm::Node]) -> ::std::io::Result<()> {
+    /// [`nodes`]: struct.SpatialIndex.html#method.nodes
+    pub fn set_nodes(&self, vector: &[super::osm::SpatialBox]) -> ::std::io::Result<()> {
         use flatdata::SliceExt;
-        self.storage.write("nodes", schema::osm::resources::NODES, vector.as_bytes())
+        self.storage.write("nodes", schema::spatial_index::resources::NODES, vector.as_bytes())
     }
 
     /// Opens [`nodes`] in the archive for buffered writing.
@@ -2063,20 +2291,20 @@ impl OsmBuilder {
     /// is called. To flush the data fully into the archive, this method must be called
     /// in the end.
     ///
-    /// [`nodes`]: struct.Osm.html#method.nodes
+    /// [`nodes`]: struct.SpatialIndex.html#method.nodes
     /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
     #[inline]
-    pub fn start_nodes(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Node>> {
-        flatdata::create_external_vector(&*self.storage, "nodes", schema::osm::resources::NODES)
+    pub fn start_nodes(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::SpatialBox>> {
+        flatdata::create_external_vector(&*self.storage, "nodes", schema::spatial_index::resources::NODES)
     }
 
     #[inline]
     /// Stores [`ways`] in the archive.
     ///
-    /// [`ways`]: struct.Osm.html#method.ways
-    pub fn set_ways(&self, vector: &[super::osm::Way]) -> ::std::io::Result<()> {
+    /// [`ways`]: struct.SpatialIndex.html#method.ways
+    pub fn set_ways(&self, vector: &[super::osm::SpatialBox]) -> ::std::io::Result<()> {
         use flatdata::SliceExt;
-        self.storage.write("ways", schema::osm::resources::WAYS, vector.as_bytes())
+        self.storage.write("ways", schema::spatial_index::resources::WAYS, vector.as_bytes())
     }
 
     /// Opens [`ways`] in the archive for buffered writing.
@@ -2085,20 +2313,20 @@ impl OsmBuilder {
     /// is called. To flush the data fully into the archive, this method must be called
     /// in the end.
     ///
-    /// [`ways`]: struct.Osm.html#method.ways
+    /// [`ways`]: struct.SpatialIndex.html#method.ways
     /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
     #[inline]
-    pub fn start_ways(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Way>> {
-        flatdata::create_external_vector(&*self.storage, "ways", schema::osm::resources::WAYS)
+    pub fn start_ways(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::SpatialBox>> {
+        flatdata::create_external_vector(&*self.storage, "ways", schema::spatial_index::resources::WAYS)
     }
 
     #[inline]
     /// Stores [`relations`] in the archive.
     ///
-    /// [`relations`]: struct.Osm.html#method.relations
-    pub fn set_relations(&self, vector: &[super::osm::Relation]) -> ::std::io::Result<()> {
+    /// [`relations`]: struct.SpatialIndex.html#method.relations
+    pub fn set_relations(&self, vector: &[super::osm::SpatialBox]) -> ::std::io::Result<()> {
         use flatdata::SliceExt;
-        self.storage.write("relations", schema::osm::resources::RELATIONS, vector.as_bytes())
+        self.storage.write("relations", schema::spatial_index::resources::RELATIONS, vector.as_bytes())
     }
 
     /// Opens [`relations`] in the archive for buffered writing.
@@ -2107,127 +2335,7217 @@ impl OsmBuilder {
     /// is called. To flush the data fully into the archive, this method must be called
     /// in the end.
     ///
-    /// [`relations`]: struct.Osm.html#method.relations
+    /// [`relations`]: struct.SpatialIndex.html#method.relations
     /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
     #[inline]
-    pub fn start_relations(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Relation>> {
-        flatdata::create_external_vector(&*self.storage, "relations", schema::osm::resources::RELATIONS)
+    pub fn start_relations(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::SpatialBox>> {
+        flatdata::create_external_vector(&*self.storage, "relations", schema::spatial_index::resources::RELATIONS)
     }
 
-    /// Opens [`relation_members`] in the archive for buffered writing.
-    ///
-    /// Elements can be added to the multivector until the [`MultiVector::close`] method
-    /// is called. To flush the data fully into the archive, this method must be called
-    /// in the end.
-    ///
-    /// [`relation_members`]: struct.Osm.html#method.relation_members
-    /// [`MultiVector::close`]: flatdata/struct.MultiVector.html#method.close
-    #[inline]
-    pub fn start_relation_members(&self) -> ::std::io::Result<flatdata::MultiVector<RelationMembers>> {
-        flatdata::create_multi_vector(&*self.storage, "relation_members", schema::osm::resources::RELATION_MEMBERS)
+}
+
+impl SpatialIndexBuilder {
+    pub fn new(
+        storage: flatdata::StorageHandle,
+    ) -> Result<Self, flatdata::ResourceStorageError> {
+        flatdata::create_archive("SpatialIndex", schema::spatial_index::SPATIAL_INDEX, &storage)?;
+        Ok(Self { storage })
     }
+}
 
-    #[inline]
-    /// Stores [`tags`] in the archive.
-    ///
-    /// [`tags`]: struct.Osm.html#method.tags
-    pub fn set_tags(&self, vector: &[super::osm::Tag]) -> ::std::io::Result<()> {
-        use flatdata::SliceExt;
-        self.storage.write("tags", schema::osm::resources::TAGS, vector.as_bytes())
+
+
+/// Entities having a tag in the inverted tag index.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct TagPostings {
+    data: [u8; 15],
+}
+
+impl TagPostings {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 15]}
     }
+}
 
-    /// Opens [`tags`] in the archive for buffered writing.
-    ///
-    /// Elements can be added to the vector until the [`ExternalVector::close`] method
-    /// is called. To flush the data fully into the archive, this method must be called
-    /// in the end.
-    ///
-    /// [`tags`]: struct.Osm.html#method.tags
-    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
-    #[inline]
-    pub fn start_tags(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Tag>> {
-        flatdata::create_external_vector(&*self.storage, "tags", schema::osm::resources::TAGS)
+impl flatdata::Struct for TagPostings {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 15]}
     }
 
-    #[inline]
-    /// Stores [`tags_index`] in the archive.
-    ///
-    /// [`tags_index`]: struct.Osm.html#method.tags_index
-    pub fn set_tags_index(&self, vector: &[super::osm::TagIndex]) -> ::std::io::Result<()> {
-        use flatdata::SliceExt;
-        self.storage.write("tags_index", schema::osm::resources::TAGS_INDEX, vector.as_bytes())
+    const SIZE_IN_BYTES: usize = 15;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
+}
+
+impl TagPostings {
+    pub fn new( ) -> Self {
+        Self{data : [0; 15]}
     }
 
-    /// Opens [`tags_index`] in the archive for buffered writing.
-    ///
-    /// Elements can be added to the vector until the [`ExternalVector::close`] method
-    /// is called. To flush the data fully into the archive, this method must be called
-    /// in the end.
-    ///
-    /// [`tags_index`]: struct.Osm.html#method.tags_index
-    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
-    #[inline]
-    pub fn start_tags_index(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::TagIndex>> {
-        flatdata::create_external_vector(&*self.storage, "tags_index", schema::osm::resources::TAGS_INDEX)
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 15]) -> &Self {
+        // Safety: This is safe since TagPostings is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
     }
 
-    #[inline]
-    /// Stores [`nodes_index`] in the archive.
-    ///
-    /// [`nodes_index`]: struct.Osm.html#method.nodes_index
-    pub fn set_nodes_index(&self, vector: &[super::osm::NodeIndex]) -> ::std::io::Result<()> {
-        use flatdata::SliceExt;
-        self.storage.write("nodes_index", schema::osm::resources::NODES_INDEX, vector.as_bytes())
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 15]) -> &mut Self {
+        // Safety: This is safe since TagPostings is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
     }
 
-    /// Opens [`nodes_index`] in the archive for buffered writing.
-    ///
-    /// Elements can be added to the vector until the [`ExternalVector::close`] method
-    /// is called. To flush the data fully into the archive, this method must be called
-    /// in the end.
-    ///
-    /// [`nodes_index`]: struct.Osm.html#method.nodes_index
-    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 15 {
+            assert_eq!(data.len(), 15);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *const [u8; 15];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 15 {
+            assert_eq!(data.len(), 15);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 15];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 15] {
+        &self.data
+    }
+}
+
+impl Default for TagPostings {
+    fn default( ) -> Self {
+        Self::new( )
+    }
+}
+
+unsafe impl flatdata::NoOverlap for TagPostings {}
+
+impl TagPostings {
+    /// Index of the key of the tag in the `stringtable`
     #[inline]
-    pub fn start_nodes_index(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::NodeIndex>> {
-        flatdata::create_external_vector(&*self.storage, "nodes_index", schema::osm::resources::NODES_INDEX)
+    pub fn key_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
     }
 
-    /// Stores [`stringtable`] in the archive.
-    ///
-    /// [`stringtable`]: struct.Osm.html#method.stringtable
+    /// Index of the value of the tag in the `stringtable`, or invalid if the entities have the
+    /// key with any value
     #[inline]
-    pub fn set_stringtable(&self, data: &[u8]) -> ::std::io::Result<()> {
-        self.storage.write("stringtable", schema::osm::resources::STRINGTABLE, data)
+    pub fn value_idx(&self) -> Option<u64> {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 40, 40);
+        let x = unsafe { std::mem::transmute::<u64, u64>(value) };
+        Some(x).filter(|&x| x != super::osm::INVALID_IDX)
     }
 
-    /// Stores [`ids`] in the archive.
-    ///
-    /// [`ids`]: struct.Osm.html#method.ids
+    /// Index of the first entity in the corresponding entities vector; the entities end at the
+    /// first entity of the next postings
     #[inline]
-    pub fn ids(&self) -> Result<super::osm::IdsBuilder, flatdata::ResourceStorageError> {
-        let storage = self.storage.subdir("ids");
-        super::osm::IdsBuilder::new(storage)
+    pub fn first_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 80, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
     }
 
 }
 
-impl OsmBuilder {
-    pub fn new(
-        storage: flatdata::StorageHandle,
-    ) -> Result<Self, flatdata::ResourceStorageError> {
-        flatdata::create_archive("Osm", schema::osm::OSM, &storage)?;
-        Ok(Self { storage })
+impl std::fmt::Debug for TagPostings {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("TagPostings")
+            .field("key_idx", &self.key_idx())
+            .field("value_idx", &self.value_idx())
+            .field("first_idx", &self.first_idx())
+            .finish()
     }
 }
 
+impl std::cmp::PartialEq for TagPostings {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.key_idx() == other.key_idx() &&        self.value_idx() == other.value_idx() &&        self.first_idx() == other.first_idx()     }
+}
 
-#[doc(hidden)]
-pub mod schema {
-pub mod ids {
-
-pub const IDS: &str = r#"namespace osm {
-struct Id
+impl TagPostings {
+    /// Index of the key of the tag in the `stringtable`
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_key_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 0, 40)
+    }
+
+    /// Index of the value of the tag in the `stringtable`, or invalid if the entities have the
+    /// key with any value
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_value_idx(&mut self, value: Option<u64>) {
+let value = value.unwrap_or(super::osm::INVALID_IDX);        flatdata_write_bytes!(u64; value, self.data, 40, 40)
+    }
+
+    /// Index of the first entity in the corresponding entities vector; the entities end at the
+    /// first entity of the next postings
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_first_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 80, 40)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &TagPostings) {
+        self.set_key_idx(other.key_idx());
+        self.set_value_idx(other.value_idx());
+        self.set_first_idx(other.first_idx());
+    }
+}
+
+/// Index of an entity in the inverted tag index.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct EntityIndex {
+    data: [u8; 5],
+}
+
+impl EntityIndex {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+}
+
+impl flatdata::Struct for EntityIndex {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+
+    const SIZE_IN_BYTES: usize = 5;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
+}
+
+impl EntityIndex {
+    pub fn new( ) -> Self {
+        Self{data : [0; 5]}
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 5]) -> &Self {
+        // Safety: This is safe since EntityIndex is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 5]) -> &mut Self {
+        // Safety: This is safe since EntityIndex is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 5 {
+            assert_eq!(data.len(), 5);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *const [u8; 5];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 5 {
+            assert_eq!(data.len(), 5);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 5];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 5] {
+        &self.data
+    }
+}
+
+impl Default for EntityIndex {
+    fn default( ) -> Self {
+        Self::new( )
+    }
+}
+
+unsafe impl flatdata::NoOverlap for EntityIndex {}
+
+impl EntityIndex {
+    /// Index in the `nodes`, `ways`, or `relations` vector
+    #[inline]
+    pub fn value(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+}
+
+impl std::fmt::Debug for EntityIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("EntityIndex")
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for EntityIndex {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.value() == other.value()     }
+}
+
+impl EntityIndex {
+    /// Index in the `nodes`, `ways`, or `relations` vector
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_value(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 0, 40)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &EntityIndex) {
+        self.set_value(other.value());
+    }
+}
+
+
+/// An optional sub-archive storing an inverted index of tags.
+///
+/// For each entity type, the postings of all tags and of all tag keys are sorted by key and
+/// value, where the postings of a key come before the postings of its tags. The postings of
+/// each key and each tag refer to the ascending indexes of the entities having it. The
+/// postings vectors end with a sentinel referring to the end of the entities vector.
+#[derive(Clone)]
+pub struct InvertedIndex {
+    _storage: flatdata::StorageHandle,
+    node_tags : &'static [super::osm::TagPostings],
+    nodes : &'static [super::osm::EntityIndex],
+    way_tags : &'static [super::osm::TagPostings],
+    ways : &'static [super::osm::EntityIndex],
+    relation_tags : &'static [super::osm::TagPostings],
+    relations : &'static [super::osm::EntityIndex],
+}
+
+impl InvertedIndex {
+    fn signature_name(archive_name: &str) -> String {
+        format!("{}.archive", archive_name)
+    }
+
+    /// Postings of the tags of nodes
+    #[inline]
+    pub fn node_tags(&self) -> &[super::osm::TagPostings] {
+        self.node_tags
+    }
+
+    /// Indexes of nodes referred to by `node_tags`
+    #[inline]
+    pub fn nodes(&self) -> &[super::osm::EntityIndex] {
+        self.nodes
+    }
+
+    /// Postings of the tags of ways
+    #[inline]
+    pub fn way_tags(&self) -> &[super::osm::TagPostings] {
+        self.way_tags
+    }
+
+    /// Indexes of ways referred to by `way_tags`
+    #[inline]
+    pub fn ways(&self) -> &[super::osm::EntityIndex] {
+        self.ways
+    }
+
+    /// Postings of the tags of relations
+    #[inline]
+    pub fn relation_tags(&self) -> &[super::osm::TagPostings] {
+        self.relation_tags
+    }
+
+    /// Indexes of relations referred to by `relation_tags`
+    #[inline]
+    pub fn relations(&self) -> &[super::osm::EntityIndex] {
+        self.relations
+    }
+
+}
+
+impl ::std::fmt::Debug for InvertedIndex {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("InvertedIndex")
+            .field("node_tags", &self.node_tags())
+            .field("nodes", &self.nodes())
+            .field("way_tags", &self.way_tags())
+            .field("ways", &self.ways())
+            .field("relation_tags", &self.relation_tags())
+            .field("relations", &self.relations())
+            .finish()
+    }
+}
+
+impl InvertedIndex {
+    pub fn open(storage: flatdata::StorageHandle)
+        -> ::std::result::Result<Self, flatdata::ResourceStorageError>
+    {
+        #[allow(unused_imports)]
+        use flatdata::SliceExt;
+        #[allow(unused_variables)]
+        use flatdata::ResourceStorageError as Error;
+        // extend lifetime since Rust cannot know that we reference a cache here
+        #[allow(unused_variables)]
+        let extend = |x : Result<&[u8], Error>| -> Result<&'static [u8], Error> {x.map(|x| unsafe{std::mem::transmute(x)})};
+
+        storage.read(&Self::signature_name("InvertedIndex"), schema::inverted_index::INVERTED_INDEX)?;
+
+        let node_tags = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("node_tags", schema::inverted_index::resources::NODE_TAGS));
+            check("node_tags", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::TagPostings]>::from_bytes(x)))?
+        };
+        let nodes = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("nodes", schema::inverted_index::resources::NODES));
+            check("nodes", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::EntityIndex]>::from_bytes(x)))?
+        };
+        let way_tags = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("way_tags", schema::inverted_index::resources::WAY_TAGS));
+            check("way_tags", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::TagPostings]>::from_bytes(x)))?
+        };
+        let ways = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("ways", schema::inverted_index::resources::WAYS));
+            check("ways", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::EntityIndex]>::from_bytes(x)))?
+        };
+        let relation_tags = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("relation_tags", schema::inverted_index::resources::RELATION_TAGS));
+            check("relation_tags", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::TagPostings]>::from_bytes(x)))?
+        };
+        let relations = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("relations", schema::inverted_index::resources::RELATIONS));
+            check("relations", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::EntityIndex]>::from_bytes(x)))?
+        };
+
+        Ok(Self {
+            _storage: storage,
+            node_tags,
+            nodes,
+            way_tags,
+            ways,
+            relation_tags,
+            relations,
+        })
+    }
+}
+
+/// Builder for creating [`InvertedIndex`] archives.
+///
+///[`InvertedIndex`]: struct.InvertedIndex.html
+#[derive(Clone, Debug)]
+pub struct InvertedIndexBuilder {
+    storage: flatdata::StorageHandle
+}
+
+impl InvertedIndexBuilder {
+    #[inline]
+    /// Stores [`node_tags`] in the archive.
+    ///
+    /// [`node_tags`]: struct.InvertedIndex.html#method.node_tags
+    pub fn set_node_tags(&self, vector: &[super::osm::TagPostings]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("node_tags", schema::inverted_index::resources::NODE_TAGS, vector.as_bytes())
+    }
+
+    /// Opens [`node_tags`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`node_tags`]: struct.InvertedIndex.html#method.node_tags
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_node_tags(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::TagPostings>> {
+        flatdata::create_external_vector(&*self.storage, "node_tags", schema::inverted_index::resources::NODE_TAGS)
+    }
+
+    #[inline]
+    /// Stores [`nodes`] in the archive.
+    ///
+    /// [`nodes`]: struct.InvertedIndex.html#method.nodes
+    pub fn set_nodes(&self, vector: &[super::osm::EntityIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("nodes", schema::inverted_index::resources::NODES, vector.as_bytes())
+    }
+
+    /// Opens [`nodes`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`nodes`]: struct.InvertedIndex.html#method.nodes
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_nodes(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::EntityIndex>> {
+        flatdata::create_external_vector(&*self.storage, "nodes", schema::inverted_index::resources::NODES)
+    }
+
+    #[inline]
+    /// Stores [`way_tags`] in the archive.
+    ///
+    /// [`way_tags`]: struct.InvertedIndex.html#method.way_tags
+    pub fn set_way_tags(&self, vector: &[super::osm::TagPostings]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("way_tags", schema::inverted_index::resources::WAY_TAGS, vector.as_bytes())
+    }
+
+    /// Opens [`way_tags`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`way_tags`]: struct.InvertedIndex.html#method.way_tags
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_way_tags(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::TagPostings>> {
+        flatdata::create_external_vector(&*self.storage, "way_tags", schema::inverted_index::resources::WAY_TAGS)
+    }
+
+    #[inline]
+    /// Stores [`ways`] in the archive.
+    ///
+    /// [`ways`]: struct.InvertedIndex.html#method.ways
+    pub fn set_ways(&self, vector: &[super::osm::EntityIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("ways", schema::inverted_index::resources::WAYS, vector.as_bytes())
+    }
+
+    /// Opens [`ways`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`ways`]: struct.InvertedIndex.html#method.ways
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_ways(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::EntityIndex>> {
+        flatdata::create_external_vector(&*self.storage, "ways", schema::inverted_index::resources::WAYS)
+    }
+
+    #[inline]
+    /// Stores [`relation_tags`] in the archive.
+    ///
+    /// [`relation_tags`]: struct.InvertedIndex.html#method.relation_tags
+    pub fn set_relation_tags(&self, vector: &[super::osm::TagPostings]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("relation_tags", schema::inverted_index::resources::RELATION_TAGS, vector.as_bytes())
+    }
+
+    /// Opens [`relation_tags`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`relation_tags`]: struct.InvertedIndex.html#method.relation_tags
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_relation_tags(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::TagPostings>> {
+        flatdata::create_external_vector(&*self.storage, "relation_tags", schema::inverted_index::resources::RELATION_TAGS)
+    }
+
+    #[inline]
+    /// Stores [`relations`] in the archive.
+    ///
+    /// [`relations`]: struct.InvertedIndex.html#method.relations
+    pub fn set_relations(&self, vector: &[super::osm::EntityIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("relations", schema::inverted_index::resources::RELATIONS, vector.as_bytes())
+    }
+
+    /// Opens [`relations`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`relations`]: struct.InvertedIndex.html#method.relations
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_relations(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::EntityIndex>> {
+        flatdata::create_external_vector(&*self.storage, "relations", schema::inverted_index::resources::RELATIONS)
+    }
+
+}
+
+impl InvertedIndexBuilder {
+    pub fn new(
+        storage: flatdata::StorageHandle,
+    ) -> Result<Self, flatdata::ResourceStorageError> {
+        flatdata::create_archive("InvertedIndex", schema::inverted_index::INVERTED_INDEX, &storage)?;
+        Ok(Self { storage })
+    }
+}
+
+
+
+/// Bounding box of a way or a relation.
+///
+/// Coordinates are stored in the same scale as node coordinates, cf. `Header.coord_scale`.
+/// Entities without any node in the archive have an empty bounding box with
+/// `left > right`.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct BoundingBox {
+    data: [u8; 16],
+}
+
+impl BoundingBox {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 16]}
+    }
+}
+
+impl flatdata::Struct for BoundingBox {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 16]}
+    }
+
+    const SIZE_IN_BYTES: usize = 16;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
+}
+
+impl BoundingBox {
+    pub fn new( ) -> Self {
+        Self{data : [0; 16]}
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 16]) -> &Self {
+        // Safety: This is safe since BoundingBox is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 16]) -> &mut Self {
+        // Safety: This is safe since BoundingBox is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 16 {
+            assert_eq!(data.len(), 16);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *const [u8; 16];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 16 {
+            assert_eq!(data.len(), 16);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 16];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.data
+    }
+}
+
+impl Default for BoundingBox {
+    fn default( ) -> Self {
+        Self::new( )
+    }
+}
+
+unsafe impl flatdata::NoOverlap for BoundingBox {}
+
+impl BoundingBox {
+    /// Minimal longitude
+    #[inline]
+    pub fn left(&self) -> i32 {
+        let value = flatdata_read_bytes!(i32, self.data.as_ptr(), 0, 32);
+        unsafe { std::mem::transmute::<i32, i32>(value) }
+    }
+
+    /// Minimal latitude
+    #[inline]
+    pub fn bottom(&self) -> i32 {
+        let value = flatdata_read_bytes!(i32, self.data.as_ptr(), 32, 32);
+        unsafe { std::mem::transmute::<i32, i32>(value) }
+    }
+
+    /// Maximal longitude
+    #[inline]
+    pub fn right(&self) -> i32 {
+        let value = flatdata_read_bytes!(i32, self.data.as_ptr(), 64, 32);
+        unsafe { std::mem::transmute::<i32, i32>(value) }
+    }
+
+    /// Maximal latitude
+    #[inline]
+    pub fn top(&self) -> i32 {
+        let value = flatdata_read_bytes!(i32, self.data.as_ptr(), 96, 32);
+        unsafe { std::mem::transmute::<i32, i32>(value) }
+    }
+
+}
+
+impl std::fmt::Debug for BoundingBox {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("BoundingBox")
+            .field("left", &self.left())
+            .field("bottom", &self.bottom())
+            .field("right", &self.right())
+            .field("top", &self.top())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for BoundingBox {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.left() == other.left() &&        self.bottom() == other.bottom() &&        self.right() == other.right() &&        self.top() == other.top()     }
+}
+
+impl BoundingBox {
+    /// Minimal longitude
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_left(&mut self, value: i32) {
+        flatdata_write_bytes!(i32; value, self.data, 0, 32)
+    }
+
+    /// Minimal latitude
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_bottom(&mut self, value: i32) {
+        flatdata_write_bytes!(i32; value, self.data, 32, 32)
+    }
+
+    /// Maximal longitude
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_right(&mut self, value: i32) {
+        flatdata_write_bytes!(i32; value, self.data, 64, 32)
+    }
+
+    /// Maximal latitude
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_top(&mut self, value: i32) {
+        flatdata_write_bytes!(i32; value, self.data, 96, 32)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &BoundingBox) {
+        self.set_left(other.left());
+        self.set_bottom(other.bottom());
+        self.set_right(other.right());
+        self.set_top(other.top());
+    }
+}
+/// Metadata of a changeset.
+///
+/// See <https://wiki.openstreetmap.org/wiki/Changeset>.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct Changeset {
+    data: [u8; 26],
+}
+
+impl Changeset {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 26]}
+    }
+}
+
+impl flatdata::Struct for Changeset {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 26]}
+    }
+
+    const SIZE_IN_BYTES: usize = 26;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
+}
+
+impl Changeset {
+    pub fn new( ) -> Self {
+        Self{data : [0; 26]}
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 26]) -> &Self {
+        // Safety: This is safe since Changeset is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 26]) -> &mut Self {
+        // Safety: This is safe since Changeset is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 26 {
+            assert_eq!(data.len(), 26);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *const [u8; 26];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 26 {
+            assert_eq!(data.len(), 26);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 26];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 26] {
+        &self.data
+    }
+}
+
+impl Default for Changeset {
+    fn default( ) -> Self {
+        Self::new( )
+    }
+}
+
+unsafe impl flatdata::NoOverlap for Changeset {}
+
+impl Changeset {
+    /// OSM id of the changeset
+    #[inline]
+    pub fn id(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+    /// Time the changeset was created, expressed in seconds since the epoch
+    #[inline]
+    pub fn created_at(&self) -> i64 {
+        let value = flatdata_read_bytes!(i64, self.data.as_ptr(), 40, 64);
+        unsafe { std::mem::transmute::<i64, i64>(value) }
+    }
+
+    /// Time the changeset was closed, expressed in seconds since the epoch, or 0 if it is open
+    #[inline]
+    pub fn closed_at(&self) -> i64 {
+        let value = flatdata_read_bytes!(i64, self.data.as_ptr(), 104, 64);
+        unsafe { std::mem::transmute::<i64, i64>(value) }
+    }
+
+    /// Name of the user who created the changeset (index in `stringtable` of the parent archive)
+    #[inline]
+    pub fn user_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 168, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+}
+
+impl std::fmt::Debug for Changeset {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Changeset")
+            .field("id", &self.id())
+            .field("created_at", &self.created_at())
+            .field("closed_at", &self.closed_at())
+            .field("user_idx", &self.user_idx())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for Changeset {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.id() == other.id() &&        self.created_at() == other.created_at() &&        self.closed_at() == other.closed_at() &&        self.user_idx() == other.user_idx()     }
+}
+
+impl Changeset {
+    /// OSM id of the changeset
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_id(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 0, 40)
+    }
+
+    /// Time the changeset was created, expressed in seconds since the epoch
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_created_at(&mut self, value: i64) {
+        flatdata_write_bytes!(i64; value, self.data, 40, 64)
+    }
+
+    /// Time the changeset was closed, expressed in seconds since the epoch, or 0 if it is open
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_closed_at(&mut self, value: i64) {
+        flatdata_write_bytes!(i64; value, self.data, 104, 64)
+    }
+
+    /// Name of the user who created the changeset (index in `stringtable` of the parent archive)
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_user_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 168, 40)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &Changeset) {
+        self.set_id(other.id());
+        self.set_created_at(other.created_at());
+        self.set_closed_at(other.closed_at());
+        self.set_user_idx(other.user_idx());
+    }
+}
+/// Range of the tags of a changeset, cf. `Changesets`.
+#[repr(transparent)]
+pub struct ChangesetTags {
+    data: [u8; 5],
+}
+
+impl ChangesetTags {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+}
+
+impl flatdata::Struct for ChangesetTags {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+
+    const SIZE_IN_BYTES: usize = 5;
+    const IS_OVERLAPPING_WITH_NEXT : bool = true;
+}
+
+impl flatdata::Overlap for ChangesetTags {}
+
+impl ChangesetTags {
+    /// First element of the range [`tags`].
+    ///
+    /// [`tags`]: #method.tags
+    #[inline]
+    pub fn tag_first_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+    /// Range of the tags of the changeset.
+///
+/// The values of the range are indexes in the `tags_index` vector of the `Changesets`
+/// sub-archive.
+    #[inline]
+    pub fn tags(&self) -> std::ops::Range<u64> {
+        let start = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        let end = flatdata_read_bytes!(u64, self.data.as_ptr(), 0 + 5 * 8, 40);
+        start..end
+    }
+
+}
+
+impl std::fmt::Debug for ChangesetTags {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ChangesetTags")
+            .field("tag_first_idx", &self.tag_first_idx())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for ChangesetTags {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.tag_first_idx() == other.tag_first_idx()     }
+}
+
+impl ChangesetTags {
+    /// First element of the range [`tags`].
+    ///
+    /// [`tags`]: struct.ChangesetTagsRef.html#method.tags
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_tag_first_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 0, 40)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &ChangesetTags) {
+        self.set_tag_first_idx(other.tag_first_idx());
+    }
+}
+/// An optional sub-archive storing the changesets of the input, cf.
+/// `osmflatc --changesets`.
+///
+/// Changesets are only stored as metadata; they are not referenced by the
+/// entities of the parent archive.
+#[derive(Clone)]
+pub struct Changesets {
+    _storage: flatdata::StorageHandle,
+    changesets : &'static [super::osm::Changeset],
+    bboxes : &'static [super::osm::BoundingBox],
+    tag_ranges : Option<&'static [super::osm::ChangesetTags]>,
+    tags_index : Option<&'static [super::osm::TagIndex]>,
+}
+
+impl Changesets {
+    fn signature_name(archive_name: &str) -> String {
+        format!("{}.archive", archive_name)
+    }
+
+    /// Changesets in the order of the input
+    #[inline]
+    pub fn changesets(&self) -> &[super::osm::Changeset] {
+        self.changesets
+    }
+
+    /// Bounding boxes of all changesets; changesets[i] has its bounding box stored in
+/// bboxes[i]. Changesets without a bounding box, e.g. without changes of nodes,
+/// have an empty bounding box.
+    #[inline]
+    pub fn bboxes(&self) -> &[super::osm::BoundingBox] {
+        self.bboxes
+    }
+
+    /// Ranges of the tags of all changesets, with a sentinel; changesets[i] has its tags in
+/// tags_index[tag_ranges[i].tags()]. Archives compiled by older versions of osmflatc do
+/// not store the tags of changesets.
+    #[inline]
+    pub fn tag_ranges(&self) -> Option<&[super::osm::ChangesetTags]> {
+        self.tag_ranges
+    }
+
+    /// Indexes of the tags of all changesets in the `tags` vector of the parent archive
+    #[inline]
+    pub fn tags_index(&self) -> Option<&[super::osm::TagIndex]> {
+        self.tags_index
+    }
+
+}
+
+impl ::std::fmt::Debug for Changesets {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("Changesets")
+            .field("changesets", &self.changesets())
+            .field("bboxes", &self.bboxes())
+            .field("tag_ranges", &self.tag_ranges())
+            .field("tags_index", &self.tags_index())
+            .finish()
+    }
+}
+
+impl Changesets {
+    pub fn open(storage: flatdata::StorageHandle)
+        -> ::std::result::Result<Self, flatdata::ResourceStorageError>
+    {
+        #[allow(unused_imports)]
+        use flatdata::SliceExt;
+        #[allow(unused_variables)]
+        use flatdata::ResourceStorageError as Error;
+        // extend lifetime since Rust cannot know that we reference a cache here
+        #[allow(unused_variables)]
+        let extend = |x : Result<&[u8], Error>| -> Result<&'static [u8], Error> {x.map(|x| unsafe{std::mem::transmute(x)})};
+
+        storage.read(&Self::signature_name("Changesets"), schema::changesets::CHANGESETS)?;
+
+        let changesets = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("changesets", schema::changesets::resources::CHANGESETS));
+            check("changesets", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Changeset]>::from_bytes(x)))?
+        };
+        let bboxes = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("bboxes", schema::changesets::resources::BBOXES));
+            check("bboxes", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::BoundingBox]>::from_bytes(x)))?
+        };
+        let tag_ranges = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("tag_ranges", schema::changesets::resources::TAG_RANGES));
+            check("tag_ranges", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::ChangesetTags]>::from_bytes(x)))?
+        };
+        let tags_index = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("tags_index", schema::changesets::resources::TAGS_INDEX));
+            check("tags_index", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::TagIndex]>::from_bytes(x)))?
+        };
+
+        Ok(Self {
+            _storage: storage,
+            changesets,
+            bboxes,
+            tag_ranges,
+            tags_index,
+        })
+    }
+}
+
+/// Builder for creating [`Changesets`] archives.
+///
+///[`Changesets`]: struct.Changesets.html
+#[derive(Clone, Debug)]
+pub struct ChangesetsBuilder {
+    storage: flatdata::StorageHandle
+}
+
+impl ChangesetsBuilder {
+    #[inline]
+    /// Stores [`changesets`] in the archive.
+    ///
+    /// [`changesets`]: struct.Changesets.html#method.changesets
+    pub fn set_changesets(&self, vector: &[super::osm::Changeset]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("changesets", schema::changesets::resources::CHANGESETS, vector.as_bytes())
+    }
+
+    /// Opens [`changesets`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`changesets`]: struct.Changesets.html#method.changesets
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_changesets(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Changeset>> {
+        flatdata::create_external_vector(&*self.storage, "changesets", schema::changesets::resources::CHANGESETS)
+    }
+
+    #[inline]
+    /// Stores [`bboxes`] in the archive.
+    ///
+    /// [`bboxes`]: struct.Changesets.html#method.bboxes
+    pub fn set_bboxes(&self, vector: &[super::osm::BoundingBox]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("bboxes", schema::changesets::resources::BBOXES, vector.as_bytes())
+    }
+
+    /// Opens [`bboxes`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`bboxes`]: struct.Changesets.html#method.bboxes
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_bboxes(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::BoundingBox>> {
+        flatdata::create_external_vector(&*self.storage, "bboxes", schema::changesets::resources::BBOXES)
+    }
+
+    #[inline]
+    /// Stores [`tag_ranges`] in the archive.
+    ///
+    /// [`tag_ranges`]: struct.Changesets.html#method.tag_ranges
+    pub fn set_tag_ranges(&self, vector: &[super::osm::ChangesetTags]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("tag_ranges", schema::changesets::resources::TAG_RANGES, vector.as_bytes())
+    }
+
+    /// Opens [`tag_ranges`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`tag_ranges`]: struct.Changesets.html#method.tag_ranges
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_tag_ranges(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::ChangesetTags>> {
+        flatdata::create_external_vector(&*self.storage, "tag_ranges", schema::changesets::resources::TAG_RANGES)
+    }
+
+    #[inline]
+    /// Stores [`tags_index`] in the archive.
+    ///
+    /// [`tags_index`]: struct.Changesets.html#method.tags_index
+    pub fn set_tags_index(&self, vector: &[super::osm::TagIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("tags_index", schema::changesets::resources::TAGS_INDEX, vector.as_bytes())
+    }
+
+    /// Opens [`tags_index`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`tags_index`]: struct.Changesets.html#method.tags_index
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_tags_index(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::TagIndex>> {
+        flatdata::create_external_vector(&*self.storage, "tags_index", schema::changesets::resources::TAGS_INDEX)
+    }
+
+}
+
+impl ChangesetsBuilder {
+    pub fn new(
+        storage: flatdata::StorageHandle,
+    ) -> Result<Self, flatdata::ResourceStorageError> {
+        flatdata::create_archive("Changesets", schema::changesets::CHANGESETS, &storage)?;
+        Ok(Self { storage })
+    }
+}
+
+
+
+/// An optional sub-archive mapping OSM ids to the indexes of the entities, cf.
+/// `Osm::node_index_by_id`.
+///
+/// Each vector contains the indexes of all entities of a type sorted by their ids in the
+/// `ids` sub-archive. Versions of an entity in a full-history archive are ordered by version.
+#[derive(Clone)]
+pub struct IdIndex {
+    _storage: flatdata::StorageHandle,
+    nodes : &'static [super::osm::EntityIndex],
+    ways : &'static [super::osm::EntityIndex],
+    relations : &'static [super::osm::EntityIndex],
+}
+
+impl IdIndex {
+    fn signature_name(archive_name: &str) -> String {
+        format!("{}.archive", archive_name)
+    }
+
+    /// Indexes of the nodes in the parent archive sorted by id
+    #[inline]
+    pub fn nodes(&self) -> &[super::osm::EntityIndex] {
+        self.nodes
+    }
+
+    /// Indexes of the ways in the parent archive sorted by id
+    #[inline]
+    pub fn ways(&self) -> &[super::osm::EntityIndex] {
+        self.ways
+    }
+
+    /// Indexes of the relations in the parent archive sorted by id
+    #[inline]
+    pub fn relations(&self) -> &[super::osm::EntityIndex] {
+        self.relations
+    }
+
+}
+
+impl ::std::fmt::Debug for IdIndex {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("IdIndex")
+            .field("nodes", &self.nodes())
+            .field("ways", &self.ways())
+            .field("relations", &self.relations())
+            .finish()
+    }
+}
+
+impl IdIndex {
+    pub fn open(storage: flatdata::StorageHandle)
+        -> ::std::result::Result<Self, flatdata::ResourceStorageError>
+    {
+        #[allow(unused_imports)]
+        use flatdata::SliceExt;
+        #[allow(unused_variables)]
+        use flatdata::ResourceStorageError as Error;
+        // extend lifetime since Rust cannot know that we reference a cache here
+        #[allow(unused_variables)]
+        let extend = |x : Result<&[u8], Error>| -> Result<&'static [u8], Error> {x.map(|x| unsafe{std::mem::transmute(x)})};
+
+        storage.read(&Self::signature_name("IdIndex"), schema::id_index::ID_INDEX)?;
+
+        let nodes = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("nodes", schema::id_index::resources::NODES));
+            check("nodes", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::EntityIndex]>::from_bytes(x)))?
+        };
+        let ways = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("ways", schema::id_index::resources::WAYS));
+            check("ways", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::EntityIndex]>::from_bytes(x)))?
+        };
+        let relations = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("relations", schema::id_index::resources::RELATIONS));
+            check("relations", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::EntityIndex]>::from_bytes(x)))?
+        };
+
+        Ok(Self {
+            _storage: storage,
+            nodes,
+            ways,
+            relations,
+        })
+    }
+}
+
+/// Builder for creating [`IdIndex`] archives.
+///
+///[`IdIndex`]: struct.IdIndex.html
+#[derive(Clone, Debug)]
+pub struct IdIndexBuilder {
+    storage: flatdata::StorageHandle
+}
+
+impl IdIndexBuilder {
+    #[inline]
+    /// Stores [`nodes`] in the archive.
+    ///
+    /// [`nodes`]: struct.IdIndex.html#method.nodes
+    pub fn set_nodes(&self, vector: &[super::osm::EntityIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("nodes", schema::id_index::resources::NODES, vector.as_bytes())
+    }
+
+    /// Opens [`nodes`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`nodes`]: struct.IdIndex.html#method.nodes
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_nodes(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::EntityIndex>> {
+        flatdata::create_external_vector(&*self.storage, "nodes", schema::id_index::resources::NODES)
+    }
+
+    #[inline]
+    /// Stores [`ways`] in the archive.
+    ///
+    /// [`ways`]: struct.IdIndex.html#method.ways
+    pub fn set_ways(&self, vector: &[super::osm::EntityIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("ways", schema::id_index::resources::WAYS, vector.as_bytes())
+    }
+
+    /// Opens [`ways`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`ways`]: struct.IdIndex.html#method.ways
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_ways(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::EntityIndex>> {
+        flatdata::create_external_vector(&*self.storage, "ways", schema::id_index::resources::WAYS)
+    }
+
+    #[inline]
+    /// Stores [`relations`] in the archive.
+    ///
+    /// [`relations`]: struct.IdIndex.html#method.relations
+    pub fn set_relations(&self, vector: &[super::osm::EntityIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("relations", schema::id_index::resources::RELATIONS, vector.as_bytes())
+    }
+
+    /// Opens [`relations`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`relations`]: struct.IdIndex.html#method.relations
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_relations(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::EntityIndex>> {
+        flatdata::create_external_vector(&*self.storage, "relations", schema::id_index::resources::RELATIONS)
+    }
+
+}
+
+impl IdIndexBuilder {
+    pub fn new(
+        storage: flatdata::StorageHandle,
+    ) -> Result<Self, flatdata::ResourceStorageError> {
+        flatdata::create_archive("IdIndex", schema::id_index::ID_INDEX, &storage)?;
+        Ok(Self { storage })
+    }
+}
+
+
+/// Start of the entries of an entity in a `ReverseIndex`.
+#[repr(transparent)]
+pub struct ReverseIndexRange {
+    data: [u8; 5],
+}
+
+impl ReverseIndexRange {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+}
+
+impl flatdata::Struct for ReverseIndexRange {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+
+    const SIZE_IN_BYTES: usize = 5;
+    const IS_OVERLAPPING_WITH_NEXT : bool = true;
+}
+
+impl flatdata::Overlap for ReverseIndexRange {}
+
+impl ReverseIndexRange {
+    /// First element of the range [`entries`].
+    ///
+    /// [`entries`]: #method.entries
+    #[inline]
+    pub fn first_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+    /// Range of the entries of the entity.
+///
+/// The values of the range are indexes in the `entries` vector.
+    #[inline]
+    pub fn entries(&self) -> std::ops::Range<u64> {
+        let start = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        let end = flatdata_read_bytes!(u64, self.data.as_ptr(), 0 + 5 * 8, 40);
+        start..end
+    }
+
+}
+
+impl std::fmt::Debug for ReverseIndexRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ReverseIndexRange")
+            .field("first_idx", &self.first_idx())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for ReverseIndexRange {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.first_idx() == other.first_idx()     }
+}
+
+impl ReverseIndexRange {
+    /// First element of the range [`entries`].
+    ///
+    /// [`entries`]: struct.ReverseIndexRangeRef.html#method.entries
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_first_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 0, 40)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &ReverseIndexRange) {
+        self.set_first_idx(other.first_idx());
+    }
+}
+
+/// An optional sub-archive mapping each entity of a type to the entities referencing it,
+/// cf. `Osm::ways_containing_node` and `Osm::relations_containing`.
+///
+/// The entity at index `i` is referenced by the entities in
+/// `entries[ranges[i].entries()]`, which are sorted in ascending order.
+#[derive(Clone)]
+pub struct ReverseIndex {
+    _storage: flatdata::StorageHandle,
+    ranges : &'static [super::osm::ReverseIndexRange],
+    entries : &'static [super::osm::EntityIndex],
+}
+
+impl ReverseIndex {
+    fn signature_name(archive_name: &str) -> String {
+        format!("{}.archive", archive_name)
+    }
+
+    /// Range of referencing entities of each entity, with a sentinel
+    #[inline]
+    pub fn ranges(&self) -> &[super::osm::ReverseIndexRange] {
+        self.ranges
+    }
+
+    /// Indexes of the referencing entities
+    #[inline]
+    pub fn entries(&self) -> &[super::osm::EntityIndex] {
+        self.entries
+    }
+
+}
+
+impl ::std::fmt::Debug for ReverseIndex {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("ReverseIndex")
+            .field("ranges", &self.ranges())
+            .field("entries", &self.entries())
+            .finish()
+    }
+}
+
+impl ReverseIndex {
+    pub fn open(storage: flatdata::StorageHandle)
+        -> ::std::result::Result<Self, flatdata::ResourceStorageError>
+    {
+        #[allow(unused_imports)]
+        use flatdata::SliceExt;
+        #[allow(unused_variables)]
+        use flatdata::ResourceStorageError as Error;
+        // extend lifetime since Rust cannot know that we reference a cache here
+        #[allow(unused_variables)]
+        let extend = |x : Result<&[u8], Error>| -> Result<&'static [u8], Error> {x.map(|x| unsafe{std::mem::transmute(x)})};
+
+        storage.read(&Self::signature_name("ReverseIndex"), schema::reverse_index::REVERSE_INDEX)?;
+
+        let ranges = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("ranges", schema::reverse_index::resources::RANGES));
+            check("ranges", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::ReverseIndexRange]>::from_bytes(x)))?
+        };
+        let entries = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("entries", schema::reverse_index::resources::ENTRIES));
+            check("entries", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::EntityIndex]>::from_bytes(x)))?
+        };
+
+        Ok(Self {
+            _storage: storage,
+            ranges,
+            entries,
+        })
+    }
+}
+
+/// Builder for creating [`ReverseIndex`] archives.
+///
+///[`ReverseIndex`]: struct.ReverseIndex.html
+#[derive(Clone, Debug)]
+pub struct ReverseIndexBuilder {
+    storage: flatdata::StorageHandle
+}
+
+impl ReverseIndexBuilder {
+    #[inline]
+    /// Stores [`ranges`] in the archive.
+    ///
+    /// [`ranges`]: struct.ReverseIndex.html#method.ranges
+    pub fn set_ranges(&self, vector: &[super::osm::ReverseIndexRange]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("ranges", schema::reverse_index::resources::RANGES, vector.as_bytes())
+    }
+
+    /// Opens [`ranges`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`ranges`]: struct.ReverseIndex.html#method.ranges
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_ranges(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::ReverseIndexRange>> {
+        flatdata::create_external_vector(&*self.storage, "ranges", schema::reverse_index::resources::RANGES)
+    }
+
+    #[inline]
+    /// Stores [`entries`] in the archive.
+    ///
+    /// [`entries`]: struct.ReverseIndex.html#method.entries
+    pub fn set_entries(&self, vector: &[super::osm::EntityIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("entries", schema::reverse_index::resources::ENTRIES, vector.as_bytes())
+    }
+
+    /// Opens [`entries`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`entries`]: struct.ReverseIndex.html#method.entries
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_entries(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::EntityIndex>> {
+        flatdata::create_external_vector(&*self.storage, "entries", schema::reverse_index::resources::ENTRIES)
+    }
+
+}
+
+impl ReverseIndexBuilder {
+    pub fn new(
+        storage: flatdata::StorageHandle,
+    ) -> Result<Self, flatdata::ResourceStorageError> {
+        flatdata::create_archive("ReverseIndex", schema::reverse_index::REVERSE_INDEX, &storage)?;
+        Ok(Self { storage })
+    }
+}
+
+
+/// An area of an `Areas` sub-archive, assembled from a closed way or a multipolygon
+/// relation.
+#[repr(transparent)]
+pub struct Area {
+    data: [u8; 15],
+}
+
+impl Area {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 15]}
+    }
+}
+
+impl flatdata::Struct for Area {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 15]}
+    }
+
+    const SIZE_IN_BYTES: usize = 15;
+    const IS_OVERLAPPING_WITH_NEXT : bool = true;
+}
+
+impl flatdata::Overlap for Area {}
+
+impl Area {
+    /// First element of the range [`polygons`].
+    ///
+    /// [`polygons`]: #method.polygons
+    #[inline]
+    pub fn first_polygon_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+    /// Range of the polygons of the area.
+///
+/// The values of the range are indexes in the `polygons` vector.
+    #[inline]
+    pub fn polygons(&self) -> std::ops::Range<u64> {
+        let start = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        let end = flatdata_read_bytes!(u64, self.data.as_ptr(), 0 + 15 * 8, 40);
+        start..end
+    }
+
+    /// Index of the closed way in the `ways` vector of the parent archive
+    #[inline]
+    pub fn way_idx(&self) -> Option<u64> {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 40, 40);
+        let x = unsafe { std::mem::transmute::<u64, u64>(value) };
+        Some(x).filter(|&x| x != super::osm::INVALID_IDX)
+    }
+
+    /// Index of the relation in the `relations` vector of the parent archive
+    #[inline]
+    pub fn relation_idx(&self) -> Option<u64> {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 80, 40);
+        let x = unsafe { std::mem::transmute::<u64, u64>(value) };
+        Some(x).filter(|&x| x != super::osm::INVALID_IDX)
+    }
+
+}
+
+impl std::fmt::Debug for Area {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Area")
+            .field("first_polygon_idx", &self.first_polygon_idx())
+            .field("way_idx", &self.way_idx())
+            .field("relation_idx", &self.relation_idx())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for Area {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.first_polygon_idx() == other.first_polygon_idx() &&        self.way_idx() == other.way_idx() &&        self.relation_idx() == other.relation_idx()     }
+}
+
+impl Area {
+    /// First element of the range [`polygons`].
+    ///
+    /// [`polygons`]: struct.AreaRef.html#method.polygons
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_first_polygon_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 0, 40)
+    }
+
+    /// Index of the closed way in the `ways` vector of the parent archive
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_way_idx(&mut self, value: Option<u64>) {
+let value = value.unwrap_or(super::osm::INVALID_IDX);        flatdata_write_bytes!(u64; value, self.data, 40, 40)
+    }
+
+    /// Index of the relation in the `relations` vector of the parent archive
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_relation_idx(&mut self, value: Option<u64>) {
+let value = value.unwrap_or(super::osm::INVALID_IDX);        flatdata_write_bytes!(u64; value, self.data, 80, 40)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &Area) {
+        self.set_first_polygon_idx(other.first_polygon_idx());
+        self.set_way_idx(other.way_idx());
+        self.set_relation_idx(other.relation_idx());
+    }
+}
+
+/// A polygon of an `Areas` sub-archive.
+#[repr(transparent)]
+pub struct AreaPolygon {
+    data: [u8; 5],
+}
+
+impl AreaPolygon {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+}
+
+impl flatdata::Struct for AreaPolygon {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+
+    const SIZE_IN_BYTES: usize = 5;
+    const IS_OVERLAPPING_WITH_NEXT : bool = true;
+}
+
+impl flatdata::Overlap for AreaPolygon {}
+
+impl AreaPolygon {
+    /// First element of the range [`rings`].
+    ///
+    /// [`rings`]: #method.rings
+    #[inline]
+    pub fn first_ring_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+    /// Range of the rings of the polygon: the outer ring followed by its holes.
+///
+/// The values of the range are indexes in the `rings` vector.
+    #[inline]
+    pub fn rings(&self) -> std::ops::Range<u64> {
+        let start = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        let end = flatdata_read_bytes!(u64, self.data.as_ptr(), 0 + 5 * 8, 40);
+        start..end
+    }
+
+}
+
+impl std::fmt::Debug for AreaPolygon {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("AreaPolygon")
+            .field("first_ring_idx", &self.first_ring_idx())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for AreaPolygon {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.first_ring_idx() == other.first_ring_idx()     }
+}
+
+impl AreaPolygon {
+    /// First element of the range [`rings`].
+    ///
+    /// [`rings`]: struct.AreaPolygonRef.html#method.rings
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_first_ring_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 0, 40)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &AreaPolygon) {
+        self.set_first_ring_idx(other.first_ring_idx());
+    }
+}
+
+/// A closed ring of an `Areas` sub-archive.
+#[repr(transparent)]
+pub struct AreaRing {
+    data: [u8; 5],
+}
+
+impl AreaRing {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+}
+
+impl flatdata::Struct for AreaRing {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+
+    const SIZE_IN_BYTES: usize = 5;
+    const IS_OVERLAPPING_WITH_NEXT : bool = true;
+}
+
+impl flatdata::Overlap for AreaRing {}
+
+impl AreaRing {
+    /// First element of the range [`nodes`].
+    ///
+    /// [`nodes`]: #method.nodes
+    #[inline]
+    pub fn first_node_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+    /// Range of the nodes of the ring; its first and last nodes are equal.
+///
+/// The values of the range are indexes in the `nodes` vector.
+    #[inline]
+    pub fn nodes(&self) -> std::ops::Range<u64> {
+        let start = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        let end = flatdata_read_bytes!(u64, self.data.as_ptr(), 0 + 5 * 8, 40);
+        start..end
+    }
+
+}
+
+impl std::fmt::Debug for AreaRing {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("AreaRing")
+            .field("first_node_idx", &self.first_node_idx())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for AreaRing {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.first_node_idx() == other.first_node_idx()     }
+}
+
+impl AreaRing {
+    /// First element of the range [`nodes`].
+    ///
+    /// [`nodes`]: struct.AreaRingRef.html#method.nodes
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_first_node_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 0, 40)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &AreaRing) {
+        self.set_first_node_idx(other.first_node_idx());
+    }
+}
+
+/// An optional sub-archive of the areas assembled from closed ways and valid multipolygon
+/// and boundary relations, cf. `Osm::areas`.
+///
+/// The area at index `i` has the polygons `polygons[areas[i].polygons()]`. Outer rings are
+/// counterclockwise and holes clockwise.
+#[derive(Clone)]
+pub struct Areas {
+    _storage: flatdata::StorageHandle,
+    areas : &'static [super::osm::Area],
+    polygons : &'static [super::osm::AreaPolygon],
+    rings : &'static [super::osm::AreaRing],
+    nodes : &'static [super::osm::EntityIndex],
+}
+
+impl Areas {
+    fn signature_name(archive_name: &str) -> String {
+        format!("{}.archive", archive_name)
+    }
+
+    /// Areas of the ways in ascending order, followed by the areas of the relations, with a
+/// sentinel
+    #[inline]
+    pub fn areas(&self) -> &[super::osm::Area] {
+        self.areas
+    }
+
+    /// Polygons of all areas, with a sentinel
+    #[inline]
+    pub fn polygons(&self) -> &[super::osm::AreaPolygon] {
+        self.polygons
+    }
+
+    /// Rings of all polygons, with a sentinel
+    #[inline]
+    pub fn rings(&self) -> &[super::osm::AreaRing] {
+        self.rings
+    }
+
+    /// Indexes of the nodes of all rings in the `nodes` vector of the parent archive
+    #[inline]
+    pub fn nodes(&self) -> &[super::osm::EntityIndex] {
+        self.nodes
+    }
+
+}
+
+impl ::std::fmt::Debug for Areas {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("Areas")
+            .field("areas", &self.areas())
+            .field("polygons", &self.polygons())
+            .field("rings", &self.rings())
+            .field("nodes", &self.nodes())
+            .finish()
+    }
+}
+
+impl Areas {
+    pub fn open(storage: flatdata::StorageHandle)
+        -> ::std::result::Result<Self, flatdata::ResourceStorageError>
+    {
+        #[allow(unused_imports)]
+        use flatdata::SliceExt;
+        #[allow(unused_variables)]
+        use flatdata::ResourceStorageError as Error;
+        // extend lifetime since Rust cannot know that we reference a cache here
+        #[allow(unused_variables)]
+        let extend = |x : Result<&[u8], Error>| -> Result<&'static [u8], Error> {x.map(|x| unsafe{std::mem::transmute(x)})};
+
+        storage.read(&Self::signature_name("Areas"), schema::areas::AREAS)?;
+
+        let areas = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("areas", schema::areas::resources::AREAS));
+            check("areas", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Area]>::from_bytes(x)))?
+        };
+        let polygons = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("polygons", schema::areas::resources::POLYGONS));
+            check("polygons", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::AreaPolygon]>::from_bytes(x)))?
+        };
+        let rings = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("rings", schema::areas::resources::RINGS));
+            check("rings", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::AreaRing]>::from_bytes(x)))?
+        };
+        let nodes = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("nodes", schema::areas::resources::NODES));
+            check("nodes", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::EntityIndex]>::from_bytes(x)))?
+        };
+
+        Ok(Self {
+            _storage: storage,
+            areas,
+            polygons,
+            rings,
+            nodes,
+        })
+    }
+}
+
+/// Builder for creating [`Areas`] archives.
+///
+///[`Areas`]: struct.Areas.html
+#[derive(Clone, Debug)]
+pub struct AreasBuilder {
+    storage: flatdata::StorageHandle
+}
+
+impl AreasBuilder {
+    #[inline]
+    /// Stores [`areas`] in the archive.
+    ///
+    /// [`areas`]: struct.Areas.html#method.areas
+    pub fn set_areas(&self, vector: &[super::osm::Area]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("areas", schema::areas::resources::AREAS, vector.as_bytes())
+    }
+
+    /// Opens [`areas`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`areas`]: struct.Areas.html#method.areas
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_areas(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Area>> {
+        flatdata::create_external_vector(&*self.storage, "areas", schema::areas::resources::AREAS)
+    }
+
+    #[inline]
+    /// Stores [`polygons`] in the archive.
+    ///
+    /// [`polygons`]: struct.Areas.html#method.polygons
+    pub fn set_polygons(&self, vector: &[super::osm::AreaPolygon]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("polygons", schema::areas::resources::POLYGONS, vector.as_bytes())
+    }
+
+    /// Opens [`polygons`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`polygons`]: struct.Areas.html#method.polygons
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_polygons(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::AreaPolygon>> {
+        flatdata::create_external_vector(&*self.storage, "polygons", schema::areas::resources::POLYGONS)
+    }
+
+    #[inline]
+    /// Stores [`rings`] in the archive.
+    ///
+    /// [`rings`]: struct.Areas.html#method.rings
+    pub fn set_rings(&self, vector: &[super::osm::AreaRing]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("rings", schema::areas::resources::RINGS, vector.as_bytes())
+    }
+
+    /// Opens [`rings`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`rings`]: struct.Areas.html#method.rings
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_rings(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::AreaRing>> {
+        flatdata::create_external_vector(&*self.storage, "rings", schema::areas::resources::RINGS)
+    }
+
+    #[inline]
+    /// Stores [`nodes`] in the archive.
+    ///
+    /// [`nodes`]: struct.Areas.html#method.nodes
+    pub fn set_nodes(&self, vector: &[super::osm::EntityIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("nodes", schema::areas::resources::NODES, vector.as_bytes())
+    }
+
+    /// Opens [`nodes`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`nodes`]: struct.Areas.html#method.nodes
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_nodes(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::EntityIndex>> {
+        flatdata::create_external_vector(&*self.storage, "nodes", schema::areas::resources::NODES)
+    }
+
+}
+
+impl AreasBuilder {
+    pub fn new(
+        storage: flatdata::StorageHandle,
+    ) -> Result<Self, flatdata::ResourceStorageError> {
+        flatdata::create_archive("Areas", schema::areas::AREAS, &storage)?;
+        Ok(Self { storage })
+    }
+}
+
+/// Enum for read-only heterogeneous access to elements in a
+/// bucket of the [`relation_members`] resource.
+///
+/// [`relation_members`]: struct.Archive{.osm.Osm}.html#method.relation_members
+#[derive(Clone, PartialEq)]
+pub enum RelationMembersRef<'a> {
+    #[allow(missing_docs)]
+    NodeMember(&'a super::osm::NodeMember),    #[allow(missing_docs)]
+    WayMember(&'a super::osm::WayMember),    #[allow(missing_docs)]
+    RelationMember(&'a super::osm::RelationMember),}
+
+impl<'a> ::std::fmt::Debug for RelationMembersRef<'a> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        match *self {
+            RelationMembersRef::NodeMember(ref inner) => write!(f, "{:?}", inner),
+            RelationMembersRef::WayMember(ref inner) => write!(f, "{:?}", inner),
+            RelationMembersRef::RelationMember(ref inner) => write!(f, "{:?}", inner),
+        }
+    }
+}
+
+impl<'a> flatdata::VariadicRef for RelationMembersRef<'a> {
+    #[inline]
+    fn size_in_bytes(&self) -> usize {
+        match *self {
+            RelationMembersRef::NodeMember(_) => <super::osm::NodeMember as flatdata::Struct>::SIZE_IN_BYTES,
+            RelationMembersRef::WayMember(_) => <super::osm::WayMember as flatdata::Struct>::SIZE_IN_BYTES,
+            RelationMembersRef::RelationMember(_) => <super::osm::RelationMember as flatdata::Struct>::SIZE_IN_BYTES,
+        }
+    }
+}
+
+/// Builder of buckets in the [`relation_members`] resource.
+///
+/// Refers to a single bucket in the [`relation_members`] multivector and
+/// provides methods for adding heterogeneous data to the bucket.
+///
+/// [`relation_members`]: struct.Archive{.osm.Osm}.html#method.relation_members
+pub struct RelationMembersBuilder<'a> {
+    data: &'a mut Vec<u8>
+}
+
+impl<'a> RelationMembersBuilder<'a> {
+    /// Adds data of the type [`NodeMember`] to the bucket.
+    ///
+    /// [`NodeMember`]: struct.NodeMember.html
+    #[inline]
+    pub fn add_node_member<'b>(&'b mut self) -> &'b mut super::osm::NodeMember {
+        let old_len = self.data.len();
+        let increment = 1 + <super::osm::NodeMember as flatdata::Struct>::SIZE_IN_BYTES;
+        self.data.resize(old_len + increment, 0);
+        self.data[old_len] = 0;
+        let slice = &mut self.data[1 + old_len..];
+        super::osm::NodeMember::from_bytes_slice_mut(slice).expect("Logic error: Cannot create super::osm::NodeMember from slice")
+    }
+    /// Adds data of the type [`WayMember`] to the bucket.
+    ///
+    /// [`WayMember`]: struct.WayMember.html
+    #[inline]
+    pub fn add_way_member<'b>(&'b mut self) -> &'b mut super::osm::WayMember {
+        let old_len = self.data.len();
+        let increment = 1 + <super::osm::WayMember as flatdata::Struct>::SIZE_IN_BYTES;
+        self.data.resize(old_len + increment, 0);
+        self.data[old_len] = 1;
+        let slice = &mut self.data[1 + old_len..];
+        super::osm::WayMember::from_bytes_slice_mut(slice).expect("Logic error: Cannot create super::osm::WayMember from slice")
+    }
+    /// Adds data of the type [`RelationMember`] to the bucket.
+    ///
+    /// [`RelationMember`]: struct.RelationMember.html
+    #[inline]
+    pub fn add_relation_member<'b>(&'b mut self) -> &'b mut super::osm::RelationMember {
+        let old_len = self.data.len();
+        let increment = 1 + <super::osm::RelationMember as flatdata::Struct>::SIZE_IN_BYTES;
+        self.data.resize(old_len + increment, 0);
+        self.data[old_len] = 2;
+        let slice = &mut self.data[1 + old_len..];
+        super::osm::RelationMember::from_bytes_slice_mut(slice).expect("Logic error: Cannot create super::osm::RelationMember from slice")
+    }
+}
+
+/// Variadic struct attached to the [`relation_members`] archive resource.
+///
+/// It unifies the following data types:
+//
+/// * [`NodeMember`]
+/// * [`WayMember`]
+/// * [`RelationMember`]
+///
+/// ## Access pattern
+///
+/// This structure is used as a template parameter in [`relation_members`] multivector/
+/// multiarray view. It does not contain any data, instead it references
+///
+/// * [`RelationMembersRef`] for the read-only heterogeneous access, and
+/// * [`RelationMembersBuilder`] for the mutable builder pattern access.
+///
+/// [`relation_members`]: struct.Archive{.osm.Osm}.html#method.relation_members
+/// [`RelationMembersRef`]: enum.RelationMembersRef.html
+/// [`RelationMembersBuilder`]: struct.RelationMembersBuilder.html
+/// [`NodeMember`]: struct.NodeMember.html
+/// [`WayMember`]: struct.WayMember.html
+/// [`RelationMember`]: struct.RelationMember.html
+#[derive(Clone)]
+pub struct RelationMembers {}
+
+impl flatdata::VariadicIndex for RelationMembers {
+    type Index = super::_builtin::multivector::IndexType40;
+}
+
+impl<'a> flatdata::VariadicStruct<'a> for RelationMembers {
+    type Item = RelationMembersRef<'a>;
+
+    #[inline]
+    fn create(index: flatdata::TypeIndex, data: &'a [u8]) -> Self::Item
+    {
+        match index {
+                0 => RelationMembersRef::NodeMember(super::osm::NodeMember::from_bytes_slice(&data).expect("Corrupted data")),
+                1 => RelationMembersRef::WayMember(super::osm::WayMember::from_bytes_slice(&data).expect("Corrupted data")),
+                2 => RelationMembersRef::RelationMember(super::osm::RelationMember::from_bytes_slice(&data).expect("Corrupted data")),
+            _ => panic!("invalid type index {} for variadic type RelationMembersRef", index),
+        }
+    }
+
+    type ItemMut = RelationMembersBuilder<'a>;
+
+    #[inline]
+    fn create_mut(data: &'a mut Vec<u8>) -> Self::ItemMut
+    {
+        Self::ItemMut { data }
+    }
+}
+
+/// Extent of the grid of tiles of a `TileIndex`.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct TileGrid {
+    data: [u8; 17],
+}
+
+impl TileGrid {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 17]}
+    }
+}
+
+impl flatdata::Struct for TileGrid {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 17]}
+    }
+
+    const SIZE_IN_BYTES: usize = 17;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
+}
+
+impl TileGrid {
+    pub fn new( ) -> Self {
+        Self{data : [0; 17]}
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 17]) -> &Self {
+        // Safety: This is safe since TileGrid is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 17]) -> &mut Self {
+        // Safety: This is safe since TileGrid is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 17 {
+            assert_eq!(data.len(), 17);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *const [u8; 17];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 17 {
+            assert_eq!(data.len(), 17);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 17];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 17] {
+        &self.data
+    }
+}
+
+impl Default for TileGrid {
+    fn default( ) -> Self {
+        Self::new( )
+    }
+}
+
+unsafe impl flatdata::NoOverlap for TileGrid {}
+
+impl TileGrid {
+    /// Zoom level of the tiles
+    #[inline]
+    pub fn zoom(&self) -> u8 {
+        let value = flatdata_read_bytes!(u8, self.data.as_ptr(), 0, 8);
+        unsafe { std::mem::transmute::<u8, u8>(value) }
+    }
+
+    /// Column of the westernmost tile of the grid
+    #[inline]
+    pub fn min_x(&self) -> u32 {
+        let value = flatdata_read_bytes!(u32, self.data.as_ptr(), 8, 32);
+        unsafe { std::mem::transmute::<u32, u32>(value) }
+    }
+
+    /// Row of the northernmost tile of the grid
+    #[inline]
+    pub fn min_y(&self) -> u32 {
+        let value = flatdata_read_bytes!(u32, self.data.as_ptr(), 40, 32);
+        unsafe { std::mem::transmute::<u32, u32>(value) }
+    }
+
+    /// Number of columns of the grid
+    #[inline]
+    pub fn width(&self) -> u32 {
+        let value = flatdata_read_bytes!(u32, self.data.as_ptr(), 72, 32);
+        unsafe { std::mem::transmute::<u32, u32>(value) }
+    }
+
+    /// Number of rows of the grid
+    #[inline]
+    pub fn height(&self) -> u32 {
+        let value = flatdata_read_bytes!(u32, self.data.as_ptr(), 104, 32);
+        unsafe { std::mem::transmute::<u32, u32>(value) }
+    }
+
+}
+
+impl std::fmt::Debug for TileGrid {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("TileGrid")
+            .field("zoom", &self.zoom())
+            .field("min_x", &self.min_x())
+            .field("min_y", &self.min_y())
+            .field("width", &self.width())
+            .field("height", &self.height())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for TileGrid {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.zoom() == other.zoom() &&        self.min_x() == other.min_x() &&        self.min_y() == other.min_y() &&        self.width() == other.width() &&        self.height() == other.height()     }
+}
+
+impl TileGrid {
+    /// Zoom level of the tiles
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_zoom(&mut self, value: u8) {
+        flatdata_write_bytes!(u8; value, self.data, 0, 8)
+    }
+
+    /// Column of the westernmost tile of the grid
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_min_x(&mut self, value: u32) {
+        flatdata_write_bytes!(u32; value, self.data, 8, 32)
+    }
+
+    /// Row of the northernmost tile of the grid
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_min_y(&mut self, value: u32) {
+        flatdata_write_bytes!(u32; value, self.data, 40, 32)
+    }
+
+    /// Number of columns of the grid
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_width(&mut self, value: u32) {
+        flatdata_write_bytes!(u32; value, self.data, 72, 32)
+    }
+
+    /// Number of rows of the grid
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_height(&mut self, value: u32) {
+        flatdata_write_bytes!(u32; value, self.data, 104, 32)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &TileGrid) {
+        self.set_zoom(other.zoom());
+        self.set_min_x(other.min_x());
+        self.set_min_y(other.min_y());
+        self.set_width(other.width());
+        self.set_height(other.height());
+    }
+}
+/// Start of the runs of a tile in a `TileIndex`.
+#[repr(transparent)]
+pub struct TileCell {
+    data: [u8; 5],
+}
+
+impl TileCell {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+}
+
+impl flatdata::Struct for TileCell {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+
+    const SIZE_IN_BYTES: usize = 5;
+    const IS_OVERLAPPING_WITH_NEXT : bool = true;
+}
+
+impl flatdata::Overlap for TileCell {}
+
+impl TileCell {
+    /// First element of the range [`runs`].
+    ///
+    /// [`runs`]: #method.runs
+    #[inline]
+    pub fn first_run_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+    /// Range of the runs of the tile.
+///
+/// The values of the range are indexes in the `node_runs` or `way_runs` vector.
+    #[inline]
+    pub fn runs(&self) -> std::ops::Range<u64> {
+        let start = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        let end = flatdata_read_bytes!(u64, self.data.as_ptr(), 0 + 5 * 8, 40);
+        start..end
+    }
+
+}
+
+impl std::fmt::Debug for TileCell {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("TileCell")
+            .field("first_run_idx", &self.first_run_idx())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for TileCell {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.first_run_idx() == other.first_run_idx()     }
+}
+
+impl TileCell {
+    /// First element of the range [`runs`].
+    ///
+    /// [`runs`]: struct.TileCellRef.html#method.runs
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_first_run_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 0, 40)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &TileCell) {
+        self.set_first_run_idx(other.first_run_idx());
+    }
+}
+
+/// Consecutive indexes of entities in a tile of a `TileIndex`.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct IndexRun {
+    data: [u8; 10],
+}
+
+impl IndexRun {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 10]}
+    }
+}
+
+impl flatdata::Struct for IndexRun {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 10]}
+    }
+
+    const SIZE_IN_BYTES: usize = 10;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
+}
+
+impl IndexRun {
+    pub fn new( ) -> Self {
+        Self{data : [0; 10]}
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 10]) -> &Self {
+        // Safety: This is safe since IndexRun is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 10]) -> &mut Self {
+        // Safety: This is safe since IndexRun is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 10 {
+            assert_eq!(data.len(), 10);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *const [u8; 10];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 10 {
+            assert_eq!(data.len(), 10);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 10];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 10] {
+        &self.data
+    }
+}
+
+impl Default for IndexRun {
+    fn default( ) -> Self {
+        Self::new( )
+    }
+}
+
+unsafe impl flatdata::NoOverlap for IndexRun {}
+
+impl IndexRun {
+    /// First index of the run
+    #[inline]
+    pub fn start(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+    /// Index after the last index of the run
+    #[inline]
+    pub fn end(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 40, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+}
+
+impl std::fmt::Debug for IndexRun {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("IndexRun")
+            .field("start", &self.start())
+            .field("end", &self.end())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for IndexRun {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.start() == other.start() &&        self.end() == other.end()     }
+}
+
+impl IndexRun {
+    /// First index of the run
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_start(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 0, 40)
+    }
+
+    /// Index after the last index of the run
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_end(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 40, 40)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &IndexRun) {
+        self.set_start(other.start());
+        self.set_end(other.end());
+    }
+}
+/// An optional sub-archive mapping the tiles of a fixed zoom level to the nodes and ways
+/// inside of them, cf. `Osm::tile_index`.
+///
+/// The grid covers the tiles of the bounding box of the archive. The tile `(x, y)` has the
+/// cell `(y - grid.min_y) * grid.width + (x - grid.min_x)`, whose nodes are the runs
+/// `node_runs[node_cells[cell].runs()]`.
+#[derive(Clone)]
+pub struct TileIndex {
+    _storage: flatdata::StorageHandle,
+    grid : &'static super::osm::TileGrid,
+    node_cells : &'static [super::osm::TileCell],
+    node_runs : &'static [super::osm::IndexRun],
+    way_cells : &'static [super::osm::TileCell],
+    way_runs : &'static [super::osm::IndexRun],
+}
+
+impl TileIndex {
+    fn signature_name(archive_name: &str) -> String {
+        format!("{}.archive", archive_name)
+    }
+
+    /// Zoom level and extent of the grid
+    #[inline]
+    pub fn grid(&self) -> &super::osm::TileGrid {
+        self.grid
+    }
+
+    /// Runs of the nodes of each tile of the grid in row-major order, with a sentinel
+    #[inline]
+    pub fn node_cells(&self) -> &[super::osm::TileCell] {
+        self.node_cells
+    }
+
+    /// Runs of consecutive indexes of the nodes inside the tiles
+    #[inline]
+    pub fn node_runs(&self) -> &[super::osm::IndexRun] {
+        self.node_runs
+    }
+
+    /// Runs of the ways of each tile of the grid in row-major order, with a sentinel
+    #[inline]
+    pub fn way_cells(&self) -> &[super::osm::TileCell] {
+        self.way_cells
+    }
+
+    /// Runs of consecutive indexes of the ways whose bounding boxes intersect the tiles
+    #[inline]
+    pub fn way_runs(&self) -> &[super::osm::IndexRun] {
+        self.way_runs
+    }
+
+}
+
+impl ::std::fmt::Debug for TileIndex {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("TileIndex")
+            .field("grid", &self.grid())
+            .field("node_cells", &self.node_cells())
+            .field("node_runs", &self.node_runs())
+            .field("way_cells", &self.way_cells())
+            .field("way_runs", &self.way_runs())
+            .finish()
+    }
+}
+
+impl TileIndex {
+    pub fn open(storage: flatdata::StorageHandle)
+        -> ::std::result::Result<Self, flatdata::ResourceStorageError>
+    {
+        #[allow(unused_imports)]
+        use flatdata::SliceExt;
+        #[allow(unused_variables)]
+        use flatdata::ResourceStorageError as Error;
+        // extend lifetime since Rust cannot know that we reference a cache here
+        #[allow(unused_variables)]
+        let extend = |x : Result<&[u8], Error>| -> Result<&'static [u8], Error> {x.map(|x| unsafe{std::mem::transmute(x)})};
+
+        storage.read(&Self::signature_name("TileIndex"), schema::tile_index::TILE_INDEX)?;
+
+        let grid = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("grid", schema::tile_index::resources::GRID));
+            check("grid", |_| 0, max_size, resource.and_then(|x| super::osm::TileGrid::from_bytes_slice(x)))?
+        };
+        let node_cells = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("node_cells", schema::tile_index::resources::NODE_CELLS));
+            check("node_cells", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::TileCell]>::from_bytes(x)))?
+        };
+        let node_runs = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("node_runs", schema::tile_index::resources::NODE_RUNS));
+            check("node_runs", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::IndexRun]>::from_bytes(x)))?
+        };
+        let way_cells = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("way_cells", schema::tile_index::resources::WAY_CELLS));
+            check("way_cells", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::TileCell]>::from_bytes(x)))?
+        };
+        let way_runs = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("way_runs", schema::tile_index::resources::WAY_RUNS));
+            check("way_runs", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::IndexRun]>::from_bytes(x)))?
+        };
+
+        Ok(Self {
+            _storage: storage,
+            grid,
+            node_cells,
+            node_runs,
+            way_cells,
+            way_runs,
+        })
+    }
+}
+
+/// Builder for creating [`TileIndex`] archives.
+///
+///[`TileIndex`]: struct.TileIndex.html
+#[derive(Clone, Debug)]
+pub struct TileIndexBuilder {
+    storage: flatdata::StorageHandle
+}
+
+impl TileIndexBuilder {
+    #[inline]
+    /// Stores [`grid`] in the archive.
+    ///
+    /// [`grid`]: struct.TileIndex.html#method.grid
+    /// Stores [`grid`] in the archive.
+    pub fn set_grid(&self, resource: &super::osm::TileGrid) -> ::std::io::Result<()> {
+        let data = resource.as_bytes();
+        self.storage.write("grid", schema::tile_index::resources::GRID, data)
+    }
+
+    #[inline]
+    /// Stores [`node_cells`] in the archive.
+    ///
+    /// [`node_cells`]: struct.TileIndex.html#method.node_cells
+    pub fn set_node_cells(&self, vector: &[super::osm::TileCell]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("node_cells", schema::tile_index::resources::NODE_CELLS, vector.as_bytes())
+    }
+
+    /// Opens [`node_cells`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`node_cells`]: struct.TileIndex.html#method.node_cells
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_node_cells(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::TileCell>> {
+        flatdata::create_external_vector(&*self.storage, "node_cells", schema::tile_index::resources::NODE_CELLS)
+    }
+
+    #[inline]
+    /// Stores [`node_runs`] in the archive.
+    ///
+    /// [`node_runs`]: struct.TileIndex.html#method.node_runs
+    pub fn set_node_runs(&self, vector: &[super::osm::IndexRun]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("node_runs", schema::tile_index::resources::NODE_RUNS, vector.as_bytes())
+    }
+
+    /// Opens [`node_runs`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`node_runs`]: struct.TileIndex.html#method.node_runs
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_node_runs(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::IndexRun>> {
+        flatdata::create_external_vector(&*self.storage, "node_runs", schema::tile_index::resources::NODE_RUNS)
+    }
+
+    #[inline]
+    /// Stores [`way_cells`] in the archive.
+    ///
+    /// [`way_cells`]: struct.TileIndex.html#method.way_cells
+    pub fn set_way_cells(&self, vector: &[super::osm::TileCell]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("way_cells", schema::tile_index::resources::WAY_CELLS, vector.as_bytes())
+    }
+
+    /// Opens [`way_cells`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`way_cells`]: struct.TileIndex.html#method.way_cells
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_way_cells(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::TileCell>> {
+        flatdata::create_external_vector(&*self.storage, "way_cells", schema::tile_index::resources::WAY_CELLS)
+    }
+
+    #[inline]
+    /// Stores [`way_runs`] in the archive.
+    ///
+    /// [`way_runs`]: struct.TileIndex.html#method.way_runs
+    pub fn set_way_runs(&self, vector: &[super::osm::IndexRun]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("way_runs", schema::tile_index::resources::WAY_RUNS, vector.as_bytes())
+    }
+
+    /// Opens [`way_runs`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`way_runs`]: struct.TileIndex.html#method.way_runs
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_way_runs(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::IndexRun>> {
+        flatdata::create_external_vector(&*self.storage, "way_runs", schema::tile_index::resources::WAY_RUNS)
+    }
+
+}
+
+impl TileIndexBuilder {
+    pub fn new(
+        storage: flatdata::StorageHandle,
+    ) -> Result<Self, flatdata::ResourceStorageError> {
+        flatdata::create_archive("TileIndex", schema::tile_index::TILE_INDEX, &storage)?;
+        Ok(Self { storage })
+    }
+}
+
+
+/// Administrative boundaries containing an entity in an `AdminHierarchy`.
+#[repr(transparent)]
+pub struct AdminChain {
+    data: [u8; 5],
+}
+
+impl AdminChain {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+}
+
+impl flatdata::Struct for AdminChain {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+
+    const SIZE_IN_BYTES: usize = 5;
+    const IS_OVERLAPPING_WITH_NEXT : bool = true;
+}
+
+impl flatdata::Overlap for AdminChain {}
+
+impl AdminChain {
+    /// First element of the range [`boundaries`].
+    ///
+    /// [`boundaries`]: #method.boundaries
+    #[inline]
+    pub fn first_boundary_idx(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+    /// Range of the boundaries of the chain, ordered by ascending admin level.
+///
+/// The values of the range are indexes in the `boundaries` vector.
+    #[inline]
+    pub fn boundaries(&self) -> std::ops::Range<u64> {
+        let start = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        let end = flatdata_read_bytes!(u64, self.data.as_ptr(), 0 + 5 * 8, 40);
+        start..end
+    }
+
+}
+
+impl std::fmt::Debug for AdminChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("AdminChain")
+            .field("first_boundary_idx", &self.first_boundary_idx())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for AdminChain {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.first_boundary_idx() == other.first_boundary_idx()     }
+}
+
+impl AdminChain {
+    /// First element of the range [`boundaries`].
+    ///
+    /// [`boundaries`]: struct.AdminChainRef.html#method.boundaries
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_first_boundary_idx(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 0, 40)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &AdminChain) {
+        self.set_first_boundary_idx(other.first_boundary_idx());
+    }
+}
+
+/// Index of a chain of an `AdminHierarchy`.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct AdminChainIndex {
+    data: [u8; 4],
+}
+
+impl AdminChainIndex {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 4]}
+    }
+}
+
+impl flatdata::Struct for AdminChainIndex {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 4]}
+    }
+
+    const SIZE_IN_BYTES: usize = 4;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
+}
+
+impl AdminChainIndex {
+    pub fn new( ) -> Self {
+        Self{data : [0; 4]}
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 4]) -> &Self {
+        // Safety: This is safe since AdminChainIndex is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 4]) -> &mut Self {
+        // Safety: This is safe since AdminChainIndex is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 4 {
+            assert_eq!(data.len(), 4);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *const [u8; 4];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 4 {
+            assert_eq!(data.len(), 4);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 4];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 4] {
+        &self.data
+    }
+}
+
+impl Default for AdminChainIndex {
+    fn default( ) -> Self {
+        Self::new( )
+    }
+}
+
+unsafe impl flatdata::NoOverlap for AdminChainIndex {}
+
+impl AdminChainIndex {
+    /// Index in the `chains` vector
+    #[inline]
+    pub fn value(&self) -> u32 {
+        let value = flatdata_read_bytes!(u32, self.data.as_ptr(), 0, 32);
+        unsafe { std::mem::transmute::<u32, u32>(value) }
+    }
+
+}
+
+impl std::fmt::Debug for AdminChainIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("AdminChainIndex")
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for AdminChainIndex {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.value() == other.value()     }
+}
+
+impl AdminChainIndex {
+    /// Index in the `chains` vector
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_value(&mut self, value: u32) {
+        flatdata_write_bytes!(u32; value, self.data, 0, 32)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &AdminChainIndex) {
+        self.set_value(other.value());
+    }
+}
+/// An optional sub-archive mapping each node to the administrative boundaries containing
+/// it, cf. `Osm::admin_hierarchy`.
+///
+/// Administrative boundaries are relations tagged `boundary=administrative` with a numeric
+/// `admin_level`. Nodes inside of the same boundaries share a chain: the node at index `i`
+/// is inside of the relations `boundaries[chains[node_chains[i]].boundaries()]`, e.g. its
+/// country, state and city. The chain at index 0 is empty.
+#[derive(Clone)]
+pub struct AdminHierarchy {
+    _storage: flatdata::StorageHandle,
+    chains : &'static [super::osm::AdminChain],
+    boundaries : &'static [super::osm::EntityIndex],
+    node_chains : &'static [super::osm::AdminChainIndex],
+}
+
+impl AdminHierarchy {
+    fn signature_name(archive_name: &str) -> String {
+        format!("{}.archive", archive_name)
+    }
+
+    /// Distinct chains of boundaries, with a sentinel
+    #[inline]
+    pub fn chains(&self) -> &[super::osm::AdminChain] {
+        self.chains
+    }
+
+    /// Indexes of the boundary relations of all chains in the `relations` vector of the
+/// parent archive
+    #[inline]
+    pub fn boundaries(&self) -> &[super::osm::EntityIndex] {
+        self.boundaries
+    }
+
+    /// Chain of each node; nodes[i] has its chain stored in node_chains[i]
+    #[inline]
+    pub fn node_chains(&self) -> &[super::osm::AdminChainIndex] {
+        self.node_chains
+    }
+
+}
+
+impl ::std::fmt::Debug for AdminHierarchy {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("AdminHierarchy")
+            .field("chains", &self.chains())
+            .field("boundaries", &self.boundaries())
+            .field("node_chains", &self.node_chains())
+            .finish()
+    }
+}
+
+impl AdminHierarchy {
+    pub fn open(storage: flatdata::StorageHandle)
+        -> ::std::result::Result<Self, flatdata::ResourceStorageError>
+    {
+        #[allow(unused_imports)]
+        use flatdata::SliceExt;
+        #[allow(unused_variables)]
+        use flatdata::ResourceStorageError as Error;
+        // extend lifetime since Rust cannot know that we reference a cache here
+        #[allow(unused_variables)]
+        let extend = |x : Result<&[u8], Error>| -> Result<&'static [u8], Error> {x.map(|x| unsafe{std::mem::transmute(x)})};
+
+        storage.read(&Self::signature_name("AdminHierarchy"), schema::admin_hierarchy::ADMIN_HIERARCHY)?;
+
+        let chains = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("chains", schema::admin_hierarchy::resources::CHAINS));
+            check("chains", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::AdminChain]>::from_bytes(x)))?
+        };
+        let boundaries = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("boundaries", schema::admin_hierarchy::resources::BOUNDARIES));
+            check("boundaries", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::EntityIndex]>::from_bytes(x)))?
+        };
+        let node_chains = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("node_chains", schema::admin_hierarchy::resources::NODE_CHAINS));
+            check("node_chains", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::AdminChainIndex]>::from_bytes(x)))?
+        };
+
+        Ok(Self {
+            _storage: storage,
+            chains,
+            boundaries,
+            node_chains,
+        })
+    }
+}
+
+/// Builder for creating [`AdminHierarchy`] archives.
+///
+///[`AdminHierarchy`]: struct.AdminHierarchy.html
+#[derive(Clone, Debug)]
+pub struct AdminHierarchyBuilder {
+    storage: flatdata::StorageHandle
+}
+
+impl AdminHierarchyBuilder {
+    #[inline]
+    /// Stores [`chains`] in the archive.
+    ///
+    /// [`chains`]: struct.AdminHierarchy.html#method.chains
+    pub fn set_chains(&self, vector: &[super::osm::AdminChain]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("chains", schema::admin_hierarchy::resources::CHAINS, vector.as_bytes())
+    }
+
+    /// Opens [`chains`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`chains`]: struct.AdminHierarchy.html#method.chains
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_chains(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::AdminChain>> {
+        flatdata::create_external_vector(&*self.storage, "chains", schema::admin_hierarchy::resources::CHAINS)
+    }
+
+    #[inline]
+    /// Stores [`boundaries`] in the archive.
+    ///
+    /// [`boundaries`]: struct.AdminHierarchy.html#method.boundaries
+    pub fn set_boundaries(&self, vector: &[super::osm::EntityIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("boundaries", schema::admin_hierarchy::resources::BOUNDARIES, vector.as_bytes())
+    }
+
+    /// Opens [`boundaries`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`boundaries`]: struct.AdminHierarchy.html#method.boundaries
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_boundaries(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::EntityIndex>> {
+        flatdata::create_external_vector(&*self.storage, "boundaries", schema::admin_hierarchy::resources::BOUNDARIES)
+    }
+
+    #[inline]
+    /// Stores [`node_chains`] in the archive.
+    ///
+    /// [`node_chains`]: struct.AdminHierarchy.html#method.node_chains
+    pub fn set_node_chains(&self, vector: &[super::osm::AdminChainIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("node_chains", schema::admin_hierarchy::resources::NODE_CHAINS, vector.as_bytes())
+    }
+
+    /// Opens [`node_chains`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`node_chains`]: struct.AdminHierarchy.html#method.node_chains
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_node_chains(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::AdminChainIndex>> {
+        flatdata::create_external_vector(&*self.storage, "node_chains", schema::admin_hierarchy::resources::NODE_CHAINS)
+    }
+
+}
+
+impl AdminHierarchyBuilder {
+    pub fn new(
+        storage: flatdata::StorageHandle,
+    ) -> Result<Self, flatdata::ResourceStorageError> {
+        flatdata::create_archive("AdminHierarchy", schema::admin_hierarchy::ADMIN_HIERARCHY, &storage)?;
+        Ok(Self { storage })
+    }
+}
+
+
+/// Coordinates of a node, cf. `Osm::node_coords`.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct NodeCoord {
+    data: [u8; 8],
+}
+
+impl NodeCoord {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 8]}
+    }
+}
+
+impl flatdata::Struct for NodeCoord {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 8]}
+    }
+
+    const SIZE_IN_BYTES: usize = 8;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
+}
+
+impl NodeCoord {
+    pub fn new( ) -> Self {
+        Self{data : [0; 8]}
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 8]) -> &Self {
+        // Safety: This is safe since NodeCoord is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 8]) -> &mut Self {
+        // Safety: This is safe since NodeCoord is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 8 {
+            assert_eq!(data.len(), 8);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *const [u8; 8];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 8 {
+            assert_eq!(data.len(), 8);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 8];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 8] {
+        &self.data
+    }
+}
+
+impl Default for NodeCoord {
+    fn default( ) -> Self {
+        Self::new( )
+    }
+}
+
+unsafe impl flatdata::NoOverlap for NodeCoord {}
+
+impl NodeCoord {
+    /// Latitude (scaled with `header.coord_scale`).
+    #[inline]
+    pub fn lat(&self) -> i32 {
+        let value = flatdata_read_bytes!(i32, self.data.as_ptr(), 0, 32);
+        unsafe { std::mem::transmute::<i32, i32>(value) }
+    }
+
+    /// Longitude (scaled with `header.coord_scale`).
+    #[inline]
+    pub fn lon(&self) -> i32 {
+        let value = flatdata_read_bytes!(i32, self.data.as_ptr(), 32, 32);
+        unsafe { std::mem::transmute::<i32, i32>(value) }
+    }
+
+}
+
+impl std::fmt::Debug for NodeCoord {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("NodeCoord")
+            .field("lat", &self.lat())
+            .field("lon", &self.lon())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for NodeCoord {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.lat() == other.lat() &&        self.lon() == other.lon()     }
+}
+
+impl NodeCoord {
+    /// Latitude (scaled with `header.coord_scale`).
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_lat(&mut self, value: i32) {
+        flatdata_write_bytes!(i32; value, self.data, 0, 32)
+    }
+
+    /// Longitude (scaled with `header.coord_scale`).
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_lon(&mut self, value: i32) {
+        flatdata_write_bytes!(i32; value, self.data, 32, 32)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &NodeCoord) {
+        self.set_lat(other.lat());
+        self.set_lon(other.lon());
+    }
+}
+/// A node is one of the core elements in the OpenStreetMap data model.
+///
+/// It consists of a single point in space defined by its latitude, longitude and node id.
+///
+/// Offset of a string in the `stringtable`.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct StringOffset {
+    data: [u8; 5],
+}
+
+impl StringOffset {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+}
+
+impl flatdata::Struct for StringOffset {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 5]}
+    }
+
+    const SIZE_IN_BYTES: usize = 5;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
+}
+
+impl StringOffset {
+    pub fn new( ) -> Self {
+        Self{data : [0; 5]}
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 5]) -> &Self {
+        // Safety: This is safe since StringOffset is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 5]) -> &mut Self {
+        // Safety: This is safe since StringOffset is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 5 {
+            assert_eq!(data.len(), 5);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *const [u8; 5];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 5 {
+            assert_eq!(data.len(), 5);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 5];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 5] {
+        &self.data
+    }
+}
+
+impl Default for StringOffset {
+    fn default( ) -> Self {
+        Self::new( )
+    }
+}
+
+unsafe impl flatdata::NoOverlap for StringOffset {}
+
+impl StringOffset {
+    /// Index in the `stringtable` of the first byte of the string.
+    #[inline]
+    pub fn value(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+}
+
+impl std::fmt::Debug for StringOffset {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("StringOffset")
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for StringOffset {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.value() == other.value()     }
+}
+
+impl StringOffset {
+    /// Index in the `stringtable` of the first byte of the string.
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_value(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 0, 40)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &StringOffset) {
+        self.set_value(other.value());
+    }
+}
+/// Elevation of a node, cf. `Osm::elevation`.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct Elevation {
+    data: [u8; 2],
+}
+
+impl Elevation {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 2]}
+    }
+}
+
+impl flatdata::Struct for Elevation {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 2]}
+    }
+
+    const SIZE_IN_BYTES: usize = 2;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
+}
+
+impl Elevation {
+    pub fn new( ) -> Self {
+        Self{data : [0; 2]}
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 2]) -> &Self {
+        // Safety: This is safe since Elevation is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 2]) -> &mut Self {
+        // Safety: This is safe since Elevation is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 2 {
+            assert_eq!(data.len(), 2);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *const [u8; 2];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 2 {
+            assert_eq!(data.len(), 2);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 2];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 2] {
+        &self.data
+    }
+}
+
+impl Default for Elevation {
+    fn default( ) -> Self {
+        Self::new( )
+    }
+}
+
+unsafe impl flatdata::NoOverlap for Elevation {}
+
+impl Elevation {
+    /// Elevation in meters above sea level
+    #[inline]
+    pub fn value(&self) -> Option<i16> {
+        let value = flatdata_read_bytes!(i16, self.data.as_ptr(), 0, 16);
+        let x = unsafe { std::mem::transmute::<i16, i16>(value) };
+        Some(x).filter(|&x| x != super::osm::NO_ELEVATION)
+    }
+
+}
+
+impl std::fmt::Debug for Elevation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Elevation")
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for Elevation {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.value() == other.value()     }
+}
+
+impl Elevation {
+    /// Elevation in meters above sea level
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_value(&mut self, value: Option<i16>) {
+let value = value.unwrap_or(super::osm::NO_ELEVATION);        flatdata_write_bytes!(i16; value, self.data, 0, 16)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &Elevation) {
+        self.set_value(other.value());
+    }
+}
+/// 64 bits of a bitset over the entities of a vector, cf. `Osm::tagged_node_bits`.
+///
+/// The entity at index `i` is marked by the bit `i % 64`, counted from the least
+/// significant bit, of the word at index `i / 64`.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct BitsetWord {
+    data: [u8; 8],
+}
+
+impl BitsetWord {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 8]}
+    }
+}
+
+impl flatdata::Struct for BitsetWord {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 8]}
+    }
+
+    const SIZE_IN_BYTES: usize = 8;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
+}
+
+impl BitsetWord {
+    pub fn new( ) -> Self {
+        Self{data : [0; 8]}
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 8]) -> &Self {
+        // Safety: This is safe since BitsetWord is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 8]) -> &mut Self {
+        // Safety: This is safe since BitsetWord is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 8 {
+            assert_eq!(data.len(), 8);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *const [u8; 8];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 8 {
+            assert_eq!(data.len(), 8);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 8];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 8] {
+        &self.data
+    }
+}
+
+impl Default for BitsetWord {
+    fn default( ) -> Self {
+        Self::new( )
+    }
+}
+
+unsafe impl flatdata::NoOverlap for BitsetWord {}
+
+impl BitsetWord {
+    /// Bits of 64 consecutive entities
+    #[inline]
+    pub fn value(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 0, 64);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+}
+
+impl std::fmt::Debug for BitsetWord {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("BitsetWord")
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for BitsetWord {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.value() == other.value()     }
+}
+
+impl BitsetWord {
+    /// Bits of 64 consecutive entities
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_value(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 0, 64)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &BitsetWord) {
+        self.set_value(other.value());
+    }
+}
+/// Numeric value of a tag, cf. `Osm::tag_numbers`.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct TagNumber {
+    data: [u8; 8],
+}
+
+impl TagNumber {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 8]}
+    }
+}
+
+impl flatdata::Struct for TagNumber {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 8]}
+    }
+
+    const SIZE_IN_BYTES: usize = 8;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
+}
+
+impl TagNumber {
+    pub fn new( ) -> Self {
+        Self{data : [0; 8]}
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 8]) -> &Self {
+        // Safety: This is safe since TagNumber is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 8]) -> &mut Self {
+        // Safety: This is safe since TagNumber is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 8 {
+            assert_eq!(data.len(), 8);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *const [u8; 8];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 8 {
+            assert_eq!(data.len(), 8);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 8];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 8] {
+        &self.data
+    }
+}
+
+impl Default for TagNumber {
+    fn default( ) -> Self {
+        Self::new( )
+    }
+}
+
+unsafe impl flatdata::NoOverlap for TagNumber {}
+
+impl TagNumber {
+    /// Value scaled by 1000
+    #[inline]
+    pub fn value(&self) -> Option<i64> {
+        let value = flatdata_read_bytes!(i64, self.data.as_ptr(), 0, 64);
+        let x = unsafe { std::mem::transmute::<i64, i64>(value) };
+        Some(x).filter(|&x| x != super::osm::NO_NUMBER)
+    }
+
+}
+
+impl std::fmt::Debug for TagNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("TagNumber")
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for TagNumber {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.value() == other.value()     }
+}
+
+impl TagNumber {
+    /// Value scaled by 1000
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_value(&mut self, value: Option<i64>) {
+let value = value.unwrap_or(super::osm::NO_NUMBER);        flatdata_write_bytes!(i64; value, self.data, 0, 64)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &TagNumber) {
+        self.set_value(other.value());
+    }
+}
+/// Representative point of a way or relation, cf. `Osm::way_centroids`.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct Centroid {
+    data: [u8; 8],
+}
+
+impl Centroid {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 8]}
+    }
+}
+
+impl flatdata::Struct for Centroid {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 8]}
+    }
+
+    const SIZE_IN_BYTES: usize = 8;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
+}
+
+impl Centroid {
+    pub fn new( ) -> Self {
+        Self{data : [0; 8]}
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 8]) -> &Self {
+        // Safety: This is safe since Centroid is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 8]) -> &mut Self {
+        // Safety: This is safe since Centroid is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 8 {
+            assert_eq!(data.len(), 8);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *const [u8; 8];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 8 {
+            assert_eq!(data.len(), 8);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 8];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 8] {
+        &self.data
+    }
+}
+
+impl Default for Centroid {
+    fn default( ) -> Self {
+        Self::new( )
+    }
+}
+
+unsafe impl flatdata::NoOverlap for Centroid {}
+
+impl Centroid {
+    /// Latitude (scaled with `header.coord_scale`), or `NO_CENTROID` if the entity has no
+/// resolved geometry.
+    #[inline]
+    pub fn lat(&self) -> Option<i32> {
+        let value = flatdata_read_bytes!(i32, self.data.as_ptr(), 0, 32);
+        let x = unsafe { std::mem::transmute::<i32, i32>(value) };
+        Some(x).filter(|&x| x != super::osm::NO_CENTROID)
+    }
+
+    /// Longitude (scaled with `header.coord_scale`).
+    #[inline]
+    pub fn lon(&self) -> i32 {
+        let value = flatdata_read_bytes!(i32, self.data.as_ptr(), 32, 32);
+        unsafe { std::mem::transmute::<i32, i32>(value) }
+    }
+
+}
+
+impl std::fmt::Debug for Centroid {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Centroid")
+            .field("lat", &self.lat())
+            .field("lon", &self.lon())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for Centroid {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.lat() == other.lat() &&        self.lon() == other.lon()     }
+}
+
+impl Centroid {
+    /// Latitude (scaled with `header.coord_scale`), or `NO_CENTROID` if the entity has no
+/// resolved geometry.
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_lat(&mut self, value: Option<i32>) {
+let value = value.unwrap_or(super::osm::NO_CENTROID);        flatdata_write_bytes!(i32; value, self.data, 0, 32)
+    }
+
+    /// Longitude (scaled with `header.coord_scale`).
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_lon(&mut self, value: i32) {
+        flatdata_write_bytes!(i32; value, self.data, 32, 32)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &Centroid) {
+        self.set_lat(other.lat());
+        self.set_lon(other.lon());
+    }
+}
+/// OSM data archive
+///
+/// Relations and relation members are indexed with the same index, i.e.
+/// a relation at index `i` in the vector `relations` has the members
+/// at index `i` in the multivector `relation_members`.
+///
+/// All 1:n relationships are modeled in-place by using an additional index. This is a
+/// common pattern in flatdata. For example, a node might have multiple tags attached
+/// to it. To model this, a node in `nodes` references the first tag attached to it
+/// by storing an index in the `tags_index` vector. The next node in `nodes` again
+/// references its first tag, which is the last tag (exclusive) of the previous node.
+///
+/// ```text
+/// nodes:      [ ..., n_1, n_2, ... ]
+///                     |    |
+///                     |    +-------+
+///                     v            v
+/// tags_index: [ ..., t_11, t_12, ..., t_1n, t_21, ... t_2m, ... ]
+/// ```
+#[derive(Clone)]
+pub struct Osm {
+    _storage: flatdata::StorageHandle,
+    header : &'static super::osm::Header,
+    nodes : &'static [super::osm::Node],
+    ways : &'static [super::osm::Way],
+    relations : &'static [super::osm::Relation],
+    relation_members : flatdata::MultiArrayView<'static, RelationMembers>,
+    tags : &'static [super::osm::Tag],
+    tags_index : &'static [super::osm::TagIndex],
+    nodes_index : &'static [super::osm::NodeIndex],
+    stringtable : flatdata::RawData<'static>,
+    ids : Option<super::osm::Ids
+>,
+    history : Option<super::osm::History
+>,
+    spatial_index : Option<super::osm::SpatialIndex
+>,
+    inverted_index : Option<super::osm::InvertedIndex
+>,
+    provenance : Option<flatdata::RawData<'static>>,
+    way_bboxes : Option<&'static [super::osm::BoundingBox]>,
+    relation_bboxes : Option<&'static [super::osm::BoundingBox]>,
+    tagged_nodes : Option<&'static [super::osm::EntityIndex]>,
+    changesets : Option<super::osm::Changesets
+>,
+    checksums : Option<flatdata::RawData<'static>>,
+    id_index : Option<super::osm::IdIndex
+>,
+    node_ways : Option<super::osm::ReverseIndex
+>,
+    node_relations : Option<super::osm::ReverseIndex
+>,
+    way_relations : Option<super::osm::ReverseIndex
+>,
+    relation_relations : Option<super::osm::ReverseIndex
+>,
+    areas : Option<super::osm::Areas
+>,
+    stringtable_offsets : Option<&'static [super::osm::StringOffset]>,
+    node_coords : Option<&'static [super::osm::NodeCoord]>,
+    elevation : Option<&'static [super::osm::Elevation]>,
+    tile_index : Option<super::osm::TileIndex
+>,
+    admin_hierarchy : Option<super::osm::AdminHierarchy
+>,
+    tagged_node_bits : Option<&'static [super::osm::BitsetWord]>,
+    tagged_way_bits : Option<&'static [super::osm::BitsetWord]>,
+    tag_numbers : Option<&'static [super::osm::TagNumber]>,
+    roles : Option<&'static [super::osm::StringOffset]>,
+    way_centroids : Option<&'static [super::osm::Centroid]>,
+    relation_centroids : Option<&'static [super::osm::Centroid]>,
+}
+
+impl Osm {
+    fn signature_name(archive_name: &str) -> String {
+        format!("{}.archive", archive_name)
+    }
+
+    /// Header which contains the metadata attached to the archive.
+    #[inline]
+    pub fn header(&self) -> &super::osm::Header {
+        self.header
+    }
+
+    /// List of nodes.
+///
+/// A node references a range of tags in the `tags_index` vector.
+    #[inline]
+    pub fn nodes(&self) -> &[super::osm::Node] {
+        self.nodes
+    }
+
+    /// List of ways.
+///
+/// A way references
+///
+/// * a range of tags in the `tags_index` vector, and
+/// * a range of nodes in the `nodes_index` vector.
+    #[inline]
+    pub fn ways(&self) -> &[super::osm::Way] {
+        self.ways
+    }
+
+    /// List of relations.
+///
+/// A relation references a range of tags in `tags_index` vectors.
+/// Members are attached to a relation implicitly: members that belong to a
+/// relation at index `i` are at index `i` in the `relation_members` multivector.
+    #[inline]
+    pub fn relations(&self) -> &[super::osm::Relation] {
+        self.relations
+    }
+
+    /// Members attached to relations.
+///
+/// An index in this multivector corresponds to an index in the `relations` vector.
+///
+/// A member has a variadic type: `NodeMember`, `WayMember` or `RelationMember`.
+/// Each type references its role in the `stringtable` raw data. Additionally,
+///
+/// * a node member references a node in the `nodes` vector,
+/// * a way member references a way in the `ways` vector,
+/// * a relation member references a relation in the `relations` vector.
+    #[inline]
+    pub fn relation_members(&self) -> &flatdata::MultiArrayView<RelationMembers> {
+        &self.relation_members
+    }
+
+    /// List of tags.
+///
+/// A tag references its key and value in the `stringtable` raw data.
+    #[inline]
+    pub fn tags(&self) -> &[super::osm::Tag] {
+        self.tags
+    }
+
+    /// Auxiliary index of tags to model 1:n relationships between nodes, ways, relations
+/// and tags.
+    #[inline]
+    pub fn tags_index(&self) -> &[super::osm::TagIndex] {
+        self.tags_index
+    }
+
+    /// Auxiliary index of nodes to model 1:n relationship between ways and nodes.
+    #[inline]
+    pub fn nodes_index(&self) -> &[super::osm::NodeIndex] {
+        self.nodes_index
+    }
+
+    /// List of strings separated by `\0`.
+    #[inline]
+    pub fn stringtable(&self) -> flatdata::RawData {
+        self.stringtable
+    }
+
+    #[inline]
+    pub fn ids(&self) -> Option<&super::osm::Ids> {
+        self.ids.as_ref()
+    }
+
+    #[inline]
+    pub fn history(&self) -> Option<&super::osm::History> {
+        self.history.as_ref()
+    }
+
+    #[inline]
+    pub fn spatial_index(&self) -> Option<&super::osm::SpatialIndex> {
+        self.spatial_index.as_ref()
+    }
+
+    #[inline]
+    pub fn inverted_index(&self) -> Option<&super::osm::InvertedIndex> {
+        self.inverted_index.as_ref()
+    }
+
+    /// JSON object describing how the archive was produced: the version of osmflatc,
+/// its flags, the checksum of the input, the time of the conversion, and its stats.
+    #[inline]
+    pub fn provenance(&self) -> Option<flatdata::RawData> {
+        self.provenance
+    }
+
+    /// Bounding boxes of all ways; ways[i] has its bounding box stored in
+/// way_bboxes[i].
+    #[inline]
+    pub fn way_bboxes(&self) -> Option<&[super::osm::BoundingBox]> {
+        self.way_bboxes
+    }
+
+    /// Bounding boxes of all relations; relations[i] has its bounding box stored in
+/// relation_bboxes[i].
+///
+/// The bounding box of a relation contains the bounding boxes of all its members,
+/// including the members of relation members.
+    #[inline]
+    pub fn relation_bboxes(&self) -> Option<&[super::osm::BoundingBox]> {
+        self.relation_bboxes
+    }
+
+    /// Indexes of the nodes having tags in ascending order.
+///
+/// Most nodes have no tags and only define the geometry of ways. Iterating
+/// over the tagged nodes, e.g. points of interest, skips them.
+    #[inline]
+    pub fn tagged_nodes(&self) -> Option<&[super::osm::EntityIndex]> {
+        self.tagged_nodes
+    }
+
+    /// Changesets of the input, cf. [`Changesets`].
+    #[inline]
+    pub fn changesets(&self) -> Option<&super::osm::Changesets> {
+        self.changesets.as_ref()
+    }
+
+    /// Manifest of the files of the archive with one line `<path> <size> <xxh64>` per
+/// file, where the path is relative to the archive and the checksum is hex encoded.
+/// The manifest does not list itself and its schema.
+    #[inline]
+    pub fn checksums(&self) -> Option<flatdata::RawData> {
+        self.checksums
+    }
+
+    /// Indexes of the entities sorted by id, cf. [`IdIndex`].
+    #[inline]
+    pub fn id_index(&self) -> Option<&super::osm::IdIndex> {
+        self.id_index.as_ref()
+    }
+
+    /// Ways containing each node: node `i` is referenced by the ways in
+/// `node_ways.entries[node_ways.ranges[i].entries()]`.
+    #[inline]
+    pub fn node_ways(&self) -> Option<&super::osm::ReverseIndex> {
+        self.node_ways.as_ref()
+    }
+
+    /// Relations having each node as member: node `i` is a member of the relations in
+/// `node_relations.entries[node_relations.ranges[i].entries()]`.
+    #[inline]
+    pub fn node_relations(&self) -> Option<&super::osm::ReverseIndex> {
+        self.node_relations.as_ref()
+    }
+
+    /// Relations having each way as member: way `i` is a member of the relations in
+/// `way_relations.entries[way_relations.ranges[i].entries()]`.
+    #[inline]
+    pub fn way_relations(&self) -> Option<&super::osm::ReverseIndex> {
+        self.way_relations.as_ref()
+    }
+
+    /// Relations having each relation as member: relation `i` is a member of the
+/// relations in `relation_relations.entries[relation_relations.ranges[i].entries()]`.
+    #[inline]
+    pub fn relation_relations(&self) -> Option<&super::osm::ReverseIndex> {
+        self.relation_relations.as_ref()
+    }
+
+    /// Areas assembled from closed ways and multipolygon relations.
+    #[inline]
+    pub fn areas(&self) -> Option<&super::osm::Areas> {
+        self.areas.as_ref()
+    }
+
+    /// Offsets of all strings in the `stringtable` in ascending order, followed by the
+/// size of the `stringtable`.
+///
+/// The string starting at `stringtable_offsets[i]` ends before the NUL terminator at
+/// `stringtable_offsets[i + 1] - 1`, which allows to slice strings without scanning
+/// for the terminator, cf. `Osm::substring_fast`.
+    #[inline]
+    pub fn stringtable_offsets(&self) -> Option<&[super::osm::StringOffset]> {
+        self.stringtable_offsets
+    }
+
+    /// Coordinates of all nodes; nodes[i] has its coordinates stored in node_coords[i].
+///
+/// They duplicate the coordinates of `nodes` without the tag ranges, so that scans over
+/// the coordinates only, e.g. for rendering or computing bounding boxes, read fewer
+/// bytes, and compress better, cf. `Osm::node_coord`.
+    #[inline]
+    pub fn node_coords(&self) -> Option<&[super::osm::NodeCoord]> {
+        self.node_coords
+    }
+
+    /// Elevations of all nodes; nodes[i] has its elevation stored in elevation[i].
+///
+/// The elevations are sampled from a digital elevation model during the conversion,
+/// cf. `osmflatc --elevation`.
+    #[inline]
+    pub fn elevation(&self) -> Option<&[super::osm::Elevation]> {
+        self.elevation
+    }
+
+    /// Nodes and ways by tiles of a fixed zoom level, cf. [`TileIndex`].
+    #[inline]
+    pub fn tile_index(&self) -> Option<&super::osm::TileIndex> {
+        self.tile_index.as_ref()
+    }
+
+    /// Administrative boundaries containing each node, cf. [`AdminHierarchy`].
+    #[inline]
+    pub fn admin_hierarchy(&self) -> Option<&super::osm::AdminHierarchy> {
+        self.admin_hierarchy.as_ref()
+    }
+
+    /// Bitset marking the nodes having tags, cf. [`BitsetWord`].
+///
+/// Scans over points of interest skip 64 untagged nodes at once, without reading their
+/// tag ranges.
+    #[inline]
+    pub fn tagged_node_bits(&self) -> Option<&[super::osm::BitsetWord]> {
+        self.tagged_node_bits
+    }
+
+    /// Bitset marking the ways having tags, cf. [`BitsetWord`].
+    #[inline]
+    pub fn tagged_way_bits(&self) -> Option<&[super::osm::BitsetWord]> {
+        self.tagged_way_bits
+    }
+
+    /// Numeric values of all tags; tags[i] has its value parsed as a number stored in
+/// tag_numbers[i].
+///
+/// Only the values of the keys listed with `osmflatc --numeric-tags` are parsed, cf.
+/// `osmflat::tag_number`.
+    #[inline]
+    pub fn tag_numbers(&self) -> Option<&[super::osm::TagNumber]> {
+        self.tag_numbers
+    }
+
+    /// Roles of all relation members in ascending order of their index in the `stringtable`.
+///
+/// The position of a role in this table is a narrow index of the role, since there are
+/// only a few hundred distinct roles, cf. `Osm::interned_role`.
+    #[inline]
+    pub fn roles(&self) -> Option<&[super::osm::StringOffset]> {
+        self.roles
+    }
+
+    /// Representative points of all ways; ways[i] has its point stored in way_centroids[i].
+///
+/// The point of a closed way is inside of its polygon, the point of an open way is the
+/// middle of the line, cf. `osmflat::compute_way_centroid`.
+    #[inline]
+    pub fn way_centroids(&self) -> Option<&[super::osm::Centroid]> {
+        self.way_centroids
+    }
+
+    /// Representative points of all relations; relations[i] has its point stored in
+/// relation_centroids[i], cf. `osmflat::compute_relation_centroid`.
+    #[inline]
+    pub fn relation_centroids(&self) -> Option<&[super::osm::Centroid]> {
+        self.relation_centroids
+    }
+
+}
+
+impl ::std::fmt::Debug for Osm {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("Osm")
+            .field("header", &self.header())
+            .field("nodes", &self.nodes())
+            .field("ways", &self.ways())
+            .field("relations", &self.relations())
+            .field("relation_members", &self.relation_members())
+            .field("tags", &self.tags())
+            .field("tags_index", &self.tags_index())
+            .field("nodes_index", &self.nodes_index())
+            .field("stringtable", &self.stringtable())
+            .field("ids", &self.ids())
+            .field("history", &self.history())
+            .field("spatial_index", &self.spatial_index())
+            .field("inverted_index", &self.inverted_index())
+            .field("provenance", &self.provenance())
+            .field("way_bboxes", &self.way_bboxes())
+            .field("relation_bboxes", &self.relation_bboxes())
+            .field("tagged_nodes", &self.tagged_nodes())
+            .field("changesets", &self.changesets())
+            .field("checksums", &self.checksums())
+            .field("id_index", &self.id_index())
+            .field("node_ways", &self.node_ways())
+            .field("node_relations", &self.node_relations())
+            .field("way_relations", &self.way_relations())
+            .field("relation_relations", &self.relation_relations())
+            .field("areas", &self.areas())
+            .field("stringtable_offsets", &self.stringtable_offsets())
+            .field("node_coords", &self.node_coords())
+            .field("elevation", &self.elevation())
+            .field("tile_index", &self.tile_index())
+            .field("admin_hierarchy", &self.admin_hierarchy())
+            .field("tagged_node_bits", &self.tagged_node_bits())
+            .field("tagged_way_bits", &self.tagged_way_bits())
+            .field("tag_numbers", &self.tag_numbers())
+            .field("roles", &self.roles())
+            .field("way_centroids", &self.way_centroids())
+            .field("relation_centroids", &self.relation_centroids())
+            .finish()
+    }
+}
+
+impl Osm {
+    pub fn open(storage: flatdata::StorageHandle)
+        -> ::std::result::Result<Self, flatdata::ResourceStorageError>
+    {
+        #[allow(unused_imports)]
+        use flatdata::SliceExt;
+        #[allow(unused_variables)]
+        use flatdata::ResourceStorageError as Error;
+        // extend lifetime since Rust cannot know that we reference a cache here
+        #[allow(unused_variables)]
+        let extend = |x : Result<&[u8], Error>| -> Result<&'static [u8], Error> {x.map(|x| unsafe{std::mem::transmute(x)})};
+
+        storage.read(&Self::signature_name("Osm"), schema::osm::OSM)?;
+
+        let header = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("header", schema::osm::resources::HEADER));
+            check("header", |_| 0, max_size, resource.and_then(|x| super::osm::Header::from_bytes_slice(x)))?
+        };
+        let nodes = {
+            use flatdata::check_resource as check;
+            let max_size = Some(1099511627776);
+            let resource = extend(storage.read("nodes", schema::osm::resources::NODES));
+            check("nodes", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Node]>::from_bytes(x)))?
+        };
+        let ways = {
+            use flatdata::check_resource as check;
+            let max_size = Some(1099511627776);
+            let resource = extend(storage.read("ways", schema::osm::resources::WAYS));
+            check("ways", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Way]>::from_bytes(x)))?
+        };
+        let relations = {
+            use flatdata::check_resource as check;
+            let max_size = Some(1099511627776);
+            let resource = extend(storage.read("relations", schema::osm::resources::RELATIONS));
+            check("relations", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Relation]>::from_bytes(x)))?
+        };
+        let relation_members = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let index_schema = &format!("index({})", schema::osm::resources::RELATION_MEMBERS);
+            let index = extend(storage.read("relation_members_index", &index_schema));
+            let data = extend(storage.read("relation_members", schema::osm::resources::RELATION_MEMBERS));
+            let result = match (index, data) {
+                (Ok(index), Ok(data)) => {
+                    Ok(flatdata::MultiArrayView::new(
+                        <&[super::_builtin::multivector::IndexType40]>::from_bytes(index)?,
+                        data
+                    ))
+                }
+                // is resource completely missing?
+                (Err(Error::Missing), Err(Error::Missing))  => Err(Error::Missing),
+                // is resource partially missing / broken -> extract best error to propagate
+                (Ok(_), Err(Error::Missing)) | (Err(Error::Missing), Ok(_)) => Err(Error::MissingData),
+                (Err(Error::Missing), Err(x)) | (Err(x), Err(Error::Missing)) => {return Err(x);}
+                (_, Err(x)) | (Err(x), _) => {return Err(x);}
+            };
+            check("relation_members", |r| r.len(), max_size, result)?
+        };
+        let tags = {
+            use flatdata::check_resource as check;
+            let max_size = Some(1099511627776);
+            let resource = extend(storage.read("tags", schema::osm::resources::TAGS));
+            check("tags", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Tag]>::from_bytes(x)))?
+        };
+        let tags_index = {
+            use flatdata::check_resource as check;
+            let max_size = Some(1099511627776);
+            let resource = extend(storage.read("tags_index", schema::osm::resources::TAGS_INDEX));
+            check("tags_index", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::TagIndex]>::from_bytes(x)))?
+        };
+        let nodes_index = {
+            use flatdata::check_resource as check;
+            let max_size = Some(1099511627776);
+            let resource = extend(storage.read("nodes_index", schema::osm::resources::NODES_INDEX));
+            check("nodes_index", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::NodeIndex]>::from_bytes(x)))?
+        };
+        let stringtable = {
+            use flatdata::check_resource as check;
+            let max_size = Some(1099511627776);
+            let resource = extend(storage.read("stringtable", schema::osm::resources::STRINGTABLE));
+            check("stringtable", |r| r.len(), max_size, resource.map(|x| flatdata::RawData::new(x)))?
+        };
+        let ids = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            check("ids", |_| 0, max_size, super::osm::Ids::open(storage.subdir("ids")))?
+        };
+        let history = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            check("history", |_| 0, max_size, super::osm::History::open(storage.subdir("history")))?
+        };
+        let spatial_index = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            check("spatial_index", |_| 0, max_size, super::osm::SpatialIndex::open(storage.subdir("spatial_index")))?
+        };
+        let inverted_index = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            check("inverted_index", |_| 0, max_size, super::osm::InvertedIndex::open(storage.subdir("inverted_index")))?
+        };
+        let provenance = {
+            use flatdata::check_optional_resource as check;
+            let max_size = Some(1099511627776);
+            let resource = extend(storage.read("provenance", schema::osm::resources::PROVENANCE));
+            check("provenance", |r| r.len(), max_size, resource.map(|x| flatdata::RawData::new(x)))?
+        };
+        let way_bboxes = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("way_bboxes", schema::osm::resources::WAY_BBOXES));
+            check("way_bboxes", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::BoundingBox]>::from_bytes(x)))?
+        };
+        let relation_bboxes = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("relation_bboxes", schema::osm::resources::RELATION_BBOXES));
+            check("relation_bboxes", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::BoundingBox]>::from_bytes(x)))?
+        };
+        let tagged_nodes = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("tagged_nodes", schema::osm::resources::TAGGED_NODES));
+            check("tagged_nodes", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::EntityIndex]>::from_bytes(x)))?
+        };
+        let changesets = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            check("changesets", |_| 0, max_size, super::osm::Changesets::open(storage.subdir("changesets")))?
+        };
+        let checksums = {
+            use flatdata::check_optional_resource as check;
+            let max_size = Some(1099511627776);
+            let resource = extend(storage.read("checksums", schema::osm::resources::CHECKSUMS));
+            check("checksums", |r| r.len(), max_size, resource.map(|x| flatdata::RawData::new(x)))?
+        };
+        let id_index = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            check("id_index", |_| 0, max_size, super::osm::IdIndex::open(storage.subdir("id_index")))?
+        };
+        let node_ways = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            check("node_ways", |_| 0, max_size, super::osm::ReverseIndex::open(storage.subdir("node_ways")))?
+        };
+        let node_relations = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            check("node_relations", |_| 0, max_size, super::osm::ReverseIndex::open(storage.subdir("node_relations")))?
+        };
+        let way_relations = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            check("way_relations", |_| 0, max_size, super::osm::ReverseIndex::open(storage.subdir("way_relations")))?
+        };
+        let relation_relations = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            check("relation_relations", |_| 0, max_size, super::osm::ReverseIndex::open(storage.subdir("relation_relations")))?
+        };
+        let areas = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            check("areas", |_| 0, max_size, super::osm::Areas::open(storage.subdir("areas")))?
+        };
+        let stringtable_offsets = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("stringtable_offsets", schema::osm::resources::STRINGTABLE_OFFSETS));
+            check("stringtable_offsets", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::StringOffset]>::from_bytes(x)))?
+        };
+        let node_coords = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("node_coords", schema::osm::resources::NODE_COORDS));
+            check("node_coords", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::NodeCoord]>::from_bytes(x)))?
+        };
+        let elevation = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("elevation", schema::osm::resources::ELEVATION));
+            check("elevation", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Elevation]>::from_bytes(x)))?
+        };
+        let tile_index = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            check("tile_index", |_| 0, max_size, super::osm::TileIndex::open(storage.subdir("tile_index")))?
+        };
+        let admin_hierarchy = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            check("admin_hierarchy", |_| 0, max_size, super::osm::AdminHierarchy::open(storage.subdir("admin_hierarchy")))?
+        };
+        let tagged_node_bits = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("tagged_node_bits", schema::osm::resources::TAGGED_NODE_BITS));
+            check("tagged_node_bits", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::BitsetWord]>::from_bytes(x)))?
+        };
+        let tagged_way_bits = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("tagged_way_bits", schema::osm::resources::TAGGED_WAY_BITS));
+            check("tagged_way_bits", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::BitsetWord]>::from_bytes(x)))?
+        };
+        let tag_numbers = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("tag_numbers", schema::osm::resources::TAG_NUMBERS));
+            check("tag_numbers", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::TagNumber]>::from_bytes(x)))?
+        };
+        let roles = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("roles", schema::osm::resources::ROLES));
+            check("roles", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::StringOffset]>::from_bytes(x)))?
+        };
+        let way_centroids = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("way_centroids", schema::osm::resources::WAY_CENTROIDS));
+            check("way_centroids", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Centroid]>::from_bytes(x)))?
+        };
+        let relation_centroids = {
+            use flatdata::check_optional_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("relation_centroids", schema::osm::resources::RELATION_CENTROIDS));
+            check("relation_centroids", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Centroid]>::from_bytes(x)))?
+        };
+
+        Ok(Self {
+            _storage: storage,
+            header,
+            nodes,
+            ways,
+            relations,
+            relation_members,
+            tags,
+            tags_index,
+            nodes_index,
+            stringtable,
+            ids,
+            history,
+            spatial_index,
+            inverted_index,
+            provenance,
+            way_bboxes,
+            relation_bboxes,
+            tagged_nodes,
+            changesets,
+            checksums,
+            id_index,
+            node_ways,
+            node_relations,
+            way_relations,
+            relation_relations,
+            areas,
+            stringtable_offsets,
+            node_coords,
+            elevation,
+            tile_index,
+            admin_hierarchy,
+            tagged_node_bits,
+            tagged_way_bits,
+            tag_numbers,
+            roles,
+            way_centroids,
+            relation_centroids,
+        })
+    }
+}
+
+/// Builder for creating [`Osm`] archives.
+///
+///[`Osm`]: struct.Osm.html
+#[derive(Clone, Debug)]
+pub struct OsmBuilder {
+    storage: flatdata::StorageHandle
+}
+
+impl OsmBuilder {
+    #[inline]
+    /// Stores [`header`] in the archive.
+    ///
+    /// [`header`]: struct.Osm.html#method.header
+    /// Stores [`header`] in the archive.
+    pub fn set_header(&self, resource: &super::osm::Header) -> ::std::io::Result<()> {
+        let data = resource.as_bytes();
+        self.storage.write("header", schema::osm::resources::HEADER, data)
+    }
+
+    #[inline]
+    /// Stores [`nodes`] in the archive.
+    ///
+    /// [`nodes`]: struct.Osm.html#method.nodes
+    pub fn set_nodes(&self, vector: &[super::osm::Node]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("nodes", schema::osm::resources::NODES, vector.as_bytes())
+    }
+
+    /// Opens [`nodes`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`nodes`]: struct.Osm.html#method.nodes
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_nodes(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Node>> {
+        flatdata::create_external_vector(&*self.storage, "nodes", schema::osm::resources::NODES)
+    }
+
+    #[inline]
+    /// Stores [`ways`] in the archive.
+    ///
+    /// [`ways`]: struct.Osm.html#method.ways
+    pub fn set_ways(&self, vector: &[super::osm::Way]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("ways", schema::osm::resources::WAYS, vector.as_bytes())
+    }
+
+    /// Opens [`ways`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`ways`]: struct.Osm.html#method.ways
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_ways(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Way>> {
+        flatdata::create_external_vector(&*self.storage, "ways", schema::osm::resources::WAYS)
+    }
+
+    #[inline]
+    /// Stores [`relations`] in the archive.
+    ///
+    /// [`relations`]: struct.Osm.html#method.relations
+    pub fn set_relations(&self, vector: &[super::osm::Relation]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("relations", schema::osm::resources::RELATIONS, vector.as_bytes())
+    }
+
+    /// Opens [`relations`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`relations`]: struct.Osm.html#method.relations
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_relations(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Relation>> {
+        flatdata::create_external_vector(&*self.storage, "relations", schema::osm::resources::RELATIONS)
+    }
+
+    /// Opens [`relation_members`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the multivector until the [`MultiVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`relation_members`]: struct.Osm.html#method.relation_members
+    /// [`MultiVector::close`]: flatdata/struct.MultiVector.html#method.close
+    #[inline]
+    pub fn start_relation_members(&self) -> ::std::io::Result<flatdata::MultiVector<RelationMembers>> {
+        flatdata::create_multi_vector(&*self.storage, "relation_members", schema::osm::resources::RELATION_MEMBERS)
+    }
+
+    #[inline]
+    /// Stores [`tags`] in the archive.
+    ///
+    /// [`tags`]: struct.Osm.html#method.tags
+    pub fn set_tags(&self, vector: &[super::osm::Tag]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("tags", schema::osm::resources::TAGS, vector.as_bytes())
+    }
+
+    /// Opens [`tags`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`tags`]: struct.Osm.html#method.tags
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_tags(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Tag>> {
+        flatdata::create_external_vector(&*self.storage, "tags", schema::osm::resources::TAGS)
+    }
+
+    #[inline]
+    /// Stores [`tags_index`] in the archive.
+    ///
+    /// [`tags_index`]: struct.Osm.html#method.tags_index
+    pub fn set_tags_index(&self, vector: &[super::osm::TagIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("tags_index", schema::osm::resources::TAGS_INDEX, vector.as_bytes())
+    }
+
+    /// Opens [`tags_index`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`tags_index`]: struct.Osm.html#method.tags_index
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_tags_index(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::TagIndex>> {
+        flatdata::create_external_vector(&*self.storage, "tags_index", schema::osm::resources::TAGS_INDEX)
+    }
+
+    #[inline]
+    /// Stores [`nodes_index`] in the archive.
+    ///
+    /// [`nodes_index`]: struct.Osm.html#method.nodes_index
+    pub fn set_nodes_index(&self, vector: &[super::osm::NodeIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("nodes_index", schema::osm::resources::NODES_INDEX, vector.as_bytes())
+    }
+
+    /// Opens [`nodes_index`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`nodes_index`]: struct.Osm.html#method.nodes_index
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_nodes_index(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::NodeIndex>> {
+        flatdata::create_external_vector(&*self.storage, "nodes_index", schema::osm::resources::NODES_INDEX)
+    }
+
+    /// Stores [`stringtable`] in the archive.
+    ///
+    /// [`stringtable`]: struct.Osm.html#method.stringtable
+    #[inline]
+    pub fn set_stringtable(&self, data: &[u8]) -> ::std::io::Result<()> {
+        self.storage.write("stringtable", schema::osm::resources::STRINGTABLE, data)
+    }
+
+    /// Stores [`ids`] in the archive.
+    ///
+    /// [`ids`]: struct.Osm.html#method.ids
+    #[inline]
+    pub fn ids(&self) -> Result<super::osm::IdsBuilder, flatdata::ResourceStorageError> {
+        let storage = self.storage.subdir("ids");
+        super::osm::IdsBuilder::new(storage)
+    }
+
+    /// Stores [`history`] in the archive.
+    ///
+    /// [`history`]: struct.Osm.html#method.history
+    #[inline]
+    pub fn history(&self) -> Result<super::osm::HistoryBuilder, flatdata::ResourceStorageError> {
+        let storage = self.storage.subdir("history");
+        super::osm::HistoryBuilder::new(storage)
+    }
+
+    /// Stores [`spatial_index`] in the archive.
+    ///
+    /// [`spatial_index`]: struct.Osm.html#method.spatial_index
+    #[inline]
+    pub fn spatial_index(&self) -> Result<super::osm::SpatialIndexBuilder, flatdata::ResourceStorageError> {
+        let storage = self.storage.subdir("spatial_index");
+        super::osm::SpatialIndexBuilder::new(storage)
+    }
+
+    /// Stores [`inverted_index`] in the archive.
+    ///
+    /// [`inverted_index`]: struct.Osm.html#method.inverted_index
+    #[inline]
+    pub fn inverted_index(&self) -> Result<super::osm::InvertedIndexBuilder, flatdata::ResourceStorageError> {
+        let storage = self.storage.subdir("inverted_index");
+        super::osm::InvertedIndexBuilder::new(storage)
+    }
+
+    /// Stores [`provenance`] in the archive.
+    ///
+    /// [`provenance`]: struct.Osm.html#method.provenance
+    #[inline]
+    pub fn set_provenance(&self, data: &[u8]) -> ::std::io::Result<()> {
+        self.storage.write("provenance", schema::osm::resources::PROVENANCE, data)
+    }
+
+    #[inline]
+    /// Stores [`way_bboxes`] in the archive.
+    ///
+    /// [`way_bboxes`]: struct.Osm.html#method.way_bboxes
+    pub fn set_way_bboxes(&self, vector: &[super::osm::BoundingBox]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("way_bboxes", schema::osm::resources::WAY_BBOXES, vector.as_bytes())
+    }
+
+    /// Opens [`way_bboxes`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`way_bboxes`]: struct.Osm.html#method.way_bboxes
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_way_bboxes(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::BoundingBox>> {
+        flatdata::create_external_vector(&*self.storage, "way_bboxes", schema::osm::resources::WAY_BBOXES)
+    }
+
+    #[inline]
+    /// Stores [`relation_bboxes`] in the archive.
+    ///
+    /// [`relation_bboxes`]: struct.Osm.html#method.relation_bboxes
+    pub fn set_relation_bboxes(&self, vector: &[super::osm::BoundingBox]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("relation_bboxes", schema::osm::resources::RELATION_BBOXES, vector.as_bytes())
+    }
+
+    /// Opens [`relation_bboxes`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`relation_bboxes`]: struct.Osm.html#method.relation_bboxes
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_relation_bboxes(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::BoundingBox>> {
+        flatdata::create_external_vector(&*self.storage, "relation_bboxes", schema::osm::resources::RELATION_BBOXES)
+    }
+
+    #[inline]
+    /// Stores [`tagged_nodes`] in the archive.
+    ///
+    /// [`tagged_nodes`]: struct.Osm.html#method.tagged_nodes
+    pub fn set_tagged_nodes(&self, vector: &[super::osm::EntityIndex]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("tagged_nodes", schema::osm::resources::TAGGED_NODES, vector.as_bytes())
+    }
+
+    /// Opens [`tagged_nodes`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`tagged_nodes`]: struct.Osm.html#method.tagged_nodes
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_tagged_nodes(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::EntityIndex>> {
+        flatdata::create_external_vector(&*self.storage, "tagged_nodes", schema::osm::resources::TAGGED_NODES)
+    }
+
+    /// Stores [`changesets`] in the archive.
+    ///
+    /// [`changesets`]: struct.Osm.html#method.changesets
+    #[inline]
+    pub fn changesets(&self) -> Result<super::osm::ChangesetsBuilder, flatdata::ResourceStorageError> {
+        let storage = self.storage.subdir("changesets");
+        super::osm::ChangesetsBuilder::new(storage)
+    }
+
+    /// Stores [`checksums`] in the archive.
+    ///
+    /// [`checksums`]: struct.Osm.html#method.checksums
+    #[inline]
+    pub fn set_checksums(&self, data: &[u8]) -> ::std::io::Result<()> {
+        self.storage.write("checksums", schema::osm::resources::CHECKSUMS, data)
+    }
+
+    /// Stores [`id_index`] in the archive.
+    ///
+    /// [`id_index`]: struct.Osm.html#method.id_index
+    #[inline]
+    pub fn id_index(&self) -> Result<super::osm::IdIndexBuilder, flatdata::ResourceStorageError> {
+        let storage = self.storage.subdir("id_index");
+        super::osm::IdIndexBuilder::new(storage)
+    }
+
+    /// Stores [`node_ways`] in the archive.
+    ///
+    /// [`node_ways`]: struct.Osm.html#method.node_ways
+    #[inline]
+    pub fn node_ways(&self) -> Result<super::osm::ReverseIndexBuilder, flatdata::ResourceStorageError> {
+        let storage = self.storage.subdir("node_ways");
+        super::osm::ReverseIndexBuilder::new(storage)
+    }
+
+    /// Stores [`node_relations`] in the archive.
+    ///
+    /// [`node_relations`]: struct.Osm.html#method.node_relations
+    #[inline]
+    pub fn node_relations(&self) -> Result<super::osm::ReverseIndexBuilder, flatdata::ResourceStorageError> {
+        let storage = self.storage.subdir("node_relations");
+        super::osm::ReverseIndexBuilder::new(storage)
+    }
+
+    /// Stores [`way_relations`] in the archive.
+    ///
+    /// [`way_relations`]: struct.Osm.html#method.way_relations
+    #[inline]
+    pub fn way_relations(&self) -> Result<super::osm::ReverseIndexBuilder, flatdata::ResourceStorageError> {
+        let storage = self.storage.subdir("way_relations");
+        super::osm::ReverseIndexBuilder::new(storage)
+    }
+
+    /// Stores [`relation_relations`] in the archive.
+    ///
+    /// [`relation_relations`]: struct.Osm.html#method.relation_relations
+    #[inline]
+    pub fn relation_relations(&self) -> Result<super::osm::ReverseIndexBuilder, flatdata::ResourceStorageError> {
+        let storage = self.storage.subdir("relation_relations");
+        super::osm::ReverseIndexBuilder::new(storage)
+    }
+
+    /// Stores [`areas`] in the archive.
+    ///
+    /// [`areas`]: struct.Osm.html#method.areas
+    #[inline]
+    pub fn areas(&self) -> Result<super::osm::AreasBuilder, flatdata::ResourceStorageError> {
+        let storage = self.storage.subdir("areas");
+        super::osm::AreasBuilder::new(storage)
+    }
+
+    #[inline]
+    /// Stores [`stringtable_offsets`] in the archive.
+    ///
+    /// [`stringtable_offsets`]: struct.Osm.html#method.stringtable_offsets
+    pub fn set_stringtable_offsets(&self, vector: &[super::osm::StringOffset]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("stringtable_offsets", schema::osm::resources::STRINGTABLE_OFFSETS, vector.as_bytes())
+    }
+
+    /// Opens [`stringtable_offsets`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`stringtable_offsets`]: struct.Osm.html#method.stringtable_offsets
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_stringtable_offsets(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::StringOffset>> {
+        flatdata::create_external_vector(&*self.storage, "stringtable_offsets", schema::osm::resources::STRINGTABLE_OFFSETS)
+    }
+
+    #[inline]
+    /// Stores [`node_coords`] in the archive.
+    ///
+    /// [`node_coords`]: struct.Osm.html#method.node_coords
+    pub fn set_node_coords(&self, vector: &[super::osm::NodeCoord]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("node_coords", schema::osm::resources::NODE_COORDS, vector.as_bytes())
+    }
+
+    /// Opens [`node_coords`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`node_coords`]: struct.Osm.html#method.node_coords
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_node_coords(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::NodeCoord>> {
+        flatdata::create_external_vector(&*self.storage, "node_coords", schema::osm::resources::NODE_COORDS)
+    }
+
+    #[inline]
+    /// Stores [`elevation`] in the archive.
+    ///
+    /// [`elevation`]: struct.Osm.html#method.elevation
+    pub fn set_elevation(&self, vector: &[super::osm::Elevation]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("elevation", schema::osm::resources::ELEVATION, vector.as_bytes())
+    }
+
+    /// Opens [`elevation`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`elevation`]: struct.Osm.html#method.elevation
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_elevation(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Elevation>> {
+        flatdata::create_external_vector(&*self.storage, "elevation", schema::osm::resources::ELEVATION)
+    }
+
+    /// Stores [`tile_index`] in the archive.
+    ///
+    /// [`tile_index`]: struct.Osm.html#method.tile_index
+    #[inline]
+    pub fn tile_index(&self) -> Result<super::osm::TileIndexBuilder, flatdata::ResourceStorageError> {
+        let storage = self.storage.subdir("tile_index");
+        super::osm::TileIndexBuilder::new(storage)
+    }
+
+    /// Stores [`admin_hierarchy`] in the archive.
+    ///
+    /// [`admin_hierarchy`]: struct.Osm.html#method.admin_hierarchy
+    #[inline]
+    pub fn admin_hierarchy(&self) -> Result<super::osm::AdminHierarchyBuilder, flatdata::ResourceStorageError> {
+        let storage = self.storage.subdir("admin_hierarchy");
+        super::osm::AdminHierarchyBuilder::new(storage)
+    }
+
+    #[inline]
+    /// Stores [`tagged_node_bits`] in the archive.
+    ///
+    /// [`tagged_node_bits`]: struct.Osm.html#method.tagged_node_bits
+    pub fn set_tagged_node_bits(&self, vector: &[super::osm::BitsetWord]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("tagged_node_bits", schema::osm::resources::TAGGED_NODE_BITS, vector.as_bytes())
+    }
+
+    /// Opens [`tagged_node_bits`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`tagged_node_bits`]: struct.Osm.html#method.tagged_node_bits
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_tagged_node_bits(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::BitsetWord>> {
+        flatdata::create_external_vector(&*self.storage, "tagged_node_bits", schema::osm::resources::TAGGED_NODE_BITS)
+    }
+
+    #[inline]
+    /// Stores [`tagged_way_bits`] in the archive.
+    ///
+    /// [`tagged_way_bits`]: struct.Osm.html#method.tagged_way_bits
+    pub fn set_tagged_way_bits(&self, vector: &[super::osm::BitsetWord]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("tagged_way_bits", schema::osm::resources::TAGGED_WAY_BITS, vector.as_bytes())
+    }
+
+    /// Opens [`tagged_way_bits`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`tagged_way_bits`]: struct.Osm.html#method.tagged_way_bits
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_tagged_way_bits(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::BitsetWord>> {
+        flatdata::create_external_vector(&*self.storage, "tagged_way_bits", schema::osm::resources::TAGGED_WAY_BITS)
+    }
+
+    #[inline]
+    /// Stores [`tag_numbers`] in the archive.
+    ///
+    /// [`tag_numbers`]: struct.Osm.html#method.tag_numbers
+    pub fn set_tag_numbers(&self, vector: &[super::osm::TagNumber]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("tag_numbers", schema::osm::resources::TAG_NUMBERS, vector.as_bytes())
+    }
+
+    /// Opens [`tag_numbers`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`tag_numbers`]: struct.Osm.html#method.tag_numbers
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_tag_numbers(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::TagNumber>> {
+        flatdata::create_external_vector(&*self.storage, "tag_numbers", schema::osm::resources::TAG_NUMBERS)
+    }
+
+    #[inline]
+    /// Stores [`roles`] in the archive.
+    ///
+    /// [`roles`]: struct.Osm.html#method.roles
+    pub fn set_roles(&self, vector: &[super::osm::StringOffset]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("roles", schema::osm::resources::ROLES, vector.as_bytes())
+    }
+
+    /// Opens [`roles`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`roles`]: struct.Osm.html#method.roles
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_roles(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::StringOffset>> {
+        flatdata::create_external_vector(&*self.storage, "roles", schema::osm::resources::ROLES)
+    }
+
+    #[inline]
+    /// Stores [`way_centroids`] in the archive.
+    ///
+    /// [`way_centroids`]: struct.Osm.html#method.way_centroids
+    pub fn set_way_centroids(&self, vector: &[super::osm::Centroid]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("way_centroids", schema::osm::resources::WAY_CENTROIDS, vector.as_bytes())
+    }
+
+    /// Opens [`way_centroids`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`way_centroids`]: struct.Osm.html#method.way_centroids
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_way_centroids(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Centroid>> {
+        flatdata::create_external_vector(&*self.storage, "way_centroids", schema::osm::resources::WAY_CENTROIDS)
+    }
+
+    #[inline]
+    /// Stores [`relation_centroids`] in the archive.
+    ///
+    /// [`relation_centroids`]: struct.Osm.html#method.relation_centroids
+    pub fn set_relation_centroids(&self, vector: &[super::osm::Centroid]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("relation_centroids", schema::osm::resources::RELATION_CENTROIDS, vector.as_bytes())
+    }
+
+    /// Opens [`relation_centroids`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`relation_centroids`]: struct.Osm.html#method.relation_centroids
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_relation_centroids(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Centroid>> {
+        flatdata::create_external_vector(&*self.storage, "relation_centroids", schema::osm::resources::RELATION_CENTROIDS)
+    }
+
+}
+
+impl OsmBuilder {
+    pub fn new(
+        storage: flatdata::StorageHandle,
+    ) -> Result<Self, flatdata::ResourceStorageError> {
+        flatdata::create_archive("Osm", schema::osm::OSM, &storage)?;
+        Ok(Self { storage })
+    }
+}
+
+/// A Web Mercator tile of an archive split into tiles.
+///
+/// The archive of the tile is stored in the directory `<zoom>/<x>/<y>` next to the
+/// [`Tiles`] archive.
+#[repr(transparent)]
+#[derive(Clone)]
+pub struct Tile {
+    data: [u8; 24],
+}
+
+impl Tile {
+    /// Unsafe since the struct might not be self-contained
+    pub unsafe fn new_unchecked( ) -> Self {
+        Self{data : [0; 24]}
+    }
+}
+
+impl flatdata::Struct for Tile {
+    unsafe fn create_unchecked( ) -> Self {
+        Self{data : [0; 24]}
+    }
+
+    const SIZE_IN_BYTES: usize = 24;
+    const IS_OVERLAPPING_WITH_NEXT : bool = false;
+}
+
+impl Tile {
+    pub fn new( ) -> Self {
+        Self{data : [0; 24]}
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes(data: &[u8; 24]) -> &Self {
+        // Safety: This is safe since Tile is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array of matching size
+    pub fn from_bytes_mut(data: &mut [u8; 24]) -> &mut Self {
+        // Safety: This is safe since Tile is repr(transparent)
+        unsafe{ std::mem::transmute( data ) }
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice(data: &[u8]) -> Result<&Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 24 {
+            assert_eq!(data.len(), 24);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *const [u8; 24];
+        // Safety: We checked length before
+        Ok(Self::from_bytes(unsafe { &*ptr }))
+    }
+
+    /// Create reference from byte array
+    pub fn from_bytes_slice_mut(data: &mut [u8]) -> Result<&mut Self, flatdata::ResourceStorageError> {
+        // We cannot rely on TryFrom here, since it does not yet support > 33 bytes
+        if data.len() < 24 {
+            assert_eq!(data.len(), 24);
+            return Err(flatdata::ResourceStorageError::UnexpectedDataSize);
+        }
+        let ptr = data.as_ptr() as *mut [u8; 24];
+        // Safety: We checked length before
+        Ok(Self::from_bytes_mut(unsafe { &mut *ptr }))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 24] {
+        &self.data
+    }
+}
+
+impl Default for Tile {
+    fn default( ) -> Self {
+        Self::new( )
+    }
+}
+
+unsafe impl flatdata::NoOverlap for Tile {}
+
+impl Tile {
+    /// Zoom level of the tile
+    #[inline]
+    pub fn zoom(&self) -> u8 {
+        let value = flatdata_read_bytes!(u8, self.data.as_ptr(), 0, 8);
+        unsafe { std::mem::transmute::<u8, u8>(value) }
+    }
+
+    /// Column of the tile, counted from the west
+    #[inline]
+    pub fn x(&self) -> u32 {
+        let value = flatdata_read_bytes!(u32, self.data.as_ptr(), 8, 32);
+        unsafe { std::mem::transmute::<u32, u32>(value) }
+    }
+
+    /// Row of the tile, counted from the north
+    #[inline]
+    pub fn y(&self) -> u32 {
+        let value = flatdata_read_bytes!(u32, self.data.as_ptr(), 40, 32);
+        unsafe { std::mem::transmute::<u32, u32>(value) }
+    }
+
+    /// Number of nodes in the archive of the tile
+    #[inline]
+    pub fn num_nodes(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 72, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+    /// Number of ways in the archive of the tile
+    #[inline]
+    pub fn num_ways(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 112, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+    /// Number of relations in the archive of the tile
+    #[inline]
+    pub fn num_relations(&self) -> u64 {
+        let value = flatdata_read_bytes!(u64, self.data.as_ptr(), 152, 40);
+        unsafe { std::mem::transmute::<u64, u64>(value) }
+    }
+
+}
+
+impl std::fmt::Debug for Tile {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Tile")
+            .field("zoom", &self.zoom())
+            .field("x", &self.x())
+            .field("y", &self.y())
+            .field("num_nodes", &self.num_nodes())
+            .field("num_ways", &self.num_ways())
+            .field("num_relations", &self.num_relations())
+            .finish()
+    }
+}
+
+impl std::cmp::PartialEq for Tile {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.zoom() == other.zoom() &&        self.x() == other.x() &&        self.y() == other.y() &&        self.num_nodes() == other.num_nodes() &&        self.num_ways() == other.num_ways() &&        self.num_relations() == other.num_relations()     }
+}
+
+impl Tile {
+    /// Zoom level of the tile
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_zoom(&mut self, value: u8) {
+        flatdata_write_bytes!(u8; value, self.data, 0, 8)
+    }
+
+    /// Column of the tile, counted from the west
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_x(&mut self, value: u32) {
+        flatdata_write_bytes!(u32; value, self.data, 8, 32)
+    }
+
+    /// Row of the tile, counted from the north
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_y(&mut self, value: u32) {
+        flatdata_write_bytes!(u32; value, self.data, 40, 32)
+    }
+
+    /// Number of nodes in the archive of the tile
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_num_nodes(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 72, 40)
+    }
+
+    /// Number of ways in the archive of the tile
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_num_ways(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 112, 40)
+    }
+
+    /// Number of relations in the archive of the tile
+    #[inline]
+    #[allow(missing_docs)]
+    pub fn set_num_relations(&mut self, value: u64) {
+        flatdata_write_bytes!(u64; value, self.data, 152, 40)
+    }
+
+
+    /// Copies the data from `other` into this struct.
+    #[inline]
+    pub fn fill_from(&mut self, other: &Tile) {
+        self.set_zoom(other.zoom());
+        self.set_x(other.x());
+        self.set_y(other.y());
+        self.set_num_nodes(other.num_nodes());
+        self.set_num_ways(other.num_ways());
+        self.set_num_relations(other.num_relations());
+    }
+}
+/// An archive split into Web Mercator tiles of one zoom level, cf.
+/// `osmflatc split-tiles`.
+///
+/// Each tile is a complete [`Osm`] archive containing the nodes in the tile, the
+/// ways and relations whose bounding boxes intersect the tile, and the nodes of
+/// these ways. Entities may therefore be contained in several tiles; their ids
+/// are stored in the `ids` subarchive of each tile.
+#[derive(Clone)]
+pub struct Tiles {
+    _storage: flatdata::StorageHandle,
+    tiles : &'static [super::osm::Tile],
+}
+
+impl Tiles {
+    fn signature_name(archive_name: &str) -> String {
+        format!("{}.archive", archive_name)
+    }
+
+    /// Tiles sorted by `x` and `y`
+    #[inline]
+    pub fn tiles(&self) -> &[super::osm::Tile] {
+        self.tiles
+    }
+
+}
+
+impl ::std::fmt::Debug for Tiles {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("Tiles")
+            .field("tiles", &self.tiles())
+            .finish()
+    }
+}
+
+impl Tiles {
+    pub fn open(storage: flatdata::StorageHandle)
+        -> ::std::result::Result<Self, flatdata::ResourceStorageError>
+    {
+        #[allow(unused_imports)]
+        use flatdata::SliceExt;
+        #[allow(unused_variables)]
+        use flatdata::ResourceStorageError as Error;
+        // extend lifetime since Rust cannot know that we reference a cache here
+        #[allow(unused_variables)]
+        let extend = |x : Result<&[u8], Error>| -> Result<&'static [u8], Error> {x.map(|x| unsafe{std::mem::transmute(x)})};
+
+        storage.read(&Self::signature_name("Tiles"), schema::tiles::TILES)?;
+
+        let tiles = {
+            use flatdata::check_resource as check;
+            let max_size = None;
+            let resource = extend(storage.read("tiles", schema::tiles::resources::TILES));
+            check("tiles", |r| r.len(), max_size, resource.and_then(|x| <&[super::osm::Tile]>::from_bytes(x)))?
+        };
+
+        Ok(Self {
+            _storage: storage,
+            tiles,
+        })
+    }
+}
+
+/// Builder for creating [`Tiles`] archives.
+///
+///[`Tiles`]: struct.Tiles.html
+#[derive(Clone, Debug)]
+pub struct TilesBuilder {
+    storage: flatdata::StorageHandle
+}
+
+impl TilesBuilder {
+    #[inline]
+    /// Stores [`tiles`] in the archive.
+    ///
+    /// [`tiles`]: struct.Tiles.html#method.tiles
+    pub fn set_tiles(&self, vector: &[super::osm::Tile]) -> ::std::io::Result<()> {
+        use flatdata::SliceExt;
+        self.storage.write("tiles", schema::tiles::resources::TILES, vector.as_bytes())
+    }
+
+    /// Opens [`tiles`] in the archive for buffered writing.
+    ///
+    /// Elements can be added to the vector until the [`ExternalVector::close`] method
+    /// is called. To flush the data fully into the archive, this method must be called
+    /// in the end.
+    ///
+    /// [`tiles`]: struct.Tiles.html#method.tiles
+    /// [`ExternalVector::close`]: flatdata/struct.ExternalVector.html#method.close
+    #[inline]
+    pub fn start_tiles(&self) -> ::std::io::Result<flatdata::ExternalVector<super::osm::Tile>> {
+        flatdata::create_external_vector(&*self.storage, "tiles", schema::tiles::resources::TILES)
+    }
+
+}
+
+impl TilesBuilder {
+    pub fn new(
+        storage: flatdata::StorageHandle,
+    ) -> Result<Self, flatdata::ResourceStorageError> {
+        flatdata::create_archive("Tiles", schema::tiles::TILES, &storage)?;
+        Ok(Self { storage })
+    }
+}
+
+
+
+
+#[doc(hidden)]
+pub mod schema {
+pub mod ids {
+
+pub const IDS: &str = r#"namespace osm {
+struct Id
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive Ids
+{
+    nodes : vector< .osm.Id >;
+    ways : vector< .osm.Id >;
+    relations : vector< .osm.Id >;
+}
+}
+
+"#;
+
+pub mod resources {
+pub const NODES: &str = r#"namespace osm {
+struct Id
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive Ids
+{
+    nodes : vector< .osm.Id >;
+}
+}
+
+"#;
+pub const WAYS: &str = r#"namespace osm {
+struct Id
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive Ids
+{
+    ways : vector< .osm.Id >;
+}
+}
+
+"#;
+pub const RELATIONS: &str = r#"namespace osm {
+struct Id
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive Ids
+{
+    relations : vector< .osm.Id >;
+}
+}
+
+"#;
+}
+}
+pub mod history {
+
+pub const HISTORY: &str = r#"namespace osm {
+struct Version
+{
+    version : u32 : 32;
+    timestamp : i64 : 64;
+    visible : bool : 1;
+}
+}
+
+namespace osm {
+archive History
+{
+    nodes : vector< .osm.Version >;
+    ways : vector< .osm.Version >;
+    relations : vector< .osm.Version >;
+}
+}
+
+"#;
+
+pub mod resources {
+pub const NODES: &str = r#"namespace osm {
+struct Version
+{
+    version : u32 : 32;
+    timestamp : i64 : 64;
+    visible : bool : 1;
+}
+}
+
+namespace osm {
+archive History
+{
+    nodes : vector< .osm.Version >;
+}
+}
+
+"#;
+pub const WAYS: &str = r#"namespace osm {
+struct Version
+{
+    version : u32 : 32;
+    timestamp : i64 : 64;
+    visible : bool : 1;
+}
+}
+
+namespace osm {
+archive History
+{
+    ways : vector< .osm.Version >;
+}
+}
+
+"#;
+pub const RELATIONS: &str = r#"namespace osm {
+struct Version
+{
+    version : u32 : 32;
+    timestamp : i64 : 64;
+    visible : bool : 1;
+}
+}
+
+namespace osm {
+archive History
+{
+    relations : vector< .osm.Version >;
+}
+}
+
+"#;
+}
+}
+pub mod spatial_index {
+
+pub const SPATIAL_INDEX: &str = r#"namespace osm {
+struct SpatialBox
+{
+    left : i32 : 32;
+    bottom : i32 : 32;
+    right : i32 : 32;
+    top : i32 : 32;
+    index : u64 : 40;
+}
+}
+
+namespace osm {
+archive SpatialIndex
+{
+    nodes : vector< .osm.SpatialBox >;
+    ways : vector< .osm.SpatialBox >;
+    relations : vector< .osm.SpatialBox >;
+}
+}
+
+"#;
+
+pub mod resources {
+pub const NODES: &str = r#"namespace osm {
+struct SpatialBox
+{
+    left : i32 : 32;
+    bottom : i32 : 32;
+    right : i32 : 32;
+    top : i32 : 32;
+    index : u64 : 40;
+}
+}
+
+namespace osm {
+archive SpatialIndex
+{
+    nodes : vector< .osm.SpatialBox >;
+}
+}
+
+"#;
+pub const WAYS: &str = r#"namespace osm {
+struct SpatialBox
+{
+    left : i32 : 32;
+    bottom : i32 : 32;
+    right : i32 : 32;
+    top : i32 : 32;
+    index : u64 : 40;
+}
+}
+
+namespace osm {
+archive SpatialIndex
+{
+    ways : vector< .osm.SpatialBox >;
+}
+}
+
+"#;
+pub const RELATIONS: &str = r#"namespace osm {
+struct SpatialBox
+{
+    left : i32 : 32;
+    bottom : i32 : 32;
+    right : i32 : 32;
+    top : i32 : 32;
+    index : u64 : 40;
+}
+}
+
+namespace osm {
+archive SpatialIndex
+{
+    relations : vector< .osm.SpatialBox >;
+}
+}
+
+"#;
+}
+}
+pub mod inverted_index {
+
+pub const INVERTED_INDEX: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
+struct TagPostings
+{
+    key_idx : u64 : 40;
+    @optional( .osm.INVALID_IDX )
+    value_idx : u64 : 40;
+    first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct EntityIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive InvertedIndex
+{
+    node_tags : vector< .osm.TagPostings >;
+    nodes : vector< .osm.EntityIndex >;
+    way_tags : vector< .osm.TagPostings >;
+    ways : vector< .osm.EntityIndex >;
+    relation_tags : vector< .osm.TagPostings >;
+    relations : vector< .osm.EntityIndex >;
+}
+}
+
+"#;
+
+pub mod resources {
+pub const NODE_TAGS: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
+struct TagPostings
+{
+    key_idx : u64 : 40;
+    @optional( .osm.INVALID_IDX )
+    value_idx : u64 : 40;
+    first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive InvertedIndex
+{
+    node_tags : vector< .osm.TagPostings >;
+}
+}
+
+"#;
+pub const NODES: &str = r#"namespace osm {
+struct EntityIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive InvertedIndex
+{
+    nodes : vector< .osm.EntityIndex >;
+}
+}
+
+"#;
+pub const WAY_TAGS: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
+struct TagPostings
+{
+    key_idx : u64 : 40;
+    @optional( .osm.INVALID_IDX )
+    value_idx : u64 : 40;
+    first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive InvertedIndex
+{
+    way_tags : vector< .osm.TagPostings >;
+}
+}
+
+"#;
+pub const WAYS: &str = r#"namespace osm {
+struct EntityIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive InvertedIndex
+{
+    ways : vector< .osm.EntityIndex >;
+}
+}
+
+"#;
+pub const RELATION_TAGS: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
+struct TagPostings
+{
+    key_idx : u64 : 40;
+    @optional( .osm.INVALID_IDX )
+    value_idx : u64 : 40;
+    first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive InvertedIndex
+{
+    relation_tags : vector< .osm.TagPostings >;
+}
+}
+
+"#;
+pub const RELATIONS: &str = r#"namespace osm {
+struct EntityIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive InvertedIndex
+{
+    relations : vector< .osm.EntityIndex >;
+}
+}
+
+"#;
+}
+}
+pub mod changesets {
+
+pub const CHANGESETS: &str = r#"namespace osm {
+struct BoundingBox
+{
+    left : i32 : 32;
+    bottom : i32 : 32;
+    right : i32 : 32;
+    top : i32 : 32;
+}
+}
+
+namespace osm {
+struct Changeset
+{
+    id : u64 : 40;
+    created_at : i64 : 64;
+    closed_at : i64 : 64;
+    user_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct ChangesetTags
+{
+    @range( tags )
+    tag_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct TagIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive Changesets
+{
+    changesets : vector< .osm.Changeset >;
+    bboxes : vector< .osm.BoundingBox >;
+    @optional
+    tag_ranges : vector< .osm.ChangesetTags >;
+    @optional
+    tags_index : vector< .osm.TagIndex >;
+}
+}
+
+"#;
+
+pub mod resources {
+pub const CHANGESETS: &str = r#"namespace osm {
+struct Changeset
+{
+    id : u64 : 40;
+    created_at : i64 : 64;
+    closed_at : i64 : 64;
+    user_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive Changesets
+{
+    changesets : vector< .osm.Changeset >;
+}
+}
+
+"#;
+pub const BBOXES: &str = r#"namespace osm {
+struct BoundingBox
+{
+    left : i32 : 32;
+    bottom : i32 : 32;
+    right : i32 : 32;
+    top : i32 : 32;
+}
+}
+
+namespace osm {
+archive Changesets
+{
+    bboxes : vector< .osm.BoundingBox >;
+}
+}
+
+"#;
+pub const TAG_RANGES: &str = r#"namespace osm {
+struct ChangesetTags
+{
+    @range( tags )
+    tag_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive Changesets
+{
+    @optional
+    tag_ranges : vector< .osm.ChangesetTags >;
+}
+}
+
+"#;
+pub const TAGS_INDEX: &str = r#"namespace osm {
+struct TagIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive Changesets
+{
+    @optional
+    tags_index : vector< .osm.TagIndex >;
+}
+}
+
+"#;
+}
+}
+pub mod id_index {
+
+pub const ID_INDEX: &str = r#"namespace osm {
+struct EntityIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive IdIndex
+{
+    nodes : vector< .osm.EntityIndex >;
+    ways : vector< .osm.EntityIndex >;
+    relations : vector< .osm.EntityIndex >;
+}
+}
+
+"#;
+
+pub mod resources {
+pub const NODES: &str = r#"namespace osm {
+struct EntityIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive IdIndex
+{
+    nodes : vector< .osm.EntityIndex >;
+}
+}
+
+"#;
+pub const WAYS: &str = r#"namespace osm {
+struct EntityIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive IdIndex
+{
+    ways : vector< .osm.EntityIndex >;
+}
+}
+
+"#;
+pub const RELATIONS: &str = r#"namespace osm {
+struct EntityIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive IdIndex
+{
+    relations : vector< .osm.EntityIndex >;
+}
+}
+
+"#;
+}
+}
+pub mod reverse_index {
+
+pub const REVERSE_INDEX: &str = r#"namespace osm {
+struct ReverseIndexRange
+{
+    @range( entries )
+    first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct EntityIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive ReverseIndex
+{
+    ranges : vector< .osm.ReverseIndexRange >;
+    entries : vector< .osm.EntityIndex >;
+}
+}
+
+"#;
+
+pub mod resources {
+pub const RANGES: &str = r#"namespace osm {
+struct ReverseIndexRange
+{
+    @range( entries )
+    first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive ReverseIndex
+{
+    ranges : vector< .osm.ReverseIndexRange >;
+}
+}
+
+"#;
+pub const ENTRIES: &str = r#"namespace osm {
+struct EntityIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive ReverseIndex
+{
+    entries : vector< .osm.EntityIndex >;
+}
+}
+
+"#;
+}
+}
+pub mod areas {
+
+pub const AREAS: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
+struct Area
+{
+    @range( polygons )
+    first_polygon_idx : u64 : 40;
+    @optional( .osm.INVALID_IDX )
+    way_idx : u64 : 40;
+    @optional( .osm.INVALID_IDX )
+    relation_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct AreaPolygon
+{
+    @range( rings )
+    first_ring_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct AreaRing
+{
+    @range( nodes )
+    first_node_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct EntityIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive Areas
+{
+    areas : vector< .osm.Area >;
+    polygons : vector< .osm.AreaPolygon >;
+    rings : vector< .osm.AreaRing >;
+    nodes : vector< .osm.EntityIndex >;
+}
+}
+
+"#;
+
+pub mod resources {
+pub const AREAS: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
+struct Area
+{
+    @range( polygons )
+    first_polygon_idx : u64 : 40;
+    @optional( .osm.INVALID_IDX )
+    way_idx : u64 : 40;
+    @optional( .osm.INVALID_IDX )
+    relation_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive Areas
+{
+    areas : vector< .osm.Area >;
+}
+}
+
+"#;
+pub const POLYGONS: &str = r#"namespace osm {
+struct AreaPolygon
+{
+    @range( rings )
+    first_ring_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive Areas
+{
+    polygons : vector< .osm.AreaPolygon >;
+}
+}
+
+"#;
+pub const RINGS: &str = r#"namespace osm {
+struct AreaRing
+{
+    @range( nodes )
+    first_node_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive Areas
+{
+    rings : vector< .osm.AreaRing >;
+}
+}
+
+"#;
+pub const NODES: &str = r#"namespace osm {
+struct EntityIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive Areas
+{
+    nodes : vector< .osm.EntityIndex >;
+}
+}
+
+"#;
+}
+}
+pub mod tile_index {
+
+pub const TILE_INDEX: &str = r#"namespace osm {
+struct TileGrid
+{
+    zoom : u8 : 8;
+    min_x : u32 : 32;
+    min_y : u32 : 32;
+    width : u32 : 32;
+    height : u32 : 32;
+}
+}
+
+namespace osm {
+struct TileCell
+{
+    @range( runs )
+    first_run_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct IndexRun
+{
+    start : u64 : 40;
+    end : u64 : 40;
+}
+}
+
+namespace osm {
+archive TileIndex
+{
+    grid : .osm.TileGrid;
+    node_cells : vector< .osm.TileCell >;
+    node_runs : vector< .osm.IndexRun >;
+    way_cells : vector< .osm.TileCell >;
+    way_runs : vector< .osm.IndexRun >;
+}
+}
+
+"#;
+
+pub mod resources {
+pub const GRID: &str = r#"namespace osm {
+struct TileGrid
+{
+    zoom : u8 : 8;
+    min_x : u32 : 32;
+    min_y : u32 : 32;
+    width : u32 : 32;
+    height : u32 : 32;
+}
+}
+
+namespace osm {
+archive TileIndex
+{
+    grid : .osm.TileGrid;
+}
+}
+
+"#;
+pub const NODE_CELLS: &str = r#"namespace osm {
+struct TileCell
+{
+    @range( runs )
+    first_run_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive TileIndex
+{
+    node_cells : vector< .osm.TileCell >;
+}
+}
+
+"#;
+pub const NODE_RUNS: &str = r#"namespace osm {
+struct IndexRun
+{
+    start : u64 : 40;
+    end : u64 : 40;
+}
+}
+
+namespace osm {
+archive TileIndex
+{
+    node_runs : vector< .osm.IndexRun >;
+}
+}
+
+"#;
+pub const WAY_CELLS: &str = r#"namespace osm {
+struct TileCell
+{
+    @range( runs )
+    first_run_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive TileIndex
+{
+    way_cells : vector< .osm.TileCell >;
+}
+}
+
+"#;
+pub const WAY_RUNS: &str = r#"namespace osm {
+struct IndexRun
+{
+    start : u64 : 40;
+    end : u64 : 40;
+}
+}
+
+namespace osm {
+archive TileIndex
+{
+    way_runs : vector< .osm.IndexRun >;
+}
+}
+
+"#;
+}
+}
+pub mod admin_hierarchy {
+
+pub const ADMIN_HIERARCHY: &str = r#"namespace osm {
+struct AdminChain
+{
+    @range( boundaries )
+    first_boundary_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct EntityIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+struct AdminChainIndex
+{
+    value : u32 : 32;
+}
+}
+
+namespace osm {
+archive AdminHierarchy
+{
+    chains : vector< .osm.AdminChain >;
+    boundaries : vector< .osm.EntityIndex >;
+    node_chains : vector< .osm.AdminChainIndex >;
+}
+}
+
+"#;
+
+pub mod resources {
+pub const CHAINS: &str = r#"namespace osm {
+struct AdminChain
+{
+    @range( boundaries )
+    first_boundary_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive AdminHierarchy
+{
+    chains : vector< .osm.AdminChain >;
+}
+}
+
+"#;
+pub const BOUNDARIES: &str = r#"namespace osm {
+struct EntityIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive AdminHierarchy
+{
+    boundaries : vector< .osm.EntityIndex >;
+}
+}
+
+"#;
+pub const NODE_CHAINS: &str = r#"namespace osm {
+struct AdminChainIndex
+{
+    value : u32 : 32;
+}
+}
+
+namespace osm {
+archive AdminHierarchy
+{
+    node_chains : vector< .osm.AdminChainIndex >;
+}
+}
+
+"#;
+}
+}
+pub mod osm {
+
+pub const OSM: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
+struct Header
+{
+    coord_scale : i32 : 32;
+    bbox_left : i32 : 32;
+    bbox_right : i32 : 32;
+    bbox_top : i32 : 32;
+    bbox_bottom : i32 : 32;
+    writingprogram_idx : u64 : 40;
+    @optional( .osm.INVALID_IDX )
+    source_idx : u64 : 40;
+    replication_timestamp : i64 : 64;
+    replication_sequence_number : i64 : 64;
+    @optional( .osm.INVALID_IDX )
+    replication_base_url_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct Node
+{
+    lat : i32 : 32;
+    lon : i32 : 32;
+    @range( tags )
+    tag_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct Way
+{
+    @range( tags )
+    tag_first_idx : u64 : 40;
+    @range( refs )
+    ref_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct Relation
+{
+    @range( tags )
+    tag_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct NodeMember
+{
+    @optional( .osm.INVALID_IDX )
+    node_idx : u64 : 40;
+    role_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct WayMember
+{
+    @optional( .osm.INVALID_IDX )
+    way_idx : u64 : 40;
+    role_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct RelationMember
+{
+    @optional( .osm.INVALID_IDX )
+    relation_idx : u64 : 40;
+    role_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct Tag
+{
+    key_idx : u64 : 40;
+    value_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct TagIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+struct NodeIndex
+{
+    @optional( .osm.INVALID_IDX )
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+struct Id
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive Ids
+{
+    nodes : vector< .osm.Id >;
+    ways : vector< .osm.Id >;
+    relations : vector< .osm.Id >;
+}
+}
+
+namespace osm {
+struct Version
+{
+    version : u32 : 32;
+    timestamp : i64 : 64;
+    visible : bool : 1;
+}
+}
+
+namespace osm {
+archive History
+{
+    nodes : vector< .osm.Version >;
+    ways : vector< .osm.Version >;
+    relations : vector< .osm.Version >;
+}
+}
+
+namespace osm {
+struct SpatialBox
+{
+    left : i32 : 32;
+    bottom : i32 : 32;
+    right : i32 : 32;
+    top : i32 : 32;
+    index : u64 : 40;
+}
+}
+
+namespace osm {
+archive SpatialIndex
+{
+    nodes : vector< .osm.SpatialBox >;
+    ways : vector< .osm.SpatialBox >;
+    relations : vector< .osm.SpatialBox >;
+}
+}
+
+namespace osm {
+struct TagPostings
+{
+    key_idx : u64 : 40;
+    @optional( .osm.INVALID_IDX )
+    value_idx : u64 : 40;
+    first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct EntityIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive InvertedIndex
+{
+    node_tags : vector< .osm.TagPostings >;
+    nodes : vector< .osm.EntityIndex >;
+    way_tags : vector< .osm.TagPostings >;
+    ways : vector< .osm.EntityIndex >;
+    relation_tags : vector< .osm.TagPostings >;
+    relations : vector< .osm.EntityIndex >;
+}
+}
+
+namespace osm {
+struct BoundingBox
+{
+    left : i32 : 32;
+    bottom : i32 : 32;
+    right : i32 : 32;
+    top : i32 : 32;
+}
+}
+
+namespace osm {
+struct BoundingBox
+{
+    left : i32 : 32;
+    bottom : i32 : 32;
+    right : i32 : 32;
+    top : i32 : 32;
+}
+}
+
+namespace osm {
+struct Changeset
+{
+    id : u64 : 40;
+    created_at : i64 : 64;
+    closed_at : i64 : 64;
+    user_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct ChangesetTags
+{
+    @range( tags )
+    tag_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive Changesets
+{
+    changesets : vector< .osm.Changeset >;
+    bboxes : vector< .osm.BoundingBox >;
+    @optional
+    tag_ranges : vector< .osm.ChangesetTags >;
+    @optional
+    tags_index : vector< .osm.TagIndex >;
+}
+}
+
+namespace osm {
+archive IdIndex
+{
+    nodes : vector< .osm.EntityIndex >;
+    ways : vector< .osm.EntityIndex >;
+    relations : vector< .osm.EntityIndex >;
+}
+}
+
+namespace osm {
+struct ReverseIndexRange
+{
+    @range( entries )
+    first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive ReverseIndex
+{
+    ranges : vector< .osm.ReverseIndexRange >;
+    entries : vector< .osm.EntityIndex >;
+}
+}
+
+namespace osm {
+struct Area
+{
+    @range( polygons )
+    first_polygon_idx : u64 : 40;
+    @optional( .osm.INVALID_IDX )
+    way_idx : u64 : 40;
+    @optional( .osm.INVALID_IDX )
+    relation_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct AreaPolygon
+{
+    @range( rings )
+    first_ring_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct AreaRing
+{
+    @range( nodes )
+    first_node_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive Areas
+{
+    areas : vector< .osm.Area >;
+    polygons : vector< .osm.AreaPolygon >;
+    rings : vector< .osm.AreaRing >;
+    nodes : vector< .osm.EntityIndex >;
+}
+}
+
+namespace osm {
+struct TileGrid
+{
+    zoom : u8 : 8;
+    min_x : u32 : 32;
+    min_y : u32 : 32;
+    width : u32 : 32;
+    height : u32 : 32;
+}
+}
+
+namespace osm {
+struct TileCell
+{
+    @range( runs )
+    first_run_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct IndexRun
+{
+    start : u64 : 40;
+    end : u64 : 40;
+}
+}
+
+namespace osm {
+archive TileIndex
+{
+    grid : .osm.TileGrid;
+    node_cells : vector< .osm.TileCell >;
+    node_runs : vector< .osm.IndexRun >;
+    way_cells : vector< .osm.TileCell >;
+    way_runs : vector< .osm.IndexRun >;
+}
+}
+
+namespace osm {
+struct AdminChain
+{
+    @range( boundaries )
+    first_boundary_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct AdminChainIndex
+{
+    value : u32 : 32;
+}
+}
+
+namespace osm {
+archive AdminHierarchy
+{
+    chains : vector< .osm.AdminChain >;
+    boundaries : vector< .osm.EntityIndex >;
+    node_chains : vector< .osm.AdminChainIndex >;
+}
+}
+
+namespace osm {
+struct NodeCoord
+{
+    lat : i32 : 32;
+    lon : i32 : 32;
+}
+}
+
+namespace osm {
+struct StringOffset
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+const i16 NO_ELEVATION = -32768;
+}
+
+namespace osm {
+struct Elevation
+{
+    @optional( .osm.NO_ELEVATION )
+    value : i16 : 16;
+}
+}
+
+namespace osm {
+struct BitsetWord
+{
+    value : u64 : 64;
+}
+}
+
+namespace osm {
+const i64 NO_NUMBER = -9223372036854775808;
+}
+
+namespace osm {
+struct TagNumber
+{
+    @optional( .osm.NO_NUMBER )
+    value : i64 : 64;
+}
+}
+
+namespace osm {
+const i32 NO_CENTROID = -2147483648;
+}
+
+namespace osm {
+struct Centroid
+{
+    @optional( .osm.NO_CENTROID )
+    lat : i32 : 32;
+    lon : i32 : 32;
+}
+}
+
+namespace osm {
+@bound_implicitly( Relations : .osm.Osm.relations, .osm.Osm.relation_members )
+archive Osm
+{
+    @explicit_reference( .osm.Header.writingprogram_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.Header.source_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.Header.replication_base_url_idx, .osm.Osm.stringtable )
+    header : .osm.Header;
+    @explicit_reference( .osm.Node.tag_first_idx, .osm.Osm.tags_index )
+    nodes : vector< .osm.Node >;
+    @explicit_reference( .osm.Way.tag_first_idx, .osm.Osm.tags_index )
+    @explicit_reference( .osm.Way.ref_first_idx, .osm.Osm.nodes_index )
+    ways : vector< .osm.Way >;
+    @explicit_reference( .osm.Relation.tag_first_idx, .osm.Osm.tags_index )
+    relations : vector< .osm.Relation >;
+    @explicit_reference( .osm.NodeMember.node_idx, .osm.Osm.nodes )
+    @explicit_reference( .osm.NodeMember.role_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.WayMember.way_idx, .osm.Osm.ways )
+    @explicit_reference( .osm.WayMember.role_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.RelationMember.relation_idx, .osm.Osm.relations )
+    @explicit_reference( .osm.RelationMember.role_idx, .osm.Osm.stringtable )
+    relation_members : multivector< 40, .osm.NodeMember, .osm.WayMember, .osm.RelationMember >;
+    @explicit_reference( .osm.Tag.key_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.Tag.value_idx, .osm.Osm.stringtable )
+    tags : vector< .osm.Tag >;
+    @explicit_reference( .osm.TagIndex.value, .osm.Osm.tags )
+    tags_index : vector< .osm.TagIndex >;
+    @explicit_reference( .osm.NodeIndex.value, .osm.Osm.nodes )
+    nodes_index : vector< .osm.NodeIndex >;
+    stringtable : raw_data;
+    @optional
+    ids : archive .osm.Ids;
+    @optional
+    history : archive .osm.History;
+    @optional
+    spatial_index : archive .osm.SpatialIndex;
+    @optional
+    inverted_index : archive .osm.InvertedIndex;
+    @optional
+    provenance : raw_data;
+    @optional
+    way_bboxes : vector< .osm.BoundingBox >;
+    @optional
+    relation_bboxes : vector< .osm.BoundingBox >;
+    @optional
+    tagged_nodes : vector< .osm.EntityIndex >;
+    @optional
+    changesets : archive .osm.Changesets;
+    @optional
+    checksums : raw_data;
+    @optional
+    id_index : archive .osm.IdIndex;
+    @optional
+    node_ways : archive .osm.ReverseIndex;
+    @optional
+    node_relations : archive .osm.ReverseIndex;
+    @optional
+    way_relations : archive .osm.ReverseIndex;
+    @optional
+    relation_relations : archive .osm.ReverseIndex;
+    @optional
+    areas : archive .osm.Areas;
+    @optional
+    @explicit_reference( .osm.StringOffset.value, .osm.Osm.stringtable )
+    stringtable_offsets : vector< .osm.StringOffset >;
+    @optional
+    node_coords : vector< .osm.NodeCoord >;
+    @optional
+    elevation : vector< .osm.Elevation >;
+    @optional
+    tile_index : archive .osm.TileIndex;
+    @optional
+    admin_hierarchy : archive .osm.AdminHierarchy;
+    @optional
+    tagged_node_bits : vector< .osm.BitsetWord >;
+    @optional
+    tagged_way_bits : vector< .osm.BitsetWord >;
+    @optional
+    tag_numbers : vector< .osm.TagNumber >;
+    @optional
+    @explicit_reference( .osm.StringOffset.value, .osm.Osm.stringtable )
+    roles : vector< .osm.StringOffset >;
+    @optional
+    way_centroids : vector< .osm.Centroid >;
+    @optional
+    relation_centroids : vector< .osm.Centroid >;
+}
+}
+
+"#;
+
+pub mod resources {
+pub const HEADER: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
+struct Header
+{
+    coord_scale : i32 : 32;
+    bbox_left : i32 : 32;
+    bbox_right : i32 : 32;
+    bbox_top : i32 : 32;
+    bbox_bottom : i32 : 32;
+    writingprogram_idx : u64 : 40;
+    @optional( .osm.INVALID_IDX )
+    source_idx : u64 : 40;
+    replication_timestamp : i64 : 64;
+    replication_sequence_number : i64 : 64;
+    @optional( .osm.INVALID_IDX )
+    replication_base_url_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @explicit_reference( .osm.Header.writingprogram_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.Header.source_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.Header.replication_base_url_idx, .osm.Osm.stringtable )
+    header : .osm.Header;
+}
+}
+
+"#;
+pub const NODES: &str = r#"namespace osm {
+struct Node
+{
+    lat : i32 : 32;
+    lon : i32 : 32;
+    @range( tags )
+    tag_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @explicit_reference( .osm.Node.tag_first_idx, .osm.Osm.tags_index )
+    nodes : vector< .osm.Node >;
+}
+}
+
+"#;
+pub const WAYS: &str = r#"namespace osm {
+struct Way
+{
+    @range( tags )
+    tag_first_idx : u64 : 40;
+    @range( refs )
+    ref_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @explicit_reference( .osm.Way.tag_first_idx, .osm.Osm.tags_index )
+    @explicit_reference( .osm.Way.ref_first_idx, .osm.Osm.nodes_index )
+    ways : vector< .osm.Way >;
+}
+}
+
+"#;
+pub const RELATIONS: &str = r#"namespace osm {
+struct Relation
+{
+    @range( tags )
+    tag_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @explicit_reference( .osm.Relation.tag_first_idx, .osm.Osm.tags_index )
+    relations : vector< .osm.Relation >;
+}
+}
+
+"#;
+pub const RELATION_MEMBERS: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
+struct NodeMember
+{
+    @optional( .osm.INVALID_IDX )
+    node_idx : u64 : 40;
+    role_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct WayMember
+{
+    @optional( .osm.INVALID_IDX )
+    way_idx : u64 : 40;
+    role_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct RelationMember
+{
+    @optional( .osm.INVALID_IDX )
+    relation_idx : u64 : 40;
+    role_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @explicit_reference( .osm.NodeMember.node_idx, .osm.Osm.nodes )
+    @explicit_reference( .osm.NodeMember.role_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.WayMember.way_idx, .osm.Osm.ways )
+    @explicit_reference( .osm.WayMember.role_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.RelationMember.relation_idx, .osm.Osm.relations )
+    @explicit_reference( .osm.RelationMember.role_idx, .osm.Osm.stringtable )
+    relation_members : multivector< 40, .osm.NodeMember, .osm.WayMember, .osm.RelationMember >;
+}
+}
+
+"#;
+pub const TAGS: &str = r#"namespace osm {
+struct Tag
+{
+    key_idx : u64 : 40;
+    value_idx : u64 : 40;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @explicit_reference( .osm.Tag.key_idx, .osm.Osm.stringtable )
+    @explicit_reference( .osm.Tag.value_idx, .osm.Osm.stringtable )
+    tags : vector< .osm.Tag >;
+}
+}
+
+"#;
+pub const TAGS_INDEX: &str = r#"namespace osm {
+struct TagIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @explicit_reference( .osm.TagIndex.value, .osm.Osm.tags )
+    tags_index : vector< .osm.TagIndex >;
+}
+}
+
+"#;
+pub const NODES_INDEX: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
+}
+
+namespace osm {
+struct NodeIndex
+{
+    @optional( .osm.INVALID_IDX )
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @explicit_reference( .osm.NodeIndex.value, .osm.Osm.nodes )
+    nodes_index : vector< .osm.NodeIndex >;
+}
+}
+
+"#;
+pub const STRINGTABLE: &str = r#"namespace osm {
+archive Osm
+{
+    stringtable : raw_data;
+}
+}
+
+"#;
+pub const IDS: &str = r#"namespace osm {
+struct Id
 {
     value : u64 : 40;
 }
@@ -2242,414 +9560,692 @@ archive Ids
 }
 }
 
+namespace osm {
+archive Osm
+{
+    @optional
+    ids : archive .osm.Ids;
+}
+}
+
 "#;
+pub const HISTORY: &str = r#"namespace osm {
+struct Version
+{
+    version : u32 : 32;
+    timestamp : i64 : 64;
+    visible : bool : 1;
+}
+}
 
-pub mod resources {
-pub const NODES: &str = r#"namespace osm {
-struct Id
+namespace osm {
+archive History
+{
+    nodes : vector< .osm.Version >;
+    ways : vector< .osm.Version >;
+    relations : vector< .osm.Version >;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @optional
+    history : archive .osm.History;
+}
+}
+
+"#;
+pub const SPATIAL_INDEX: &str = r#"namespace osm {
+struct SpatialBox
+{
+    left : i32 : 32;
+    bottom : i32 : 32;
+    right : i32 : 32;
+    top : i32 : 32;
+    index : u64 : 40;
+}
+}
+
+namespace osm {
+archive SpatialIndex
+{
+    nodes : vector< .osm.SpatialBox >;
+    ways : vector< .osm.SpatialBox >;
+    relations : vector< .osm.SpatialBox >;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @optional
+    spatial_index : archive .osm.SpatialIndex;
+}
+}
+
+"#;
+pub const INVERTED_INDEX: &str = r#"namespace osm {
+struct TagPostings
+{
+    key_idx : u64 : 40;
+    @optional( .osm.INVALID_IDX )
+    value_idx : u64 : 40;
+    first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct EntityIndex
 {
     value : u64 : 40;
 }
 }
 
 namespace osm {
-archive Ids
+archive InvertedIndex
 {
-    nodes : vector< .osm.Id >;
+    node_tags : vector< .osm.TagPostings >;
+    nodes : vector< .osm.EntityIndex >;
+    way_tags : vector< .osm.TagPostings >;
+    ways : vector< .osm.EntityIndex >;
+    relation_tags : vector< .osm.TagPostings >;
+    relations : vector< .osm.EntityIndex >;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @optional
+    inverted_index : archive .osm.InvertedIndex;
 }
 }
 
 "#;
-pub const WAYS: &str = r#"namespace osm {
-struct Id
+pub const PROVENANCE: &str = r#"namespace osm {
+archive Osm
+{
+    @optional
+    provenance : raw_data;
+}
+}
+
+"#;
+pub const WAY_BBOXES: &str = r#"namespace osm {
+struct BoundingBox
+{
+    left : i32 : 32;
+    bottom : i32 : 32;
+    right : i32 : 32;
+    top : i32 : 32;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @optional
+    way_bboxes : vector< .osm.BoundingBox >;
+}
+}
+
+"#;
+pub const RELATION_BBOXES: &str = r#"namespace osm {
+struct BoundingBox
+{
+    left : i32 : 32;
+    bottom : i32 : 32;
+    right : i32 : 32;
+    top : i32 : 32;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @optional
+    relation_bboxes : vector< .osm.BoundingBox >;
+}
+}
+
+"#;
+pub const TAGGED_NODES: &str = r#"namespace osm {
+struct EntityIndex
 {
     value : u64 : 40;
 }
 }
 
 namespace osm {
-archive Ids
+archive Osm
 {
-    ways : vector< .osm.Id >;
+    @optional
+    tagged_nodes : vector< .osm.EntityIndex >;
 }
 }
 
 "#;
-pub const RELATIONS: &str = r#"namespace osm {
-struct Id
+pub const CHANGESETS: &str = r#"namespace osm {
+struct BoundingBox
+{
+    left : i32 : 32;
+    bottom : i32 : 32;
+    right : i32 : 32;
+    top : i32 : 32;
+}
+}
+
+namespace osm {
+struct Changeset
+{
+    id : u64 : 40;
+    created_at : i64 : 64;
+    closed_at : i64 : 64;
+    user_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct ChangesetTags
+{
+    @range( tags )
+    tag_first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct TagIndex
 {
     value : u64 : 40;
 }
 }
 
 namespace osm {
-archive Ids
+archive Changesets
 {
-    relations : vector< .osm.Id >;
+    changesets : vector< .osm.Changeset >;
+    bboxes : vector< .osm.BoundingBox >;
+    @optional
+    tag_ranges : vector< .osm.ChangesetTags >;
+    @optional
+    tags_index : vector< .osm.TagIndex >;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @optional
+    changesets : archive .osm.Changesets;
 }
 }
 
 "#;
+pub const CHECKSUMS: &str = r#"namespace osm {
+archive Osm
+{
+    @optional
+    checksums : raw_data;
 }
 }
-pub mod osm {
 
-pub const OSM: &str = r#"namespace osm {
-struct Header
+"#;
+pub const ID_INDEX: &str = r#"namespace osm {
+struct EntityIndex
 {
-    coord_scale : i32 : 32;
-    bbox_left : i32 : 32;
-    bbox_right : i32 : 32;
-    bbox_top : i32 : 32;
-    bbox_bottom : i32 : 32;
-    writingprogram_idx : u64 : 40;
-    source_idx : u64 : 40;
-    replication_timestamp : i64 : 64;
-    replication_sequence_number : i64 : 64;
-    replication_base_url_idx : u64 : 40;
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive IdIndex
+{
+    nodes : vector< .osm.EntityIndex >;
+    ways : vector< .osm.EntityIndex >;
+    relations : vector< .osm.EntityIndex >;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @optional
+    id_index : archive .osm.IdIndex;
+}
+}
+
+"#;
+pub const NODE_WAYS: &str = r#"namespace osm {
+struct ReverseIndexRange
+{
+    @range( entries )
+    first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct EntityIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive ReverseIndex
+{
+    ranges : vector< .osm.ReverseIndexRange >;
+    entries : vector< .osm.EntityIndex >;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @optional
+    node_ways : archive .osm.ReverseIndex;
+}
+}
+
+"#;
+pub const NODE_RELATIONS: &str = r#"namespace osm {
+struct ReverseIndexRange
+{
+    @range( entries )
+    first_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct EntityIndex
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
+archive ReverseIndex
+{
+    ranges : vector< .osm.ReverseIndexRange >;
+    entries : vector< .osm.EntityIndex >;
+}
+}
+
+namespace osm {
+archive Osm
+{
+    @optional
+    node_relations : archive .osm.ReverseIndex;
+}
+}
+
+"#;
+pub const WAY_RELATIONS: &str = r#"namespace osm {
+struct ReverseIndexRange
+{
+    @range( entries )
+    first_idx : u64 : 40;
 }
 }
 
 namespace osm {
-struct Node
+struct EntityIndex
 {
-    lat : i32 : 32;
-    lon : i32 : 32;
-    @range( tags )
-    tag_first_idx : u64 : 40;
+    value : u64 : 40;
 }
 }
 
 namespace osm {
-struct Way
+archive ReverseIndex
 {
-    @range( tags )
-    tag_first_idx : u64 : 40;
-    @range( refs )
-    ref_first_idx : u64 : 40;
+    ranges : vector< .osm.ReverseIndexRange >;
+    entries : vector< .osm.EntityIndex >;
 }
 }
 
 namespace osm {
-struct Relation
+archive Osm
 {
-    @range( tags )
-    tag_first_idx : u64 : 40;
+    @optional
+    way_relations : archive .osm.ReverseIndex;
 }
 }
 
-namespace osm {
-const u64 INVALID_IDX = 1099511627775;
+"#;
+pub const RELATION_RELATIONS: &str = r#"namespace osm {
+struct ReverseIndexRange
+{
+    @range( entries )
+    first_idx : u64 : 40;
+}
 }
 
 namespace osm {
-struct NodeMember
+struct EntityIndex
 {
-    @optional( .osm.INVALID_IDX )
-    node_idx : u64 : 40;
-    role_idx : u64 : 40;
+    value : u64 : 40;
 }
 }
 
 namespace osm {
-struct WayMember
+archive ReverseIndex
 {
-    @optional( .osm.INVALID_IDX )
-    way_idx : u64 : 40;
-    role_idx : u64 : 40;
+    ranges : vector< .osm.ReverseIndexRange >;
+    entries : vector< .osm.EntityIndex >;
 }
 }
 
 namespace osm {
-struct RelationMember
+archive Osm
 {
-    @optional( .osm.INVALID_IDX )
-    relation_idx : u64 : 40;
-    role_idx : u64 : 40;
+    @optional
+    relation_relations : archive .osm.ReverseIndex;
+}
 }
+
+"#;
+pub const AREAS: &str = r#"namespace osm {
+const u64 INVALID_IDX = 1099511627775;
 }
 
 namespace osm {
-struct Tag
+struct Area
 {
-    key_idx : u64 : 40;
-    value_idx : u64 : 40;
+    @range( polygons )
+    first_polygon_idx : u64 : 40;
+    @optional( .osm.INVALID_IDX )
+    way_idx : u64 : 40;
+    @optional( .osm.INVALID_IDX )
+    relation_idx : u64 : 40;
 }
 }
 
 namespace osm {
-struct TagIndex
+struct AreaPolygon
 {
-    value : u64 : 40;
+    @range( rings )
+    first_ring_idx : u64 : 40;
 }
 }
 
 namespace osm {
-struct NodeIndex
+struct AreaRing
 {
-    @optional( .osm.INVALID_IDX )
-    value : u64 : 40;
+    @range( nodes )
+    first_node_idx : u64 : 40;
 }
 }
 
 namespace osm {
-struct Id
+struct EntityIndex
 {
     value : u64 : 40;
 }
 }
 
 namespace osm {
-archive Ids
+archive Areas
 {
-    nodes : vector< .osm.Id >;
-    ways : vector< .osm.Id >;
-    relations : vector< .osm.Id >;
+    areas : vector< .osm.Area >;
+    polygons : vector< .osm.AreaPolygon >;
+    rings : vector< .osm.AreaRing >;
+    nodes : vector< .osm.EntityIndex >;
 }
 }
 
 namespace osm {
-@bound_implicitly( Relations : .osm.Osm.relations, .osm.Osm.relation_members )
 archive Osm
 {
-    @explicit_reference( .osm.Header.writingprogram_idx, .osm.Osm.stringtable )
-    @explicit_reference( .osm.Header.source_idx, .osm.Osm.stringtable )
-    @explicit_reference( .osm.Header.replication_base_url_idx, .osm.Osm.stringtable )
-    header : .osm.Header;
-    @explicit_reference( .osm.Node.tag_first_idx, .osm.Osm.tags_index )
-    nodes : vector< .osm.Node >;
-    @explicit_reference( .osm.Way.tag_first_idx, .osm.Osm.tags_index )
-    @explicit_reference( .osm.Way.ref_first_idx, .osm.Osm.nodes_index )
-    ways : vector< .osm.Way >;
-    @explicit_reference( .osm.Relation.tag_first_idx, .osm.Osm.tags_index )
-    relations : vector< .osm.Relation >;
-    @explicit_reference( .osm.NodeMember.node_idx, .osm.Osm.nodes )
-    @explicit_reference( .osm.NodeMember.role_idx, .osm.Osm.stringtable )
-    @explicit_reference( .osm.WayMember.way_idx, .osm.Osm.ways )
-    @explicit_reference( .osm.WayMember.role_idx, .osm.Osm.stringtable )
-    @explicit_reference( .osm.RelationMember.relation_idx, .osm.Osm.relations )
-    @explicit_reference( .osm.RelationMember.role_idx, .osm.Osm.stringtable )
-    relation_members : multivector< 40, .osm.NodeMember, .osm.WayMember, .osm.RelationMember >;
-    @explicit_reference( .osm.Tag.key_idx, .osm.Osm.stringtable )
-    @explicit_reference( .osm.Tag.value_idx, .osm.Osm.stringtable )
-    tags : vector< .osm.Tag >;
-    @explicit_reference( .osm.TagIndex.value, .osm.Osm.tags )
-    tags_index : vector< .osm.TagIndex >;
-    @explicit_reference( .osm.NodeIndex.value, .osm.Osm.nodes )
-    nodes_index : vector< .osm.NodeIndex >;
-    stringtable : raw_data;
     @optional
-    ids : archive .osm.Ids;
+    areas : archive .osm.Areas;
 }
 }
 
 "#;
-
-pub mod resources {
-pub const HEADER: &str = r#"namespace osm {
-struct Header
+pub const STRINGTABLE_OFFSETS: &str = r#"namespace osm {
+struct StringOffset
 {
-    coord_scale : i32 : 32;
-    bbox_left : i32 : 32;
-    bbox_right : i32 : 32;
-    bbox_top : i32 : 32;
-    bbox_bottom : i32 : 32;
-    writingprogram_idx : u64 : 40;
-    source_idx : u64 : 40;
-    replication_timestamp : i64 : 64;
-    replication_sequence_number : i64 : 64;
-    replication_base_url_idx : u64 : 40;
+    value : u64 : 40;
 }
 }
 
 namespace osm {
 archive Osm
 {
-    @explicit_reference( .osm.Header.writingprogram_idx, .osm.Osm.stringtable )
-    @explicit_reference( .osm.Header.source_idx, .osm.Osm.stringtable )
-    @explicit_reference( .osm.Header.replication_base_url_idx, .osm.Osm.stringtable )
-    header : .osm.Header;
+    @optional
+    @explicit_reference( .osm.StringOffset.value, .osm.Osm.stringtable )
+    stringtable_offsets : vector< .osm.StringOffset >;
 }
 }
 
 "#;
-pub const NODES: &str = r#"namespace osm {
-struct Node
+pub const NODE_COORDS: &str = r#"namespace osm {
+struct NodeCoord
 {
     lat : i32 : 32;
     lon : i32 : 32;
-    @range( tags )
-    tag_first_idx : u64 : 40;
 }
 }
 
 namespace osm {
 archive Osm
 {
-    @explicit_reference( .osm.Node.tag_first_idx, .osm.Osm.tags_index )
-    nodes : vector< .osm.Node >;
+    @optional
+    node_coords : vector< .osm.NodeCoord >;
 }
 }
 
 "#;
-pub const WAYS: &str = r#"namespace osm {
-struct Way
+pub const ELEVATION: &str = r#"namespace osm {
+const i16 NO_ELEVATION = -32768;
+}
+
+namespace osm {
+struct Elevation
 {
-    @range( tags )
-    tag_first_idx : u64 : 40;
-    @range( refs )
-    ref_first_idx : u64 : 40;
+    @optional( .osm.NO_ELEVATION )
+    value : i16 : 16;
 }
 }
 
 namespace osm {
 archive Osm
 {
-    @explicit_reference( .osm.Way.tag_first_idx, .osm.Osm.tags_index )
-    @explicit_reference( .osm.Way.ref_first_idx, .osm.Osm.nodes_index )
-    ways : vector< .osm.Way >;
+    @optional
+    elevation : vector< .osm.Elevation >;
 }
 }
 
 "#;
-pub const RELATIONS: &str = r#"namespace osm {
-struct Relation
+pub const TILE_INDEX: &str = r#"namespace osm {
+struct TileGrid
 {
-    @range( tags )
-    tag_first_idx : u64 : 40;
+    zoom : u8 : 8;
+    min_x : u32 : 32;
+    min_y : u32 : 32;
+    width : u32 : 32;
+    height : u32 : 32;
+}
+}
+
+namespace osm {
+struct TileCell
+{
+    @range( runs )
+    first_run_idx : u64 : 40;
+}
+}
+
+namespace osm {
+struct IndexRun
+{
+    start : u64 : 40;
+    end : u64 : 40;
+}
+}
+
+namespace osm {
+archive TileIndex
+{
+    grid : .osm.TileGrid;
+    node_cells : vector< .osm.TileCell >;
+    node_runs : vector< .osm.IndexRun >;
+    way_cells : vector< .osm.TileCell >;
+    way_runs : vector< .osm.IndexRun >;
 }
 }
 
 namespace osm {
 archive Osm
 {
-    @explicit_reference( .osm.Relation.tag_first_idx, .osm.Osm.tags_index )
-    relations : vector< .osm.Relation >;
+    @optional
+    tile_index : archive .osm.TileIndex;
 }
 }
 
 "#;
-pub const RELATION_MEMBERS: &str = r#"namespace osm {
-const u64 INVALID_IDX = 1099511627775;
+pub const ADMIN_HIERARCHY: &str = r#"namespace osm {
+struct AdminChain
+{
+    @range( boundaries )
+    first_boundary_idx : u64 : 40;
+}
 }
 
 namespace osm {
-struct NodeMember
+struct EntityIndex
 {
-    @optional( .osm.INVALID_IDX )
-    node_idx : u64 : 40;
-    role_idx : u64 : 40;
+    value : u64 : 40;
 }
 }
 
 namespace osm {
-struct WayMember
+struct AdminChainIndex
 {
-    @optional( .osm.INVALID_IDX )
-    way_idx : u64 : 40;
-    role_idx : u64 : 40;
+    value : u32 : 32;
 }
 }
 
 namespace osm {
-struct RelationMember
+archive AdminHierarchy
 {
-    @optional( .osm.INVALID_IDX )
-    relation_idx : u64 : 40;
-    role_idx : u64 : 40;
+    chains : vector< .osm.AdminChain >;
+    boundaries : vector< .osm.EntityIndex >;
+    node_chains : vector< .osm.AdminChainIndex >;
 }
 }
 
 namespace osm {
 archive Osm
 {
-    @explicit_reference( .osm.NodeMember.node_idx, .osm.Osm.nodes )
-    @explicit_reference( .osm.NodeMember.role_idx, .osm.Osm.stringtable )
-    @explicit_reference( .osm.WayMember.way_idx, .osm.Osm.ways )
-    @explicit_reference( .osm.WayMember.role_idx, .osm.Osm.stringtable )
-    @explicit_reference( .osm.RelationMember.relation_idx, .osm.Osm.relations )
-    @explicit_reference( .osm.RelationMember.role_idx, .osm.Osm.stringtable )
-    relation_members : multivector< 40, .osm.NodeMember, .osm.WayMember, .osm.RelationMember >;
+    @optional
+    admin_hierarchy : archive .osm.AdminHierarchy;
 }
 }
 
 "#;
-pub const TAGS: &str = r#"namespace osm {
-struct Tag
+pub const TAGGED_NODE_BITS: &str = r#"namespace osm {
+struct BitsetWord
 {
-    key_idx : u64 : 40;
-    value_idx : u64 : 40;
+    value : u64 : 64;
 }
 }
 
 namespace osm {
 archive Osm
 {
-    @explicit_reference( .osm.Tag.key_idx, .osm.Osm.stringtable )
-    @explicit_reference( .osm.Tag.value_idx, .osm.Osm.stringtable )
-    tags : vector< .osm.Tag >;
+    @optional
+    tagged_node_bits : vector< .osm.BitsetWord >;
 }
 }
 
 "#;
-pub const TAGS_INDEX: &str = r#"namespace osm {
-struct TagIndex
+pub const TAGGED_WAY_BITS: &str = r#"namespace osm {
+struct BitsetWord
 {
-    value : u64 : 40;
+    value : u64 : 64;
 }
 }
 
 namespace osm {
 archive Osm
 {
-    @explicit_reference( .osm.TagIndex.value, .osm.Osm.tags )
-    tags_index : vector< .osm.TagIndex >;
+    @optional
+    tagged_way_bits : vector< .osm.BitsetWord >;
 }
 }
 
 "#;
-pub const NODES_INDEX: &str = r#"namespace osm {
-const u64 INVALID_IDX = 1099511627775;
+pub const TAG_NUMBERS: &str = r#"namespace osm {
+const i64 NO_NUMBER = -9223372036854775808;
 }
 
 namespace osm {
-struct NodeIndex
+struct TagNumber
 {
-    @optional( .osm.INVALID_IDX )
-    value : u64 : 40;
+    @optional( .osm.NO_NUMBER )
+    value : i64 : 64;
 }
 }
 
 namespace osm {
 archive Osm
 {
-    @explicit_reference( .osm.NodeIndex.value, .osm.Osm.nodes )
-    nodes_index : vector< .osm.NodeIndex >;
+    @optional
+    tag_numbers : vector< .osm.TagNumber >;
 }
 }
 
 "#;
-pub const STRINGTABLE: &str = r#"namespace osm {
+pub const ROLES: &str = r#"namespace osm {
+struct StringOffset
+{
+    value : u64 : 40;
+}
+}
+
+namespace osm {
 archive Osm
 {
-    stringtable : raw_data;
+    @optional
+    @explicit_reference( .osm.StringOffset.value, .osm.Osm.stringtable )
+    roles : vector< .osm.StringOffset >;
 }
 }
 
 "#;
-pub const IDS: &str = r#"namespace osm {
-struct Id
+pub const WAY_CENTROIDS: &str = r#"namespace osm {
+const i32 NO_CENTROID = -2147483648;
+}
+
+namespace osm {
+struct Centroid
 {
-    value : u64 : 40;
+    @optional( .osm.NO_CENTROID )
+    lat : i32 : 32;
+    lon : i32 : 32;
 }
 }
 
 namespace osm {
-archive Ids
+archive Osm
 {
-    nodes : vector< .osm.Id >;
-    ways : vector< .osm.Id >;
-    relations : vector< .osm.Id >;
+    @optional
+    way_centroids : vector< .osm.Centroid >;
+}
+}
+
+"#;
+pub const RELATION_CENTROIDS: &str = r#"namespace osm {
+const i32 NO_CENTROID = -2147483648;
+}
+
+namespace osm {
+struct Centroid
+{
+    @optional( .osm.NO_CENTROID )
+    lat : i32 : 32;
+    lon : i32 : 32;
 }
 }
 
@@ -2657,7 +10253,53 @@ namespace osm {
 archive Osm
 {
     @optional
-    ids : archive .osm.Ids;
+    relation_centroids : vector< .osm.Centroid >;
+}
+}
+
+"#;
+}
+}
+pub mod tiles {
+
+pub const TILES: &str = r#"namespace osm {
+struct Tile
+{
+    zoom : u8 : 8;
+    x : u32 : 32;
+    y : u32 : 32;
+    num_nodes : u64 : 40;
+    num_ways : u64 : 40;
+    num_relations : u64 : 40;
+}
+}
+
+namespace osm {
+archive Tiles
+{
+    tiles : vector< .osm.Tile >;
+}
+}
+
+"#;
+
+pub mod resources {
+pub const TILES: &str = r#"namespace osm {
+struct Tile
+{
+    zoom : u8 : 8;
+    x : u32 : 32;
+    y : u32 : 32;
+    num_nodes : u64 : 40;
+    num_ways : u64 : 40;
+    num_relations : u64 : 40;
+}
+}
+
+namespace osm {
+archive Tiles
+{
+    tiles : vector< .osm.Tile >;
 }
 }
 
@@ -0,0 +1,94 @@
+//! Parallel iteration over the entities of an archive, enabled by the feature
+//! `rayon`.
+//!
+//! The vectors of an archive are slices, so the parallel iterators of rayon
+//! work on them directly, e.g. `archive.ways().par_iter()` after
+//! `use osmflat::rayon::prelude::*`. The functions of this module are the
+//! parallel versions of the tag helpers.
+
+use crate::{find_tag, has_tag, set_bits, Node, Osm, Relation, Way};
+
+use rayon::iter::Either;
+use rayon::prelude::*;
+
+use std::ops::Range;
+
+/// Returns a parallel iterator over the nodes having tags together with their
+/// indexes, cf. [`iter_tagged_nodes`].
+///
+/// [`iter_tagged_nodes`]: crate::iter_tagged_nodes
+pub fn par_iter_tagged_nodes(archive: &Osm) -> impl ParallelIterator<Item = (usize, &Node)> {
+    let nodes = archive.nodes();
+    match (archive.tagged_nodes(), archive.tagged_node_bits()) {
+        (Some(tagged_nodes), _) => Either::Left(tagged_nodes.par_iter().map(move |idx| {
+            let idx = idx.value() as usize;
+            (idx, &nodes[idx])
+        })),
+        (None, Some(bits)) => {
+            Either::Right(Either::Left(bits.par_iter().enumerate().flat_map_iter(
+                move |(word_idx, word)| {
+                    set_bits(std::slice::from_ref(word)).map(move |bit| {
+                        let idx = word_idx * 64 + bit;
+                        (idx, &nodes[idx])
+                    })
+                },
+            )))
+        }
+        (None, None) => Either::Right(Either::Right(
+            nodes
+                .par_iter()
+                .enumerate()
+                .filter(|(_, node)| !node.tags().is_empty()),
+        )),
+    }
+}
+
+fn par_with_tag<'a, T: Sync>(
+    archive: &'a Osm,
+    entities: &'a [T],
+    tags: impl Fn(&T) -> Range<u64> + Sync + Send + 'a,
+    key: &'a [u8],
+    value: Option<&'a [u8]>,
+) -> impl ParallelIterator<Item = (usize, &'a T)> + 'a {
+    entities
+        .par_iter()
+        .enumerate()
+        .filter(move |(_, entity)| match value {
+            Some(value) => has_tag(archive, tags(entity), key, value),
+            None => find_tag(archive, tags(entity), key).is_some(),
+        })
+}
+
+/// Returns a parallel iterator over the nodes having a tag with `key`, and
+/// with `value` if given, together with their indexes.
+///
+/// Unlike [`query::entities_with_tag`], it always scans all nodes.
+///
+/// [`query::entities_with_tag`]: crate::query::entities_with_tag
+pub fn par_nodes_with_tag<'a>(
+    archive: &'a Osm,
+    key: &'a [u8],
+    value: Option<&'a [u8]>,
+) -> impl ParallelIterator<Item = (usize, &'a Node)> + 'a {
+    par_with_tag(archive, archive.nodes(), Node::tags, key, value)
+}
+
+/// Returns a parallel iterator over the ways having a tag with `key`, and
+/// with `value` if given, together with their indexes.
+pub fn par_ways_with_tag<'a>(
+    archive: &'a Osm,
+    key: &'a [u8],
+    value: Option<&'a [u8]>,
+) -> impl ParallelIterator<Item = (usize, &'a Way)> + 'a {
+    par_with_tag(archive, archive.ways(), Way::tags, key, value)
+}
+
+/// Returns a parallel iterator over the relations having a tag with `key`, and
+/// with `value` if given, together with their indexes.
+pub fn par_relations_with_tag<'a>(
+    archive: &'a Osm,
+    key: &'a [u8],
+    value: Option<&'a [u8]>,
+) -> impl ParallelIterator<Item = (usize, &'a Relation)> + 'a {
+    par_with_tag(archive, archive.relations(), Relation::tags, key, value)
+}
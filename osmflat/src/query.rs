@@ -0,0 +1,214 @@
+//! Queries of entities by tag, and of the distinct keys of the tags.
+//!
+//! The queries use the `inverted_index` subarchive if present, cf.
+//! `osmflatc --inverted-index`; otherwise, they scan the tags of all entities,
+//! in parallel with the feature `rayon`.
+
+use crate::{find_tag, has_tag, EntityIndex, EntityType, Osm};
+
+use std::collections::{BTreeMap, HashMap};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use std::ops::Range;
+
+/// Iterator over the ascending indexes of the entities matching a query.
+#[derive(Debug, Clone)]
+pub struct Indexes<'a>(IndexesInner<'a>);
+
+#[derive(Debug, Clone)]
+enum IndexesInner<'a> {
+    InvertedIndex(std::slice::Iter<'a, EntityIndex>),
+    Scan(std::vec::IntoIter<u64>),
+}
+
+impl Iterator for Indexes<'_> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        match &mut self.0 {
+            IndexesInner::InvertedIndex(iter) => iter.next().map(|entity| entity.value()),
+            IndexesInner::Scan(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.0 {
+            IndexesInner::InvertedIndex(iter) => iter.size_hint(),
+            IndexesInner::Scan(iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl ExactSizeIterator for Indexes<'_> {}
+
+/// Indexes of the nodes, ways, and relations matching a query, cf.
+/// [`entities_with_tag`].
+#[derive(Debug, Clone)]
+pub struct EntitiesWithTag<'a> {
+    /// Indexes in the `nodes` vector
+    pub nodes: Indexes<'a>,
+    /// Indexes in the `ways` vector
+    pub ways: Indexes<'a>,
+    /// Indexes in the `relations` vector
+    pub relations: Indexes<'a>,
+}
+
+/// Scans the tags of `num_entities` entities for the tag.
+fn scan<'a>(
+    archive: &'a Osm,
+    num_entities: usize,
+    tags: impl Fn(usize) -> Range<u64> + Sync,
+    key: &[u8],
+    value: Option<&[u8]>,
+) -> Indexes<'a> {
+    let matches = |idx: &usize| match value {
+        Some(value) => has_tag(archive, tags(*idx), key, value),
+        None => find_tag(archive, tags(*idx), key).is_some(),
+    };
+    #[cfg(feature = "rayon")]
+    let indexes: Vec<u64> = (0..num_entities)
+        .into_par_iter()
+        .filter(matches)
+        .map(|idx| idx as u64)
+        .collect();
+    #[cfg(not(feature = "rayon"))]
+    let indexes: Vec<u64> = (0..num_entities)
+        .filter(matches)
+        .map(|idx| idx as u64)
+        .collect();
+    Indexes(IndexesInner::Scan(indexes.into_iter()))
+}
+
+/// Returns the indexes of the entities of type `entity_type` having a tag
+/// with `key`, and with `value` if given, in ascending order.
+pub fn entities_of_type_with_tag<'a>(
+    archive: &'a Osm,
+    entity_type: EntityType,
+    key: &[u8],
+    value: Option<&[u8]>,
+) -> Indexes<'a> {
+    if let Some(inverted_index) = archive.inverted_index() {
+        return Indexes(IndexesInner::InvertedIndex(inverted_index.postings_of_tag(
+            archive,
+            entity_type,
+            key,
+            value,
+        )));
+    }
+    match entity_type {
+        EntityType::Node => {
+            let nodes = archive.nodes();
+            scan(archive, nodes.len(), |idx| nodes[idx].tags(), key, value)
+        }
+        EntityType::Way => {
+            let ways = archive.ways();
+            scan(archive, ways.len(), |idx| ways[idx].tags(), key, value)
+        }
+        EntityType::Relation => {
+            let relations = archive.relations();
+            scan(
+                archive,
+                relations.len(),
+                |idx| relations[idx].tags(),
+                key,
+                value,
+            )
+        }
+    }
+}
+
+/// Returns the indexes of the nodes, ways, and relations having a tag with
+/// `key`, and with `value` if given, in ascending order.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use osmflat::{query, FileResourceStorage, Osm};
+///
+/// let archive = Osm::open(FileResourceStorage::new("path/to/archive.osm.flatdata")).unwrap();
+/// let pubs = query::entities_with_tag(&archive, b"amenity", Some(b"pub"));
+/// println!("{} pubs mapped as nodes", pubs.nodes.len());
+/// ```
+pub fn entities_with_tag<'a>(
+    archive: &'a Osm,
+    key: &[u8],
+    value: Option<&[u8]>,
+) -> EntitiesWithTag<'a> {
+    EntitiesWithTag {
+        nodes: entities_of_type_with_tag(archive, EntityType::Node, key, value),
+        ways: entities_of_type_with_tag(archive, EntityType::Way, key, value),
+        relations: entities_of_type_with_tag(archive, EntityType::Relation, key, value),
+    }
+}
+
+/// Counts the keys in `tags_index` by their indexes in the stringtable.
+fn count_key_indexes(archive: &Osm) -> HashMap<u64, u64> {
+    let tags = archive.tags();
+    let count = |mut counts: HashMap<u64, u64>, idx: &crate::TagIndex| {
+        *counts
+            .entry(tags[idx.value() as usize].key_idx())
+            .or_default() += 1;
+        counts
+    };
+    #[cfg(feature = "rayon")]
+    let counts = archive
+        .tags_index()
+        .par_iter()
+        .fold(HashMap::new, count)
+        .reduce(HashMap::new, |mut a, b| {
+            for (key_idx, n) in b {
+                *a.entry(key_idx).or_default() += n;
+            }
+            a
+        });
+    #[cfg(not(feature = "rayon"))]
+    let counts = archive.tags_index().iter().fold(HashMap::new(), count);
+    counts
+}
+
+impl Osm {
+    /// Returns the distinct keys of the tags of all entities in ascending
+    /// order, together with the number of tags with the key.
+    ///
+    /// The keys are read from the `inverted_index` subarchive if present;
+    /// otherwise, all tags are scanned, in parallel with the feature `rayon`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use osmflat::{FileResourceStorage, Osm};
+    ///
+    /// let archive = Osm::open(FileResourceStorage::new("path/to/archive.osm.flatdata")).unwrap();
+    /// for (key, count) in archive.tag_keys() {
+    ///     println!("{}: {count}", String::from_utf8_lossy(key));
+    /// }
+    /// ```
+    pub fn tag_keys(&self) -> Vec<(&[u8], u64)> {
+        let strings = self.stringtable();
+        let mut keys: BTreeMap<&[u8], u64> = BTreeMap::new();
+        match self.inverted_index() {
+            Some(index) => {
+                let postings = [index.node_tags(), index.way_tags(), index.relation_tags()];
+                // the postings of a key without value list all entities with
+                // the key; the last postings are the sentinel
+                for w in postings.iter().flat_map(|p| p.windows(2)) {
+                    if w[0].value_idx().is_none() {
+                        let key = strings.substring_raw(w[0].key_idx() as usize);
+                        *keys.entry(key).or_default() += w[1].first_idx() - w[0].first_idx();
+                    }
+                }
+            }
+            None => {
+                // equal keys may be stored several times in the stringtable
+                for (key_idx, count) in count_key_indexes(self) {
+                    *keys
+                        .entry(strings.substring_raw(key_idx as usize))
+                        .or_default() += count;
+                }
+            }
+        }
+        keys.into_iter().collect()
+    }
+}
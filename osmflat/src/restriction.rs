@@ -0,0 +1,309 @@
+//! Turn restrictions, i.e. relations with the tag `type=restriction`.
+//!
+//! A turn restriction forbids or requires the turn from the way with role
+//! `from` via a node or ways with role `via` into the way with role `to`, cf.
+//! <https://wiki.openstreetmap.org/wiki/Relation:restriction>.
+
+use crate::{find_tag, Osm, RelationMembersRef};
+
+use std::fmt;
+
+/// Kind of a turn restriction, i.e. the value of its `restriction` tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RestrictionKind {
+    /// `no_left_turn`
+    NoLeftTurn,
+    /// `no_right_turn`
+    NoRightTurn,
+    /// `no_straight_on`
+    NoStraightOn,
+    /// `no_u_turn`
+    NoUTurn,
+    /// `no_entry`
+    NoEntry,
+    /// `no_exit`
+    NoExit,
+    /// `only_left_turn`
+    OnlyLeftTurn,
+    /// `only_right_turn`
+    OnlyRightTurn,
+    /// `only_straight_on`
+    OnlyStraightOn,
+    /// `only_u_turn`
+    OnlyUTurn,
+}
+
+impl RestrictionKind {
+    /// Parses the value of a `restriction` tag.
+    pub fn from_value(value: &[u8]) -> Option<Self> {
+        Some(match value {
+            b"no_left_turn" => Self::NoLeftTurn,
+            b"no_right_turn" => Self::NoRightTurn,
+            b"no_straight_on" => Self::NoStraightOn,
+            b"no_u_turn" => Self::NoUTurn,
+            b"no_entry" => Self::NoEntry,
+            b"no_exit" => Self::NoExit,
+            b"only_left_turn" => Self::OnlyLeftTurn,
+            b"only_right_turn" => Self::OnlyRightTurn,
+            b"only_straight_on" => Self::OnlyStraightOn,
+            b"only_u_turn" => Self::OnlyUTurn,
+            _ => return None,
+        })
+    }
+
+    /// Whether the restriction requires the turn, i.e. forbids all other turns
+    /// from the `from` way, instead of forbidding it.
+    pub fn is_mandatory(&self) -> bool {
+        matches!(
+            self,
+            Self::OnlyLeftTurn | Self::OnlyRightTurn | Self::OnlyStraightOn | Self::OnlyUTurn
+        )
+    }
+}
+
+/// Node or ways a turn restriction leads over.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Via {
+    /// Index of the node
+    Node(u64),
+    /// Indexes of the ways in the order of the members
+    Ways(Vec<u64>),
+}
+
+/// Turn restriction of a relation, cf. [`turn_restriction`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TurnRestriction {
+    /// Index of the way with role `from`
+    pub from_way: u64,
+    /// Members with role `via`
+    pub via: Via,
+    /// Index of the way with role `to`
+    pub to_way: u64,
+    /// Kind of the restriction
+    pub kind: RestrictionKind,
+}
+
+/// Error interpreting a relation as turn restriction, cf.
+/// [`turn_restriction`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TurnRestrictionError {
+    /// The relation does not have the tag `type=restriction`.
+    NotARestriction,
+    /// The relation has no `restriction` tag, or one with the given unknown
+    /// value.
+    UnknownKind(Option<String>),
+    /// The relation has no member with the given role.
+    MissingMember(&'static str),
+    /// The relation has more than one member with the given role; the via
+    /// role may have several ways, but not several nodes or a node and ways.
+    DuplicateMember(&'static str),
+    /// The member at the given position has a role which does not allow its
+    /// type, e.g. a node with role `from`.
+    InvalidMemberType(usize),
+    /// The member at the given position is not resolved in the archive.
+    UnresolvedMember(usize),
+}
+
+impl fmt::Display for TurnRestrictionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotARestriction => write!(f, "not a restriction"),
+            Self::UnknownKind(None) => write!(f, "missing restriction tag"),
+            Self::UnknownKind(Some(value)) => write!(f, "unknown restriction {value:?}"),
+            Self::MissingMember(role) => write!(f, "missing {role} member"),
+            Self::DuplicateMember(role) => write!(f, "duplicate {role} member"),
+            Self::InvalidMemberType(member) => write!(f, "invalid type of member {member}"),
+            Self::UnresolvedMember(member) => write!(f, "unresolved member {member}"),
+        }
+    }
+}
+
+impl std::error::Error for TurnRestrictionError {}
+
+fn set_once(
+    slot: &mut Option<u64>,
+    idx: u64,
+    role: &'static str,
+) -> Result<(), TurnRestrictionError> {
+    if slot.replace(idx).is_some() {
+        return Err(TurnRestrictionError::DuplicateMember(role));
+    }
+    Ok(())
+}
+
+/// Interprets the relation at `relation_idx` as turn restriction.
+///
+/// The `from` and `to` members must be single ways, and the `via` members a
+/// single node or one or more ways. Members with other roles, e.g.
+/// `location_hint`, are ignored. Restrictions for specific modes of transport,
+/// i.e. with a `restriction:<mode>` tag instead of `restriction`, are rejected
+/// with [`TurnRestrictionError::UnknownKind`]. The connectivity of the members
+/// is not checked.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use osmflat::{FileResourceStorage, Osm};
+///
+/// let archive = Osm::open(FileResourceStorage::new("path/to/archive.osm.flatdata")).unwrap();
+/// for (idx, restriction) in osmflat::turn_restrictions(&archive) {
+///     match restriction {
+///         Ok(r) => println!("{:?} from way {} to way {}", r.kind, r.from_way, r.to_way),
+///         Err(e) => eprintln!("invalid restriction {idx}: {e}"),
+///     }
+/// }
+/// ```
+pub fn turn_restriction(
+    archive: &Osm,
+    relation_idx: usize,
+) -> Result<TurnRestriction, TurnRestrictionError> {
+    let tags = archive.relations()[relation_idx].tags();
+    if find_tag(archive, tags.clone(), b"type") != Some(b"restriction") {
+        return Err(TurnRestrictionError::NotARestriction);
+    }
+    let value = find_tag(archive, tags, b"restriction");
+    let kind = value.and_then(RestrictionKind::from_value).ok_or_else(|| {
+        TurnRestrictionError::UnknownKind(value.map(|v| String::from_utf8_lossy(v).into_owned()))
+    })?;
+
+    let strings = archive.stringtable();
+    let (mut from_way, mut to_way, mut via_node) = (None, None, None);
+    let mut via_ways = Vec::new();
+    for (pos, member) in archive.relation_members().at(relation_idx).enumerate() {
+        let (role_idx, way_idx, node_idx) = match member {
+            RelationMembersRef::NodeMember(m) => (m.role_idx(), None, Some(m.node_idx())),
+            RelationMembersRef::WayMember(m) => (m.role_idx(), Some(m.way_idx()), None),
+            RelationMembersRef::RelationMember(m) => (m.role_idx(), None, None),
+        };
+        let role = strings.substring_raw(role_idx as usize);
+        if !matches!(role, b"from" | b"via" | b"to") {
+            continue;
+        }
+        let resolved = |idx: Option<u64>| idx.ok_or(TurnRestrictionError::UnresolvedMember(pos));
+        match (role, way_idx, node_idx) {
+            (b"from", Some(idx), _) => set_once(&mut from_way, resolved(idx)?, "from")?,
+            (b"to", Some(idx), _) => set_once(&mut to_way, resolved(idx)?, "to")?,
+            (b"via", Some(idx), _) => via_ways.push(resolved(idx)?),
+            (b"via", _, Some(idx)) => set_once(&mut via_node, resolved(idx)?, "via")?,
+            _ => return Err(TurnRestrictionError::InvalidMemberType(pos)),
+        }
+    }
+
+    let via = match (via_node, via_ways.is_empty()) {
+        (Some(node_idx), true) => Via::Node(node_idx),
+        (None, false) => Via::Ways(via_ways),
+        (None, true) => return Err(TurnRestrictionError::MissingMember("via")),
+        (Some(_), false) => return Err(TurnRestrictionError::DuplicateMember("via")),
+    };
+    Ok(TurnRestriction {
+        from_way: from_way.ok_or(TurnRestrictionError::MissingMember("from"))?,
+        via,
+        to_way: to_way.ok_or(TurnRestrictionError::MissingMember("to"))?,
+        kind,
+    })
+}
+
+/// Returns the indexes of all relations with the tag `type=restriction`
+/// together with their turn restrictions, cf. [`turn_restriction`].
+pub fn turn_restrictions(
+    archive: &Osm,
+) -> impl Iterator<Item = (usize, Result<TurnRestriction, TurnRestrictionError>)> + '_ {
+    (0..archive.relations().len())
+        .map(move |idx| (idx, turn_restriction(archive, idx)))
+        .filter(|(_, r)| *r != Err(TurnRestrictionError::NotARestriction))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Member, OsmWriter};
+
+    #[test]
+    fn test_turn_restriction() {
+        let storage = flatdata::MemoryResourceStorage::new("/test");
+        let mut writer = OsmWriter::new(storage.clone()).unwrap();
+        let tags = None::<(&str, &str)>;
+        let a = writer.add_node(0.0, 0.0, tags);
+        let b = writer.add_node(1.0, 0.0, tags);
+        let c = writer.add_node(1.0, 1.0, tags);
+        let from = writer.add_way(&[a, b], tags);
+        let to = writer.add_way(&[b, c], tags);
+        let restriction = |kind| [("type", "restriction"), ("restriction", kind)];
+        writer.add_relation(
+            &[
+                Member::Way(from, "from"),
+                Member::Node(b, "via"),
+                Member::Way(to, "to"),
+                Member::Node(c, "location_hint"),
+            ],
+            restriction("no_left_turn"),
+        );
+        writer.add_relation(
+            &[
+                Member::Way(from, "from"),
+                Member::Way(from, "via"),
+                Member::Way(to, "via"),
+                Member::Way(to, "to"),
+            ],
+            restriction("only_u_turn"),
+        );
+        writer.add_relation(&[Member::Way(from, "from")], [("type", "route")]);
+        writer.add_relation(
+            &[Member::Way(from, "from"), Member::Node(b, "via")],
+            restriction("no_entry"),
+        );
+        writer.add_relation(
+            &[
+                Member::Node(a, "from"),
+                Member::Node(b, "via"),
+                Member::Way(to, "to"),
+            ],
+            restriction("no_right_turn"),
+        );
+        writer.add_relation(&[], restriction("no_parking"));
+        writer.add_relation(
+            &[
+                Member::Way(from, "from"),
+                Member::Node(b, "via"),
+                Member::Way(from, "via"),
+                Member::Way(to, "to"),
+            ],
+            restriction("no_u_turn"),
+        );
+        writer.finish().unwrap();
+        let archive = Osm::open(storage).unwrap();
+
+        assert_eq!(
+            turn_restriction(&archive, 0),
+            Ok(TurnRestriction {
+                from_way: from,
+                via: Via::Node(b),
+                to_way: to,
+                kind: RestrictionKind::NoLeftTurn,
+            })
+        );
+        let restriction = turn_restriction(&archive, 1).unwrap();
+        assert_eq!(restriction.via, Via::Ways(vec![from, to]));
+        assert!(restriction.kind.is_mandatory());
+
+        let restrictions: Vec<_> = turn_restrictions(&archive)
+            .map(|(idx, r)| (idx, r.err()))
+            .collect();
+        assert_eq!(
+            restrictions,
+            [
+                (0, None),
+                (1, None),
+                (3, Some(TurnRestrictionError::MissingMember("to"))),
+                (4, Some(TurnRestrictionError::InvalidMemberType(0))),
+                (
+                    5,
+                    Some(TurnRestrictionError::UnknownKind(Some(
+                        "no_parking".to_string()
+                    )))
+                ),
+                (6, Some(TurnRestrictionError::DuplicateMember("via"))),
+            ]
+        );
+    }
+}
@@ -0,0 +1,262 @@
+//! Lookup of the entities referencing an entity, backed by [`ReverseIndex`]
+//! subarchives.
+//!
+//! The `node_ways` subarchive, compiled with `osmflatc --node-ways`, maps each
+//! node to the ways containing it, cf. [`Osm::ways_containing_node`]. Without
+//! it, finding the ways of a node requires a scan of all ways, or a hash map
+//! built from such a scan.
+//!
+//! The `node_relations`, `way_relations` and `relation_relations` subarchives,
+//! compiled with `osmflatc --member-relations`, map each entity to the
+//! relations having it as member, cf. [`Osm::relations_containing`].
+
+use crate::{
+    EntityIndex, EntityType, Osm, RelationMembersRef, ReverseIndex, ReverseIndexBuilder,
+    ReverseIndexRange,
+};
+
+use std::io;
+
+impl ReverseIndex {
+    /// Returns the indexes of the entities referencing the entity with the
+    /// given index, in ascending order.
+    ///
+    /// Panics if the index is out of bounds.
+    pub fn entries_of(&self, idx: usize) -> impl ExactSizeIterator<Item = u64> + '_ {
+        let range = self.ranges()[idx].entries();
+        self.entries()[range.start as usize..range.end as usize]
+            .iter()
+            .map(|entity| entity.value())
+    }
+}
+
+/// Writes the reverse index of `len` entities to `builder`.
+///
+/// `references` returns the pairs `(entity, referencing entity)` ordered by the
+/// referencing entities, without duplicates. It is called twice: once to count
+/// the entries of each entity, and once to fill them in.
+fn write_reverse_index<I>(
+    len: usize,
+    references: impl Fn() -> I,
+    builder: &ReverseIndexBuilder,
+) -> io::Result<()>
+where
+    I: Iterator<Item = (u64, u64)>,
+{
+    let mut offsets = vec![0u64; len + 1];
+    for (idx, _) in references() {
+        offsets[idx as usize + 1] += 1;
+    }
+    for idx in 1..offsets.len() {
+        offsets[idx] += offsets[idx - 1];
+    }
+
+    let mut ranges = flatdata::Vector::<ReverseIndexRange>::new();
+    for &offset in &offsets {
+        ranges.grow().set_first_idx(offset);
+    }
+    builder.set_ranges(ranges.as_view())?;
+
+    let mut entries = vec![EntityIndex::new(); offsets[len] as usize];
+    for (idx, referencing) in references() {
+        let pos = &mut offsets[idx as usize];
+        entries[*pos as usize].set_value(referencing);
+        *pos += 1;
+    }
+    builder.set_entries(&entries)
+}
+
+/// Builds the `node_ways` subarchive of `archive` mapping each node to the ways
+/// containing it.
+///
+/// Ways containing a node several times, e.g. closed ways, are stored once.
+/// References to nodes missing in the archive are skipped.
+pub fn build_node_ways(archive: &Osm, builder: &ReverseIndexBuilder) -> io::Result<()> {
+    let nodes_index = archive.nodes_index();
+    let references = || {
+        archive
+            .ways()
+            .iter()
+            .enumerate()
+            .flat_map(move |(way_idx, way)| {
+                let mut nodes: Vec<u64> = way
+                    .refs()
+                    .filter_map(|idx| nodes_index[idx as usize].value())
+                    .collect();
+                nodes.sort_unstable();
+                nodes.dedup();
+                nodes
+                    .into_iter()
+                    .map(move |node_idx| (node_idx, way_idx as u64))
+            })
+    };
+    write_reverse_index(archive.nodes().len(), references, builder)
+}
+
+/// Builds the subarchive of `archive` mapping each entity of type
+/// `entity_type` to the relations having it as member, i.e. `node_relations`,
+/// `way_relations` or `relation_relations`.
+///
+/// Relations having an entity as member several times, e.g. with different
+/// roles, are stored once. Members missing in the archive are skipped.
+pub fn build_member_relations(
+    archive: &Osm,
+    entity_type: EntityType,
+    builder: &ReverseIndexBuilder,
+) -> io::Result<()> {
+    let relation_members = archive.relation_members();
+    let references = || {
+        (0..archive.relations().len()).flat_map(move |relation_idx| {
+            let mut members: Vec<u64> = relation_members
+                .at(relation_idx)
+                .filter_map(|member| match (member, entity_type) {
+                    (RelationMembersRef::NodeMember(m), EntityType::Node) => m.node_idx(),
+                    (RelationMembersRef::WayMember(m), EntityType::Way) => m.way_idx(),
+                    (RelationMembersRef::RelationMember(m), EntityType::Relation) => {
+                        m.relation_idx()
+                    }
+                    _ => None,
+                })
+                .collect();
+            members.sort_unstable();
+            members.dedup();
+            members
+                .into_iter()
+                .map(move |idx| (idx, relation_idx as u64))
+        })
+    };
+    let len = match entity_type {
+        EntityType::Node => archive.nodes().len(),
+        EntityType::Way => archive.ways().len(),
+        EntityType::Relation => archive.relations().len(),
+    };
+    write_reverse_index(len, references, builder)
+}
+
+impl Osm {
+    /// Returns the indexes of the ways containing the node with the given
+    /// index, in ascending order.
+    ///
+    /// Returns `None` if the archive does not contain the `node_ways`
+    /// subarchive. Panics if the index is out of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use osmflat::{FileResourceStorage, Osm};
+    ///
+    /// let archive = Osm::open(FileResourceStorage::new("path/to/archive.osm.flatdata")).unwrap();
+    /// for idx in 0..archive.nodes().len() {
+    ///     if archive.ways_containing_node(idx).unwrap().len() > 1 {
+    ///         println!("node {idx} is an intersection");
+    ///     }
+    /// }
+    /// ```
+    pub fn ways_containing_node(
+        &self,
+        idx: usize,
+    ) -> Option<impl ExactSizeIterator<Item = u64> + '_> {
+        Some(self.node_ways()?.entries_of(idx))
+    }
+
+    /// Returns the indexes of the relations having the entity of type
+    /// `entity_type` with the given index as member, in ascending order.
+    ///
+    /// Returns `None` if the archive does not contain the subarchive of the
+    /// entity type, cf. [`build_member_relations`]. Panics if the index is out
+    /// of bounds.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use osmflat::{find_tag, EntityType, FileResourceStorage, Osm};
+    ///
+    /// let archive = Osm::open(FileResourceStorage::new("path/to/archive.osm.flatdata")).unwrap();
+    /// for relation_idx in archive.relations_containing(EntityType::Way, 0).unwrap() {
+    ///     let relation = &archive.relations()[relation_idx as usize];
+    ///     println!("{:?}", find_tag(&archive, relation.tags(), b"type"));
+    /// }
+    /// ```
+    pub fn relations_containing(
+        &self,
+        entity_type: EntityType,
+        idx: usize,
+    ) -> Option<impl ExactSizeIterator<Item = u64> + '_> {
+        let index = match entity_type {
+            EntityType::Node => self.node_relations(),
+            EntityType::Way => self.way_relations(),
+            EntityType::Relation => self.relation_relations(),
+        };
+        Some(index?.entries_of(idx))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Member, OsmWriter};
+    use flatdata::ResourceStorage;
+
+    #[test]
+    fn test_ways_containing_node() {
+        let storage = flatdata::MemoryResourceStorage::new("/test");
+        let mut writer = OsmWriter::new(storage.clone()).unwrap();
+        let tags = None::<(&str, &str)>;
+        for i in 0..4 {
+            writer.add_node(f64::from(i), 0.0, tags);
+        }
+        writer.add_way(&[0, 1, 2, 0], tags);
+        writer.add_way(&[2, 3], tags);
+        writer.finish().unwrap();
+
+        let archive = Osm::open(storage.clone()).unwrap();
+        assert!(archive.ways_containing_node(0).is_none());
+        let builder = ReverseIndexBuilder::new(storage.subdir("node_ways")).unwrap();
+        build_node_ways(&archive, &builder).unwrap();
+
+        let archive = Osm::open(storage).unwrap();
+        let ways: Vec<Vec<u64>> = (0..4)
+            .map(|idx| archive.ways_containing_node(idx).unwrap().collect())
+            .collect();
+        assert_eq!(ways, [vec![0], vec![0], vec![0, 1], vec![1]]);
+    }
+
+    #[test]
+    fn test_relations_containing() {
+        let storage = flatdata::MemoryResourceStorage::new("/test");
+        let mut writer = OsmWriter::new(storage.clone()).unwrap();
+        let tags = None::<(&str, &str)>;
+        let node = writer.add_node(0.0, 0.0, tags);
+        let way = writer.add_way(&[node], tags);
+        writer.add_relation(
+            &[Member::Way(way, "outer"), Member::Way(way, "inner")],
+            tags,
+        );
+        writer.add_relation(&[Member::Node(node, ""), Member::Relation(0, "")], tags);
+        writer.add_relation(&[Member::Relation(0, ""), Member::Way(way, "")], tags);
+        writer.finish().unwrap();
+
+        let archive = Osm::open(storage.clone()).unwrap();
+        assert!(archive.relations_containing(EntityType::Way, 0).is_none());
+        for (entity_type, name) in [
+            (EntityType::Node, "node_relations"),
+            (EntityType::Way, "way_relations"),
+            (EntityType::Relation, "relation_relations"),
+        ] {
+            let builder = ReverseIndexBuilder::new(storage.subdir(name)).unwrap();
+            build_member_relations(&archive, entity_type, &builder).unwrap();
+        }
+
+        let archive = Osm::open(storage).unwrap();
+        let relations = |entity_type, idx| -> Vec<u64> {
+            archive
+                .relations_containing(entity_type, idx)
+                .unwrap()
+                .collect()
+        };
+        assert_eq!(relations(EntityType::Node, 0), [1]);
+        assert_eq!(relations(EntityType::Way, 0), [0, 2]);
+        assert_eq!(relations(EntityType::Relation, 0), [1, 2]);
+        assert!(relations(EntityType::Relation, 1).is_empty());
+    }
+}
@@ -0,0 +1,142 @@
+//! Table of the distinct roles of relation members.
+//!
+//! Members reference their role by its 40 bit index in the `stringtable`.
+//! Since the strings are deduplicated, members with the same role have the
+//! same `role_idx`, so roles can be compared as integers once the index of a
+//! role is known. The optional `roles` vector, compiled with `osmflatc
+//! --roles`, lists the distinct roles, which finds the index of a role without
+//! scanning the `stringtable`, cf. [`Osm::find_role`], and numbers the roles
+//! densely, cf. [`Osm::interned_role`].
+
+use crate::{Osm, RelationMembersRef, StringOffset};
+
+use std::collections::BTreeSet;
+
+/// Returns the index in the `stringtable` of the role of a member.
+pub fn member_role_idx(member: &RelationMembersRef) -> u64 {
+    match member {
+        RelationMembersRef::NodeMember(m) => m.role_idx(),
+        RelationMembersRef::WayMember(m) => m.role_idx(),
+        RelationMembersRef::RelationMember(m) => m.role_idx(),
+    }
+}
+
+/// Builds the `roles` vector of `archive` from the roles of all relation
+/// members.
+pub fn build_roles(archive: &Osm) -> Vec<StringOffset> {
+    let relation_members = archive.relation_members();
+    let roles: BTreeSet<u64> = (0..archive.relations().len().min(relation_members.len()))
+        .flat_map(|idx| relation_members.at(idx))
+        .map(|member| member_role_idx(&member))
+        .collect();
+    roles
+        .into_iter()
+        .map(|role_idx| {
+            let mut offset = StringOffset::new();
+            offset.set_value(role_idx);
+            offset
+        })
+        .collect()
+}
+
+impl Osm {
+    /// Returns the index in the `stringtable` of `role`, which is the
+    /// `role_idx` of all members with this role.
+    ///
+    /// Returns `None` if no member has this role, or if the archive does not
+    /// contain the `roles` vector.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use osmflat::{member_role_idx, FileResourceStorage, Osm};
+    ///
+    /// let archive = Osm::open(FileResourceStorage::new("path/to/archive.osm.flatdata")).unwrap();
+    /// let inner = archive.find_role(b"inner");
+    /// let num_inner = archive
+    ///     .relation_members()
+    ///     .at(0)
+    ///     .filter(|member| Some(member_role_idx(member)) == inner)
+    ///     .count();
+    /// ```
+    pub fn find_role(&self, role: &[u8]) -> Option<u64> {
+        let strings = self.stringtable();
+        self.roles()?
+            .iter()
+            .map(|offset| offset.value())
+            .find(|&role_idx| strings.substring_raw(role_idx as usize) == role)
+    }
+
+    /// Returns the position in the `roles` vector of the role with index
+    /// `role_idx` in the `stringtable`, a narrow index numbering the distinct
+    /// roles from 0.
+    ///
+    /// Returns `None` if no member has this role, or if the archive does not
+    /// contain the `roles` vector.
+    pub fn interned_role(&self, role_idx: u64) -> Option<u32> {
+        let roles = self.roles()?;
+        let pos = roles.partition_point(|offset| offset.value() < role_idx);
+        (roles.get(pos)?.value() == role_idx).then_some(pos as u32)
+    }
+
+    /// Returns the role at position `interned` in the `roles` vector, cf.
+    /// [`Osm::interned_role`].
+    ///
+    /// Returns `None` if the position is out of bounds, or if the archive does
+    /// not contain the `roles` vector.
+    pub fn role(&self, interned: u32) -> Option<&[u8]> {
+        let role_idx = self.roles()?.get(interned as usize)?.value();
+        Some(self.stringtable().substring_raw(role_idx as usize))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Member, OsmWriter};
+    use flatdata::{ResourceStorage, SliceExt};
+
+    #[test]
+    fn test_roles() {
+        let storage = flatdata::MemoryResourceStorage::new("/test");
+        let mut writer = OsmWriter::new(storage.clone()).unwrap();
+        let tags = None::<(&str, &str)>;
+        let node = writer.add_node(0.0, 0.0, tags);
+        let way = writer.add_way(&[node, node], tags);
+        writer.add_relation(&[Member::Way(way, "outer"), Member::Node(node, "")], tags);
+        writer.add_relation(
+            &[Member::Way(way, "inner"), Member::Relation(0, "outer")],
+            tags,
+        );
+        writer.finish().unwrap();
+
+        let archive = Osm::open(storage.clone()).unwrap();
+        assert_eq!(archive.find_role(b"outer"), None);
+        let roles = build_roles(&archive);
+        assert_eq!(roles.len(), 3);
+        storage
+            .write(
+                "roles",
+                crate::schema::osm::resources::ROLES,
+                roles.as_slice().as_bytes(),
+            )
+            .unwrap();
+
+        let archive = Osm::open(storage).unwrap();
+        let outer = archive.find_role(b"outer").unwrap();
+        let members: Vec<_> = archive.relation_members().at(1).collect();
+        assert_eq!(member_role_idx(&members[1]), outer);
+        assert_ne!(member_role_idx(&members[0]), outer);
+        assert_eq!(archive.find_role(b"forward"), None);
+
+        let interned: Vec<u32> = (0..2)
+            .flat_map(|idx| archive.relation_members().at(idx))
+            .map(|member| archive.interned_role(member_role_idx(&member)).unwrap())
+            .collect();
+        assert_eq!(interned.iter().max(), Some(&2));
+        assert_eq!(interned[0], interned[3]);
+        assert_eq!(archive.role(interned[2]), Some(&b"inner"[..]));
+        assert_eq!(archive.role(3), None);
+        assert_eq!(archive.interned_role(outer + 1), None);
+    }
+}
@@ -0,0 +1,622 @@
+//! Bounding box queries backed by the `spatial_index` subarchive.
+//!
+//! The subarchive is compiled with `osmflatc --spatial-index`. It contains a
+//! packed R-tree for each entity type, cf. [`SpatialIndex`].
+
+use crate::geometry::{area_contains, polyline_intersects};
+use crate::{
+    find_tag, hilbert_index, BoundingBox, Node, NodeCoord, Osm, RelationMembersRef, SpatialBox,
+    SpatialIndex,
+};
+
+use std::collections::HashSet;
+
+/// Maximal number of children of a box in the spatial index.
+pub const SPATIAL_INDEX_NODE_SIZE: usize = 16;
+
+/// Bounding box in the coordinates of an archive, cf. `Header::coord_scale`.
+///
+/// The bounds are inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BBox {
+    /// Minimal longitude
+    pub left: i32,
+    /// Minimal latitude
+    pub bottom: i32,
+    /// Maximal longitude
+    pub right: i32,
+    /// Maximal latitude
+    pub top: i32,
+}
+
+impl BBox {
+    /// Bounding box containing a single coordinate.
+    pub fn from_coord(lon: i32, lat: i32) -> Self {
+        Self {
+            left: lon,
+            bottom: lat,
+            right: lon,
+            top: lat,
+        }
+    }
+
+    /// Bounding box given in degrees, converted to the coordinates of
+    /// `archive`.
+    pub fn from_degrees(archive: &Osm, min: (f64, f64), max: (f64, f64)) -> Self {
+        let scale = f64::from(archive.header().coord_scale());
+        let coord = |x: f64| (x * scale).round() as i32;
+        Self {
+            left: coord(min.0),
+            bottom: coord(min.1),
+            right: coord(max.0),
+            top: coord(max.1),
+        }
+    }
+
+    /// Bounding box in degrees as `(min, max)` with coordinates `(lon, lat)`,
+    /// the inverse of [`BBox::from_degrees`].
+    pub fn to_degrees(&self, archive: &Osm) -> ((f64, f64), (f64, f64)) {
+        let scale = f64::from(archive.header().coord_scale());
+        let degrees = |x: i32| f64::from(x) / scale;
+        (
+            (degrees(self.left), degrees(self.bottom)),
+            (degrees(self.right), degrees(self.top)),
+        )
+    }
+
+    /// Extends the bounding box to contain `other`.
+    pub fn extend(&mut self, other: &BBox) {
+        self.left = self.left.min(other.left);
+        self.bottom = self.bottom.min(other.bottom);
+        self.right = self.right.max(other.right);
+        self.top = self.top.max(other.top);
+    }
+
+    /// Whether the bounding boxes have a point in common.
+    pub fn intersects(&self, other: &BBox) -> bool {
+        self.left <= other.right
+            && other.left <= self.right
+            && self.bottom <= other.top
+            && other.bottom <= self.top
+    }
+
+    /// Center of the bounding box as `(lon, lat)`.
+    pub fn center(&self) -> (i32, i32) {
+        let mid = |a: i32, b: i32| ((i64::from(a) + i64::from(b)) / 2) as i32;
+        (mid(self.left, self.right), mid(self.bottom, self.top))
+    }
+
+    fn from_spatial_box(b: &SpatialBox) -> Self {
+        Self {
+            left: b.left(),
+            bottom: b.bottom(),
+            right: b.right(),
+            top: b.top(),
+        }
+    }
+}
+
+impl BoundingBox {
+    /// The bounding box, or `None` if it is empty.
+    pub fn bbox(&self) -> Option<BBox> {
+        (self.left() <= self.right()).then(|| BBox {
+            left: self.left(),
+            bottom: self.bottom(),
+            right: self.right(),
+            top: self.top(),
+        })
+    }
+}
+
+impl From<Option<BBox>> for BoundingBox {
+    fn from(bbox: Option<BBox>) -> Self {
+        let bbox = bbox.unwrap_or(BBox {
+            left: i32::MAX,
+            bottom: i32::MAX,
+            right: i32::MIN,
+            top: i32::MIN,
+        });
+        let mut b = BoundingBox::new();
+        b.set_left(bbox.left);
+        b.set_bottom(bbox.bottom);
+        b.set_right(bbox.right);
+        b.set_top(bbox.top);
+        b
+    }
+}
+
+/// Bounding box of the way at `idx`, or `None` if none of its nodes is in the
+/// archive.
+///
+/// The bounding box is taken from the `way_bboxes` resource if present, cf.
+/// `osmflatc --bboxes`; otherwise, it is computed from the nodes of the way.
+pub fn way_bbox(archive: &Osm, idx: usize) -> Option<BBox> {
+    if let Some(bboxes) = archive.way_bboxes() {
+        return bboxes[idx].bbox();
+    }
+    let nodes_index = archive.nodes_index();
+    archive.ways()[idx]
+        .refs()
+        .filter_map(|i| nodes_index[i as usize].value())
+        .map(|node_idx| {
+            let (lon, lat) = archive.node_coord_scaled(node_idx as usize);
+            BBox::from_coord(lon, lat)
+        })
+        .reduce(|mut bbox, other| {
+            bbox.extend(&other);
+            bbox
+        })
+}
+
+/// Bounding box of the relation at `idx`, or `None` if none of its members is
+/// in the archive.
+///
+/// The bounding box contains the bounding boxes of all members, including the
+/// members of relation members. It is taken from the `relation_bboxes`
+/// resource if present, cf. `osmflatc --bboxes`; otherwise, it is computed by
+/// walking the members of the relation.
+pub fn relation_bbox(archive: &Osm, idx: usize) -> Option<BBox> {
+    if let Some(bboxes) = archive.relation_bboxes() {
+        return bboxes[idx].bbox();
+    }
+    let relation_members = archive.relation_members();
+    let mut bbox: Option<BBox> = None;
+    let mut extend = |other: Option<BBox>| match (&mut bbox, other) {
+        (Some(bbox), Some(other)) => bbox.extend(&other),
+        (bbox, other) => *bbox = bbox.or(other),
+    };
+    // Relations may contain each other, so each one is visited only once.
+    let mut visited = HashSet::from([idx]);
+    let mut stack = vec![idx];
+    while let Some(relation_idx) = stack.pop() {
+        for member in relation_members.at(relation_idx) {
+            match member {
+                RelationMembersRef::NodeMember(m) => {
+                    extend(m.node_idx().map(|i| {
+                        let (lon, lat) = archive.node_coord_scaled(i as usize);
+                        BBox::from_coord(lon, lat)
+                    }));
+                }
+                RelationMembersRef::WayMember(m) => {
+                    extend(m.way_idx().and_then(|i| way_bbox(archive, i as usize)));
+                }
+                RelationMembersRef::RelationMember(m) => {
+                    if let Some(i) = m.relation_idx().map(|i| i as usize) {
+                        if visited.insert(i) {
+                            stack.push(i);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    bbox
+}
+
+fn spatial_box(bbox: &BBox, index: u64) -> SpatialBox {
+    let mut b = SpatialBox::new();
+    b.set_left(bbox.left);
+    b.set_bottom(bbox.bottom);
+    b.set_right(bbox.right);
+    b.set_top(bbox.top);
+    b.set_index(index);
+    b
+}
+
+/// Builds a packed R-tree from the bounding boxes of entities given together
+/// with their indexes, in the layout of the [`SpatialIndex`] vectors.
+pub fn build_spatial_index(mut entities: Vec<(BBox, u64)>) -> Vec<SpatialBox> {
+    entities.sort_by_cached_key(|(bbox, _)| {
+        let (lon, lat) = bbox.center();
+        hilbert_index(lon, lat)
+    });
+
+    let mut boxes: Vec<SpatialBox> = entities
+        .iter()
+        .map(|(bbox, idx)| spatial_box(bbox, *idx))
+        .collect();
+    let mut level = 0..boxes.len();
+    while level.len() > 1 {
+        let mut parent_level = boxes.len()..boxes.len();
+        for first in level.clone().step_by(SPATIAL_INDEX_NODE_SIZE) {
+            let children = first..(first + SPATIAL_INDEX_NODE_SIZE).min(level.end);
+            let mut bbox = BBox::from_spatial_box(&boxes[first]);
+            for child in &boxes[children] {
+                bbox.extend(&BBox::from_spatial_box(child));
+            }
+            boxes.push(spatial_box(&bbox, first as u64));
+            parent_level.end += 1;
+        }
+        level = parent_level;
+    }
+    boxes
+}
+
+/// Iterator over the indexes of the entities whose bounding boxes intersect a
+/// bounding box, cf. [`query_spatial_index`].
+///
+/// The indexes are not ordered.
+#[derive(Debug, Clone)]
+pub struct SpatialQuery<'a> {
+    boxes: &'a [SpatialBox],
+    bbox: BBox,
+    /// Start of each level of the tree, from the root to the leaves
+    level_starts: Vec<usize>,
+    /// Boxes to visit as (level, position)
+    stack: Vec<(usize, usize)>,
+}
+
+impl Iterator for SpatialQuery<'_> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        while let Some((level, pos)) = self.stack.pop() {
+            let b = &self.boxes[pos];
+            if level + 1 == self.level_starts.len() {
+                return Some(b.index());
+            }
+            let first = b.index() as usize;
+            let end = (first + SPATIAL_INDEX_NODE_SIZE).min(self.level_starts[level]);
+            for child in (first..end).rev() {
+                if self
+                    .bbox
+                    .intersects(&BBox::from_spatial_box(&self.boxes[child]))
+                {
+                    self.stack.push((level + 1, child));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Queries a vector of the spatial index for the entities whose bounding
+/// boxes intersect `bbox`.
+pub fn query_spatial_index(boxes: &[SpatialBox], bbox: BBox) -> SpatialQuery<'_> {
+    let mut level_starts = Vec::new();
+    let mut stack = Vec::new();
+    if let Some(root) = boxes.len().checked_sub(1) {
+        // the leaves are the level starting at 0
+        let mut start = root;
+        level_starts.push(start);
+        while start != 0 {
+            start = boxes[start].index() as usize;
+            level_starts.push(start);
+        }
+        if bbox.intersects(&BBox::from_spatial_box(&boxes[root])) {
+            stack.push((0, root));
+        }
+    }
+    SpatialQuery {
+        boxes,
+        bbox,
+        level_starts,
+        stack,
+    }
+}
+
+impl SpatialIndex {
+    /// Indexes of the nodes inside `bbox`.
+    pub fn nodes_in_bbox(&self, bbox: BBox) -> SpatialQuery<'_> {
+        query_spatial_index(self.nodes(), bbox)
+    }
+
+    /// Indexes of the ways whose bounding boxes intersect `bbox`.
+    pub fn ways_in_bbox(&self, bbox: BBox) -> SpatialQuery<'_> {
+        query_spatial_index(self.ways(), bbox)
+    }
+
+    /// Indexes of the relations whose bounding boxes intersect `bbox`.
+    pub fn relations_in_bbox(&self, bbox: BBox) -> SpatialQuery<'_> {
+        query_spatial_index(self.relations(), bbox)
+    }
+}
+
+/// How [`Osm::ways_in_bbox`] and [`Osm::relations_in_bbox`] match entities
+/// with a bounding box.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BBoxMatch {
+    /// Entities whose bounding boxes intersect the bounding box
+    ///
+    /// This is fast, but includes entities whose geometry is outside of the
+    /// bounding box, e.g. ways passing by diagonally.
+    #[default]
+    BoundingBox,
+    /// Entities whose geometry has a point in common with the bounding box
+    ///
+    /// Closed ways and relations of type `multipolygon` or `boundary` are
+    /// areas, which also match bounding boxes inside of them.
+    Geometry,
+}
+
+/// Coordinates of the nodes of the way at `idx` in the archive.
+fn way_coords(archive: &Osm, idx: usize) -> Vec<(i32, i32)> {
+    let nodes_index = archive.nodes_index();
+    archive.ways()[idx]
+        .refs()
+        .filter_map(|i| nodes_index[i as usize].value())
+        .map(|node_idx| archive.node_coord_scaled(node_idx as usize))
+        .collect()
+}
+
+fn is_closed(coords: &[(i32, i32)]) -> bool {
+    coords.len() >= 4 && coords.first() == coords.last()
+}
+
+fn segments(coords: &[(i32, i32)]) -> impl Iterator<Item = ((i32, i32), (i32, i32))> + '_ {
+    coords.windows(2).map(|w| (w[0], w[1]))
+}
+
+fn corner(bbox: &BBox) -> (i32, i32) {
+    (bbox.left, bbox.bottom)
+}
+
+/// Whether the geometry of the way at `idx` has a point in common with `bbox`.
+fn way_intersects(archive: &Osm, idx: usize, bbox: &BBox) -> bool {
+    let coords = way_coords(archive, idx);
+    polyline_intersects(&coords, bbox)
+        || (is_closed(&coords) && area_contains(segments(&coords), corner(bbox)))
+}
+
+/// Whether the geometry of the relation at `idx`, i.e. of all its members, has
+/// a point in common with `bbox`.
+fn relation_intersects(archive: &Osm, idx: usize, bbox: &BBox) -> bool {
+    let relations = archive.relations();
+    let relation_members = archive.relation_members();
+    // Relations may contain each other, so each one is visited only once.
+    let mut visited = HashSet::from([idx]);
+    let mut stack = vec![idx];
+    while let Some(relation_idx) = stack.pop() {
+        let is_area = matches!(
+            find_tag(archive, relations[relation_idx].tags(), b"type"),
+            Some(b"multipolygon" | b"boundary")
+        );
+        let mut rings = Vec::new();
+        for member in relation_members.at(relation_idx) {
+            match member {
+                RelationMembersRef::NodeMember(m) => {
+                    if let Some(i) = m.node_idx() {
+                        let (lon, lat) = archive.node_coord_scaled(i as usize);
+                        if bbox.intersects(&BBox::from_coord(lon, lat)) {
+                            return true;
+                        }
+                    }
+                }
+                RelationMembersRef::WayMember(m) => {
+                    if let Some(i) = m.way_idx() {
+                        if way_intersects(archive, i as usize, bbox) {
+                            return true;
+                        }
+                        if is_area {
+                            rings.push(way_coords(archive, i as usize));
+                        }
+                    }
+                }
+                RelationMembersRef::RelationMember(m) => {
+                    if let Some(i) = m.relation_idx().map(|i| i as usize) {
+                        if visited.insert(i) {
+                            stack.push(i);
+                        }
+                    }
+                }
+            }
+        }
+        if is_area && area_contains(rings.iter().flat_map(|c| segments(c)), corner(bbox)) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Iterator over the indexes of the nodes inside a bounding box, cf.
+/// [`Osm::nodes_in_bbox`].
+///
+/// The indexes are not ordered if they are taken from the spatial index.
+#[derive(Debug, Clone)]
+pub struct NodesInBBox<'a>(NodesInBBoxInner<'a>);
+
+#[derive(Debug, Clone)]
+enum NodesInBBoxInner<'a> {
+    SpatialIndex(SpatialQuery<'a>),
+    Scan {
+        nodes: std::iter::Enumerate<std::slice::Iter<'a, Node>>,
+        bbox: BBox,
+    },
+    ScanCoords {
+        coords: std::iter::Enumerate<std::slice::Iter<'a, NodeCoord>>,
+        bbox: BBox,
+    },
+}
+
+impl Iterator for NodesInBBox<'_> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        match &mut self.0 {
+            NodesInBBoxInner::SpatialIndex(query) => query.next(),
+            NodesInBBoxInner::Scan { nodes, bbox } => nodes
+                .find(|(_, node)| bbox.intersects(&BBox::from_coord(node.lon(), node.lat())))
+                .map(|(idx, _)| idx as u64),
+            NodesInBBoxInner::ScanCoords { coords, bbox } => coords
+                .find(|(_, coord)| bbox.intersects(&BBox::from_coord(coord.lon(), coord.lat())))
+                .map(|(idx, _)| idx as u64),
+        }
+    }
+}
+
+impl Osm {
+    /// Returns the indexes of the nodes inside the bounding box given in
+    /// degrees; the bounds are inclusive.
+    ///
+    /// The nodes are looked up in the `spatial_index` subarchive if present,
+    /// cf. `osmflatc --spatial-index`; otherwise, all nodes are scanned, or
+    /// their coordinates in the `node_coords` vector if present.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use osmflat::{FileResourceStorage, Osm};
+    ///
+    /// let archive = Osm::open(FileResourceStorage::new("path/to/archive.osm.flatdata")).unwrap();
+    /// let nodes = archive.nodes();
+    /// for idx in archive.nodes_in_bbox(13.37, 52.51, 13.38, 52.52) {
+    ///     println!("{:?}", nodes[idx as usize]);
+    /// }
+    /// ```
+    pub fn nodes_in_bbox(
+        &self,
+        min_lon: f64,
+        min_lat: f64,
+        max_lon: f64,
+        max_lat: f64,
+    ) -> NodesInBBox<'_> {
+        let bbox = BBox::from_degrees(self, (min_lon, min_lat), (max_lon, max_lat));
+        NodesInBBox(match (self.spatial_index(), self.node_coords()) {
+            (Some(spatial_index), _) => {
+                NodesInBBoxInner::SpatialIndex(spatial_index.nodes_in_bbox(bbox))
+            }
+            (None, Some(node_coords)) => NodesInBBoxInner::ScanCoords {
+                coords: node_coords.iter().enumerate(),
+                bbox,
+            },
+            (None, None) => NodesInBBoxInner::Scan {
+                nodes: self.nodes().iter().enumerate(),
+                bbox,
+            },
+        })
+    }
+
+    /// Returns the indexes of the ways intersecting the bounding box given in
+    /// degrees; the bounds are inclusive. The indexes are not ordered.
+    ///
+    /// The candidates are looked up in the `spatial_index` subarchive if
+    /// present, cf. `osmflatc --spatial-index`; otherwise, the bounding boxes of
+    /// all ways are checked, cf. [`way_bbox`]. With [`BBoxMatch::Geometry`],
+    /// the candidates are refined by their geometry.
+    pub fn ways_in_bbox(
+        &self,
+        min_lon: f64,
+        min_lat: f64,
+        max_lon: f64,
+        max_lat: f64,
+        matching: BBoxMatch,
+    ) -> Box<dyn Iterator<Item = u64> + '_> {
+        let bbox = BBox::from_degrees(self, (min_lon, min_lat), (max_lon, max_lat));
+        let candidates: Box<dyn Iterator<Item = u64>> = match self.spatial_index() {
+            Some(spatial_index) => Box::new(spatial_index.ways_in_bbox(bbox)),
+            None => Box::new(
+                (0..self.ways().len())
+                    .filter(move |&idx| way_bbox(self, idx).is_some_and(|b| b.intersects(&bbox)))
+                    .map(|idx| idx as u64),
+            ),
+        };
+        match matching {
+            BBoxMatch::BoundingBox => candidates,
+            BBoxMatch::Geometry => {
+                Box::new(candidates.filter(move |&idx| way_intersects(self, idx as usize, &bbox)))
+            }
+        }
+    }
+
+    /// Returns the indexes of the relations intersecting the bounding box given
+    /// in degrees; the bounds are inclusive. The indexes are not ordered.
+    ///
+    /// The candidates are looked up in the `spatial_index` subarchive if
+    /// present; otherwise, the bounding boxes of all relations are checked, cf.
+    /// [`relation_bbox`]. With [`BBoxMatch::Geometry`], the candidates are
+    /// refined by the geometry of their members.
+    pub fn relations_in_bbox(
+        &self,
+        min_lon: f64,
+        min_lat: f64,
+        max_lon: f64,
+        max_lat: f64,
+        matching: BBoxMatch,
+    ) -> Box<dyn Iterator<Item = u64> + '_> {
+        let bbox = BBox::from_degrees(self, (min_lon, min_lat), (max_lon, max_lat));
+        let candidates: Box<dyn Iterator<Item = u64>> = match self.spatial_index() {
+            Some(spatial_index) => Box::new(spatial_index.relations_in_bbox(bbox)),
+            None => Box::new(
+                (0..self.relations().len())
+                    .filter(move |&idx| {
+                        relation_bbox(self, idx).is_some_and(|b| b.intersects(&bbox))
+                    })
+                    .map(|idx| idx as u64),
+            ),
+        };
+        match matching {
+            BBoxMatch::BoundingBox => candidates,
+            BBoxMatch::Geometry => Box::new(
+                candidates.filter(move |&idx| relation_intersects(self, idx as usize, &bbox)),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_query_spatial_index() {
+        let entities: Vec<_> = (0..50)
+            .flat_map(|x| (0..50).map(move |y| (x, y)))
+            .enumerate()
+            .map(|(idx, (x, y))| (BBox::from_coord(x * 10, y * 10), idx as u64))
+            .collect();
+        let boxes = build_spatial_index(entities.clone());
+        assert!(boxes.len() > entities.len());
+
+        for bbox in [
+            BBox {
+                left: 15,
+                bottom: 0,
+                right: 42,
+                top: 300,
+            },
+            BBox::from_coord(250, 250),
+            BBox::from_coord(255, 250),
+            BBox {
+                left: -100,
+                bottom: -100,
+                right: 1000,
+                top: 1000,
+            },
+        ] {
+            let mut result: Vec<_> = query_spatial_index(&boxes, bbox).collect();
+            result.sort_unstable();
+            let expected: Vec<_> = entities
+                .iter()
+                .filter(|(b, _)| b.intersects(&bbox))
+                .map(|(_, idx)| *idx)
+                .collect();
+            assert_eq!(result, expected);
+        }
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let bbox = BBox {
+            left: -3,
+            bottom: 1,
+            right: 5,
+            top: 2,
+        };
+        assert_eq!(BoundingBox::from(Some(bbox)).bbox(), Some(bbox));
+        assert_eq!(BoundingBox::from(None).bbox(), None);
+        let point = BBox::from_coord(i32::MAX, i32::MIN);
+        assert_eq!(BoundingBox::from(Some(point)).bbox(), Some(point));
+    }
+
+    #[test]
+    fn test_query_small_spatial_index() {
+        let bbox = BBox::from_coord(1, 1);
+        assert_eq!(query_spatial_index(&[], bbox).count(), 0);
+        let boxes = build_spatial_index(vec![(bbox, 7)]);
+        assert_eq!(query_spatial_index(&boxes, bbox).collect::<Vec<_>>(), [7]);
+        assert_eq!(
+            query_spatial_index(&boxes, BBox::from_coord(0, 0)).count(),
+            0
+        );
+    }
+}
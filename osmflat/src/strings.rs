@@ -0,0 +1,116 @@
+//! Offsets of the strings in the `stringtable`.
+//!
+//! Strings in the `stringtable` are terminated by NUL, so reading a string
+//! scans for its terminator. The optional `stringtable_offsets` vector,
+//! compiled with `osmflatc --stringtable-offsets`, stores the offsets of all
+//! strings, which bounds a string by binary searching the offset of the next
+//! one, cf. [`Osm::substring_fast`].
+
+use crate::{Osm, StringOffset};
+
+/// Builds the `stringtable_offsets` vector from the bytes of the `stringtable`.
+///
+/// The vector contains the offset of each string in ascending order, followed
+/// by the size of the `stringtable`.
+pub fn build_stringtable_offsets(strings: &[u8]) -> Vec<StringOffset> {
+    let offset = |value: usize| {
+        let mut offset = StringOffset::new();
+        offset.set_value(value as u64);
+        offset
+    };
+    let starts = strings
+        .iter()
+        .enumerate()
+        .filter(|&(idx, &b)| b == 0 && idx + 1 < strings.len())
+        .map(|(idx, _)| idx + 1);
+    std::iter::once(0)
+        .chain(starts)
+        .chain(std::iter::once(strings.len()))
+        .map(offset)
+        .collect()
+}
+
+impl Osm {
+    /// Returns the string at index `idx` of the `stringtable` without its NUL
+    /// terminator.
+    ///
+    /// If the archive contains the `stringtable_offsets` vector, the end of the
+    /// string is found by a binary search of the offset of the next string
+    /// instead of scanning for the terminator. Otherwise, it falls back to
+    /// [`RawData::substring_raw`]. Indexes into the middle of a string, e.g. of
+    /// suffixes shared in an optimized `stringtable`, return the rest of the
+    /// string.
+    ///
+    /// Panics if the index is out of bounds.
+    ///
+    /// [`RawData::substring_raw`]: flatdata::RawData::substring_raw
+    pub fn substring_fast(&self, idx: usize) -> &[u8] {
+        let strings = self.stringtable();
+        let Some(offsets) = self.stringtable_offsets() else {
+            return strings.substring_raw(idx);
+        };
+        let strings = strings.as_bytes();
+        let pos = offsets.partition_point(|offset| offset.value() as usize <= idx);
+        let end = match offsets.get(pos) {
+            Some(next) => next.value() as usize - 1,
+            None => strings.len(),
+        };
+        &strings[idx..end]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::OsmBuilder;
+
+    fn open_archive(strings: &[u8], with_offsets: bool) -> Osm {
+        let storage = flatdata::MemoryResourceStorage::new("/test");
+        let builder = OsmBuilder::new(storage.clone()).unwrap();
+        builder.set_stringtable(strings).unwrap();
+        if with_offsets {
+            let offsets = build_stringtable_offsets(strings);
+            builder.set_stringtable_offsets(&offsets).unwrap();
+        }
+        builder.set_header(&crate::Header::new()).unwrap();
+        builder.start_nodes().unwrap().close().unwrap();
+        builder.start_ways().unwrap().close().unwrap();
+        builder.start_relations().unwrap().close().unwrap();
+        builder.start_relation_members().unwrap().close().unwrap();
+        builder.start_tags().unwrap().close().unwrap();
+        builder.start_tags_index().unwrap().close().unwrap();
+        builder.start_nodes_index().unwrap().close().unwrap();
+        Osm::open(storage).unwrap()
+    }
+
+    #[test]
+    fn test_build_stringtable_offsets() {
+        let values = |strings: &[u8]| -> Vec<u64> {
+            build_stringtable_offsets(strings)
+                .iter()
+                .map(|offset| offset.value())
+                .collect()
+        };
+        assert_eq!(values(b"osmflatc\0name\0\0Berlin\0"), [0, 9, 14, 15, 22]);
+        assert_eq!(values(b""), [0, 0]);
+    }
+
+    #[test]
+    fn test_substring_fast() {
+        let strings = b"osmflatc\0name\0\0Berlin\0";
+        let archive = open_archive(strings, true);
+        assert!(archive.stringtable_offsets().is_some());
+        assert_eq!(archive.substring_fast(0), b"osmflatc");
+        assert_eq!(archive.substring_fast(9), b"name");
+        assert_eq!(archive.substring_fast(14), b"");
+        assert_eq!(archive.substring_fast(18), b"lin");
+        for idx in 0..strings.len() {
+            let expected = archive.stringtable().substring_raw(idx);
+            assert_eq!(archive.substring_fast(idx), expected, "at {idx}");
+        }
+
+        let archive = open_archive(strings, false);
+        assert!(archive.stringtable_offsets().is_none());
+        assert_eq!(archive.substring_fast(15), b"Berlin");
+    }
+}
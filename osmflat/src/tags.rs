@@ -2,9 +2,87 @@
 //! It is easy to combine these with `std::str::from_utf8` family of functions,
 //! to lift them to operate on `str`.
 
-use crate::Osm;
+use crate::{set_bits, Node, Osm, Way};
+use std::borrow::Cow;
 use std::ops::Range;
 
+/// Map-like view of the tags in a range, e.g. of a single entity.
+///
+/// The keys and values are resolved in the stringtable once on construction,
+/// so reading several keys of the same entity does not scan the strings of
+/// its tags again. Use [`find_tag`] for reading a single key.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use osmflat::{FileResourceStorage, Osm, Tags};
+/// use std::collections::BTreeMap;
+///
+/// let archive = Osm::open(FileResourceStorage::new("path/to/archive.osm.flatdata")).unwrap();
+/// let tags = Tags::new(&archive, archive.ways()[0].tags());
+/// if tags.contains(b"highway") {
+///     println!("{:?} {:?}", tags.get(b"name"), tags.get(b"maxspeed"));
+/// }
+/// let map: BTreeMap<&[u8], &[u8]> = tags.iter().collect();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tags<'a> {
+    tags: Vec<(&'a [u8], &'a [u8])>,
+}
+
+impl<'a> Tags<'a> {
+    /// Resolves the tags specified by `range`.
+    pub fn new(archive: &'a Osm, range: Range<u64>) -> Self {
+        Self {
+            tags: iter_tags(archive, range).collect(),
+        }
+    }
+
+    /// Returns the value of the tag with `key`.
+    pub fn get(&self, key: &[u8]) -> Option<&'a [u8]> {
+        self.tags.iter().find(|(k, _)| *k == key).map(|(_, v)| *v)
+    }
+
+    /// Checks if there is a tag with `key`.
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns an iterator over the keys and values of the tags in the order
+    /// of the archive.
+    pub fn iter(&self) -> std::iter::Copied<std::slice::Iter<'_, (&'a [u8], &'a [u8])>> {
+        self.tags.iter().copied()
+    }
+
+    /// Returns the number of tags.
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    /// Checks if there are no tags.
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+}
+
+impl<'a> IntoIterator for Tags<'a> {
+    type Item = (&'a [u8], &'a [u8]);
+    type IntoIter = std::vec::IntoIter<(&'a [u8], &'a [u8])>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.tags.into_iter()
+    }
+}
+
+impl<'a, 'b> IntoIterator for &'b Tags<'a> {
+    type Item = (&'a [u8], &'a [u8]);
+    type IntoIter = std::iter::Copied<std::slice::Iter<'b, (&'a [u8], &'a [u8])>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
 /// Returns an iterator over tags specified by `range`.
 ///
 /// When searching for a tag by key consider to use `find_tag` which
@@ -23,6 +101,94 @@ pub fn iter_tags(archive: &Osm, range: Range<u64>) -> impl Iterator<Item = (&[u8
     })
 }
 
+/// Returns an iterator over tags specified by `range` as strings.
+///
+/// Invalid UTF-8 sequences are replaced by `U+FFFD REPLACEMENT CHARACTER`,
+/// cf. [`String::from_utf8_lossy`]; valid strings are borrowed from the
+/// stringtable.
+#[inline]
+pub fn iter_tags_lossy(
+    archive: &Osm,
+    range: Range<u64>,
+) -> impl Iterator<Item = (Cow<'_, str>, Cow<'_, str>)> + Clone {
+    iter_tags(archive, range)
+        .map(|(key, val)| (String::from_utf8_lossy(key), String::from_utf8_lossy(val)))
+}
+
+/// Returns an iterator over the tags of the changeset at index `idx`, e.g.
+/// `comment` and `created_by`.
+///
+/// Returns `None` if the archive does not contain the `changesets` subarchive,
+/// or if it was compiled without the tags of changesets. Panics if the index is
+/// out of bounds.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use osmflat::{FileResourceStorage, Osm};
+///
+/// let archive = Osm::open(FileResourceStorage::new("path/to/archive.osm.flatdata")).unwrap();
+/// for (key, val) in osmflat::iter_changeset_tags(&archive, 0).unwrap() {
+///     println!("{}={}", String::from_utf8_lossy(key), String::from_utf8_lossy(val));
+/// }
+/// ```
+pub fn iter_changeset_tags(
+    archive: &Osm,
+    idx: usize,
+) -> Option<impl Iterator<Item = (&[u8], &[u8])> + Clone> {
+    let changesets = archive.changesets()?;
+    let range = changesets.tag_ranges()?[idx].tags();
+    let tags_index = changesets.tags_index()?;
+    let tags = archive.tags();
+    let strings = archive.stringtable();
+
+    Some(range.map(move |idx| {
+        let tag = &tags[tags_index[idx as usize].value() as usize];
+        let key = strings.substring_raw(tag.key_idx() as usize);
+        let val = strings.substring_raw(tag.value_idx() as usize);
+        (key, val)
+    }))
+}
+
+/// Returns an iterator over the nodes having tags together with their indexes.
+///
+/// The nodes are taken from the `tagged_nodes` resource if present, cf.
+/// `osmflatc --tagged-nodes`, or from the `tagged_node_bits` bitset, cf.
+/// `osmflatc --tagged-bitsets`, which skip the untagged nodes; otherwise, all
+/// nodes are checked.
+pub fn iter_tagged_nodes(archive: &Osm) -> Box<dyn Iterator<Item = (usize, &Node)> + '_> {
+    let nodes = archive.nodes();
+    match (archive.tagged_nodes(), archive.tagged_node_bits()) {
+        (Some(tagged_nodes), _) => Box::new(tagged_nodes.iter().map(move |idx| {
+            let idx = idx.value() as usize;
+            (idx, &nodes[idx])
+        })),
+        (None, Some(bits)) => Box::new(set_bits(bits).map(move |idx| (idx, &nodes[idx]))),
+        (None, None) => Box::new(
+            nodes
+                .iter()
+                .enumerate()
+                .filter(|(_, node)| !node.tags().is_empty()),
+        ),
+    }
+}
+
+/// Returns an iterator over the ways having tags together with their indexes.
+///
+/// The ways are taken from the `tagged_way_bits` bitset if present, cf.
+/// `osmflatc --tagged-bitsets`; otherwise, all ways are checked.
+pub fn iter_tagged_ways(archive: &Osm) -> Box<dyn Iterator<Item = (usize, &Way)> + '_> {
+    let ways = archive.ways();
+    match archive.tagged_way_bits() {
+        Some(bits) => Box::new(set_bits(bits).map(move |idx| (idx, &ways[idx]))),
+        None => Box::new(
+            ways.iter()
+                .enumerate()
+                .filter(|(_, way)| !way.tags().is_empty()),
+        ),
+    }
+}
+
 /// Finds the first tag in the given `range` which satisfies the predicate
 /// applied to the key and value and returns the corresponding value.
 ///
@@ -62,6 +228,51 @@ pub fn find_tag<'a>(archive: &'a Osm, range: Range<u64>, key: &[u8]) -> Option<&
     })
 }
 
+/// Finds the tags with several keys in the given `range` in a single pass
+/// and returns their values in the order of `keys`.
+///
+/// Compared to calling [`find_tag`] for each key, the tags and their keys in
+/// the stringtable are read once. As with [`find_tag`], the first tag with a
+/// key is taken.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use osmflat::{get_tags, FileResourceStorage, Osm};
+///
+/// let archive = Osm::open(FileResourceStorage::new("path/to/archive.osm.flatdata")).unwrap();
+/// let node = &archive.nodes()[0];
+/// let [name, population] = get_tags(&archive, node.tags(), &[b"name", b"population"]);
+/// ```
+pub fn get_tags<'a, const N: usize>(
+    archive: &'a Osm,
+    range: Range<u64>,
+    keys: &[&[u8]; N],
+) -> [Option<&'a [u8]>; N] {
+    let tags = archive.tags();
+    let tags_index = archive.tags_index();
+    let strings = archive.stringtable();
+
+    let mut values = [None; N];
+    let mut missing = N;
+    for idx in range {
+        if missing == 0 {
+            break;
+        }
+        let tag = &tags[tags_index[idx as usize].value() as usize];
+        let key = strings.substring_raw(tag.key_idx() as usize);
+        for (value, _) in values
+            .iter_mut()
+            .zip(keys)
+            .filter(|(value, k)| value.is_none() && **k == key)
+        {
+            *value = Some(strings.substring_raw(tag.value_idx() as usize));
+            missing -= 1;
+        }
+    }
+    values
+}
+
 /// Checks if there is a tag in `range` with a given `key` and `value`.
 #[inline]
 pub fn has_tag(archive: &Osm, range: Range<u64>, key: &[u8], value: &[u8]) -> bool {
@@ -82,3 +293,57 @@ pub fn has_tag(archive: &Osm, range: Range<u64>, key: &[u8], value: &[u8]) -> bo
     }
     false
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::OsmWriter;
+
+    #[test]
+    fn test_get_tags() {
+        let storage = flatdata::MemoryResourceStorage::new("/test");
+        let mut writer = OsmWriter::new(storage.clone()).unwrap();
+        writer.add_node(
+            0.0,
+            0.0,
+            [("place", "city"), ("name", "Berlin"), ("name", "Other")],
+        );
+        writer.finish().unwrap();
+        let archive = Osm::open(storage).unwrap();
+
+        let range = archive.nodes()[0].tags();
+        let [name, population, place] =
+            get_tags(&archive, range.clone(), &[b"name", b"population", b"place"]);
+        assert_eq!(name, Some(&b"Berlin"[..]));
+        assert_eq!(population, None);
+        assert_eq!(place, Some(&b"city"[..]));
+        assert_eq!(get_tags(&archive, range, &[b"nam", b"place"])[0], None);
+        assert_eq!(get_tags(&archive, 0..0, &[]), []);
+    }
+
+    #[test]
+    fn test_iter_tags_lossy() {
+        let storage = flatdata::MemoryResourceStorage::new("/test");
+        let builder = crate::OsmBuilder::new(storage.clone()).unwrap();
+        builder.set_stringtable(b"name\0Caf\xe9\0").unwrap();
+        let mut tags = builder.start_tags().unwrap();
+        let tag = tags.grow().unwrap();
+        tag.set_key_idx(0);
+        tag.set_value_idx(5);
+        tags.close().unwrap();
+        let mut tags_index = builder.start_tags_index().unwrap();
+        tags_index.grow().unwrap().set_value(0);
+        tags_index.close().unwrap();
+        builder.set_header(&crate::Header::new()).unwrap();
+        builder.start_nodes().unwrap().close().unwrap();
+        builder.start_ways().unwrap().close().unwrap();
+        builder.start_relations().unwrap().close().unwrap();
+        builder.start_relation_members().unwrap().close().unwrap();
+        builder.start_nodes_index().unwrap().close().unwrap();
+        let archive = Osm::open(storage).unwrap();
+
+        let tags: Vec<_> = iter_tags_lossy(&archive, 0..1).collect();
+        assert_eq!(tags, [("name".into(), "Caf\u{fffd}".into())]);
+        assert!(matches!(tags[0].0, Cow::Borrowed(_)));
+    }
+}
@@ -0,0 +1,77 @@
+//! Writing archives as single tar files, enabled by the feature `tar`.
+//!
+//! Tar files are read with [`TarArchiveResourceStorage`], which memory maps
+//! the tar file and the resources in place. The data of each entry starts at
+//! a multiple of 512 bytes, so the resources are as aligned in the tar file as
+//! in their own files. Entries are written in the order of their paths with
+//! mode 0644 and zero modification time, so equal archives result in equal tar
+//! files.
+//!
+//! [`TarArchiveResourceStorage`]: crate::TarArchiveResourceStorage
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+fn append(
+    builder: &mut ::tar::Builder<impl Write>,
+    path: &Path,
+    size: u64,
+    data: impl Read,
+) -> io::Result<()> {
+    let mut header = ::tar::Header::new_gnu();
+    header.set_entry_type(::tar::EntryType::Regular);
+    header.set_size(size);
+    header.set_mode(0o644);
+    header.set_mtime(0);
+    builder.append_data(&mut header, path, data)
+}
+
+fn collect_files(dir: &Path, prefix: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = prefix.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            collect_files(&entry.path(), &path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Packs the archive in the directory `archive_dir`, including its
+/// subarchives, into the tar file at `tar_path`.
+///
+/// The resources are streamed from their files, so the archive does not need
+/// to fit into memory. Archives written by [`OsmWriter`] to a
+/// [`FileResourceStorage`] are packed the same way.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use osmflat::{pack_tar_archive, Osm, TarArchiveResourceStorage};
+///
+/// pack_tar_archive("path/to/archive.osm.flatdata", "archive.osm.tar").unwrap();
+/// let archive = Osm::open(TarArchiveResourceStorage::new("archive.osm.tar").unwrap()).unwrap();
+/// ```
+///
+/// [`OsmWriter`]: crate::OsmWriter
+/// [`FileResourceStorage`]: crate::FileResourceStorage
+pub fn pack_tar_archive(
+    archive_dir: impl AsRef<Path>,
+    tar_path: impl AsRef<Path>,
+) -> io::Result<()> {
+    let archive_dir = archive_dir.as_ref();
+    let mut files = Vec::new();
+    collect_files(archive_dir, Path::new(""), &mut files)?;
+    files.sort();
+
+    let mut builder = ::tar::Builder::new(BufWriter::new(File::create(tar_path)?));
+    for path in files {
+        let file = File::open(archive_dir.join(&path))?;
+        let size = file.metadata()?.len();
+        append(&mut builder, &path, size, file)?;
+    }
+    builder.into_inner()?.flush()
+}
@@ -0,0 +1,106 @@
+//! In-memory archives for unit tests.
+//!
+//! [`TestArchive`] describes a small archive by its entities, and builds it in
+//! memory with [`OsmWriter`], so that code using osmflat can be tested without
+//! binary fixtures or running `osmflatc`.
+
+use crate::{Member, Osm, OsmWriter};
+
+/// Tags of an entity in a [`TestArchive`] as key-value pairs.
+pub type TestTags<'a> = Vec<(&'a str, &'a str)>;
+
+/// Description of an archive built in memory for tests.
+///
+/// Ways and relations reference entities by their indexes in the description.
+///
+/// # Examples
+///
+/// ```rust
+/// use osmflat::{find_tag, Member, TestArchive};
+///
+/// let archive = TestArchive {
+///     nodes: vec![
+///         (13.37, 52.52, vec![("amenity", "pub")]),
+///         (13.38, 52.52, vec![]),
+///     ],
+///     ways: vec![(vec![0, 1], vec![("highway", "residential")])],
+///     relations: vec![(vec![Member::Way(0, "")], vec![("type", "route")])],
+/// }
+/// .build();
+///
+/// let way = &archive.ways()[0];
+/// assert_eq!(find_tag(&archive, way.tags(), b"highway"), Some(&b"residential"[..]));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TestArchive<'a> {
+    /// Nodes as longitude and latitude in degrees with their tags
+    pub nodes: Vec<(f64, f64, TestTags<'a>)>,
+    /// Ways as the indexes of their nodes with their tags
+    pub ways: Vec<(Vec<u64>, TestTags<'a>)>,
+    /// Relations as their members with their tags
+    pub relations: Vec<(Vec<Member<'a>>, TestTags<'a>)>,
+}
+
+impl TestArchive<'_> {
+    /// Builds the archive in memory.
+    ///
+    /// Panics if a way or a relation references an entity which is not in
+    /// the description.
+    pub fn build(&self) -> Osm {
+        let storage = flatdata::MemoryResourceStorage::new("/osmflat-test");
+        let mut writer = OsmWriter::new(storage.clone()).expect("failed to create archive");
+        for (lon, lat, tags) in &self.nodes {
+            writer.add_node(*lon, *lat, tags.iter().copied());
+        }
+        for (refs, tags) in &self.ways {
+            writer.add_way(refs, tags.iter().copied());
+        }
+        for (members, tags) in &self.relations {
+            writer.add_relation(members, tags.iter().copied());
+        }
+        writer.finish().expect("invalid test archive");
+        Osm::open(storage).expect("failed to open test archive")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{iter_tags, RelationMembersRef};
+
+    #[test]
+    fn test_build() {
+        let archive = TestArchive {
+            nodes: vec![(1.0, 2.0, vec![("a", "b")]), (3.0, 4.0, vec![])],
+            ways: vec![(vec![1, 0], vec![("c", "d"), ("a", "b")])],
+            relations: vec![(vec![Member::Node(1, "x")], vec![])],
+        }
+        .build();
+
+        let nodes = archive.nodes();
+        assert_eq!(nodes.len(), 2);
+        assert_eq!((nodes[1].lon(), nodes[1].lat()), (30_000_000, 40_000_000));
+        let ways = archive.ways();
+        let tags: Vec<_> = iter_tags(&archive, ways[0].tags()).collect();
+        assert_eq!(tags, [(&b"c"[..], &b"d"[..]), (b"a", b"b")]);
+        let refs: Vec<_> = ways[0]
+            .refs()
+            .map(|idx| archive.nodes_index()[idx as usize].value())
+            .collect();
+        assert_eq!(refs, [Some(1), Some(0)]);
+        match archive.relation_members().at(0).next() {
+            Some(RelationMembersRef::NodeMember(m)) => assert_eq!(m.node_idx(), Some(1)),
+            _ => panic!("expected node member"),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid test archive")]
+    fn test_missing_reference() {
+        TestArchive {
+            ways: vec![(vec![0], vec![])],
+            ..Default::default()
+        }
+        .build();
+    }
+}
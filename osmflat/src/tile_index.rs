@@ -0,0 +1,235 @@
+//! Nodes and ways by tiles of a fixed zoom level, stored in the `tile_index`
+//! subarchive.
+//!
+//! The subarchive, compiled with `osmflatc --tile-index <ZOOM>`, stores a
+//! dense grid of the tiles covering the archive, so the entities of a tile
+//! `z/x/y` are found without a search, cf. [`TileIndex::nodes_in_tile`]. The
+//! entities of a tile are stored as runs of consecutive indexes; in archives
+//! with nodes sorted along the Hilbert curve, cf. `osmflatc --sort hilbert`,
+//! the nodes of a tile form few runs.
+
+use crate::{
+    tile_at, way_bbox, IndexRun, Osm, TileCell, TileGrid, TileIndex, TileIndexBuilder,
+    MAX_TILE_ZOOM,
+};
+
+use std::io;
+
+/// Maximal number of tiles of the grid of a [`TileIndex`].
+pub const MAX_TILE_INDEX_CELLS: usize = 1 << 26;
+
+impl TileIndex {
+    /// Returns the cell of the tile `(x, y)` at the zoom level of the grid, or
+    /// `None` if the tile is outside of the grid.
+    pub fn cell(&self, x: u32, y: u32) -> Option<usize> {
+        let grid = self.grid();
+        let (dx, dy) = (x.checked_sub(grid.min_x())?, y.checked_sub(grid.min_y())?);
+        (dx < grid.width() && dy < grid.height())
+            .then(|| dy as usize * grid.width() as usize + dx as usize)
+    }
+
+    /// Returns the runs of the nodes inside the tile `(x, y)`.
+    pub fn node_runs_in_tile(&self, x: u32, y: u32) -> &[IndexRun] {
+        runs_of(self.node_cells(), self.node_runs(), self.cell(x, y))
+    }
+
+    /// Returns the runs of the ways whose bounding boxes intersect the tile
+    /// `(x, y)`.
+    pub fn way_runs_in_tile(&self, x: u32, y: u32) -> &[IndexRun] {
+        runs_of(self.way_cells(), self.way_runs(), self.cell(x, y))
+    }
+
+    /// Returns the indexes of the nodes inside the tile `(x, y)` in ascending
+    /// order.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use osmflat::{FileResourceStorage, Osm};
+    ///
+    /// let archive = Osm::open(FileResourceStorage::new("path/to/archive.osm.flatdata")).unwrap();
+    /// let tile_index = archive.tile_index().expect("compiled without --tile-index");
+    /// assert_eq!(tile_index.grid().zoom(), 14);
+    /// for idx in tile_index.nodes_in_tile(8802, 5373) {
+    ///     println!("{:?}", archive.nodes()[idx as usize]);
+    /// }
+    /// ```
+    pub fn nodes_in_tile(&self, x: u32, y: u32) -> impl Iterator<Item = u64> + '_ {
+        let runs = self.node_runs_in_tile(x, y);
+        runs.iter().flat_map(|run| run.start()..run.end())
+    }
+
+    /// Returns the indexes of the ways whose bounding boxes intersect the tile
+    /// `(x, y)` in ascending order.
+    pub fn ways_in_tile(&self, x: u32, y: u32) -> impl Iterator<Item = u64> + '_ {
+        let runs = self.way_runs_in_tile(x, y);
+        runs.iter().flat_map(|run| run.start()..run.end())
+    }
+}
+
+fn runs_of<'a>(cells: &[TileCell], runs: &'a [IndexRun], cell: Option<usize>) -> &'a [IndexRun] {
+    match cell {
+        Some(cell) => {
+            let range = cells[cell].runs();
+            &runs[range.start as usize..range.end as usize]
+        }
+        None => &[],
+    }
+}
+
+/// Runs of consecutive indexes of each cell, extended with ascending indexes.
+struct CellRuns(Vec<Vec<(u64, u64)>>);
+
+impl CellRuns {
+    fn add(&mut self, cell: usize, idx: u64) {
+        let runs = &mut self.0[cell];
+        match runs.last_mut() {
+            Some((_, end)) if *end == idx => *end += 1,
+            _ => runs.push((idx, idx + 1)),
+        }
+    }
+
+    fn write(
+        self,
+        mut cells: flatdata::ExternalVector<TileCell>,
+        mut runs: flatdata::ExternalVector<IndexRun>,
+    ) -> io::Result<()> {
+        let mut num_runs = 0;
+        for cell_runs in self.0 {
+            cells.grow()?.set_first_run_idx(num_runs);
+            for (start, end) in cell_runs {
+                let run = runs.grow()?;
+                run.set_start(start);
+                run.set_end(end);
+                num_runs += 1;
+            }
+        }
+        // sentinel
+        cells.grow()?.set_first_run_idx(num_runs);
+        cells.close().map_err(io::Error::other)?;
+        runs.close().map_err(io::Error::other)?;
+        Ok(())
+    }
+}
+
+/// Builds the `tile_index` subarchive of `archive` with the tiles of the
+/// given zoom level.
+///
+/// The grid covers the tiles of all nodes of the archive. Fails if the zoom
+/// level is larger than [`MAX_TILE_ZOOM`], or if the grid has more than
+/// [`MAX_TILE_INDEX_CELLS`] tiles.
+pub fn build_tile_index(archive: &Osm, zoom: u8, builder: &TileIndexBuilder) -> io::Result<()> {
+    if zoom > MAX_TILE_ZOOM {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("zoom level {zoom} is larger than {MAX_TILE_ZOOM}"),
+        ));
+    }
+    let node_tile = |idx: usize| {
+        let (lon, lat) = archive.node_coord(idx);
+        tile_at(zoom, lon, lat)
+    };
+    let num_nodes = archive.nodes().len();
+    let extent = (0..num_nodes).map(node_tile).fold(None, |extent, (x, y)| {
+        Some(match extent {
+            None => (x, y, x, y),
+            Some((min_x, min_y, max_x, max_y)) => {
+                (x.min(min_x), y.min(min_y), x.max(max_x), y.max(max_y))
+            }
+        })
+    });
+    let mut grid = TileGrid::new();
+    grid.set_zoom(zoom);
+    if let Some((min_x, min_y, max_x, max_y)) = extent {
+        grid.set_min_x(min_x);
+        grid.set_min_y(min_y);
+        grid.set_width(max_x - min_x + 1);
+        grid.set_height(max_y - min_y + 1);
+    }
+    let num_cells = grid.width() as usize * grid.height() as usize;
+    if num_cells > MAX_TILE_INDEX_CELLS {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{num_cells} tiles at zoom level {zoom} exceed {MAX_TILE_INDEX_CELLS}"),
+        ));
+    }
+    builder.set_grid(&grid)?;
+    let cell = |x: u32, y: u32| {
+        (y - grid.min_y()) as usize * grid.width() as usize + (x - grid.min_x()) as usize
+    };
+
+    let mut node_runs = CellRuns(vec![Vec::new(); num_cells]);
+    for idx in 0..num_nodes {
+        let (x, y) = node_tile(idx);
+        node_runs.add(cell(x, y), idx as u64);
+    }
+    node_runs.write(builder.start_node_cells()?, builder.start_node_runs()?)?;
+
+    let scale = f64::from(archive.header().coord_scale());
+    let mut way_runs = CellRuns(vec![Vec::new(); num_cells]);
+    for idx in 0..archive.ways().len() {
+        let Some(bbox) = way_bbox(archive, idx) else {
+            continue;
+        };
+        let degrees = |x: i32| f64::from(x) / scale;
+        let (min_x, min_y) = tile_at(zoom, degrees(bbox.left), degrees(bbox.top));
+        let (max_x, max_y) = tile_at(zoom, degrees(bbox.right), degrees(bbox.bottom));
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                way_runs.add(cell(x, y), idx as u64);
+            }
+        }
+    }
+    way_runs.write(builder.start_way_cells()?, builder.start_way_runs()?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::OsmWriter;
+    use flatdata::ResourceStorage;
+
+    #[test]
+    fn test_tile_index() {
+        let storage = flatdata::MemoryResourceStorage::new("/test");
+        let mut writer = OsmWriter::new(storage.clone()).unwrap();
+        let tags = None::<(&str, &str)>;
+        // tiles (1, 0), (0, 0), (1, 0), and (1, 1) at zoom level 1
+        let nodes: Vec<u64> = [(10.0, 10.0), (-10.0, 10.0), (20.0, 20.0), (10.0, -10.0)]
+            .into_iter()
+            .map(|(lon, lat)| writer.add_node(lon, lat, tags))
+            .collect();
+        writer.add_way(&[nodes[0], nodes[3]], tags);
+        writer.add_way(&[nodes[1], nodes[2]], tags);
+        writer.finish().unwrap();
+
+        let archive = Osm::open(storage.clone()).unwrap();
+        assert!(archive.tile_index().is_none());
+        let builder = TileIndexBuilder::new(storage.subdir("tile_index")).unwrap();
+        assert!(build_tile_index(&archive, MAX_TILE_ZOOM + 1, &builder).is_err());
+        build_tile_index(&archive, 1, &builder).unwrap();
+
+        let archive = Osm::open(storage).unwrap();
+        let tile_index = archive.tile_index().unwrap();
+        let grid = tile_index.grid();
+        assert_eq!(grid.zoom(), 1);
+        assert_eq!((grid.width(), grid.height()), (2, 2));
+        assert_eq!(tile_index.cell(1, 1), Some(3));
+        assert_eq!(tile_index.cell(2, 0), None);
+
+        let nodes_in_tile = |x, y| tile_index.nodes_in_tile(x, y).collect::<Vec<_>>();
+        assert_eq!(nodes_in_tile(1, 0), [0, 2]);
+        assert_eq!(tile_index.node_runs_in_tile(1, 0).len(), 2);
+        assert_eq!(nodes_in_tile(0, 0), [1]);
+        assert!(nodes_in_tile(0, 1).is_empty());
+        assert_eq!(nodes_in_tile(1, 1), [3]);
+        assert!(nodes_in_tile(5, 5).is_empty());
+
+        let ways_in_tile = |x, y| tile_index.ways_in_tile(x, y).collect::<Vec<_>>();
+        assert_eq!(ways_in_tile(1, 0), [0, 1]);
+        assert_eq!(tile_index.way_runs_in_tile(1, 0).len(), 1);
+        assert_eq!(ways_in_tile(0, 0), [1]);
+        assert_eq!(ways_in_tile(1, 1), [0]);
+        assert!(ways_in_tile(0, 1).is_empty());
+    }
+}
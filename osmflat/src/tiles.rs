@@ -0,0 +1,123 @@
+//! Web Mercator tiles of archives split by `osmflatc split-tiles`.
+//!
+//! Tiles are addressed as in slippy maps: at zoom level `z`, the world between
+//! the latitudes ±[`MAX_TILE_LATITUDE`] is divided into `2^z × 2^z` tiles,
+//! where `x` counts the columns from the west and `y` the rows from the north.
+
+use crate::{FileResourceStorage, Osm, Tile, Tiles};
+
+use std::f64::consts::PI;
+use std::path::{Path, PathBuf};
+
+/// Maximal latitude covered by Web Mercator tiles in degrees.
+pub const MAX_TILE_LATITUDE: f64 = 85.051_128_779_806_59;
+
+/// Maximal supported zoom level of tiles.
+pub const MAX_TILE_ZOOM: u8 = 16;
+
+fn num_tiles(zoom: u8) -> f64 {
+    2f64.powi(i32::from(zoom))
+}
+
+/// Returns the tile `(x, y)` containing a coordinate given in degrees.
+///
+/// Coordinates outside of the tiled world are clamped to the nearest tile.
+pub fn tile_at(zoom: u8, lon: f64, lat: f64) -> (u32, u32) {
+    let n = num_tiles(zoom);
+    let lat = lat
+        .clamp(-MAX_TILE_LATITUDE, MAX_TILE_LATITUDE)
+        .to_radians();
+    let x = (lon + 180.0) / 360.0 * n;
+    let y = (1.0 - lat.tan().asinh() / PI) / 2.0 * n;
+    let clamp = |t: f64| t.floor().clamp(0.0, n - 1.0) as u32;
+    (clamp(x), clamp(y))
+}
+
+/// Returns the bounding box of the tile `(x, y)` as minimal and maximal
+/// `(lon, lat)` in degrees.
+pub fn tile_bounds(zoom: u8, x: u32, y: u32) -> ((f64, f64), (f64, f64)) {
+    let n = num_tiles(zoom);
+    let lon = |x: u32| f64::from(x) / n * 360.0 - 180.0;
+    let lat = |y: u32| {
+        (PI * (1.0 - 2.0 * f64::from(y) / n))
+            .sinh()
+            .atan()
+            .to_degrees()
+    };
+    ((lon(x), lat(y + 1)), (lon(x + 1), lat(y)))
+}
+
+impl Tile {
+    /// Path of the archive of the tile relative to the [`Tiles`] archive.
+    pub fn path(&self) -> PathBuf {
+        [u32::from(self.zoom()), self.x(), self.y()]
+            .iter()
+            .map(|x| x.to_string())
+            .collect()
+    }
+
+    /// Bounding box of the tile, cf. [`tile_bounds`].
+    pub fn bounds(&self) -> ((f64, f64), (f64, f64)) {
+        tile_bounds(self.zoom(), self.x(), self.y())
+    }
+
+    /// Opens the archive of the tile, given the directory of the [`Tiles`]
+    /// archive.
+    pub fn open(&self, dir: &Path) -> Result<Osm, flatdata::ResourceStorageError> {
+        Osm::open(FileResourceStorage::new(dir.join(self.path())))
+    }
+}
+
+impl Tiles {
+    /// The tile `(x, y)`, or `None` if it contains no entities.
+    pub fn tile(&self, x: u32, y: u32) -> Option<&Tile> {
+        let tiles = self.tiles();
+        tiles
+            .binary_search_by_key(&(x, y), |tile| (tile.x(), tile.y()))
+            .ok()
+            .map(|pos| &tiles[pos])
+    }
+
+    /// Tiles intersecting the bounding box between `min` and `max` given as
+    /// `(lon, lat)` in degrees.
+    pub fn tiles_in_bbox(
+        &self,
+        min: (f64, f64),
+        max: (f64, f64),
+    ) -> impl Iterator<Item = &Tile> + '_ {
+        let zoom = self.tiles().first().map_or(0, |tile| tile.zoom());
+        let (min_x, max_y) = tile_at(zoom, min.0, min.1);
+        let (max_x, min_y) = tile_at(zoom, max.0, max.1);
+        self.tiles().iter().filter(move |tile| {
+            (min_x..=max_x).contains(&tile.x()) && (min_y..=max_y).contains(&tile.y())
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tile_at() {
+        // Berlin
+        assert_eq!(tile_at(6, 13.4, 52.52), (34, 20));
+        assert_eq!(tile_at(8, 13.4, 52.52), (137, 83));
+        assert_eq!(tile_at(0, -179.0, -89.0), (0, 0));
+        assert_eq!(tile_at(2, 180.0, -90.0), (3, 3));
+        assert_eq!(tile_at(2, -180.0, 90.0), (0, 0));
+    }
+
+    #[test]
+    fn test_tile_bounds() {
+        let ((left, bottom), (right, top)) = tile_bounds(1, 0, 0);
+        assert_eq!((left, bottom, right), (-180.0, 0.0, 0.0));
+        assert!((top - MAX_TILE_LATITUDE).abs() < 1e-9);
+
+        for (x, y) in [(34, 20), (0, 63), (63, 0)] {
+            let (min, max) = tile_bounds(6, x, y);
+            let center = ((min.0 + max.0) / 2.0, (min.1 + max.1) / 2.0);
+            assert_eq!(tile_at(6, center.0, center.1), (x, y));
+        }
+    }
+}
@@ -0,0 +1,307 @@
+//! Validation of the invariants of an archive.
+//!
+//! Opening an archive only checks the sizes and schemas of its resources. The
+//! references between the resources are used unchecked when reading, so a
+//! corrupted archive panics on an index out of bounds in the middle of a scan,
+//! or returns wrong data. [`validate`] checks all references up front, e.g.
+//! before processing archives received from third parties.
+
+use crate::{Osm, RelationMembersRef};
+
+use std::fmt;
+use std::ops::Range;
+
+/// Number of violations of each invariant of an archive, cf. [`validate`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// Tag ranges of nodes, ways, and relations which are decreasing or exceed
+    /// `tags_index`
+    pub tag_ranges: usize,
+    /// Node ranges of ways which are decreasing or exceed `nodes_index`
+    pub ref_ranges: usize,
+    /// Sentinels which do not end the ranges of their vectors where the ranges
+    /// of the next vector start, and a `relation_members` index of different
+    /// length than `relations`
+    pub sentinels: usize,
+    /// Entries of `tags_index` outside of `tags`
+    pub tags_index: usize,
+    /// Entries of `nodes_index` outside of `nodes`
+    pub nodes_index: usize,
+    /// Relation members outside of `nodes`, `ways`, or `relations`
+    pub members: usize,
+    /// References into the stringtable which are not the start of a
+    /// NUL-terminated string
+    pub strings: usize,
+    /// Strings in the stringtable which are not valid UTF-8
+    pub utf8: usize,
+    /// Coordinates in the optional `node_coords` vector which differ from the
+    /// coordinates in `nodes`, and a `node_coords` vector of different length
+    /// than `nodes`
+    pub node_coords: usize,
+}
+
+impl ValidationReport {
+    /// Total number of violations.
+    pub fn total(&self) -> usize {
+        self.tag_ranges
+            + self.ref_ranges
+            + self.sentinels
+            + self.tags_index
+            + self.nodes_index
+            + self.members
+            + self.strings
+            + self.utf8
+            + self.node_coords
+    }
+
+    /// Whether the archive has no violations.
+    pub fn is_valid(&self) -> bool {
+        self.total() == 0
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            r#"Violations:
+  tag ranges:   {}
+  ref ranges:   {}
+  sentinels:    {}
+  tags index:   {}
+  nodes index:  {}
+  members:      {}
+  strings:      {}
+  utf-8:        {}
+  node coords:  {}"#,
+            self.tag_ranges,
+            self.ref_ranges,
+            self.sentinels,
+            self.tags_index,
+            self.nodes_index,
+            self.members,
+            self.strings,
+            self.utf8,
+            self.node_coords
+        )
+    }
+}
+
+/// Number of ranges which are decreasing or exceed `len`.
+///
+/// Since the end of a range is the start of the next one, this also checks
+/// that the ranges are non-decreasing up to the sentinel.
+fn invalid_ranges(ranges: impl Iterator<Item = Range<u64>>, len: usize) -> usize {
+    ranges
+        .filter(|range| range.start > range.end || range.end > len as u64)
+        .count()
+}
+
+/// Validates the invariants of `archive`, which are not checked when opening
+/// it.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use osmflat::{FileResourceStorage, Osm};
+///
+/// let archive = Osm::open(FileResourceStorage::new("path/to/archive.osm.flatdata")).unwrap();
+/// let report = osmflat::validate(&archive);
+/// if !report.is_valid() {
+///     eprintln!("corrupt archive\n{report}");
+/// }
+/// ```
+pub fn validate(archive: &Osm) -> ValidationReport {
+    let nodes = archive.nodes();
+    let ways = archive.ways();
+    let relations = archive.relations();
+    let tags = archive.tags();
+    let tags_index = archive.tags_index();
+    let nodes_index = archive.nodes_index();
+    let strings = archive.stringtable().as_bytes();
+
+    // all strings are terminated, if they start before the last NUL
+    let last_nul = strings.iter().rposition(|&b| b == 0);
+    let is_string = |idx: u64| {
+        let idx = idx as usize;
+        last_nul.is_some_and(|last_nul| idx <= last_nul) && (idx == 0 || strings[idx - 1] == 0)
+    };
+
+    let mut report = ValidationReport {
+        tag_ranges: invalid_ranges(nodes.iter().map(|n| n.tags()), tags_index.len())
+            + invalid_ranges(ways.iter().map(|w| w.tags()), tags_index.len())
+            + invalid_ranges(relations.iter().map(|r| r.tags()), tags_index.len()),
+        ref_ranges: invalid_ranges(ways.iter().map(|w| w.refs()), nodes_index.len()),
+        tags_index: tags_index
+            .iter()
+            .filter(|idx| idx.value() >= tags.len() as u64)
+            .count(),
+        nodes_index: nodes_index
+            .iter()
+            .filter(|idx| idx.value().is_some_and(|idx| idx >= nodes.len() as u64))
+            .count(),
+        strings: tags
+            .iter()
+            .flat_map(|tag| [tag.key_idx(), tag.value_idx()])
+            .filter(|&idx| !is_string(idx))
+            .count(),
+        utf8: strings
+            .split(|&b| b == 0)
+            .filter(|s| std::str::from_utf8(s).is_err())
+            .count(),
+        ..Default::default()
+    };
+
+    // the tags of nodes, ways and relations follow each other in `tags_index`,
+    // and the refs of the ways cover `nodes_index`
+    let tag_bounds = [
+        nodes.first().map(|n| n.tags()),
+        nodes.last().map(|n| n.tags()),
+        ways.first().map(|w| w.tags()),
+        ways.last().map(|w| w.tags()),
+        relations.first().map(|r| r.tags()),
+        relations.last().map(|r| r.tags()),
+    ];
+    let mut tags_end = 0;
+    for bounds in tag_bounds.chunks(2) {
+        if let [Some(first), Some(last)] = bounds {
+            if first.start != tags_end {
+                report.sentinels += 1;
+            }
+            tags_end = last.end;
+        }
+    }
+    if tags_end != tags_index.len() as u64 {
+        report.sentinels += 1;
+    }
+    let refs_end = ways.last().map_or(0, |w| w.refs().end);
+    if refs_end != nodes_index.len() as u64 {
+        report.sentinels += 1;
+    }
+    let relation_members = archive.relation_members();
+    if relation_members.len() != relations.len() {
+        report.sentinels += 1;
+    }
+
+    let header = archive.header();
+    report.strings += [
+        Some(header.writingprogram_idx()),
+        header.source_idx(),
+        header.replication_base_url_idx(),
+    ]
+    .into_iter()
+    .flatten()
+    .filter(|&idx| !is_string(idx))
+    .count();
+
+    // `node_coords` duplicates the coordinates of `nodes`
+    if let Some(node_coords) = archive.node_coords() {
+        if node_coords.len() != nodes.len() {
+            report.node_coords += 1;
+        }
+        report.node_coords += nodes
+            .iter()
+            .zip(node_coords)
+            .filter(|(node, coord)| (node.lon(), node.lat()) != (coord.lon(), coord.lat()))
+            .count();
+    }
+
+    for idx in 0..relations.len().min(relation_members.len()) {
+        for member in relation_members.at(idx) {
+            let (member_idx, len, role_idx) = match member {
+                RelationMembersRef::NodeMember(m) => (m.node_idx(), nodes.len(), m.role_idx()),
+                RelationMembersRef::WayMember(m) => (m.way_idx(), ways.len(), m.role_idx()),
+                RelationMembersRef::RelationMember(m) => {
+                    (m.relation_idx(), relations.len(), m.role_idx())
+                }
+            };
+            if member_idx.is_some_and(|idx| idx >= len as u64) {
+                report.members += 1;
+            }
+            if !is_string(role_idx) {
+                report.strings += 1;
+            }
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Header, Member, OsmBuilder, OsmWriter};
+
+    #[test]
+    fn test_validate() {
+        let storage = flatdata::MemoryResourceStorage::new("/test");
+        let builder = OsmBuilder::new(storage.clone()).unwrap();
+        builder.set_header(&Header::new()).unwrap();
+        builder
+            .set_stringtable(b"osmflatc\0key\0value\0\xff\0")
+            .unwrap();
+
+        let mut tags = builder.start_tags().unwrap();
+        let tag = tags.grow().unwrap();
+        tag.set_key_idx(9);
+        tag.set_value_idx(14); // not the start of a string
+        tags.close().unwrap();
+
+        let mut tags_index = builder.start_tags_index().unwrap();
+        tags_index.grow().unwrap().set_value(0);
+        tags_index.grow().unwrap().set_value(7); // outside of tags
+        tags_index.close().unwrap();
+
+        let mut nodes = builder.start_nodes().unwrap();
+        nodes.grow().unwrap().set_tag_first_idx(0);
+        nodes.grow().unwrap().set_tag_first_idx(5); // outside of tags_index
+        nodes.close().unwrap();
+
+        let mut node_coords = builder.start_node_coords().unwrap();
+        node_coords.grow().unwrap().set_lon(1); // differs from the node
+        node_coords.close().unwrap();
+
+        let mut nodes_index = builder.start_nodes_index().unwrap();
+        nodes_index.grow().unwrap().set_value(Some(3)); // outside of nodes
+        nodes_index.close().unwrap();
+
+        let mut ways = builder.start_ways().unwrap();
+        ways.grow().unwrap().set_ref_first_idx(0);
+        ways.grow().unwrap().set_ref_first_idx(1);
+        ways.close().unwrap();
+
+        let mut relations = builder.start_relations().unwrap();
+        relations.grow().unwrap();
+        relations.close().unwrap();
+        builder.start_relation_members().unwrap().close().unwrap();
+
+        let archive = Osm::open(storage).unwrap();
+        assert_eq!(
+            validate(&archive),
+            ValidationReport {
+                tag_ranges: 1,
+                // the tags of the node end after the start of the tags of the
+                // way, and the relation does not end at the end of tags_index
+                sentinels: 2,
+                tags_index: 1,
+                nodes_index: 1,
+                strings: 1,
+                utf8: 1,
+                node_coords: 1,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_validate_valid() {
+        let storage = flatdata::MemoryResourceStorage::new("/test");
+        let mut writer = OsmWriter::new(storage.clone()).unwrap();
+        let a = writer.add_node(1.0, 2.0, [("name", "a")]);
+        let b = writer.add_node(3.0, 4.0, None::<(&str, &str)>);
+        let way = writer.add_way(&[a, b], [("highway", "primary")]);
+        writer.add_relation(&[Member::Way(way, "outer")], [("type", "route")]);
+        writer.finish().unwrap();
+        let report = validate(&Osm::open(storage).unwrap());
+        assert!(report.is_valid(), "{report}");
+    }
+}
@@ -0,0 +1,327 @@
+//! Views of archives restricted to selected entities.
+//!
+//! A [`View`] selects nodes, ways and relations of an archive by bitsets, and
+//! exposes them as vectors indexed from 0, without writing a new archive. The
+//! entities themselves are not changed: their tags are read from the archive
+//! as usual, while the references of ways to nodes are remapped to the indexes
+//! in the view by [`View::way_refs`].
+
+use crate::{Node, Osm, Relation, Way};
+
+use std::ops::Index;
+
+/// Set of indexes of the entities of one type as bitset.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selection {
+    bits: Vec<u64>,
+    len: usize,
+}
+
+impl Selection {
+    /// Creates an empty selection of `len` entities.
+    pub fn new(len: usize) -> Self {
+        Self {
+            bits: vec![0; len.div_ceil(64)],
+            len,
+        }
+    }
+
+    /// Creates a selection of all `len` entities.
+    pub fn all(len: usize) -> Self {
+        let mut selection = Self::new(len);
+        (0..len).for_each(|idx| selection.insert(idx));
+        selection
+    }
+
+    /// Creates a selection of `len` entities containing the indexes.
+    ///
+    /// Panics if an index is not smaller than `len`.
+    pub fn from_indexes(len: usize, indexes: impl IntoIterator<Item = u64>) -> Self {
+        let mut selection = Self::new(len);
+        for idx in indexes {
+            selection.insert(idx as usize);
+        }
+        selection
+    }
+
+    /// Number of entities the selection is taken from.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the selection is taken from no entities.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Adds the index to the selection.
+    ///
+    /// Panics if the index is not smaller than [`Selection::len`].
+    pub fn insert(&mut self, idx: usize) {
+        assert!(idx < self.len, "index {idx} out of bounds of {}", self.len);
+        self.bits[idx / 64] |= 1 << (idx % 64);
+    }
+
+    /// Whether the index is selected.
+    pub fn contains(&self, idx: usize) -> bool {
+        idx < self.len && self.bits[idx / 64] & (1 << (idx % 64)) != 0
+    }
+
+    /// Number of selected indexes.
+    pub fn count(&self) -> usize {
+        self.bits
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Iterates over the selected indexes in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.bits.iter().enumerate().flat_map(|(word_idx, &word)| {
+            (0..64)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| word_idx * 64 + bit)
+        })
+    }
+}
+
+/// Selected entities of one type of a [`View`], indexed from 0.
+#[derive(Debug, Clone, Copy)]
+pub struct ViewVector<'a, T> {
+    data: &'a [T],
+    indexes: &'a [u64],
+}
+
+impl<'a, T> ViewVector<'a, T> {
+    /// Number of selected entities.
+    pub fn len(&self) -> usize {
+        self.indexes.len()
+    }
+
+    /// Whether no entity is selected.
+    pub fn is_empty(&self) -> bool {
+        self.indexes.is_empty()
+    }
+
+    /// Returns the entity with the index in the view.
+    pub fn get(&self, idx: usize) -> Option<&'a T> {
+        let archive_idx = *self.indexes.get(idx)?;
+        Some(&self.data[archive_idx as usize])
+    }
+
+    /// Iterates over the selected entities.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &'a T> + Clone + 'a {
+        let data = self.data;
+        self.indexes.iter().map(move |&idx| &data[idx as usize])
+    }
+
+    /// Returns the index in the archive of the entity with the index in the
+    /// view.
+    ///
+    /// Panics if the index is out of bounds.
+    pub fn archive_idx(&self, idx: usize) -> u64 {
+        self.indexes[idx]
+    }
+
+    /// Returns the index in the view of the entity with the index in the
+    /// archive, or `None` if the entity is not selected.
+    pub fn view_idx(&self, archive_idx: u64) -> Option<usize> {
+        self.indexes.binary_search(&archive_idx).ok()
+    }
+}
+
+impl<T> Index<usize> for ViewVector<'_, T> {
+    type Output = T;
+
+    fn index(&self, idx: usize) -> &T {
+        &self.data[self.indexes[idx] as usize]
+    }
+}
+
+/// View of an archive restricted to selected nodes, ways and relations.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use osmflat::{query, EntityType, FileResourceStorage, Osm, Selection, View};
+///
+/// let archive = Osm::open(FileResourceStorage::new("path/to/archive.osm.flatdata")).unwrap();
+/// let highways = query::entities_of_type_with_tag(&archive, EntityType::Way, b"highway", None);
+/// let view = View::from_ways(&archive, Selection::from_indexes(archive.ways().len(), highways));
+/// let nodes = view.nodes();
+/// for way in view.ways().iter() {
+///     let lons: Vec<i32> = view.way_refs(way).flatten().map(|idx| nodes[idx].lon()).collect();
+///     println!("{lons:?}");
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct View<'a> {
+    archive: &'a Osm,
+    nodes: Vec<u64>,
+    ways: Vec<u64>,
+    relations: Vec<u64>,
+}
+
+fn indexes(selection: &Selection, len: usize, name: &str) -> Vec<u64> {
+    assert_eq!(
+        selection.len(),
+        len,
+        "selection of {name} does not match the archive"
+    );
+    selection.iter().map(|idx| idx as u64).collect()
+}
+
+impl<'a> View<'a> {
+    /// Creates a view of the selected entities of `archive`.
+    ///
+    /// Panics if the lengths of the selections differ from the numbers of
+    /// entities in the archive.
+    pub fn new(
+        archive: &'a Osm,
+        nodes: &Selection,
+        ways: &Selection,
+        relations: &Selection,
+    ) -> Self {
+        Self {
+            archive,
+            nodes: indexes(nodes, archive.nodes().len(), "nodes"),
+            ways: indexes(ways, archive.ways().len(), "ways"),
+            relations: indexes(relations, archive.relations().len(), "relations"),
+        }
+    }
+
+    /// Creates a view of the selected ways and of all their nodes, without
+    /// relations.
+    pub fn from_ways(archive: &'a Osm, ways: Selection) -> Self {
+        let nodes_index = archive.nodes_index();
+        let mut nodes = Selection::new(archive.nodes().len());
+        for idx in ways.iter() {
+            for node_idx in archive.ways()[idx]
+                .refs()
+                .filter_map(|idx| nodes_index[idx as usize].value())
+            {
+                nodes.insert(node_idx as usize);
+            }
+        }
+        let relations = Selection::new(archive.relations().len());
+        Self::new(archive, &nodes, &ways, &relations)
+    }
+
+    /// The archive of the view.
+    pub fn archive(&self) -> &'a Osm {
+        self.archive
+    }
+
+    /// Selected nodes.
+    pub fn nodes(&self) -> ViewVector<'_, Node> {
+        ViewVector {
+            data: self.archive.nodes(),
+            indexes: &self.nodes,
+        }
+    }
+
+    /// Selected ways.
+    pub fn ways(&self) -> ViewVector<'_, Way> {
+        ViewVector {
+            data: self.archive.ways(),
+            indexes: &self.ways,
+        }
+    }
+
+    /// Selected relations.
+    pub fn relations(&self) -> ViewVector<'_, Relation> {
+        ViewVector {
+            data: self.archive.relations(),
+            indexes: &self.relations,
+        }
+    }
+
+    /// Returns the indexes in the view of the nodes of `way`.
+    ///
+    /// A node is `None` if it is not resolved in the archive, or not
+    /// selected.
+    pub fn way_refs<'b>(&'b self, way: &Way) -> impl Iterator<Item = Option<usize>> + 'b {
+        let nodes_index = self.archive.nodes_index();
+        let nodes = self.nodes();
+        way.refs()
+            .map(move |idx| nodes.view_idx(nodes_index[idx as usize].value()?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{find_tag, OsmWriter};
+
+    #[test]
+    fn test_selection() {
+        let mut selection = Selection::new(130);
+        assert_eq!(selection.count(), 0);
+        for idx in [129, 0, 64, 63] {
+            selection.insert(idx);
+        }
+        assert_eq!(selection.iter().collect::<Vec<_>>(), [0, 63, 64, 129]);
+        assert_eq!(selection.count(), 4);
+        assert!(selection.contains(64));
+        assert!(!selection.contains(65));
+        assert!(!selection.contains(1000));
+        assert_eq!(Selection::all(70).count(), 70);
+        assert_eq!(
+            Selection::from_indexes(3, [2, 2])
+                .iter()
+                .collect::<Vec<_>>(),
+            [2]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "out of bounds")]
+    fn test_selection_out_of_bounds() {
+        Selection::new(10).insert(10);
+    }
+
+    #[test]
+    fn test_view() {
+        let storage = flatdata::MemoryResourceStorage::new("/test");
+        let mut writer = OsmWriter::new(storage.clone()).unwrap();
+        let tags = None::<(&str, &str)>;
+        for i in 0..5 {
+            writer.add_node(f64::from(i), 0.0, tags);
+        }
+        writer.add_way(&[0, 1], [("building", "yes")]);
+        writer.add_way(&[3, 1, 4], [("highway", "primary")]);
+        writer.add_relation(&[], tags);
+        writer.finish().unwrap();
+        let archive = Osm::open(storage).unwrap();
+
+        let view = View::from_ways(&archive, Selection::from_indexes(2, [1]));
+        let ways = view.ways();
+        assert_eq!(ways.len(), 1);
+        assert_eq!(
+            find_tag(&archive, ways[0].tags(), b"highway"),
+            Some(&b"primary"[..])
+        );
+        assert_eq!(ways.archive_idx(0), 1);
+        assert_eq!(ways.view_idx(0), None);
+
+        let nodes = view.nodes();
+        assert_eq!(nodes.len(), 3);
+        let lons: Vec<_> = nodes.iter().map(|node| node.lon()).collect();
+        assert_eq!(lons, [10_000_000, 30_000_000, 40_000_000]);
+        let refs: Vec<_> = view.way_refs(&ways[0]).collect();
+        assert_eq!(refs, [Some(1), Some(0), Some(2)]);
+        assert!(view.relations().is_empty());
+
+        // nodes missing in the view are not remapped
+        let view = View::new(
+            &archive,
+            &Selection::from_indexes(5, [1, 4]),
+            &Selection::all(2),
+            &Selection::all(1),
+        );
+        let refs: Vec<_> = view.way_refs(&view.ways()[1]).collect();
+        assert_eq!(refs, [None, Some(0), Some(1)]);
+        assert_eq!(view.relations().len(), 1);
+        assert!(view.nodes().get(2).is_none());
+    }
+}
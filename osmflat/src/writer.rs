@@ -0,0 +1,468 @@
+//! Programmatic construction of archives.
+//!
+//! [`OsmWriter`] writes archives from entities added one by one, e.g. from
+//! synthetic data in tests or from other sources than PBF files. It takes care
+//! of deduplicating strings and tags, the sentinels of the entity vectors, and
+//! the indexes between them.
+
+use crate::{Header, Node, NodeIndex, OsmBuilder, Relation, Tag, TagIndex, Way};
+
+use flatdata::{ResourceStorageError, StorageHandle};
+
+use std::collections::HashMap;
+use std::io;
+
+/// Coordinate scale of written archives, i.e. 100 nanodegrees as in PBF files.
+const COORD_SCALE: i32 = 1_000_000_000 / 100;
+
+/// Member of a relation added to an [`OsmWriter`] with its role.
+///
+/// Members reference entities by their indexes returned by
+/// [`OsmWriter::add_node`], [`OsmWriter::add_way`] and
+/// [`OsmWriter::add_relation`]; relations may also reference relations added
+/// later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Member<'a> {
+    /// Node at the index with the role
+    Node(u64, &'a str),
+    /// Way at the index with the role
+    Way(u64, &'a str),
+    /// Relation at the index with the role
+    Relation(u64, &'a str),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum MemberIdx {
+    Node(u64),
+    Way(u64),
+    Relation(u64),
+}
+
+/// Writer of an archive from entities added one by one.
+///
+/// The entities are kept in memory until the archive is written by
+/// [`OsmWriter::finish`].
+///
+/// # Examples
+///
+/// ```rust
+/// use osmflat::{find_tag, Member, Osm, OsmWriter};
+///
+/// let storage = flatdata::MemoryResourceStorage::new("/synthetic");
+/// let mut writer = OsmWriter::new(storage.clone()).unwrap();
+/// let a = writer.add_node(13.37, 52.52, [("amenity", "pub")]);
+/// let b = writer.add_node(13.38, 52.52, [("name", "Hirsch")]);
+/// let way = writer.add_way(&[a, b], [("highway", "residential")]);
+/// writer.add_relation(&[Member::Way(way, "")], [("type", "route")]);
+/// writer.finish().unwrap();
+///
+/// let archive = Osm::open(storage).unwrap();
+/// let way = &archive.ways()[0];
+/// assert_eq!(find_tag(&archive, way.tags(), b"highway"), Some(&b"residential"[..]));
+/// ```
+pub struct OsmWriter {
+    builder: OsmBuilder,
+    strings: Vec<u8>,
+    string_indexes: HashMap<String, u64>,
+    tags: Vec<Tag>,
+    tag_indexes: HashMap<(u64, u64), u64>,
+    // tags of nodes, ways and relations; they are concatenated on finish, so
+    // that the tag ranges of each entity type are contiguous
+    tags_index: [Vec<u64>; 3],
+    // (lon, lat, tag_first_idx)
+    nodes: Vec<(i32, i32, u64)>,
+    // (tag_first_idx, ref_first_idx)
+    ways: Vec<(u64, u64)>,
+    nodes_index: Vec<u64>,
+    // tag_first_idx
+    relations: Vec<u64>,
+    members: Vec<Vec<(MemberIdx, u64)>>,
+}
+
+impl std::fmt::Debug for OsmWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("OsmWriter")
+            .field("nodes", &self.nodes.len())
+            .field("ways", &self.ways.len())
+            .field("relations", &self.relations.len())
+            .finish()
+    }
+}
+
+fn invalid_input(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+impl OsmWriter {
+    /// Creates a writer of a new archive in `storage`.
+    pub fn new(storage: StorageHandle) -> Result<Self, ResourceStorageError> {
+        let mut writer = Self {
+            builder: OsmBuilder::new(storage)?,
+            strings: Vec::new(),
+            string_indexes: HashMap::new(),
+            tags: Vec::new(),
+            tag_indexes: HashMap::new(),
+            tags_index: Default::default(),
+            nodes: Vec::new(),
+            ways: Vec::new(),
+            nodes_index: Vec::new(),
+            relations: Vec::new(),
+            members: Vec::new(),
+        };
+        writer.insert_string("osmflat");
+        Ok(writer)
+    }
+
+    fn insert_string(&mut self, s: &str) -> u64 {
+        if let Some(&idx) = self.string_indexes.get(s) {
+            return idx;
+        }
+        let idx = self.strings.len() as u64;
+        self.strings.extend(s.as_bytes());
+        self.strings.push(0);
+        self.string_indexes.insert(s.to_string(), idx);
+        idx
+    }
+
+    /// Adds the tags to the tags index of the entity type and returns the
+    /// index of the first one.
+    fn add_tags(
+        &mut self,
+        entity: usize,
+        tags: impl IntoIterator<Item = (impl AsRef<str>, impl AsRef<str>)>,
+    ) -> u64 {
+        let first_idx = self.tags_index[entity].len() as u64;
+        for (key, value) in tags {
+            let key_idx = self.insert_string(key.as_ref());
+            let value_idx = self.insert_string(value.as_ref());
+            let tag_idx = *self
+                .tag_indexes
+                .entry((key_idx, value_idx))
+                .or_insert_with(|| {
+                    let mut tag = Tag::new();
+                    tag.set_key_idx(key_idx);
+                    tag.set_value_idx(value_idx);
+                    self.tags.push(tag);
+                    self.tags.len() as u64 - 1
+                });
+            self.tags_index[entity].push(tag_idx);
+        }
+        first_idx
+    }
+
+    /// Adds a node at the coordinates in degrees with the tags, and returns its
+    /// index.
+    pub fn add_node(
+        &mut self,
+        lon: f64,
+        lat: f64,
+        tags: impl IntoIterator<Item = (impl AsRef<str>, impl AsRef<str>)>,
+    ) -> u64 {
+        let scale = |x: f64| (x * f64::from(COORD_SCALE)).round() as i32;
+        let tag_first_idx = self.add_tags(0, tags);
+        self.nodes.push((scale(lon), scale(lat), tag_first_idx));
+        self.nodes.len() as u64 - 1
+    }
+
+    /// Adds a way through the nodes at the indexes with the tags, and returns
+    /// its index.
+    pub fn add_way(
+        &mut self,
+        nodes: &[u64],
+        tags: impl IntoIterator<Item = (impl AsRef<str>, impl AsRef<str>)>,
+    ) -> u64 {
+        let tag_first_idx = self.add_tags(1, tags);
+        self.ways
+            .push((tag_first_idx, self.nodes_index.len() as u64));
+        self.nodes_index.extend(nodes);
+        self.ways.len() as u64 - 1
+    }
+
+    /// Adds a relation with the members and the tags, and returns its index.
+    pub fn add_relation(
+        &mut self,
+        members: &[Member],
+        tags: impl IntoIterator<Item = (impl AsRef<str>, impl AsRef<str>)>,
+    ) -> u64 {
+        let tag_first_idx = self.add_tags(2, tags);
+        let members = members
+            .iter()
+            .map(|member| match *member {
+                Member::Node(idx, role) => (MemberIdx::Node(idx), self.insert_string(role)),
+                Member::Way(idx, role) => (MemberIdx::Way(idx), self.insert_string(role)),
+                Member::Relation(idx, role) => (MemberIdx::Relation(idx), self.insert_string(role)),
+            })
+            .collect();
+        self.members.push(members);
+        self.relations.push(tag_first_idx);
+        self.relations.len() as u64 - 1
+    }
+
+    /// Writes the archive.
+    ///
+    /// Fails if a way or a relation references an entity which was not added.
+    pub fn finish(mut self) -> io::Result<()> {
+        let num_nodes = self.nodes.len() as u64;
+        let num_ways = self.ways.len() as u64;
+        let num_relations = self.relations.len() as u64;
+        if let Some(idx) = self.nodes_index.iter().find(|&&idx| idx >= num_nodes) {
+            return Err(invalid_input(format!("way references missing node {idx}")));
+        }
+        for (member, _) in self.members.iter().flatten() {
+            match *member {
+                MemberIdx::Node(idx) if idx >= num_nodes => {
+                    return Err(invalid_input(format!(
+                        "relation references missing node {idx}"
+                    )))
+                }
+                MemberIdx::Way(idx) if idx >= num_ways => {
+                    return Err(invalid_input(format!(
+                        "relation references missing way {idx}"
+                    )))
+                }
+                MemberIdx::Relation(idx) if idx >= num_relations => {
+                    return Err(invalid_input(format!(
+                        "relation references missing relation {idx}"
+                    )))
+                }
+                _ => (),
+            }
+        }
+
+        let mut header = Header::new();
+        header.set_coord_scale(COORD_SCALE);
+        if num_nodes > 0 {
+            let lons = self.nodes.iter().map(|&(lon, _, _)| lon);
+            let lats = self.nodes.iter().map(|&(_, lat, _)| lat);
+            header.set_bbox_left(lons.clone().min().unwrap());
+            header.set_bbox_right(lons.max().unwrap());
+            header.set_bbox_bottom(lats.clone().min().unwrap());
+            header.set_bbox_top(lats.max().unwrap());
+        }
+        header.set_writingprogram_idx(self.insert_string("osmflat"));
+        header.set_source_idx(None);
+        header.set_replication_base_url_idx(None);
+        self.builder.set_header(&header)?;
+
+        let ways_tags_offset = self.tags_index[0].len() as u64;
+        let relations_tags_offset = ways_tags_offset + self.tags_index[1].len() as u64;
+        let tags_end = relations_tags_offset + self.tags_index[2].len() as u64;
+
+        // the sentinels contain the end of the ranges of the last entities
+        let mut nodes = flatdata::Vector::<Node>::new();
+        for &(lon, lat, tag_first_idx) in &self.nodes {
+            let node = nodes.grow();
+            node.set_lon(lon);
+            node.set_lat(lat);
+            node.set_tag_first_idx(tag_first_idx);
+        }
+        nodes.grow().set_tag_first_idx(ways_tags_offset);
+        self.builder.set_nodes(nodes.as_view())?;
+
+        let mut ways = flatdata::Vector::<Way>::new();
+        for &(tag_first_idx, ref_first_idx) in &self.ways {
+            let way = ways.grow();
+            way.set_tag_first_idx(ways_tags_offset + tag_first_idx);
+            way.set_ref_first_idx(ref_first_idx);
+        }
+        let sentinel = ways.grow();
+        sentinel.set_tag_first_idx(relations_tags_offset);
+        sentinel.set_ref_first_idx(self.nodes_index.len() as u64);
+        self.builder.set_ways(ways.as_view())?;
+
+        let mut relations = flatdata::Vector::<Relation>::new();
+        for &tag_first_idx in &self.relations {
+            relations
+                .grow()
+                .set_tag_first_idx(relations_tags_offset + tag_first_idx);
+        }
+        relations.grow().set_tag_first_idx(tags_end);
+        self.builder.set_relations(relations.as_view())?;
+        let nodes_index: Vec<NodeIndex> = self
+            .nodes_index
+            .iter()
+            .map(|&idx| {
+                let mut node_index = NodeIndex::new();
+                node_index.set_value(Some(idx));
+                node_index
+            })
+            .collect();
+        self.builder.set_nodes_index(&nodes_index)?;
+
+        let mut relation_members = self.builder.start_relation_members()?;
+        for members in &self.members {
+            let mut item = relation_members.grow()?;
+            for &(member, role_idx) in members {
+                match member {
+                    MemberIdx::Node(idx) => {
+                        let member = item.add_node_member();
+                        member.set_node_idx(Some(idx));
+                        member.set_role_idx(role_idx);
+                    }
+                    MemberIdx::Way(idx) => {
+                        let member = item.add_way_member();
+                        member.set_way_idx(Some(idx));
+                        member.set_role_idx(role_idx);
+                    }
+                    MemberIdx::Relation(idx) => {
+                        let member = item.add_relation_member();
+                        member.set_relation_idx(Some(idx));
+                        member.set_role_idx(role_idx);
+                    }
+                }
+            }
+        }
+        relation_members.close().map_err(io::Error::other)?;
+
+        self.builder.set_tags(&self.tags)?;
+        let tags_index: Vec<TagIndex> = self
+            .tags_index
+            .iter()
+            .flatten()
+            .map(|&idx| {
+                let mut tag_index = TagIndex::new();
+                tag_index.set_value(idx);
+                tag_index
+            })
+            .collect();
+        self.builder.set_tags_index(&tags_index)?;
+        self.builder.set_stringtable(&self.strings)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{iter_tags, Osm, RelationMembersRef};
+
+    #[test]
+    fn test_write() {
+        let storage = flatdata::MemoryResourceStorage::new("/test");
+        let mut writer = OsmWriter::new(storage.clone()).unwrap();
+        let a = writer.add_node(1.5, -2.25, [("name", "a"), ("amenity", "pub")]);
+        let b = writer.add_node(3.0, 4.0, [("amenity", "pub")]);
+        let c = writer.add_node(-1.0, 0.0, None::<(&str, &str)>);
+        let way = writer.add_way(&[a, b, c, a], [("highway", "primary")]);
+        writer.add_relation(
+            &[
+                Member::Node(c, "stop"),
+                Member::Way(way, ""),
+                Member::Relation(1, "sub"),
+            ],
+            [("type", "route")],
+        );
+        writer.add_relation(&[], [("name", "a")]);
+        writer.finish().unwrap();
+
+        let archive = Osm::open(storage).unwrap();
+        let header = archive.header();
+        assert_eq!(header.coord_scale(), COORD_SCALE);
+        assert_eq!(
+            (header.bbox_left(), header.bbox_right()),
+            (-10_000_000, 30_000_000)
+        );
+        assert_eq!(
+            (header.bbox_bottom(), header.bbox_top()),
+            (-22_500_000, 40_000_000)
+        );
+
+        let nodes = archive.nodes();
+        assert_eq!(nodes.len(), 3);
+        assert_eq!((nodes[0].lon(), nodes[0].lat()), (15_000_000, -22_500_000));
+        let tags = |range| -> Vec<_> { iter_tags(&archive, range).collect() };
+        assert_eq!(
+            tags(nodes[0].tags()),
+            [(&b"name"[..], &b"a"[..]), (b"amenity", b"pub")]
+        );
+        assert_eq!(tags(nodes[2].tags()), []);
+        // equal tags and strings are stored once
+        assert_eq!(archive.tags().len(), 4);
+        assert_eq!(
+            archive.tags_index()[1].value(),
+            archive.tags_index()[2].value()
+        );
+
+        let ways = archive.ways();
+        assert_eq!(ways.len(), 1);
+        let refs: Vec<_> = ways[0]
+            .refs()
+            .map(|idx| archive.nodes_index()[idx as usize].value())
+            .collect();
+        assert_eq!(refs, [Some(0), Some(1), Some(2), Some(0)]);
+
+        let relations = archive.relations();
+        assert_eq!(relations.len(), 2);
+        assert_eq!(tags(relations[1].tags()), [(&b"name"[..], &b"a"[..])]);
+        let strings = archive.stringtable();
+        let members: Vec<_> = archive
+            .relation_members()
+            .at(0)
+            .map(|member| match member {
+                RelationMembersRef::NodeMember(m) => (
+                    'n',
+                    m.node_idx(),
+                    strings.substring(m.role_idx() as usize).unwrap(),
+                ),
+                RelationMembersRef::WayMember(m) => (
+                    'w',
+                    m.way_idx(),
+                    strings.substring(m.role_idx() as usize).unwrap(),
+                ),
+                RelationMembersRef::RelationMember(m) => (
+                    'r',
+                    m.relation_idx(),
+                    strings.substring(m.role_idx() as usize).unwrap(),
+                ),
+            })
+            .collect();
+        assert_eq!(
+            members,
+            [
+                ('n', Some(2), "stop"),
+                ('w', Some(0), ""),
+                ('r', Some(1), "sub")
+            ]
+        );
+        assert_eq!(archive.relation_members().at(1).count(), 0);
+    }
+
+    #[test]
+    fn test_interleaved_entities() {
+        let storage = flatdata::MemoryResourceStorage::new("/test");
+        let mut writer = OsmWriter::new(storage.clone()).unwrap();
+        let a = writer.add_node(0.0, 0.0, [("a", "1")]);
+        writer.add_relation(&[], [("r", "1")]);
+        writer.add_way(&[a], [("w", "1"), ("w", "2")]);
+        writer.add_node(1.0, 1.0, None::<(&str, &str)>);
+        writer.add_way(&[a], [("w", "3")]);
+        writer.add_node(2.0, 2.0, [("b", "2")]);
+        writer.finish().unwrap();
+
+        let archive = Osm::open(storage).unwrap();
+        let keys = |range| -> Vec<_> { iter_tags(&archive, range).map(|(k, _)| k).collect() };
+        let nodes = archive.nodes();
+        assert_eq!(keys(nodes[0].tags()), [b"a"]);
+        assert_eq!(keys(nodes[1].tags()), Vec::<&[u8]>::new());
+        assert_eq!(keys(nodes[2].tags()), [b"b"]);
+        let ways = archive.ways();
+        assert_eq!(keys(ways[0].tags()), [b"w", b"w"]);
+        assert_eq!(keys(ways[1].tags()), [b"w"]);
+        assert_eq!(keys(archive.relations()[0].tags()), [b"r"]);
+    }
+
+    #[test]
+    fn test_missing_references() {
+        let storage = flatdata::MemoryResourceStorage::new("/test");
+        let mut writer = OsmWriter::new(storage).unwrap();
+        writer.add_node(0.0, 0.0, [("a", "b")]);
+        writer.add_way(&[0, 1], [("a", "b")]);
+        let err = writer.finish().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+        let storage = flatdata::MemoryResourceStorage::new("/test");
+        let mut writer = OsmWriter::new(storage).unwrap();
+        writer.add_relation(&[Member::Relation(1, "")], [("a", "b")]);
+        assert!(writer.finish().is_err());
+    }
+}
@@ -1,22 +1,306 @@
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 
 /// Compiler of Open Street Data from osm.pbf format to osm.flatdata format
 #[derive(Debug, Parser)]
 #[clap(about, version, author)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Args {
     /// Verbose mode (-v, -vv, -vvv, etc.)
-    #[clap(short, long, action = clap::ArgAction::Count)]
+    #[clap(short, long, global = true, action = clap::ArgAction::Count)]
     pub verbose: u8,
 
-    /// Input OSM pbf file
-    pub input: PathBuf,
+    /// Number of threads processing blocks in parallel; defaults to the number
+    /// of CPUs
+    #[arg(long = "threads", global = true, env = "OSMFLATC_THREADS")]
+    pub threads: Option<NonZeroUsize>,
+
+    /// Number of blocks per thread which are buffered between reading and
+    /// converting them; defaults to 2
+    #[arg(long = "queue-depth", global = true)]
+    pub queue_depth: Option<NonZeroUsize>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Input OSM pbf file, or OPL file if the extension is opl; may also be an
+    /// http(s) URL, whose blocks are downloaded with range requests if the
+    /// server supports them, and which is downloaded to a temporary file first
+    /// otherwise (requires the feature `remote`)
+    #[arg(required = true)]
+    pub input: Option<PathBuf>,
 
     /// Output directory for OSM flatdata archive
-    pub output: PathBuf,
+    #[arg(required = true)]
+    pub output: Option<PathBuf>,
+
+    /// Replace the output directory if it is not empty
+    #[arg(long = "force")]
+    pub force: bool,
 
     /// Whether to compile the optional ids subs
     #[arg(long = "ids")]
     pub ids: bool,
+
+    /// Whether to store all versions of a full-history file in the optional
+    /// history subs
+    #[arg(long = "history")]
+    pub history: bool,
+
+    /// Only convert entities matching a filter like `w/highway` or
+    /// `n/amenity=restaurant`, and the entities referenced by them; can be
+    /// given multiple times
+    #[arg(long = "filter")]
+    pub filter: Vec<osmflatc::TagFilter>,
+
+    /// Only convert tags with keys matching a comma separated list of
+    /// patterns, in which `*` matches any characters, e.g. `highway,name,name:*`
+    #[arg(long = "keep-tags", value_delimiter = ',')]
+    pub keep_tags: Vec<osmflatc::KeyPattern>,
+
+    /// Do not convert tags with keys matching a comma separated list of
+    /// patterns, in which `*` matches any characters, e.g.
+    /// `created_by,source,tiger:*`
+    #[arg(long = "drop-tags", value_delimiter = ',')]
+    pub drop_tags: Vec<osmflatc::KeyPattern>,
+
+    /// Skip nodes which lose all their tags by --keep-tags or --drop-tags,
+    /// unless they are referenced by ways or relations
+    #[arg(long = "drop-untagged-nodes")]
+    pub drop_untagged_nodes: bool,
+
+    /// Order of the nodes in the archive: input, or hilbert to sort them along
+    /// the Hilbert curve for spatial locality
+    #[arg(long = "sort", default_value = "input")]
+    pub sort: osmflatc::NodeOrder,
+
+    /// Write the stringtable ordered by descending frequency of the strings, so
+    /// that frequent keys and values have small indexes which compress better;
+    /// requires an additional pass over the input
+    #[arg(long = "optimize-stringtable")]
+    pub optimize_stringtable: bool,
+
+    /// Number of coordinate units per degree, e.g. 10000000 for a precision of
+    /// 1e-7 degrees; has to divide 10^9. By default, the coarsest scale
+    /// representing all input coordinates exactly is used. Coordinates are
+    /// rounded to the scale, and have to fit into 32 bits.
+    #[arg(long = "coord-scale")]
+    pub coord_scale: Option<i32>,
+
+    /// Store the changesets of the input with their ids, timestamps, users,
+    /// bounding boxes, and tags; by default, changesets are skipped
+    #[arg(long = "changesets")]
+    pub changesets: bool,
+
+    /// Store the bounding box of each way and relation for fast spatial filtering
+    #[arg(long = "bboxes")]
+    pub bboxes: bool,
+
+    /// Store the indexes of the nodes having tags, so that iterating over
+    /// points of interest skips the untagged nodes
+    #[arg(long = "tagged-nodes")]
+    pub tagged_nodes: bool,
+
+    /// Store bitsets marking the nodes and ways having tags, a more compact
+    /// alternative to `--tagged-nodes` for archives with many tagged nodes
+    #[arg(long = "tagged-bitsets")]
+    pub tagged_bitsets: bool,
+
+    /// Build a spatial index of nodes, ways, and relations for bounding box
+    /// queries
+    #[arg(long = "spatial-index")]
+    pub spatial_index: bool,
+
+    /// Build an inverted index of tags for looking up entities by tag
+    #[arg(long = "inverted-index")]
+    pub inverted_index: bool,
+
+    /// Build an index for looking up entities by their OSM ids; requires
+    /// --ids
+    #[arg(long = "id-index", requires = "ids")]
+    pub id_index: bool,
+
+    /// Build an index of the ways containing each node, e.g. for finding
+    /// intersections
+    #[arg(long = "node-ways")]
+    pub node_ways: bool,
+
+    /// Build an index of the relations having each node, way, and relation as
+    /// member, e.g. for finding the routes of a way
+    #[arg(long = "member-relations")]
+    pub member_relations: bool,
+
+    /// Assemble the areas of closed ways and multipolygon relations, e.g. for
+    /// rendering land use
+    #[arg(long = "areas")]
+    pub areas: bool,
+
+    /// Store the offsets of the strings in the stringtable, so that reading a
+    /// string does not scan for its terminator
+    #[arg(long = "stringtable-offsets")]
+    pub stringtable_offsets: bool,
+
+    /// Store a copy of the coordinates of the nodes separately from their
+    /// tags, so that scans over the coordinates only read fewer bytes; the
+    /// coordinates in the nodes are kept, which costs 8 bytes per node
+    #[arg(long = "node-coords")]
+    pub node_coords: bool,
+
+    /// Sample the elevation of each node from the tiles in SRTM .hgt format
+    /// in this directory, e.g. N52E013.hgt; nodes outside of the tiles have no
+    /// elevation
+    #[arg(long = "elevation", value_name = "DEM_DIR")]
+    pub elevation: Option<PathBuf>,
+
+    /// Build an index of the nodes and ways in each Web Mercator tile of this
+    /// zoom level, e.g. 14, for reading whole tiles; works best with
+    /// `--sort hilbert`
+    #[arg(long = "tile-index", value_name = "ZOOM")]
+    pub tile_index: Option<u8>,
+
+    /// Store the administrative boundaries containing each node, e.g. for
+    /// reverse geocoding to country, state and city
+    #[arg(long = "admin-hierarchy")]
+    pub admin_hierarchy: bool,
+
+    /// Store the values of the tags with keys matching a comma separated list
+    /// of patterns parsed as numbers, e.g.
+    /// `population,maxspeed,building:levels,width`
+    #[arg(long = "numeric-tags", value_delimiter = ',')]
+    pub numeric_tags: Vec<osmflatc::KeyPattern>,
+
+    /// Store the table of the distinct roles of relation members, which
+    /// numbers the roles densely and finds a role without scanning the
+    /// stringtable
+    #[arg(long = "roles")]
+    pub roles: bool,
+
+    /// Store a representative point of each way and relation, e.g. for
+    /// placing labels or sorting by distance
+    #[arg(long = "centroids")]
+    pub centroids: bool,
+
+    /// Validate the invariants of the archive after the conversion, e.g. that
+    /// all indexes point inside their target vectors
+    #[arg(long = "verify")]
+    pub verify: bool,
+
+    /// Compress the resources of the archive: zstd[:level] with a level from 1
+    /// to 22, by default 3. Compressed archives are read with
+    /// `osmflat::zstd_resource_storage` or `osmflat::zstd_cached_resource_storage`.
+    #[arg(long = "compress")]
+    pub compress: Option<osmflatc::Compression>,
+
+    /// After the conversion, keep the archive up to date with the replication
+    /// service given in its header; an existing archive is not converted again
+    #[arg(long = "follow")]
+    pub follow: bool,
+
+    /// Interval in seconds of polling the replication service
+    #[arg(long = "follow-interval", default_value_t = 60, requires = "follow")]
+    pub follow_interval: u64,
+
+    /// Format of the conversion stats
+    #[arg(long = "stats-format", value_enum, default_value_t = StatsFormat::Text)]
+    pub stats_format: StatsFormat,
+
+    /// Output of the progress of the conversion
+    #[arg(long = "progress", value_enum, default_value_t = ProgressOutput::Auto)]
+    pub progress: ProgressOutput,
+
+    /// Write the conversion stats to this file instead of stdout
+    #[arg(long = "stats-output")]
+    pub stats_output: Option<PathBuf>,
+
+    /// Write the ids of entities missing in the input to this file, with one
+    /// line `<parent type> <parent id> <missing type> <missing id>` per
+    /// reference of a way or relation
+    #[arg(long = "report-unresolved")]
+    pub report_unresolved: Option<PathBuf>,
+}
+
+/// Commands other than the conversion
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Write an OSM flatdata archive back to OSM pbf format
+    ExportPbf {
+        /// Input OSM flatdata archive
+        archive: PathBuf,
+        /// Output OSM pbf file
+        output: PathBuf,
+    },
+    /// Split an OSM flatdata archive into archives of Web Mercator tiles
+    SplitTiles {
+        /// Zoom level of the tiles, e.g. 6 or 8
+        #[arg(long = "zoom", default_value_t = 6)]
+        zoom: u8,
+        /// Input OSM flatdata archive
+        archive: PathBuf,
+        /// Output directory for the tiles
+        output: PathBuf,
+    },
+    /// Extract the entities in a bounding box or polygon from an OSM flatdata
+    /// archive into a new archive
+    Extract {
+        /// Bounding box as min_lon,min_lat,max_lon,max_lat
+        #[arg(
+            long = "bbox",
+            allow_hyphen_values = true,
+            required_unless_present = "polygon"
+        )]
+        bbox: Option<osmflatc::Region>,
+        /// Polygon as Osmosis polygon file if the extension is poly, or as
+        /// GeoJSON file otherwise
+        #[arg(long = "polygon", conflicts_with = "bbox")]
+        polygon: Option<PathBuf>,
+        /// Replace the output directory if it is not empty
+        #[arg(long = "force")]
+        force: bool,
+        /// Input OSM flatdata archive
+        archive: PathBuf,
+        /// Output directory for the extracted archive
+        output: PathBuf,
+    },
+    /// Measure the throughput of the conversion stages with different numbers
+    /// of threads and queue depths on a sample of the input
+    Bench {
+        /// Numbers of threads to measure, e.g. 1,4,8; defaults to the powers
+        /// of two up to the number of CPUs
+        #[arg(long = "thread-counts", value_delimiter = ',')]
+        thread_counts: Vec<NonZeroUsize>,
+        /// Queue depths to measure
+        #[arg(long = "queue-depths", value_delimiter = ',', default_value = "1,2,4")]
+        queue_depths: Vec<NonZeroUsize>,
+        /// Number of blocks of each type converted in each measurement
+        #[arg(long = "sample-blocks", default_value_t = 64)]
+        sample_blocks: usize,
+        /// Input OSM pbf file, or OPL file if the extension is opl
+        input: PathBuf,
+    },
+}
+
+/// Format of the conversion stats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StatsFormat {
+    /// Human readable text
+    Text,
+    /// JSON object with counts, stage durations and resource sizes
+    Json,
+}
+
+/// Output of the progress of the conversion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ProgressOutput {
+    /// Progress bars if stderr is a terminal, log messages otherwise
+    Auto,
+    /// Progress bars
+    Tty,
+    /// Log messages, e.g. for CI logs
+    Log,
+    /// JSON lines on stderr, one object per event
+    Json,
+    /// No progress
+    None,
 }
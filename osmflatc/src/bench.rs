@@ -0,0 +1,179 @@
+//! Benchmark of the conversion stages for tuning `--threads` and
+//! `--queue-depth` on the hardware at hand, cf. [`bench`].
+
+use crate::convert::{self, Input, Options};
+use crate::osmpbf;
+use crate::progress::{Stage, StageTimer};
+use crate::Error;
+
+use memmap2::Mmap;
+
+use std::fs::File;
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Configurations measured by [`bench`].
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    /// Numbers of threads to measure
+    pub threads: Vec<NonZeroUsize>,
+    /// Queue depths to measure, cf. `Options::queue_depth`
+    pub queue_depths: Vec<NonZeroUsize>,
+    /// Number of blocks of each type which are converted in each run
+    pub sample_blocks: usize,
+}
+
+/// Throughput of the conversion stages with a number of threads and a queue
+/// depth.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchResult {
+    /// Number of threads
+    pub threads: usize,
+    /// Queue depth, cf. `Options::queue_depth`
+    pub queue_depth: usize,
+    /// Throughput of building the index of all blocks of the input in MB/s
+    pub block_index_mb_per_sec: f64,
+    /// Throughput of converting the sampled dense nodes blocks in nodes/s
+    pub nodes_per_sec: f64,
+    /// Throughput of converting the sampled ways blocks in ways/s
+    pub ways_per_sec: f64,
+    /// Throughput of converting the sampled relations blocks in relations/s
+    pub relations_per_sec: f64,
+}
+
+fn per_sec(count: f64, duration: Duration) -> f64 {
+    count / duration.as_secs_f64().max(1e-9)
+}
+
+/// Converts the first `sample_blocks` blocks of each type of `input` into a
+/// temporary archive.
+fn bench_sample<B, R>(
+    mut input: Input<B, R>,
+    queue_depth: NonZeroUsize,
+    sample_blocks: usize,
+    block_index_duration: Duration,
+    input_len: usize,
+) -> Result<BenchResult, Error>
+where
+    B: Clone + Send,
+    R: Fn(B) -> io::Result<osmpbf::PrimitiveBlock> + Sync,
+{
+    input.dense_nodes.truncate(sample_blocks);
+    input.ways.truncate(sample_blocks);
+    input.relations.truncate(sample_blocks);
+    input.changesets.clear();
+
+    let output = tempfile::tempdir()?;
+    let timer = StageTimer::new(());
+    let options = Options {
+        queue_depth: Some(queue_depth),
+        ..Options::default()
+    };
+    let stats = convert::convert_blocks(output.path(), &options, input, &timer)?;
+    let durations = timer.into_durations();
+    let duration = |stage| {
+        durations
+            .iter()
+            .find(|(s, _)| *s == stage)
+            .map_or(Duration::ZERO, |(_, duration)| *duration)
+    };
+    Ok(BenchResult {
+        threads: rayon::current_num_threads(),
+        queue_depth: queue_depth.get(),
+        block_index_mb_per_sec: per_sec(input_len as f64 / 1e6, block_index_duration),
+        nodes_per_sec: per_sec(stats.num_nodes as f64, duration(Stage::Nodes)),
+        ways_per_sec: per_sec(stats.num_ways as f64, duration(Stage::Ways)),
+        relations_per_sec: per_sec(stats.num_relations as f64, duration(Stage::Relations)),
+    })
+}
+
+fn bench_run(
+    data: &[u8],
+    is_opl: bool,
+    queue_depth: NonZeroUsize,
+    sample_blocks: usize,
+) -> Result<BenchResult, Error> {
+    let started = Instant::now();
+    if is_opl {
+        let input = convert::opl_input(data)?;
+        bench_sample(
+            input,
+            queue_depth,
+            sample_blocks,
+            started.elapsed(),
+            data.len(),
+        )
+    } else {
+        let input = convert::pbf_input(data)?;
+        bench_sample(
+            input,
+            queue_depth,
+            sample_blocks,
+            started.elapsed(),
+            data.len(),
+        )
+    }
+}
+
+/// Measures the throughput of the conversion stages for each combination of
+/// the numbers of threads and queue depths in `options`.
+///
+/// The block index is built over the whole input, while only a sample of the
+/// blocks of each type is converted, so references to entities outside of the
+/// sample are unresolved. The throughput is therefore indicative for comparing
+/// the configurations, not for predicting the duration of a conversion.
+pub fn bench(input: &Path, options: &BenchOptions) -> Result<Vec<BenchResult>, Error> {
+    let data = unsafe { Mmap::map(&File::open(input)?)? };
+    let is_opl = input.extension().is_some_and(|ext| ext == "opl");
+    let mut results = Vec::new();
+    for &threads in &options.threads {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads.get())
+            .build()?;
+        for &queue_depth in &options.queue_depths {
+            results.push(
+                pool.install(|| bench_run(&data, is_opl, queue_depth, options.sample_blocks))?,
+            );
+        }
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::fmt::Write;
+
+    #[test]
+    fn test_bench() {
+        let mut opl = String::new();
+        for i in 1..10_000 {
+            writeln!(opl, "n{i} v1 x{} y{}", f64::from(i % 100) / 10.0, 1.5).unwrap();
+        }
+        for i in 1..100 {
+            writeln!(opl, "w{i} v1 Nn{i},n{}", i + 1).unwrap();
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+
+        let n = |x| NonZeroUsize::new(x).unwrap();
+        let options = BenchOptions {
+            threads: vec![n(1), n(2)],
+            queue_depths: vec![n(1)],
+            sample_blocks: 1,
+        };
+        let results = bench(&input, &options).unwrap();
+        assert_eq!(
+            results
+                .iter()
+                .map(|r| (r.threads, r.queue_depth))
+                .collect::<Vec<_>>(),
+            [(1, 1), (2, 1)]
+        );
+        assert!(results.iter().all(|r| r.nodes_per_sec > 0.0));
+    }
+}
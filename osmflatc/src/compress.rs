@@ -0,0 +1,187 @@
+//! Compression of the resources of an archive, cf. `osmflat::zstd_resource_storage`
+//! for reading compressed archives.
+
+use crate::Error;
+
+use flatdata::FileResourceStorage;
+use osmflat::Osm;
+use rayon::prelude::*;
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Compression of the resources of an archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// zstd with the given compression level
+    Zstd { level: i32 },
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, level) = match s.split_once(':') {
+            Some((name, level)) => (name, Some(level)),
+            None => (s, None),
+        };
+        match (name, level) {
+            ("zstd", None) => Ok(Self::Zstd { level: 3 }),
+            ("zstd", Some(level)) => match level.parse() {
+                Ok(level) if (1..=22).contains(&level) => Ok(Self::Zstd { level }),
+                _ => Err(format!(
+                    "invalid zstd level: {level}, expected a number from 1 to 22"
+                )),
+            },
+            _ => Err(format!("invalid compression: {s}, expected zstd[:level]")),
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Zstd { level } => write!(f, "zstd:{level}"),
+        }
+    }
+}
+
+/// Resources of the archive in `dir` by directory, skipping the schemas and
+/// the archive signatures.
+fn resources(dir: &Path, result: &mut Vec<(PathBuf, Vec<(String, u64)>)>) -> io::Result<()> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            resources(&entry.path(), result)?;
+        } else if !name.ends_with(".schema") && !name.ends_with(".archive") {
+            files.push((name, metadata.len()));
+        }
+    }
+    files.sort();
+    result.push((dir.to_path_buf(), files));
+    Ok(())
+}
+
+fn compress_resource(path: &Path, level: i32) -> io::Result<()> {
+    #[cfg(feature = "zstd")]
+    {
+        let mut compressed = path.as_os_str().to_owned();
+        compressed.push(".zst");
+        let mut encoder = zstd::Encoder::new(fs::File::create(compressed)?, level)?;
+        io::copy(&mut fs::File::open(path)?, &mut encoder)?;
+        encoder.finish()?;
+        fs::remove_file(path)
+    }
+    #[cfg(not(feature = "zstd"))]
+    {
+        let _ = (path, level);
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "zstd compression is not supported, enable the feature `zstd`",
+        ))
+    }
+}
+
+/// Compresses the resources of the archive at `archive` in place.
+///
+/// Each resource is replaced by its compressed file `<name>.zst`, and listed
+/// in the index file `zstd.index` of its directory.
+pub fn compress_archive(archive: &Path, compression: Compression) -> Result<(), Error> {
+    let Compression::Zstd { level } = compression;
+    let mut dirs = Vec::new();
+    resources(archive, &mut dirs)?;
+    dirs.par_iter()
+        .flat_map(|(dir, files)| files.par_iter().map(move |(name, _)| dir.join(name)))
+        .try_for_each(|path| compress_resource(&path, level))?;
+    for (dir, files) in dirs.iter().filter(|(_, files)| !files.is_empty()) {
+        let index: String = files
+            .iter()
+            .map(|(name, size)| format!("{name} {size}\n"))
+            .collect();
+        fs::write(dir.join(osmflat::ZSTD_INDEX), index)?;
+    }
+    Ok(())
+}
+
+/// Archive opened by [`open_archive`].
+pub struct Archive {
+    osm: Osm,
+    /// Decompressed resources of a compressed archive, removed after the
+    /// archive is dropped
+    _cache: Option<tempfile::TempDir>,
+}
+
+impl Deref for Archive {
+    type Target = Osm;
+
+    fn deref(&self) -> &Osm {
+        &self.osm
+    }
+}
+
+/// Opens the archive at `path`, which may be compressed.
+///
+/// A compressed archive is decompressed into a temporary directory next to it,
+/// from which it is memory mapped, so that it does not need to fit into memory.
+pub fn open_archive(path: &Path) -> Result<Archive, Error> {
+    if path.join(osmflat::ZSTD_INDEX).exists() {
+        #[cfg(feature = "zstd")]
+        {
+            let cache = crate::convert::temp_archive_dir(path)?;
+            let storage = osmflat::zstd_cached_resource_storage(path, cache.path())?;
+            return Ok(Archive {
+                osm: Osm::open(storage)?,
+                _cache: Some(cache),
+            });
+        }
+        #[cfg(not(feature = "zstd"))]
+        return Err("compressed archives are not supported, enable the feature `zstd`".into());
+    }
+    Ok(Archive {
+        osm: Osm::open(FileResourceStorage::new(path.to_path_buf()))?,
+        _cache: None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_compression() {
+        assert_eq!("zstd".parse(), Ok(Compression::Zstd { level: 3 }));
+        assert_eq!("zstd:19".parse(), Ok(Compression::Zstd { level: 19 }));
+        assert!("zstd:0".parse::<Compression>().is_err());
+        assert!("zstd:x".parse::<Compression>().is_err());
+        assert!("gzip".parse::<Compression>().is_err());
+        assert_eq!(Compression::Zstd { level: 19 }.to_string(), "zstd:19");
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_open_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        fs::write(&input, "n1 v1 x1 y1\nn2 v1 x2 y2\n").unwrap();
+        let output = dir.path().join("output");
+        let options = crate::Options {
+            compress: Some(Compression::Zstd { level: 3 }),
+            ..Default::default()
+        };
+        crate::convert(&input, &output, options, ()).unwrap();
+
+        let archive = open_archive(&output).unwrap();
+        assert_eq!(archive.nodes().len(), 2);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+        drop(archive);
+        // the decompressed resources are removed
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+}
@@ -0,0 +1,3158 @@
+use crate::compress::{self, Compression};
+use crate::elevation;
+use crate::filter::{self, KeyPattern, Selection, TagFilter, TagKeys};
+use crate::ids;
+use crate::inverted_index;
+use crate::opl::{self, OplBlock};
+use crate::osmpbf::{self, BlockIndex, BlockType};
+use crate::parallel;
+use crate::progress::{Progress, Stage, StageTimer};
+use crate::provenance;
+use crate::remote;
+use crate::spatial;
+use crate::stats::Stats;
+use crate::strings::StringTable;
+use crate::unresolved::{self, UnresolvedRef};
+use crate::Error;
+
+use flatdata::{FileResourceStorage, ResourceStorage};
+use itertools::Itertools;
+use log::warn;
+use memmap2::Mmap;
+use osmflat::EntityType;
+
+use ahash::AHashMap;
+use std::borrow::Cow;
+use std::collections::hash_map;
+use std::fs::{self, File};
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::str::{self, FromStr};
+
+/// Options of the conversion.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    /// Whether to compile the optional ids subarchive
+    pub ids: bool,
+    /// Whether to store all versions of the entities, including deleted ones,
+    /// and to compile the optional history subarchive
+    pub history: bool,
+    /// Filters selecting the entities to convert, together with the entities
+    /// they reference; all entities are converted if empty
+    pub filter: Vec<TagFilter>,
+    /// Patterns of the keys of the tags which are converted; all tags are
+    /// converted if empty
+    pub keep_tags: Vec<KeyPattern>,
+    /// Patterns of the keys of tags which are not converted
+    pub drop_tags: Vec<KeyPattern>,
+    /// Skip nodes whose tags are all not converted, unless they are referenced
+    /// by ways or relations
+    pub drop_untagged_nodes: bool,
+    /// Order of the nodes in the archive
+    pub sort: NodeOrder,
+    /// Write the strings to the stringtable in descending order of their number
+    /// of occurrences, which requires an additional pass over the input
+    pub optimize_stringtable: bool,
+    /// Number of coordinate units per degree, which has to divide 10^9; by
+    /// default the coarsest scale representing all coordinates of the input
+    /// exactly
+    pub coord_scale: Option<i32>,
+    /// Store the changesets of the input in the optional changesets
+    /// subarchive; otherwise, they are skipped
+    pub changesets: bool,
+    /// Store the bounding boxes of ways and relations in the `way_bboxes` and
+    /// `relation_bboxes` resources
+    pub bboxes: bool,
+    /// Store the indexes of the nodes having tags in the `tagged_nodes`
+    /// resource
+    pub tagged_nodes: bool,
+    /// Store the bitsets marking the nodes and ways having tags in the
+    /// `tagged_node_bits` and `tagged_way_bits` resources
+    pub tagged_bitsets: bool,
+    /// Build the `spatial_index` subarchive
+    pub spatial_index: bool,
+    /// Build the `inverted_index` subarchive
+    pub inverted_index: bool,
+    /// Build the `id_index` subarchive mapping ids to indexes, which requires
+    /// `ids`
+    pub id_index: bool,
+    /// Build the `node_ways` subarchive mapping nodes to the ways containing
+    /// them
+    pub node_ways: bool,
+    /// Build the `node_relations`, `way_relations` and `relation_relations`
+    /// subarchives mapping entities to the relations having them as member
+    pub member_relations: bool,
+    /// Build the `areas` subarchive with the assembled areas of closed ways and
+    /// multipolygon relations
+    pub areas: bool,
+    /// Store the offsets of the strings in the `stringtable_offsets` resource
+    pub stringtable_offsets: bool,
+    /// Store a copy of the coordinates of the nodes in the `node_coords`
+    /// resource; opt-in, since it duplicates the coordinates in `nodes` and
+    /// adds 8 bytes per node
+    pub node_coords: bool,
+    /// Directory of tiles in SRTM `.hgt` format, from which the elevation of
+    /// the nodes is sampled into the `elevation` resource
+    pub elevation: Option<PathBuf>,
+    /// Zoom level of the tiles of the `tile_index` subarchive
+    pub tile_index: Option<u8>,
+    /// Build the `admin_hierarchy` subarchive mapping nodes to the
+    /// administrative boundaries containing them
+    pub admin_hierarchy: bool,
+    /// Keys of the tags whose values are stored parsed as numbers in the
+    /// `tag_numbers` resource
+    pub numeric_tags: Vec<KeyPattern>,
+    /// Store the distinct roles of relation members in the `roles` resource
+    pub roles: bool,
+    /// Store the representative points of ways and relations in the
+    /// `way_centroids` and `relation_centroids` resources
+    pub centroids: bool,
+    /// Validate the invariants of the archive after the conversion, cf.
+    /// `Stats::violations`
+    pub verify: bool,
+    /// Compress the resources of the archive after the conversion; the
+    /// provenance is stored uncompressed
+    pub compress: Option<Compression>,
+    /// Write the references to entities missing in the input to this file,
+    /// cf. `Stats::unresolved_refs`
+    pub report_unresolved: Option<PathBuf>,
+    /// Replace the output directory if it is not empty; otherwise, the
+    /// conversion fails in this case
+    pub force: bool,
+    /// Number of blocks per thread which are buffered between reading and
+    /// converting them in the parallel stages; `DEFAULT_QUEUE_DEPTH` if not
+    /// set. Deeper queues even out blocks which take different times to
+    /// convert, at the cost of memory for the buffered blocks.
+    pub queue_depth: Option<NonZeroUsize>,
+}
+
+/// Order of the nodes in the archive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NodeOrder {
+    /// Order of the input
+    #[default]
+    Input,
+    /// Order along the Hilbert curve, cf. `osmflat::hilbert_index`
+    ///
+    /// The nodes are buffered in memory to be sorted.
+    Hilbert,
+}
+
+impl FromStr for NodeOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "input" => Ok(Self::Input),
+            "hilbert" => Ok(Self::Hilbert),
+            _ => Err(format!(
+                "invalid node order: {s}, expected input or hilbert"
+            )),
+        }
+    }
+}
+
+/// Converts a coordinate in nanodegrees into units of `granularity`
+/// nanodegrees, rounded to the nearest unit.
+fn scale_coord(nanodegrees: i64, granularity: i32) -> io::Result<i32> {
+    let granularity = i64::from(granularity);
+    let units = (nanodegrees + nanodegrees.signum() * (granularity / 2)) / granularity;
+    i32::try_from(units).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "coordinate {} is out of range for coordinate scale {}",
+                nanodegrees as f64 / 1e9,
+                1_000_000_000 / granularity
+            ),
+        )
+    })
+}
+
+fn serialize_header(
+    header_block: &osmpbf::HeaderBlock,
+    coord_scale: i32,
+    builder: &osmflat::OsmBuilder,
+    stringtable: &mut StringTable,
+) -> io::Result<()> {
+    let mut header = osmflat::Header::new();
+
+    header.set_coord_scale(coord_scale);
+
+    if let Some(ref bbox) = header_block.bbox {
+        let granularity = 1000000000 / coord_scale;
+        header.set_bbox_left(scale_coord(bbox.left, granularity)?);
+        header.set_bbox_right(scale_coord(bbox.right, granularity)?);
+        header.set_bbox_top(scale_coord(bbox.top, granularity)?);
+        header.set_bbox_bottom(scale_coord(bbox.bottom, granularity)?);
+    };
+
+    header.set_writingprogram_idx(stringtable.insert("osmflatc"));
+
+    header.set_source_idx(header_block.source.as_ref().map(|s| stringtable.insert(s)));
+
+    if let Some(timestamp) = header_block.osmosis_replication_timestamp {
+        header.set_replication_timestamp(timestamp);
+    }
+
+    if let Some(number) = header_block.osmosis_replication_sequence_number {
+        header.set_replication_sequence_number(number);
+    }
+
+    header.set_replication_base_url_idx(
+        (header_block.osmosis_replication_base_url.as_ref()).map(|url| stringtable.insert(url)),
+    );
+
+    builder.set_header(&header)?;
+    Ok(())
+}
+
+#[derive(PartialEq, Eq, Copy, Clone)]
+struct I40 {
+    x: [u8; 5],
+}
+
+impl I40 {
+    fn from_u64(x: u64) -> Self {
+        let x = x.to_le_bytes();
+        debug_assert_eq!((x[5], x[6], x[7]), (0, 0, 0));
+        Self {
+            x: [x[0], x[1], x[2], x[3], x[4]],
+        }
+    }
+
+    fn to_u64(self) -> u64 {
+        let extented = [
+            self.x[0], self.x[1], self.x[2], self.x[3], self.x[4], 0, 0, 0,
+        ];
+        u64::from_le_bytes(extented)
+    }
+}
+
+#[allow(clippy::derived_hash_with_manual_eq)]
+impl std::hash::Hash for I40 {
+    fn hash<H>(&self, h: &mut H)
+    where
+        H: std::hash::Hasher,
+    {
+        // We manually implement Hash like this, since [u8; 5] is slower to hash
+        // than u64 for some/many hash functions
+        self.to_u64().hash(h)
+    }
+}
+
+/// Holds tags external vector and deduplicates tags.
+struct TagSerializer<'a> {
+    tags: flatdata::ExternalVector<'a, osmflat::Tag>,
+    tags_index: flatdata::ExternalVector<'a, osmflat::TagIndex>,
+    dedup: AHashMap<(I40, I40), I40>, // deduplication table: (key_idx, val_idx) -> pos
+}
+
+impl<'a> TagSerializer<'a> {
+    fn new(builder: &'a osmflat::OsmBuilder) -> io::Result<Self> {
+        Ok(Self {
+            tags: builder.start_tags()?,
+            tags_index: builder.start_tags_index()?,
+            dedup: AHashMap::new(),
+        })
+    }
+
+    fn serialize(&mut self, key_idx: u64, val_idx: u64) -> Result<(), Error> {
+        let idx = self.insert(key_idx, val_idx)?;
+        self.push_index(idx)
+    }
+
+    /// Inserts a tag without adding it to the tags index, and returns its
+    /// index.
+    fn insert(&mut self, key_idx: u64, val_idx: u64) -> Result<u64, Error> {
+        let idx = match self
+            .dedup
+            .entry((I40::from_u64(key_idx), I40::from_u64(val_idx)))
+        {
+            hash_map::Entry::Occupied(entry) => entry.get().to_u64(),
+            hash_map::Entry::Vacant(entry) => {
+                let idx = self.tags.len() as u64;
+                let tag = self.tags.grow()?;
+                tag.set_key_idx(key_idx);
+                tag.set_value_idx(val_idx);
+                entry.insert(I40::from_u64(idx));
+                idx
+            }
+        };
+        Ok(idx)
+    }
+
+    fn push_index(&mut self, idx: u64) -> Result<(), Error> {
+        self.tags_index.grow()?.set_value(idx);
+        Ok(())
+    }
+
+    fn next_index(&self) -> u64 {
+        self.tags_index.len() as u64
+    }
+
+    fn close(self) {
+        if let Err(e) = self.tags.close() {
+            panic!("failed to close tags: {}", e);
+        }
+        if let Err(e) = self.tags_index.close() {
+            panic!("failed to close tags index: {}", e);
+        }
+    }
+}
+
+/// Strings of a block, which are added to the stringtable when they are
+/// referenced for the first time.
+///
+/// Strings only referenced by tags which are not converted are not added.
+struct BlockStrings<'a> {
+    block: &'a osmpbf::StringTable,
+    stringtable: &'a mut StringTable,
+    selection: &'a Selection,
+    /// Index in the stringtable by index in the block
+    refs: Vec<Option<u64>>,
+    /// Whether tags with the key are converted, by index of the key in the
+    /// block
+    keys: Vec<Option<bool>>,
+}
+
+impl<'a> BlockStrings<'a> {
+    fn new(
+        block: &'a osmpbf::PrimitiveBlock,
+        stringtable: &'a mut StringTable,
+        selection: &'a Selection,
+    ) -> Self {
+        Self {
+            block: &block.stringtable,
+            stringtable,
+            selection,
+            refs: vec![None; block.stringtable.s.len()],
+            keys: vec![None; block.stringtable.s.len()],
+        }
+    }
+
+    /// Returns the index in the stringtable of the string `idx` of the block.
+    fn get(&mut self, idx: u32) -> Result<u64, Error> {
+        let idx = idx as usize;
+        if let Some(string_ref) = self.refs[idx] {
+            return Ok(string_ref);
+        }
+        let string_ref = self.stringtable.insert(str::from_utf8(&self.block.s[idx])?);
+        self.refs[idx] = Some(string_ref);
+        Ok(string_ref)
+    }
+
+    /// Returns the indexes in the stringtable of the key and value of a tag,
+    /// or `None` if the tag is not converted.
+    fn tag(&mut self, key: u32, val: u32) -> Result<Option<(u64, u64)>, Error> {
+        let (block, selection) = (self.block, self.selection);
+        let converted = *self.keys[key as usize]
+            .get_or_insert_with(|| selection.contains_tag(&block.s[key as usize]));
+        if !converted {
+            return Ok(None);
+        }
+        Ok(Some((self.get(key)?, self.get(val)?)))
+    }
+}
+
+/// Whether an entity is visible, i.e. not deleted in a history file.
+fn is_visible(info: &Option<osmpbf::Info>) -> bool {
+    info.as_ref().and_then(|info| info.visible).unwrap_or(true)
+}
+
+fn serialize_version(
+    versions: &mut flatdata::ExternalVector<osmflat::Version>,
+    version: i32,
+    timestamp: i64,
+    visible: bool,
+) -> io::Result<()> {
+    let entry = versions.grow()?;
+    entry.set_version(u32::try_from(version).unwrap_or(0));
+    entry.set_timestamp(timestamp);
+    entry.set_visible(visible);
+    Ok(())
+}
+
+fn serialize_info(
+    block: &osmpbf::PrimitiveBlock,
+    info: &Option<osmpbf::Info>,
+    versions: &mut flatdata::ExternalVector<osmflat::Version>,
+) -> io::Result<()> {
+    let info = info.unwrap_or_default();
+    let timestamp = info.timestamp() * i64::from(block.date_granularity()) / 1000;
+    serialize_version(
+        versions,
+        info.version(),
+        timestamp,
+        info.visible.unwrap_or(true),
+    )
+}
+
+/// Nodes buffered in memory to be sorted.
+#[derive(Default)]
+struct NodeBuffer {
+    /// `(lat, lon)` of each node
+    coords: Vec<(i32, i32)>,
+    ids: Vec<i64>,
+    /// Start of the tags of each node in `tags`
+    tags_start: Vec<usize>,
+    tags: Vec<u64>,
+}
+
+/// Writes nodes either directly to the archive, or buffers them to be written
+/// in Hilbert order.
+struct NodesWriter<'a> {
+    nodes: flatdata::ExternalVector<'a, osmflat::Node>,
+    ids: Option<flatdata::ExternalVector<'a, osmflat::Id>>,
+    coords: Option<flatdata::ExternalVector<'a, osmflat::NodeCoord>>,
+    buffer: Option<NodeBuffer>,
+}
+
+impl<'a> NodesWriter<'a> {
+    fn new(
+        builder: &'a osmflat::OsmBuilder,
+        ids: Option<flatdata::ExternalVector<'a, osmflat::Id>>,
+        coords: Option<flatdata::ExternalVector<'a, osmflat::NodeCoord>>,
+        order: NodeOrder,
+    ) -> io::Result<Self> {
+        Ok(Self {
+            nodes: builder.start_nodes()?,
+            ids,
+            coords,
+            buffer: (order == NodeOrder::Hilbert).then(NodeBuffer::default),
+        })
+    }
+
+    fn push_coord(&mut self, lat: i32, lon: i32) -> io::Result<()> {
+        if let Some(coords) = &mut self.coords {
+            let coord = coords.grow()?;
+            coord.set_lat(lat);
+            coord.set_lon(lon);
+        }
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        match &self.buffer {
+            Some(buffer) => buffer.coords.len(),
+            None => self.nodes.len(),
+        }
+    }
+
+    fn push(
+        &mut self,
+        id: i64,
+        (lat, lon): (i32, i32),
+        node_tags: impl Iterator<Item = (u64, u64)>,
+        tags: &mut TagSerializer,
+    ) -> Result<(), Error> {
+        match &mut self.buffer {
+            Some(buffer) => {
+                buffer.coords.push((lat, lon));
+                buffer.tags_start.push(buffer.tags.len());
+                for (key_idx, val_idx) in node_tags {
+                    buffer.tags.push(tags.insert(key_idx, val_idx)?);
+                }
+                buffer.ids.push(id);
+            }
+            None => {
+                let node = self.nodes.grow()?;
+                node.set_lat(lat);
+                node.set_lon(lon);
+                node.set_tag_first_idx(tags.next_index());
+                for (key_idx, val_idx) in node_tags {
+                    tags.serialize(key_idx, val_idx)?;
+                }
+                if let Some(ids) = &mut self.ids {
+                    ids.grow()?.set_signed_value(id);
+                }
+                self.push_coord(lat, lon)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the buffered nodes sorted and closes the nodes.
+    ///
+    /// Returns the new index of each buffered node, if the nodes were sorted.
+    fn finish(mut self, tags: &mut TagSerializer) -> Result<Option<Vec<u64>>, Error> {
+        let mut new_indices = None;
+        if let Some(buffer) = self.buffer.take() {
+            let mut order: Vec<(u64, usize)> = buffer
+                .coords
+                .iter()
+                .enumerate()
+                .map(|(idx, &(lat, lon))| (osmflat::hilbert_index(lon, lat), idx))
+                .collect();
+            order.sort_unstable();
+
+            let mut indices = vec![0; order.len()];
+            for (new_idx, &(_, idx)) in order.iter().enumerate() {
+                indices[idx] = new_idx as u64;
+                let (lat, lon) = buffer.coords[idx];
+                let node = self.nodes.grow()?;
+                node.set_lat(lat);
+                node.set_lon(lon);
+                node.set_tag_first_idx(tags.next_index());
+                let tags_end = buffer
+                    .tags_start
+                    .get(idx + 1)
+                    .map_or(buffer.tags.len(), |&end| end);
+                for &tag_idx in &buffer.tags[buffer.tags_start[idx]..tags_end] {
+                    tags.push_index(tag_idx)?;
+                }
+                if let Some(ids) = &mut self.ids {
+                    ids.grow()?.set_signed_value(buffer.ids[idx]);
+                }
+                self.push_coord(lat, lon)?;
+            }
+            new_indices = Some(indices);
+        }
+
+        // fill tag_first_idx of the sentry, since it contains the end of the tag range
+        // of the last node
+        self.nodes.grow()?.set_tag_first_idx(tags.next_index());
+        self.nodes.close()?;
+        if let Some(ids) = self.ids {
+            ids.close()?;
+        }
+        if let Some(coords) = self.coords {
+            coords.close()?;
+        }
+        Ok(new_indices)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn serialize_dense_nodes(
+    block: &osmpbf::PrimitiveBlock,
+    granularity: i32,
+    nodes: &mut NodesWriter,
+    node_versions: &mut Option<flatdata::ExternalVector<osmflat::Version>>,
+    nodes_id_to_idx: &mut ids::IdTableBuilder,
+    stringtable: &mut StringTable,
+    tags: &mut TagSerializer,
+    selection: &Selection,
+) -> Result<Stats, Error> {
+    let mut stats = Stats::default();
+    let mut strings = BlockStrings::new(block, stringtable, selection);
+    let mut node_tags_buf = Vec::new();
+    for group in block.primitivegroup.iter() {
+        let dense_nodes = group.dense.as_ref().unwrap();
+
+        let pbf_granularity = block.granularity.unwrap_or(100);
+        let lat_offset = block.lat_offset.unwrap_or(0);
+        let lon_offset = block.lon_offset.unwrap_or(0);
+        let mut lat = 0;
+        let mut lon = 0;
+
+        let mut tags_offset = 0;
+
+        let info = dense_nodes.denseinfo.clone().unwrap_or_default();
+        let mut timestamp = 0;
+
+        let mut id = 0;
+        for i in 0..dense_nodes.id.len() {
+            id += dense_nodes.id[i];
+            lat += dense_nodes.lat[i];
+            lon += dense_nodes.lon[i];
+            timestamp += info.timestamp.get(i).copied().unwrap_or(0);
+            let visible = info.visible.get(i).copied().unwrap_or(true);
+
+            // tags of the node are terminated by 0, if any node in the block has tags
+            let (tags_start, mut tags_end) = (tags_offset, tags_offset);
+            if tags_offset < dense_nodes.keys_vals.len() {
+                while dense_nodes.keys_vals[tags_end] != 0 {
+                    tags_end += 2;
+                }
+                tags_offset = tags_end + 1;
+            }
+            let node_tags = dense_nodes.keys_vals[tags_start..tags_end].chunks_exact(2);
+
+            if !selection.is_all() {
+                let (keys, vals): (Vec<_>, Vec<_>) = node_tags
+                    .clone()
+                    .map(|kv| (kv[0] as u32, kv[1] as u32))
+                    .unzip();
+                if !selection.contains_node(id, &filter::block_tags(block, &keys, &vals)) {
+                    continue;
+                }
+            }
+
+            match node_versions {
+                Some(versions) => {
+                    nodes_id_to_idx.insert_version(id, nodes.len() as u64);
+                    let version = info.version.get(i).copied().unwrap_or(0);
+                    let timestamp = timestamp * i64::from(block.date_granularity()) / 1000;
+                    serialize_version(versions, version, timestamp, visible)?;
+                }
+                // deleted nodes are only stored in history mode
+                None if !visible => continue,
+                None => {
+                    let index = nodes_id_to_idx.insert(id);
+                    assert_eq!(index as usize, nodes.len());
+                }
+            }
+
+            let coord = (
+                scale_coord(lat_offset + i64::from(pbf_granularity) * lat, granularity)?,
+                scale_coord(lon_offset + i64::from(pbf_granularity) * lon, granularity)?,
+            );
+            node_tags_buf.clear();
+            for kv in node_tags {
+                if let Some(tag) = strings.tag(kv[0] as u32, kv[1] as u32)? {
+                    node_tags_buf.push(tag);
+                }
+            }
+            nodes.push(id, coord, node_tags_buf.iter().copied(), tags)?;
+            stats.num_nodes += 1;
+        }
+        assert_eq!(tags_offset, dense_nodes.keys_vals.len());
+    }
+    Ok(stats)
+}
+
+fn resolve_ways(
+    block: &osmpbf::PrimitiveBlock,
+    nodes_id_to_idx: &ids::IdTable,
+    selection: &Selection,
+    report_unresolved: bool,
+) -> (Vec<Option<u64>>, Stats) {
+    let mut result = Vec::new();
+    let mut stats = Stats::default();
+    for group in &block.primitivegroup {
+        for pbf_way in &group.ways {
+            if !is_way_selected(block, pbf_way, selection) {
+                continue;
+            }
+            let mut node_ref = 0;
+            for delta in &pbf_way.refs {
+                node_ref += delta;
+                let idx = nodes_id_to_idx.get(node_ref);
+                stats.num_unresolved_node_ids += idx.is_none() as usize;
+                if idx.is_none() && report_unresolved {
+                    stats.unresolved_refs.push(UnresolvedRef {
+                        parent_type: EntityType::Way,
+                        parent_id: pbf_way.id,
+                        missing_type: EntityType::Node,
+                        missing_id: node_ref,
+                    });
+                }
+
+                result.push(idx);
+            }
+        }
+    }
+    (result, stats)
+}
+
+fn is_way_selected(
+    block: &osmpbf::PrimitiveBlock,
+    way: &osmpbf::Way,
+    selection: &Selection,
+) -> bool {
+    selection.is_all()
+        || selection.contains_way(way.id, &filter::block_tags(block, &way.keys, &way.vals))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn serialize_ways(
+    block: &osmpbf::PrimitiveBlock,
+    nodes_id_to_idx: &[Option<u64>],
+    ways: &mut flatdata::ExternalVector<osmflat::Way>,
+    way_ids: &mut Option<flatdata::ExternalVector<osmflat::Id>>,
+    way_versions: &mut Option<flatdata::ExternalVector<osmflat::Version>>,
+    ways_id_to_idx: &mut ids::IdTableBuilder,
+    stringtable: &mut StringTable,
+    tags: &mut TagSerializer,
+    nodes_index: &mut flatdata::ExternalVector<osmflat::NodeIndex>,
+    selection: &Selection,
+) -> Result<Stats, Error> {
+    let mut stats = Stats::default();
+    let mut strings = BlockStrings::new(block, stringtable, selection);
+    let mut nodes_idx = nodes_id_to_idx.iter().cloned();
+    for group in &block.primitivegroup {
+        for pbf_way in &group.ways {
+            if !is_way_selected(block, pbf_way, selection) {
+                continue;
+            }
+
+            match way_versions {
+                Some(versions) => {
+                    ways_id_to_idx.insert_version(pbf_way.id, ways.len() as u64);
+                    serialize_info(block, &pbf_way.info, versions)?;
+                }
+                None if !is_visible(&pbf_way.info) => {
+                    // deleted ways are only stored in history mode
+                    nodes_idx.by_ref().take(pbf_way.refs.len()).for_each(drop);
+                    continue;
+                }
+                None => {
+                    let index = ways_id_to_idx.insert(pbf_way.id);
+                    assert_eq!(index as usize, ways.len());
+                }
+            }
+
+            let way = ways.grow()?;
+            if let Some(ids) = way_ids {
+                ids.grow()?.set_signed_value(pbf_way.id);
+            }
+
+            debug_assert_eq!(pbf_way.keys.len(), pbf_way.vals.len(), "invalid input data");
+            way.set_tag_first_idx(tags.next_index());
+
+            for (&key, &val) in pbf_way.keys.iter().zip(&pbf_way.vals) {
+                if let Some((key_idx, val_idx)) = strings.tag(key, val)? {
+                    tags.serialize(key_idx, val_idx)?;
+                }
+            }
+
+            way.set_ref_first_idx(nodes_index.len() as u64);
+            for _ in &pbf_way.refs {
+                nodes_index.grow()?.set_value(nodes_idx.next().unwrap());
+            }
+            stats.num_ways += 1;
+        }
+    }
+    Ok(stats)
+}
+
+fn build_relations_index<B, R>(
+    blocks: Vec<B>,
+    read: &R,
+    queue_depth: NonZeroUsize,
+    history: bool,
+    selection: &Selection,
+) -> Result<ids::IdTable, Error>
+where
+    B: Send,
+    R: Fn(B) -> io::Result<osmpbf::PrimitiveBlock> + Sync,
+{
+    let mut result = ids::IdTableBuilder::new();
+    let mut num_relations = 0;
+    parallel::parallel_process(
+        blocks.into_iter(),
+        queue_depth,
+        read,
+        |block: Result<osmpbf::PrimitiveBlock, _>| -> Result<(), Error> {
+            let block = block?;
+            for group in &block.primitivegroup {
+                for relation in &group.relations {
+                    if !selection.is_all()
+                        && !selection.contains_relation(&filter::block_tags(
+                            &block,
+                            &relation.keys,
+                            &relation.vals,
+                        ))
+                    {
+                        continue;
+                    }
+                    if history {
+                        result.insert_version(relation.id, num_relations);
+                    } else if is_visible(&relation.info) {
+                        result.insert(relation.id);
+                    } else {
+                        continue;
+                    }
+                    num_relations += 1;
+                }
+            }
+            Ok(())
+        },
+    )?;
+
+    Ok(result.build())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn serialize_relations(
+    block: &osmpbf::PrimitiveBlock,
+    nodes_id_to_idx: &ids::IdTable,
+    ways_id_to_idx: &ids::IdTable,
+    relations_id_to_idx: &ids::IdTable,
+    stringtable: &mut StringTable,
+    relations: &mut flatdata::ExternalVector<osmflat::Relation>,
+    relation_ids: &mut Option<flatdata::ExternalVector<osmflat::Id>>,
+    relation_versions: &mut Option<flatdata::ExternalVector<osmflat::Version>>,
+    relation_members: &mut flatdata::MultiVector<osmflat::RelationMembers>,
+    tags: &mut TagSerializer,
+    selection: &Selection,
+    report_unresolved: bool,
+) -> Result<Stats, Error> {
+    let mut stats = Stats::default();
+    let mut strings = BlockStrings::new(block, stringtable, selection);
+    for group in &block.primitivegroup {
+        for pbf_relation in &group.relations {
+            if !selection.is_all()
+                && !selection.contains_relation(&filter::block_tags(
+                    block,
+                    &pbf_relation.keys,
+                    &pbf_relation.vals,
+                ))
+            {
+                continue;
+            }
+
+            match relation_versions {
+                Some(versions) => serialize_info(block, &pbf_relation.info, versions)?,
+                // deleted relations are only stored in history mode
+                None if !is_visible(&pbf_relation.info) => continue,
+                None => (),
+            }
+
+            let relation = relations.grow()?;
+            if let Some(ids) = relation_ids {
+                ids.grow()?.set_signed_value(pbf_relation.id);
+            }
+
+            debug_assert_eq!(
+                pbf_relation.keys.len(),
+                pbf_relation.vals.len(),
+                "invalid input data"
+            );
+            relation.set_tag_first_idx(tags.next_index());
+            for (&key, &val) in pbf_relation.keys.iter().zip(&pbf_relation.vals) {
+                if let Some((key_idx, val_idx)) = strings.tag(key, val)? {
+                    tags.serialize(key_idx, val_idx)?;
+                }
+            }
+
+            debug_assert!(
+                pbf_relation.roles_sid.len() == pbf_relation.memids.len()
+                    && pbf_relation.memids.len() == pbf_relation.types.len(),
+                "invalid input data"
+            );
+
+            let mut memid = 0;
+            let mut members = relation_members.grow()?;
+            for i in 0..pbf_relation.roles_sid.len() {
+                memid += pbf_relation.memids[i];
+
+                let member_type = osmpbf::relation::MemberType::try_from(pbf_relation.types[i]);
+                debug_assert!(member_type.is_ok());
+
+                match member_type.unwrap() {
+                    osmpbf::relation::MemberType::Node => {
+                        let idx = nodes_id_to_idx.get(memid);
+                        stats.num_unresolved_node_ids += idx.is_none() as usize;
+                        if idx.is_none() && report_unresolved {
+                            stats.unresolved_refs.push(UnresolvedRef {
+                                parent_type: EntityType::Relation,
+                                parent_id: pbf_relation.id,
+                                missing_type: EntityType::Node,
+                                missing_id: memid,
+                            });
+                        }
+
+                        let member = members.add_node_member();
+                        member.set_node_idx(idx);
+                        member.set_role_idx(strings.get(pbf_relation.roles_sid[i] as u32)?);
+                    }
+                    osmpbf::relation::MemberType::Way => {
+                        let idx = ways_id_to_idx.get(memid);
+                        stats.num_unresolved_way_ids += idx.is_none() as usize;
+                        if idx.is_none() && report_unresolved {
+                            stats.unresolved_refs.push(UnresolvedRef {
+                                parent_type: EntityType::Relation,
+                                parent_id: pbf_relation.id,
+                                missing_type: EntityType::Way,
+                                missing_id: memid,
+                            });
+                        }
+
+                        let member = members.add_way_member();
+                        member.set_way_idx(idx);
+                        member.set_role_idx(strings.get(pbf_relation.roles_sid[i] as u32)?);
+                    }
+                    osmpbf::relation::MemberType::Relation => {
+                        let idx = relations_id_to_idx.get(memid);
+                        stats.num_unresolved_rel_ids += idx.is_none() as usize;
+                        if idx.is_none() && report_unresolved {
+                            stats.unresolved_refs.push(UnresolvedRef {
+                                parent_type: EntityType::Relation,
+                                parent_id: pbf_relation.id,
+                                missing_type: EntityType::Relation,
+                                missing_id: memid,
+                            });
+                        }
+
+                        let member = members.add_relation_member();
+                        member.set_relation_idx(idx);
+                        member.set_role_idx(strings.get(pbf_relation.roles_sid[i] as u32)?);
+                    }
+                }
+            }
+            stats.num_relations += 1;
+        }
+    }
+    Ok(stats)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn serialize_dense_node_blocks<B, R>(
+    builder: &osmflat::OsmBuilder,
+    granularity: i32,
+    node_ids: Option<flatdata::ExternalVector<osmflat::Id>>,
+    node_coords: Option<flatdata::ExternalVector<osmflat::NodeCoord>>,
+    mut node_versions: Option<flatdata::ExternalVector<osmflat::Version>>,
+    order: NodeOrder,
+    blocks: Vec<B>,
+    read: &R,
+    queue_depth: NonZeroUsize,
+    tags: &mut TagSerializer,
+    stringtable: &mut StringTable,
+    stats: &mut Stats,
+    selection: &Selection,
+    progress: &dyn Progress,
+) -> Result<ids::IdTable, Error>
+where
+    B: Send,
+    R: Fn(B) -> io::Result<osmpbf::PrimitiveBlock> + Sync,
+{
+    let mut nodes_id_to_idx = ids::IdTableBuilder::new();
+    let mut nodes = NodesWriter::new(builder, node_ids, node_coords, order)?;
+    progress.stage_started(Stage::Nodes, Some(blocks.len()));
+    parallel::parallel_process(
+        blocks.into_iter(),
+        queue_depth,
+        read,
+        |block| -> Result<osmpbf::PrimitiveBlock, Error> {
+            let block = block?;
+            *stats += serialize_dense_nodes(
+                &block,
+                granularity,
+                &mut nodes,
+                &mut node_versions,
+                &mut nodes_id_to_idx,
+                stringtable,
+                tags,
+                selection,
+            )?;
+
+            progress.block_processed(Stage::Nodes);
+            Ok(block)
+        },
+    )?;
+
+    let new_indices = nodes.finish(tags)?;
+    if let Some(versions) = node_versions {
+        versions.close()?;
+    }
+    let mut nodes_id_to_idx = nodes_id_to_idx.build();
+    if let Some(indices) = new_indices {
+        nodes_id_to_idx.set_indices(indices);
+    }
+    progress.stage_finished(Stage::Nodes);
+    Ok(nodes_id_to_idx)
+}
+
+type PrimitiveBlockWithIds = (osmpbf::PrimitiveBlock, (Vec<Option<u64>>, Stats));
+
+#[allow(clippy::too_many_arguments)]
+fn serialize_way_blocks<B, R>(
+    builder: &osmflat::OsmBuilder,
+    mut way_ids: Option<flatdata::ExternalVector<osmflat::Id>>,
+    mut way_versions: Option<flatdata::ExternalVector<osmflat::Version>>,
+    blocks: Vec<B>,
+    read: &R,
+    queue_depth: NonZeroUsize,
+    nodes_id_to_idx: &ids::IdTable,
+    tags: &mut TagSerializer,
+    stringtable: &mut StringTable,
+    stats: &mut Stats,
+    selection: &Selection,
+    report_unresolved: bool,
+    progress: &dyn Progress,
+) -> Result<ids::IdTable, Error>
+where
+    B: Send,
+    R: Fn(B) -> io::Result<osmpbf::PrimitiveBlock> + Sync,
+{
+    let mut ways_id_to_idx = ids::IdTableBuilder::new();
+    let mut ways = builder.start_ways()?;
+    progress.stage_started(Stage::Ways, Some(blocks.len()));
+    let mut nodes_index = builder.start_nodes_index()?;
+    parallel::parallel_process(
+        blocks.into_iter(),
+        queue_depth,
+        |idx| {
+            let block = read(idx)?;
+            let ids = resolve_ways(&block, nodes_id_to_idx, selection, report_unresolved);
+            Ok((block, ids))
+        },
+        |block: io::Result<PrimitiveBlockWithIds>| -> Result<osmpbf::PrimitiveBlock, Error> {
+            let (block, (ids, stats_resolve)) = block?;
+            *stats += stats_resolve;
+            *stats += serialize_ways(
+                &block,
+                &ids,
+                &mut ways,
+                &mut way_ids,
+                &mut way_versions,
+                &mut ways_id_to_idx,
+                stringtable,
+                tags,
+                &mut nodes_index,
+                selection,
+            )?;
+            progress.block_processed(Stage::Ways);
+
+            Ok(block)
+        },
+    )?;
+
+    {
+        let sentinel = ways.grow()?;
+        sentinel.set_tag_first_idx(tags.next_index());
+        sentinel.set_ref_first_idx(nodes_index.len() as u64);
+    }
+    ways.close()?;
+    if let Some(ids) = way_ids {
+        ids.close()?;
+    }
+    if let Some(versions) = way_versions {
+        versions.close()?;
+    }
+    nodes_index.close()?;
+
+    let ways_id_to_idx = ways_id_to_idx.build();
+    progress.stage_finished(Stage::Ways);
+    Ok(ways_id_to_idx)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn serialize_relation_blocks<B, R>(
+    builder: &osmflat::OsmBuilder,
+    mut relation_ids: Option<flatdata::ExternalVector<osmflat::Id>>,
+    mut relation_versions: Option<flatdata::ExternalVector<osmflat::Version>>,
+    blocks: Vec<B>,
+    read: &R,
+    queue_depth: NonZeroUsize,
+    nodes_id_to_idx: &ids::IdTable,
+    ways_id_to_idx: &ids::IdTable,
+    relations_id_to_idx: &ids::IdTable,
+    tags: &mut TagSerializer,
+    stringtable: &mut StringTable,
+    stats: &mut Stats,
+    selection: &Selection,
+    report_unresolved: bool,
+    progress: &dyn Progress,
+) -> Result<(), Error>
+where
+    B: Clone + Send,
+    R: Fn(B) -> io::Result<osmpbf::PrimitiveBlock> + Sync,
+{
+    let mut relations = builder.start_relations()?;
+    let mut relation_members = builder.start_relation_members()?;
+
+    progress.stage_started(Stage::Relations, Some(blocks.len()));
+    parallel::parallel_process(
+        blocks.into_iter(),
+        queue_depth,
+        read,
+        |block| -> Result<osmpbf::PrimitiveBlock, Error> {
+            let block = block?;
+            *stats += serialize_relations(
+                &block,
+                nodes_id_to_idx,
+                ways_id_to_idx,
+                relations_id_to_idx,
+                stringtable,
+                &mut relations,
+                &mut relation_ids,
+                &mut relation_versions,
+                &mut relation_members,
+                tags,
+                selection,
+                report_unresolved,
+            )?;
+            progress.block_processed(Stage::Relations);
+            Ok(block)
+        },
+    )?;
+
+    {
+        let sentinel = relations.grow()?;
+        sentinel.set_tag_first_idx(tags.next_index());
+    }
+
+    relations.close()?;
+    if let Some(ids) = relation_ids {
+        ids.close()?;
+    }
+    if let Some(versions) = relation_versions {
+        versions.close()?;
+    }
+    relation_members.close()?;
+
+    progress.stage_finished(Stage::Relations);
+
+    Ok(())
+}
+
+/// Writes the changesets of a block.
+///
+/// The tags of the changesets are added to the tags of the archive, but
+/// indexed in the `tags_index` of the changesets subarchive.
+#[allow(clippy::too_many_arguments)]
+fn serialize_changesets(
+    block: &osmpbf::PrimitiveBlock,
+    granularity: i32,
+    stringtable: &mut StringTable,
+    tags: &mut TagSerializer,
+    changesets: &mut flatdata::ExternalVector<osmflat::Changeset>,
+    bboxes: &mut flatdata::ExternalVector<osmflat::BoundingBox>,
+    tag_ranges: &mut flatdata::ExternalVector<osmflat::ChangesetTags>,
+    tags_index: &mut flatdata::ExternalVector<osmflat::TagIndex>,
+    selection: &Selection,
+) -> Result<(), Error> {
+    let date_granularity = i64::from(block.date_granularity());
+    let mut strings = BlockStrings::new(block, stringtable, selection);
+    for pbf_changeset in block.primitivegroup.iter().flat_map(|g| &g.changesets) {
+        let info = pbf_changeset.info.unwrap_or_default();
+        let created_at = pbf_changeset.created_at.unwrap_or(info.timestamp());
+        let closed_at = match pbf_changeset.closetime_delta {
+            Some(delta) if !pbf_changeset.open() => (created_at + delta) * date_granularity / 1000,
+            _ => 0,
+        };
+        let changeset = changesets.grow()?;
+        changeset.set_id(pbf_changeset.id as u64);
+        changeset.set_created_at(created_at * date_granularity / 1000);
+        changeset.set_closed_at(closed_at);
+        changeset.set_user_idx(strings.get(info.user_sid())?);
+
+        debug_assert_eq!(
+            pbf_changeset.keys.len(),
+            pbf_changeset.vals.len(),
+            "invalid input data"
+        );
+        tag_ranges
+            .grow()?
+            .set_tag_first_idx(tags_index.len() as u64);
+        for (&key, &val) in pbf_changeset.keys.iter().zip(&pbf_changeset.vals) {
+            if let Some((key_idx, val_idx)) = strings.tag(key, val)? {
+                let idx = tags.insert(key_idx, val_idx)?;
+                tags_index.grow()?.set_value(idx);
+            }
+        }
+
+        let bbox = match &pbf_changeset.bbox {
+            Some(bbox) => Some(osmflat::BBox {
+                left: scale_coord(bbox.left, granularity)?,
+                bottom: scale_coord(bbox.bottom, granularity)?,
+                right: scale_coord(bbox.right, granularity)?,
+                top: scale_coord(bbox.top, granularity)?,
+            }),
+            None => None,
+        };
+        *bboxes.grow()? = bbox.into();
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn serialize_changeset_blocks<B, R>(
+    builder: &osmflat::OsmBuilder,
+    granularity: i32,
+    blocks: Vec<B>,
+    read: &R,
+    queue_depth: NonZeroUsize,
+    stringtable: &mut StringTable,
+    tags: &mut TagSerializer,
+    selection: &Selection,
+    progress: &dyn Progress,
+) -> Result<(), Error>
+where
+    B: Clone + Send,
+    R: Fn(B) -> io::Result<osmpbf::PrimitiveBlock> + Sync,
+{
+    let changesets_archive = builder.changesets()?;
+    let mut changesets = changesets_archive.start_changesets()?;
+    let mut bboxes = changesets_archive.start_bboxes()?;
+    let mut tag_ranges = changesets_archive.start_tag_ranges()?;
+    let mut tags_index = changesets_archive.start_tags_index()?;
+
+    progress.stage_started(Stage::Changesets, Some(blocks.len()));
+    parallel::parallel_process(
+        blocks.into_iter(),
+        queue_depth,
+        read,
+        |block| -> Result<osmpbf::PrimitiveBlock, Error> {
+            let block = block?;
+            serialize_changesets(
+                &block,
+                granularity,
+                stringtable,
+                tags,
+                &mut changesets,
+                &mut bboxes,
+                &mut tag_ranges,
+                &mut tags_index,
+                selection,
+            )?;
+            progress.block_processed(Stage::Changesets);
+            Ok(block)
+        },
+    )?;
+
+    // sentinel
+    tag_ranges
+        .grow()?
+        .set_tag_first_idx(tags_index.len() as u64);
+    changesets.close()?;
+    bboxes.close()?;
+    tag_ranges.close()?;
+    tags_index.close()?;
+    progress.stage_finished(Stage::Changesets);
+    Ok(())
+}
+
+/// Selects the entities matching the filter of `options`, and the ways and nodes
+/// referenced by them.
+///
+/// If `options.drop_untagged_nodes` is set, the nodes which lose all their tags
+/// and are not referenced are removed from the selection.
+fn select<B, R>(
+    options: &Options,
+    nodes: Vec<B>,
+    ways: Vec<B>,
+    relations: Vec<B>,
+    read: &R,
+    queue_depth: NonZeroUsize,
+    progress: &dyn Progress,
+) -> Result<Selection, Error>
+where
+    B: Clone + Send,
+    R: Fn(B) -> io::Result<osmpbf::PrimitiveBlock> + Sync,
+{
+    let mut selection = Selection::new(
+        options.filter.clone(),
+        TagKeys::new(options.keep_tags.clone(), options.drop_tags.clone()),
+    );
+    if options.filter.is_empty() && !options.drop_untagged_nodes {
+        return Ok(selection);
+    }
+
+    let mut num_blocks = 0;
+    if !options.filter.is_empty() {
+        num_blocks += relations.len() + ways.len();
+    }
+    if options.drop_untagged_nodes {
+        num_blocks += nodes.len() + ways.len() + relations.len();
+    }
+    progress.stage_started(Stage::Select, Some(num_blocks));
+    if !options.filter.is_empty() {
+        // Relations first, since they can select additional ways.
+        parallel::parallel_process(
+            relations.clone().into_iter(),
+            queue_depth,
+            read,
+            |block: io::Result<osmpbf::PrimitiveBlock>| -> Result<(), Error> {
+                let block = block?;
+                for group in &block.primitivegroup {
+                    for relation in &group.relations {
+                        let tags = filter::block_tags(&block, &relation.keys, &relation.vals);
+                        if selection.contains_relation(&tags) {
+                            selection.add_relation(relation);
+                        }
+                    }
+                }
+                progress.block_processed(Stage::Select);
+                Ok(())
+            },
+        )?;
+        parallel::parallel_process(
+            ways.clone().into_iter(),
+            queue_depth,
+            read,
+            |block: io::Result<osmpbf::PrimitiveBlock>| -> Result<(), Error> {
+                let block = block?;
+                for group in &block.primitivegroup {
+                    for way in &group.ways {
+                        let tags = filter::block_tags(&block, &way.keys, &way.vals);
+                        if selection.contains_way(way.id, &tags) {
+                            selection.add_way(way);
+                        }
+                    }
+                }
+                progress.block_processed(Stage::Select);
+                Ok(())
+            },
+        )?;
+    }
+    if options.drop_untagged_nodes {
+        let ids = untagged_nodes(
+            &selection,
+            nodes,
+            ways,
+            relations,
+            read,
+            queue_depth,
+            progress,
+        )?;
+        selection.remove_nodes(ids);
+    }
+    progress.stage_finished(Stage::Select);
+
+    Ok(selection)
+}
+
+/// Returns the ids of the nodes which have tags, none of which are converted,
+/// and which are neither referenced by ways nor by relations.
+fn untagged_nodes<B, R>(
+    selection: &Selection,
+    nodes: Vec<B>,
+    ways: Vec<B>,
+    relations: Vec<B>,
+    read: &R,
+    queue_depth: NonZeroUsize,
+    progress: &dyn Progress,
+) -> Result<Vec<i64>, Error>
+where
+    B: Send,
+    R: Fn(B) -> io::Result<osmpbf::PrimitiveBlock> + Sync,
+{
+    let mut candidates = Vec::new();
+    parallel::parallel_process(
+        nodes.into_iter(),
+        queue_depth,
+        read,
+        |block: io::Result<osmpbf::PrimitiveBlock>| -> Result<(), Error> {
+            let block = block?;
+            let mut converted_keys: Vec<Option<bool>> = vec![None; block.stringtable.s.len()];
+            let mut is_converted = |key: i32| {
+                *converted_keys[key as usize].get_or_insert_with(|| {
+                    selection.contains_tag(&block.stringtable.s[key as usize])
+                })
+            };
+            for dense_nodes in block.primitivegroup.iter().filter_map(|g| g.dense.as_ref()) {
+                let mut id = 0;
+                let mut keys_vals = dense_nodes.keys_vals.split(|&x| x == 0);
+                for delta in &dense_nodes.id {
+                    id += delta;
+                    let tags = keys_vals.next().unwrap_or_default();
+                    if !tags.is_empty() && !tags.iter().step_by(2).any(|&key| is_converted(key)) {
+                        candidates.push(id);
+                    }
+                }
+            }
+            progress.block_processed(Stage::Select);
+            Ok(())
+        },
+    )?;
+
+    // negative ids, e.g. from JOSM, are in any order
+    candidates.sort_unstable();
+    candidates.dedup();
+    let mut referenced = vec![false; candidates.len()];
+    let mut reference = |id: i64| {
+        if let Ok(pos) = candidates.binary_search(&id) {
+            referenced[pos] = true;
+        }
+    };
+    parallel::parallel_process(
+        ways.into_iter(),
+        queue_depth,
+        read,
+        |block: io::Result<osmpbf::PrimitiveBlock>| -> Result<(), Error> {
+            let block = block?;
+            for way in block.primitivegroup.iter().flat_map(|g| &g.ways) {
+                let mut node_ref = 0;
+                for delta in &way.refs {
+                    node_ref += delta;
+                    reference(node_ref);
+                }
+            }
+            progress.block_processed(Stage::Select);
+            Ok(())
+        },
+    )?;
+    parallel::parallel_process(
+        relations.into_iter(),
+        queue_depth,
+        read,
+        |block: io::Result<osmpbf::PrimitiveBlock>| -> Result<(), Error> {
+            let block = block?;
+            for relation in block.primitivegroup.iter().flat_map(|g| &g.relations) {
+                let mut memid = 0;
+                for (delta, &member_type) in relation.memids.iter().zip(&relation.types) {
+                    memid += delta;
+                    if member_type == osmpbf::relation::MemberType::Node as i32 {
+                        reference(memid);
+                    }
+                }
+            }
+            progress.block_processed(Stage::Select);
+            Ok(())
+        },
+    )?;
+
+    Ok(candidates
+        .into_iter()
+        .zip(referenced)
+        .filter_map(|(id, referenced)| (!referenced).then_some(id))
+        .collect())
+}
+
+/// Counts the occurrences of the strings of a block in the converted entities,
+/// by index in the block.
+///
+/// The strings are the keys and values of the converted tags, and the roles of
+/// relation members.
+fn count_block_strings(
+    block: &osmpbf::PrimitiveBlock,
+    history: bool,
+    selection: &Selection,
+) -> Vec<u64> {
+    let mut counts = vec![0; block.stringtable.s.len()];
+    let mut converted_keys: Vec<Option<bool>> = vec![None; block.stringtable.s.len()];
+    let mut count_tags = |counts: &mut [u64], keys: &[u32], vals: &[u32]| {
+        for (&key, &val) in keys.iter().zip(vals) {
+            let converted = *converted_keys[key as usize]
+                .get_or_insert_with(|| selection.contains_tag(&block.stringtable.s[key as usize]));
+            if converted {
+                counts[key as usize] += 1;
+                counts[val as usize] += 1;
+            }
+        }
+    };
+    for group in &block.primitivegroup {
+        if let Some(dense_nodes) = &group.dense {
+            let visible = dense_nodes
+                .denseinfo
+                .as_ref()
+                .map_or(&[][..], |info| &info.visible[..]);
+            let mut keys_vals = dense_nodes.keys_vals.split(|&x| x == 0);
+            let mut id = 0;
+            for (i, delta) in dense_nodes.id.iter().enumerate() {
+                id += delta;
+                let (keys, vals): (Vec<_>, Vec<_>) = keys_vals
+                    .next()
+                    .unwrap_or_default()
+                    .chunks_exact(2)
+                    .map(|kv| (kv[0] as u32, kv[1] as u32))
+                    .unzip();
+                if !history && !visible.get(i).copied().unwrap_or(true) {
+                    continue;
+                }
+                if !selection.is_all()
+                    && !selection.contains_node(id, &filter::block_tags(block, &keys, &vals))
+                {
+                    continue;
+                }
+                count_tags(&mut counts, &keys, &vals);
+            }
+        }
+        for way in &group.ways {
+            if (history || is_visible(&way.info)) && is_way_selected(block, way, selection) {
+                count_tags(&mut counts, &way.keys, &way.vals);
+            }
+        }
+        for relation in &group.relations {
+            if !history && !is_visible(&relation.info) {
+                continue;
+            }
+            if !selection.is_all()
+                && !selection.contains_relation(&filter::block_tags(
+                    block,
+                    &relation.keys,
+                    &relation.vals,
+                ))
+            {
+                continue;
+            }
+            count_tags(&mut counts, &relation.keys, &relation.vals);
+            for &role in &relation.roles_sid {
+                counts[role as usize] += 1;
+            }
+        }
+    }
+    counts
+}
+
+/// Sorts strings by descending number of occurrences; strings occurring equally
+/// often are sorted lexicographically.
+fn sort_by_frequency(counts: AHashMap<Vec<u8>, u64>) -> Vec<Vec<u8>> {
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_unstable_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+    counts.into_iter().map(|(s, _)| s).collect()
+}
+
+/// Inserts the strings of the converted entities into the stringtable, the most
+/// frequent ones first.
+///
+/// Frequent strings get small indexes this way, which makes the indexes in the
+/// tags compress better. The entities are serialized afterwards as usual, and
+/// find their strings already in the stringtable.
+fn insert_strings_by_frequency<B, R>(
+    stringtable: &mut StringTable,
+    blocks: Vec<B>,
+    read: &R,
+    queue_depth: NonZeroUsize,
+    history: bool,
+    selection: &Selection,
+    progress: &dyn Progress,
+) -> Result<(), Error>
+where
+    B: Send,
+    R: Fn(B) -> io::Result<osmpbf::PrimitiveBlock> + Sync,
+{
+    progress.stage_started(Stage::CountStrings, Some(blocks.len()));
+    let mut counts: AHashMap<Vec<u8>, u64> = AHashMap::new();
+    parallel::parallel_process(
+        blocks.into_iter(),
+        queue_depth,
+        |b| {
+            read(b).map(|block| {
+                let block_counts = count_block_strings(&block, history, selection);
+                (block, block_counts)
+            })
+        },
+        |result: io::Result<(osmpbf::PrimitiveBlock, Vec<u64>)>| -> Result<(), Error> {
+            let (block, block_counts) = result?;
+            for (s, count) in block.stringtable.s.iter().zip(block_counts) {
+                if count == 0 {
+                    continue;
+                }
+                match counts.get_mut(s) {
+                    Some(total) => *total += count,
+                    None => {
+                        counts.insert(s.clone(), count);
+                    }
+                }
+            }
+            progress.block_processed(Stage::CountStrings);
+            Ok(())
+        },
+    )?;
+    for s in sort_by_frequency(counts) {
+        stringtable.insert(str::from_utf8(&s)?);
+    }
+    progress.stage_finished(Stage::CountStrings);
+    Ok(())
+}
+
+fn gcd(a: i32, b: i32) -> i32 {
+    let (mut x, mut y) = (a.min(b), a.max(b));
+    while x > 1 {
+        y %= x;
+        std::mem::swap(&mut x, &mut y);
+    }
+    y
+}
+
+/// Input blocks grouped by type, and the function reading a block.
+pub(crate) struct Input<B, R> {
+    pub header: osmpbf::HeaderBlock,
+    pub granularity: i32,
+    pub dense_nodes: Vec<B>,
+    pub ways: Vec<B>,
+    pub relations: Vec<B>,
+    pub changesets: Vec<B>,
+    pub read: R,
+}
+
+pub(crate) fn pbf_input(
+    data: &[u8],
+) -> Result<
+    Input<BlockIndex, impl Fn(BlockIndex) -> io::Result<osmpbf::PrimitiveBlock> + Sync + '_>,
+    Error,
+> {
+    let block_index = osmpbf::build_block_index(data)?;
+    blocks_input(block_index, move |idx| {
+        Ok(Cow::Borrowed(
+            &data[idx.blob_start..idx.blob_start + idx.blob_len],
+        ))
+    })
+}
+
+/// Input of the pbf blocks in `block_index`, whose encoded blobs are read by
+/// `read_blob`.
+fn blocks_input<'a>(
+    block_index: Vec<BlockIndex>,
+    read_blob: impl Fn(&BlockIndex) -> io::Result<Cow<'a, [u8]>> + Sync + 'a,
+) -> Result<
+    Input<BlockIndex, impl Fn(BlockIndex) -> io::Result<osmpbf::PrimitiveBlock> + Sync + 'a>,
+    Error,
+> {
+    let mut greatest_common_granularity = 1000000000;
+    for block in &block_index {
+        if block.block_type == BlockType::DenseNodes {
+            // only DenseNodes have coordinate we need to scale
+            if let Some(block_granularity) = block.granularity {
+                greatest_common_granularity =
+                    gcd(greatest_common_granularity, block_granularity as i32);
+            }
+        }
+    }
+
+    let groups = block_index.into_iter().chunk_by(|b| b.block_type);
+    let mut pbf_header = Vec::new();
+    let mut pbf_dense_nodes = Vec::new();
+    let mut pbf_ways = Vec::new();
+    let mut pbf_relations = Vec::new();
+    let mut pbf_changesets = Vec::new();
+    for (block_type, blocks) in &groups {
+        match block_type {
+            BlockType::Header => pbf_header = blocks.collect(),
+            BlockType::Nodes => panic!("Found nodes block, only dense nodes are supported now"),
+            BlockType::DenseNodes => pbf_dense_nodes = blocks.collect(),
+            BlockType::Ways => pbf_ways = blocks.collect(),
+            BlockType::Relations => pbf_relations = blocks.collect(),
+            BlockType::Changesets => pbf_changesets = blocks.collect(),
+        }
+    }
+
+    if pbf_header.len() != 1 {
+        return Err(format!(
+            "Require exactly one header block, but found {}",
+            pbf_header.len()
+        )
+        .into());
+    }
+    let header = osmpbf::decode_blob(&read_blob(&pbf_header[0])?)?;
+
+    Ok(Input {
+        header,
+        granularity: greatest_common_granularity,
+        dense_nodes: pbf_dense_nodes,
+        ways: pbf_ways,
+        relations: pbf_relations,
+        changesets: pbf_changesets,
+        read: move |idx| osmpbf::decode_blob(&read_blob(&idx)?),
+    })
+}
+
+pub(crate) fn opl_input(
+    data: &[u8],
+) -> Result<
+    Input<OplBlock, impl Fn(OplBlock) -> io::Result<osmpbf::PrimitiveBlock> + Sync + '_>,
+    Error,
+> {
+    let block_index = opl::build_block_index(data)?;
+    let of_type = |block_type| {
+        block_index
+            .iter()
+            .filter(|b| b.block_type == block_type)
+            .cloned()
+            .collect()
+    };
+    Ok(Input {
+        header: osmpbf::HeaderBlock::default(),
+        granularity: opl::GRANULARITY,
+        dense_nodes: of_type(BlockType::DenseNodes),
+        ways: of_type(BlockType::Ways),
+        relations: of_type(BlockType::Relations),
+        changesets: of_type(BlockType::Changesets),
+        read: move |idx| opl::read_block(data, &idx),
+    })
+}
+
+/// Converts the OSM data in `input` to an osmflat archive at `output`.
+///
+/// The input is read as OPL if its extension is `opl`, and as PBF otherwise.
+/// If the input is an `http://` or `https://` URL, it is downloaded while it is
+/// converted, or first if the server does not support range requests.
+/// The progress of the conversion is reported to `progress`, use `()` to
+/// ignore it.
+///
+/// The conversion is deterministic: for the same input, options, and version
+/// of osmflatc, all resources are byte-identical independently of the number
+/// of threads, except for the `provenance` resource, which contains the time
+/// and the durations of the conversion, and the `checksums` resource listing
+/// it.
+///
+/// The archive is built in a temporary directory with a unique name next to
+/// `output`, which replaces `output` after the conversion succeeded, cf.
+/// [`replace_archive`]. A non-empty `output` is only replaced with
+/// `Options::force`.
+pub fn convert(
+    input: &Path,
+    output: &Path,
+    options: Options,
+    progress: impl Progress,
+) -> Result<Stats, Error> {
+    recover_archive(output)?;
+    let is_empty = |dir: &Path| fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_none());
+    if output.exists() && !is_empty(output) && !options.force {
+        return Err(format!(
+            "output directory {} is not empty, use --force to replace it",
+            output.display()
+        )
+        .into());
+    }
+    // removed on failure when dropped
+    let tmp = temp_archive_dir(output)?;
+    let stats = convert_with_header(input, tmp.path(), options, None, progress)?;
+    replace_archive(&tmp.keep(), output)?;
+    Ok(stats)
+}
+
+/// Returns `path` with `suffix` appended to its last component.
+pub(crate) fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Creates a temporary directory for building an archive which replaces
+/// `archive`.
+///
+/// The directory is created next to `archive`, so that it can be renamed to
+/// `archive`, and has a unique name, so that no existing file is touched.
+pub(crate) fn temp_archive_dir(archive: &Path) -> io::Result<tempfile::TempDir> {
+    let parent = match archive.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(parent)?;
+    let name = archive
+        .file_name()
+        .map_or_else(|| "archive".into(), |name| name.to_string_lossy());
+    tempfile::Builder::new()
+        .prefix(&format!(".{name}."))
+        .suffix(".tmp")
+        .tempdir_in(parent)
+}
+
+/// Returns whether `dir` contains an osmflat archive.
+fn is_archive(dir: &Path) -> bool {
+    dir.join("Osm.archive").is_file()
+}
+
+/// Finishes a [`replace_archive`] of `archive` which was interrupted.
+///
+/// If `archive` is missing, the old archive is restored from `<archive>.old`.
+/// Otherwise, `<archive>.old` is a leftover of a completed replacement and is
+/// removed. Fails without touching `<archive>.old` if it is not an osmflat
+/// archive.
+pub(crate) fn recover_archive(archive: &Path) -> io::Result<()> {
+    let old = with_suffix(archive, ".old");
+    if !old.exists() {
+        return Ok(());
+    }
+    if !is_archive(&old) {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!(
+                "{} exists and is not an osmflat archive, remove it to continue",
+                old.display()
+            ),
+        ));
+    }
+    if archive.exists() {
+        fs::remove_dir_all(&old)
+    } else {
+        warn!("Restoring {} from {}", archive.display(), old.display());
+        fs::rename(&old, archive)
+    }
+}
+
+/// Replaces the archive at `archive`, if any, by the archive at `new`.
+///
+/// The old archive is moved to `<archive>.old`, then `new` is moved to
+/// `archive`, and finally the old archive is removed. Renaming is atomic, but
+/// the two renames are not: in between, `archive` does not exist. If the
+/// process is interrupted there, the old archive is restored by the next
+/// [`recover_archive`], which is called before replacing and converting.
+pub(crate) fn replace_archive(new: &Path, archive: &Path) -> io::Result<()> {
+    recover_archive(archive)?;
+    let old = with_suffix(archive, ".old");
+    if archive.exists() {
+        fs::rename(archive, &old)?;
+    }
+    if let Err(e) = fs::rename(new, archive) {
+        if old.exists() {
+            fs::rename(&old, archive)?;
+        }
+        return Err(e);
+    }
+    if old.exists() {
+        fs::remove_dir_all(&old)?;
+    }
+    Ok(())
+}
+
+/// Converts the OSM data in `input` to an osmflat archive at `output`, using
+/// `header` instead of the header of the input if provided.
+pub(crate) fn convert_with_header(
+    input: &Path,
+    output: &Path,
+    options: Options,
+    header: Option<osmpbf::HeaderBlock>,
+    progress: impl Progress,
+) -> Result<Stats, Error> {
+    let progress = StageTimer::new(progress);
+    let (mut stats, input_crc32) = convert_input(input, output, &options, header, &progress)?;
+    if let Some(path) = &options.report_unresolved {
+        unresolved::write_report_file(path, &stats.unresolved_refs)?;
+    }
+    if let Some(compression) = options.compress {
+        progress.stage_started(Stage::Compress, None);
+        compress::compress_archive(output, compression)?;
+        progress.stage_finished(Stage::Compress);
+    }
+    stats.stage_durations = progress.into_durations();
+    stats.resource_sizes = resource_sizes(output)?;
+    provenance::write(output, input, input_crc32, &options, &stats)?;
+    write_checksums(output)?;
+    Ok(stats)
+}
+
+/// Returns the stats of the conversion and the checksum of the input.
+fn convert_input(
+    input: &Path,
+    output: &Path,
+    options: &Options,
+    header: Option<osmpbf::HeaderBlock>,
+    progress: &dyn Progress,
+) -> Result<(Stats, u32), Error> {
+    let is_opl = input.extension().is_some_and(|ext| ext == "opl");
+    let input_file = if remote::is_url(input) {
+        let url = input.to_string_lossy();
+        if !is_opl && remote::supports_ranges(&url)? {
+            return convert_remote_input(&url, output, options, header, progress);
+        }
+        progress.stage_started(Stage::Download, None);
+        let file = remote::download(&url)?;
+        progress.stage_finished(Stage::Download);
+        file
+    } else {
+        File::open(input)?
+    };
+    let input_data = unsafe { Mmap::map(&input_file)? };
+    let input_crc32 = provenance::checksum(&input_data);
+
+    progress.stage_started(Stage::BlockIndex, None);
+    let stats = if is_opl {
+        let mut input = opl_input(&input_data)?;
+        progress.stage_finished(Stage::BlockIndex);
+        input.header = header.unwrap_or(input.header);
+        convert_blocks(output, options, input, progress)?
+    } else {
+        let mut input = pbf_input(&input_data)?;
+        progress.stage_finished(Stage::BlockIndex);
+        input.header = header.unwrap_or(input.header);
+        convert_blocks(output, options, input, progress)?
+    };
+    Ok((stats, input_crc32))
+}
+
+/// Converts the pbf file at `url` without storing it locally, cf. [`remote`].
+///
+/// The block index is built while streaming the file, and the blocks are read
+/// by range requests.
+fn convert_remote_input(
+    url: &str,
+    output: &Path,
+    options: &Options,
+    header: Option<osmpbf::HeaderBlock>,
+    progress: &dyn Progress,
+) -> Result<(Stats, u32), Error> {
+    progress.stage_started(Stage::BlockIndex, None);
+    let mut reader = provenance::ChecksumReader::new(remote::open(url)?);
+    let block_index = osmpbf::read_block_index(&mut reader)?;
+    let input_crc32 = reader.checksum();
+    let mut input = blocks_input(block_index, |idx| {
+        remote::fetch_range(url, idx.blob_start, idx.blob_len).map(Cow::Owned)
+    })?;
+    progress.stage_finished(Stage::BlockIndex);
+    input.header = header.unwrap_or(input.header);
+    let stats = convert_blocks(output, options, input, progress)?;
+    Ok((stats, input_crc32))
+}
+
+/// Writes the indexes of the nodes of `archive` which have tags.
+fn serialize_tagged_nodes(archive: &osmflat::Osm, builder: &osmflat::OsmBuilder) -> io::Result<()> {
+    let mut tagged_nodes = builder.start_tagged_nodes()?;
+    for (idx, node) in archive.nodes().iter().enumerate() {
+        if !node.tags().is_empty() {
+            tagged_nodes.grow()?.set_value(idx as u64);
+        }
+    }
+    tagged_nodes.close().map_err(io::Error::other)?;
+    Ok(())
+}
+
+/// Writes the bitsets marking the nodes and ways of `archive` which have tags.
+fn serialize_tagged_bitsets(
+    archive: &osmflat::Osm,
+    builder: &osmflat::OsmBuilder,
+) -> io::Result<()> {
+    fn write_bitset(
+        mut words: flatdata::ExternalVector<osmflat::BitsetWord>,
+        bits: impl Iterator<Item = bool>,
+    ) -> io::Result<()> {
+        for chunk in &bits.chunks(64) {
+            words.grow()?.fill_from(&osmflat::build_bitset(chunk)[0]);
+        }
+        words.close().map_err(io::Error::other)?;
+        Ok(())
+    }
+    let nodes = archive.nodes().iter();
+    write_bitset(
+        builder.start_tagged_node_bits()?,
+        nodes.map(|node| !node.tags().is_empty()),
+    )?;
+    let ways = archive.ways().iter();
+    write_bitset(
+        builder.start_tagged_way_bits()?,
+        ways.map(|way| !way.tags().is_empty()),
+    )
+}
+
+/// Writes the values of the tags of `archive` with keys matching `keys` parsed
+/// as numbers.
+fn serialize_tag_numbers(
+    archive: &osmflat::Osm,
+    builder: &osmflat::OsmBuilder,
+    keys: &[KeyPattern],
+) -> io::Result<()> {
+    let strings = archive.stringtable();
+    // tags share their keys, so each key is matched once
+    let mut is_numeric: AHashMap<u64, bool> = AHashMap::new();
+    let mut tag_numbers = builder.start_tag_numbers()?;
+    for tag in archive.tags() {
+        let numeric = *is_numeric.entry(tag.key_idx()).or_insert_with(|| {
+            let key = strings.substring_raw(tag.key_idx() as usize);
+            keys.iter().any(|pattern| pattern.matches(key))
+        });
+        let number = numeric
+            .then(|| osmflat::parse_tag_number(strings.substring_raw(tag.value_idx() as usize)))
+            .flatten();
+        tag_numbers.grow()?.set_value(number);
+    }
+    tag_numbers.close().map_err(io::Error::other)?;
+    Ok(())
+}
+
+/// Writes the `checksums` resource of the archive at `output`, listing all
+/// other files of the archive, cf. `osmflat::Osm::verify_checksums`.
+fn write_checksums(output: &Path) -> io::Result<()> {
+    let checksums = osmflat::compute_checksums(output)?;
+    FileResourceStorage::new(PathBuf::from(output)).write(
+        "checksums",
+        osmflat::schema::osm::resources::CHECKSUMS,
+        osmflat::format_checksums(&checksums).as_bytes(),
+    )
+}
+
+/// Returns the sizes of all files in the `archive` directory by their path
+/// relative to the archive, sorted by path.
+fn resource_sizes(archive: &Path) -> io::Result<Vec<(String, u64)>> {
+    fn visit(dir: &Path, prefix: &str, result: &mut Vec<(String, u64)>) -> io::Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = format!("{prefix}{}", entry.file_name().to_string_lossy());
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                visit(&entry.path(), &format!("{name}/"), result)?;
+            } else {
+                result.push((name, metadata.len()));
+            }
+        }
+        Ok(())
+    }
+    let mut result = Vec::new();
+    visit(archive, "", &mut result)?;
+    result.sort();
+    Ok(result)
+}
+
+pub(crate) fn convert_blocks<B, R>(
+    output: &Path,
+    options: &Options,
+    input: Input<B, R>,
+    progress: &dyn Progress,
+) -> Result<Stats, Error>
+where
+    B: Clone + Send,
+    R: Fn(B) -> io::Result<osmpbf::PrimitiveBlock> + Sync,
+{
+    let storage = FileResourceStorage::new(PathBuf::from(output));
+    let builder = osmflat::OsmBuilder::new(storage.clone())?;
+
+    // TODO: Would be nice not store all these strings in memory, but to flush them
+    // from time to time to disk.
+    let mut stringtable = StringTable::new();
+    let mut tags = TagSerializer::new(&builder)?;
+
+    let granularity = match options.coord_scale {
+        Some(coord_scale) if coord_scale <= 0 || 1000000000 % coord_scale != 0 => {
+            return Err(format!("coordinate scale {coord_scale} does not divide 10^9").into());
+        }
+        Some(coord_scale) => 1000000000 / coord_scale,
+        None => input.granularity,
+    };
+    if let Some(zoom) = options
+        .tile_index
+        .filter(|&zoom| zoom > osmflat::MAX_TILE_ZOOM)
+    {
+        return Err(format!(
+            "zoom level {zoom} is larger than {}",
+            osmflat::MAX_TILE_ZOOM
+        )
+        .into());
+    }
+    let coord_scale = 1000000000 / granularity;
+    serialize_header(&input.header, coord_scale, &builder, &mut stringtable)?;
+
+    let mut stats = Stats::default();
+    let queue_depth = options.queue_depth.unwrap_or(parallel::DEFAULT_QUEUE_DEPTH);
+
+    let ids_archive;
+    let mut node_ids = None;
+    let mut way_ids = None;
+    let mut relation_ids = None;
+    if options.ids {
+        ids_archive = builder.ids()?;
+        node_ids = Some(ids_archive.start_nodes()?);
+        way_ids = Some(ids_archive.start_ways()?);
+        relation_ids = Some(ids_archive.start_relations()?);
+    }
+
+    let node_coords = if options.node_coords {
+        Some(builder.start_node_coords()?)
+    } else {
+        None
+    };
+
+    if options.history && options.sort != NodeOrder::Input {
+        return Err("sorting nodes is not supported in history mode".into());
+    }
+
+    if options.id_index && !options.ids {
+        return Err("the id index requires the ids subarchive".into());
+    }
+
+    if options.history && options.drop_untagged_nodes {
+        return Err("dropping untagged nodes is not supported in history mode".into());
+    }
+
+    let selection = select(
+        options,
+        input.dense_nodes.clone(),
+        input.ways.clone(),
+        input.relations.clone(),
+        &input.read,
+        queue_depth,
+        progress,
+    )?;
+
+    if options.optimize_stringtable {
+        let blocks = (input.dense_nodes.iter())
+            .chain(&input.ways)
+            .chain(&input.relations)
+            .cloned()
+            .collect();
+        insert_strings_by_frequency(
+            &mut stringtable,
+            blocks,
+            &input.read,
+            queue_depth,
+            options.history,
+            &selection,
+            progress,
+        )?;
+    }
+
+    let history_archive;
+    let mut node_versions = None;
+    let mut way_versions = None;
+    let mut relation_versions = None;
+    if options.history {
+        history_archive = builder.history()?;
+        node_versions = Some(history_archive.start_nodes()?);
+        way_versions = Some(history_archive.start_ways()?);
+        relation_versions = Some(history_archive.start_relations()?);
+    }
+
+    std::thread::scope(|scope| -> Result<(), Error> {
+        // The index of relation ids is needed before converting relations, since
+        // relations can refer again to relations. It only depends on the input,
+        // therefore it is built in the background while nodes and ways are
+        // converted.
+        let relation_blocks = input.relations.clone();
+        let relations_index = scope.spawn(|| {
+            build_relations_index(
+                relation_blocks,
+                &input.read,
+                queue_depth,
+                options.history,
+                &selection,
+            )
+        });
+
+        let nodes_id_to_idx = serialize_dense_node_blocks(
+            &builder,
+            granularity,
+            node_ids,
+            node_coords,
+            node_versions,
+            options.sort,
+            input.dense_nodes,
+            &input.read,
+            queue_depth,
+            &mut tags,
+            &mut stringtable,
+            &mut stats,
+            &selection,
+            progress,
+        )?;
+
+        let ways_id_to_idx = serialize_way_blocks(
+            &builder,
+            way_ids,
+            way_versions,
+            input.ways,
+            &input.read,
+            queue_depth,
+            &nodes_id_to_idx,
+            &mut tags,
+            &mut stringtable,
+            &mut stats,
+            &selection,
+            options.report_unresolved.is_some(),
+            progress,
+        )?;
+
+        progress.stage_started(Stage::RelationsIndex, None);
+        let relations_id_to_idx = relations_index.join().expect("thread panicked")?;
+        progress.stage_finished(Stage::RelationsIndex);
+
+        serialize_relation_blocks(
+            &builder,
+            relation_ids,
+            relation_versions,
+            input.relations,
+            &input.read,
+            queue_depth,
+            &nodes_id_to_idx,
+            &ways_id_to_idx,
+            &relations_id_to_idx,
+            &mut tags,
+            &mut stringtable,
+            &mut stats,
+            &selection,
+            options.report_unresolved.is_some(),
+            progress,
+        )
+    })?;
+
+    if options.changesets {
+        serialize_changeset_blocks(
+            &builder,
+            granularity,
+            input.changesets,
+            &input.read,
+            queue_depth,
+            &mut stringtable,
+            &mut tags,
+            &selection,
+            progress,
+        )?;
+    } else if !input.changesets.is_empty() {
+        warn!(
+            "Skipping {} blocks of changesets, use --changesets to convert them",
+            input.changesets.len()
+        );
+    }
+
+    // Finalize data structures
+    tags.close(); // drop the reference to stringtable
+
+    progress.stage_started(Stage::StringTable, None);
+    let strings = stringtable.into_bytes();
+    builder.set_stringtable(&strings)?;
+    if options.stringtable_offsets {
+        builder.set_stringtable_offsets(&osmflat::build_stringtable_offsets(&strings))?;
+    }
+    std::mem::drop(strings);
+    progress.stage_finished(Stage::StringTable);
+
+    if options.bboxes
+        || options.tagged_nodes
+        || options.tagged_bitsets
+        || options.spatial_index
+        || options.inverted_index
+        || options.id_index
+        || options.node_ways
+        || options.member_relations
+        || options.areas
+        || options.elevation.is_some()
+        || options.tile_index.is_some()
+        || options.admin_hierarchy
+        || !options.numeric_tags.is_empty()
+        || options.roles
+        || options.centroids
+    {
+        let archive = osmflat::Osm::open(storage.clone())?;
+        if options.bboxes {
+            progress.stage_started(Stage::BoundingBoxes, None);
+            spatial::serialize_bboxes(&archive, &builder)?;
+            progress.stage_finished(Stage::BoundingBoxes);
+        }
+        if options.tagged_nodes {
+            progress.stage_started(Stage::TaggedNodes, None);
+            serialize_tagged_nodes(&archive, &builder)?;
+            progress.stage_finished(Stage::TaggedNodes);
+        }
+        if options.tagged_bitsets {
+            progress.stage_started(Stage::TaggedBitsets, None);
+            serialize_tagged_bitsets(&archive, &builder)?;
+            progress.stage_finished(Stage::TaggedBitsets);
+        }
+        if options.spatial_index {
+            progress.stage_started(Stage::SpatialIndex, None);
+            spatial::serialize_spatial_index(&archive, &builder.spatial_index()?)?;
+            progress.stage_finished(Stage::SpatialIndex);
+        }
+        if options.inverted_index {
+            progress.stage_started(Stage::InvertedIndex, None);
+            inverted_index::serialize_inverted_index(&archive, &builder.inverted_index()?)?;
+            progress.stage_finished(Stage::InvertedIndex);
+        }
+        if let (true, Some(ids)) = (options.id_index, archive.ids()) {
+            progress.stage_started(Stage::IdIndex, None);
+            let id_index = builder.id_index()?;
+            id_index.set_nodes(&osmflat::build_id_index(ids.nodes()))?;
+            id_index.set_ways(&osmflat::build_id_index(ids.ways()))?;
+            id_index.set_relations(&osmflat::build_id_index(ids.relations()))?;
+            progress.stage_finished(Stage::IdIndex);
+        }
+        if options.node_ways {
+            progress.stage_started(Stage::NodeWays, None);
+            osmflat::build_node_ways(&archive, &builder.node_ways()?)?;
+            progress.stage_finished(Stage::NodeWays);
+        }
+        if options.member_relations {
+            progress.stage_started(Stage::MemberRelations, None);
+            for (entity_type, index) in [
+                (EntityType::Node, builder.node_relations()?),
+                (EntityType::Way, builder.way_relations()?),
+                (EntityType::Relation, builder.relation_relations()?),
+            ] {
+                osmflat::build_member_relations(&archive, entity_type, &index)?;
+            }
+            progress.stage_finished(Stage::MemberRelations);
+        }
+        if options.areas {
+            progress.stage_started(Stage::Areas, None);
+            osmflat::build_areas(&archive, &builder.areas()?)?;
+            progress.stage_finished(Stage::Areas);
+        }
+        if let Some(dir) = &options.elevation {
+            progress.stage_started(Stage::Elevation, None);
+            let num_unknown = elevation::serialize_elevation(&archive, &builder, dir)?;
+            if num_unknown > 0 {
+                warn!("{num_unknown} nodes have no elevation, since they are outside of the tiles or in voids");
+            }
+            progress.stage_finished(Stage::Elevation);
+        }
+        if let Some(zoom) = options.tile_index {
+            progress.stage_started(Stage::TileIndex, None);
+            osmflat::build_tile_index(&archive, zoom, &builder.tile_index()?)?;
+            progress.stage_finished(Stage::TileIndex);
+        }
+        if options.admin_hierarchy {
+            progress.stage_started(Stage::AdminHierarchy, None);
+            osmflat::build_admin_hierarchy(&archive, &builder.admin_hierarchy()?)?;
+            progress.stage_finished(Stage::AdminHierarchy);
+        }
+        if !options.numeric_tags.is_empty() {
+            progress.stage_started(Stage::TagNumbers, None);
+            serialize_tag_numbers(&archive, &builder, &options.numeric_tags)?;
+            progress.stage_finished(Stage::TagNumbers);
+        }
+        if options.roles {
+            progress.stage_started(Stage::Roles, None);
+            builder.set_roles(&osmflat::build_roles(&archive))?;
+            progress.stage_finished(Stage::Roles);
+        }
+        if options.centroids {
+            progress.stage_started(Stage::Centroids, None);
+            osmflat::build_centroids(&archive, &builder)?;
+            progress.stage_finished(Stage::Centroids);
+        }
+    }
+
+    std::mem::drop(builder);
+    progress.stage_started(Stage::Verify, None);
+    let archive = osmflat::Osm::open(storage)?;
+    if options.verify {
+        stats.violations = Some(osmflat::validate(&archive));
+    }
+    progress.stage_finished(Stage::Verify);
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::fmt::Write;
+
+    #[test]
+    fn test_sort_by_frequency() {
+        let counts = AHashMap::from_iter([
+            (b"name".to_vec(), 3),
+            (b"highway".to_vec(), 5),
+            (b"building".to_vec(), 3),
+            (b"yes".to_vec(), 7),
+        ]);
+        assert_eq!(
+            sort_by_frequency(counts),
+            [&b"yes"[..], b"highway", b"building", b"name"]
+        );
+    }
+
+    #[test]
+    fn test_scale_coord() {
+        assert_eq!(scale_coord(131_234_567_890, 100).unwrap(), 1_312_345_679);
+        assert_eq!(scale_coord(-131_234_567_850, 100).unwrap(), -1_312_345_679);
+        assert_eq!(scale_coord(-131_234_567_849, 100).unwrap(), -1_312_345_678);
+        assert_eq!(scale_coord(2_000_000_000, 1).unwrap(), 2_000_000_000);
+        assert!(scale_coord(13_100_000_000, 1).is_err());
+        assert!(scale_coord(-180_000_000_000, 50).is_err());
+    }
+
+    #[test]
+    fn test_deterministic() {
+        // several blocks of each type, so that they are processed in parallel
+        let mut opl = String::new();
+        for i in 1..20_000 {
+            let (lon, lat) = (f64::from(i % 137) / 10.0, f64::from(i % 89) / 10.0);
+            writeln!(
+                opl,
+                "n{i} v1 Tname=n{},amenity=a{} x{lon} y{lat}",
+                i % 97,
+                i % 7
+            )
+            .unwrap();
+        }
+        for i in 1..10_000 {
+            writeln!(opl, "w{i} v1 Thighway=h{} Nn{i},n{}", i % 13, i + 1).unwrap();
+        }
+        for i in 1..9_000 {
+            writeln!(
+                opl,
+                "r{i} v1 Ttype=t{} Mw{i}@outer,n{i}@,r{}@",
+                i % 5,
+                i + 1
+            )
+            .unwrap();
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+
+        let options = Options {
+            ids: true,
+            sort: NodeOrder::Hilbert,
+            optimize_stringtable: true,
+            bboxes: true,
+            tagged_nodes: true,
+            tagged_bitsets: true,
+            spatial_index: true,
+            inverted_index: true,
+            id_index: true,
+            node_ways: true,
+            member_relations: true,
+            areas: true,
+            stringtable_offsets: true,
+            node_coords: true,
+            ..Default::default()
+        };
+        let checksums = |num_threads: usize, queue_depth: Option<NonZeroUsize>| {
+            let output = dir.path().join(format!("{num_threads}-{queue_depth:?}"));
+            let options = Options {
+                queue_depth,
+                ..options.clone()
+            };
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(num_threads)
+                .build()
+                .unwrap()
+                .install(|| convert(&input, &output, options, ()))
+                .unwrap();
+            let mut checksums = osmflat::compute_checksums(&output).unwrap();
+            checksums.retain(|c| !c.path.starts_with("provenance"));
+            checksums
+        };
+        let expected = checksums(1, None);
+        assert_eq!(checksums(4, None), expected);
+        assert_eq!(checksums(4, NonZeroUsize::new(1)), expected);
+    }
+
+    #[test]
+    fn test_id_index() {
+        let opl = "n-2 v1 x9 y9\nn-1 v1 x0 y0\nn4 v1 x5 y5\nn8 v1 x1 y1\n\
+                   w3 v1 Nn4,n8\nw6 v1 Nn-1,n4\nr5 v1 Mw3@\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+        let output = dir.path().join("output");
+        let options = Options {
+            ids: true,
+            sort: NodeOrder::Hilbert,
+            id_index: true,
+            ..Default::default()
+        };
+        convert(&input, &output, options, ()).unwrap();
+
+        let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+        let ids = archive.ids().unwrap();
+        for id in [-2, -1, 4, 8] {
+            let idx = archive.node_index_by_id(id).unwrap();
+            assert_eq!(ids.nodes()[idx].signed_value(), id);
+        }
+        assert_eq!(archive.way_index_by_id(3), Some(0));
+        assert_eq!(archive.way_index_by_id(6), Some(1));
+        assert_eq!(archive.relation_index_by_id(5), Some(0));
+        for id in [-3, 0, 5, 9] {
+            assert_eq!(archive.node_index_by_id(id), None);
+        }
+        assert_eq!(archive.way_index_by_id(4), None);
+    }
+
+    #[test]
+    fn test_open_with_options() {
+        let opl = "n1 v1 Tname=a x0 y0\nn2 v1 x1 y0\nw1 v1 Nn1,n2\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+        let output = dir.path().join("output");
+        convert(&input, &output, Options::default(), ()).unwrap();
+
+        let strict = osmflat::OpenOptions {
+            deny_unknown_resources: true,
+            verify_checksums: true,
+            ..Default::default()
+        };
+        let buffered = osmflat::OpenOptions {
+            buffered: true,
+            ..strict.clone()
+        };
+        for options in [osmflat::OpenOptions::default(), strict.clone(), buffered] {
+            let archive = osmflat::Osm::open_with_options(&output, &options).unwrap();
+            assert_eq!(archive.nodes().len(), 2);
+            assert!(archive.missing_optional_resources().contains(&"ids"));
+        }
+        let options = osmflat::OpenOptions {
+            deny_missing_optional: true,
+            ..Default::default()
+        };
+        let err = osmflat::Osm::open_with_options(&output, &options).unwrap_err();
+        assert!(
+            err.to_string().contains("missing optional resources ids"),
+            "{err}"
+        );
+
+        std::fs::write(output.join("unknown"), b"").unwrap();
+        let err = osmflat::Osm::open_with_options(&output, &strict).unwrap_err();
+        assert!(
+            err.to_string().contains("unknown resources unknown"),
+            "{err}"
+        );
+        std::fs::remove_file(output.join("unknown")).unwrap();
+
+        // a truncated file is named by the checksums
+        let ways = std::fs::read(output.join("ways")).unwrap();
+        std::fs::write(output.join("ways"), &ways[..ways.len() - 1]).unwrap();
+        let options = osmflat::OpenOptions::default();
+        let err = osmflat::Osm::open_with_options(&output, &options).unwrap_err();
+        assert!(err.to_string().contains("ways has size"), "{err}");
+    }
+
+    #[test]
+    fn test_node_ways() {
+        let opl = "n1 v1 x0 y0\nn2 v1 x1 y0\nn3 v1 x1 y1\nn4 v1 x0 y1\n\
+                   w1 v1 Nn1,n2,n3,n1\nw2 v1 Nn3,n4,n99\nw3 v1 Nn2,n3\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+        let output = dir.path().join("output");
+        let options = Options {
+            ids: true,
+            node_ways: true,
+            ..Default::default()
+        };
+        convert(&input, &output, options, ()).unwrap();
+
+        let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+        let ways: Vec<Vec<u64>> = (1..=4)
+            .map(|id| {
+                let idx = (archive.ids().unwrap().nodes().iter())
+                    .position(|node_id| node_id.value() == id)
+                    .unwrap();
+                archive.ways_containing_node(idx).unwrap().collect()
+            })
+            .collect();
+        assert_eq!(ways, [vec![0], vec![0, 2], vec![0, 1, 2], vec![1]]);
+    }
+
+    #[test]
+    fn test_member_relations() {
+        let opl = "n1 v1 x0 y0\nn2 v1 x1 y0\nw1 v1 Nn1,n2\n\
+                   r1 v1 Mw1@outer,n2@label,n99@\nr2 v1 Mr1@,w1@\nr3 v1 Mr1@,r2@\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+        let output = dir.path().join("output");
+        let options = Options {
+            member_relations: true,
+            ..Default::default()
+        };
+        convert(&input, &output, options, ()).unwrap();
+
+        let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+        let relations = |entity_type, idx| -> Vec<u64> {
+            archive
+                .relations_containing(entity_type, idx)
+                .unwrap()
+                .collect()
+        };
+        assert!(relations(EntityType::Node, 0).is_empty());
+        assert_eq!(relations(EntityType::Node, 1), [0]);
+        assert_eq!(relations(EntityType::Way, 0), [0, 1]);
+        assert_eq!(relations(EntityType::Relation, 0), [1, 2]);
+        assert_eq!(relations(EntityType::Relation, 1), [2]);
+        assert!(relations(EntityType::Relation, 2).is_empty());
+    }
+
+    #[test]
+    fn test_drop_untagged_nodes() {
+        // negative ids in descending order, as exported by JOSM
+        let opl = "\
+n-1 v1 Tsource=a x0 y0
+n-3 v1 Tsource=a x1 y1
+n-2 v1 Tsource=a x2 y2
+n4 v1 Tname=a x3 y3
+n6 v1 Tsource=a x4 y4
+w1 v1 Thighway=primary Nn-1,n4
+r1 v1 Ttype=route Mn-2@
+";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+        let output = dir.path().join("output");
+        let options = Options {
+            ids: true,
+            drop_tags: vec!["source".parse().unwrap()],
+            drop_untagged_nodes: true,
+            ..Default::default()
+        };
+        convert(&input, &output, options, ()).unwrap();
+        let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+        let mut ids: Vec<i64> = (archive.ids().unwrap().nodes().iter())
+            .map(|id| id.signed_value())
+            .collect();
+        ids.sort_unstable();
+        assert_eq!(ids, [-2, -1, 4]);
+    }
+
+    #[test]
+    fn test_history() {
+        let opl = "\
+n1 v1 dV t2020-01-01T00:00:00Z Tname=a x1 y1
+n1 v2 dV t2020-01-03T00:00:00Z Tname=b x1.5 y1
+n1 v3 dD t2020-01-05T00:00:00Z T x y
+n2 v1 dV t2020-01-02T00:00:00Z T x2 y2
+w1 v1 dV t2020-01-02T00:00:00Z Thighway=primary Nn1,n2
+w1 v2 dD t2020-01-04T00:00:00Z T N
+r1 v1 dV t2020-01-02T00:00:00Z Ttype=route Mw1@
+";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+        let output = dir.path().join("output");
+        let options = Options {
+            ids: true,
+            history: true,
+            ..Default::default()
+        };
+        convert(&input, &output, options, ()).unwrap();
+        let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+        assert_eq!(archive.nodes().len(), 4);
+        assert_eq!(archive.ways().len(), 2);
+
+        // days since 2020-01-01
+        let day = |n: i64| 1_577_836_800 + n * 86_400;
+        let versions = |entity_type, idx| {
+            osmflat::versions(&archive, entity_type, idx)
+                .unwrap()
+                .map(|(idx, v)| (idx, v.version(), v.timestamp(), v.visible()))
+                .collect::<Vec<_>>()
+        };
+        let node_versions = [
+            (0, 1, day(0), true),
+            (1, 2, day(2), true),
+            (2, 3, day(4), false),
+        ];
+        for idx in 0..3 {
+            assert_eq!(
+                osmflat::version_range(&archive, EntityType::Node, idx),
+                Some(0..3)
+            );
+            assert_eq!(versions(EntityType::Node, idx), node_versions);
+        }
+        assert_eq!(
+            osmflat::version_range(&archive, EntityType::Node, 3),
+            Some(3..4)
+        );
+        assert_eq!(versions(EntityType::Node, 3), [(3, 1, day(1), true)]);
+        assert_eq!(
+            versions(EntityType::Way, 1),
+            [(0, 1, day(1), true), (1, 2, day(3), false)]
+        );
+        assert_eq!(versions(EntityType::Relation, 0), [(0, 1, day(1), true)]);
+
+        let node_at = |timestamp| osmflat::version_at(&archive, EntityType::Node, 2, timestamp);
+        assert_eq!(node_at(day(-1)), None);
+        assert_eq!(node_at(day(0)), Some(0));
+        assert_eq!(node_at(day(2) - 1), Some(0));
+        assert_eq!(node_at(day(2)), Some(1));
+        // deleted
+        assert_eq!(node_at(day(4)), None);
+        assert_eq!(node_at(day(10)), None);
+        let way_at = |timestamp| osmflat::version_at(&archive, EntityType::Way, 0, timestamp);
+        assert_eq!(way_at(day(0)), None);
+        assert_eq!(way_at(day(2)), Some(0));
+        assert_eq!(way_at(day(3)), None);
+
+        // without the history subarchive, the versions are unknown
+        let output = dir.path().join("latest");
+        let opl = "n1 v2 dV t2020-01-03T00:00:00Z Tname=b x1.5 y1\n";
+        std::fs::write(&input, opl).unwrap();
+        let options = Options {
+            ids: true,
+            ..Default::default()
+        };
+        convert(&input, &output, options, ()).unwrap();
+        let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+        assert_eq!(
+            osmflat::version_range(&archive, EntityType::Node, 0),
+            Some(0..1)
+        );
+        assert!(osmflat::versions(&archive, EntityType::Node, 0).is_none());
+        assert_eq!(
+            osmflat::version_at(&archive, EntityType::Node, 0, day(5)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_changesets() {
+        let opl = "n1 v1 x0 y0\n\
+                   c1 k1 s2020-01-01T00:00:00Z e2020-01-01T01:00:00Z d0 i7 uAnn \
+                   x0 y0 X1 Y1 Tcomment=fix,created_by=JOSM\n\
+                   c2 k0 s2020-01-02T00:00:00Z e d0 i8 uBob x y X Y T\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+        let output = dir.path().join("output");
+        let options = Options {
+            changesets: true,
+            ..Default::default()
+        };
+        convert(&input, &output, options, ()).unwrap();
+
+        let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+        let changesets = archive.changesets().unwrap();
+        let changeset = &changesets.changesets()[0];
+        assert_eq!(changeset.id(), 1);
+        assert_eq!(changeset.created_at(), 1_577_836_800);
+        assert_eq!(changeset.closed_at(), 1_577_840_400);
+        let user = archive
+            .stringtable()
+            .substring_raw(changeset.user_idx() as usize);
+        assert_eq!(user, b"Ann");
+        assert_eq!(changesets.changesets()[1].closed_at(), 0);
+
+        let tags: Vec<_> = osmflat::iter_changeset_tags(&archive, 0).unwrap().collect();
+        assert_eq!(
+            tags,
+            [(&b"comment"[..], &b"fix"[..]), (b"created_by", b"JOSM")]
+        );
+        assert_eq!(
+            osmflat::iter_changeset_tags(&archive, 1).unwrap().count(),
+            0
+        );
+        // the tags of changesets are not tags of entities
+        assert_eq!(archive.tags_index().len(), 0);
+        assert!(osmflat::validate(&archive).is_valid());
+    }
+
+    #[test]
+    fn test_areas() {
+        let opl = "n1 v1 x0 y0\nn2 v1 x1 y0\nn3 v1 x1 y1\nn4 v1 x0 y1\n\
+                   w1 v1 Tlanduse=forest Nn1,n2,n3,n1\nw2 v1 Nn2,n3,n4,n1\nw3 v1 Nn1,n2\n\
+                   r1 v1 Ttype=multipolygon Mw3@outer,w2@outer\n\
+                   r2 v1 Ttype=multipolygon Mw3@outer\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+        let output = dir.path().join("output");
+        let options = Options {
+            areas: true,
+            ..Default::default()
+        };
+        convert(&input, &output, options, ()).unwrap();
+
+        let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+        let areas = archive.areas().unwrap();
+        // w2 and w3 are open, and the ring of r2 is not closed
+        assert_eq!(areas.areas().len(), 2);
+        assert_eq!(areas.find(EntityType::Way, 0), Some(0));
+        assert_eq!(areas.find(EntityType::Relation, 0), Some(1));
+        assert_eq!(areas.find(EntityType::Relation, 1), None);
+        let polygons = archive.area_polygons(0).unwrap();
+        assert_eq!(
+            polygons[0].outer,
+            [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 0.0)]
+        );
+        let polygons = archive.area_polygons(1).unwrap();
+        assert_eq!(
+            polygons,
+            osmflat::geometry::multipolygon(&archive, 0).unwrap()
+        );
+        assert_eq!(polygons[0].outer.len(), 5);
+    }
+
+    #[test]
+    fn test_node_coords() {
+        let opl = "n1 v1 Tname=a x3 y3\nn2 v1 x-1 y2\nn3 v1 x1 y-1\nw1 v1 Nn1,n2,n3\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+        for sort in [NodeOrder::Input, NodeOrder::Hilbert] {
+            let output = dir.path().join(format!("output-{sort:?}"));
+            let options = Options {
+                sort,
+                node_coords: true,
+                ..Default::default()
+            };
+            convert(&input, &output, options, ()).unwrap();
+
+            let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+            let nodes = archive.nodes();
+            let node_coords = archive.node_coords().unwrap();
+            assert_eq!(node_coords.len(), nodes.len());
+            for (node, coord) in nodes.iter().zip(node_coords) {
+                assert_eq!((node.lat(), node.lon()), (coord.lat(), coord.lon()));
+            }
+            let report = osmflat::validate(&archive);
+            assert!(report.is_valid(), "{report}");
+            assert_eq!(
+                osmflat::way_bbox(&archive, 0).unwrap(),
+                osmflat::BBox::from_degrees(&archive, (-1.0, -1.0), (3.0, 3.0))
+            );
+        }
+    }
+
+    #[test]
+    fn test_elevation() {
+        let opl = "n1 v1 x13.5 y52.5\nn2 v1 x13.25 y52.75\nn3 v1 x14.5 y52.5\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+        let dem = dir.path().join("dem");
+        std::fs::create_dir(&dem).unwrap();
+        let samples: [i16; 9] = [100, 200, 300, 100, 200, 300, 0, 0, 0];
+        let tile: Vec<u8> = samples.iter().flat_map(|s| s.to_be_bytes()).collect();
+        std::fs::write(dem.join("N52E013.hgt"), tile).unwrap();
+        let output = dir.path().join("output");
+        let options = Options {
+            elevation: Some(dem),
+            ..Default::default()
+        };
+        convert(&input, &output, options, ()).unwrap();
+
+        let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+        assert_eq!(archive.elevation().unwrap().len(), 3);
+        assert_eq!(archive.node_elevation(0), Some(200));
+        assert_eq!(archive.node_elevation(1), Some(150));
+        // outside of the tiles
+        assert_eq!(archive.node_elevation(2), None);
+    }
+
+    #[test]
+    fn test_tile_index() {
+        let opl = "n1 v1 x10 y10\nn2 v1 x-10 y10\nn3 v1 x20 y20\nn4 v1 x10 y-10\n\
+                   w1 v1 Nn1,n4\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+        let options = Options {
+            tile_index: Some(osmflat::MAX_TILE_ZOOM + 1),
+            ..Default::default()
+        };
+        assert!(convert(&input, &dir.path().join("invalid"), options, ()).is_err());
+
+        let output = dir.path().join("output");
+        let options = Options {
+            sort: NodeOrder::Hilbert,
+            tile_index: Some(1),
+            ..Default::default()
+        };
+        convert(&input, &output, options, ()).unwrap();
+
+        let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+        let tile_index = archive.tile_index().unwrap();
+        assert_eq!(tile_index.grid().zoom(), 1);
+        let mut nodes: Vec<u64> = (0..2)
+            .flat_map(|y| (0..2).map(move |x| (x, y)))
+            .flat_map(|(x, y)| {
+                let nodes: Vec<u64> = tile_index.nodes_in_tile(x, y).collect();
+                for &idx in &nodes {
+                    let (lon, lat) = archive.node_coord(idx as usize);
+                    assert_eq!(osmflat::tile_at(1, lon, lat), (x, y));
+                }
+                nodes
+            })
+            .collect();
+        nodes.sort();
+        assert_eq!(nodes, [0, 1, 2, 3]);
+        assert_eq!(tile_index.ways_in_tile(1, 0).collect::<Vec<_>>(), [0]);
+        assert_eq!(tile_index.ways_in_tile(1, 1).collect::<Vec<_>>(), [0]);
+        assert_eq!(tile_index.ways_in_tile(0, 0).count(), 0);
+    }
+
+    #[test]
+    fn test_admin_hierarchy() {
+        let opl = "n1 v1 x0 y0\nn2 v1 x4 y0\nn3 v1 x4 y4\nn4 v1 x0 y4\n\
+                   n5 v1 Tname=inside x1 y1\nn6 v1 Tname=outside x5 y5\n\
+                   w1 v1 Nn1,n2,n3,n4,n1\n\
+                   r1 v1 Ttype=boundary,boundary=administrative,admin_level=2 Mw1@outer\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+        let output = dir.path().join("output");
+        let options = Options {
+            admin_hierarchy: true,
+            ..Default::default()
+        };
+        convert(&input, &output, options, ()).unwrap();
+
+        let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+        let admin = archive.admin_hierarchy().unwrap();
+        assert_eq!(admin.boundaries_of_node(4).collect::<Vec<_>>(), [0]);
+        assert_eq!(admin.boundaries_of_node(5).count(), 0);
+    }
+
+    #[test]
+    fn test_tag_numbers() {
+        let opl = "n1 v1 Tpopulation=3500,name=12 x0 y0\n\
+                   w1 v1 Tmaxspeed=50mph,width=2.5 Nn1\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+        let output = dir.path().join("output");
+        let options = Options {
+            numeric_tags: vec!["population".parse().unwrap(), "max*".parse().unwrap()],
+            ..Default::default()
+        };
+        convert(&input, &output, options, ()).unwrap();
+
+        let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+        let tag_numbers = archive.tag_numbers().unwrap();
+        assert_eq!(tag_numbers.len(), archive.tags().len());
+        let numbers: Vec<_> = archive
+            .tags()
+            .iter()
+            .zip(tag_numbers)
+            .map(|(tag, number)| {
+                let key = archive.stringtable().substring_raw(tag.key_idx() as usize);
+                (key, number.value())
+            })
+            .sorted()
+            .collect();
+        assert_eq!(
+            numbers,
+            [
+                (&b"maxspeed"[..], Some(50_000)),
+                (b"name", None),
+                (b"population", Some(3_500_000)),
+                (b"width", None),
+            ]
+        );
+        // the values of other keys are parsed on the fly
+        let way_tags = archive.ways()[0].tags();
+        assert_eq!(osmflat::tag_number(&archive, way_tags, b"width"), Some(2.5));
+    }
+
+    #[test]
+    fn test_roles() {
+        let opl = "n1 v1 x0 y0\nw1 v1 Nn1\nr1 v1 Mw1@outer,n1@label\nr2 v1 Mw1@outer,r1@\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+        let output = dir.path().join("output");
+        let options = Options {
+            roles: true,
+            ..Default::default()
+        };
+        convert(&input, &output, options, ()).unwrap();
+
+        let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+        assert_eq!(archive.roles().unwrap().len(), 3);
+        let outer = archive.find_role(b"outer").unwrap();
+        let roles: Vec<_> = (0..2)
+            .flat_map(|idx| archive.relation_members().at(idx))
+            .map(|member| osmflat::member_role_idx(&member))
+            .collect();
+        assert_eq!(roles[0], outer);
+        assert_eq!(roles[2], outer);
+        let interned = archive.interned_role(roles[1]).unwrap();
+        assert_eq!(archive.role(interned), Some(&b"label"[..]));
+    }
+
+    #[test]
+    fn test_centroids() {
+        let opl = "n1 v1 x0 y0\nn2 v1 x2 y0\nn3 v1 x2 y2\nn4 v1 x0 y2\n\
+                   w1 v1 Nn1,n2,n3,n4,n1\nw2 v1 Nn1,n9\n\
+                   r1 v1 Ttype=multipolygon Mw1@outer\nr2 v1 Mw2@\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+        let output = dir.path().join("output");
+        let options = Options {
+            centroids: true,
+            ..Default::default()
+        };
+        convert(&input, &output, options, ()).unwrap();
+
+        let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+        assert_eq!(archive.way_centroids().unwrap().len(), 2);
+        assert_eq!(archive.relation_centroids().unwrap().len(), 2);
+        assert_eq!(archive.way_centroid(0), Some((1.0, 1.0)));
+        assert_eq!(archive.relation_centroid(0), Some((1.0, 1.0)));
+        // the way has an unresolved node
+        assert_eq!(archive.way_centroid(1), None);
+        assert_eq!(archive.relation_centroid(1), None);
+    }
+
+    #[test]
+    fn test_stringtable_offsets() {
+        let opl = "n1 v1 Tname=Zur%20%Linde,amenity=pub x1 y1\n\
+                   n2 v1 Tname=Linde,amenity=pub x2 y2\n\
+                   w1 v1 Thighway=residential Nn1,n2\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+        for optimize_stringtable in [false, true] {
+            let output = dir.path().join(format!("output-{optimize_stringtable}"));
+            let options = Options {
+                optimize_stringtable,
+                stringtable_offsets: true,
+                ..Default::default()
+            };
+            convert(&input, &output, options, ()).unwrap();
+
+            let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+            let offsets = archive.stringtable_offsets().unwrap();
+            let strings = archive.stringtable();
+            assert_eq!(
+                offsets.last().unwrap().value(),
+                strings.as_bytes().len() as u64
+            );
+            for tag in archive.tags() {
+                for idx in [tag.key_idx(), tag.value_idx()] {
+                    let idx = idx as usize;
+                    assert_eq!(archive.substring_fast(idx), strings.substring_raw(idx));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_tags() {
+        let opl = "n1 v1 Tname=Zur%20%Linde,amenity=pub,opening_hours=24/7 x1 y1\nn2 v1 x2 y2\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+        let output = dir.path().join("output");
+        convert(&input, &output, Options::default(), ()).unwrap();
+
+        let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+        let tags = osmflat::Tags::new(&archive, archive.nodes()[0].tags());
+        assert_eq!(tags.len(), 3);
+        assert_eq!(tags.get(b"amenity"), Some(&b"pub"[..]));
+        assert_eq!(tags.get(b"name"), Some(&b"Zur Linde"[..]));
+        assert_eq!(tags.get(b"amen"), None);
+        assert!(tags.contains(b"opening_hours"));
+        assert!(!tags.contains(b"pub"));
+        let keys: Vec<_> = tags.iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, [&b"name"[..], b"amenity", b"opening_hours"]);
+        let map: std::collections::BTreeMap<_, _> = tags.into_iter().collect();
+        assert_eq!(map.keys().next(), Some(&&b"amenity"[..]));
+
+        let tags = osmflat::Tags::new(&archive, archive.nodes()[1].tags());
+        assert!(tags.is_empty());
+    }
+
+    #[test]
+    fn test_header_api() {
+        let url = "https://planet.openstreetmap.org/replication/minute";
+        let opl = format!("n1 v1 Turl={url} x1.5 y-2\nn2 v1 x2 y3\n");
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+        let output = dir.path().join("output");
+        convert(&input, &output, Options::default(), ()).unwrap();
+
+        let storage = FileResourceStorage::new(&output);
+        let archive = osmflat::Osm::open(storage.clone()).unwrap();
+        assert_eq!(archive.replication(), None);
+        let mut header = archive.header().clone();
+        assert_eq!(header.source_idx(), None);
+        assert_eq!(header.replication_base_url_idx(), None);
+        assert_eq!(header.bounding_box(), None);
+
+        let url_idx = osmflat::Tags::new(&archive, archive.nodes()[0].tags())
+            .get(b"url")
+            .map(|value| {
+                value.as_ptr() as usize - archive.stringtable().as_bytes().as_ptr() as usize
+            })
+            .unwrap();
+        header.set_bbox_left(15_000_000);
+        header.set_bbox_right(20_000_000);
+        header.set_bbox_bottom(-20_000_000);
+        header.set_bbox_top(30_000_000);
+        header.set_replication_timestamp(1_700_000_000);
+        header.set_replication_sequence_number(42);
+        header.set_replication_base_url_idx(Some(url_idx as u64));
+        storage
+            .write(
+                "header",
+                osmflat::schema::osm::resources::HEADER,
+                header.as_bytes(),
+            )
+            .unwrap();
+        drop(archive);
+
+        let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+        let bbox = archive.header().bounding_box().unwrap();
+        assert_eq!(bbox.to_degrees(&archive), ((1.5, -2.0), (2.0, 3.0)));
+        let replication = archive.replication().unwrap();
+        assert_eq!(replication.timestamp.timestamp(), 1_700_000_000);
+        assert_eq!(replication.sequence_number, 42);
+        assert_eq!(replication.base_url.as_deref(), Some(url));
+    }
+
+    #[test]
+    fn test_pack_tar_archive() {
+        let opl = "n1 v1 Tname=a x1 y1\nn2 v1 x2 y2\nw3 v1 Thighway=primary Nn1,n2\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+        let output = dir.path().join("output");
+        let options = Options {
+            ids: true,
+            ..Default::default()
+        };
+        convert(&input, &output, options, ()).unwrap();
+
+        let tar = dir.path().join("output.tar");
+        osmflat::pack_tar_archive(&output, &tar).unwrap();
+        let archive = osmflat::Osm::open(FileResourceStorage::new(&output)).unwrap();
+        let packed =
+            osmflat::Osm::open(osmflat::TarArchiveResourceStorage::new(&tar).unwrap()).unwrap();
+        assert_eq!(packed.nodes(), archive.nodes());
+        assert_eq!(packed.ways(), archive.ways());
+        assert_eq!(
+            packed.stringtable().as_bytes(),
+            archive.stringtable().as_bytes()
+        );
+        assert_eq!(
+            packed.ids().unwrap().nodes(),
+            archive.ids().unwrap().nodes()
+        );
+
+        // deterministic
+        let tar2 = dir.path().join("output2.tar");
+        osmflat::pack_tar_archive(&output, &tar2).unwrap();
+        assert_eq!(std::fs::read(&tar).unwrap(), std::fs::read(&tar2).unwrap());
+    }
+
+    #[test]
+    fn test_verify() {
+        let opl = "n1 v1 Tname=a x1 y1\nn2 v1 x2 y2\nn3 v1 Tb=c x3 y3\n\
+                   w4 v1 Nn1,n2,n5\nw5 v1 Thighway=primary Nn2,n3\n\
+                   r6 v1 Ttype=route Mn1@stop,w5@,r7@sub\nr7 v1 Mw4@outer\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+        let output = dir.path().join("output");
+        let options = Options {
+            verify: true,
+            ..Default::default()
+        };
+        let stats = convert(&input, &output, options.clone(), ()).unwrap();
+        let violations = stats.violations.unwrap();
+        assert!(violations.is_valid(), "{violations}");
+
+        let output = dir.path().join("sorted");
+        let options = Options {
+            sort: NodeOrder::Hilbert,
+            ..options
+        };
+        let stats = convert(&input, &output, options, ()).unwrap();
+        let violations = stats.violations.unwrap();
+        assert!(violations.is_valid(), "{violations}");
+    }
+
+    #[test]
+    fn test_replace_archive() {
+        let opl = "n1 v1 Tname=a x0 y0\nn2 v1 x1 y0\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+        let output = dir.path().join("output");
+        let options = Options {
+            force: true,
+            ..Default::default()
+        };
+
+        // files of the user next to the output are not touched
+        let tmp = with_suffix(&output, ".tmp");
+        std::fs::create_dir(&tmp).unwrap();
+        std::fs::write(tmp.join("data"), b"").unwrap();
+        convert(&input, &output, options.clone(), ()).unwrap();
+        assert!(tmp.join("data").exists());
+        let entries = std::fs::read_dir(dir.path()).unwrap().count();
+        assert_eq!(entries, 3);
+
+        let old = with_suffix(&output, ".old");
+        std::fs::create_dir(&old).unwrap();
+        std::fs::write(old.join("data"), b"").unwrap();
+        let err = convert(&input, &output, options.clone(), ()).unwrap_err();
+        assert!(
+            err.to_string().contains("is not an osmflat archive"),
+            "{err}"
+        );
+        assert!(old.join("data").exists());
+        std::fs::remove_dir_all(&old).unwrap();
+
+        // interrupted between moving the old archive away and the new one in
+        std::fs::rename(&output, &old).unwrap();
+        recover_archive(&output).unwrap();
+        assert!(!old.exists());
+        assert_eq!(
+            osmflat::Osm::open(FileResourceStorage::new(&output))
+                .unwrap()
+                .nodes()
+                .len(),
+            2
+        );
+
+        // interrupted before removing the old archive
+        let copy = dir.path().join("copy");
+        convert(&input, &copy, options.clone(), ()).unwrap();
+        std::fs::rename(&copy, &old).unwrap();
+        convert(&input, &output, options, ()).unwrap();
+        assert!(!old.exists());
+    }
+}
@@ -0,0 +1,180 @@
+//! Elevation of the nodes sampled from SRTM tiles.
+//!
+//! A directory of tiles in the SRTM `.hgt` format is read, e.g. SRTM 1 or 3
+//! arc-second tiles, or Copernicus DEM tiles converted with
+//! `gdal_translate -of SRTMHGT`. A tile covers one degree of latitude and
+//! longitude and is named after its south west corner, e.g. `N52E013.hgt`.
+
+use osmflat::{Osm, OsmBuilder, NO_ELEVATION};
+
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Maximal number of tiles kept in memory; a tile with 1 arc-second
+/// resolution takes 26 MB.
+const MAX_CACHED_TILES: usize = 16;
+
+/// Elevation samples of a tile from north to south, and west to east, with
+/// `size * size` samples.
+struct Tile {
+    size: usize,
+    samples: Vec<i16>,
+}
+
+impl Tile {
+    fn read(path: &Path) -> io::Result<Option<Self>> {
+        let data = match std::fs::read(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let size = ((data.len() / 2) as f64).sqrt() as usize;
+        if size < 2 || size * size * 2 != data.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} is not a tile in SRTM format", path.display()),
+            ));
+        }
+        let samples = data
+            .chunks_exact(2)
+            .map(|b| i16::from_be_bytes([b[0], b[1]]))
+            .collect();
+        Ok(Some(Self { size, samples }))
+    }
+
+    /// Samples the elevation by bilinear interpolation at the offsets of
+    /// `(lon, lat)` from the south west corner of the tile in degrees.
+    fn sample(&self, lon: f64, lat: f64) -> Option<i16> {
+        let max = (self.size - 1) as f64;
+        let x = lon * max;
+        let y = (1.0 - lat) * max;
+        let (col, row) = (
+            (x.floor() as usize).min(self.size - 2),
+            (y.floor() as usize).min(self.size - 2),
+        );
+        let (dx, dy) = (x - col as f64, y - row as f64);
+        let corners = [
+            (row, col, (1.0 - dx) * (1.0 - dy)),
+            (row, col + 1, dx * (1.0 - dy)),
+            (row + 1, col, (1.0 - dx) * dy),
+            (row + 1, col + 1, dx * dy),
+        ];
+        let mut elevation = 0.0;
+        // voids only matter at the samples contributing to the elevation
+        for (row, col, weight) in corners.into_iter().filter(|&(_, _, w)| w > 0.0) {
+            let sample = self.samples[row * self.size + col];
+            if sample == NO_ELEVATION {
+                return None;
+            }
+            elevation += weight * f64::from(sample);
+        }
+        Some(elevation.round() as i16)
+    }
+}
+
+/// Name of the tile with the south west corner at `(lon, lat)`.
+fn tile_name(lon: i32, lat: i32) -> String {
+    format!(
+        "{}{:02}{}{:03}.hgt",
+        if lat < 0 { 'S' } else { 'N' },
+        lat.unsigned_abs(),
+        if lon < 0 { 'W' } else { 'E' },
+        lon.unsigned_abs()
+    )
+}
+
+/// Tiles of a directory, read on first use.
+pub struct ElevationModel {
+    dir: PathBuf,
+    tiles: HashMap<(i32, i32), Option<Tile>>,
+}
+
+impl ElevationModel {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            tiles: HashMap::new(),
+        }
+    }
+
+    /// Returns the elevation at `(lon, lat)` in degrees, or `None` if there is
+    /// no tile, or the tile has a void there.
+    pub fn elevation(&mut self, lon: f64, lat: f64) -> io::Result<Option<i16>> {
+        let corner = (lon.floor() as i32, lat.floor() as i32);
+        if !self.tiles.contains_key(&corner) {
+            if self.tiles.len() >= MAX_CACHED_TILES {
+                self.tiles.clear();
+            }
+            let tile = Tile::read(&self.dir.join(tile_name(corner.0, corner.1)))?;
+            self.tiles.insert(corner, tile);
+        }
+        Ok(self.tiles[&corner]
+            .as_ref()
+            .and_then(|tile| tile.sample(lon - f64::from(corner.0), lat - f64::from(corner.1))))
+    }
+}
+
+/// Stores the elevation of each node of `archive` in the `elevation` vector,
+/// sampled from the tiles in `dir`.
+///
+/// Returns the number of nodes without elevation.
+pub fn serialize_elevation(archive: &Osm, builder: &OsmBuilder, dir: &Path) -> io::Result<usize> {
+    let mut model = ElevationModel::new(dir);
+    let mut elevation = builder.start_elevation()?;
+    let mut num_unknown = 0;
+    for idx in 0..archive.nodes().len() {
+        let (lon, lat) = archive.node_coord(idx);
+        let value = model.elevation(lon, lat)?;
+        num_unknown += usize::from(value.is_none());
+        elevation.grow()?.set_value(value);
+    }
+    elevation.close().map_err(io::Error::other)?;
+    Ok(num_unknown)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Writes a tile with 3x3 samples, given from north to south.
+    fn write_tile(dir: &Path, name: &str, samples: [i16; 9]) {
+        let data: Vec<u8> = samples.iter().flat_map(|s| s.to_be_bytes()).collect();
+        std::fs::write(dir.join(name), data).unwrap();
+    }
+
+    #[test]
+    fn test_tile_name() {
+        assert_eq!(tile_name(13, 52), "N52E013.hgt");
+        assert_eq!(tile_name(-1, -34), "S34W001.hgt");
+    }
+
+    #[test]
+    fn test_elevation() {
+        let dir = tempfile::tempdir().unwrap();
+        #[rustfmt::skip]
+        write_tile(dir.path(), "N52E013.hgt", [
+            100, 200, 300,
+            100, 200, NO_ELEVATION,
+            0, 0, 0,
+        ]);
+        std::fs::write(dir.path().join("N00E000.hgt"), [0; 3]).unwrap();
+
+        let mut model = ElevationModel::new(dir.path());
+        // corners and center of the tile; the northern edge belongs to the
+        // next tile
+        assert_eq!(model.elevation(13.0, 52.999_999).unwrap(), Some(100));
+        assert_eq!(model.elevation(13.0, 53.0).unwrap(), None);
+        assert_eq!(model.elevation(13.0, 52.0).unwrap(), Some(0));
+        assert_eq!(model.elevation(13.5, 52.5).unwrap(), Some(200));
+        // interpolated
+        assert_eq!(model.elevation(13.25, 52.75).unwrap(), Some(150));
+        assert_eq!(model.elevation(13.25, 52.25).unwrap(), Some(75));
+        // next to a void
+        assert_eq!(model.elevation(13.75, 52.75).unwrap(), None);
+        // missing tile
+        assert_eq!(model.elevation(14.5, 52.5).unwrap(), None);
+        // invalid tile
+        assert!(model.elevation(0.5, 0.5).is_err());
+    }
+}
@@ -0,0 +1,352 @@
+//! Reconstruction of OSM pbf files from archives.
+//!
+//! The entities are written in the order of the archive in blocks of dense
+//! nodes, ways, and relations. The OSM ids are taken from the `ids`
+//! subarchive; without it, the index of an entity plus one is used as its id.
+//! Versions are taken from the `history` subarchive if present. Unresolved
+//! references, which are not contained in the archive, are dropped.
+
+use crate::compress;
+use crate::osmpbf;
+use crate::Error;
+
+use ahash::AHashMap;
+use flate2::write::ZlibEncoder;
+use osmflat::{Id, Osm, RelationMembersRef, Version};
+use prost::Message;
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+
+/// Maximal number of entities in a block
+const BLOCK_SIZE: usize = 8000;
+
+/// Header block with the bounding box, source and replication state of the
+/// archive.
+pub fn header_block(archive: &Osm) -> osmpbf::HeaderBlock {
+    let header = archive.header();
+    let nanodegrees = |x: i32| i64::from(x) * (1_000_000_000 / i64::from(header.coord_scale()));
+    let bbox = [
+        header.bbox_left(),
+        header.bbox_right(),
+        header.bbox_top(),
+        header.bbox_bottom(),
+    ];
+    osmpbf::HeaderBlock {
+        bbox: bbox.iter().any(|&x| x != 0).then(|| osmpbf::HeaderBBox {
+            left: nanodegrees(header.bbox_left()),
+            right: nanodegrees(header.bbox_right()),
+            top: nanodegrees(header.bbox_top()),
+            bottom: nanodegrees(header.bbox_bottom()),
+        }),
+        required_features: vec!["OsmSchema-V0.6".into(), "DenseNodes".into()],
+        writingprogram: Some("osmflatc".into()),
+        source: archive.header_string(header.source_idx()),
+        osmosis_replication_timestamp: Some(header.replication_timestamp())
+            .filter(|&timestamp| timestamp != 0),
+        osmosis_replication_sequence_number: Some(header.replication_sequence_number())
+            .filter(|&number| number != 0),
+        osmosis_replication_base_url: archive.header_string(header.replication_base_url_idx()),
+        ..Default::default()
+    }
+}
+
+pub fn write_blob(out: &mut impl Write, blob_type: &str, data: &[u8]) -> io::Result<()> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Default::default());
+    encoder.write_all(data)?;
+    let blob = osmpbf::Blob {
+        raw_size: Some(data.len() as i32),
+        zlib_data: Some(encoder.finish()?),
+        ..Default::default()
+    }
+    .encode_to_vec();
+    let header = osmpbf::BlobHeader {
+        r#type: blob_type.into(),
+        indexdata: None,
+        datasize: blob.len() as i32,
+    }
+    .encode_to_vec();
+    out.write_all(&(header.len() as u32).to_be_bytes())?;
+    out.write_all(&header)?;
+    out.write_all(&blob)
+}
+
+/// Stringtable of a block, built from strings of the archive.
+struct BlockStrings<'a> {
+    archive: &'a Osm,
+    strings: Vec<Vec<u8>>,
+    /// Index in `strings` by index in the stringtable of the archive
+    indexes: AHashMap<u64, u32>,
+}
+
+impl<'a> BlockStrings<'a> {
+    fn new(archive: &'a Osm) -> Self {
+        Self {
+            archive,
+            // index 0 is reserved as delimiter
+            strings: vec![Vec::new()],
+            indexes: AHashMap::new(),
+        }
+    }
+
+    fn get(&mut self, idx: u64) -> u32 {
+        *self.indexes.entry(idx).or_insert_with(|| {
+            let s = self.archive.stringtable().substring_raw(idx as usize);
+            self.strings.push(s.to_vec());
+            (self.strings.len() - 1) as u32
+        })
+    }
+
+    /// Keys and values of the tags in `range`.
+    fn tags(&mut self, range: Range<u64>) -> (Vec<u32>, Vec<u32>) {
+        let tags = self.archive.tags();
+        let tags_index = self.archive.tags_index();
+        range
+            .map(|idx| {
+                let tag = &tags[tags_index[idx as usize].value() as usize];
+                (self.get(tag.key_idx()), self.get(tag.value_idx()))
+            })
+            .unzip()
+    }
+
+    fn into_block(self, granularity: i32, group: osmpbf::PrimitiveGroup) -> osmpbf::PrimitiveBlock {
+        osmpbf::PrimitiveBlock {
+            stringtable: osmpbf::StringTable { s: self.strings },
+            primitivegroup: vec![group],
+            granularity: Some(granularity),
+            ..Default::default()
+        }
+    }
+}
+
+fn entity_id(ids: Option<&[Id]>, idx: usize) -> i64 {
+    ids.map_or(idx as i64 + 1, |ids| ids[idx].signed_value())
+}
+
+fn info(versions: Option<&[Version]>, idx: usize) -> Option<osmpbf::Info> {
+    versions.map(|versions| osmpbf::Info {
+        version: Some(versions[idx].version() as i32),
+        timestamp: Some(versions[idx].timestamp()),
+        visible: Some(versions[idx].visible()),
+        ..Default::default()
+    })
+}
+
+fn delta(values: impl Iterator<Item = i64>) -> Vec<i64> {
+    let mut last = 0;
+    values
+        .map(|value| {
+            let delta = value - last;
+            last = value;
+            delta
+        })
+        .collect()
+}
+
+/// Builds blocks from the entities of an archive given by their indexes.
+pub struct Exporter<'a> {
+    archive: &'a Osm,
+    granularity: i32,
+    node_ids: Option<&'a [Id]>,
+    way_ids: Option<&'a [Id]>,
+    relation_ids: Option<&'a [Id]>,
+}
+
+impl<'a> Exporter<'a> {
+    pub fn new(archive: &'a Osm) -> Result<Self, Error> {
+        let coord_scale = archive.header().coord_scale();
+        if coord_scale <= 0 || 1_000_000_000 % coord_scale != 0 {
+            return Err(format!("unsupported coordinate scale: {coord_scale}").into());
+        }
+        let ids = archive.ids();
+        Ok(Self {
+            archive,
+            granularity: 1_000_000_000 / coord_scale,
+            node_ids: ids.map(|ids| ids.nodes()),
+            way_ids: ids.map(|ids| ids.ways()),
+            relation_ids: ids.map(|ids| ids.relations()),
+        })
+    }
+
+    pub fn dense_nodes_block(
+        &self,
+        indexes: impl Iterator<Item = usize> + Clone,
+    ) -> osmpbf::PrimitiveBlock {
+        let nodes = self.archive.nodes();
+        let mut strings = BlockStrings::new(self.archive);
+        let mut keys_vals = Vec::new();
+        for idx in indexes.clone() {
+            let (keys, vals) = strings.tags(nodes[idx].tags());
+            for (key, val) in keys.into_iter().zip(vals) {
+                keys_vals.extend([key as i32, val as i32]);
+            }
+            keys_vals.push(0);
+        }
+        let denseinfo = self.archive.history().map(|history| {
+            let versions: Vec<_> = indexes.clone().map(|idx| &history.nodes()[idx]).collect();
+            osmpbf::DenseInfo {
+                version: versions.iter().map(|v| v.version() as i32).collect(),
+                timestamp: delta(versions.iter().map(|v| v.timestamp())),
+                changeset: vec![0; versions.len()],
+                uid: vec![0; versions.len()],
+                user_sid: vec![0; versions.len()],
+                visible: versions.iter().map(|v| v.visible()).collect(),
+            }
+        });
+        let dense = osmpbf::DenseNodes {
+            id: delta(indexes.clone().map(|idx| entity_id(self.node_ids, idx))),
+            denseinfo,
+            lat: delta(indexes.clone().map(|idx| i64::from(nodes[idx].lat()))),
+            lon: delta(indexes.map(|idx| i64::from(nodes[idx].lon()))),
+            keys_vals,
+        };
+        strings.into_block(
+            self.granularity,
+            osmpbf::PrimitiveGroup {
+                dense: Some(dense),
+                ..Default::default()
+            },
+        )
+    }
+
+    pub fn ways_block(&self, indexes: impl Iterator<Item = usize>) -> osmpbf::PrimitiveBlock {
+        let nodes_index = self.archive.nodes_index();
+        let versions = self.archive.history().map(|history| history.ways());
+        let mut strings = BlockStrings::new(self.archive);
+        let ways = indexes
+            .map(|idx| {
+                let way = &self.archive.ways()[idx];
+                let (keys, vals) = strings.tags(way.tags());
+                let refs = way
+                    .refs()
+                    .filter_map(|i| nodes_index[i as usize].value())
+                    .map(|node_idx| entity_id(self.node_ids, node_idx as usize));
+                osmpbf::Way {
+                    id: entity_id(self.way_ids, idx),
+                    keys,
+                    vals,
+                    info: info(versions, idx),
+                    refs: delta(refs),
+                }
+            })
+            .collect();
+        strings.into_block(
+            self.granularity,
+            osmpbf::PrimitiveGroup {
+                ways,
+                ..Default::default()
+            },
+        )
+    }
+
+    pub fn relations_block(&self, indexes: impl Iterator<Item = usize>) -> osmpbf::PrimitiveBlock {
+        use osmpbf::relation::MemberType;
+
+        let relation_members = self.archive.relation_members();
+        let versions = self.archive.history().map(|history| history.relations());
+        let mut strings = BlockStrings::new(self.archive);
+        let relations = indexes
+            .map(|idx| {
+                let relation = &self.archive.relations()[idx];
+                let (keys, vals) = strings.tags(relation.tags());
+                let mut roles_sid = Vec::new();
+                let mut memids = Vec::new();
+                let mut types = Vec::new();
+                for member in relation_members.at(idx) {
+                    let (member, role_idx) = match member {
+                        RelationMembersRef::NodeMember(m) => (
+                            m.node_idx()
+                                .map(|i| (entity_id(self.node_ids, i as usize), MemberType::Node)),
+                            m.role_idx(),
+                        ),
+                        RelationMembersRef::WayMember(m) => (
+                            m.way_idx()
+                                .map(|i| (entity_id(self.way_ids, i as usize), MemberType::Way)),
+                            m.role_idx(),
+                        ),
+                        RelationMembersRef::RelationMember(m) => (
+                            m.relation_idx().map(|i| {
+                                (
+                                    entity_id(self.relation_ids, i as usize),
+                                    MemberType::Relation,
+                                )
+                            }),
+                            m.role_idx(),
+                        ),
+                    };
+                    if let Some((id, member_type)) = member {
+                        roles_sid.push(strings.get(role_idx) as i32);
+                        memids.push(id);
+                        types.push(member_type as i32);
+                    }
+                }
+                osmpbf::Relation {
+                    id: entity_id(self.relation_ids, idx),
+                    keys,
+                    vals,
+                    info: info(versions, idx),
+                    roles_sid,
+                    memids: delta(memids.into_iter()),
+                    types,
+                }
+            })
+            .collect();
+        strings.into_block(
+            self.granularity,
+            osmpbf::PrimitiveGroup {
+                relations,
+                ..Default::default()
+            },
+        )
+    }
+}
+
+/// Writes the entities `0..len` in blocks.
+pub fn write_blocks(
+    out: &mut impl Write,
+    len: usize,
+    mut block: impl FnMut(Range<usize>) -> osmpbf::PrimitiveBlock,
+) -> io::Result<()> {
+    for start in (0..len).step_by(BLOCK_SIZE) {
+        let block = block(start..(start + BLOCK_SIZE).min(len));
+        write_blob(out, "OSMData", &block.encode_to_vec())?;
+    }
+    Ok(())
+}
+
+/// Writes the entities of the archive at `archive` as OSM pbf file `output`.
+pub fn export_pbf(archive: &Path, output: &Path) -> Result<(), Error> {
+    let archive = compress::open_archive(archive)?;
+    let exporter = Exporter::new(&archive)?;
+
+    let mut out = BufWriter::new(File::create(output)?);
+    write_blob(
+        &mut out,
+        "OSMHeader",
+        &header_block(&archive).encode_to_vec(),
+    )?;
+    write_blocks(&mut out, archive.nodes().len(), |range| {
+        exporter.dense_nodes_block(range)
+    })?;
+    write_blocks(&mut out, archive.ways().len(), |range| {
+        exporter.ways_block(range)
+    })?;
+    write_blocks(&mut out, archive.relations().len(), |range| {
+        exporter.relations_block(range)
+    })?;
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_delta() {
+        assert_eq!(delta([3, 5, 4, 10].into_iter()), [3, 2, -1, 6]);
+        assert!(delta(std::iter::empty()).is_empty());
+    }
+}
@@ -0,0 +1,362 @@
+//! Extraction of the entities in a region from an archive into a new archive.
+//!
+//! Like [`split_tiles`], the selected entities are exported to a temporary OSM
+//! pbf file, which is converted to the archive of the extract. All indexes are
+//! therefore remapped, while the OSM ids are kept.
+//!
+//! [`split_tiles`]: crate::split_tiles
+
+use crate::compress;
+use crate::convert::{self, Options};
+use crate::export::{self, Exporter};
+use crate::osmpbf;
+use crate::stats::Stats;
+use crate::tiles::{write_tile_pbf, TileEntities};
+use crate::Error;
+
+use osmflat::{Osm, RelationMembersRef};
+use serde_json::Value;
+
+use std::fs;
+use std::io::BufWriter;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Region of an extract given in degrees.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Region {
+    /// Bounding box given by its minimal and maximal `(lon, lat)`
+    BBox((f64, f64), (f64, f64)),
+    /// Area enclosed by closed rings of `(lon, lat)`
+    ///
+    /// A point is inside of the area if it is inside of an odd number of
+    /// rings, so that holes are rings inside of outer rings.
+    Polygon(Vec<Vec<(f64, f64)>>),
+}
+
+impl FromStr for Region {
+    type Err = String;
+
+    /// Parses a bounding box `min_lon,min_lat,max_lon,max_lat`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values: Vec<f64> = s
+            .split(',')
+            .map(|v| v.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("invalid bounding box '{s}': {e}"))?;
+        match values[..] {
+            [min_lon, min_lat, max_lon, max_lat] if min_lon < max_lon && min_lat < max_lat => {
+                Ok(Self::BBox((min_lon, min_lat), (max_lon, max_lat)))
+            }
+            _ => Err(format!(
+                "invalid bounding box '{s}', expected min_lon,min_lat,max_lon,max_lat"
+            )),
+        }
+    }
+}
+
+impl Region {
+    /// Reads a polygon from an Osmosis polygon file if the extension is
+    /// `poly`, and from a GeoJSON file otherwise.
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        let content = fs::read_to_string(path)?;
+        let rings = if path.extension().is_some_and(|ext| ext == "poly") {
+            parse_poly(&content)?
+        } else {
+            parse_geojson(&serde_json::from_str(&content)?)?
+        };
+        if rings.is_empty() {
+            return Err(format!("{} contains no polygon", path.display()).into());
+        }
+        Ok(Self::Polygon(rings))
+    }
+
+    /// Minimal and maximal `(lon, lat)` of the region.
+    fn bounds(&self) -> ((f64, f64), (f64, f64)) {
+        match self {
+            Self::BBox(min, max) => (*min, *max),
+            Self::Polygon(rings) => rings.iter().flatten().fold(
+                ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN)),
+                |((min_x, min_y), (max_x, max_y)), &(x, y)| {
+                    ((min_x.min(x), min_y.min(y)), (max_x.max(x), max_y.max(y)))
+                },
+            ),
+        }
+    }
+
+    /// Whether the region contains the point `(lon, lat)` inside of its
+    /// bounds.
+    fn contains_in_bounds(&self, (x, y): (f64, f64)) -> bool {
+        match self {
+            Self::BBox(..) => true,
+            Self::Polygon(rings) => {
+                let crossings = rings
+                    .iter()
+                    .flat_map(|ring| ring.iter().zip(ring.iter().cycle().skip(1)))
+                    .filter(|&(&(x0, y0), &(x1, y1))| {
+                        (y0 > y) != (y1 > y) && x < (x1 - x0) * (y - y0) / (y1 - y0) + x0
+                    })
+                    .count();
+                crossings % 2 == 1
+            }
+        }
+    }
+}
+
+/// Parses the rings of an Osmosis polygon file.
+///
+/// The file consists of a name line followed by sections of a name line,
+/// lines of `lon lat` and a line `END`; the file ends with another `END`.
+/// Sections whose name starts with `!` are holes.
+fn parse_poly(content: &str) -> Result<Vec<Vec<(f64, f64)>>, Error> {
+    let mut rings = Vec::new();
+    let mut lines = content.lines().map(str::trim).filter(|l| !l.is_empty());
+    lines.next().ok_or("empty polygon file")?;
+    while let Some(name) = lines.next() {
+        if name == "END" {
+            return Ok(rings);
+        }
+        let mut ring = Vec::new();
+        loop {
+            let line = lines.next().ok_or("unexpected end of polygon file")?;
+            if line == "END" {
+                break;
+            }
+            let coords: Vec<f64> = line
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .map_err(|e| format!("invalid coordinates '{line}': {e}"))?;
+            match coords[..] {
+                [lon, lat] => ring.push((lon, lat)),
+                _ => return Err(format!("invalid coordinates '{line}'").into()),
+            }
+        }
+        rings.push(ring);
+    }
+    Err("missing END at the end of polygon file".into())
+}
+
+/// Parses the rings of a GeoJSON `Polygon` or `MultiPolygon`, or of all
+/// polygons of a `Feature` or `FeatureCollection`.
+fn parse_geojson(value: &Value) -> Result<Vec<Vec<(f64, f64)>>, Error> {
+    let invalid = || format!("invalid GeoJSON geometry: {value}");
+    let ring = |ring: &Value| -> Option<Vec<(f64, f64)>> {
+        ring.as_array()?
+            .iter()
+            .map(|coord| Some((coord.get(0)?.as_f64()?, coord.get(1)?.as_f64()?)))
+            .collect()
+    };
+    let polygon = |polygon: &Value| -> Option<Vec<Vec<(f64, f64)>>> {
+        polygon.as_array()?.iter().map(ring).collect()
+    };
+    match value.get("type").and_then(Value::as_str) {
+        Some("Polygon") => polygon(&value["coordinates"]).ok_or_else(|| invalid().into()),
+        Some("MultiPolygon") => {
+            let polygons = value["coordinates"].as_array().ok_or_else(invalid)?;
+            let rings: Option<Vec<_>> = polygons.iter().map(polygon).collect();
+            Ok(rings.ok_or_else(invalid)?.concat())
+        }
+        Some("Feature") => parse_geojson(&value["geometry"]),
+        Some("FeatureCollection") => {
+            let features = value["features"].as_array().ok_or_else(invalid)?;
+            let rings: Result<Vec<_>, _> = features.iter().map(parse_geojson).collect();
+            Ok(rings?.concat())
+        }
+        _ => Err(invalid().into()),
+    }
+}
+
+/// Selects the entities of `archive` in `region`.
+///
+/// The extract contains the nodes in the region, the ways with a node in the
+/// region together with all their nodes, and the relations with a selected
+/// node, way or relation as member.
+fn select(archive: &Osm, region: &Region) -> TileEntities {
+    let coords = archive.coord_reader();
+    let ((min_lon, min_lat), (max_lon, max_lat)) = region.bounds();
+    let mut node_selected: Vec<bool> = archive
+        .nodes()
+        .iter()
+        .map(|node| {
+            let (lon, lat) = (coords.lon(node), coords.lat(node));
+            (min_lon..=max_lon).contains(&lon)
+                && (min_lat..=max_lat).contains(&lat)
+                && region.contains_in_bounds((lon, lat))
+        })
+        .collect();
+
+    let nodes_index = archive.nodes_index();
+    let way_nodes = |way: &osmflat::Way| {
+        way.refs()
+            .filter_map(|i| nodes_index[i as usize].value())
+            .map(|node_idx| node_idx as usize)
+    };
+    let way_selected: Vec<bool> = archive
+        .ways()
+        .iter()
+        .map(|way| way_nodes(way).any(|node_idx| node_selected[node_idx]))
+        .collect();
+    for (way, _) in archive.ways().iter().zip(&way_selected).filter(|(_, &s)| s) {
+        for node_idx in way_nodes(way) {
+            node_selected[node_idx] = true;
+        }
+    }
+
+    // relations may be members of relations, which are selected with them
+    let relation_members = archive.relation_members();
+    let mut relation_selected = vec![false; archive.relations().len()];
+    loop {
+        let mut changed = false;
+        for idx in 0..relation_selected.len() {
+            if relation_selected[idx] {
+                continue;
+            }
+            let selected = relation_members.at(idx).any(|member| match member {
+                RelationMembersRef::NodeMember(m) => {
+                    m.node_idx().is_some_and(|i| node_selected[i as usize])
+                }
+                RelationMembersRef::WayMember(m) => {
+                    m.way_idx().is_some_and(|i| way_selected[i as usize])
+                }
+                RelationMembersRef::RelationMember(m) => m
+                    .relation_idx()
+                    .is_some_and(|i| relation_selected[i as usize]),
+            });
+            if selected {
+                relation_selected[idx] = true;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let indexes = |selected: &[bool]| {
+        (selected.iter().enumerate())
+            .filter_map(|(idx, &s)| s.then_some(idx))
+            .collect()
+    };
+    TileEntities {
+        nodes: indexes(&node_selected),
+        ways: indexes(&way_selected),
+        relations: indexes(&relation_selected),
+    }
+}
+
+/// Extracts the entities of the archive at `archive` in `region` into a new
+/// archive with the `ids` subarchive at `output`, cf. [`select`].
+///
+/// Members of the selected relations outside of the region are unresolved in
+/// the extract. The output directory is replaced if `force` is set.
+pub fn extract(
+    archive: &Path,
+    output: &Path,
+    region: &Region,
+    force: bool,
+) -> Result<Stats, Error> {
+    let archive = compress::open_archive(archive)?;
+    let exporter = Exporter::new(&archive)?;
+    let ((min_lon, min_lat), (max_lon, max_lat)) = region.bounds();
+    let nanodegrees = |x: f64| (x * 1e9).round() as i64;
+    let header = osmpbf::HeaderBlock {
+        bbox: Some(osmpbf::HeaderBBox {
+            left: nanodegrees(min_lon),
+            right: nanodegrees(max_lon),
+            top: nanodegrees(max_lat),
+            bottom: nanodegrees(min_lat),
+        }),
+        ..export::header_block(&archive)
+    };
+
+    let entities = select(&archive, region);
+    let mut pbf_file = tempfile::Builder::new().suffix(".osm.pbf").tempfile()?;
+    {
+        let mut out = BufWriter::new(pbf_file.as_file_mut());
+        write_tile_pbf(&mut out, &exporter, &header, &entities)?;
+    }
+
+    let options = Options {
+        ids: true,
+        coord_scale: Some(archive.header().coord_scale()),
+        force,
+        ..Default::default()
+    };
+    convert::convert(pbf_file.path(), output, options, ())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use flatdata::FileResourceStorage;
+
+    #[test]
+    fn test_parse_region() {
+        assert_eq!(
+            "13.3,52.4,13.5,52.6".parse(),
+            Ok(Region::BBox((13.3, 52.4), (13.5, 52.6)))
+        );
+        assert!("13.5,52.4,13.3,52.6".parse::<Region>().is_err());
+        assert!("13.3,52.4,13.5".parse::<Region>().is_err());
+
+        let poly = "berlin\nouter\n  0 0\n  4 0\n  4 4\n  0 4\nEND\n!hole\n  1 1\n  3 1\n  \
+                    3 3\n  1 3\nEND\nEND\n";
+        let rings = parse_poly(poly).unwrap();
+        assert_eq!(rings.len(), 2);
+        assert_eq!(rings[1][2], (3.0, 3.0));
+        assert!(parse_poly("berlin\nouter\n  0 0\n").is_err());
+
+        let region = Region::Polygon(rings);
+        assert!(region.contains_in_bounds((0.5, 2.0)));
+        assert!(!region.contains_in_bounds((2.0, 2.0)));
+        assert!(!region.contains_in_bounds((3.5, 4.5)));
+
+        let geojson: Value = serde_json::from_str(
+            r#"{"type": "Feature", "geometry": {"type": "MultiPolygon", "coordinates":
+                [[[[0, 0], [1, 0], [1, 1], [0, 0]]], [[[5, 5], [6, 5], [6, 6], [5, 5]]]]}}"#,
+        )
+        .unwrap();
+        let rings = parse_geojson(&geojson).unwrap();
+        assert_eq!(rings.len(), 2);
+        assert_eq!(rings[1][1], (6.0, 5.0));
+        assert!(parse_geojson(&serde_json::json!({"type": "Point"})).is_err());
+    }
+
+    #[test]
+    fn test_extract() {
+        let opl = "n1 v1 x0 y0\nn2 v1 x1 y1\nn3 v1 x5 y5\nn4 v1 x9 y9 Tamenity=cafe\n\
+                   w10 v1 Thighway=primary Nn2,n3\nw11 v1 Nn3,n4\n\
+                   r20 v1 Ttype=route Mw10@,w11@\nr21 v1 Ttype=super Mr20@\nr22 v1 Mn4@\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+        let archive = dir.path().join("archive");
+        let options = Options {
+            ids: true,
+            ..Default::default()
+        };
+        convert::convert(&input, &archive, options, ()).unwrap();
+
+        let output = dir.path().join("extract");
+        let region = Region::BBox((-0.5, -0.5), (2.0, 2.0));
+        let stats = extract(&archive, &output, &region, false).unwrap();
+        assert_eq!(
+            (stats.num_nodes, stats.num_ways, stats.num_relations),
+            (3, 1, 2)
+        );
+
+        let extract = Osm::open(FileResourceStorage::new(output)).unwrap();
+        let ids = extract.ids().unwrap();
+        let node_ids: Vec<_> = ids.nodes().iter().map(|id| id.value()).collect();
+        assert_eq!(node_ids, [1, 2, 3]);
+        assert_eq!(ids.ways()[0].value(), 10);
+        let relation_ids: Vec<_> = ids.relations().iter().map(|id| id.value()).collect();
+        assert_eq!(relation_ids, [20, 21]);
+        let header = extract.header();
+        assert_eq!(
+            (header.bbox_left(), header.bbox_top()),
+            (-5_000_000, 20_000_000)
+        );
+    }
+}
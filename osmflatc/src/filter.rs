@@ -0,0 +1,362 @@
+use crate::osmpbf;
+
+use ahash::AHashSet;
+use osmflat::EntityType;
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Expression selecting entities by their tags.
+///
+/// The syntax follows `osmium tags-filter`: an optional prefix of entity types
+/// (`n`, `w`, `r`) separated by `/`, followed by a key and optionally by `=`
+/// and a comma separated list of values, e.g. `w/highway`,
+/// `n/amenity=restaurant` or `nw/shop=bakery,butcher`. Without prefix, all
+/// entity types are selected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagFilter {
+    types: Vec<EntityType>,
+    key: String,
+    values: Vec<String>,
+}
+
+impl TagFilter {
+    /// Whether an entity of type `entity_type` with `tags` matches the filter.
+    pub fn matches<'a>(
+        &self,
+        entity_type: EntityType,
+        mut tags: impl Iterator<Item = (&'a [u8], &'a [u8])>,
+    ) -> bool {
+        self.types.contains(&entity_type)
+            && tags.any(|(key, value)| {
+                key == self.key.as_bytes()
+                    && (self.values.is_empty() || self.values.iter().any(|v| v.as_bytes() == value))
+            })
+    }
+}
+
+impl FromStr for TagFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (types, tag) = match s.split_once('/') {
+            Some((prefix, tag)) => {
+                let types = prefix
+                    .chars()
+                    .map(|c| match c {
+                        'n' => Ok(EntityType::Node),
+                        'w' => Ok(EntityType::Way),
+                        'r' => Ok(EntityType::Relation),
+                        _ => Err(format!("invalid entity type '{c}' in filter: {s}")),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                (types, tag)
+            }
+            None => (
+                vec![EntityType::Node, EntityType::Way, EntityType::Relation],
+                s,
+            ),
+        };
+        let (key, values) = match tag.split_once('=') {
+            Some((key, values)) => (key, values.split(',').map(String::from).collect()),
+            None => (tag, Vec::new()),
+        };
+        if types.is_empty() || key.is_empty() {
+            return Err(format!("invalid filter: {s}"));
+        }
+        Ok(Self {
+            types,
+            key: key.into(),
+            values,
+        })
+    }
+}
+
+impl fmt::Display for TagFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for entity_type in &self.types {
+            let c = match entity_type {
+                EntityType::Node => 'n',
+                EntityType::Way => 'w',
+                EntityType::Relation => 'r',
+            };
+            write!(f, "{c}")?;
+        }
+        write!(f, "/{}", self.key)?;
+        if !self.values.is_empty() {
+            write!(f, "={}", self.values.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// Glob pattern matching tag keys, in which `*` matches any sequence of
+/// characters, e.g. `created_by` or `tiger:*`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyPattern(String);
+
+impl KeyPattern {
+    /// Whether `key` matches the pattern.
+    pub fn matches(&self, key: &[u8]) -> bool {
+        let mut parts = self.0.as_bytes().split(|&c| c == b'*');
+        let first = parts.next().unwrap_or_default();
+        let Some(mut rest) = key.strip_prefix(first) else {
+            return false;
+        };
+        let mut parts = parts.peekable();
+        while let Some(part) = parts.next() {
+            if parts.peek().is_none() {
+                // the last part has to match the end of the key
+                return rest.ends_with(part);
+            }
+            match rest.windows(part.len()).position(|w| w == part) {
+                Some(pos) => rest = &rest[pos + part.len()..],
+                None => return false,
+            }
+        }
+        // no `*` in the pattern
+        rest.is_empty()
+    }
+}
+
+impl FromStr for KeyPattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("empty tag key pattern".into());
+        }
+        Ok(Self(s.into()))
+    }
+}
+
+impl fmt::Display for KeyPattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Selection of the tags to convert by their keys.
+///
+/// If there are patterns to keep, only tags whose keys match any of them are
+/// converted. Tags whose keys match any of the patterns to drop are not
+/// converted.
+#[derive(Debug, Clone, Default)]
+pub struct TagKeys {
+    keep: Vec<KeyPattern>,
+    drop: Vec<KeyPattern>,
+}
+
+impl TagKeys {
+    pub fn new(keep: Vec<KeyPattern>, drop: Vec<KeyPattern>) -> Self {
+        Self { keep, drop }
+    }
+
+    /// Whether tags with `key` are converted.
+    pub fn contains(&self, key: &[u8]) -> bool {
+        (self.keep.is_empty() || self.keep.iter().any(|pattern| pattern.matches(key)))
+            && !self.drop.iter().any(|pattern| pattern.matches(key))
+    }
+}
+
+/// Selection of the entities and tags to convert.
+///
+/// An entity is selected if it matches any of the filters, or if it is
+/// referenced by a selected entity: nodes of selected ways, and node and way
+/// members of selected relations. Without filters, all entities are selected.
+/// The filters match the tags of the input, including the tags which are not
+/// converted. Removed nodes are not selected in any case.
+#[derive(Debug, Default)]
+pub struct Selection {
+    filters: Vec<TagFilter>,
+    tag_keys: TagKeys,
+    nodes: AHashSet<i64>,
+    ways: AHashSet<i64>,
+    /// Ids of the removed nodes, sorted
+    removed_nodes: Vec<i64>,
+}
+
+impl Selection {
+    pub fn new(filters: Vec<TagFilter>, tag_keys: TagKeys) -> Self {
+        Self {
+            filters,
+            tag_keys,
+            ..Default::default()
+        }
+    }
+
+    /// Whether all entities are selected.
+    pub fn is_all(&self) -> bool {
+        self.filters.is_empty() && self.removed_nodes.is_empty()
+    }
+
+    fn matches(&self, entity_type: EntityType, tags: &[(&[u8], &[u8])]) -> bool {
+        self.filters.is_empty()
+            || self
+                .filters
+                .iter()
+                .any(|filter| filter.matches(entity_type, tags.iter().copied()))
+    }
+
+    pub fn contains_node(&self, id: i64, tags: &[(&[u8], &[u8])]) -> bool {
+        (self.nodes.contains(&id) || self.matches(EntityType::Node, tags))
+            && self.removed_nodes.binary_search(&id).is_err()
+    }
+
+    pub fn contains_way(&self, id: i64, tags: &[(&[u8], &[u8])]) -> bool {
+        self.ways.contains(&id) || self.matches(EntityType::Way, tags)
+    }
+
+    pub fn contains_relation(&self, tags: &[(&[u8], &[u8])]) -> bool {
+        self.matches(EntityType::Relation, tags)
+    }
+
+    /// Whether tags with `key` of selected entities are converted.
+    pub fn contains_tag(&self, key: &[u8]) -> bool {
+        self.tag_keys.contains(key)
+    }
+
+    /// Removes the nodes with `ids` from the selection.
+    pub fn remove_nodes(&mut self, mut ids: Vec<i64>) {
+        ids.sort_unstable();
+        self.removed_nodes = ids;
+    }
+
+    /// Adds the nodes of a selected way.
+    pub fn add_way(&mut self, way: &osmpbf::Way) {
+        let mut node_ref = 0;
+        for delta in &way.refs {
+            node_ref += delta;
+            self.nodes.insert(node_ref);
+        }
+    }
+
+    /// Adds the node and way members of a selected relation.
+    pub fn add_relation(&mut self, relation: &osmpbf::Relation) {
+        let mut memid = 0;
+        for (delta, member_type) in relation.memids.iter().zip(&relation.types) {
+            memid += delta;
+            match osmpbf::relation::MemberType::try_from(*member_type) {
+                Ok(osmpbf::relation::MemberType::Node) => self.nodes.insert(memid),
+                Ok(osmpbf::relation::MemberType::Way) => self.ways.insert(memid),
+                _ => false,
+            };
+        }
+    }
+}
+
+/// Tags of an entity given by the ids of its keys and values in the
+/// stringtable of `block`.
+pub fn block_tags<'a>(
+    block: &'a osmpbf::PrimitiveBlock,
+    keys: &[u32],
+    vals: &[u32],
+) -> Vec<(&'a [u8], &'a [u8])> {
+    let s = &block.stringtable.s;
+    keys.iter()
+        .zip(vals)
+        .map(|(&k, &v)| (&s[k as usize][..], &s[v as usize][..]))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let filter: TagFilter = "w/highway".parse().unwrap();
+        assert_eq!(filter.types, [EntityType::Way]);
+        assert_eq!(filter.key, "highway");
+        assert!(filter.values.is_empty());
+
+        let filter: TagFilter = "nw/shop=bakery,butcher".parse().unwrap();
+        assert_eq!(filter.types, [EntityType::Node, EntityType::Way]);
+        assert_eq!(filter.values, ["bakery", "butcher"]);
+        assert_eq!(filter.to_string(), "nw/shop=bakery,butcher");
+
+        let filter: TagFilter = "amenity".parse().unwrap();
+        assert_eq!(filter.to_string(), "nwr/amenity");
+
+        assert!("x/highway".parse::<TagFilter>().is_err());
+        assert!("/highway".parse::<TagFilter>().is_err());
+        assert!("n/".parse::<TagFilter>().is_err());
+    }
+
+    #[test]
+    fn test_matches() {
+        let filter: TagFilter = "n/amenity=restaurant,cafe".parse().unwrap();
+        let tags: [(&[u8], &[u8]); 2] = [(b"name", b"Luigi"), (b"amenity", b"restaurant")];
+        assert!(filter.matches(EntityType::Node, tags.iter().copied()));
+        assert!(!filter.matches(EntityType::Way, tags.iter().copied()));
+        let tags: [(&[u8], &[u8]); 1] = [(b"amenity", b"bench")];
+        assert!(!filter.matches(EntityType::Node, tags.iter().copied()));
+    }
+
+    #[test]
+    fn test_selection() {
+        let mut selection = Selection::new(vec!["w/highway".parse().unwrap()], TagKeys::default());
+        let highway: [(&[u8], &[u8]); 1] = [(b"highway", b"primary")];
+        assert!(selection.contains_way(1, &highway));
+        assert!(!selection.contains_way(2, &[]));
+        assert!(!selection.contains_node(10, &highway));
+
+        selection.add_way(&osmpbf::Way {
+            id: 1,
+            refs: vec![10, 1, 1],
+            ..Default::default()
+        });
+        assert!(selection.contains_node(10, &[]));
+        assert!(selection.contains_node(12, &[]));
+        assert!(!selection.contains_node(13, &[]));
+
+        assert!(Selection::default().contains_relation(&[]));
+
+        let mut selection = Selection::default();
+        selection.remove_nodes(vec![5, 3]);
+        assert!(!selection.is_all());
+        assert!(!selection.contains_node(3, &[]));
+        assert!(selection.contains_node(4, &[]));
+        assert!(selection.contains_way(3, &[]));
+    }
+
+    #[test]
+    fn test_key_pattern() {
+        let matches = |pattern: &str, key: &str| {
+            KeyPattern::from_str(pattern)
+                .unwrap()
+                .matches(key.as_bytes())
+        };
+        assert!(matches("source", "source"));
+        assert!(!matches("source", "source:date"));
+        assert!(!matches("source", "sourc"));
+        assert!(matches("tiger:*", "tiger:cfcc"));
+        assert!(matches("tiger:*", "tiger:"));
+        assert!(!matches("tiger:*", "name:tiger:x"));
+        assert!(matches("*:source", "name:source"));
+        assert!(!matches("*:source", "name:source:date"));
+        assert!(matches("a*b*c", "abc"));
+        assert!(matches("a*b*c", "a_b_b_c"));
+        assert!(!matches("a*b*c", "ac"));
+        assert!(!matches("ab*ba", "aba"));
+        assert!(matches("*", "anything"));
+        assert!("".parse::<KeyPattern>().is_err());
+
+        let tag_keys = TagKeys::new(
+            Vec::new(),
+            vec!["created_by".parse().unwrap(), "tiger:*".parse().unwrap()],
+        );
+        assert!(!tag_keys.contains(b"created_by"));
+        assert!(!tag_keys.contains(b"tiger:county"));
+        assert!(tag_keys.contains(b"highway"));
+
+        let tag_keys = TagKeys::new(
+            vec!["highway".parse().unwrap(), "name*".parse().unwrap()],
+            vec!["name:*".parse().unwrap()],
+        );
+        assert!(tag_keys.contains(b"highway"));
+        assert!(tag_keys.contains(b"name"));
+        assert!(!tag_keys.contains(b"name:de"));
+        assert!(!tag_keys.contains(b"building"));
+    }
+}
@@ -91,19 +91,28 @@ impl IdBlock {
     }
 }
 
-/// Maps u64 integers to a consecutive range of ids
+/// Maps i64 integers to a consecutive range of ids
+///
+/// Negative ids, which occur in files exported from editors like JOSM, are
+/// expected to be rare; they are stored in a separate sorted table.
 #[derive(Debug)]
 pub struct IdTable {
     // map u64 id x to u32 by storing a sorted mapping table for each value of x / 2^24
     data: Vec<(u64, IdBlock)>,
+    // negative ids sorted by id with their mapped index
+    negative: Vec<(i64, u64)>,
+    // index of the first version of each id, if multiple versions were inserted
+    indices: Vec<u64>,
 }
 
 #[derive(Debug, Default)]
 pub struct IdTableBuilder {
     // stored the same data as IdTable, but still in process of being build
     data: Vec<IdBlock>,
-    last_id: Option<u64>,
+    negative: Vec<(i64, u64)>,
+    last_id: Option<i64>,
     next_id: u64,
+    indices: Vec<u64>,
 }
 
 impl IdTableBuilder {
@@ -112,21 +121,45 @@ impl IdTableBuilder {
     }
 
     /// Inserts an Id and returns a mapped index
-    pub fn insert(&mut self, x: u64) -> u64 {
-        if let Some(last_id) = self.last_id {
-            assert!(last_id < x, "Ids are expected to be sorted");
+    ///
+    /// Non-negative ids are expected to be sorted. Negative ids are expected
+    /// before all non-negative ids, but in any order, since tools disagree on
+    /// how to sort them.
+    pub fn insert(&mut self, x: i64) -> u64 {
+        if x < 0 {
+            assert!(
+                self.last_id.is_none_or(|last_id| last_id < 0),
+                "Negative ids are expected before positive ids"
+            );
+            self.negative.push((x, self.next_id));
+        } else {
+            if let Some(last_id) = self.last_id {
+                assert!(last_id < x, "Ids are expected to be sorted");
+            }
+            let x = x as u64;
+            let id_set = (x >> 24) as usize;
+            if self.data.len() <= id_set {
+                self.data.resize(id_set + 1, IdBlock::Sparse(Vec::new()));
+            }
+            self.data[id_set].insert((x % (1u64 << 24)) as u32);
         }
         self.last_id = Some(x);
-        let id_set = (x >> 24) as usize;
-        if self.data.len() <= id_set {
-            self.data.resize(id_set + 1, IdBlock::Sparse(Vec::new()));
-        }
-        self.data[id_set].insert((x % (1u64 << 24)) as u32);
         let result = self.next_id;
         self.next_id += 1;
         result
     }
 
+    /// Inserts an id of an entity with multiple versions stored at `idx`
+    ///
+    /// The versions of an id are expected to be inserted consecutively. The id
+    /// is mapped to the index of its first version.
+    pub fn insert_version(&mut self, x: i64, idx: u64) {
+        if self.last_id != Some(x) {
+            self.insert(x);
+            self.indices.push(idx);
+        }
+    }
+
     pub fn build(mut self) -> IdTable {
         for ids in &mut self.data {
             ids.finalize();
@@ -134,26 +167,47 @@ impl IdTableBuilder {
         let result = self
             .data
             .into_iter()
-            .scan(0, |state, ids| {
+            .scan(self.negative.len() as u64, |state, ids| {
                 let offset = *state;
                 *state += ids.count() as u64;
                 Some((offset, ids))
             })
             .collect();
-        IdTable { data: result }
+        self.negative.sort_unstable();
+        if let Some(w) = self.negative.windows(2).find(|w| w[0].0 == w[1].0) {
+            panic!("Duplicate id {}", w[0].0);
+        }
+        IdTable {
+            data: result,
+            negative: self.negative,
+            indices: self.indices,
+        }
     }
 }
 
 impl IdTable {
-    pub fn get(&self, x: u64) -> Option<u64> {
-        let id_set = (x >> 24) as usize;
-        if id_set > self.data.len() {
-            return None;
-        }
-        self.data[id_set]
-            .1
-            .pos((x % (1u64 << 24)) as u32)
-            .map(|pos| self.data[id_set].0 + pos as u64)
+    /// Maps the `i`-th inserted id to `indices[i]` instead of `i`
+    pub fn set_indices(&mut self, indices: Vec<u64>) {
+        debug_assert!(self.indices.is_empty(), "ids are already mapped");
+        self.indices = indices;
+    }
+
+    pub fn get(&self, x: i64) -> Option<u64> {
+        let idx = if x < 0 {
+            let pos = self.negative.binary_search_by_key(&x, |&(id, _)| id).ok()?;
+            self.negative[pos].1
+        } else {
+            let x = x as u64;
+            let id_set = (x >> 24) as usize;
+            if id_set >= self.data.len() {
+                return None;
+            }
+            self.data[id_set]
+                .1
+                .pos((x % (1u64 << 24)) as u32)
+                .map(|pos| self.data[id_set].0 + pos as u64)?
+        };
+        Some(self.indices.get(idx as usize).copied().unwrap_or(idx))
     }
 }
 
@@ -176,16 +230,23 @@ mod test {
             assert_eq!(res, Some(pos as u64));
         }
 
-        for x in [0, 1, 2, 5, 6, 11, 12, 14].iter() {
+        for x in [0, 1, 2, 5, 6, 11, 12, 14, 1 << 24].iter() {
             let res = lookup.get(*x);
             assert_eq!(res, None);
         }
     }
 
+    #[test]
+    fn test_empty() {
+        let lookup = IdTableBuilder::new().build();
+        assert_eq!(lookup.get(0), None);
+        assert_eq!(lookup.get(1), None);
+    }
+
     #[test]
     fn test_mapping_of_large_ints() {
         let mut builder = IdTableBuilder::new();
-        let mut data = [2, 1, 1_u64 << 33, 1_u64 << 34];
+        let mut data = [2, 1, 1_i64 << 33, 1_i64 << 34];
         data.sort_unstable();
         for x in data.iter() {
             builder.insert(*x);
@@ -197,7 +258,7 @@ mod test {
             assert_eq!(res, Some(pos as u64));
         }
 
-        for x in [0, 3, (1_u64 << 33) + 1, (1_u64 << 34) + 1, 1_u64 << 35].iter() {
+        for x in [0, 3, (1_i64 << 33) + 1, (1_i64 << 34) + 1, 1_i64 << 35].iter() {
             let res = lookup.get(*x);
             assert_eq!(res, None);
         }
@@ -206,7 +267,7 @@ mod test {
     #[test]
     fn test_large_indices() {
         let mut builder = IdTableBuilder::new();
-        let mut data = [2, 1, 1_u64 << 33, 1_u64 << 34];
+        let mut data = [2, 1, 1_i64 << 33, 1_i64 << 34];
         data.sort_unstable();
         for x in data.iter() {
             builder.insert(*x);
@@ -218,18 +279,33 @@ mod test {
             assert_eq!(res, Some(pos as u64));
         }
 
-        for x in [0, 3, (1_u64 << 33) + 1, (1_u64 << 34) + 1, 1_u64 << 35].iter() {
+        for x in [0, 3, (1_i64 << 33) + 1, (1_i64 << 34) + 1, 1_i64 << 35].iter() {
             let res = lookup.get(*x);
             assert_eq!(res, None);
         }
     }
 
+    #[test]
+    fn test_mapping_of_versions() {
+        let mut builder = IdTableBuilder::new();
+        for (idx, x) in [3, 3, 4, 7, 7, 7, 8].into_iter().enumerate() {
+            builder.insert_version(x, idx as u64);
+        }
+
+        let lookup = builder.build();
+        assert_eq!(lookup.get(3), Some(0));
+        assert_eq!(lookup.get(4), Some(2));
+        assert_eq!(lookup.get(7), Some(3));
+        assert_eq!(lookup.get(8), Some(6));
+        assert_eq!(lookup.get(5), None);
+    }
+
     #[test]
     fn test_dense() {
         let mut builder = IdTableBuilder::new();
         let mut data = Vec::new();
         for i in 0..ID_BLOCK_SIZE {
-            data.push(i as u64 * 3 + (1_u64 << 34));
+            data.push(i as i64 * 3 + (1_i64 << 34));
         }
         data.sort_unstable();
         for x in data.iter() {
@@ -238,7 +314,7 @@ mod test {
 
         let lookup = builder.build();
         for i in 0..ID_BLOCK_SIZE * 3 {
-            let res = lookup.get(i as u64 + (1_u64 << 34));
+            let res = lookup.get(i as i64 + (1_i64 << 34));
             if i % 3 == 0 {
                 assert_eq!(Some(i as u64 / 3), res);
             } else {
@@ -246,4 +322,43 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn test_negative() {
+        let mut builder = IdTableBuilder::new();
+        for x in [-1, -3, -2, 2, 5] {
+            builder.insert(x);
+        }
+
+        let lookup = builder.build();
+        assert_eq!(lookup.get(-1), Some(0));
+        assert_eq!(lookup.get(-3), Some(1));
+        assert_eq!(lookup.get(-2), Some(2));
+        assert_eq!(lookup.get(2), Some(3));
+        assert_eq!(lookup.get(5), Some(4));
+        for x in [-4, 0, 1, 3, i64::MIN] {
+            assert_eq!(lookup.get(x), None);
+        }
+    }
+
+    #[test]
+    fn test_negative_versions() {
+        let mut builder = IdTableBuilder::new();
+        for (idx, x) in [-2, -2, -1, 3, 3].into_iter().enumerate() {
+            builder.insert_version(x, idx as u64);
+        }
+
+        let lookup = builder.build();
+        assert_eq!(lookup.get(-2), Some(0));
+        assert_eq!(lookup.get(-1), Some(2));
+        assert_eq!(lookup.get(3), Some(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "Negative ids are expected before positive ids")]
+    fn test_negative_after_positive() {
+        let mut builder = IdTableBuilder::new();
+        builder.insert(1);
+        builder.insert(-1);
+    }
 }
@@ -0,0 +1,204 @@
+//! Inverted tag index of a converted archive.
+
+use ahash::AHashMap;
+use osmflat::{EntityIndex, InvertedIndexBuilder, Osm, TagPostings};
+
+use std::io;
+use std::ops::Range;
+
+/// Postings of tags (key and value) and of keys (value is `None`), ordered by
+/// key and value.
+fn build_postings(
+    archive: &Osm,
+    tag_ranges: impl Iterator<Item = Range<u64>>,
+) -> (Vec<TagPostings>, Vec<EntityIndex>) {
+    let tags = archive.tags();
+    let tags_index = archive.tags_index();
+    let strings = archive.stringtable();
+
+    let mut entities_by_tag: AHashMap<(u64, Option<u64>), Vec<u64>> = AHashMap::new();
+    for (idx, range) in tag_ranges.enumerate() {
+        let idx = idx as u64;
+        for tag_idx in range {
+            let tag = &tags[tags_index[tag_idx as usize].value() as usize];
+            for key in [
+                (tag.key_idx(), Some(tag.value_idx())),
+                (tag.key_idx(), None),
+            ] {
+                let entities = entities_by_tag.entry(key).or_default();
+                // an entity is listed once, even if it has a key multiple times
+                if entities.last() != Some(&idx) {
+                    entities.push(idx);
+                }
+            }
+        }
+    }
+
+    let mut entries: Vec<_> = entities_by_tag.into_iter().collect();
+    let string = |idx: u64| strings.substring_raw(idx as usize);
+    entries.sort_unstable_by(|((key1, value1), _), ((key2, value2), _)| {
+        string(*key1)
+            .cmp(string(*key2))
+            .then_with(|| match (value1, value2) {
+                (Some(value1), Some(value2)) => string(*value1).cmp(string(*value2)),
+                _ => value1.cmp(value2),
+            })
+            // the order of the map is random, so equal strings are ordered by index
+            .then_with(|| (key1, value1).cmp(&(key2, value2)))
+    });
+
+    let mut postings = Vec::with_capacity(entries.len() + 1);
+    let mut entities = Vec::new();
+    let mut push_postings = |key_idx, value_idx, first_idx| {
+        let mut p = TagPostings::new();
+        p.set_key_idx(key_idx);
+        p.set_value_idx(value_idx);
+        p.set_first_idx(first_idx);
+        postings.push(p);
+    };
+    for ((key_idx, value_idx), indexes) in entries {
+        push_postings(key_idx, value_idx, entities.len() as u64);
+        entities.extend(indexes.into_iter().map(|idx| {
+            let mut entity = EntityIndex::new();
+            entity.set_value(idx);
+            entity
+        }));
+    }
+    push_postings(0, None, entities.len() as u64);
+    (postings, entities)
+}
+
+/// Writes the inverted index of the tags of all entities of `archive`.
+pub fn serialize_inverted_index(archive: &Osm, builder: &InvertedIndexBuilder) -> io::Result<()> {
+    let (postings, entities) = build_postings(archive, archive.nodes().iter().map(|n| n.tags()));
+    builder.set_node_tags(&postings)?;
+    builder.set_nodes(&entities)?;
+
+    let (postings, entities) = build_postings(archive, archive.ways().iter().map(|w| w.tags()));
+    builder.set_way_tags(&postings)?;
+    builder.set_ways(&entities)?;
+
+    let (postings, entities) =
+        build_postings(archive, archive.relations().iter().map(|r| r.tags()));
+    builder.set_relation_tags(&postings)?;
+    builder.set_relations(&entities)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::convert::{convert, Options};
+
+    use osmflat::{query, FileResourceStorage};
+
+    #[test]
+    fn test_query_with_and_without_inverted_index() {
+        let opl = "n1 v1 Tamenity=pub,name=A x1 y1\nn2 v1 x2 y2\nn3 v1 Tamenity=cafe x3 y3\n\
+                   w1 v1 Thighway=primary Nn1,n2\nw2 v1 Thighway=track Nn2,n3\n\
+                   w3 v1 Tamenity=pub Nn1,n3\nr1 v1 Ttype=route Mw1@\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+
+        let query = |inverted_index: bool, key: &[u8], value: Option<&[u8]>| {
+            let output = dir.path().join(inverted_index.to_string());
+            let options = Options {
+                inverted_index,
+                force: true,
+                ..Default::default()
+            };
+            convert(&input, &output, options, ()).unwrap();
+            let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+            assert_eq!(archive.inverted_index().is_some(), inverted_index);
+            let result = query::entities_with_tag(&archive, key, value);
+            (
+                result.nodes.collect::<Vec<_>>(),
+                result.ways.collect::<Vec<_>>(),
+                result.relations.collect::<Vec<_>>(),
+            )
+        };
+        for (key, value, expected) in [
+            (
+                &b"amenity"[..],
+                Some(&b"pub"[..]),
+                (vec![0], vec![2], vec![]),
+            ),
+            (b"amenity", None, (vec![0, 2], vec![2], vec![])),
+            (b"highway", None, (vec![], vec![0, 1], vec![])),
+            (b"type", Some(b"route"), (vec![], vec![], vec![0])),
+            (b"name", Some(b"B"), (vec![], vec![], vec![])),
+        ] {
+            assert_eq!(query(false, key, value), expected);
+            assert_eq!(query(true, key, value), expected);
+        }
+    }
+
+    #[test]
+    fn test_tag_keys() {
+        let opl = "n1 v1 Tamenity=pub,name=A x1 y1\nn2 v1 x2 y2\nn3 v1 Tamenity=cafe x3 y3\n\
+                   w1 v1 Thighway=primary,name=B Nn1,n2\nr1 v1 Ttype=route Mw1@\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+
+        for inverted_index in [false, true] {
+            let output = dir.path().join(inverted_index.to_string());
+            let options = Options {
+                inverted_index,
+                ..Default::default()
+            };
+            convert(&input, &output, options, ()).unwrap();
+            let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+            assert_eq!(
+                archive.tag_keys(),
+                [
+                    (&b"amenity"[..], 2),
+                    (b"highway", 1),
+                    (b"name", 2),
+                    (b"type", 1)
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn test_parallel_tag_scans() {
+        use osmflat::rayon::prelude::*;
+
+        let opl = "n1 v1 Tamenity=pub x1 y1\nn2 v1 x2 y2\nn3 v1 Tamenity=cafe x3 y3\n\
+                   w1 v1 Thighway=primary Nn1,n2\nw2 v1 Thighway=track Nn2,n3\n\
+                   r1 v1 Ttype=route Mw1@\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+
+        for (tagged_nodes, tagged_bitsets) in [(false, false), (true, false), (false, true)] {
+            let output = dir.path().join(format!("{tagged_nodes}_{tagged_bitsets}"));
+            let options = Options {
+                tagged_nodes,
+                tagged_bitsets,
+                ..Default::default()
+            };
+            convert(&input, &output, options, ()).unwrap();
+            let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+
+            let sorted = |mut indexes: Vec<usize>| {
+                indexes.sort_unstable();
+                indexes
+            };
+            let tagged = osmflat::par_iter_tagged_nodes(&archive).map(|(idx, _)| idx);
+            assert_eq!(sorted(tagged.collect()), [0, 2]);
+            let tagged = osmflat::iter_tagged_nodes(&archive).map(|(idx, _)| idx);
+            assert_eq!(tagged.collect::<Vec<_>>(), [0, 2]);
+            assert_eq!(osmflat::iter_tagged_ways(&archive).count(), 2);
+            assert_eq!(archive.tagged_way_bits().is_some(), tagged_bitsets);
+            let pubs = osmflat::par_nodes_with_tag(&archive, b"amenity", Some(b"pub"));
+            assert_eq!(sorted(pubs.map(|(idx, _)| idx).collect()), [0]);
+            let highways = osmflat::par_ways_with_tag(&archive, b"highway", None);
+            assert_eq!(sorted(highways.map(|(idx, _)| idx).collect()), [0, 1]);
+            let routes = osmflat::par_relations_with_tag(&archive, b"type", Some(b"route"));
+            assert_eq!(routes.count(), 1);
+            assert_eq!(archive.ways().par_iter().count(), 2);
+        }
+    }
+}
@@ -0,0 +1,54 @@
+//! Compiler of OpenStreetMap (OSM) data from osm.pbf format to osm.flatdata
+//! format.
+//!
+//! ```no_run
+//! use std::path::Path;
+//!
+//! let stats = osmflatc::convert(
+//!     Path::new("berlin.osm.pbf"),
+//!     Path::new("berlin.osm.flatdata"),
+//!     osmflatc::Options::default(),
+//!     (),
+//! )?;
+//! println!("{stats}");
+//! # Ok::<(), osmflatc::Error>(())
+//! ```
+
+mod bench;
+mod compress;
+mod convert;
+mod elevation;
+mod export;
+mod extract;
+mod filter;
+mod ids;
+mod inverted_index;
+mod opl;
+mod osc;
+mod osmpbf;
+mod parallel;
+mod progress;
+mod provenance;
+mod remote;
+mod replication;
+mod spatial;
+mod stats;
+mod strings;
+mod tiles;
+mod unresolved;
+
+pub use crate::bench::{bench, BenchOptions, BenchResult};
+pub use crate::compress::Compression;
+pub use crate::convert::{convert, NodeOrder, Options};
+pub use crate::export::export_pbf;
+pub use crate::extract::{extract, Region};
+pub use crate::filter::{KeyPattern, TagFilter};
+pub use crate::parallel::DEFAULT_QUEUE_DEPTH;
+pub use crate::progress::{JsonProgress, LogProgress, Progress, Stage};
+pub use crate::replication::{follow, update, State};
+pub use crate::stats::Stats;
+pub use crate::tiles::split_tiles;
+pub use crate::unresolved::UnresolvedRef;
+
+/// Error of a conversion.
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
@@ -0,0 +1,512 @@
+//! Reader of the [OPL format], a line based text format with one entity per
+//! line.
+//!
+//! The lines are split into blocks of consecutive entities of the same type,
+//! which are converted to PBF primitive blocks. Thereby, the OPL input runs
+//! through the same serialization pipeline as PBF input.
+//!
+//! [OPL format]: https://osmcode.org/opl-file-format/
+
+use crate::osmpbf::{self, BlockType};
+
+use ahash::AHashMap;
+
+use std::io;
+use std::ops::Range;
+
+/// Granularity of OPL coordinates, which have 7 decimal places.
+pub const GRANULARITY: i32 = 100;
+
+/// Maximum number of entities in a block
+const BLOCK_SIZE: usize = 8000;
+
+/// Range of lines in the OPL data containing entities of the same type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OplBlock {
+    pub block_type: BlockType,
+    pub range: Range<usize>,
+}
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+fn block_type(line: &[u8]) -> io::Result<BlockType> {
+    match line.first() {
+        Some(b'n') => Ok(BlockType::DenseNodes),
+        Some(b'w') => Ok(BlockType::Ways),
+        Some(b'r') => Ok(BlockType::Relations),
+        Some(b'c') => Ok(BlockType::Changesets),
+        _ => Err(invalid_data(format!(
+            "unsupported OPL entity: {}",
+            String::from_utf8_lossy(line)
+        ))),
+    }
+}
+
+/// Splits OPL data into blocks.
+///
+/// The entities are expected to be sorted by type (nodes, ways, relations,
+/// changesets).
+pub fn build_block_index(data: &[u8]) -> io::Result<Vec<OplBlock>> {
+    let mut blocks: Vec<OplBlock> = Vec::new();
+    let mut block_len = 0;
+    let mut pos = 0;
+    for line in data.split_inclusive(|&c| c == b'\n') {
+        let start = pos;
+        pos += line.len();
+        if line.trim_ascii().is_empty() {
+            continue;
+        }
+        let block_type = block_type(line)?;
+        match blocks.last_mut() {
+            Some(block) if block.block_type == block_type && block_len < BLOCK_SIZE => {
+                block.range.end = pos;
+                block_len += 1;
+                continue;
+            }
+            Some(block) if block.block_type > block_type => {
+                return Err(invalid_data(
+                    "OPL entities are expected to be sorted by type: nodes, ways, relations, changesets",
+                ));
+            }
+            _ => (),
+        }
+        blocks.push(OplBlock {
+            block_type,
+            range: start..pos,
+        });
+        block_len = 1;
+    }
+    Ok(blocks)
+}
+
+/// Decodes the OPL escape sequences `%<hex codepoint>%`.
+fn unescape(s: &[u8]) -> io::Result<Vec<u8>> {
+    let mut result = Vec::with_capacity(s.len());
+    let mut parts = s.split(|&c| c == b'%');
+    result.extend_from_slice(parts.next().unwrap_or_default());
+    while let Some(code) = parts.next() {
+        let c = std::str::from_utf8(code)
+            .ok()
+            .and_then(|code| u32::from_str_radix(code, 16).ok())
+            .and_then(char::from_u32)
+            .ok_or_else(|| invalid_data("invalid escape sequence in OPL data"))?;
+        let mut buf = [0; 4];
+        result.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+        let text = parts
+            .next()
+            .ok_or_else(|| invalid_data("unterminated escape sequence in OPL data"))?;
+        result.extend_from_slice(text);
+    }
+    Ok(result)
+}
+
+/// Appends `s` to `out`, encoding non-printable and non-ASCII characters, and
+/// the characters with a special meaning in OPL as escape sequences.
+pub fn escape(s: &[u8], out: &mut Vec<u8>) {
+    for c in String::from_utf8_lossy(s).chars() {
+        if c.is_ascii_graphic() && !matches!(c, ',' | '=' | '@' | '%') {
+            out.push(c as u8);
+        } else {
+            out.extend(format!("%{:x}%", c as u32).bytes());
+        }
+    }
+}
+
+fn parse_id(s: &[u8]) -> io::Result<i64> {
+    std::str::from_utf8(s)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| invalid_data(format!("invalid id: {}", String::from_utf8_lossy(s))))
+}
+
+/// Parses a coordinate in degrees into units of the granularity.
+fn parse_coord(s: &[u8]) -> io::Result<i64> {
+    std::str::from_utf8(s)
+        .ok()
+        .and_then(|s| s.parse::<f64>().ok())
+        .map(|deg| (deg * 1e9 / f64::from(GRANULARITY)).round() as i64)
+        .ok_or_else(|| {
+            invalid_data(format!(
+                "invalid coordinate: {}",
+                String::from_utf8_lossy(s)
+            ))
+        })
+}
+
+/// Parses a timestamp in the format `yyyy-mm-ddThh:mm:ssZ` into seconds since
+/// the epoch.
+pub fn parse_timestamp(s: &[u8]) -> io::Result<i64> {
+    let parse = |s: &str| -> Option<i64> {
+        let (date, time) = s.strip_suffix('Z')?.split_once('T')?;
+        let mut date = date.splitn(3, '-').map(|x| x.parse::<i64>().ok());
+        let (y, m, d) = (date.next()??, date.next()??, date.next()??);
+        let mut time = time.splitn(3, ':').map(|x| x.parse::<i64>().ok());
+        let (hh, mm, ss) = (time.next()??, time.next()??, time.next()??);
+        // days since the epoch in the proleptic Gregorian calendar, cf.
+        // http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+        let y = if m <= 2 { y - 1 } else { y };
+        let era = y.div_euclid(400);
+        let yoe = y - era * 400;
+        let doy = (153 * (m + if m > 2 { -3 } else { 9 }) + 2) / 5 + d - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * 146_097 + doe - 719_468;
+        Some(days * 86_400 + hh * 3600 + mm * 60 + ss)
+    };
+    std::str::from_utf8(s)
+        .ok()
+        .and_then(parse)
+        .ok_or_else(|| invalid_data(format!("invalid timestamp: {}", String::from_utf8_lossy(s))))
+}
+
+/// Splits a comma separated list, ignoring empty lists.
+fn list(s: &[u8]) -> impl Iterator<Item = &[u8]> {
+    s.split(|&c| c == b',').filter(|item| !item.is_empty())
+}
+
+/// String table of a block; index 0 is reserved as separator of dense tags.
+struct StringTableBuilder {
+    strings: Vec<Vec<u8>>,
+    index: AHashMap<Vec<u8>, u32>,
+}
+
+impl StringTableBuilder {
+    fn new() -> Self {
+        Self {
+            strings: vec![Vec::new()],
+            index: AHashMap::new(),
+        }
+    }
+
+    fn insert(&mut self, s: Vec<u8>) -> u32 {
+        let next = self.strings.len() as u32;
+        *self.index.entry(s).or_insert_with_key(|s| {
+            self.strings.push(s.clone());
+            next
+        })
+    }
+
+    fn tags(&mut self, s: &[u8]) -> io::Result<Vec<(u32, u32)>> {
+        list(s)
+            .map(|tag| {
+                let pos = tag
+                    .iter()
+                    .position(|&c| c == b'=')
+                    .ok_or_else(|| invalid_data("invalid tag in OPL data"))?;
+                let key = self.insert(unescape(&tag[..pos])?);
+                let value = self.insert(unescape(&tag[pos + 1..])?);
+                Ok((key, value))
+            })
+            .collect()
+    }
+
+    fn build(self) -> osmpbf::StringTable {
+        osmpbf::StringTable { s: self.strings }
+    }
+}
+
+/// Entity parsed from an OPL line, fields which are not converted are skipped.
+#[derive(Default)]
+struct Entity<'a> {
+    id: i64,
+    version: Option<i32>,
+    timestamp: Option<i64>,
+    deleted: bool,
+    tags: &'a [u8],
+    lon: &'a [u8],
+    lat: &'a [u8],
+    nodes: &'a [u8],
+    members: &'a [u8],
+    created_at: Option<i64>,
+    closed_at: Option<i64>,
+    user: &'a [u8],
+    max_lon: &'a [u8],
+    max_lat: &'a [u8],
+}
+
+fn parse_entity(line: &[u8]) -> io::Result<Entity<'_>> {
+    let mut fields = line.split(|&c| c == b' ').filter(|f| !f.is_empty());
+    let id = fields.next().unwrap_or_default();
+    let mut entity = Entity {
+        id: parse_id(&id[1..])?,
+        ..Default::default()
+    };
+    for field in fields {
+        let (name, value) = field.split_first().unwrap();
+        match name {
+            b'v' => entity.version = Some(parse_id(value)? as i32),
+            b't' if !value.is_empty() => entity.timestamp = Some(parse_timestamp(value)?),
+            b'd' => entity.deleted = value == b"D",
+            b'T' => entity.tags = value,
+            b'x' => entity.lon = value,
+            b'y' => entity.lat = value,
+            b'N' => entity.nodes = value,
+            b'M' => entity.members = value,
+            b's' if !value.is_empty() => entity.created_at = Some(parse_timestamp(value)?),
+            b'e' if !value.is_empty() => entity.closed_at = Some(parse_timestamp(value)?),
+            b'u' => entity.user = value,
+            b'X' => entity.max_lon = value,
+            b'Y' => entity.max_lat = value,
+            _ => (), // changeset, uid, number of changes and comments
+        }
+    }
+    Ok(entity)
+}
+
+/// Converts the lines of an OPL block into a PBF primitive block.
+pub fn read_block(data: &[u8], idx: &OplBlock) -> io::Result<osmpbf::PrimitiveBlock> {
+    let mut strings = StringTableBuilder::new();
+    let mut group = osmpbf::PrimitiveGroup::default();
+    let mut dense = osmpbf::DenseNodes::default();
+    let mut dense_info = osmpbf::DenseInfo::default();
+    let (mut last_id, mut last_lat, mut last_lon, mut last_timestamp) = (0, 0, 0, 0);
+
+    let lines = data[idx.range.clone()]
+        .split(|&c| c == b'\n')
+        .map(|line| line.trim_ascii())
+        .filter(|line| !line.is_empty());
+    for line in lines {
+        let entity = parse_entity(line)?;
+        let tags = strings.tags(entity.tags)?;
+        // deleted entities are kept for the history mode of the converter
+        let info = osmpbf::Info {
+            version: entity.version,
+            timestamp: entity.timestamp,
+            visible: Some(!entity.deleted),
+            ..Default::default()
+        };
+        match idx.block_type {
+            BlockType::DenseNodes => {
+                // deleted nodes have no location
+                let (lat, lon) = if entity.deleted {
+                    (0, 0)
+                } else {
+                    (parse_coord(entity.lat)?, parse_coord(entity.lon)?)
+                };
+                let timestamp = info.timestamp();
+                dense.id.push(entity.id - last_id);
+                dense.lat.push(lat - last_lat);
+                dense.lon.push(lon - last_lon);
+                dense_info.version.push(info.version());
+                dense_info.timestamp.push(timestamp - last_timestamp);
+                dense_info.visible.push(!entity.deleted);
+                (last_id, last_lat, last_lon, last_timestamp) = (entity.id, lat, lon, timestamp);
+                for (key, value) in tags {
+                    dense.keys_vals.extend([key as i32, value as i32]);
+                }
+                dense.keys_vals.push(0);
+            }
+            BlockType::Ways => {
+                let mut way = osmpbf::Way {
+                    id: entity.id,
+                    info: Some(info),
+                    ..Default::default()
+                };
+                (way.keys, way.vals) = tags.into_iter().unzip();
+                let mut last_ref = 0;
+                for node in list(entity.nodes) {
+                    let node_ref = parse_id(node.strip_prefix(b"n").unwrap_or(node))?;
+                    way.refs.push(node_ref - last_ref);
+                    last_ref = node_ref;
+                }
+                group.ways.push(way);
+            }
+            BlockType::Relations => {
+                let mut relation = osmpbf::Relation {
+                    id: entity.id,
+                    info: Some(info),
+                    ..Default::default()
+                };
+                (relation.keys, relation.vals) = tags.into_iter().unzip();
+                let mut last_memid = 0;
+                for member in list(entity.members) {
+                    let pos = member
+                        .iter()
+                        .position(|&c| c == b'@')
+                        .ok_or_else(|| invalid_data("invalid member in OPL data"))?;
+                    let member_type = match member[0] {
+                        b'n' => osmpbf::relation::MemberType::Node,
+                        b'w' => osmpbf::relation::MemberType::Way,
+                        b'r' => osmpbf::relation::MemberType::Relation,
+                        _ => return Err(invalid_data("invalid member type in OPL data")),
+                    };
+                    let memid = parse_id(&member[1..pos])?;
+                    relation.types.push(member_type as i32);
+                    relation.memids.push(memid - last_memid);
+                    last_memid = memid;
+                    let role = strings.insert(unescape(&member[pos + 1..])?);
+                    relation.roles_sid.push(role as i32);
+                }
+                group.relations.push(relation);
+            }
+            BlockType::Changesets => {
+                // changesets without changes have no bounding box
+                let bbox = if entity.lon.is_empty() {
+                    None
+                } else {
+                    let nanodegrees =
+                        |s| Ok::<_, io::Error>(parse_coord(s)? * i64::from(GRANULARITY));
+                    Some(osmpbf::HeaderBBox {
+                        left: nanodegrees(entity.lon)?,
+                        right: nanodegrees(entity.max_lon)?,
+                        top: nanodegrees(entity.max_lat)?,
+                        bottom: nanodegrees(entity.lat)?,
+                    })
+                };
+                let created_at = entity.created_at.unwrap_or_default();
+                let (keys, vals) = tags.into_iter().unzip();
+                group.changesets.push(osmpbf::ChangeSet {
+                    id: entity.id,
+                    keys,
+                    vals,
+                    info: Some(osmpbf::Info {
+                        user_sid: Some(strings.insert(unescape(entity.user)?)),
+                        ..Default::default()
+                    }),
+                    created_at: Some(created_at),
+                    closetime_delta: entity.closed_at.map(|closed_at| closed_at - created_at),
+                    open: Some(entity.closed_at.is_none()),
+                    bbox,
+                });
+            }
+            _ => unreachable!("OPL blocks contain only nodes, ways, relations and changesets"),
+        }
+    }
+    if idx.block_type == BlockType::DenseNodes {
+        dense.denseinfo = Some(dense_info);
+        group.dense = Some(dense);
+    }
+
+    Ok(osmpbf::PrimitiveBlock {
+        stringtable: strings.build(),
+        primitivegroup: vec![group],
+        granularity: Some(GRANULARITY),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DATA: &[u8] = b"\
+n1 v1 dV Tamenity=cafe,name=Caf%e9% x13.4 y52.5
+n2 v1 dV T x13.4000001 y-52.5
+
+w10 v2 dV Thighway=primary Nn1,n2
+r20 v1 dV Ttype=route Mw10@forward,n1@,r21@sub%20%route
+";
+
+    #[test]
+    fn test_block_index() {
+        let blocks = build_block_index(DATA).unwrap();
+        let types: Vec<_> = blocks.iter().map(|b| b.block_type).collect();
+        assert_eq!(
+            types,
+            [BlockType::DenseNodes, BlockType::Ways, BlockType::Relations]
+        );
+        assert_eq!(blocks[2].range.end, DATA.len());
+
+        assert!(build_block_index(b"w1\nn1\n").is_err());
+        assert!(build_block_index(b"c1\nr1\n").is_err());
+        assert!(build_block_index(b"x1\n").is_err());
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(unescape(b"a%20%b%2c%").unwrap(), b"a b,");
+        assert_eq!(unescape(b"Caf%e9%").unwrap(), "Café".as_bytes());
+        assert!(unescape(b"a%20").is_err());
+        assert!(unescape(b"a%zz%").is_err());
+    }
+
+    #[test]
+    fn test_escape() {
+        let mut out = Vec::new();
+        escape("sub route,a=b@c%Ü".as_bytes(), &mut out);
+        assert_eq!(out, b"sub%20%route%2c%a%3d%b%40%c%25%%dc%");
+        assert_eq!(unescape(&out).unwrap(), "sub route,a=b@c%Ü".as_bytes());
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp(b"1970-01-01T00:00:00Z").unwrap(), 0);
+        assert_eq!(
+            parse_timestamp(b"2000-03-01T01:02:03Z").unwrap(),
+            951_872_523
+        );
+        assert_eq!(parse_timestamp(b"1969-12-31T23:59:59Z").unwrap(), -1);
+        assert!(parse_timestamp(b"2000-03-01 01:02:03").is_err());
+    }
+
+    #[test]
+    fn test_read_deleted_versions() {
+        let data =
+            b"n1 v1 dV t2020-01-01T00:00:00Z Tname=a x1 y2\nn1 v2 dD t2020-01-02T00:00:00Z T x y\n";
+        let blocks = build_block_index(data).unwrap();
+        let nodes = read_block(data, &blocks[0]).unwrap();
+        let dense = nodes.primitivegroup[0].dense.as_ref().unwrap();
+        assert_eq!(dense.id, [1, 0]);
+        let info = dense.denseinfo.as_ref().unwrap();
+        assert_eq!(info.version, [1, 2]);
+        assert_eq!(info.timestamp, [1_577_836_800, 86_400]);
+        assert_eq!(info.visible, [true, false]);
+    }
+
+    #[test]
+    fn test_read_changesets() {
+        let data = b"\
+c1 k2 s2020-01-01T00:00:00Z e2020-01-01T01:00:00Z d0 i7 uAnn%20%B x13.4 y52.5 X13.5 Y52.6 Tcomment=fix
+c2 k0 s2020-01-02T00:00:00Z e d0 i8 uC x y X Y T
+";
+        let blocks = build_block_index(data).unwrap();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].block_type, BlockType::Changesets);
+        let block = read_block(data, &blocks[0]).unwrap();
+        let strings = &block.stringtable.s;
+        let changesets = &block.primitivegroup[0].changesets;
+
+        assert_eq!(changesets[0].id, 1);
+        assert_eq!(changesets[0].created_at, Some(1_577_836_800));
+        assert_eq!(changesets[0].closetime_delta, Some(3600));
+        assert_eq!(changesets[0].open, Some(false));
+        let user_sid = changesets[0].info.unwrap().user_sid.unwrap();
+        assert_eq!(strings[user_sid as usize], b"Ann B");
+        let bbox = changesets[0].bbox.as_ref().unwrap();
+        assert_eq!((bbox.left, bbox.top), (13_400_000_000, 52_600_000_000));
+
+        let tag = (changesets[0].keys[0], changesets[0].vals[0]);
+        assert_eq!(strings[tag.0 as usize], b"comment");
+        assert_eq!(strings[tag.1 as usize], b"fix");
+
+        assert_eq!(changesets[1].open, Some(true));
+        assert!(changesets[1].bbox.is_none());
+        assert!(changesets[1].keys.is_empty());
+    }
+
+    #[test]
+    fn test_read_block() {
+        let blocks = build_block_index(DATA).unwrap();
+
+        let nodes = read_block(DATA, &blocks[0]).unwrap();
+        let strings = &nodes.stringtable.s;
+        let dense = nodes.primitivegroup[0].dense.as_ref().unwrap();
+        assert_eq!(dense.id, [1, 1]);
+        assert_eq!(dense.lat, [525_000_000, -1_050_000_000]);
+        assert_eq!(dense.lon, [134_000_000, 1]);
+        assert_eq!(dense.keys_vals.len(), 6);
+        assert_eq!(strings[dense.keys_vals[3] as usize], "Café".as_bytes());
+
+        let ways = read_block(DATA, &blocks[1]).unwrap();
+        let way = &ways.primitivegroup[0].ways[0];
+        assert_eq!((way.id, &way.refs[..]), (10, &[1, 1][..]));
+
+        let relations = read_block(DATA, &blocks[2]).unwrap();
+        let strings = &relations.stringtable.s;
+        let relation = &relations.primitivegroup[0].relations[0];
+        assert_eq!(relation.memids, [10, -9, 20]);
+        assert_eq!(relation.types, [1, 0, 2]);
+        assert_eq!(strings[relation.roles_sid[2] as usize], b"sub route");
+    }
+}
@@ -0,0 +1,320 @@
+//! Reader of [OsmChange] files as published by the OSM replication service.
+//!
+//! The changed entities are converted to lines in the [OPL format], such that
+//! they can be merged into the OPL representation of an archive.
+//!
+//! [OsmChange]: https://wiki.openstreetmap.org/wiki/OsmChange
+//! [OPL format]: https://osmcode.org/opl-file-format/
+
+use crate::opl;
+use crate::osmpbf::BlockType;
+
+use std::collections::BTreeMap;
+use std::io;
+
+/// Changed entities by type and id.
+///
+/// A deleted entity has no OPL line.
+pub type Changes = BTreeMap<(BlockType, i64), Option<Vec<u8>>>;
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Decodes the XML entities in an attribute value.
+fn unescape_xml(s: &str) -> io::Result<String> {
+    let mut result = String::with_capacity(s.len());
+    let mut parts = s.split('&');
+    result.push_str(parts.next().unwrap_or_default());
+    for part in parts {
+        let (entity, text) = part
+            .split_once(';')
+            .ok_or_else(|| invalid_data("unterminated XML entity"))?;
+        let c = match entity {
+            "amp" => '&',
+            "lt" => '<',
+            "gt" => '>',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => entity
+                .strip_prefix("#x")
+                .map(|code| u32::from_str_radix(code, 16))
+                .or_else(|| entity.strip_prefix('#').map(|code| code.parse()))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32)
+                .ok_or_else(|| invalid_data(format!("invalid XML entity: &{entity};")))?,
+        };
+        result.push(c);
+        result.push_str(text);
+    }
+    Ok(result)
+}
+
+/// XML element tag, i.e. the part between `<` and `>`.
+struct Element<'a> {
+    name: &'a str,
+    attrs: &'a str,
+    closing: bool,
+    empty: bool,
+}
+
+impl<'a> Element<'a> {
+    fn parse(s: &'a str) -> Self {
+        let (closing, s) = match s.strip_prefix('/') {
+            Some(s) => (true, s),
+            None => (false, s),
+        };
+        let (empty, s) = match s.strip_suffix('/') {
+            Some(s) => (true, s),
+            None => (false, s),
+        };
+        let (name, attrs) = s
+            .split_once(|c: char| c.is_ascii_whitespace())
+            .unwrap_or((s, ""));
+        Self {
+            name,
+            attrs,
+            closing,
+            empty,
+        }
+    }
+
+    fn attr(&self, name: &str) -> io::Result<Option<String>> {
+        let mut rest = self.attrs;
+        while let Some((key, value)) = rest.split_once('=') {
+            let value = value.trim_start();
+            let quote = value
+                .chars()
+                .next()
+                .filter(|&c| c == '"' || c == '\'')
+                .ok_or_else(|| invalid_data("unquoted XML attribute"))?;
+            let (value, tail) = value[1..]
+                .split_once(quote)
+                .ok_or_else(|| invalid_data("unterminated XML attribute"))?;
+            if key.trim() == name {
+                return unescape_xml(value).map(Some);
+            }
+            rest = tail;
+        }
+        Ok(None)
+    }
+
+    fn required_attr(&self, name: &str) -> io::Result<String> {
+        self.attr(name)?.ok_or_else(|| {
+            invalid_data(format!("missing attribute {name} of element {}", self.name))
+        })
+    }
+}
+
+/// Entity in the process of being parsed.
+struct Entity {
+    block_type: BlockType,
+    id: i64,
+    deleted: bool,
+    line: Vec<u8>,
+    tags: Vec<u8>,
+    refs: Vec<u8>,
+}
+
+impl Entity {
+    fn new(element: &Element, block_type: BlockType, deleted: bool) -> io::Result<Self> {
+        let id: i64 = element
+            .required_attr("id")?
+            .parse()
+            .map_err(|_| invalid_data("invalid id"))?;
+        let prefix = match block_type {
+            BlockType::DenseNodes => 'n',
+            BlockType::Ways => 'w',
+            _ => 'r',
+        };
+        let mut line = format!("{prefix}{id}").into_bytes();
+        if let Some(version) = element.attr("version")? {
+            line.extend(format!(" v{version}").bytes());
+        }
+        line.extend_from_slice(if deleted { b" dD" } else { b" dV" });
+        if let Some(timestamp) = element.attr("timestamp")? {
+            line.extend(format!(" t{timestamp}").bytes());
+        }
+        if let (Some(lon), Some(lat)) = (element.attr("lon")?, element.attr("lat")?) {
+            line.extend(format!(" x{lon} y{lat}").bytes());
+        }
+        Ok(Self {
+            block_type,
+            id,
+            deleted,
+            line,
+            tags: Vec::new(),
+            refs: Vec::new(),
+        })
+    }
+
+    fn add_tag(&mut self, element: &Element) -> io::Result<()> {
+        if !self.tags.is_empty() {
+            self.tags.push(b',');
+        }
+        opl::escape(element.required_attr("k")?.as_bytes(), &mut self.tags);
+        self.tags.push(b'=');
+        opl::escape(element.required_attr("v")?.as_bytes(), &mut self.tags);
+        Ok(())
+    }
+
+    fn add_ref(&mut self, element: &Element) -> io::Result<()> {
+        if !self.refs.is_empty() {
+            self.refs.push(b',');
+        }
+        self.refs.push(b'n');
+        self.refs.extend(element.required_attr("ref")?.bytes());
+        Ok(())
+    }
+
+    fn add_member(&mut self, element: &Element) -> io::Result<()> {
+        if !self.refs.is_empty() {
+            self.refs.push(b',');
+        }
+        let member_type = element.required_attr("type")?;
+        self.refs
+            .push(*member_type.as_bytes().first().unwrap_or(&b'n'));
+        self.refs.extend(element.required_attr("ref")?.bytes());
+        self.refs.push(b'@');
+        let role = element.attr("role")?.unwrap_or_default();
+        opl::escape(role.as_bytes(), &mut self.refs);
+        Ok(())
+    }
+
+    fn finish(mut self, changes: &mut Changes) {
+        let line = (!self.deleted).then(|| {
+            self.line.extend_from_slice(b" T");
+            self.line.extend_from_slice(&self.tags);
+            match self.block_type {
+                BlockType::Ways => self.line.extend_from_slice(b" N"),
+                BlockType::Relations => self.line.extend_from_slice(b" M"),
+                _ => (),
+            }
+            self.line.extend_from_slice(&self.refs);
+            self.line
+        });
+        changes.insert((self.block_type, self.id), line);
+    }
+}
+
+/// Parses an OsmChange document and adds its changes to `changes`.
+///
+/// Changes of an entity replace earlier changes of the same entity.
+pub fn parse(data: &str, changes: &mut Changes) -> io::Result<()> {
+    let mut deleting = false;
+    let mut entity: Option<Entity> = None;
+    for part in data.split('<').skip(1) {
+        let (tag, _text) = part
+            .split_once('>')
+            .ok_or_else(|| invalid_data("unterminated XML element"))?;
+        if tag.starts_with('?') || tag.starts_with('!') {
+            continue; // declaration or comment
+        }
+        let element = Element::parse(tag.trim());
+        let block_type = match element.name {
+            "node" => Some(BlockType::DenseNodes),
+            "way" => Some(BlockType::Ways),
+            "relation" => Some(BlockType::Relations),
+            _ => None,
+        };
+        match (element.name, block_type) {
+            (_, Some(block_type)) if !element.closing => {
+                let new = Entity::new(&element, block_type, deleting)?;
+                if element.empty {
+                    new.finish(changes);
+                } else {
+                    entity = Some(new);
+                }
+            }
+            (_, Some(_)) => {
+                if let Some(entity) = entity.take() {
+                    entity.finish(changes);
+                }
+            }
+            ("delete", _) => deleting = !element.closing,
+            ("tag", _) => {
+                if let Some(entity) = &mut entity {
+                    entity.add_tag(&element)?;
+                }
+            }
+            ("nd", _) => {
+                if let Some(entity) = &mut entity {
+                    entity.add_ref(&element)?;
+                }
+            }
+            ("member", _) => {
+                if let Some(entity) = &mut entity {
+                    entity.add_member(&element)?;
+                }
+            }
+            _ => (), // osmChange, create, modify
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DATA: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<osmChange version="0.6" generator="test">
+  <create>
+    <node id="1" version="1" timestamp="2024-01-01T00:00:00Z" lat="52.5" lon="13.4">
+      <tag k="name" v="Caf&#233; &amp; Bar"/>
+    </node>
+  </create>
+  <modify>
+    <way id="10" version="2" timestamp="2024-01-01T00:00:00Z">
+      <nd ref="1"/>
+      <nd ref="2"/>
+      <tag k="highway" v="primary"/>
+    </way>
+    <relation id="20" version="3">
+      <member type="way" ref="10" role="outer ring"/>
+      <member type="node" ref="1" role=""/>
+    </relation>
+  </modify>
+  <delete>
+    <node id="2" version="4" lat="52.0" lon="13.0"/>
+  </delete>
+</osmChange>
+"#;
+
+    #[test]
+    fn test_parse() {
+        let mut changes = Changes::new();
+        parse(DATA, &mut changes).unwrap();
+        let lines: Vec<_> = changes
+            .iter()
+            .map(|(key, line)| (*key, line.as_deref().map(String::from_utf8_lossy)))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                (
+                    (BlockType::DenseNodes, 1),
+                    Some(
+                        "n1 v1 dV t2024-01-01T00:00:00Z x13.4 y52.5 Tname=Caf%e9%%20%&%20%Bar"
+                            .into()
+                    )
+                ),
+                ((BlockType::DenseNodes, 2), None),
+                (
+                    (BlockType::Ways, 10),
+                    Some("w10 v2 dV t2024-01-01T00:00:00Z Thighway=primary Nn1,n2".into())
+                ),
+                (
+                    (BlockType::Relations, 20),
+                    Some("r20 v3 dV T Mw10@outer%20%ring,n1@".into())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unescape_xml() {
+        assert_eq!(unescape_xml("a &lt;b&gt; &#x41;&#66;").unwrap(), "a <b> AB");
+        assert!(unescape_xml("a & b").is_err());
+    }
+}
@@ -2,10 +2,11 @@
 
 use byteorder::{ByteOrder, NetworkEndian};
 use flate2::read::ZlibDecoder;
-use log::info;
+use log::debug;
 use prost::{self, Message};
 use rayon::prelude::*;
 
+use std::borrow::Cow;
 use std::io::{self, Read};
 
 include!(concat!(env!("OUT_DIR"), "/osmpbf.rs"));
@@ -17,6 +18,7 @@ pub enum BlockType {
     DenseNodes,
     Ways,
     Relations,
+    Changesets,
 }
 
 /// Decode block type from PrimitiveBlock protobuf message
@@ -58,9 +60,7 @@ pub fn type_and_granularity_from_osmdata_blob(mut blob: &[u8]) -> io::Result<(Bl
                 DENSE_NODES_TAG => Some(BlockType::DenseNodes),
                 WAY_STAG => Some(BlockType::Ways),
                 RELATIONS_TAG => Some(BlockType::Relations),
-                CHANGESETS_TAG => {
-                    panic!("found block containing unsupported changesets");
-                }
+                CHANGESETS_TAG => Some(BlockType::Changesets),
                 _ => {
                     panic!("invalid input data: malformed primitive block");
                 }
@@ -90,9 +90,10 @@ pub struct BlockIndex {
     pub blob_len: usize,
 }
 
-struct BlockIndexIterator<'a> {
-    data: &'a [u8],
+struct BlockIndexIterator<R> {
+    reader: R,
     cursor: usize,
+    failed: bool,
 }
 
 enum BlobInfo {
@@ -100,28 +101,56 @@ enum BlobInfo {
     Unknown(usize, usize, Vec<u8>),
 }
 
-impl<'a> BlockIndexIterator<'a> {
-    fn new(data: &'a [u8]) -> Self {
-        Self { data, cursor: 0 }
+impl<R: Read> BlockIndexIterator<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            cursor: 0,
+            failed: false,
+        }
     }
 
-    fn read(&mut self, len: usize) -> &[u8] {
-        let data = &self.data[self.cursor..self.cursor + len];
+    fn read(&mut self, len: usize) -> Result<Vec<u8>, io::Error> {
+        let mut data = vec![0; len];
+        self.reader.read_exact(&mut data)?;
         self.cursor += len;
-        data
+        Ok(data)
     }
 
-    fn next_blob(&mut self) -> Result<BlobInfo, io::Error> {
-        // read size of blob header
-        let blob_header_len: i32 = NetworkEndian::read_i32(self.read(4));
+    /// Reads the size of the next blob header, or `None` at the end of the
+    /// input.
+    fn read_blob_header_len(&mut self) -> Result<Option<usize>, io::Error> {
+        let mut buf = [0; 4];
+        let mut filled = 0;
+        while filled < buf.len() {
+            match self.reader.read(&mut buf[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        self.cursor += buf.len();
+        Ok(Some(NetworkEndian::read_i32(&buf) as usize))
+    }
 
+    fn next_blob(&mut self, blob_header_len: usize) -> Result<BlobInfo, io::Error> {
         // read blob header
-        let blob_header = BlobHeader::decode(self.read(blob_header_len as usize))?;
+        let blob_header = BlobHeader::decode(&self.read(blob_header_len)?[..])?;
 
         let blob_start = self.cursor;
         let blob_len = blob_header.datasize as usize;
 
         if blob_header.r#type == "OSMHeader" {
+            // skip blob
+            let skipped = io::copy(
+                &mut (&mut self.reader).take(blob_len as u64),
+                &mut io::sink(),
+            )?;
+            if skipped != blob_len as u64 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
             self.cursor += blob_len;
             Ok(BlobInfo::Header(BlockIndex {
                 block_type: BlockType::Header,
@@ -134,7 +163,7 @@ impl<'a> BlockIndexIterator<'a> {
             Ok(BlobInfo::Unknown(
                 blob_start,
                 blob_len,
-                self.read(blob_header.datasize as usize).to_vec(),
+                self.read(blob_len)?,
             ))
         } else {
             panic!("unknown blob type");
@@ -142,39 +171,89 @@ impl<'a> BlockIndexIterator<'a> {
     }
 }
 
-impl<'a> Iterator for BlockIndexIterator<'a> {
+impl<R: Read> Iterator for BlockIndexIterator<R> {
     type Item = Result<BlobInfo, io::Error>;
     fn next(&mut self) -> Option<Self::Item> {
-        if self.cursor < self.data.len() {
-            Some(self.next_blob())
-        } else {
-            None
+        if self.failed {
+            return None;
         }
+        let blob = self
+            .read_blob_header_len()
+            .transpose()?
+            .and_then(|len| self.next_blob(len));
+        // the position of the next blob is unknown after an error
+        self.failed = blob.is_err();
+        Some(blob)
     }
 }
 
-pub fn read_block<T: prost::Message + Default>(
-    data: &[u8],
-    idx: &BlockIndex,
-) -> Result<T, io::Error> {
-    let blob = Blob::decode(&data[idx.blob_start..idx.blob_start + idx.blob_len])?;
-
-    let mut blob_buf = Vec::new();
-    let blob_data = if blob.raw.is_some() {
-        blob.raw.as_ref().unwrap()
-    } else if blob.zlib_data.is_some() {
-        // decompress zlib data
-        let data: &Vec<u8> = blob.zlib_data.as_ref().unwrap();
-        let mut decoder = ZlibDecoder::new(&data[..]);
-        decoder.read_to_end(&mut blob_buf)?;
-        &blob_buf
+fn unsupported_compression(name: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{name} compressed blobs are not supported, enable the feature `{name}`"),
+    )
+}
+
+fn read_to_end(mut decoder: impl Read, size_hint: usize) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(size_hint);
+    decoder.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+/// Returns the decompressed data of a blob.
+#[allow(deprecated)] // bzip2 compressed blobs may still be found in old files
+fn decompress(blob: &Blob) -> io::Result<Cow<'_, [u8]>> {
+    let size_hint = blob.raw_size.unwrap_or(0).max(0) as usize;
+    if let Some(raw) = &blob.raw {
+        Ok(Cow::Borrowed(raw))
+    } else if let Some(data) = &blob.zlib_data {
+        read_to_end(ZlibDecoder::new(&data[..]), size_hint).map(Cow::Owned)
+    } else if let Some(data) = &blob.lzma_data {
+        #[cfg(feature = "lzma")]
+        {
+            let stream = xz2::stream::Stream::new_lzma_decoder(u64::MAX)?;
+            let decoder = xz2::read::XzDecoder::new_stream(&data[..], stream);
+            read_to_end(decoder, size_hint).map(Cow::Owned)
+        }
+        #[cfg(not(feature = "lzma"))]
+        {
+            let _ = data;
+            Err(unsupported_compression("lzma"))
+        }
+    } else if let Some(data) = &blob.obsolete_bzip2_data {
+        #[cfg(feature = "bzip2")]
+        {
+            read_to_end(bzip2::read::BzDecoder::new(&data[..]), size_hint).map(Cow::Owned)
+        }
+        #[cfg(not(feature = "bzip2"))]
+        {
+            let _ = data;
+            Err(unsupported_compression("bzip2"))
+        }
+    } else if let Some(data) = &blob.zstd_data {
+        #[cfg(feature = "zstd")]
+        {
+            read_to_end(zstd::Decoder::new(&data[..])?, size_hint).map(Cow::Owned)
+        }
+        #[cfg(not(feature = "zstd"))]
+        {
+            let _ = data;
+            Err(unsupported_compression("zstd"))
+        }
+    } else if blob.lz4_data.is_some() {
+        Err(unsupported_compression("lz4"))
     } else {
-        return Err(io::Error::new(
+        Err(io::Error::new(
             io::ErrorKind::InvalidData,
             "unknown compression",
-        ));
-    };
-    Ok(T::decode(blob_data.as_slice())?)
+        ))
+    }
+}
+
+/// Decodes the block in the encoded `blob`.
+pub fn decode_blob<T: prost::Message + Default>(blob: &[u8]) -> Result<T, io::Error> {
+    let blob = Blob::decode(blob)?;
+    Ok(T::decode(&decompress(&blob)?[..])?)
 }
 
 fn blob_type_and_granularity_from_blob_info(
@@ -183,20 +262,7 @@ fn blob_type_and_granularity_from_blob_info(
     blob: Vec<u8>,
 ) -> Result<BlockIndex, io::Error> {
     let blob = Blob::decode(blob.as_slice())?;
-
-    let mut blob_buf = Vec::new();
-    let blob_data = if blob.raw.is_some() {
-        // use raw bytes
-        blob.raw.as_ref().unwrap()
-    } else if blob.zlib_data.is_some() {
-        // decompress zlib data
-        let data: &Vec<u8> = blob.zlib_data.as_ref().unwrap();
-        let mut decoder = ZlibDecoder::new(&data[..]);
-        decoder.read_to_end(&mut blob_buf)?;
-        &blob_buf
-    } else {
-        panic!("can only read raw or zlib compressed blob");
-    };
+    let blob_data = decompress(&blob)?;
     assert_eq!(
         blob_data.len(),
         blob.raw_size.unwrap_or(blob_data.len() as i32) as usize
@@ -211,8 +277,18 @@ fn blob_type_and_granularity_from_blob_info(
     })
 }
 
-pub fn build_block_index(pbf_data: &[u8]) -> Vec<BlockIndex> {
-    let mut result: Vec<BlockIndex> = BlockIndexIterator::new(pbf_data)
+/// Builds the index of the blocks of the pbf file in `pbf_data`.
+pub fn build_block_index(pbf_data: &[u8]) -> Result<Vec<BlockIndex>, io::Error> {
+    read_block_index(pbf_data)
+}
+
+/// Builds the index of the blocks of a pbf file by reading it once from
+/// `reader`, e.g. while downloading it.
+///
+/// Blocks which cannot be decoded are skipped, but failing to read the input,
+/// e.g. if it is truncated, is an error.
+pub fn read_block_index(reader: impl Read + Send) -> Result<Vec<BlockIndex>, io::Error> {
+    let mut result = BlockIndexIterator::new(reader)
         .par_bridge()
         .filter_map(|blob| {
             let block = match blob {
@@ -220,18 +296,106 @@ pub fn build_block_index(pbf_data: &[u8]) -> Vec<BlockIndex> {
                 Ok(BlobInfo::Unknown(start, len, blob)) => {
                     blob_type_and_granularity_from_blob_info(start, len, blob)
                 }
-                Err(e) => Err(e),
+                Err(e) => return Some(Err(e)),
             };
             match block {
-                Ok(b) => Some(b),
+                Ok(b) => Some(Ok(b)),
                 Err(e) => {
                     eprintln!("Skipping block due to error: {e}");
                     None
                 }
             }
         })
-        .collect();
+        .collect::<Result<Vec<BlockIndex>, io::Error>>()?;
     result.par_sort_unstable();
-    info!("Found {} blocks", result.len());
-    result
+    debug!("Found {} blocks", result.len());
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DATA: &[u8] = b"OSMData OSMData OSMData OSMData";
+
+    fn blob(set: impl FnOnce(&mut Blob)) -> Blob {
+        let mut blob = Blob {
+            raw_size: Some(DATA.len() as i32),
+            ..Default::default()
+        };
+        set(&mut blob);
+        blob
+    }
+
+    #[test]
+    fn test_block_type() {
+        let block = |group: PrimitiveGroup| PrimitiveBlock {
+            primitivegroup: vec![group],
+            granularity: Some(10),
+            ..Default::default()
+        };
+        let ways = block(PrimitiveGroup {
+            ways: vec![Way::default()],
+            ..Default::default()
+        });
+        assert_eq!(
+            type_and_granularity_from_osmdata_blob(&ways.encode_to_vec()).unwrap(),
+            (BlockType::Ways, 10)
+        );
+        let changesets = block(PrimitiveGroup {
+            changesets: vec![ChangeSet::default()],
+            ..Default::default()
+        });
+        assert_eq!(
+            type_and_granularity_from_osmdata_blob(&changesets.encode_to_vec()).unwrap(),
+            (BlockType::Changesets, 10)
+        );
+    }
+
+    #[test]
+    fn test_decompress() {
+        let raw = blob(|b| b.raw = Some(DATA.to_vec()));
+        assert_eq!(&decompress(&raw).unwrap()[..], DATA);
+
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), Default::default());
+        std::io::Write::write_all(&mut encoder, DATA).unwrap();
+        let zlib = blob(|b| b.zlib_data = Some(encoder.finish().unwrap()));
+        assert_eq!(&decompress(&zlib).unwrap()[..], DATA);
+
+        let lz4 = blob(|b| b.lz4_data = Some(Vec::new()));
+        assert!(decompress(&lz4).is_err());
+        assert!(decompress(&Blob::default()).is_err());
+    }
+
+    #[cfg(feature = "lzma")]
+    #[test]
+    fn test_decompress_lzma() {
+        let options = xz2::stream::LzmaOptions::new_preset(6).unwrap();
+        let stream = xz2::stream::Stream::new_lzma_encoder(&options).unwrap();
+        let mut compressed = Vec::new();
+        xz2::read::XzEncoder::new_stream(DATA, stream)
+            .read_to_end(&mut compressed)
+            .unwrap();
+        let lzma = blob(|b| b.lzma_data = Some(compressed));
+        assert_eq!(&decompress(&lzma).unwrap()[..], DATA);
+    }
+
+    #[cfg(feature = "bzip2")]
+    #[test]
+    #[allow(deprecated)]
+    fn test_decompress_bzip2() {
+        let mut compressed = Vec::new();
+        bzip2::read::BzEncoder::new(DATA, Default::default())
+            .read_to_end(&mut compressed)
+            .unwrap();
+        let bzip2 = blob(|b| b.obsolete_bzip2_data = Some(compressed));
+        assert_eq!(&decompress(&bzip2).unwrap()[..], DATA);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_decompress_zstd() {
+        let zstd = blob(|b| b.zstd_data = Some(zstd::encode_all(DATA, 3).unwrap()));
+        assert_eq!(&decompress(&zstd).unwrap()[..], DATA);
+    }
 }
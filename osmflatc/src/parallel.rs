@@ -1,11 +1,25 @@
 use std::cmp::Reverse;
 use std::collections::BTreeMap;
+use std::num::NonZeroUsize;
 use std::sync::{mpsc::sync_channel, Arc};
 
 use parking_lot::{Condvar, Mutex};
 
+/// Default number of blocks per thread which are buffered between the
+/// producing threads and the consumer, cf. `Options::queue_depth`.
+pub const DEFAULT_QUEUE_DEPTH: NonZeroUsize = NonZeroUsize::new(2).unwrap();
+
+/// Produces data from the items of `iter` in parallel, and consumes it in the
+/// order of the items.
+///
+/// The number of producing threads is the number of threads of the current
+/// rayon thread pool, e.g. as configured by `--threads`. At most
+/// `queue_depth` items per thread are produced ahead of the consumer; deeper
+/// queues even out items which take different times to produce, at the cost
+/// of memory for the buffered data.
 pub fn parallel_process<Iter, Item, Producer, Data, Consumer, Error, Garbage>(
     iter: Iter,
+    queue_depth: NonZeroUsize,
     produce: Producer,
     mut consume: Consumer,
 ) -> Result<(), Error>
@@ -17,12 +31,13 @@ where
     Garbage: Send + 'static,
 {
     let num_threads = rayon::current_num_threads();
+    let queue_len = queue_depth.get() * num_threads;
 
     let iter = Arc::new(Mutex::new(iter.enumerate()));
-    let next = Arc::new((Mutex::new(2 * num_threads), Condvar::new()));
+    let next = Arc::new((Mutex::new(queue_len), Condvar::new()));
 
     crossbeam::scope(|s| {
-        let (sender, receiver) = sync_channel(2 * num_threads);
+        let (sender, receiver) = sync_channel(queue_len);
         for _ in 0..num_threads {
             let sender = sender.clone();
             let iter = iter.clone();
@@ -53,7 +68,7 @@ where
         }
         drop(sender); // drop to make sure iteration will finish once all senders are out of scope
 
-        let (garbage_sender, garbage_receiver) = sync_channel(2 * num_threads);
+        let (garbage_sender, garbage_receiver) = sync_channel(queue_len);
 
         std::thread::spawn(move || {
             // we move dropping of heavy objects to other threads as they can have a lot
@@ -0,0 +1,336 @@
+use log::info;
+use serde_json::json;
+
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+/// Stage of the conversion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stage {
+    /// Downloading the input from a remote URL
+    Download,
+    /// Indexing the blocks of the input
+    BlockIndex,
+    /// Selecting the entities matching the filter, and the entities referenced by
+    /// them
+    Select,
+    /// Counting the strings of the converted entities, to write them to the
+    /// stringtable by frequency
+    CountStrings,
+    /// Converting nodes and building the index of their ids
+    Nodes,
+    /// Converting ways and building the index of their ids
+    Ways,
+    /// Waiting for the index of relation ids, which is built in the background
+    /// while nodes and ways are converted
+    RelationsIndex,
+    /// Converting relations
+    Relations,
+    /// Converting changesets
+    Changesets,
+    /// Writing the stringtable
+    StringTable,
+    /// Computing the bounding boxes of ways and relations
+    BoundingBoxes,
+    /// Collecting the nodes having tags
+    TaggedNodes,
+    /// Collecting the bitsets of the nodes and ways having tags
+    TaggedBitsets,
+    /// Building the spatial index
+    SpatialIndex,
+    /// Building the inverted tag index
+    InvertedIndex,
+    /// Building the index of the entities by id
+    IdIndex,
+    /// Building the index of the ways containing each node
+    NodeWays,
+    /// Building the index of the relations having each entity as member
+    MemberRelations,
+    /// Assembling the areas of closed ways and multipolygons
+    Areas,
+    /// Sampling the elevation of the nodes
+    Elevation,
+    /// Building the index of the nodes and ways in each tile
+    TileIndex,
+    /// Finding the administrative boundaries containing each node
+    AdminHierarchy,
+    /// Parsing the values of numeric tags
+    TagNumbers,
+    /// Collecting the roles of relation members
+    Roles,
+    /// Computing the representative points of ways and relations
+    Centroids,
+    /// Verifying that the archive can be opened, and validating it if enabled
+    Verify,
+    /// Compressing the resources of the archive
+    Compress,
+}
+
+impl Stage {
+    /// Returns the name of the stage as identifier, e.g. `block_index`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::Download => "download",
+            Stage::BlockIndex => "block_index",
+            Stage::Select => "select",
+            Stage::CountStrings => "count_strings",
+            Stage::Nodes => "nodes",
+            Stage::Ways => "ways",
+            Stage::RelationsIndex => "relations_index",
+            Stage::Relations => "relations",
+            Stage::Changesets => "changesets",
+            Stage::StringTable => "stringtable",
+            Stage::BoundingBoxes => "bboxes",
+            Stage::TaggedNodes => "tagged_nodes",
+            Stage::TaggedBitsets => "tagged_bitsets",
+            Stage::SpatialIndex => "spatial_index",
+            Stage::InvertedIndex => "inverted_index",
+            Stage::IdIndex => "id_index",
+            Stage::NodeWays => "node_ways",
+            Stage::MemberRelations => "member_relations",
+            Stage::Areas => "areas",
+            Stage::Elevation => "elevation",
+            Stage::TileIndex => "tile_index",
+            Stage::AdminHierarchy => "admin_hierarchy",
+            Stage::TagNumbers => "tag_numbers",
+            Stage::Roles => "roles",
+            Stage::Centroids => "centroids",
+            Stage::Verify => "verify",
+            Stage::Compress => "compress",
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Stage::Download => "Downloading input",
+            Stage::BlockIndex => "Building block index",
+            Stage::Select => "Selecting entities",
+            Stage::CountStrings => "Counting strings",
+            Stage::Nodes => "Converting dense nodes",
+            Stage::Ways => "Converting ways",
+            Stage::RelationsIndex => "Building relations index",
+            Stage::Relations => "Converting relations",
+            Stage::Changesets => "Converting changesets",
+            Stage::StringTable => "Writing stringtable",
+            Stage::BoundingBoxes => "Computing bounding boxes",
+            Stage::TaggedNodes => "Collecting tagged nodes",
+            Stage::TaggedBitsets => "Collecting tagged bitsets",
+            Stage::SpatialIndex => "Building spatial index",
+            Stage::InvertedIndex => "Building inverted tag index",
+            Stage::IdIndex => "Building id index",
+            Stage::NodeWays => "Building node ways index",
+            Stage::MemberRelations => "Building member relations index",
+            Stage::Areas => "Assembling areas",
+            Stage::Elevation => "Sampling elevation",
+            Stage::TileIndex => "Building tile index",
+            Stage::AdminHierarchy => "Building admin hierarchy",
+            Stage::TagNumbers => "Parsing numeric tags",
+            Stage::Roles => "Collecting roles",
+            Stage::Centroids => "Computing centroids",
+            Stage::Verify => "Verifying archive",
+            Stage::Compress => "Compressing resources",
+        };
+        f.pad(name)
+    }
+}
+
+/// Receiver of the progress of a conversion.
+///
+/// Stages are started and finished in order; all methods are called from the
+/// thread running the conversion. All methods default to doing nothing.
+pub trait Progress {
+    /// A stage is started; `num_blocks` is the number of input blocks to be
+    /// processed in the stage, if it processes blocks.
+    fn stage_started(&self, _stage: Stage, _num_blocks: Option<usize>) {}
+
+    /// An input block of the stage was processed.
+    fn block_processed(&self, _stage: Stage) {}
+
+    /// A stage is finished.
+    fn stage_finished(&self, _stage: Stage) {}
+}
+
+/// Ignores the progress.
+impl Progress for () {}
+
+impl<P: Progress + ?Sized> Progress for &P {
+    fn stage_started(&self, stage: Stage, num_blocks: Option<usize>) {
+        (**self).stage_started(stage, num_blocks)
+    }
+
+    fn block_processed(&self, stage: Stage) {
+        (**self).block_processed(stage)
+    }
+
+    fn stage_finished(&self, stage: Stage) {
+        (**self).stage_finished(stage)
+    }
+}
+
+/// Reports the progress as log messages, for output which is not a terminal,
+/// e.g. CI logs.
+///
+/// Stages processing blocks log their progress in steps of 10%.
+#[derive(Debug, Default)]
+pub struct LogProgress {
+    num_blocks: Cell<Option<usize>>,
+    processed: Cell<usize>,
+}
+
+impl Progress for LogProgress {
+    fn stage_started(&self, stage: Stage, num_blocks: Option<usize>) {
+        self.num_blocks.set(num_blocks);
+        self.processed.set(0);
+        match num_blocks {
+            Some(num_blocks) => info!("{stage}: {num_blocks} blocks..."),
+            None => info!("{stage}..."),
+        }
+    }
+
+    fn block_processed(&self, stage: Stage) {
+        let processed = self.processed.get() + 1;
+        self.processed.set(processed);
+        if let Some(num_blocks) = self.num_blocks.get() {
+            let percent = |n: usize| n * 100 / num_blocks.max(1);
+            if percent(processed) / 10 > percent(processed - 1) / 10 {
+                info!("{stage}: {processed}/{num_blocks} blocks");
+            }
+        }
+    }
+
+    fn stage_finished(&self, stage: Stage) {
+        info!("{stage} done.");
+    }
+}
+
+/// Writes the progress as JSON lines, e.g. to be consumed by a wrapper
+/// showing it in a GUI.
+///
+/// Each line is an object with the `event` (`stage_started`,
+/// `block_processed`, or `stage_finished`) and the name of the `stage`, cf.
+/// [`Stage::name`]. Started stages have the number of blocks they process in
+/// `num_blocks`, which is `null` if they do not process blocks; processed
+/// blocks have the number of blocks processed so far in the stage in
+/// `processed`.
+#[derive(Debug)]
+pub struct JsonProgress<W> {
+    out: RefCell<W>,
+    processed: Cell<usize>,
+}
+
+impl<W: Write> JsonProgress<W> {
+    /// Writes the progress to `out`.
+    pub fn new(out: W) -> Self {
+        Self {
+            out: RefCell::new(out),
+            processed: Cell::new(0),
+        }
+    }
+
+    fn write(&self, event: serde_json::Value) {
+        let mut out = self.out.borrow_mut();
+        // progress is best effort and must not fail the conversion
+        let _ = writeln!(out, "{event}").and_then(|()| out.flush());
+    }
+}
+
+impl<W: Write> Progress for JsonProgress<W> {
+    fn stage_started(&self, stage: Stage, num_blocks: Option<usize>) {
+        self.processed.set(0);
+        self.write(json!({
+            "event": "stage_started",
+            "stage": stage.name(),
+            "num_blocks": num_blocks,
+        }));
+    }
+
+    fn block_processed(&self, stage: Stage) {
+        self.processed.set(self.processed.get() + 1);
+        self.write(json!({
+            "event": "block_processed",
+            "stage": stage.name(),
+            "processed": self.processed.get(),
+        }));
+    }
+
+    fn stage_finished(&self, stage: Stage) {
+        self.write(json!({ "event": "stage_finished", "stage": stage.name() }));
+    }
+}
+
+/// Forwards the progress and measures the duration of the stages.
+pub(crate) struct StageTimer<P> {
+    inner: P,
+    started: RefCell<Option<Instant>>,
+    durations: RefCell<Vec<(Stage, Duration)>>,
+}
+
+impl<P: Progress> StageTimer<P> {
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            started: RefCell::new(None),
+            durations: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Returns the durations of the finished stages in order.
+    pub fn into_durations(self) -> Vec<(Stage, Duration)> {
+        self.durations.into_inner()
+    }
+}
+
+impl<P: Progress> Progress for StageTimer<P> {
+    fn stage_started(&self, stage: Stage, num_blocks: Option<usize>) {
+        *self.started.borrow_mut() = Some(Instant::now());
+        self.inner.stage_started(stage, num_blocks)
+    }
+
+    fn block_processed(&self, stage: Stage) {
+        self.inner.block_processed(stage)
+    }
+
+    fn stage_finished(&self, stage: Stage) {
+        if let Some(started) = self.started.borrow_mut().take() {
+            self.durations.borrow_mut().push((stage, started.elapsed()));
+        }
+        self.inner.stage_finished(stage)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_json_progress() {
+        let mut out = Vec::new();
+        {
+            let progress = JsonProgress::new(&mut out);
+            progress.stage_started(Stage::Nodes, Some(2));
+            progress.block_processed(Stage::Nodes);
+            progress.block_processed(Stage::Nodes);
+            progress.stage_finished(Stage::Nodes);
+            progress.stage_started(Stage::Verify, None);
+        }
+        let events: Vec<serde_json::Value> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            events,
+            [
+                json!({ "event": "stage_started", "stage": "nodes", "num_blocks": 2 }),
+                json!({ "event": "block_processed", "stage": "nodes", "processed": 1 }),
+                json!({ "event": "block_processed", "stage": "nodes", "processed": 2 }),
+                json!({ "event": "stage_finished", "stage": "nodes" }),
+                json!({ "event": "stage_started", "stage": "verify", "num_blocks": null }),
+            ]
+        );
+    }
+}
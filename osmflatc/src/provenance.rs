@@ -0,0 +1,208 @@
+//! Description of how an archive was produced, stored in its `provenance`
+//! resource.
+
+use crate::convert::{NodeOrder, Options};
+use crate::stats::Stats;
+
+use flatdata::{FileResourceStorage, ResourceStorage};
+use itertools::Itertools;
+use rayon::prelude::*;
+use serde_json::json;
+
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// CRC32 checksum of the input, computed in parallel.
+pub fn checksum(data: &[u8]) -> u32 {
+    checksum_in_chunks(data, 64 << 20)
+}
+
+fn checksum_in_chunks(data: &[u8], chunk_size: usize) -> u32 {
+    data.par_chunks(chunk_size)
+        .map(|chunk| {
+            let mut hasher = crc32fast::Hasher::new();
+            hasher.update(chunk);
+            hasher
+        })
+        .reduce(crc32fast::Hasher::new, |mut hasher, other| {
+            hasher.combine(&other);
+            hasher
+        })
+        .finalize()
+}
+
+/// Reader computing the CRC32 checksum of the data read from it, e.g. of an
+/// input which is not stored locally; the result equals [`checksum`].
+pub struct ChecksumReader<R> {
+    reader: R,
+    hasher: crc32fast::Hasher,
+}
+
+impl<R: Read> ChecksumReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            hasher: crc32fast::Hasher::new(),
+        }
+    }
+
+    /// Checksum of the data read so far.
+    pub fn checksum(&self) -> u32 {
+        self.hasher.clone().finalize()
+    }
+}
+
+impl<R: Read> Read for ChecksumReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.reader.read(buf)?;
+        self.hasher.update(&buf[..len]);
+        Ok(len)
+    }
+}
+
+/// Command-line flags of osmflatc corresponding to `options`.
+fn flags(options: &Options) -> Vec<String> {
+    let mut flags = Vec::new();
+    for (enabled, flag) in [(options.ids, "--ids"), (options.history, "--history")] {
+        if enabled {
+            flags.push(flag.to_string());
+        }
+    }
+    for filter in &options.filter {
+        flags.extend(["--filter".to_string(), filter.to_string()]);
+    }
+    if !options.keep_tags.is_empty() {
+        flags.extend([
+            "--keep-tags".to_string(),
+            options.keep_tags.iter().join(","),
+        ]);
+    }
+    if !options.drop_tags.is_empty() {
+        flags.extend([
+            "--drop-tags".to_string(),
+            options.drop_tags.iter().join(","),
+        ]);
+    }
+    if options.drop_untagged_nodes {
+        flags.push("--drop-untagged-nodes".to_string());
+    }
+    if options.sort == NodeOrder::Hilbert {
+        flags.extend(["--sort".to_string(), "hilbert".to_string()]);
+    }
+    if options.optimize_stringtable {
+        flags.push("--optimize-stringtable".to_string());
+    }
+    if let Some(coord_scale) = options.coord_scale {
+        flags.extend(["--coord-scale".to_string(), coord_scale.to_string()]);
+    }
+    if let Some(dir) = &options.elevation {
+        flags.extend(["--elevation".to_string(), dir.display().to_string()]);
+    }
+    if let Some(zoom) = options.tile_index {
+        flags.extend(["--tile-index".to_string(), zoom.to_string()]);
+    }
+    if !options.numeric_tags.is_empty() {
+        flags.extend([
+            "--numeric-tags".to_string(),
+            options.numeric_tags.iter().join(","),
+        ]);
+    }
+    if let Some(compression) = options.compress {
+        flags.extend(["--compress".to_string(), compression.to_string()]);
+    }
+    for (enabled, flag) in [
+        (options.changesets, "--changesets"),
+        (options.bboxes, "--bboxes"),
+        (options.tagged_nodes, "--tagged-nodes"),
+        (options.tagged_bitsets, "--tagged-bitsets"),
+        (options.spatial_index, "--spatial-index"),
+        (options.inverted_index, "--inverted-index"),
+        (options.id_index, "--id-index"),
+        (options.node_ways, "--node-ways"),
+        (options.member_relations, "--member-relations"),
+        (options.areas, "--areas"),
+        (options.stringtable_offsets, "--stringtable-offsets"),
+        (options.node_coords, "--node-coords"),
+        (options.admin_hierarchy, "--admin-hierarchy"),
+        (options.roles, "--roles"),
+        (options.centroids, "--centroids"),
+        (options.verify, "--verify"),
+    ] {
+        if enabled {
+            flags.push(flag.to_string());
+        }
+    }
+    flags
+}
+
+fn to_json(input: &Path, input_crc32: u32, options: &Options, stats: &Stats) -> serde_json::Value {
+    let converted_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    json!({
+        "osmflatc_version": env!("CARGO_PKG_VERSION"),
+        "flags": flags(options),
+        "input": {
+            "path": input.to_string_lossy(),
+            "crc32": format!("{input_crc32:08x}"),
+        },
+        "converted_at": converted_at,
+        "stats": stats.to_json(),
+    })
+}
+
+/// Writes the `provenance` resource of the archive at `output`.
+pub fn write(
+    output: &Path,
+    input: &Path,
+    input_crc32: u32,
+    options: &Options,
+    stats: &Stats,
+) -> io::Result<()> {
+    let provenance = serde_json::to_vec_pretty(&to_json(input, input_crc32, options, stats))?;
+    FileResourceStorage::new(PathBuf::from(output)).write(
+        "provenance",
+        osmflat::schema::osm::resources::PROVENANCE,
+        &provenance,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_checksum() {
+        assert_eq!(checksum(b""), 0);
+        assert_eq!(checksum(b"123456789"), 0xcbf4_3926);
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        assert_eq!(checksum_in_chunks(&data, 999), checksum(&data));
+
+        let mut reader = ChecksumReader::new(&data[..]);
+        io::copy(&mut reader, &mut io::sink()).unwrap();
+        assert_eq!(reader.checksum(), checksum(&data));
+    }
+
+    #[test]
+    fn test_flags() {
+        let options = Options {
+            ids: true,
+            filter: vec!["w/highway".parse().unwrap()],
+            sort: NodeOrder::Hilbert,
+            verify: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            flags(&options),
+            [
+                "--ids",
+                "--filter",
+                "w/highway",
+                "--sort",
+                "hilbert",
+                "--verify"
+            ]
+        );
+    }
+}
@@ -0,0 +1,308 @@
+//! Input from remote URLs.
+//!
+//! PBF files are converted without storing them locally if the server supports
+//! range requests: the file is streamed once to build the index of its blocks,
+//! and then the blocks are requested individually while they are converted. Since the conversion reads some blocks in several
+//! passes, e.g. relations are read twice, more than the size of the file is
+//! downloaded in total.
+//!
+//! Otherwise, and for OPL files, the input is streamed into an anonymous
+//! temporary file, which is removed by the OS as soon as the conversion
+//! finishes. Then the input occupies the local disk once, but is downloaded
+//! only once.
+//!
+//! Downloading requires the feature `remote`.
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Whether the input is a remote URL.
+pub fn is_url(input: &Path) -> bool {
+    input
+        .to_str()
+        .is_some_and(|s| s.starts_with("https://") || s.starts_with("http://"))
+}
+
+#[cfg(feature = "remote")]
+fn download_error(url: &str, e: impl std::fmt::Display) -> io::Error {
+    io::Error::other(format!("failed to download {url}: {e}"))
+}
+
+#[cfg(not(feature = "remote"))]
+fn unsupported(url: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("downloading {url} is not supported, enable the feature `remote`"),
+    )
+}
+
+/// Agent shared by all requests, which reuses connections to the same server.
+#[cfg(feature = "remote")]
+fn agent() -> &'static ureq::Agent {
+    static AGENT: std::sync::OnceLock<ureq::Agent> = std::sync::OnceLock::new();
+    AGENT.get_or_init(ureq::Agent::new)
+}
+
+/// Requests `url` and returns the body of the response.
+pub fn open(url: &str) -> io::Result<impl Read + Send> {
+    #[cfg(feature = "remote")]
+    {
+        let response = agent()
+            .get(url)
+            .call()
+            .map_err(|e| download_error(url, e))?;
+        Ok(response.into_reader())
+    }
+    #[cfg(not(feature = "remote"))]
+    {
+        Err::<io::Empty, _>(unsupported(url))
+    }
+}
+
+/// Whether the server of `url` supports range requests for it.
+pub fn supports_ranges(url: &str) -> io::Result<bool> {
+    #[cfg(feature = "remote")]
+    {
+        let response = agent()
+            .head(url)
+            .call()
+            .map_err(|e| download_error(url, e))?;
+        Ok(response.header("Accept-Ranges") == Some("bytes"))
+    }
+    #[cfg(not(feature = "remote"))]
+    {
+        Err(unsupported(url))
+    }
+}
+
+/// Downloads the `len` bytes of `url` starting at `start` with a range
+/// request.
+pub fn fetch_range(url: &str, start: usize, len: usize) -> io::Result<Vec<u8>> {
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+    #[cfg(feature = "remote")]
+    {
+        let range = format!("bytes={start}-{}", start + len - 1);
+        let response =
+            (agent().get(url).set("Range", &range).call()).map_err(|e| download_error(url, e))?;
+        if response.status() != 206 {
+            let status = response.status();
+            return Err(download_error(
+                url,
+                format!("range not supported: {status}"),
+            ));
+        }
+        let mut data = Vec::with_capacity(len);
+        response.into_reader().read_to_end(&mut data)?;
+        if data.len() != len {
+            let e = format!("expected {len} bytes of {range}, got {}", data.len());
+            return Err(download_error(url, e));
+        }
+        Ok(data)
+    }
+    #[cfg(not(feature = "remote"))]
+    {
+        let _ = start;
+        Err(unsupported(url))
+    }
+}
+
+/// Downloads `url` into an anonymous temporary file.
+pub fn download(url: &str) -> io::Result<File> {
+    let mut file = tempfile::tempfile()?;
+    io::copy(&mut open(url)?, &mut file)?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(file)
+}
+
+/// Downloads `url` into memory.
+pub fn fetch(url: &str) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    open(url)?.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg(feature = "remote")]
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_is_url() {
+        assert!(is_url(Path::new("https://example.com/berlin.osm.pbf")));
+        assert!(is_url(Path::new("http://example.com/berlin.opl")));
+        assert!(!is_url(Path::new("berlin.osm.pbf")));
+        assert!(!is_url(Path::new("/data/https://berlin.osm.pbf")));
+    }
+
+    /// Responds once to a request on a local port, and returns its URL.
+    #[cfg(feature = "remote")]
+    fn serve(status: &'static str, body: &'static [u8]) -> String {
+        use std::io::Write;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/input.osm.pbf", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 4096];
+            let _ = stream.read(&mut request).unwrap();
+            write!(
+                stream,
+                "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            )
+            .unwrap();
+            stream.write_all(body).unwrap();
+        });
+        url
+    }
+
+    /// Serves `body` on a local port for any number of requests, supporting
+    /// range requests if `ranges` is set, and returns its URL and the ranges
+    /// requested so far.
+    #[cfg(feature = "remote")]
+    fn serve_file(body: Vec<u8>, ranges: bool) -> (String, Arc<Mutex<Vec<String>>>) {
+        use std::io::{BufRead, BufReader, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/input.osm.pbf", listener.local_addr().unwrap());
+        let requested = Arc::new(Mutex::new(Vec::new()));
+        let requested_ranges = Arc::clone(&requested);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut lines = BufReader::new(stream.try_clone().unwrap()).lines();
+                let request = lines.next().unwrap().unwrap();
+                let mut range = None;
+                for line in lines {
+                    let line = line.unwrap();
+                    if line.is_empty() {
+                        break;
+                    }
+                    let (name, value) = line.split_once(": ").unwrap();
+                    if name.eq_ignore_ascii_case("range") {
+                        range = value.strip_prefix("bytes=").map(str::to_string);
+                    }
+                }
+
+                let (status, data) = match range.filter(|_| ranges) {
+                    Some(range) => {
+                        let (start, end) = range.split_once('-').unwrap();
+                        let (start, end): (usize, usize) =
+                            (start.parse().unwrap(), end.parse().unwrap());
+                        requested_ranges.lock().unwrap().push(range);
+                        ("206 Partial Content", &body[start..=end])
+                    }
+                    None => ("200 OK", &body[..]),
+                };
+                let accept_ranges = if ranges { "bytes" } else { "none" };
+                write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nContent-Length: {}\r\nAccept-Ranges: {accept_ranges}\r\n\
+                     Connection: close\r\n\r\n",
+                    data.len()
+                )
+                .unwrap();
+                if !request.starts_with("HEAD") {
+                    stream.write_all(data).unwrap();
+                }
+            }
+        });
+        (url, requested)
+    }
+
+    #[test]
+    #[cfg(feature = "remote")]
+    fn test_fetch_range() {
+        let (url, requested) = serve_file(b"0123456789".to_vec(), true);
+        assert!(supports_ranges(&url).unwrap());
+        assert_eq!(fetch_range(&url, 2, 3).unwrap(), b"234");
+        assert_eq!(*requested.lock().unwrap(), ["2-4"]);
+
+        let (url, _) = serve_file(b"0123456789".to_vec(), false);
+        assert!(!supports_ranges(&url).unwrap());
+        let err = fetch_range(&url, 2, 3).unwrap_err();
+        assert!(err.to_string().contains("range not supported"), "{err}");
+    }
+
+    /// Converts `opl` to pbf, and returns the pbf file and the archive
+    /// converted from it locally.
+    #[cfg(feature = "remote")]
+    fn local_pbf(dir: &Path, opl: &str) -> (Vec<u8>, std::path::PathBuf) {
+        let options = || crate::Options {
+            ids: true,
+            ..Default::default()
+        };
+        let input = dir.join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+        let archive = dir.join("archive");
+        crate::convert(&input, &archive, options(), ()).unwrap();
+        let pbf = dir.join("input.osm.pbf");
+        crate::export_pbf(&archive, &pbf).unwrap();
+        let local = dir.join("local");
+        crate::convert(&pbf, &local, options(), ()).unwrap();
+        (std::fs::read(pbf).unwrap(), local)
+    }
+
+    #[test]
+    #[cfg(feature = "remote")]
+    fn test_convert_remote() {
+        let opl = "n1 v1 Tname=a x1 y1\nn2 v1 x2 y2\nn3 v1 x3 y3\n\
+                   w10 v1 Thighway=primary Nn1,n2\nr20 v1 Ttype=route Mw10@,n3@stop\n";
+        let dir = tempfile::tempdir().unwrap();
+        let (pbf, local) = local_pbf(dir.path(), opl);
+
+        for ranges in [true, false] {
+            let (url, requested) = serve_file(pbf.clone(), ranges);
+            let output = dir.path().join(format!("remote-{ranges}"));
+            let options = crate::Options {
+                ids: true,
+                ..Default::default()
+            };
+            crate::convert(Path::new(&url), &output, options, ()).unwrap();
+            // without range requests, the input is downloaded into a file
+            assert_eq!(requested.lock().unwrap().is_empty(), !ranges);
+
+            for resource in [
+                "nodes",
+                "ways",
+                "relations",
+                "tags",
+                "stringtable",
+                "ids/nodes",
+            ] {
+                assert_eq!(
+                    std::fs::read(output.join(resource)).unwrap(),
+                    std::fs::read(local.join(resource)).unwrap(),
+                    "{resource}"
+                );
+            }
+            let provenance = |archive: &Path| -> serde_json::Value {
+                let storage = flatdata::FileResourceStorage::new(archive.to_path_buf());
+                let archive = osmflat::Osm::open(storage).unwrap();
+                serde_json::from_slice(archive.provenance().unwrap().as_bytes()).unwrap()
+            };
+            assert_eq!(
+                provenance(&output)["input"]["crc32"],
+                provenance(&local)["input"]["crc32"]
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "remote")]
+    fn test_download() {
+        let mut file = download(&serve("200 OK", b"data")).unwrap();
+        let mut data = String::new();
+        file.read_to_string(&mut data).unwrap();
+        assert_eq!(data, "data");
+
+        assert_eq!(fetch(&serve("200 OK", b"state")).unwrap(), b"state");
+        let err = fetch(&serve("404 Not Found", b"")).unwrap_err();
+        assert!(err.to_string().contains("404"), "{err}");
+    }
+}
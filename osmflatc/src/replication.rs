@@ -0,0 +1,390 @@
+//! Keeping an archive up to date with the OSM replication service.
+//!
+//! The replication base URL and the sequence number of the last applied change
+//! are read from the header of the archive. New changes are downloaded as
+//! OsmChange files and merged with the OPL representation of the archive,
+//! which is then converted to a new archive replacing the old one.
+//!
+//! Since a full archive is written for each update, the archive has to contain
+//! the `ids` subarchive. Relation members which could not be resolved in the
+//! archive are lost in an update.
+
+use crate::compress;
+use crate::convert::{self, Options};
+use crate::export;
+use crate::opl;
+use crate::osc::{self, Changes};
+use crate::osmpbf::{self, BlockType};
+use crate::progress::{Progress, Stage};
+use crate::remote;
+use crate::Error;
+
+use itertools::Itertools;
+use log::{info, warn};
+use osmflat::{iter_tags, Osm, RelationMembersRef};
+
+use std::fmt;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// Maximum time [`follow`] waits before retrying a failed download.
+const MAX_RETRY_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// State of a replication stream, as published in `state.txt`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct State {
+    pub sequence_number: i64,
+    /// Seconds since the epoch
+    pub timestamp: i64,
+}
+
+impl State {
+    fn parse(s: &str) -> io::Result<Self> {
+        let value = |key| {
+            s.lines()
+                .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+                .map(|value| value.trim().replace('\\', ""))
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("missing {key}")))
+        };
+        let sequence_number = value("sequenceNumber")?
+            .parse()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid sequenceNumber"))?;
+        let timestamp = opl::parse_timestamp(value("timestamp")?.as_bytes())?;
+        Ok(Self {
+            sequence_number,
+            timestamp,
+        })
+    }
+}
+
+/// URL of a file of the replication stream with the given sequence number,
+/// e.g. `<base_url>/000/123/456.osc.gz`.
+fn sequence_url(base_url: &str, sequence_number: i64, ext: &str) -> String {
+    let n = sequence_number;
+    format!(
+        "{}/{:03}/{:03}/{:03}.{ext}",
+        base_url.trim_end_matches('/'),
+        n / 1_000_000,
+        n / 1000 % 1000,
+        n % 1000
+    )
+}
+
+/// Failed download from the replication service, which might succeed when
+/// retried later.
+#[derive(Debug)]
+struct DownloadError(io::Error);
+
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for DownloadError {}
+
+fn fetch(url: &str) -> Result<Vec<u8>, DownloadError> {
+    remote::fetch(url).map_err(DownloadError)
+}
+
+fn fetch_changes(base_url: &str, sequence_number: i64, changes: &mut Changes) -> Result<(), Error> {
+    let data = fetch(&sequence_url(base_url, sequence_number, "osc.gz"))?;
+    let mut xml = String::new();
+    flate2::read::GzDecoder::new(&data[..]).read_to_string(&mut xml)?;
+    osc::parse(&xml, changes)?;
+    Ok(())
+}
+
+fn write_line(out: &mut impl Write, line: &[u8]) -> io::Result<()> {
+    out.write_all(line)?;
+    out.write_all(b"\n")
+}
+
+/// Writes the changed entities of a type with ids in `..until` in order.
+fn write_changes_until(
+    out: &mut impl Write,
+    changes: &mut Changes,
+    block_type: BlockType,
+    until: Option<i64>,
+) -> io::Result<()> {
+    while let Some(entry) = changes.first_entry() {
+        let (entry_type, id) = *entry.key();
+        if entry_type != block_type || until.is_some_and(|until| id >= until) {
+            break;
+        }
+        if let Some(line) = entry.remove() {
+            write_line(out, &line)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_tags(archive: &Osm, tags: std::ops::Range<u64>, line: &mut Vec<u8>) {
+    line.extend_from_slice(b" T");
+    for (i, (key, value)) in iter_tags(archive, tags).enumerate() {
+        if i > 0 {
+            line.push(b',');
+        }
+        opl::escape(key, line);
+        line.push(b'=');
+        opl::escape(value, line);
+    }
+}
+
+/// Writes the entities of `archive` as OPL, replaced by the `changes`.
+///
+/// Ways and relations are expected to be sorted by id in the archive; nodes
+/// may be sorted differently, cf. `--sort`.
+fn write_opl(archive: &Osm, mut changes: Changes, out: &mut impl Write) -> Result<(), Error> {
+    let ids = archive
+        .ids()
+        .ok_or("replication requires an archive with the ids subarchive")?;
+    let (node_ids, way_ids, relation_ids) = (ids.nodes(), ids.ways(), ids.relations());
+    let scale = f64::from(archive.header().coord_scale());
+    let nodes_index = archive.nodes_index();
+    let strings = archive.stringtable();
+
+    let nodes = archive.nodes();
+    let mut order: Vec<usize> = (0..nodes.len()).collect();
+    if !node_ids
+        .windows(2)
+        .all(|w| w[0].signed_value() <= w[1].signed_value())
+    {
+        // stable, so that the versions of a node in history mode keep their order
+        order.sort_by_key(|&idx| node_ids[idx].signed_value());
+    }
+
+    let mut line = Vec::new();
+    for idx in order {
+        let node = &nodes[idx];
+        let id = node_ids[idx].signed_value();
+        write_changes_until(out, &mut changes, BlockType::DenseNodes, Some(id))?;
+        if changes.contains_key(&(BlockType::DenseNodes, id)) {
+            continue;
+        }
+        line.clear();
+        write!(
+            line,
+            "n{id} dV x{:.7} y{:.7}",
+            f64::from(node.lon()) / scale,
+            f64::from(node.lat()) / scale
+        )?;
+        write_tags(archive, node.tags(), &mut line);
+        write_line(out, &line)?;
+    }
+    write_changes_until(out, &mut changes, BlockType::DenseNodes, None)?;
+
+    for (idx, way) in archive.ways().iter().enumerate() {
+        let id = way_ids[idx].signed_value();
+        write_changes_until(out, &mut changes, BlockType::Ways, Some(id))?;
+        if changes.contains_key(&(BlockType::Ways, id)) {
+            continue;
+        }
+        line.clear();
+        write!(line, "w{id} dV")?;
+        write_tags(archive, way.tags(), &mut line);
+        line.extend_from_slice(b" N");
+        let refs = way
+            .refs()
+            .filter_map(|i| nodes_index[i as usize].value())
+            .map(|node_idx| format!("n{}", node_ids[node_idx as usize].signed_value()))
+            .join(",");
+        line.extend(refs.bytes());
+        write_line(out, &line)?;
+    }
+    write_changes_until(out, &mut changes, BlockType::Ways, None)?;
+
+    let relation_members = archive.relation_members();
+    for (idx, relation) in archive.relations().iter().enumerate() {
+        let id = relation_ids[idx].signed_value();
+        write_changes_until(out, &mut changes, BlockType::Relations, Some(id))?;
+        if changes.contains_key(&(BlockType::Relations, id)) {
+            continue;
+        }
+        line.clear();
+        write!(line, "r{id} dV")?;
+        write_tags(archive, relation.tags(), &mut line);
+        line.extend_from_slice(b" M");
+        let mut first = true;
+        for member in relation_members.at(idx) {
+            let (member, role_idx) = match member {
+                RelationMembersRef::NodeMember(m) => (
+                    m.node_idx()
+                        .map(|i| format!("n{}", node_ids[i as usize].signed_value())),
+                    m.role_idx(),
+                ),
+                RelationMembersRef::WayMember(m) => (
+                    m.way_idx()
+                        .map(|i| format!("w{}", way_ids[i as usize].signed_value())),
+                    m.role_idx(),
+                ),
+                RelationMembersRef::RelationMember(m) => (
+                    m.relation_idx()
+                        .map(|i| format!("r{}", relation_ids[i as usize].signed_value())),
+                    m.role_idx(),
+                ),
+            };
+            if let Some(member) = member {
+                if !first {
+                    line.push(b',');
+                }
+                first = false;
+                line.extend(member.bytes());
+                line.push(b'@');
+                opl::escape(strings.substring_raw(role_idx as usize), &mut line);
+            }
+        }
+        write_line(out, &line)?;
+    }
+    write_changes_until(out, &mut changes, BlockType::Relations, None)?;
+    Ok(())
+}
+
+/// Header of the updated archive, taken over from the current one.
+fn updated_header(archive: &Osm, base_url: &str, state: &State) -> osmpbf::HeaderBlock {
+    osmpbf::HeaderBlock {
+        osmosis_replication_timestamp: Some(state.timestamp),
+        osmosis_replication_sequence_number: Some(state.sequence_number),
+        osmosis_replication_base_url: Some(base_url.into()),
+        ..export::header_block(archive)
+    }
+}
+
+/// Applies all changes published since the last update to `archive`.
+///
+/// Returns the new replication state, or `None` if the archive is up to date.
+pub fn update(
+    archive_path: &Path,
+    options: &Options,
+    progress: impl Progress,
+) -> Result<Option<State>, Error> {
+    convert::recover_archive(archive_path)?;
+    let archive = compress::open_archive(archive_path)?;
+    let header = archive.header();
+    let base_url = archive
+        .replication()
+        .and_then(|replication| replication.base_url)
+        .ok_or("archive header contains no replication base url")?;
+    let current = header.replication_sequence_number();
+
+    let state = State::parse(&String::from_utf8_lossy(&fetch(&format!(
+        "{}/state.txt",
+        base_url.trim_end_matches('/')
+    ))?))?;
+    if state.sequence_number < current {
+        return Err(format!(
+            "sequence number {} of {base_url} is behind the sequence number {current} of the archive",
+            state.sequence_number
+        )
+        .into());
+    }
+    if state.sequence_number == current {
+        return Ok(None);
+    }
+
+    progress.stage_started(
+        Stage::Download,
+        Some((state.sequence_number - current) as usize),
+    );
+    let mut changes = Changes::new();
+    for sequence_number in current + 1..=state.sequence_number {
+        fetch_changes(&base_url, sequence_number, &mut changes)?;
+        progress.block_processed(Stage::Download);
+    }
+    progress.stage_finished(Stage::Download);
+
+    let mut opl_file = tempfile::Builder::new().suffix(".opl").tempfile()?;
+    {
+        let mut out = BufWriter::new(opl_file.as_file_mut());
+        write_opl(&archive, changes, &mut out)?;
+        out.flush()?;
+    }
+    let header = updated_header(&archive, &base_url, &state);
+    drop(archive);
+
+    // Build the new archive next to the old one, and swap them.
+    let updated = convert::temp_archive_dir(archive_path)?;
+    let mut options = options.clone();
+    options.ids = true;
+    convert::convert_with_header(
+        opl_file.path(),
+        updated.path(),
+        options,
+        Some(header),
+        progress,
+    )?;
+    convert::replace_archive(&updated.keep(), archive_path)?;
+
+    Ok(Some(state))
+}
+
+/// Keeps `archive` up to date by polling the replication service every
+/// `interval`.
+///
+/// Failed downloads are logged and retried with an exponentially increasing
+/// interval up to an hour, since the replication service might be temporarily
+/// unavailable. Runs until any other error occurs, e.g. if the archive is
+/// corrupt or the sequence numbers of the service went backwards.
+pub fn follow(
+    archive: &Path,
+    options: &Options,
+    interval: Duration,
+    progress: impl Progress,
+) -> Result<(), Error> {
+    let mut retry_interval = interval;
+    loop {
+        match update(archive, options, &progress) {
+            Ok(state) => {
+                if let Some(state) = state {
+                    info!(
+                        "Updated {} to sequence number {}",
+                        archive.display(),
+                        state.sequence_number
+                    );
+                }
+                retry_interval = interval;
+                std::thread::sleep(interval);
+            }
+            Err(e) if e.is::<DownloadError>() => {
+                warn!("{e}, retrying in {}s", retry_interval.as_secs());
+                std::thread::sleep(retry_interval);
+                retry_interval = (retry_interval * 2).min(MAX_RETRY_INTERVAL.max(interval));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_state() {
+        let state = State::parse(
+            "#Sat Jan 06 20:21:02 UTC 2024\nsequenceNumber=3950\ntimestamp=2024-01-06T20\\:20\\:53Z\n",
+        )
+        .unwrap();
+        assert_eq!(
+            state,
+            State {
+                sequence_number: 3950,
+                timestamp: 1_704_572_453,
+            }
+        );
+        assert!(State::parse("timestamp=2024-01-06T20\\:20\\:53Z").is_err());
+    }
+
+    #[test]
+    fn test_sequence_url() {
+        assert_eq!(
+            sequence_url(
+                "https://planet.osm.org/replication/minute/",
+                6_012_345,
+                "osc.gz"
+            ),
+            "https://planet.osm.org/replication/minute/006/012/345.osc.gz"
+        );
+    }
+}
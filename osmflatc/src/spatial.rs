@@ -0,0 +1,357 @@
+//! Bounding boxes and spatial index of a converted archive.
+
+use osmflat::{
+    build_spatial_index, way_bbox, BBox, BoundingBox, Osm, OsmBuilder, RelationMembersRef,
+    SpatialIndexBuilder,
+};
+
+use std::io;
+
+fn extend(bbox: &mut Option<BBox>, other: &BBox) {
+    match bbox {
+        Some(bbox) => bbox.extend(other),
+        None => *bbox = Some(*other),
+    }
+}
+
+fn node_bbox(archive: &Osm, idx: u64) -> BBox {
+    let (lon, lat) = archive.node_coord_scaled(idx as usize);
+    BBox::from_coord(lon, lat)
+}
+
+fn way_bboxes(archive: &Osm) -> Vec<Option<BBox>> {
+    (0..archive.ways().len())
+        .map(|idx| way_bbox(archive, idx))
+        .collect()
+}
+
+fn relation_bboxes(archive: &Osm, way_bboxes: &[Option<BBox>]) -> Vec<Option<BBox>> {
+    let relation_members = archive.relation_members();
+    let mut bboxes: Vec<Option<BBox>> = (0..archive.relations().len())
+        .map(|idx| {
+            let mut bbox = None;
+            for member in relation_members.at(idx) {
+                match member {
+                    RelationMembersRef::NodeMember(m) => {
+                        if let Some(node_idx) = m.node_idx() {
+                            extend(&mut bbox, &node_bbox(archive, node_idx));
+                        }
+                    }
+                    RelationMembersRef::WayMember(m) => {
+                        if let Some(way_bbox) = m.way_idx().and_then(|i| way_bboxes[i as usize]) {
+                            extend(&mut bbox, &way_bbox);
+                        }
+                    }
+                    RelationMembersRef::RelationMember(_) => (),
+                }
+            }
+            bbox
+        })
+        .collect();
+
+    // Relation members are resolved by extending the bounding boxes until none
+    // of them changes anymore; the number of rounds is the nesting depth of
+    // relations.
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for idx in 0..bboxes.len() {
+            let mut bbox = bboxes[idx];
+            for member in relation_members.at(idx) {
+                if let RelationMembersRef::RelationMember(m) = member {
+                    if let Some(member_bbox) = m.relation_idx().and_then(|i| bboxes[i as usize]) {
+                        extend(&mut bbox, &member_bbox);
+                    }
+                }
+            }
+            if bbox != bboxes[idx] {
+                bboxes[idx] = bbox;
+                changed = true;
+            }
+        }
+    }
+    bboxes
+}
+
+fn entities(bboxes: Vec<Option<BBox>>) -> Vec<(BBox, u64)> {
+    bboxes
+        .into_iter()
+        .enumerate()
+        .filter_map(|(idx, bbox)| Some((bbox?, idx as u64)))
+        .collect()
+}
+
+fn bounding_boxes(bboxes: &[Option<BBox>]) -> Vec<BoundingBox> {
+    bboxes.iter().map(|&bbox| bbox.into()).collect()
+}
+
+/// Computes the bounding boxes of the ways and relations of `archive` and
+/// writes them.
+pub fn serialize_bboxes(archive: &Osm, builder: &OsmBuilder) -> io::Result<()> {
+    let way_bboxes = way_bboxes(archive);
+    builder.set_way_bboxes(&bounding_boxes(&way_bboxes))?;
+    let relation_bboxes = relation_bboxes(archive, &way_bboxes);
+    builder.set_relation_bboxes(&bounding_boxes(&relation_bboxes))?;
+    Ok(())
+}
+
+/// Computes the bounding boxes of all entities of `archive` and writes their
+/// spatial index.
+pub fn serialize_spatial_index(archive: &Osm, builder: &SpatialIndexBuilder) -> io::Result<()> {
+    let node_bboxes = (0..archive.nodes().len() as u64)
+        .map(|idx| (node_bbox(archive, idx), idx))
+        .collect();
+    builder.set_nodes(&build_spatial_index(node_bboxes))?;
+
+    let way_bboxes = way_bboxes(archive);
+    let relation_bboxes = relation_bboxes(archive, &way_bboxes);
+    builder.set_ways(&build_spatial_index(entities(way_bboxes)))?;
+    builder.set_relations(&build_spatial_index(entities(relation_bboxes)))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::convert::{convert, NodeOrder, Options};
+
+    use osmflat::{
+        geometry, hilbert_nodes_in_bbox, BBox, BBoxMatch, EntityType, FileResourceStorage,
+    };
+
+    use std::fmt::Write;
+
+    #[test]
+    fn test_nodes_in_bbox_with_and_without_spatial_index() {
+        let mut opl = String::new();
+        for i in 0..400 {
+            let (lon, lat) = (f64::from(i % 20) / 10.0, f64::from(i / 20) / 10.0);
+            writeln!(opl, "n{} v1 x{lon} y{lat}", i + 1).unwrap();
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+
+        for spatial_index in [false, true] {
+            let output = dir.path().join(spatial_index.to_string());
+            let options = Options {
+                spatial_index,
+                ..Default::default()
+            };
+            convert(&input, &output, options, ()).unwrap();
+            let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+            assert_eq!(archive.spatial_index().is_some(), spatial_index);
+
+            let mut nodes: Vec<u64> = archive.nodes_in_bbox(0.5, 1.0, 0.7, 1.15).collect();
+            nodes.sort_unstable();
+            // columns 5 to 7 of the rows 10 and 11
+            assert_eq!(nodes, [205, 206, 207, 225, 226, 227]);
+            assert_eq!(archive.nodes_in_bbox(3.0, 3.0, 4.0, 4.0).count(), 0);
+        }
+    }
+
+    #[test]
+    fn test_hilbert_nodes_in_bbox() {
+        let mut opl = String::new();
+        for i in 0..400 {
+            let (lon, lat) = (
+                f64::from(i % 20) / 10.0 - 1.0,
+                f64::from(i / 20) / 10.0 - 1.0,
+            );
+            writeln!(opl, "n{} v1 x{lon} y{lat}", i + 1).unwrap();
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        let output = dir.path().join("output");
+        std::fs::write(&input, opl).unwrap();
+        let options = Options {
+            sort: NodeOrder::Hilbert,
+            ..Default::default()
+        };
+        convert(&input, &output, options, ()).unwrap();
+        let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+
+        for (min, max) in [
+            ((-0.45, -0.25), (0.35, 0.15)),
+            ((-1.0, -1.0), (1.0, 1.0)),
+            ((0.05, 0.05), (0.05, 0.05)),
+            ((2.0, 2.0), (3.0, 3.0)),
+        ] {
+            let bbox = BBox::from_degrees(&archive, min, max);
+            let nodes: Vec<u64> = hilbert_nodes_in_bbox(&archive, bbox).collect();
+            let mut expected: Vec<u64> =
+                archive.nodes_in_bbox(min.0, min.1, max.0, max.1).collect();
+            expected.sort_unstable();
+            assert_eq!(nodes, expected);
+        }
+    }
+
+    #[test]
+    fn test_way_coords() {
+        let opl = "n1 v1 x1.5 y-2\nn2 v1 x3 y4.25\nw1 v1 Nn1,n2,n1\nw2 v1 Nn1,n3\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        let output = dir.path().join("output");
+        std::fs::write(&input, opl).unwrap();
+        convert(&input, &output, Options::default(), ()).unwrap();
+        let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+
+        let ways = archive.ways();
+        let coords: Vec<_> = geometry::way_coords(&archive, &ways[0]).unwrap().collect();
+        assert_eq!(coords, [(1.5, -2.0), (3.0, 4.25), (1.5, -2.0)]);
+        assert!(geometry::way_coords(&archive, &ways[1]).is_none());
+    }
+
+    #[test]
+    fn test_multipolygon() {
+        let opl = "n1 v1 x0 y0\nn2 v1 x4 y0\nn3 v1 x4 y4\nn4 v1 x0 y4\n\
+                   n5 v1 x1 y1\nn6 v1 x1 y2\nn7 v1 x2 y2\nn8 v1 x2 y1\n\
+                   n9 v1 x10 y0\nn10 v1 x11 y0\nn11 v1 x11 y1\n\
+                   w1 v1 Nn1,n2,n3\nw2 v1 Nn1,n4,n3\nw3 v1 Nn5,n6,n7,n8,n5\n\
+                   w4 v1 Nn9,n10,n11,n9\nw5 v1 Nn9,n10,n11\nw6 v1 Nn1,n99\n\
+                   r1 v1 Ttype=multipolygon Mw1@outer,w3@inner,w2@outer,w4@outer\n\
+                   r2 v1 Ttype=multipolygon Mw5@outer\n\
+                   r3 v1 Ttype=multipolygon Mw4@outer,w3@inner\n\
+                   r4 v1 Ttype=multipolygon Mw6@outer\n\
+                   r5 v1 Ttype=multipolygon Mn1@,w99@outer\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        let output = dir.path().join("output");
+        std::fs::write(&input, opl).unwrap();
+        convert(&input, &output, Options::default(), ()).unwrap();
+        let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+
+        let mut polygons = geometry::multipolygon(&archive, 0).unwrap();
+        polygons.sort_by(|a, b| a.outer[0].0.total_cmp(&b.outer[0].0));
+        assert_eq!(polygons.len(), 2);
+        // outer rings are counterclockwise, holes clockwise
+        assert_eq!(polygons[0].outer.len(), 5);
+        assert_eq!(polygons[0].inner.len(), 1);
+        assert_eq!(
+            polygons[0].inner[0],
+            [(1.0, 1.0), (1.0, 2.0), (2.0, 2.0), (2.0, 1.0), (1.0, 1.0)]
+        );
+        assert_eq!(
+            polygons[1].outer,
+            [(10.0, 0.0), (11.0, 0.0), (11.0, 1.0), (10.0, 0.0)]
+        );
+        assert!(polygons[1].inner.is_empty());
+
+        use geometry::MultipolygonError::*;
+        assert_eq!(
+            geometry::multipolygon(&archive, 1),
+            Err(UnclosedRing((11.0, 1.0)))
+        );
+        assert_eq!(
+            geometry::multipolygon(&archive, 2),
+            Err(InnerRingOutside((1.0, 1.0)))
+        );
+        assert_eq!(geometry::multipolygon(&archive, 3), Err(UnresolvedNode(5)));
+        assert_eq!(geometry::multipolygon(&archive, 4), Err(UnresolvedWay(1)));
+    }
+
+    #[test]
+    fn test_to_geojson_feature() {
+        let opl = "n1 v1 Tamenity=pub,name=Zum%20%Hirschen x1 y2\nn2 v1 x3 y2\nn3 v1 x3 y4\n\
+                   w1 v1 Thighway=primary Nn1,n2,n3\nw2 v1 Nn3,n1\n\
+                   w3 v1 Tlanduse=grass Nn1,n3,n2,n1\nw4 v1 Tarea=no Nn1,n2,n3,n1\n\
+                   r1 v1 Ttype=multipolygon Mw1@outer,w2@outer\nr2 v1 Ttype=route Mn1@,w2@\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        let output = dir.path().join("output");
+        std::fs::write(&input, opl).unwrap();
+        let options = Options {
+            ids: true,
+            ..Default::default()
+        };
+        convert(&input, &output, options, ()).unwrap();
+        let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+
+        let feature = |entity_type, idx| {
+            let feature = osmflat::to_geojson_feature(&archive, entity_type, idx);
+            serde_json::to_value(feature).unwrap()
+        };
+        assert_eq!(
+            feature(EntityType::Node, 0),
+            serde_json::json!({
+                "type": "Feature",
+                "id": "n1",
+                "geometry": {"type": "Point", "coordinates": [1.0, 2.0]},
+                "properties": {"amenity": "pub", "name": "Zum Hirschen"},
+            })
+        );
+        assert_eq!(
+            feature(EntityType::Way, 0)["geometry"],
+            serde_json::json!({
+                "type": "LineString",
+                "coordinates": [[1.0, 2.0], [3.0, 2.0], [3.0, 4.0]],
+            })
+        );
+        assert_eq!(
+            feature(EntityType::Way, 2)["geometry"],
+            serde_json::json!({
+                "type": "Polygon",
+                "coordinates": [[[1.0, 2.0], [3.0, 2.0], [3.0, 4.0], [1.0, 2.0]]],
+            })
+        );
+        assert_eq!(
+            feature(EntityType::Way, 3)["geometry"]["type"],
+            "LineString"
+        );
+        assert_eq!(
+            feature(EntityType::Relation, 0)["geometry"],
+            serde_json::json!({
+                "type": "MultiPolygon",
+                "coordinates": [[[[3.0, 4.0], [1.0, 2.0], [3.0, 2.0], [3.0, 4.0]]]],
+            })
+        );
+        let route = feature(EntityType::Relation, 1);
+        assert_eq!(route["id"], "r2");
+        assert_eq!(route["geometry"]["type"], "GeometryCollection");
+        assert_eq!(route["geometry"]["geometries"][0]["type"], "Point");
+        assert_eq!(route["geometry"]["geometries"][1]["type"], "LineString");
+    }
+
+    #[test]
+    fn test_ways_and_relations_in_bbox() {
+        let opl = "n1 v1 x0 y1.5\nn2 v1 x1.5 y0\n\
+                   n3 v1 x0.5 y0.5\nn4 v1 x2 y0.5\nn5 v1 x2 y2\nn6 v1 x0.5 y2\n\
+                   n7 v1 x1.1 y0\nn8 v1 x1.1 y3\nn9 v1 x5 y5\nn10 v1 x6 y6\n\
+                   n11 v1 x0 y0\nn12 v1 x3 y0\nn13 v1 x3 y3\nn14 v1 x0 y3\n\
+                   w1 v1 Nn1,n2\nw2 v1 Nn3,n4,n5,n6,n3\nw3 v1 Nn7,n8\nw4 v1 Nn9,n10\n\
+                   w5 v1 Nn11,n12,n13\nw6 v1 Nn13,n14,n11\n\
+                   r1 v1 Ttype=multipolygon Mw5@outer,w6@outer\n\
+                   r2 v1 Ttype=route Mw5@,w6@\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+
+        for spatial_index in [false, true] {
+            let output = dir.path().join(spatial_index.to_string());
+            let options = Options {
+                spatial_index,
+                ..Default::default()
+            };
+            convert(&input, &output, options, ()).unwrap();
+            let archive = osmflat::Osm::open(FileResourceStorage::new(output)).unwrap();
+
+            let ways = |matching| {
+                let mut ways: Vec<u64> =
+                    archive.ways_in_bbox(1.0, 1.0, 1.2, 1.2, matching).collect();
+                ways.sort_unstable();
+                ways
+            };
+            assert_eq!(ways(BBoxMatch::BoundingBox), [0, 1, 2, 4, 5]);
+            assert_eq!(ways(BBoxMatch::Geometry), [1, 2]);
+
+            let relations = |matching| {
+                let mut relations: Vec<u64> = archive
+                    .relations_in_bbox(1.0, 1.0, 1.2, 1.2, matching)
+                    .collect();
+                relations.sort_unstable();
+                relations
+            };
+            assert_eq!(relations(BBoxMatch::BoundingBox), [0, 1]);
+            assert_eq!(relations(BBoxMatch::Geometry), [0]);
+        }
+    }
+}
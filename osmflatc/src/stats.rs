@@ -1,5 +1,12 @@
+use crate::progress::Stage;
+use crate::unresolved::UnresolvedRef;
+
+use osmflat::ValidationReport;
+use serde_json::json;
+
 use std::fmt;
 use std::ops::AddAssign;
+use std::time::Duration;
 
 #[derive(Debug, Default)]
 pub struct Stats {
@@ -9,6 +16,64 @@ pub struct Stats {
     pub num_unresolved_node_ids: usize,
     pub num_unresolved_way_ids: usize,
     pub num_unresolved_rel_ids: usize,
+    /// References to entities missing in the input, only collected if
+    /// `Options::report_unresolved` is set
+    pub unresolved_refs: Vec<UnresolvedRef>,
+    /// Duration of each stage of the conversion, in order
+    pub stage_durations: Vec<(Stage, Duration)>,
+    /// Size in bytes of each resource of the archive by its path relative to
+    /// the archive
+    pub resource_sizes: Vec<(String, u64)>,
+    /// Violations found by the deep validation of the archive, if enabled
+    pub violations: Option<ValidationReport>,
+}
+
+impl Stats {
+    /// Returns the stats as JSON object.
+    pub fn to_json(&self) -> serde_json::Value {
+        let durations: serde_json::Map<_, _> = self
+            .stage_durations
+            .iter()
+            .map(|(stage, duration)| (stage.name().into(), json!(duration.as_secs_f64())))
+            .collect();
+        let sizes: serde_json::Map<_, _> = self
+            .resource_sizes
+            .iter()
+            .map(|(path, size)| (path.clone(), json!(size)))
+            .collect();
+        let mut stats = json!({
+            "converted": {
+                "nodes": self.num_nodes,
+                "ways": self.num_ways,
+                "relations": self.num_relations,
+            },
+            "unresolved_ids": {
+                "nodes": self.num_unresolved_node_ids,
+                "ways": self.num_unresolved_way_ids,
+                "relations": self.num_unresolved_rel_ids,
+            },
+            "stage_durations_secs": durations,
+            "resource_sizes": sizes,
+        });
+        if let Some(violations) = &self.violations {
+            stats["violations"] = violations_to_json(violations);
+        }
+        stats
+    }
+}
+
+fn violations_to_json(violations: &ValidationReport) -> serde_json::Value {
+    json!({
+        "tag_ranges": violations.tag_ranges,
+        "ref_ranges": violations.ref_ranges,
+        "sentinels": violations.sentinels,
+        "tags_index": violations.tags_index,
+        "nodes_index": violations.nodes_index,
+        "members": violations.members,
+        "strings": violations.strings,
+        "utf8": violations.utf8,
+        "node_coords": violations.node_coords,
+    })
 }
 
 impl AddAssign for Stats {
@@ -20,6 +85,10 @@ impl AddAssign for Stats {
         self.num_unresolved_node_ids += other.num_unresolved_node_ids;
         self.num_unresolved_way_ids += other.num_unresolved_way_ids;
         self.num_unresolved_rel_ids += other.num_unresolved_rel_ids;
+        self.unresolved_refs.extend(other.unresolved_refs);
+        self.stage_durations.extend(other.stage_durations);
+        self.resource_sizes.extend(other.resource_sizes);
+        self.violations = other.violations.or(self.violations.take());
     }
 }
 
@@ -41,6 +110,35 @@ Unresolved ids:
             self.num_unresolved_node_ids,
             self.num_unresolved_way_ids,
             self.num_unresolved_rel_ids
-        )
+        )?;
+        if let Some(violations) = &self.violations {
+            write!(f, "\n{violations}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_to_json() {
+        let stats = Stats {
+            num_nodes: 3,
+            num_unresolved_way_ids: 1,
+            stage_durations: vec![(Stage::Nodes, Duration::from_millis(1500))],
+            resource_sizes: vec![("ids/nodes".into(), 15)],
+            ..Default::default()
+        };
+        assert_eq!(
+            stats.to_json(),
+            json!({
+                "converted": { "nodes": 3, "ways": 0, "relations": 0 },
+                "unresolved_ids": { "nodes": 0, "ways": 1, "relations": 0 },
+                "stage_durations_secs": { "nodes": 1.5 },
+                "resource_sizes": { "ids/nodes": 15 },
+            })
+        );
     }
 }
@@ -0,0 +1,246 @@
+//! Splitting an archive into archives of Web Mercator tiles.
+//!
+//! The entities of each tile are exported to a temporary OSM pbf file, which is
+//! converted to the archive of the tile. Therefore, each tile is a complete
+//! archive; entities overlapping several tiles are contained in each of them.
+//! The tiles are listed in a [`osmflat::Tiles`] archive in the output
+//! directory.
+
+use crate::compress;
+use crate::convert::{self, Options};
+use crate::export::{self, Exporter};
+use crate::osmpbf;
+use crate::Error;
+
+use flatdata::FileResourceStorage;
+use osmflat::{
+    relation_bbox, tile_at, tile_bounds, way_bbox, BBox, Osm, Tile, TilesBuilder, MAX_TILE_ZOOM,
+};
+use prost::Message;
+
+use std::collections::BTreeMap;
+use std::io::{BufWriter, Write};
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+/// Indexes of the entities of a tile in the archive.
+#[derive(Debug, Default)]
+pub(crate) struct TileEntities {
+    pub nodes: Vec<usize>,
+    pub ways: Vec<usize>,
+    pub relations: Vec<usize>,
+}
+
+/// Ranges of the columns and rows of the tiles intersecting `bbox`.
+fn tile_ranges(zoom: u8, scale: f64, bbox: &BBox) -> (RangeInclusive<u32>, RangeInclusive<u32>) {
+    let degrees = |x: i32| f64::from(x) / scale;
+    let (min_x, max_y) = tile_at(zoom, degrees(bbox.left), degrees(bbox.bottom));
+    let (max_x, min_y) = tile_at(zoom, degrees(bbox.right), degrees(bbox.top));
+    (min_x..=max_x, min_y..=max_y)
+}
+
+/// Assigns the entities of `archive` to tiles.
+///
+/// Nodes are assigned to the tile containing them, ways to the tiles their
+/// bounding boxes intersect together with all their nodes. Relations are
+/// assigned to the tiles their bounding boxes intersect, which contain nodes.
+fn assign_tiles(archive: &Osm, zoom: u8) -> BTreeMap<(u32, u32), TileEntities> {
+    let scale = f64::from(archive.header().coord_scale());
+    let coords = archive.coord_reader();
+    let mut tiles: BTreeMap<(u32, u32), TileEntities> = BTreeMap::new();
+
+    let nodes = archive.nodes();
+    for (idx, node) in nodes.iter().enumerate() {
+        let tile = tile_at(zoom, coords.lon(node), coords.lat(node));
+        tiles.entry(tile).or_default().nodes.push(idx);
+    }
+
+    let nodes_index = archive.nodes_index();
+    for (idx, way) in archive.ways().iter().enumerate() {
+        let Some(bbox) = way_bbox(archive, idx) else {
+            continue;
+        };
+        let (xs, ys) = tile_ranges(zoom, scale, &bbox);
+        for x in xs {
+            for y in ys.clone() {
+                let tile = tiles.entry((x, y)).or_default();
+                tile.ways.push(idx);
+                tile.nodes.extend(
+                    way.refs()
+                        .filter_map(|i| nodes_index[i as usize].value())
+                        .map(|node_idx| node_idx as usize),
+                );
+            }
+        }
+    }
+
+    for idx in 0..archive.relations().len() {
+        let Some(bbox) = relation_bbox(archive, idx) else {
+            continue;
+        };
+        let (xs, ys) = tile_ranges(zoom, scale, &bbox);
+        for x in xs {
+            for y in ys.clone() {
+                if let Some(tile) = tiles.get_mut(&(x, y)) {
+                    tile.relations.push(idx);
+                }
+            }
+        }
+    }
+
+    for tile in tiles.values_mut() {
+        tile.nodes.sort_unstable();
+        tile.nodes.dedup();
+    }
+    tiles
+}
+
+/// Writes the entities of a tile as OSM pbf file.
+pub(crate) fn write_tile_pbf(
+    out: &mut impl Write,
+    exporter: &Exporter,
+    header: &osmpbf::HeaderBlock,
+    entities: &TileEntities,
+) -> Result<(), Error> {
+    export::write_blob(out, "OSMHeader", &header.encode_to_vec())?;
+    let TileEntities {
+        nodes,
+        ways,
+        relations,
+    } = entities;
+    export::write_blocks(out, nodes.len(), |range| {
+        exporter.dense_nodes_block(nodes[range].iter().copied())
+    })?;
+    export::write_blocks(out, ways.len(), |range| {
+        exporter.ways_block(ways[range].iter().copied())
+    })?;
+    export::write_blocks(out, relations.len(), |range| {
+        exporter.relations_block(relations[range].iter().copied())
+    })?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Splits the archive at `archive` into archives of the tiles of the given
+/// zoom level, and writes them together with the [`osmflat::Tiles`] archive
+/// to the directory `output`.
+///
+/// Only tiles containing nodes are written. Returns the number of tiles.
+pub fn split_tiles(archive: &Path, output: &Path, zoom: u8) -> Result<usize, Error> {
+    if zoom > MAX_TILE_ZOOM {
+        return Err(format!("zoom level {zoom} is larger than {MAX_TILE_ZOOM}").into());
+    }
+    let archive = compress::open_archive(archive)?;
+    let exporter = Exporter::new(&archive)?;
+    let header = export::header_block(&archive);
+    let options = Options {
+        ids: true,
+        coord_scale: Some(archive.header().coord_scale()),
+        // tiles of a previous split are replaced
+        force: true,
+        ..Default::default()
+    };
+
+    let builder = TilesBuilder::new(FileResourceStorage::new(output.to_path_buf()))?;
+    let mut tiles = Vec::new();
+    for ((x, y), entities) in assign_tiles(&archive, zoom) {
+        let (min, max) = tile_bounds(zoom, x, y);
+        let nanodegrees = |x: f64| (x * 1e9).round() as i64;
+        let header = osmpbf::HeaderBlock {
+            bbox: Some(osmpbf::HeaderBBox {
+                left: nanodegrees(min.0),
+                right: nanodegrees(max.0),
+                top: nanodegrees(max.1),
+                bottom: nanodegrees(min.1),
+            }),
+            ..header.clone()
+        };
+        let mut pbf_file = tempfile::Builder::new().suffix(".osm.pbf").tempfile()?;
+        {
+            let mut out = BufWriter::new(pbf_file.as_file_mut());
+            write_tile_pbf(&mut out, &exporter, &header, &entities)?;
+        }
+
+        let mut tile = Tile::new();
+        tile.set_zoom(zoom);
+        tile.set_x(x);
+        tile.set_y(y);
+        let stats = convert::convert(
+            pbf_file.path(),
+            &output.join(tile.path()),
+            options.clone(),
+            (),
+        )?;
+        tile.set_num_nodes(stats.num_nodes as u64);
+        tile.set_num_ways(stats.num_ways as u64);
+        tile.set_num_relations(stats.num_relations as u64);
+        tiles.push(tile);
+    }
+    builder.set_tiles(&tiles)?;
+    Ok(tiles.len())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use osmflat::Tiles;
+
+    #[test]
+    fn test_split_tiles() {
+        // at zoom 1, n1 is in the tile (0, 0), n2 and n4 in (1, 0), n3 in
+        // (1, 1), and no node in (0, 1)
+        let opl = "n1 v1 x-10 y10\nn2 v1 x10 y10\nn3 v1 x10 y-10\nn4 v1 x20 y20\n\
+                   w10 v1 Thighway=primary Nn1,n2\nw11 v1 Nn2,n4\n\
+                   r20 v1 Ttype=route Mw10@\nr21 v1 Mn3@\n";
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.opl");
+        std::fs::write(&input, opl).unwrap();
+        let archive = dir.path().join("archive");
+        let options = Options {
+            ids: true,
+            ..Default::default()
+        };
+        convert::convert(&input, &archive, options, ()).unwrap();
+
+        let output = dir.path().join("tiles");
+        assert!(split_tiles(&archive, &output, MAX_TILE_ZOOM + 1).is_err());
+        assert_eq!(split_tiles(&archive, &output, 1).unwrap(), 3);
+
+        let tiles = Tiles::open(FileResourceStorage::new(output.clone())).unwrap();
+        let index: Vec<_> = (tiles.tiles().iter())
+            .map(|tile| {
+                (
+                    (tile.zoom(), tile.x(), tile.y()),
+                    (tile.num_nodes(), tile.num_ways(), tile.num_relations()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            index,
+            [
+                ((1, 0, 0), (2, 1, 1)),
+                ((1, 1, 0), (3, 2, 1)),
+                ((1, 1, 1), (1, 0, 1)),
+            ]
+        );
+        assert!(tiles.tile(0, 1).is_none());
+
+        let entities = |x, y| {
+            let tile = tiles.tile(x, y).unwrap().open(&output).unwrap();
+            let ids = tile.ids().unwrap();
+            let values = |ids: &[osmflat::Id]| -> Vec<i64> {
+                ids.iter().map(|id| id.signed_value()).collect()
+            };
+            (
+                values(ids.nodes()),
+                values(ids.ways()),
+                values(ids.relations()),
+            )
+        };
+        // ways are contained in each tile they intersect, together with all
+        // their nodes
+        assert_eq!(entities(0, 0), (vec![1, 2], vec![10], vec![20]));
+        assert_eq!(entities(1, 0), (vec![1, 2, 4], vec![10, 11], vec![20]));
+        assert_eq!(entities(1, 1), (vec![3], vec![], vec![21]));
+    }
+}
@@ -0,0 +1,94 @@
+//! Report of the references to entities missing in the input, cf.
+//! `Options::report_unresolved`.
+
+use osmflat::EntityType;
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+fn type_name(entity_type: EntityType) -> &'static str {
+    match entity_type {
+        EntityType::Node => "node",
+        EntityType::Way => "way",
+        EntityType::Relation => "relation",
+    }
+}
+
+/// Reference of an entity to an entity missing in the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct UnresolvedRef {
+    /// Type of the referencing way or relation
+    pub parent_type: EntityType,
+    /// Id of the referencing way or relation
+    pub parent_id: i64,
+    /// Type of the missing entity
+    pub missing_type: EntityType,
+    /// Id of the missing entity
+    pub missing_id: i64,
+}
+
+impl fmt::Display for UnresolvedRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            type_name(self.parent_type),
+            self.parent_id,
+            type_name(self.missing_type),
+            self.missing_id
+        )
+    }
+}
+
+/// Writes the references to `out` with one line
+/// `<parent type> <parent id> <missing type> <missing id>` per reference,
+/// sorted and without duplicates.
+pub fn write_report(out: &mut impl Write, refs: &[UnresolvedRef]) -> io::Result<()> {
+    let mut refs = refs.to_vec();
+    refs.sort_unstable();
+    refs.dedup();
+    for r in refs {
+        writeln!(out, "{r}")?;
+    }
+    out.flush()
+}
+
+/// Writes the report of the references to the file at `path`, cf.
+/// [`write_report`].
+pub fn write_report_file(path: &Path, refs: &[UnresolvedRef]) -> io::Result<()> {
+    write_report(&mut BufWriter::new(File::create(path)?), refs)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_write_report() {
+        let way_ref = |parent_id, missing_id| UnresolvedRef {
+            parent_type: EntityType::Way,
+            parent_id,
+            missing_type: EntityType::Node,
+            missing_id,
+        };
+        let refs = [
+            UnresolvedRef {
+                parent_type: EntityType::Relation,
+                parent_id: 1,
+                missing_type: EntityType::Way,
+                missing_id: 7,
+            },
+            way_ref(5, 3),
+            way_ref(2, 4),
+            way_ref(5, 3),
+        ];
+        let mut out = Vec::new();
+        write_report(&mut out, &refs).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "way 2 node 4\nway 5 node 3\nrelation 1 way 7\n"
+        );
+    }
+}